@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use crate::{TimeValue, Timeline, TimelineError};
+
+// The characters events are drawn with, in the order their names get them
+const EVENT_CHARS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+// Drawn where events of different names share a column, or for names past `EVENT_CHARS`
+const OVERLAP_CHAR: char = '#';
+
+// Drawn where there are no events
+const EMPTY_CHAR: char = '.';
+
+impl<T: TimeValue> Timeline<T> {
+    /// Write a rough text rendering of the timeline
+    ///
+    /// This is for a quick look at a timeline in a terminal, over SSH for example, without
+    /// copying an SVG around. Every drawn row is a line with its name followed by `columns`
+    /// characters, each covering an equal slice of the time range. Events fill the columns they
+    /// touch with the character of their name, or `#` where events of different names share a
+    /// column, and columns without events are dots. The times of the start and end of the
+    /// timeline are written below the rows, followed by a legend of the character of every name,
+    /// sorted by name. Filters and the order of the rows apply as they do to the SVG.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("read", 0, 5, "CPU 0");
+    /// timeline.add_event("write", 5, 10, "CPU 1");
+    /// let mut output = Vec::new();
+    /// timeline.write_ascii(&mut output, 10).unwrap();
+    /// let text = String::from_utf8(output).unwrap();
+    /// assert!(text.starts_with("CPU 0 |AAAAA.....|\nCPU 1 |.....BBBBB|\n"));
+    /// # }
+    /// ```
+    pub fn write_ascii<W: Write>(
+        &self,
+        mut writer: W,
+        columns: usize,
+    ) -> Result<(), TimelineError> {
+        self.check_ranges()?;
+        let columns = columns.max(1);
+        let rows = self.rows();
+        let categories = rows.categories();
+        let events: Vec<_> = self
+            .events
+            .iter()
+            .filter(|event| self.is_drawn(event) && categories.contains(&event.location.as_str()))
+            .collect();
+
+        let mut names: Vec<&str> = events.iter().map(|event| event.name.as_str()).collect();
+        names.sort();
+        names.dedup();
+        let chars: BTreeMap<&str, char> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (*name, EVENT_CHARS.chars().nth(i).unwrap_or(OVERLAP_CHAR)))
+            .collect();
+
+        let span = self.span();
+        // Where a time falls along the columns
+        let position = |time: T| {
+            if span > 0.0 {
+                time.offset_from(self.start_time, self.units) / span * columns as f64
+            } else {
+                0.0
+            }
+        };
+        let width = categories
+            .iter()
+            .map(|category| category.chars().count())
+            .max()
+            .unwrap_or(0);
+        for category in categories {
+            let mut line = vec![EMPTY_CHAR; columns];
+            for event in events.iter().filter(|event| event.location == *category) {
+                let c = chars[event.name.as_str()];
+                // An event ending on the boundary of a column doesn't reach into it
+                let start = (position(event.start_time).floor() as usize).min(columns - 1);
+                let end = (position(event.end_time).ceil() as usize)
+                    .saturating_sub(1)
+                    .clamp(start, columns - 1);
+                for cell in &mut line[start..=end] {
+                    *cell = match *cell {
+                        EMPTY_CHAR => c,
+                        other if other == c => c,
+                        _ => OVERLAP_CHAR,
+                    };
+                }
+            }
+            let line: String = line.into_iter().collect();
+            writeln!(writer, "{:<width$} |{}|", category, line, width = width)?;
+        }
+
+        let origin = match self.axis_origin {
+            Some(origin) if span > 0.0 => origin.offset_from(self.start_time, self.units),
+            _ => 0.0,
+        };
+        let step = self.tick_step();
+        let unit = self.axis_unit_label();
+        let start = format!("{} {}", self.tick_label(0.0, origin, step), unit);
+        let end = format!("{} {}", self.tick_label(span, origin, step), unit);
+        let gap = (columns + 2).saturating_sub(start.chars().count() + end.chars().count());
+        writeln!(
+            writer,
+            "{:width$} {}{:gap$}{}",
+            "",
+            start,
+            "",
+            end,
+            width = width,
+            gap = gap.max(1)
+        )?;
+        for (name, c) in chars {
+            writeln!(writer, "{} {}", c, name)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_ascii() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("write", 10, 14, "CPU 10");
+        timeline.add_event("read", 10, 12, "CPU 2");
+        timeline.add_event("write", 11, 13, "CPU 2");
+        timeline.add_event("irq", 20, 20, "CPU 2");
+        timeline.add_event("hidden", 10, 20, "CPU 3");
+        timeline.set_category_filter(Some(vec!["CPU 2".to_string(), "CPU 10".to_string()]));
+
+        let mut output = Vec::new();
+        timeline.write_ascii(&mut output, 10).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "CPU 10 |CCCC......|\nCPU 2  |B#C......A|\n       0 ns   10 ns\n\
+             A irq\nB read\nC write\n"
+        );
+    }
+}
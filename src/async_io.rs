@@ -0,0 +1,78 @@
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::{stream, TimeValue, Timeline, TimelineError};
+
+impl<T: TimeValue> Timeline<T> {
+    /// Write the SVG of the timeline to an asynchronous writer
+    ///
+    /// This is `write` for services that render timelines on request, like into the body of an
+    /// HTTP response, without blocking the runtime on a slow client. The timeline is rendered
+    /// up front, and then written a part of the document at a time as `writer` accepts it, so
+    /// its text is never held in memory as a whole. The returned future can be spawned onto a
+    /// multithreaded runtime. This fails like `write`.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # async fn render() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("read", 0, 5, "CPU 0");
+    /// let mut output = Vec::new();
+    /// timeline.write_async(&mut output).await.unwrap();
+    /// assert!(String::from_utf8(output).unwrap().starts_with("<svg"));
+    /// # }
+    /// # fn main() {}
+    /// ```
+    pub async fn write_async<W: AsyncWrite + Unpin>(
+        &self,
+        mut writer: W,
+    ) -> Result<(), TimelineError> {
+        let doc = self.document(&self.colormap)?;
+        for chunk in stream::chunks(doc) {
+            writer.write_all(chunk.as_bytes()).await?;
+        }
+        writer.flush().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    use super::*;
+
+    // Writing into a `Vec` never has to wait, so the future is done the first time it's polled
+    fn ready<F: Future>(future: F) -> F::Output {
+        match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("writing into a Vec waited"),
+        }
+    }
+
+    fn assert_send<F: Future + Send>(future: F) -> F {
+        future
+    }
+
+    #[test]
+    fn test_write_async() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1", 0, 10, "Location 1");
+        timeline.add_marker("Marker".to_string(), 5);
+        let mut expected = Vec::new();
+        timeline.write(&mut expected).unwrap();
+
+        let mut output = Vec::new();
+        ready(assert_send(timeline.write_async(&mut output))).unwrap();
+        assert_eq!(output, expected);
+
+        timeline.add_trigger("Location 1", "Location 2", 5);
+        assert!(matches!(
+            ready(timeline.write_async(Vec::new())),
+            Err(TimelineError::UnknownLocation(_))
+        ));
+    }
+}
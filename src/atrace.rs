@@ -0,0 +1,316 @@
+use std::collections::HashMap;
+use std::io::Read;
+
+use crate::ftrace::{parse_line, task_row, SchedTimeline};
+use crate::import::sniff_text;
+use crate::{Timeline, TimelineError, TraceImporter};
+
+// The output of atrace and the HTML of systrace, see `Timeline::from_atrace`
+pub(crate) struct AtraceImporter;
+
+impl TraceImporter for AtraceImporter {
+    fn format(&self) -> &str {
+        "atrace"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        let text = sniff_text(data);
+        text.contains("class=\"trace-data\"")
+            || text.lines().any(|line| line == "TRACE:")
+            || text.contains("tracing_mark_write: ")
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_atrace(data)
+    }
+}
+
+// The ftrace text of a trace, out of the `trace-data` scripts of the HTML systrace saves traces
+// as, which can also have JSON traces in them
+fn trace_text(text: &str) -> String {
+    if !text.contains("class=\"trace-data\"") {
+        return text.to_string();
+    }
+    let mut trace = String::new();
+    for script in text.split("<script class=\"trace-data\"").skip(1) {
+        let Some((_, data)) = script.split_once('>') else {
+            continue;
+        };
+        let data = data.split("</script>").next().unwrap_or_default();
+        if !data.trim_start().starts_with(['{', '[']) {
+            trace.push_str(data);
+            trace.push('\n');
+        }
+    }
+    trace
+}
+
+impl Timeline {
+    /// Read an Android trace of atrace or systrace into a timeline
+    ///
+    /// This reads the ftrace text that `atrace` prints, and the HTML that systrace saves it
+    /// in, drawing the scheduler events like `Timeline::from_ftrace` does, along with what apps
+    /// and the platform write to `trace_marker` through `android.os.Trace` and `ATrace`, as
+    /// `tracing_mark_write` events:
+    ///
+    /// - `B|pid|name` and `E|pid` begin and end a slice, which becomes an event named `name` on
+    ///   the row of the thread that wrote it, like "RenderThread-1234", ending with the last
+    ///   slice the thread began
+    /// - `S|pid|name|cookie` and `F|pid|name|cookie` begin and finish an async slice, which
+    ///   becomes an event on a row named after it
+    /// - `I|pid|name` is an instant on the row of the thread
+    /// - `C|pid|name|value` is a sample of the counter `name`
+    ///
+    /// Slices that don't end last until the end of the trace. Times are in nanoseconds.
+    /// Compressed traces, of `atrace -z`, have to be decompressed first.
+    ///
+    /// This returns `TimelineError::Parse` if no line of the trace looks like ftrace output,
+    /// and `TimelineError::Io` if reading it fails. `Timeline::import` detects traces of this
+    /// format, as "atrace".
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let trace = "\
+    ///     RenderThread-1234 (1200) [001] ...1 10.000000: tracing_mark_write: B|1200|DrawFrame\n\
+    ///     RenderThread-1234 (1200) [001] ...1 10.000500: tracing_mark_write: E|1200\n";
+    /// let timeline = Timeline::from_atrace(trace.as_bytes()).unwrap();
+    /// assert_eq!(timeline.categories(), ["RenderThread-1234"]);
+    /// assert_eq!(timeline.time_range(), Some((10_000_000_000, 10_000_500_000)));
+    /// # }
+    /// ```
+    pub fn from_atrace(mut reader: impl Read) -> Result<Timeline, TimelineError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let mut sched = SchedTimeline::default();
+        let mut parsed = false;
+        let mut last = 0;
+        // The slices begun and not ended yet, by thread, as their name and start
+        let mut open: HashMap<String, Vec<(String, i64)>> = HashMap::new();
+        // The async slices started and not finished yet, by name and cookie, with their start
+        let mut started: HashMap<(String, String), i64> = HashMap::new();
+        let mut slices = Vec::new();
+        let mut samples = Vec::new();
+        for line in trace_text(&text).lines() {
+            if line.starts_with('#') {
+                continue;
+            }
+            let Some(line) = parse_line(line) else {
+                continue;
+            };
+            parsed = true;
+            last = last.max(line.time);
+            // Kernels without the tracing_mark_write event print the marker itself
+            let mark = match line.event {
+                "tracing_mark_write" => Some(line.args),
+                "print" => line.args.strip_prefix("tracing_mark_write: "),
+                _ => None,
+            };
+            let Some(mark) = mark else {
+                sched.line(&line);
+                continue;
+            };
+            sched.event(line.time);
+            let here = task_row(line.comm, line.pid, line.cpu);
+            let fields: Vec<&str> = mark.trim_end().splitn(4, '|').collect();
+            match fields[..] {
+                ["B", _, name, ..] => {
+                    let name = mark.splitn(3, '|').nth(2).unwrap_or(name);
+                    open.entry(here)
+                        .or_default()
+                        .push((name.to_string(), line.time));
+                }
+                ["E", ..] => {
+                    if let Some((name, start)) = open.get_mut(&here).and_then(|slices| slices.pop())
+                    {
+                        slices.push((name, start, line.time, here));
+                    }
+                }
+                ["S", _, name, cookie] => {
+                    started.insert((name.to_string(), cookie.to_string()), line.time);
+                }
+                ["F", _, name, cookie] => {
+                    let key = (name.to_string(), cookie.to_string());
+                    if let Some(start) = started.remove(&key) {
+                        slices.push((key.0.clone(), start, line.time, key.0));
+                    }
+                }
+                ["I", _, name, ..] => sched.instant(here, line.time, name),
+                ["C", _, name, value] => {
+                    // Counters of async tracks have a cookie after the value
+                    let value = value.split('|').next().unwrap_or(value);
+                    if let Ok(value) = value.parse::<f64>() {
+                        samples.push((name.to_string(), line.time, value));
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !parsed {
+            return Err(TimelineError::Parse("no atrace lines".to_string()));
+        }
+
+        let mut timeline = sched.finish();
+        let mut unfinished: Vec<_> = open.into_iter().collect();
+        unfinished.sort();
+        for (row, begun) in unfinished {
+            for (name, start) in begun {
+                slices.push((name, start, last, row.clone()));
+            }
+        }
+        let mut unfinished: Vec<_> = started.into_iter().collect();
+        unfinished.sort();
+        for ((name, _), start) in unfinished {
+            slices.push((name.clone(), start, last, name));
+        }
+        for (name, start, end, row) in slices {
+            timeline.add_event(name, start, end, row);
+        }
+        for (counter, time, value) in samples {
+            timeline.add_counter_sample(counter, time, value);
+        }
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A trace of the markers `marks` wrote, as the task and the microsecond they wrote them at
+    fn trace(marks: &[(&str, u32, &str)]) -> String {
+        marks
+            .iter()
+            .map(|(task, micros, mark)| {
+                format!(
+                    "{} (1200) [001] ...1 10.{:06}: tracing_mark_write: {}\n",
+                    task, micros, mark
+                )
+            })
+            .collect()
+    }
+
+    // The events of a trace, with times in microseconds since 10s
+    fn events(trace: &str) -> Vec<(String, String, i64, i64)> {
+        let timeline = Timeline::from_atrace(trace.as_bytes()).unwrap();
+        timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name().to_string(),
+                    event.location().to_string(),
+                    (event.start_time() - 10_000_000_000) / 1000,
+                    (event.end_time() - 10_000_000_000) / 1000,
+                )
+            })
+            .collect()
+    }
+
+    fn event(name: &str, row: &str, start: i64, end: i64) -> (String, String, i64, i64) {
+        (name.to_string(), row.to_string(), start, end)
+    }
+
+    #[test]
+    fn test_slices() {
+        let sync_trace = trace(&[
+            ("RenderThread-1234", 0, "B|1200|DrawFrame 7"),
+            ("RenderThread-1234", 100, "B|1200|flush|commands"),
+            ("RenderThread-1234", 200, "E|1200"),
+            ("RenderThread-1234", 250, "I|1200|vsync"),
+            ("RenderThread-1234", 400, "E|1200"),
+            // Ends without a begin are of slices begun before the trace
+            ("RenderThread-1234", 450, "E|1200"),
+            ("main-1200", 500, "B|1200|inflate"),
+            ("main-1200", 600, "B|1200|measure"),
+            ("main-1200", 700, "E|1200"),
+        ]);
+        assert!(AtraceImporter.sniff(sync_trace.as_bytes()));
+        // Plain ftrace is left to its own importer
+        assert!(!AtraceImporter.sniff(
+            b"# tracer: nop\n bash-10 [000] d..2. 1.000000: sched_waking: comm=cat pid=11\n"
+        ));
+        // Names keep the bars after the pid, and slices that don't end last until the end
+        assert_eq!(
+            events(&sync_trace),
+            [
+                event("vsync", "RenderThread-1234", 250, 250),
+                event("flush|commands", "RenderThread-1234", 100, 200),
+                event("DrawFrame 7", "RenderThread-1234", 0, 400),
+                event("measure", "main-1200", 600, 700),
+                event("inflate", "main-1200", 500, 700),
+            ]
+        );
+
+        let async_trace = trace(&[
+            ("surfaceflinger-600", 0, "S|600|launching: app|42"),
+            ("surfaceflinger-600", 100, "S|600|launching: app|43"),
+            ("binder-601", 200, "F|600|launching: app|42"),
+            ("binder-601", 300, "F|600|launching: app|44"),
+            ("binder-601", 400, "S|600|frame|1"),
+        ]);
+        // Async slices are told apart by their cookie, and can finish on other threads
+        assert_eq!(
+            events(&async_trace),
+            [
+                event("launching: app", "launching: app", 0, 200),
+                event("frame", "frame", 400, 400),
+                event("launching: app", "launching: app", 100, 400),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_counters() {
+        let trace = trace(&[
+            ("surfaceflinger-600", 0, "C|600|HW_VSYNC|1"),
+            ("surfaceflinger-600", 100, "C|600|HW_VSYNC|0"),
+            ("surfaceflinger-600", 200, "C|600|frames|3|7"),
+            ("surfaceflinger-600", 300, "C|600|frames|many"),
+        ]);
+        let timeline = Timeline::from_atrace(trace.as_bytes()).unwrap();
+        assert_eq!(
+            timeline.counters["HW_VSYNC"],
+            [(10_000_000_000, 1.0), (10_000_100_000, 0.0)]
+        );
+        assert_eq!(timeline.counters["frames"], [(10_000_200_000, 3.0)]);
+    }
+
+    #[test]
+    fn test_html() {
+        let html = "\
+<html><script class=\"trace-data\" type=\"application/json\">{\"traceEvents\": []}</script>
+<script class=\"trace-data\" type=\"application/text\">first</script>
+<script class=\"trace-data\" type=\"application/text\">second</script></html>";
+        assert_eq!(trace_text(html), "first\nsecond\n");
+        assert_eq!(trace_text("TRACE:\nlines\n"), "TRACE:\nlines\n");
+
+        let trace = "\
+<html><script class=\"trace-data\" type=\"application/json\">{\"traceEvents\": []}</script>
+<script class=\"trace-data\" type=\"application/text\">
+# tracer: nop
+#
+ surfaceflinger-600   ( 600) [000] ...1 10.000450: print: tracing_mark_write: B|600|composite
+ surfaceflinger-600   ( 600) [000] ...1 10.000500: tracing_mark_write: E|600
+</script></html>
+";
+        assert!(AtraceImporter.sniff(trace.as_bytes()));
+        // Kernels without the tracing_mark_write event print the markers
+        assert_eq!(
+            events(trace),
+            [event("composite", "surfaceflinger-600", 450, 500)]
+        );
+        // Pages without any trace text are errors
+        assert!(matches!(
+            Timeline::from_atrace("TRACE:\n".as_bytes()),
+            Err(TimelineError::Parse(_))
+        ));
+        assert!(matches!(
+            Timeline::from_atrace(
+                "<script class=\"trace-data\">{\"traceEvents\": []}</script>".as_bytes()
+            ),
+            Err(TimelineError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,386 @@
+use std::io::{BufRead, BufReader, Read};
+
+use crate::ftrace::parse_time;
+use crate::import::{new_timeline, sniff_text};
+use crate::{EventBuilder, Timeline, TimelineError, TraceImporter};
+
+// The text output of blkparse, see `Timeline::from_blkparse`
+pub(crate) struct BlkparseImporter;
+
+impl TraceImporter for BlkparseImporter {
+    fn format(&self) -> &str {
+        "blkparse"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        sniff_text(data)
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .is_some_and(|line| parse_line(line).is_some())
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_blkparse(data)
+    }
+}
+
+// A line of blkparse output, like
+// "  8,0    3        1     0.000000000   697  Q  WS 3417048 + 8 [kworker/u8:2]"
+#[derive(Debug, PartialEq)]
+struct Line<'a> {
+    device: &'a str,
+    time: i64,
+    action: &'a str,
+    rwbs: &'a str,
+    // The first sector and the number of sectors, which not every action has
+    sectors: Option<(u64, u64)>,
+    // The process in brackets, or the error of completions
+    process: &'a str,
+}
+
+fn parse_line(line: &str) -> Option<Line<'_>> {
+    let mut fields = line.split_whitespace();
+    let device = fields.next()?;
+    let (major, minor) = device.split_once(',')?;
+    major.parse::<u32>().ok()?;
+    minor.parse::<u32>().ok()?;
+    let _cpu: u32 = fields.next()?.parse().ok()?;
+    let _sequence: u64 = fields.next()?.parse().ok()?;
+    let time = parse_time(fields.next()?)?;
+    let _pid: u32 = fields.next()?.parse().ok()?;
+    let action = fields.next()?;
+    let rwbs = fields.next().unwrap_or_default();
+    let rest: Vec<&str> = fields.collect();
+    let sectors = match rest[..] {
+        [sector, "+", count, ..] => Some((sector.parse().ok()?, count.parse().ok()?)),
+        _ => None,
+    };
+    let process = rest
+        .last()
+        .and_then(|last| last.strip_prefix('[')?.strip_suffix(']'))
+        .unwrap_or_default();
+    Some(Line {
+        device,
+        time,
+        action,
+        rwbs,
+        sectors,
+        process,
+    })
+}
+
+// An I/O between being queued and completing
+struct Io {
+    device: String,
+    sector: u64,
+    count: u64,
+    rwbs: String,
+    process: String,
+    queued: Option<i64>,
+    dispatched: Option<i64>,
+}
+
+impl Io {
+    fn covered_by(&self, device: &str, (sector, count): (u64, u64)) -> bool {
+        self.device == device && self.sector >= sector && self.sector < sector + count.max(1)
+    }
+}
+
+impl Timeline {
+    /// Read the output of blkparse into a timeline
+    ///
+    /// This reads what `blkparse` prints for the traces of `blktrace`, in its default format,
+    /// and draws every I/O in two rows of its device: from being queued (`Q`) until being
+    /// dispatched to the device (`D`) on a row named like "8,0 queue", and from then until it
+    /// completes (`C`) on a row named like "8,0 device", with a trigger from when it was
+    /// queued to when it completed. The events are named like blkparse prints I/Os, like
+    /// "WS 3417048 + 8", with the "process" that queued them and their "rwbs", "sector" and
+    /// "sectors" as metadata. I/Os merged into others (`M` and `F`) complete with them, and
+    /// requeued ones (`R`) wait to be dispatched again. I/Os that never complete last until
+    /// the end of the trace. Other actions are skipped. Times are in nanoseconds.
+    ///
+    /// This returns `TimelineError::Parse` if no line looks like blkparse output, and
+    /// `TimelineError::Io` if reading it fails. `Timeline::import` detects traces of this
+    /// format, as "blkparse".
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let trace = "\
+    ///     8,0    3        1     0.000000000   697  Q   W 3417048 + 8 [dd]\n\
+    ///     8,0    3        2     0.000002000   697  D   W 3417048 + 8 [dd]\n\
+    ///     8,0    0        3     0.000200000     0  C   W 3417048 + 8 [0]\n";
+    /// let timeline = Timeline::from_blkparse(trace.as_bytes()).unwrap();
+    /// assert_eq!(timeline.categories(), ["8,0 device", "8,0 queue"]);
+    /// assert_eq!(timeline.time_range(), Some((0, 200_000)));
+    /// # }
+    /// ```
+    pub fn from_blkparse(reader: impl Read) -> Result<Timeline, TimelineError> {
+        let mut timeline = new_timeline();
+        let mut parsed = false;
+        let mut end = 0;
+        let mut ios: Vec<Io> = Vec::new();
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            let Some(line) = parse_line(&line) else {
+                continue;
+            };
+            parsed = true;
+            end = end.max(line.time);
+            let Some(sectors) = line.sectors else {
+                continue;
+            };
+            match line.action {
+                "Q" => ios.push(Io {
+                    device: line.device.to_string(),
+                    sector: sectors.0,
+                    count: sectors.1,
+                    rwbs: line.rwbs.to_string(),
+                    process: line.process.to_string(),
+                    queued: Some(line.time),
+                    dispatched: None,
+                }),
+                "D" => {
+                    let mut found = false;
+                    for io in ios.iter_mut() {
+                        if io.dispatched.is_none() && io.covered_by(line.device, sectors) {
+                            io.dispatched = Some(line.time);
+                            found = true;
+                        }
+                    }
+                    // I/Os queued before the trace started
+                    if !found {
+                        ios.push(Io {
+                            device: line.device.to_string(),
+                            sector: sectors.0,
+                            count: sectors.1,
+                            rwbs: line.rwbs.to_string(),
+                            process: line.process.to_string(),
+                            queued: None,
+                            dispatched: Some(line.time),
+                        });
+                    }
+                }
+                "R" => {
+                    for io in ios.iter_mut() {
+                        if io.covered_by(line.device, sectors) {
+                            io.dispatched = None;
+                        }
+                    }
+                }
+                "C" => {
+                    let (done, waiting) = std::mem::take(&mut ios)
+                        .into_iter()
+                        .partition(|io| io.covered_by(line.device, sectors));
+                    ios = waiting;
+                    for io in done {
+                        add_io(&mut timeline, io, line.time, true);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !parsed {
+            return Err(TimelineError::Parse("no blkparse lines".to_string()));
+        }
+        for io in ios {
+            add_io(&mut timeline, io, end, false);
+        }
+        Ok(timeline)
+    }
+}
+
+// Add the events of an I/O that completed at `end`, or was still in flight when the trace ended
+// then, and the trigger from when it was queued to when it completed
+fn add_io(timeline: &mut Timeline, io: Io, end: i64, completed: bool) {
+    let name = format!("{} {} + {}", io.rwbs, io.sector, io.count);
+    let event = |start, end, location| {
+        EventBuilder::new(name.clone(), start, end, location)
+            .metadata("process".to_string(), io.process.clone())
+            .metadata("rwbs".to_string(), io.rwbs.clone())
+            .metadata("sector".to_string(), io.sector.to_string())
+            .metadata("sectors".to_string(), io.count.to_string())
+            .build()
+    };
+    let (queue, device) = (
+        format!("{} queue", io.device),
+        format!("{} device", io.device),
+    );
+    if let Some(queued) = io.queued {
+        timeline.insert_event(event(queued, io.dispatched.unwrap_or(end), queue.clone()));
+    }
+    if let Some(dispatched) = io.dispatched {
+        timeline.insert_event(event(dispatched, end, device.clone()));
+        if let Some(queued) = io.queued.filter(|_| completed) {
+            timeline.add_trigger_span(queue, queued, device, end);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The events of a trace, with times in microseconds since 1s
+    fn events(trace: &str) -> Vec<(String, String, i64, i64)> {
+        let timeline = Timeline::from_blkparse(trace.as_bytes()).unwrap();
+        timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name().to_string(),
+                    event.location().to_string(),
+                    (event.start_time() - 1_000_000_000) / 1000,
+                    (event.end_time() - 1_000_000_000) / 1000,
+                )
+            })
+            .collect()
+    }
+
+    fn event(name: &str, row: &str, start: i64, end: i64) -> (String, String, i64, i64) {
+        (name.to_string(), row.to_string(), start, end)
+    }
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(
+            parse_line("  8,0    3        4     0.000003000   697  P   N [kworker/u8:2]"),
+            Some(Line {
+                device: "8,0",
+                time: 3000,
+                action: "P",
+                rwbs: "N",
+                sectors: None,
+                process: "kworker/u8:2",
+            })
+        );
+        // Completions have their error in brackets
+        assert_eq!(
+            parse_line("259,0  0        1     1.000200000     0  C  WS 100 + 16 [0]"),
+            Some(Line {
+                device: "259,0",
+                time: 1_000_200_000,
+                action: "C",
+                rwbs: "WS",
+                sectors: Some((100, 16)),
+                process: "0",
+            })
+        );
+        assert_eq!(parse_line("CPU0 (8,0):"), None);
+        assert_eq!(
+            parse_line(" Reads Queued:           0,        0KiB\t Writes Queued:  2,  8KiB"),
+            None
+        );
+        assert_eq!(
+            parse_line("  8,0    3        1     0.000000000   697  Q  WS x + 8 [dd]"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_from_blkparse() {
+        let trace = "\
+  8,0    3        1     1.000000000   697  Q  WS 100 + 8 [kworker/u8:2]
+  8,0    3        2     1.000001000   697  G  WS 100 + 8 [kworker/u8:2]
+  8,0    3        3     1.000003000   697  D  WS 100 + 8 [kworker/u8:2]
+  8,0    0        1     1.000200000     0  C  WS 100 + 8 [0]
+CPU0 (8,0):
+ Reads Queued:           0,        0KiB\t Writes Queued:           1,        4KiB
+";
+        assert!(BlkparseImporter.sniff(trace.as_bytes()));
+        assert!(!BlkparseImporter.sniff(b"CPU0 (8,0):\n"));
+        assert_eq!(
+            events(trace),
+            [
+                event("WS 100 + 8", "8,0 queue", 0, 3),
+                event("WS 100 + 8", "8,0 device", 3, 200),
+            ]
+        );
+        let timeline = Timeline::from_blkparse(trace.as_bytes()).unwrap();
+        let (_, io) = timeline.events().next().unwrap();
+        assert_eq!(io.metadata()["process"], "kworker/u8:2");
+        assert_eq!(io.metadata()["rwbs"], "WS");
+        assert_eq!(io.metadata()["sector"], "100");
+        assert_eq!(io.metadata()["sectors"], "8");
+        let trigger = timeline.triggers().next().unwrap();
+        assert_eq!(trigger.start_location(), "8,0 queue");
+        assert_eq!(trigger.end_location(), "8,0 device");
+        assert_eq!(
+            (trigger.time(), trigger.end_time()),
+            (1_000_000_000, 1_000_200_000)
+        );
+    }
+
+    #[test]
+    fn test_merges_and_requeues() {
+        // I/Os merged into another are dispatched and complete with it, on their own device
+        let trace = "\
+  8,0    3        1     1.000000000   697  Q   W 100 + 8 [kworker/u8:2]
+  8,0    3        2     1.000002000   698  Q   W 108 + 8 [dd]
+  8,16   3        3     1.000002000   698  Q   W 108 + 8 [dd]
+  8,0    3        4     1.000002500   698  M   W 108 + 8 [dd]
+  8,0    3        5     1.000003000   697  D   W 100 + 16 [kworker/u8:2]
+  8,0    0        1     1.000200000     0  C   W 100 + 16 [0]
+";
+        assert_eq!(
+            events(trace),
+            [
+                event("W 100 + 8", "8,0 queue", 0, 3),
+                event("W 100 + 8", "8,0 device", 3, 200),
+                event("W 108 + 8", "8,0 queue", 2, 3),
+                event("W 108 + 8", "8,0 device", 3, 200),
+                event("W 108 + 8", "8,16 queue", 2, 200),
+            ]
+        );
+
+        // A requeued I/O waits in the queue again until it is dispatched again
+        let trace = "\
+  8,0    1        1     1.000000000   700  Q   R 0 + 8 [cat]
+  8,0    1        2     1.000010000   700  D   R 0 + 8 [cat]
+  8,0    1        3     1.000020000   700  R   R 0 + 8 [0]
+  8,0    1        4     1.000030000   700  D   R 0 + 8 [cat]
+  8,0    1        5     1.000040000   700  C   R 0 + 8 [0]
+  8,0    1        6     1.000050000   701  Q   R 8 + 8 [cat]
+  8,0    1        7     1.000060000   701  D   R 8 + 8 [cat]
+  8,0    1        8     1.000070000   701  R   R 8 + 8 [0]
+";
+        assert_eq!(
+            events(trace),
+            [
+                event("R 0 + 8", "8,0 queue", 0, 30),
+                event("R 0 + 8", "8,0 device", 30, 40),
+                event("R 8 + 8", "8,0 queue", 50, 70),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unfinished() {
+        // I/Os queued before the trace started are only drawn on the device, without a
+        // trigger, and those still in flight last until the end of the trace
+        let trace = "\
+  8,0    1        1     1.000000000   700  D   R 0 + 8 [cat]
+  8,0    1        2     1.000010000   700  C   R 0 + 8 [0]
+  8,0    1        3     1.000020000   701  Q   R 8 + 8 [cat]
+  8,0    1        4     1.000030000   701  D   R 8 + 8 [cat]
+  8,0    1        5     1.000050000   701  P   N [cat]
+";
+        assert_eq!(
+            events(trace),
+            [
+                event("R 0 + 8", "8,0 device", 0, 10),
+                event("R 8 + 8", "8,0 queue", 20, 30),
+                event("R 8 + 8", "8,0 device", 30, 50),
+            ]
+        );
+        let timeline = Timeline::from_blkparse(trace.as_bytes()).unwrap();
+        assert_eq!(timeline.triggers().count(), 0);
+        // Summaries alone aren't a trace
+        assert!(matches!(
+            Timeline::from_blkparse("CPU0 (8,0):".as_bytes()),
+            Err(TimelineError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,157 @@
+use svg::node::element::{Anchor, Group, Line, Text};
+
+use crate::{TimeValue, Timeline};
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct Bookmark<T> {
+    pub(crate) name: String,
+    pub(crate) time: T,
+}
+
+impl<T: TimeValue> Timeline<T> {
+    /// Bookmark `time` as `name`
+    ///
+    /// Bookmarks point reviewers straight at the interesting moments of a long trace. Each one is
+    /// drawn as a blue line across the rows with its name, and listed in an index below the
+    /// timeline, sorted by time. The entries of the index link to their lines, so clicking one in
+    /// a browser jumps to the bookmark, and carry the time in their `data-time` attribute for
+    /// viewers that zoom instead. Bookmarks extend the time range like markers do.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("read".to_string(), 0, 500, "CPU 0".to_string());
+    /// timeline.add_bookmark(320, "slow path".to_string());
+    /// assert_eq!(timeline.bookmarks().collect::<Vec<_>>(), [("slow path", 320)]);
+    /// # }
+    /// ```
+    pub fn add_bookmark(&mut self, time: T, name: impl Into<String>) {
+        if time < self.start_time {
+            self.start_time = time;
+        }
+        if time > self.end_time {
+            self.end_time = time;
+        }
+        self.bookmarks.push(Bookmark {
+            name: name.into(),
+            time,
+        });
+    }
+
+    /// The names and times of the bookmarks, sorted by time
+    pub fn bookmarks(&self) -> impl Iterator<Item = (&str, T)> + '_ {
+        self.sorted_bookmarks()
+            .into_iter()
+            .map(|(_, bookmark)| (bookmark.name.as_str(), bookmark.time))
+    }
+
+    // The bookmarks with the position they were added at, which makes up their element id,
+    // sorted by time and then name
+    fn sorted_bookmarks(&self) -> Vec<(usize, &Bookmark<T>)> {
+        let mut bookmarks: Vec<_> = self.bookmarks.iter().enumerate().collect();
+        bookmarks.sort_by(|(_, a), (_, b)| {
+            a.time
+                .partial_cmp(&b.time)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        bookmarks
+    }
+
+    // The line of a bookmark across the rows from `top` to `bottom`
+    pub(crate) fn bookmark_line(&self, index: usize, top: f64, bottom: f64) -> Group {
+        let bookmark = &self.bookmarks[index];
+        let x = self.time_x(bookmark.time);
+        let line = Line::new()
+            .set("x1", x)
+            .set("y1", top)
+            .set("x2", x)
+            .set("y2", bottom)
+            .set("stroke", "royalblue")
+            .set("stroke-width", 1);
+        let label = Text::new(bookmark.name.as_str())
+            .set("x", x + 2.0)
+            .set("y", bottom - 4.0)
+            .set("font-size", 10)
+            .set("fill", "royalblue");
+        Group::new()
+            .set("id", format!("bookmark-{}", index))
+            .set("class", "bookmark")
+            .add(line)
+            .add(label)
+    }
+
+    // The index of the bookmarks, a caption and one linked entry per bookmark, each a row high,
+    // starting at `top`
+    pub(crate) fn bookmark_index(&self, top: f64) -> Group {
+        let row_height = self.row_height as f64;
+        let mut index = Group::new().set("class", "bookmark-index").add(
+            Text::new(self.strings.bookmarks.as_str())
+                .set("x", 0)
+                .set("y", top + row_height * 0.7)
+                .set("font-size", 10)
+                .set("font-weight", "bold")
+                .set("fill", "black"),
+        );
+        let span = self.span();
+        let origin = match self.axis_origin {
+            Some(origin) if span > 0.0 => origin.offset_from(self.start_time, self.units),
+            _ => 0.0,
+        };
+        let step = self.tick_step();
+        for (row, (id, bookmark)) in self.sorted_bookmarks().into_iter().enumerate() {
+            let offset = bookmark.time.offset_from(self.start_time, self.units);
+            let entry = Text::new(format!(
+                "{} {}: {}",
+                self.tick_label(offset, origin, step),
+                self.axis_unit_label(),
+                bookmark.name
+            ))
+            .set("x", 0)
+            .set("y", top + row_height * (row as f64 + 1.7))
+            .set("font-size", 10)
+            .set("fill", "royalblue");
+            let link = Anchor::new()
+                .set("href", format!("#bookmark-{}", id))
+                .set("data-time", offset)
+                .add(entry);
+            index = index.add(link);
+        }
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bookmarks() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event".to_string(), 100, 200, "Location".to_string());
+        timeline.add_bookmark(300, "retry".to_string());
+        timeline.add_bookmark(150, "stall".to_string());
+        assert_eq!(
+            timeline.bookmarks().collect::<Vec<_>>(),
+            [("stall", 150), ("retry", 300)]
+        );
+        assert_eq!(timeline.time_range(), Some((100, 300)));
+
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.contains(r#"class="bookmark" id="bookmark-0""#));
+        // The index takes a row for its caption and one per bookmark
+        assert!(svg.contains(r#"height="101""#));
+        assert!(svg.contains(r##"<a data-time="50" href="#bookmark-1">"##));
+        assert!(svg.contains("50 ns: stall"));
+
+        timeline.crop(100, 200);
+        assert_eq!(timeline.bookmarks().count(), 1);
+        timeline.clear();
+        assert_eq!(timeline.bookmarks().count(), 0);
+    }
+}
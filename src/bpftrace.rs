@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+
+use crate::import::{new_timeline, sniff_text};
+use crate::{Timeline, TimelineError, TraceImporter};
+
+// The output of bpftrace scripts printing events, see `Timeline::from_bpftrace`
+pub(crate) struct BpftraceImporter;
+
+impl TraceImporter for BpftraceImporter {
+    fn format(&self) -> &str {
+        "bpftrace"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        sniff_text(data)
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .is_some_and(|line| {
+                line.starts_with("Attaching ")
+                    && (line.ends_with(" probe...") || line.ends_with(" probes..."))
+            })
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_bpftrace(data)
+    }
+}
+
+// A line printed by a script, like "1500 B tid42 read"
+#[derive(Debug, PartialEq)]
+struct Line<'a> {
+    time: i64,
+    kind: Kind,
+    location: &'a str,
+    name: &'a str,
+}
+
+#[derive(Debug, PartialEq)]
+enum Kind {
+    Begin,
+    End,
+    Instant,
+    Complete(i64),
+}
+
+// A time in nanoseconds, like `nsecs` prints, or of the day, like `time("%H:%M:%S")` prints
+fn parse_time(text: &str) -> Option<i64> {
+    if let Ok(nanos) = text.parse() {
+        return Some(nanos);
+    }
+    let mut fields = text.split(':');
+    let (hours, minutes, seconds) = (fields.next()?, fields.next()?, fields.next()?);
+    if fields.next().is_some() {
+        return None;
+    }
+    let minutes = hours
+        .parse::<i64>()
+        .ok()?
+        .checked_mul(60)?
+        .checked_add(minutes.parse().ok()?)?;
+    minutes
+        .checked_mul(60_000_000_000)?
+        .checked_add(crate::ftrace::parse_time(seconds)?)
+}
+
+// Take the first field off `rest`
+fn field<'a>(rest: &mut &'a str) -> Option<&'a str> {
+    let text = rest.trim_start();
+    let (field, after) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    *rest = after.trim_start();
+    Some(field).filter(|field| !field.is_empty())
+}
+
+fn parse_line(line: &str) -> Option<Line<'_>> {
+    let mut rest = line;
+    let time = parse_time(field(&mut rest)?)?;
+    let kind = match field(&mut rest)? {
+        "B" => Kind::Begin,
+        "E" => Kind::End,
+        "I" => Kind::Instant,
+        "X" => Kind::Complete(field(&mut rest)?.parse().ok()?),
+        _ => return None,
+    };
+    let location = field(&mut rest)?;
+    Some(Line {
+        time,
+        kind,
+        location,
+        name: rest.trim_end(),
+    })
+}
+
+impl Timeline {
+    /// Read the output of a bpftrace script into a timeline
+    ///
+    /// bpftrace scripts print whatever they like, so this reads the lines that follow a small
+    /// convention, and skips the others, like the "Attaching 2 probes..." bpftrace starts
+    /// with and the maps it prints when it exits. Every event line has these fields, separated
+    /// by spaces:
+    ///
+    /// - the time, as a number of nanoseconds like `nsecs` and `elapsed` print, or a time of
+    ///   the day like `time("%H:%M:%S ")` prints
+    /// - `B` for the beginning of an event, `E` for its end, `I` for an instant, or `X` for a
+    ///   complete event followed by its duration in nanoseconds
+    /// - the row of the event, without spaces, like "cpu3" or "tid42"
+    /// - the name of the event, the rest of the line, which `E` lines don't need
+    ///
+    /// An `E` ends the last event that began on its row and hasn't ended, and events that never
+    /// end last until the last time of the output. For example, this script draws the reads
+    /// of every thread:
+    ///
+    /// ```text
+    /// tracepoint:syscalls:sys_enter_read { printf("%lld B tid%d read %s\n", nsecs, tid, comm); }
+    /// tracepoint:syscalls:sys_exit_read { printf("%lld E tid%d\n", nsecs, tid); }
+    /// ```
+    ///
+    /// Times are in nanoseconds. This returns `TimelineError::Parse` if no line follows the
+    /// convention or a complete event ends before it starts or too late to tell, and
+    /// `TimelineError::Io` if reading fails. `Timeline::import` detects output that starts like
+    /// bpftrace's, as "bpftrace".
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let output = "\
+    ///     Attaching 2 probes...\n\
+    ///     1000 B tid42 read bash\n\
+    ///     1500 E tid42\n\
+    ///     1700 X 300 tid43 write\n";
+    /// let timeline = Timeline::from_bpftrace(output.as_bytes()).unwrap();
+    /// assert_eq!(timeline.categories(), ["tid42", "tid43"]);
+    /// assert_eq!(timeline.time_range(), Some((1000, 2000)));
+    /// # }
+    /// ```
+    pub fn from_bpftrace(reader: impl Read) -> Result<Timeline, TimelineError> {
+        let mut timeline = new_timeline();
+        let mut parsed = false;
+        let mut end = 0;
+        // The events that began and haven't ended yet, by row
+        let mut open: HashMap<String, Vec<(String, i64)>> = HashMap::new();
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            let Some(line) = parse_line(&line) else {
+                continue;
+            };
+            parsed = true;
+            end = end.max(line.time);
+            match line.kind {
+                Kind::Begin => open
+                    .entry(line.location.to_string())
+                    .or_default()
+                    .push((line.name.to_string(), line.time)),
+                Kind::End => {
+                    let began = open.get_mut(line.location).and_then(|events| events.pop());
+                    if let Some((name, start)) = began {
+                        timeline.add_event(name, start, line.time.max(start), line.location);
+                    }
+                }
+                Kind::Instant => {
+                    timeline.add_instant(line.name, line.time, line.location);
+                }
+                Kind::Complete(duration) => {
+                    let finish = line
+                        .time
+                        .checked_add(duration)
+                        .filter(|_| duration >= 0)
+                        .ok_or_else(|| {
+                            TimelineError::Parse(format!(
+                                "{} at {} lasts {}ns",
+                                line.name, line.time, duration
+                            ))
+                        })?;
+                    end = end.max(finish);
+                    timeline.add_event(line.name, line.time, finish, line.location);
+                }
+            }
+        }
+        if !parsed {
+            return Err(TimelineError::Parse("no bpftrace events".to_string()));
+        }
+        let mut open: Vec<_> = open.into_iter().collect();
+        open.sort();
+        for (location, events) in open {
+            for (name, start) in events {
+                timeline.add_event(name, start, end, location.clone());
+            }
+        }
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(output: &str) -> Vec<(String, String, i64, i64)> {
+        let timeline = Timeline::from_bpftrace(output.as_bytes()).unwrap();
+        timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name().to_string(),
+                    event.location().to_string(),
+                    event.start_time(),
+                    event.end_time(),
+                )
+            })
+            .collect()
+    }
+
+    fn event(name: &str, row: &str, start: i64, end: i64) -> (String, String, i64, i64) {
+        (name.to_string(), row.to_string(), start, end)
+    }
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(parse_time("1500"), Some(1500));
+        assert_eq!(parse_time("10:00:01"), Some(36_001_000_000_000));
+        assert_eq!(parse_time("10:00:01.5"), Some(36_001_500_000_000));
+        assert_eq!(parse_time("10:00"), None);
+        assert_eq!(parse_time("10:00:01:00"), None);
+        assert_eq!(parse_time("9999999999999:00:00"), None);
+        assert_eq!(parse_time("@reads[bash]:"), None);
+
+        // Names are the rest of the line, with its inner spaces
+        assert_eq!(
+            parse_line("10:00:01 I cpu3 page fault  at 0x10 "),
+            Some(Line {
+                time: 36_001_000_000_000,
+                kind: Kind::Instant,
+                location: "cpu3",
+                name: "page fault  at 0x10",
+            })
+        );
+        assert_eq!(
+            parse_line("1300  X  50  tid43 write"),
+            Some(Line {
+                time: 1300,
+                kind: Kind::Complete(50),
+                location: "tid43",
+                name: "write",
+            })
+        );
+        assert_eq!(
+            parse_line("1200 E tid42"),
+            Some(Line {
+                time: 1200,
+                kind: Kind::End,
+                location: "tid42",
+                name: "",
+            })
+        );
+        assert_eq!(parse_line("1300 X tid43 write"), None);
+        assert_eq!(parse_line("1300 Q tid43 write"), None);
+        assert_eq!(parse_line("1300 B"), None);
+        assert_eq!(parse_line("@reads[bash]: 3"), None);
+        assert_eq!(parse_line(""), None);
+    }
+
+    #[test]
+    fn test_nesting() {
+        // An end ends the last event that began on its row, whatever name it has
+        let output = "\
+Attaching 3 probes...
+1000 B tid42 read
+1050 B tid43 read
+1100 B tid42 ext4_file_read_iter
+1200 E tid42
+1300 E tid43
+1400 E tid42 write
+1500 E tid42
+";
+        assert!(BpftraceImporter.sniff(output.as_bytes()));
+        // The output of a script without the line bpftrace starts with is up to the caller
+        assert!(!BpftraceImporter.sniff(b"1000 I cpu0 tick\n"));
+        assert_eq!(
+            events(output),
+            [
+                event("ext4_file_read_iter", "tid42", 1100, 1200),
+                event("read", "tid43", 1050, 1300),
+                event("read", "tid42", 1000, 1400),
+            ]
+        );
+
+        // Per-CPU buffers can print an end before its begin
+        let output = "\
+1000 B tid42 read
+900 E tid42
+";
+        assert_eq!(events(output), [event("read", "tid42", 1000, 1000)]);
+    }
+
+    #[test]
+    fn test_unfinished() {
+        // Events that don't end last until the last time, also the end of a complete event,
+        // and maps printed at the exit are skipped
+        let output = "\
+Attaching 3 probes...
+1000 B tid42 read
+1100 B tid43 fsync
+1300 X 500 tid44 write
+1400 I tid44 wakeup
+
+@reads[bash]: 1
+";
+        assert_eq!(
+            events(output),
+            [
+                event("write", "tid44", 1300, 1800),
+                event("wakeup", "tid44", 1400, 1400),
+                event("read", "tid42", 1000, 1800),
+                event("fsync", "tid43", 1100, 1800),
+            ]
+        );
+
+        // Output without any events is an error
+        assert!(matches!(
+            Timeline::from_bpftrace("Attaching 1 probe...\n".as_bytes()),
+            Err(TimelineError::Parse(_))
+        ));
+        assert!(matches!(
+            Timeline::from_bpftrace("@reads[bash]: 1\n".as_bytes()),
+            Err(TimelineError::Parse(_))
+        ));
+        // as are complete events that end before they start or too late to tell
+        for output in [
+            "1300 X -5 tid44 write\n",
+            "1300 X 9223372036854775807 tid44 write\n",
+        ] {
+            assert!(matches!(
+                Timeline::from_bpftrace(output.as_bytes()),
+                Err(TimelineError::Parse(_))
+            ));
+        }
+    }
+}
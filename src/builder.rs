@@ -0,0 +1,163 @@
+use rand::prelude::*;
+
+use crate::{Margins, ScaleMode, Strings, TimeUnit, TimeValue, Timeline};
+
+/// A builder for configuring a `Timeline`
+///
+/// Every setting starts out with the same value as `Timeline::default()`, so only the settings
+/// that differ need to be given.
+///
+/// ```
+/// # extern crate timeline_svg;
+/// # fn main() {
+/// use timeline_svg::{Margins, ScaleMode, TimeUnit, Timeline};
+///
+/// let mut timeline = Timeline::builder()
+///     .units(TimeUnit::Microseconds)
+///     .row_height(30)
+///     .scale_mode(ScaleMode::FitWidth(1200))
+///     .margins(Margins {
+///         top: 10,
+///         right: 10,
+///         bottom: 10,
+///         left: 10,
+///     })
+///     .build();
+/// timeline.add_event("Process A".to_string(), 0, 100, "CPU 0".to_string());
+/// # }
+/// ```
+pub struct TimelineBuilder<T = i64> {
+    timeline: Timeline<T>,
+}
+
+impl Default for TimelineBuilder {
+    fn default() -> Self {
+        TimelineBuilder::new()
+    }
+}
+
+impl<T: TimeValue> TimelineBuilder<T> {
+    /// Create a builder with the default settings
+    pub fn new() -> Self {
+        TimelineBuilder {
+            timeline: Timeline::new(),
+        }
+    }
+
+    /// Set the height of each row in pixels, the default is 20
+    pub fn row_height(mut self, row_height: u64) -> Self {
+        self.timeline.row_height = row_height;
+        self
+    }
+
+    /// Set the width of one time unit in pixels, the default is 200
+    ///
+    /// This is only used with `ScaleMode::PerUnit`.
+    pub fn column_width(mut self, column_width: u64) -> Self {
+        self.timeline.column_width = column_width;
+        self
+    }
+
+    /// Set the space between the top of a row and its events in pixels, the default is 1
+    pub fn row_padding(mut self, row_padding: u64) -> Self {
+        self.timeline.row_padding = row_padding;
+        self
+    }
+
+    /// Set the horizontal offset of everything after the start time in pixels, the default is 0
+    pub fn column_padding(mut self, column_padding: u64) -> Self {
+        self.timeline.column_padding = column_padding;
+        self
+    }
+
+    /// Set the units of the timeline, the default is nanoseconds
+    pub fn units(mut self, units: TimeUnit) -> Self {
+        self.timeline.units = units;
+        self
+    }
+
+    /// Set the static text of the timeline, see `Timeline::set_strings`
+    pub fn strings(mut self, strings: Strings) -> Self {
+        self.timeline.strings = strings;
+        self
+    }
+
+    /// Set the colors events are picked from
+    ///
+    /// Any valid SVG color can be used. An empty palette is ignored.
+    pub fn palette(mut self, palette: Vec<String>) -> Self {
+        if !palette.is_empty() {
+            self.timeline.palette = palette;
+        }
+        self
+    }
+
+    /// Set how times are scaled to pixels, the default is `ScaleMode::PerUnit`
+    pub fn scale_mode(mut self, scale_mode: ScaleMode) -> Self {
+        self.timeline.scale_mode = scale_mode;
+        self
+    }
+
+    /// Set the empty space around the timeline, the default is no margins
+    pub fn margins(mut self, margins: Margins) -> Self {
+        self.timeline.margins = margins;
+        self
+    }
+
+    /// Seed the random color picker
+    ///
+    /// Colors are picked at random from the palette for each new event name. With a seed the
+    /// picks are the same every time the same events are added in the same order, which makes
+    /// the output reproducible.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.timeline.rng = Some(StdRng::seed_from_u64(seed));
+        self
+    }
+
+    /// Build the configured timeline
+    pub fn build(self) -> Timeline<T> {
+        self.timeline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder() {
+        let mut timeline = TimelineBuilder::new()
+            .row_height(10)
+            .palette(vec!["red".to_string()])
+            .scale_mode(ScaleMode::FitWidth(1000))
+            .margins(Margins {
+                top: 5,
+                right: 0,
+                bottom: 0,
+                left: 5,
+            })
+            .build();
+        timeline.add_event("Event 1".to_string(), 0, 10, "Location 1".to_string());
+        assert_eq!(timeline.colormap["Event 1"], "red");
+        assert_eq!(timeline.time_x(5), 500.0);
+
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.contains(r#"width="1005""#));
+        assert!(svg.contains(r#"height="26""#));
+        assert!(svg.contains("translate(5,5)"));
+    }
+
+    #[test]
+    fn test_seed() {
+        let colors = |seed| {
+            let mut timeline = TimelineBuilder::new().seed(seed).build();
+            for i in 0..10 {
+                timeline.add_event(format!("Event {}", i), 0, 1, "Location 1".to_string());
+            }
+            timeline.export_colormap()
+        };
+        assert_eq!(colors(1), colors(1));
+    }
+}
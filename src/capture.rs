@@ -0,0 +1,172 @@
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::{ScaleMode, TimeUnit, Timeline, TimelineBuilder, TimelineError};
+
+// The timeline of the running capture, shared by every thread so scopes on any thread are
+// recorded
+static ACTIVE: Mutex<Option<Timeline>> = Mutex::new(None);
+
+// Takes the timeline out of ACTIVE when the capture ends, even if the closure panics
+struct ActiveGuard;
+
+impl Drop for ActiveGuard {
+    fn drop(&mut self) {
+        lock().take();
+    }
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Timeline>> {
+    // A panic while holding the lock leaves nothing half-updated worth refusing
+    ACTIVE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// An event that lasts until it is dropped, see `scope`
+#[must_use = "the event ends when the scope is dropped"]
+pub struct Scope {
+    name: String,
+    start: Instant,
+}
+
+/// Start an event that lasts until the returned `Scope` is dropped
+///
+/// While a capture is running, see `Timeline::capture`, the event is added to its timeline on the
+/// row of the current thread, named after the thread. Outside of a capture this does nothing, so
+/// scopes can be left in code that isn't always captured.
+///
+/// ```
+/// # extern crate timeline_svg;
+/// # fn main() {
+/// fn parse() {
+///     let _scope = timeline_svg::scope("parse");
+///     // ... the work to show on the timeline ...
+/// }
+/// # parse();
+/// # }
+/// ```
+pub fn scope(name: impl Into<String>) -> Scope {
+    Scope {
+        name: name.into(),
+        start: Instant::now(),
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let mut active = lock();
+        if let Some(timeline) = active.as_mut() {
+            let thread = std::thread::current();
+            let location = match thread.name() {
+                Some(name) => name.to_string(),
+                None => format!("{:?}", thread.id()),
+            };
+            let name = std::mem::take(&mut self.name);
+            timeline.add_event_duration(name, self.start, self.start.elapsed(), location);
+        }
+    }
+}
+
+impl Timeline {
+    /// Record a timeline of running `f` and save it to `path`
+    ///
+    /// This is the quickest way to instrument a program: the start and end of `f` are marked on
+    /// a fresh timeline, every `scope` dropped while `f` runs is added to it, and it is saved to
+    /// `path` once `f` returns. The timeline is in microseconds and scaled to 1200 pixels wide,
+    /// use `TimelineBuilder::capture` to configure it differently. Only one capture can run at a
+    /// time.
+    ///
+    /// ```no_run
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::{scope, Timeline};
+    ///
+    /// Timeline::capture("run.svg", || {
+    ///     let _setup = scope("setup");
+    ///     // ...
+    /// })
+    /// .unwrap();
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This panics if another capture is already running.
+    pub fn capture<R>(path: impl AsRef<Path>, f: impl FnOnce() -> R) -> Result<R, TimelineError> {
+        Timeline::builder()
+            .units(TimeUnit::Microseconds)
+            .scale_mode(ScaleMode::FitWidth(1200))
+            .capture(path, f)
+    }
+}
+
+impl TimelineBuilder {
+    /// Record a timeline of running `f` with the configured timeline, see `Timeline::capture`
+    ///
+    /// # Panics
+    ///
+    /// This panics if another capture is already running.
+    pub fn capture<R>(
+        self,
+        path: impl AsRef<Path>,
+        f: impl FnOnce() -> R,
+    ) -> Result<R, TimelineError> {
+        let mut timeline = self.build();
+        let start = Instant::now();
+        timeline.set_epoch(start);
+        let label = timeline.strings.capture_start.clone();
+        timeline.add_marker(label, 0);
+        {
+            let mut active = lock();
+            assert!(active.is_none(), "a timeline capture is already running");
+            *active = Some(timeline);
+        }
+
+        let guard = ActiveGuard;
+        let result = f();
+        let mut timeline = lock().take().expect("the running capture disappeared");
+        drop(guard);
+
+        let end = timeline.units.from_duration(start.elapsed()) as i64;
+        let label = timeline.strings.capture_end.clone();
+        timeline.add_marker(label, end);
+        timeline.save(path)?;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture() {
+        let dir = std::env::temp_dir().join(format!("timeline-svg-capture-{}", std::process::id()));
+        let path = dir.join("run.svg");
+        std::fs::create_dir_all(&dir).unwrap();
+        let value = Timeline::capture(&path, || {
+            let _outer = scope("outer");
+            drop(scope("inner"));
+            std::thread::Builder::new()
+                .name("worker".to_string())
+                .spawn(|| drop(scope("work")))
+                .unwrap()
+                .join()
+                .unwrap();
+            5
+        })
+        .unwrap();
+        assert_eq!(value, 5);
+        // Scopes outside of a capture are ignored
+        drop(scope("ignored"));
+
+        let svg = std::fs::read_to_string(&path).unwrap();
+        assert!(svg.contains("outer"));
+        assert!(svg.contains("inner"));
+        assert!(svg.contains("work"));
+        assert!(svg.contains("start"));
+        assert!(svg.contains("end"));
+        assert!(!svg.contains("ignored"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
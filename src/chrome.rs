@@ -0,0 +1,377 @@
+use std::collections::{BTreeSet, HashMap};
+use std::io::Read;
+
+use serde_json::Value;
+
+use crate::import::{new_timeline, sniff_text};
+use crate::{EventBuilder, Timeline, TimelineError, TraceImporter};
+
+// The Chrome trace event format, see `Timeline::from_chrome_trace`
+pub(crate) struct ChromeJsonImporter;
+
+impl TraceImporter for ChromeJsonImporter {
+    fn format(&self) -> &str {
+        "chrome-json"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        let text = sniff_text(data);
+        let text = text.trim_start();
+        (text.starts_with('{') && text.contains("\"traceEvents\""))
+            || (text.starts_with('[') && text.contains("\"ph\""))
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_chrome_trace(data)
+    }
+}
+
+// A trace event field as text, like the arguments of events
+pub(crate) fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+// Chrome traces count in microseconds, timelines made from them in nanoseconds
+fn nanos(value: &Value) -> Option<i64> {
+    value
+        .as_f64()
+        .map(|micros| (micros * 1000.0).round() as i64)
+}
+
+impl Timeline {
+    /// Read a trace in the Chrome trace event format into a timeline
+    ///
+    /// This reads the JSON written by chrome://tracing, the Go execution tracer, `perf` and many
+    /// profilers, either an object with a `traceEvents` array or a bare array of events, which
+    /// may be cut off without its closing bracket as the format allows. Every thread becomes a
+    /// row, named after its `thread_name` metadata or its ids, and prefixed with the name of its
+    /// process if the trace has more than one. Complete (`X`) events and matching begin and end
+    /// (`B`/`E`) events become events, with their arguments as metadata, and begin events that
+    /// never end last until the end of the trace. Instant (`i`) events become instants, or
+    /// markers if they are global. Flow events (`s`, `t` and `f`) become triggers from each
+    /// step of the flow to the next. Times are in nanoseconds. Other events are skipped.
+    ///
+    /// This returns `TimelineError::Parse` if the trace isn't JSON of the expected shape or has
+    /// an event that ends before it starts, and `TimelineError::Io` if reading it fails.
+    /// `Timeline::import` detects traces of this format, as "chrome-json".
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let trace = r#"[{"name": "read", "ph": "X", "ts": 1, "dur": 4, "pid": 1, "tid": 2}"#;
+    /// let timeline = Timeline::from_chrome_trace(trace.as_bytes()).unwrap();
+    /// assert_eq!(timeline.time_range(), Some((1000, 5000)));
+    /// assert_eq!(timeline.categories(), ["1/2"]);
+    /// # }
+    /// ```
+    pub fn from_chrome_trace(mut reader: impl Read) -> Result<Timeline, TimelineError> {
+        let mut data = String::new();
+        reader.read_to_string(&mut data)?;
+        let trace: Value = match serde_json::from_str(&data) {
+            Ok(trace) => trace,
+            Err(e) => {
+                // The array format can end anywhere after an event, trailing comma and all
+                let trimmed = data.trim_end().trim_end_matches(',');
+                if !trimmed.trim_start().starts_with('[') {
+                    return Err(TimelineError::Parse(e.to_string()));
+                }
+                serde_json::from_str(&format!("{}]", trimmed))
+                    .map_err(|_| TimelineError::Parse(e.to_string()))?
+            }
+        };
+        let events = match &trace {
+            Value::Array(events) => events,
+            Value::Object(object) => object
+                .get("traceEvents")
+                .and_then(Value::as_array)
+                .ok_or_else(|| TimelineError::Parse("no traceEvents array".to_string()))?,
+            _ => {
+                return Err(TimelineError::Parse(
+                    "not a trace object or array".to_string(),
+                ))
+            }
+        };
+
+        let field = |event: &Value, name: &str| event.get(name).map(text).unwrap_or_default();
+        let mut thread_names = HashMap::new();
+        let mut process_names = HashMap::new();
+        let mut pids = BTreeSet::new();
+        for event in events {
+            let pid = field(event, "pid");
+            let name = event.pointer("/args/name").map(text);
+            match (event["ph"].as_str(), event["name"].as_str(), name) {
+                (Some("M"), Some("thread_name"), Some(name)) => {
+                    thread_names.insert((pid.clone(), field(event, "tid")), name);
+                }
+                (Some("M"), Some("process_name"), Some(name)) => {
+                    process_names.insert(pid.clone(), name);
+                }
+                _ => {}
+            }
+            pids.insert(pid);
+        }
+        let location = |event: &Value| {
+            let (pid, tid) = (field(event, "pid"), field(event, "tid"));
+            let thread = thread_names
+                .get(&(pid.clone(), tid.clone()))
+                .cloned()
+                .unwrap_or_else(|| format!("{}/{}", pid, tid));
+            match process_names.get(&pid) {
+                Some(process) if pids.len() > 1 => format!("{}/{}", process, thread),
+                _ => thread,
+            }
+        };
+
+        let mut timeline = new_timeline();
+        let end = events
+            .iter()
+            .filter_map(|event| {
+                let ts = nanos(&event["ts"])?;
+                ts.checked_add(nanos(&event["dur"]).unwrap_or(0))
+            })
+            .max()
+            .unwrap_or(0);
+        // The open begin events of every row, innermost last
+        let mut open: HashMap<String, Vec<(&Value, i64)>> = HashMap::new();
+        // The last step of every flow, by category and id
+        let mut flows: HashMap<(String, String), (String, i64)> = HashMap::new();
+        let add = |timeline: &mut Timeline, begin: &Value, start, end, location: String| {
+            if end < start {
+                return Err(TimelineError::Parse(format!(
+                    "event {:?} at {} ends before it starts",
+                    field(begin, "name"),
+                    begin["ts"]
+                )));
+            }
+            let mut builder = EventBuilder::new(field(begin, "name"), start, end, location);
+            if let Some(args) = begin["args"].as_object() {
+                for (key, value) in args {
+                    builder = builder.metadata(key.clone(), text(value));
+                }
+            }
+            timeline.insert_event(builder.build());
+            Ok(())
+        };
+        for event in events {
+            let Some(ts) = nanos(&event["ts"]) else {
+                continue;
+            };
+            match event["ph"].as_str() {
+                Some("X") => {
+                    let dur = nanos(&event["dur"]).unwrap_or(0);
+                    let end = ts.checked_add(dur).ok_or_else(|| {
+                        TimelineError::Parse(format!("event at {} lasts too long", event["ts"]))
+                    })?;
+                    add(&mut timeline, event, ts, end, location(event))?;
+                }
+                Some("B") => open.entry(location(event)).or_default().push((event, ts)),
+                Some("E") => {
+                    let location = location(event);
+                    if let Some((begin, start)) = open.get_mut(&location).and_then(Vec::pop) {
+                        add(&mut timeline, begin, start, ts, location)?;
+                    }
+                }
+                Some("i" | "I") if event["s"].as_str() == Some("g") => {
+                    timeline.add_marker(field(event, "name"), ts);
+                }
+                Some("i" | "I") => {
+                    timeline.add_instant(field(event, "name"), ts, location(event));
+                }
+                Some(phase @ ("s" | "t" | "f")) => {
+                    let key = (field(event, "cat"), field(event, "id"));
+                    let location = location(event);
+                    if phase != "s" {
+                        if let Some((from, start)) = flows.get(&key) {
+                            timeline.add_trigger_span(from.clone(), *start, location.clone(), ts);
+                        }
+                    }
+                    if phase == "f" {
+                        flows.remove(&key);
+                    } else {
+                        flows.insert(key, (location, ts));
+                    }
+                }
+                _ => {}
+            }
+        }
+        let mut unfinished: Vec<_> = open.into_iter().collect();
+        unfinished.sort_by(|a, b| a.0.cmp(&b.0));
+        for (location, begins) in unfinished {
+            for (begin, start) in begins {
+                add(&mut timeline, begin, start, end, location.clone())?;
+            }
+        }
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields() {
+        assert_eq!(text(&serde_json::json!("read")), "read");
+        assert_eq!(text(&serde_json::json!(7)), "7");
+        assert_eq!(text(&serde_json::json!({"a": [1]})), r#"{"a":[1]}"#);
+        assert_eq!(nanos(&serde_json::json!(1.0005)), Some(1001));
+        assert_eq!(nanos(&serde_json::json!(-2)), Some(-2000));
+        assert_eq!(nanos(&serde_json::json!("1")), None);
+    }
+
+    #[test]
+    fn test_from_chrome_trace() {
+        let trace = r#"{"traceEvents": [
+            {"name": "run", "ph": "B", "ts": 0, "pid": 1, "tid": 1, "args": {"depth": 1}},
+            {"name": "parse", "ph": "B", "ts": 1, "pid": 1, "tid": 1},
+            {"ph": "E", "ts": 2, "pid": 1, "tid": 1},
+            {"ph": "E", "ts": 3, "pid": 1, "tid": 2},
+            {"ph": "E", "ts": 10, "pid": 1, "tid": 1},
+            {"name": "loop", "ph": "B", "ts": 8, "pid": 1, "tid": 2},
+            {"name": "tail", "ph": "X", "ts": 9, "dur": 3.5, "pid": 1, "tid": 1},
+            {"name": "gc", "ph": "i", "s": "t", "ts": 4, "pid": 1, "tid": 3},
+            {"name": "vsync", "ph": "i", "s": "g", "ts": 6, "pid": 1, "tid": 3},
+            {"name": "counter", "ph": "C", "ts": 9, "pid": 1, "tid": 3, "args": {"n": 1}}
+        ]}"#;
+        assert!(ChromeJsonImporter.sniff(trace.as_bytes()));
+        assert!(!ChromeJsonImporter.sniff(b"{\"resourceSpans\": []}"));
+        assert!(!ChromeJsonImporter.sniff(b"[{\"traceEvents\": []}]"));
+        let timeline = Timeline::from_chrome_trace(trace.as_bytes()).unwrap();
+        // Ends close the innermost begin of their thread, begins that never end last until the
+        // end of the last event, global instants are markers and counters are skipped
+        let events: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name(),
+                    event.location(),
+                    event.start_time(),
+                    event.end_time(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("parse", "1/1", 1000, 2000),
+                ("run", "1/1", 0, 10000),
+                ("tail", "1/1", 9000, 12500),
+                ("gc", "1/3", 4000, 4000),
+                ("loop", "1/2", 8000, 12500),
+            ]
+        );
+        let (_, run) = timeline.events().nth(1).unwrap();
+        assert_eq!(run.metadata()["depth"], "1");
+        assert_eq!(timeline.markers[0].label, "vsync");
+        assert_eq!(timeline.markers[0].time, 6000);
+
+        // Events can't end before they start, or later than a time can be
+        for trace in [
+            r#"[{"name": "a", "ph": "X", "ts": 5, "dur": -1, "pid": 1, "tid": 1}]"#,
+            r#"[{"name": "a", "ph": "X", "ts": 9e15, "dur": 9e15, "pid": 1, "tid": 1}]"#,
+            r#"[{"name": "a", "ph": "B", "ts": 5, "pid": 1, "tid": 1},
+                {"ph": "E", "ts": 2, "pid": 1, "tid": 1}]"#,
+        ] {
+            assert!(matches!(
+                Timeline::from_chrome_trace(trace.as_bytes()),
+                Err(TimelineError::Parse(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_rows_and_flows() {
+        // Threads are named after their metadata, and prefixed with their process when the
+        // trace has more than one
+        let trace = r#"[
+            {"name": "thread_name", "ph": "M", "pid": 1, "tid": 1, "args": {"name": "main"}},
+            {"name": "process_name", "ph": "M", "pid": 1, "args": {"name": "app"}},
+            {"name": "a", "ph": "X", "ts": 0, "pid": 1, "tid": 1},
+            {"name": "b", "ph": "X", "ts": 0, "pid": 1, "tid": 2},
+            {"name": "c", "ph": "X", "ts": 0, "pid": 2, "tid": 4}
+        ]"#;
+        let timeline = Timeline::from_chrome_trace(trace.as_bytes()).unwrap();
+        assert_eq!(timeline.categories(), ["2/4", "app/1/2", "app/main"]);
+
+        // Flows are told apart by their category and id, and end with their finish
+        let trace = r#"[
+            {"name": "send", "ph": "s", "cat": "ipc", "id": 7, "ts": 1, "pid": 1, "tid": 1},
+            {"name": "send", "ph": "s", "cat": "net", "id": 7, "ts": 2, "pid": 1, "tid": 1},
+            {"name": "hop", "ph": "t", "cat": "ipc", "id": 7, "ts": 3, "pid": 1, "tid": 2},
+            {"name": "recv", "ph": "f", "cat": "ipc", "id": 7, "ts": 5, "pid": 1, "tid": 3},
+            {"name": "late", "ph": "f", "cat": "ipc", "id": 7, "ts": 6, "pid": 1, "tid": 4},
+            {"name": "recv", "ph": "f", "cat": "net", "id": 7, "ts": 7, "pid": 1, "tid": 4}
+        ]"#;
+        let timeline = Timeline::from_chrome_trace(trace.as_bytes()).unwrap();
+        let triggers: Vec<_> = timeline
+            .triggers()
+            .map(|trigger| {
+                (
+                    trigger.start_location(),
+                    trigger.time(),
+                    trigger.end_location(),
+                    trigger.end_time(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            triggers,
+            [
+                ("1/1", 1000, "1/2", 3000),
+                ("1/2", 3000, "1/3", 5000),
+                ("1/1", 2000, "1/4", 7000),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_imported() {
+        // A trace of milliseconds is scaled to fit the window rather than drawn a column per
+        // nanosecond
+        let events: Vec<_> = (0..50)
+            .map(|i| {
+                format!(
+                    r#"{{"name": "tick", "ph": "X", "ts": {}, "dur": 80, "pid": 1, "tid": {}}}"#,
+                    i * 100,
+                    i % 4
+                )
+            })
+            .collect();
+        let trace = format!("[{}]", events.join(","));
+        let timeline = Timeline::from_chrome_trace(trace.as_bytes()).unwrap();
+        assert_eq!(timeline.time_range(), Some((0, 4_980_000)));
+        let mut svg = Vec::new();
+        timeline.write(&mut svg).unwrap();
+        assert!(svg.len() < 100_000, "{} bytes", svg.len());
+    }
+
+    #[test]
+    fn test_from_chrome_trace_cut_off() {
+        // The array format can end after any event, with or without a comma
+        for trace in [
+            r#"[{"name": "a", "ph": "X", "ts": 1, "dur": 1, "pid": 1, "tid": 1},"#,
+            "[{\"name\": \"a\", \"ph\": \"X\", \"ts\": 1, \"dur\": 1, \"pid\": 1, \"tid\": 1}\n",
+        ] {
+            let timeline = Timeline::from_chrome_trace(trace.as_bytes()).unwrap();
+            assert_eq!(timeline.time_range(), Some((1000, 2000)));
+        }
+        // but not in the middle of one, and objects can't be cut off
+        for trace in [
+            r#"[{"name": "a", "ph": "X", "ts": 1"#,
+            r#"{"traceEvents": [{"name": "a", "ph": "X", "ts": 1, "dur": 1},"#,
+            "{\"traceEvents\": 1}",
+            "3",
+            "",
+        ] {
+            assert!(matches!(
+                Timeline::from_chrome_trace(trace.as_bytes()),
+                Err(TimelineError::Parse(_))
+            ));
+        }
+    }
+}
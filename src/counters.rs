@@ -0,0 +1,198 @@
+use svg::node::element::path::Data;
+use svg::node::element::{Group, Line, Path, Text};
+
+use crate::time::format_offset;
+use crate::{TimeValue, Timeline};
+
+/// How the samples of a counter are plotted, see `Timeline::set_counter_style`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CounterStyle {
+    /// Draw a line through the values
+    #[default]
+    Line,
+    /// Fill the area below the values
+    Area,
+}
+
+type Sample<T> = (T, f64);
+
+impl<T: TimeValue> Timeline<T> {
+    /// Record that `counter` has `value` from `time` on
+    ///
+    /// Counters are numeric series, like the RSS of a process or the depth of a queue, plotted
+    /// next to the events. Every counter gets a row of its own, named and sorted like the rows of
+    /// events, where its value is drawn as a step from each sample to the next, and from the last
+    /// sample to the end of the timeline. The row is scaled from the smallest to the largest
+    /// value, or to 0 if that is further out, which are written at the right end of the row.
+    /// Samples can be added in any order, and extend the time range like events do.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("malloc".to_string(), 0, 5, "thread 1".to_string());
+    /// timeline.add_counter_sample("memory".to_string(), 0, 1024.0);
+    /// timeline.add_counter_sample("memory".to_string(), 5, 4096.0);
+    /// assert_eq!(timeline.categories(), ["memory", "thread 1"]);
+    /// # }
+    /// ```
+    pub fn add_counter_sample(&mut self, counter: impl Into<String>, time: T, value: f64) {
+        let counter = counter.into();
+        if time < self.start_time {
+            self.start_time = time;
+        }
+        if time > self.end_time {
+            self.end_time = time;
+        }
+        self.assign_color(&counter);
+        self.counters
+            .entry(counter)
+            .or_default()
+            .push((time, value));
+        self.invalidate_layout();
+    }
+
+    /// Plot `counter` in `style`
+    ///
+    /// The default is `CounterStyle::Line`. The style can be set before the first sample.
+    pub fn set_counter_style(&mut self, counter: impl Into<String>, style: CounterStyle) {
+        self.counter_styles.insert(counter.into(), style);
+    }
+
+    /// Draw gridlines across counter rows
+    ///
+    /// With `gridlines` set, dotted lines mark the smallest value, the largest value and the
+    /// middle between them on every counter row, so values can be read off the plot. The default
+    /// is false, which only writes the smallest and largest values.
+    pub fn set_counter_gridlines(&mut self, gridlines: bool) {
+        self.counter_gridlines = gridlines;
+    }
+
+    // The samples of a counter sorted by time, and the range its row is scaled to
+    fn counter_samples(&self, counter: &str) -> Option<(Vec<Sample<T>>, f64, f64)> {
+        let mut samples = self.counters.get(counter)?.clone();
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        let (low, high) = samples
+            .iter()
+            .fold((0.0f64, 0.0f64), |(low, high), &(_, value)| {
+                (low.min(value), high.max(value))
+            });
+        // A flat counter is drawn along the bottom of its row
+        let high = if high > low { high } else { low + 1.0 };
+        Some((samples, low, high))
+    }
+
+    // The plot and value axis of `counter` on the row starting at `y` and `row_height` high, if
+    // it has samples
+    pub(crate) fn counter_track(&self, counter: &str, y: f64, row_height: f64) -> Option<Group> {
+        let (samples, low, high) = self.counter_samples(counter)?;
+        let width = self.span() * self.scale();
+        let bottom = y + row_height;
+        let value_y = |value: f64| bottom - (value - low) / (high - low) * row_height;
+        let style = self
+            .counter_styles
+            .get(counter)
+            .copied()
+            .unwrap_or_default();
+        let color = self.colormap.get(counter).map_or("gray", String::as_str);
+
+        let (first_time, first_value) = samples[0];
+        let mut data = match style {
+            CounterStyle::Line => {
+                Data::new().move_to((self.time_x(first_time), value_y(first_value)))
+            }
+            CounterStyle::Area => Data::new()
+                .move_to((self.time_x(first_time), bottom))
+                .vertical_line_to(value_y(first_value)),
+        };
+        for &(time, value) in &samples[1..] {
+            data = data
+                .horizontal_line_to(self.time_x(time))
+                .vertical_line_to(value_y(value));
+        }
+        data = data.horizontal_line_to(width);
+        let plot = match style {
+            CounterStyle::Line => Path::new().set("fill", "none"),
+            CounterStyle::Area => {
+                data = data.vertical_line_to(bottom).close();
+                Path::new().set("fill", color).set("fill-opacity", 0.5)
+            }
+        };
+        let plot = plot
+            .set("d", data)
+            .set("stroke", color)
+            .set("stroke-width", 1);
+
+        let mut g = Group::new()
+            .set("class", "counter")
+            .set("data-counter", counter);
+        if self.counter_gridlines {
+            for value in [low, (low + high) / 2.0, high] {
+                let grid = Line::new()
+                    .set("x1", 0)
+                    .set("y1", value_y(value))
+                    .set("x2", width)
+                    .set("y2", value_y(value))
+                    .set("stroke", "lightgray")
+                    .set("stroke-width", 1)
+                    .set("stroke-dasharray", "2 2")
+                    .set("class", "counter-grid");
+                g = g.add(grid);
+            }
+        }
+        g = g.add(plot);
+        // Labels precise to a tenth of the range
+        let step = (high - low) / 10.0;
+        for (value, label_y) in [(high, y + 8.0), (low, bottom - 1.0)] {
+            let label = Text::new(format_offset(value, step))
+                .set("x", width - 2.0)
+                .set("y", label_y)
+                .set("font-size", 8)
+                .set("text-anchor", "end")
+                .set("fill", "gray")
+                .set("class", "counter-axis");
+            g = g.add(label);
+        }
+        Some(g)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event".to_string(), 0, 10, "Location".to_string());
+        timeline.add_counter_sample("queue".to_string(), 5, 2.0);
+        timeline.add_counter_sample("queue".to_string(), 0, 4.0);
+        assert_eq!(timeline.categories(), ["Location", "queue"]);
+        let render = |timeline: &Timeline| {
+            let mut output = Vec::new();
+            timeline.write(&mut output).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        // The row of the counter spans 0 to 4 from y 41 to 61
+        let svg = render(&timeline);
+        assert!(svg.contains(r#"d="M0,41 H1000 V51 H2000" fill="none""#));
+        assert!(svg.contains("class=\"counter-axis\" fill=\"gray\" font-size=\"8\" text-anchor=\"end\" x=\"1998\" y=\"49\">\n4.0"));
+        assert!(!svg.contains("counter-grid"));
+
+        timeline.set_counter_style("queue".to_string(), CounterStyle::Area);
+        timeline.set_counter_gridlines(true);
+        let svg = render(&timeline);
+        assert!(svg.contains(r#"d="M0,61 V41 H1000 V51 H2000 V61 z""#));
+        assert_eq!(svg.matches("class=\"counter-grid\"").count(), 3);
+
+        timeline.set_category_filter(Some(vec!["Location".to_string()]));
+        assert!(!render(&timeline).contains("data-counter"));
+    }
+}
@@ -0,0 +1,342 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::import::{new_timeline, sniff_text};
+use crate::{EventBuilder, Timeline, TimelineError, TraceImporter};
+
+// The raw samples Criterion saves of a benchmark, see `Timeline::from_criterion_samples`
+pub(crate) struct CriterionImporter;
+
+impl TraceImporter for CriterionImporter {
+    fn format(&self) -> &str {
+        "criterion"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        let text = sniff_text(data);
+        text.trim_start().starts_with('{')
+            && text.contains("\"sampling_mode\"")
+            && text.contains("\"iters\"")
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_criterion_samples(data, "benchmark")
+    }
+}
+
+// The value at `fraction` of sorted values, between the two closest ones like Criterion does
+fn percentile(sorted: &[f64], fraction: f64) -> f64 {
+    let rank = fraction * (sorted.len() - 1) as f64;
+    let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[below] + (sorted[above] - sorted[below]) * (rank - below as f64)
+}
+
+// The name of the event of every sample by the time it took per iteration, "sample" or the kind
+// of outlier it is by the Tukey fences Criterion classifies them with
+fn classify(per_iteration: &[f64]) -> Vec<&'static str> {
+    let mut sorted = per_iteration.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let (q1, q3) = (percentile(&sorted, 0.25), percentile(&sorted, 0.75));
+    let iqr = q3 - q1;
+    per_iteration
+        .iter()
+        .map(|&time| {
+            if time < q1 - 3.0 * iqr || time > q3 + 3.0 * iqr {
+                "severe outlier"
+            } else if time < q1 - 1.5 * iqr || time > q3 + 1.5 * iqr {
+                "mild outlier"
+            } else {
+                "sample"
+            }
+        })
+        .collect()
+}
+
+// Draw the samples of `sample.json` on the row `benchmark`, one after the other from 0
+fn add_samples(
+    timeline: &mut Timeline,
+    json: &Value,
+    benchmark: &str,
+) -> Result<(), TimelineError> {
+    let numbers = |key: &str| -> Result<Vec<f64>, TimelineError> {
+        json[key]
+            .as_array()
+            .and_then(|list| list.iter().map(Value::as_f64).collect())
+            .ok_or_else(|| TimelineError::Parse(format!("no {} array of numbers", key)))
+    };
+    let (iters, times) = (numbers("iters")?, numbers("times")?);
+    if iters.is_empty() || iters.len() != times.len() {
+        return Err(TimelineError::Parse(format!(
+            "{} iteration counts for {} times",
+            iters.len(),
+            times.len()
+        )));
+    }
+    let per_iteration: Vec<f64> = iters
+        .iter()
+        .zip(&times)
+        .map(|(iters, time)| time / iters.max(1.0))
+        .collect();
+    let mut start: i64 = 0;
+    for (index, name) in classify(&per_iteration).into_iter().enumerate() {
+        let end = start
+            .checked_add(times[index].round() as i64)
+            .filter(|end| *end >= start)
+            .ok_or_else(|| {
+                TimelineError::Parse(format!("sample {} takes {} ns", index + 1, times[index]))
+            })?;
+        let event = EventBuilder::new(name.to_string(), start, end, benchmark.to_string())
+            .metadata("sample".to_string(), (index + 1).to_string())
+            .metadata("iterations".to_string(), iters[index].to_string())
+            .metadata(
+                "time per iteration".to_string(),
+                format!("{:.1} ns", per_iteration[index]),
+            );
+        timeline.insert_event(event.build());
+        start = end;
+    }
+    Ok(())
+}
+
+fn read_json(path: &Path) -> Result<Value, TimelineError> {
+    serde_json::from_slice(&std::fs::read(path)?)
+        .map_err(|e| TimelineError::Parse(format!("{}: {}", path.display(), e)))
+}
+
+// The directories of benchmarks under `path`, with the samples of their last run in `new`
+fn find_benchmarks(path: &Path, benchmarks: &mut Vec<PathBuf>) -> Result<(), TimelineError> {
+    if path.join("new").join("sample.json").is_file() {
+        benchmarks.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = std::fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() && entry.file_name().is_some_and(|name| name != "report") {
+            find_benchmarks(&entry, benchmarks)?;
+        }
+    }
+    Ok(())
+}
+
+impl Timeline {
+    /// Read the raw samples Criterion saved of a benchmark into a timeline
+    ///
+    /// This reads the `sample.json` of a benchmark, like
+    /// `target/criterion/parse/new/sample.json`, and draws every sample, a batch of
+    /// iterations Criterion timed together, as an event on the row `benchmark`, one after the
+    /// other from 0, as long as the sample took. Samples are named after what Criterion makes
+    /// of the time they took per iteration, "sample", or "mild outlier" and "severe outlier"
+    /// past its Tukey fences, so outliers stand out in their own colors. Criterion doesn't save
+    /// the iterations it warms up with, but samples that were still warming up stand out the
+    /// same way, at the start of the row. Their number, their "iterations" and their "time per
+    /// iteration" are their metadata. Times are in nanoseconds.
+    ///
+    /// This returns `TimelineError::Parse` if the samples aren't JSON of this shape or their
+    /// times are negative or too long to add up, and `TimelineError::Io` if reading them fails.
+    /// `Timeline::import` detects samples of this format, as "criterion", and draws them on the
+    /// row "benchmark".
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let samples = r#"{"sampling_mode": "Linear", "iters": [1, 2, 3], "times": [10, 20, 30]}"#;
+    /// let timeline = Timeline::from_criterion_samples(samples.as_bytes(), "parse").unwrap();
+    /// assert_eq!(timeline.categories(), ["parse"]);
+    /// assert_eq!(timeline.time_range(), Some((0, 60)));
+    /// # }
+    /// ```
+    pub fn from_criterion_samples(
+        reader: impl Read,
+        benchmark: &str,
+    ) -> Result<Timeline, TimelineError> {
+        let json: Value =
+            serde_json::from_reader(reader).map_err(|e| TimelineError::Parse(e.to_string()))?;
+        let mut timeline = new_timeline();
+        add_samples(&mut timeline, &json, benchmark)?;
+        Ok(timeline)
+    }
+
+    /// Read the samples of every benchmark Criterion ran into a timeline
+    ///
+    /// `path` is the output directory of Criterion, like `target/criterion`, or the directory
+    /// of a group or a benchmark in it. Every benchmark under it with samples of its last run
+    /// gets a row, named after its id from its `benchmark.json`, like "parse/large", with its
+    /// samples drawn like `from_criterion_samples` draws them, all starting at 0 so the rows
+    /// line up.
+    ///
+    /// This returns `TimelineError::Parse` if there is no benchmark under `path` or its files
+    /// aren't JSON of their shape, and `TimelineError::Io` if reading them fails.
+    /// `Timeline::import` doesn't detect these directories, as they aren't files.
+    pub fn from_criterion(path: impl AsRef<Path>) -> Result<Timeline, TimelineError> {
+        let path = path.as_ref();
+        let mut benchmarks = Vec::new();
+        find_benchmarks(path, &mut benchmarks)?;
+        if benchmarks.is_empty() {
+            return Err(TimelineError::Parse(format!(
+                "no Criterion samples under {}",
+                path.display()
+            )));
+        }
+        let mut timeline = new_timeline();
+        for benchmark in benchmarks {
+            let new = benchmark.join("new");
+            let info = new.join("benchmark.json");
+            let id = if info.is_file() {
+                read_json(&info)?["full_id"].as_str().map(str::to_string)
+            } else {
+                None
+            };
+            // Without its id, a benchmark is named after its directory under `path`
+            let id = id.unwrap_or_else(|| {
+                let relative = benchmark.strip_prefix(path).unwrap_or(&benchmark);
+                match relative.to_string_lossy() {
+                    name if name.is_empty() => "benchmark".to_string(),
+                    name => name.into_owned(),
+                }
+            });
+            add_samples(&mut timeline, &read_json(&new.join("sample.json"))?, &id)?;
+        }
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: &str = r#"{
+        "sampling_mode": "Linear",
+        "iters": [1, 2, 3, 4, 5, 6, 7, 8, 9],
+        "times": [90, 20, 33, 40, 55, 60, 77, 80, 117]
+    }"#;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(percentile(&[1.0, 2.0, 3.0, 4.0], 0.25), 1.75);
+        assert_eq!(percentile(&[1.0, 2.0, 3.0, 4.0], 0.75), 3.25);
+        assert_eq!(percentile(&[5.0], 0.25), 5.0);
+        // Fences 1.5 and 3 interquartile ranges past the quartiles, which are 10 and 14 here
+        assert_eq!(
+            classify(&[12.0, 1.0, 10.0, 21.0, 10.0, 13.0, 30.0, 11.0, 14.0]),
+            [
+                "sample",
+                "mild outlier",
+                "sample",
+                "mild outlier",
+                "sample",
+                "sample",
+                "severe outlier",
+                "sample",
+                "sample",
+            ]
+        );
+        // Samples that all took as long are all samples
+        assert_eq!(classify(&[3.0, 3.0, 3.0]), ["sample"; 3]);
+    }
+
+    #[test]
+    fn test_from_criterion_samples() {
+        assert!(CriterionImporter.sniff(SAMPLES.as_bytes()));
+        // The other files Criterion saves of a benchmark
+        assert!(!CriterionImporter.sniff(br#"{"group_id": "parse", "full_id": "parse/large"}"#));
+        let timeline = Timeline::from_criterion_samples(SAMPLES.as_bytes(), "parse").unwrap();
+        let events: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| (event.name(), event.start_time(), event.end_time()))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("severe outlier", 0, 90),
+                ("sample", 90, 110),
+                ("sample", 110, 143),
+                ("sample", 143, 183),
+                ("sample", 183, 238),
+                ("sample", 238, 298),
+                ("sample", 298, 375),
+                ("sample", 375, 455),
+                ("mild outlier", 455, 572),
+            ]
+        );
+        let (_, last) = timeline.events().last().unwrap();
+        assert_eq!(last.metadata()["sample"], "9");
+        assert_eq!(last.metadata()["iterations"], "9");
+        assert_eq!(last.metadata()["time per iteration"], "13.0 ns");
+
+        // Flat sampling saves iterations as floats, and times are rounded where the events
+        // meet, so the row doesn't drift
+        let samples = r#"{"sampling_mode": "Flat", "iters": [0.0, 2.0], "times": [10.4, 20.4]}"#;
+        let timeline = Timeline::from_criterion_samples(samples.as_bytes(), "parse").unwrap();
+        let events: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| (event.start_time(), event.end_time()))
+            .collect();
+        assert_eq!(events, [(0, 10), (10, 30)]);
+        let (_, first) = timeline.events().next().unwrap();
+        assert_eq!(first.metadata()["time per iteration"], "10.4 ns");
+
+        // Samples need as many times as iterations, and times that add up
+        for samples in [
+            r#"{"iters": [1], "times": []}"#,
+            r#"{"iters": [], "times": []}"#,
+            r#"{"iters": [1], "times": ["10"]}"#,
+            r#"{"times": [10]}"#,
+            "{",
+            r#"{"iters": [1], "times": [-10]}"#,
+            r#"{"iters": [1, 1], "times": [9e18, 9e18]}"#,
+        ] {
+            assert!(matches!(
+                Timeline::from_criterion_samples(samples.as_bytes(), "parse"),
+                Err(TimelineError::Parse(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_from_criterion() {
+        let dir =
+            std::env::temp_dir().join(format!("timeline-svg-criterion-{}", std::process::id()));
+        let benchmark = |path: &[&str], id: Option<&str>| {
+            let new = path
+                .iter()
+                .fold(dir.clone(), |dir, part| dir.join(part))
+                .join("new");
+            std::fs::create_dir_all(&new).unwrap();
+            std::fs::write(new.join("sample.json"), SAMPLES).unwrap();
+            if let Some(id) = id {
+                let info = format!(r#"{{"full_id": "{}"}}"#, id);
+                std::fs::write(new.join("benchmark.json"), info).unwrap();
+            }
+        };
+        benchmark(&["parse", "large"], Some("parse/large"));
+        benchmark(&["parse", "small"], None);
+        // The reports of Criterion are skipped, even with samples of their own
+        benchmark(&["report", "old"], Some("report/old"));
+        let timeline = Timeline::from_criterion(&dir).unwrap();
+        assert_eq!(timeline.categories(), ["parse/large", "parse/small"]);
+        // All rows start at 0
+        assert_eq!(timeline.time_range(), Some((0, 572)));
+
+        // The directory of a benchmark itself
+        let timeline = Timeline::from_criterion(dir.join("parse").join("small")).unwrap();
+        assert_eq!(timeline.categories(), ["benchmark"]);
+
+        std::fs::create_dir_all(dir.join("empty")).unwrap();
+        assert!(matches!(
+            Timeline::from_criterion(dir.join("empty")),
+            Err(TimelineError::Parse(_))
+        ));
+        assert!(matches!(
+            Timeline::from_criterion(dir.join("missing")),
+            Err(TimelineError::Io(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,298 @@
+use std::io::Read;
+
+use crate::import::new_timeline;
+use crate::{TimeUnit, Timeline, TimelineError};
+
+/// Which columns of a CSV file `Timeline::from_csv` reads events from
+///
+/// Columns are named by their header. The default reads the `name`, `location`, `start` and
+/// `end` columns, with times in nanoseconds, like `Timeline::export_csv` writes them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CsvMapping {
+    /// The column with the names of the events
+    pub name: String,
+    /// The column with the times the events start at
+    pub start: String,
+    /// The column with the times the events end at, if there is one
+    pub end: Option<String>,
+    /// The column with how long the events last, for files without end times
+    pub duration: Option<String>,
+    /// The column with the rows the events go on
+    pub location: String,
+    /// The unit of the times and durations, which can have fractions, like "1.5"
+    pub unit: TimeUnit,
+    /// The character between the fields of a line
+    pub delimiter: char,
+}
+
+impl Default for CsvMapping {
+    fn default() -> Self {
+        CsvMapping {
+            name: "name".to_string(),
+            start: "start".to_string(),
+            end: Some("end".to_string()),
+            duration: None,
+            location: "location".to_string(),
+            unit: TimeUnit::Nanoseconds,
+            delimiter: ',',
+        }
+    }
+}
+
+// The records of CSV text, with quoted fields that can have delimiters, doubled quotes and line
+// breaks in them, and the number of the line every record starts on
+fn records(text: &str, delimiter: char) -> Vec<(usize, Vec<String>)> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let (mut line, mut start_line) = (1, 1);
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    field.push(chars.next().unwrap_or('"'));
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push((start_line, std::mem::take(&mut record)));
+                line += 1;
+                start_line = line;
+            }
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            c if c == delimiter && !quoted => record.push(std::mem::take(&mut field)),
+            c => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((start_line, record));
+    }
+    // Blank lines
+    records.retain(|(_, record)| record.len() > 1 || !record[0].trim().is_empty());
+    records
+}
+
+// A number of `unit`s, like "12" or "-0.25", in nanoseconds
+fn parse_time(text: &str, unit: TimeUnit) -> Option<i64> {
+    let text = text.trim();
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if (whole.is_empty() && fraction.is_empty())
+        || !whole
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let nanos = unit.nanoseconds() as i128;
+    let whole: i128 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().ok()?
+    };
+    // Digits past the 18th are far below a nanosecond
+    let fraction = &fraction[..fraction.len().min(18)];
+    let fraction = match fraction {
+        "" => 0,
+        _ => fraction.parse::<i128>().ok()? * nanos / 10i128.pow(fraction.len() as u32),
+    };
+    let time = i64::try_from(whole.checked_mul(nanos)? + fraction).ok()?;
+    Some(if negative { -time } else { time })
+}
+
+impl Timeline {
+    /// Read the rows of a CSV file into a timeline
+    ///
+    /// This reads files with a header line, like spreadsheets and logs export them, taking the
+    /// name, location and times of every event from the columns `mapping` names. Events end at
+    /// their `end`, or after their `duration`, and lines without either, or with empty ones,
+    /// become instants. The other columns of a line that aren't empty are the metadata of its
+    /// event, keyed by their headers. Fields can be quoted, with doubled quotes in them. Blank
+    /// lines are skipped. Times are in nanoseconds.
+    ///
+    /// This returns `TimelineError::Parse` if the header doesn't have a column of `mapping`, or
+    /// a line has a time that isn't a number, `TimelineError::InvalidRange` if an event ends
+    /// before it starts, and `TimelineError::Io` if reading fails.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::{CsvMapping, TimeUnit, Timeline};
+    ///
+    /// let csv = "\
+    ///     task,worker,began,took\n\
+    ///     compile,builder 1,0.5,2\n\
+    ///     link,builder 1,2.5,0.25\n";
+    /// let mapping = CsvMapping {
+    ///     name: "task".to_string(),
+    ///     start: "began".to_string(),
+    ///     end: None,
+    ///     duration: Some("took".to_string()),
+    ///     location: "worker".to_string(),
+    ///     unit: TimeUnit::Seconds,
+    ///     ..CsvMapping::default()
+    /// };
+    /// let timeline = Timeline::from_csv(csv.as_bytes(), &mapping).unwrap();
+    /// assert_eq!(timeline.categories(), ["builder 1"]);
+    /// assert_eq!(timeline.time_range(), Some((500_000_000, 2_750_000_000)));
+    /// # }
+    /// ```
+    pub fn from_csv(
+        mut reader: impl Read,
+        mapping: &CsvMapping,
+    ) -> Result<Timeline, TimelineError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let mut records =
+            records(text.trim_start_matches('\u{feff}'), mapping.delimiter).into_iter();
+        let Some((_, header)) = records.next() else {
+            return Err(TimelineError::Parse("no header line".to_string()));
+        };
+        let header: Vec<&str> = header.iter().map(|column| column.trim()).collect();
+        let column = |name: &str| {
+            header
+                .iter()
+                .position(|column| *column == name)
+                .ok_or_else(|| TimelineError::Parse(format!("no column {:?}", name)))
+        };
+        let name = column(&mapping.name)?;
+        let start = column(&mapping.start)?;
+        let location = column(&mapping.location)?;
+        let end = mapping.end.as_deref().map(column).transpose()?;
+        let duration = mapping.duration.as_deref().map(column).transpose()?;
+
+        let mut timeline = new_timeline();
+        for (line, record) in records {
+            let field = |index: usize| record.get(index).map_or("", |field| field.trim());
+            let time = |index: usize| match field(index) {
+                "" => Ok(None),
+                text => parse_time(text, mapping.unit).map(Some).ok_or_else(|| {
+                    TimelineError::Parse(format!("line {}: {:?} isn't a time", line, text))
+                }),
+            };
+            let Some(start_time) = time(start)? else {
+                return Err(TimelineError::Parse(format!(
+                    "line {}: no start time",
+                    line
+                )));
+            };
+            let end_time = match (end.map(time).transpose()?.flatten(), duration) {
+                (Some(end_time), _) => Some(end_time),
+                (None, Some(duration)) => match time(duration)? {
+                    Some(duration) => Some(start_time.checked_add(duration).ok_or_else(|| {
+                        TimelineError::Parse(format!("line {}: the event lasts too long", line))
+                    })?),
+                    None => None,
+                },
+                (None, None) => None,
+            };
+            let id = match end_time {
+                Some(end_time) => {
+                    timeline.try_add_event(field(name), start_time, end_time, field(location))?
+                }
+                None => timeline.add_instant(field(name), start_time, field(location)),
+            };
+            let mapped = [Some(name), Some(start), Some(location), end, duration];
+            for (index, key) in header.iter().enumerate() {
+                if !mapped.contains(&Some(index)) && !field(index).is_empty() {
+                    timeline.set_event_metadata(id, key.to_string(), field(index).to_string());
+                }
+            }
+        }
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_csv() {
+        assert_eq!(
+            records("a,\"b, \"\"c\"\"\"\r\n\n\"d\ne\",f", ','),
+            [
+                (1, vec!["a".to_string(), "b, \"c\"".to_string()]),
+                (3, vec!["d\ne".to_string(), "f".to_string()]),
+            ]
+        );
+        assert_eq!(parse_time("1.5", TimeUnit::Milliseconds), Some(1_500_000));
+        assert_eq!(parse_time("-.25", TimeUnit::Seconds), Some(-250_000_000));
+        assert_eq!(parse_time("1e3", TimeUnit::Seconds), None);
+
+        let mut exported = Timeline::default();
+        exported.add_event("sleep, then run", 10, 20, "CPU 0");
+        exported.add_instant("wake", 25, "CPU 1");
+        let mut csv = Vec::new();
+        exported.export_csv(&mut csv).unwrap();
+        let timeline = Timeline::from_csv(&csv[..], &CsvMapping::default()).unwrap();
+        let events: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name(),
+                    event.location(),
+                    event.start_time(),
+                    event.end_time(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("sleep, then run", "CPU 0", 10, 20),
+                ("wake", "CPU 1", 25, 25),
+            ]
+        );
+        let (_, event) = timeline.events().next().unwrap();
+        assert_eq!(event.metadata()["type"], "event");
+        assert_eq!(event.metadata()["metadata"], "{}");
+
+        let mapping = CsvMapping {
+            delimiter: ';',
+            end: None,
+            ..CsvMapping::default()
+        };
+        let timeline =
+            Timeline::from_csv("name;location;start\nboot;main;3\n".as_bytes(), &mapping);
+        assert!(timeline.unwrap().events().all(|(_, event)| event.instant));
+        assert!(matches!(
+            Timeline::from_csv("name,start\nboot,3\n".as_bytes(), &CsvMapping::default()),
+            Err(TimelineError::Parse(_))
+        ));
+        assert!(matches!(
+            Timeline::from_csv(
+                "name,location,start,end\nboot,main,5,3\n".as_bytes(),
+                &CsvMapping::default()
+            ),
+            Err(TimelineError::InvalidRange(_))
+        ));
+        let mapping = CsvMapping {
+            end: None,
+            duration: Some("duration".to_string()),
+            ..CsvMapping::default()
+        };
+        assert!(matches!(
+            Timeline::from_csv(
+                "name,location,start,duration\nboot,main,9223372036854775807,1\n".as_bytes(),
+                &mapping
+            ),
+            Err(TimelineError::Parse(_))
+        ));
+    }
+}
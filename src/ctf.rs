@@ -0,0 +1,1464 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::ftrace::{task_row, SchedTimeline};
+use crate::{Timeline, TimelineError};
+
+// The magic number that starts every packet of packetized metadata
+const METADATA_MAGIC: u32 = 0x75d1_1d57;
+// The size of the header of a metadata packet, in bytes
+const METADATA_HEADER_SIZE: usize = 37;
+
+fn truncated() -> TimelineError {
+    TimelineError::Parse("CTF stream is truncated".to_string())
+}
+
+fn invalid(message: &str) -> TimelineError {
+    TimelineError::Parse(format!("invalid CTF metadata: {}", message))
+}
+
+// The name of a field from its declaration or a path to it like "event.header.id", without
+// the underscore CTF names can start with to not clash with keywords
+fn field_name(path: &str) -> String {
+    let name = path.rsplit('.').next().unwrap_or(path);
+    name.strip_prefix('_').unwrap_or(name).to_string()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(i64),
+    Str(String),
+    Punct(&'static str),
+}
+
+const PUNCTUATION: [&str; 16] = [
+    ":=", "...", "{", "}", "[", "]", "(", ")", "<", ">", ";", ",", "=", ":", ".", "-",
+];
+
+// Split the text of metadata in the Trace Stream Description Language into tokens
+fn tokenize(text: &str) -> Result<Vec<Token>, TimelineError> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < text.len() {
+        let rest = &text[pos..];
+        if rest.starts_with(|c: char| c.is_whitespace() || c == '\0') {
+            pos += rest.chars().next().map_or(1, char::len_utf8);
+        } else if rest.starts_with("/*") {
+            pos += rest.find("*/").map_or(rest.len(), |end| end + 2);
+        } else if rest.starts_with("//") {
+            pos += rest.find('\n').unwrap_or(rest.len());
+        } else if let Some(punct) = PUNCTUATION.iter().find(|punct| rest.starts_with(**punct)) {
+            tokens.push(Token::Punct(punct));
+            pos += punct.len();
+        } else if let Some(string) = rest.strip_prefix('"') {
+            let mut value = String::new();
+            let mut escaped = false;
+            let mut end = None;
+            for (index, c) in string.char_indices() {
+                match c {
+                    _ if escaped => {
+                        value.push(match c {
+                            'n' => '\n',
+                            't' => '\t',
+                            c => c,
+                        });
+                        escaped = false;
+                    }
+                    '\\' => escaped = true,
+                    '"' => {
+                        end = Some(index + 2);
+                        break;
+                    }
+                    c => value.push(c),
+                }
+            }
+            pos += end.ok_or_else(|| invalid("unterminated string"))?;
+            tokens.push(Token::Str(value));
+        } else {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            if len == 0 {
+                let c = rest.chars().next().unwrap_or_default();
+                return Err(invalid(&format!("unexpected {:?}", c)));
+            }
+            let word = &rest[..len];
+            pos += len;
+            if word.starts_with(|c: char| c.is_ascii_digit()) {
+                tokens.push(Token::Number(parse_number(word)?));
+            } else {
+                tokens.push(Token::Ident(word.to_string()));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// A C integer constant, in decimal, hexadecimal or octal, with any suffix
+fn parse_number(word: &str) -> Result<i64, TimelineError> {
+    let digits = word.trim_end_matches(['u', 'U', 'l', 'L']);
+    let value = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        u64::from_str_radix(hex, 16)
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        u64::from_str_radix(&digits[1..], 8)
+    } else {
+        digits.parse()
+    };
+    value
+        .map(|value| value as i64)
+        .map_err(|_| invalid(&format!("bad number {:?}", word)))
+}
+
+#[derive(Clone, Debug)]
+struct Integer {
+    size: usize,
+    // The alignment in bits
+    align: usize,
+    signed: bool,
+    // None for the byte order of the trace
+    big_endian: Option<bool>,
+    // Whether arrays and sequences of it are text
+    text: bool,
+    // Whether its values are times of the clock
+    clock: bool,
+}
+
+#[derive(Clone, Debug)]
+enum Type {
+    Integer(Integer),
+    Float {
+        size: usize,
+        align: usize,
+        big_endian: Option<bool>,
+    },
+    String,
+    // An integer and its labels, as the label and the range of values it's for
+    Enum(Integer, Vec<(String, i64, i64)>),
+    // The fields and the minimum alignment in bits
+    Struct(Vec<(String, Type)>, usize),
+    // The field with the enum that picks the option, and the options by label
+    Variant(String, Vec<(String, Type)>),
+    Array(Box<Type>, usize),
+    // A sequence, with the field with its length
+    Sequence(Box<Type>, String),
+}
+
+impl Type {
+    // The alignment of the type in bits
+    fn align(&self) -> usize {
+        match self {
+            Type::Integer(int) | Type::Enum(int, _) => int.align,
+            Type::Float { align, .. } => *align,
+            Type::String => 8,
+            Type::Struct(fields, align) => fields
+                .iter()
+                .map(|(_, field)| field.align())
+                .fold(*align, usize::max),
+            // Variants are aligned like the option they have
+            Type::Variant(..) => 1,
+            Type::Array(element, _) | Type::Sequence(element, _) => element.align(),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Literal {
+    Number(i64),
+    // Strings and names, with dotted names like "clock.monotonic.value" joined
+    Text(String),
+}
+
+impl Literal {
+    fn flag(&self) -> bool {
+        match self {
+            Literal::Number(value) => *value != 0,
+            Literal::Text(text) => text.eq_ignore_ascii_case("true"),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Entry {
+    Value(Literal),
+    Type(Type),
+}
+
+// The entries of a block like `trace { ... }`, with lookups by key
+struct Block(Vec<(String, Entry)>);
+
+impl Block {
+    fn number(&self, key: &str) -> Option<i64> {
+        self.0.iter().find_map(|(name, entry)| match entry {
+            Entry::Value(Literal::Number(value)) if name == key => Some(*value),
+            _ => None,
+        })
+    }
+
+    fn text(&self, key: &str) -> Option<&str> {
+        self.0.iter().find_map(|(name, entry)| match entry {
+            Entry::Value(Literal::Text(text)) if name == key => Some(text.as_str()),
+            _ => None,
+        })
+    }
+
+    fn flag(&self, key: &str) -> Option<bool> {
+        self.0.iter().find_map(|(name, entry)| match entry {
+            Entry::Value(literal) if name == key => Some(literal.flag()),
+            _ => None,
+        })
+    }
+
+    fn ty(&self, key: &str) -> Option<Type> {
+        self.0.iter().find_map(|(name, entry)| match entry {
+            Entry::Type(ty) if name == key => Some(ty.clone()),
+            _ => None,
+        })
+    }
+}
+
+// Byte orders, as whether they are big endian, or None for the byte order of the trace
+fn byte_order(order: Option<&str>) -> Option<bool> {
+    match order? {
+        "be" | "network" => Some(true),
+        "le" => Some(false),
+        _ => None,
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    // Types by their alias, and named structs, enums and variants by names like "struct name"
+    types: HashMap<String, Type>,
+}
+
+impl Parser {
+    fn peek_ident(&self) -> Option<&str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Ident(name)) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn at(&self, punct: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some(Token::Punct(p)) if *p == punct)
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        let at = self.at(punct);
+        if at {
+            self.pos += 1;
+        }
+        at
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), TimelineError> {
+        if self.eat(punct) {
+            Ok(())
+        } else {
+            Err(invalid(&format!(
+                "expected {:?}, found {:?}",
+                punct,
+                self.tokens.get(self.pos)
+            )))
+        }
+    }
+
+    fn next(&mut self) -> Result<Token, TimelineError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| invalid("unexpected end"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn ident(&mut self) -> Result<String, TimelineError> {
+        match self.next()? {
+            Token::Ident(name) => Ok(name),
+            token => Err(invalid(&format!("expected a name, found {:?}", token))),
+        }
+    }
+
+    fn number(&mut self) -> Result<i64, TimelineError> {
+        let negative = self.eat("-");
+        match self.next()? {
+            Token::Number(value) if negative => Ok(-value),
+            Token::Number(value) => Ok(value),
+            token => Err(invalid(&format!("expected a number, found {:?}", token))),
+        }
+    }
+
+    fn literal(&mut self) -> Result<Literal, TimelineError> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Number(_) | Token::Punct("-")) => self.number().map(Literal::Number),
+            Some(Token::Str(_)) => match self.next()? {
+                Token::Str(text) => Ok(Literal::Text(text)),
+                _ => unreachable!(),
+            },
+            _ => {
+                let mut text = self.ident()?;
+                while self.eat(".") {
+                    text.push('.');
+                    text.push_str(&self.ident()?);
+                }
+                Ok(Literal::Text(text))
+            }
+        }
+    }
+
+    // A block of "key = value;" and "key := type;" entries in braces
+    fn block(&mut self) -> Result<Block, TimelineError> {
+        self.expect("{")?;
+        let mut entries = Vec::new();
+        while !self.eat("}") {
+            let mut key = self.ident()?;
+            while self.eat(".") {
+                key.push('.');
+                key.push_str(&self.ident()?);
+            }
+            let entry = if self.eat(":=") {
+                Entry::Type(self.parse_type(false)?)
+            } else {
+                self.expect("=")?;
+                Entry::Value(self.literal()?)
+            };
+            self.expect(";")?;
+            entries.push((key, entry));
+        }
+        Ok(Block(entries))
+    }
+
+    fn named(&self, kind: &str, name: Option<String>) -> Result<Type, TimelineError> {
+        let name = format!("{} {}", kind, name.unwrap_or_default());
+        self.types
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| invalid(&format!("unknown type {:?}", name)))
+    }
+
+    // A type specifier, followed by the name of a field if `declarator` is set, which can't be
+    // told apart from the words of an alias until the end
+    fn parse_type(&mut self, declarator: bool) -> Result<Type, TimelineError> {
+        match self.peek_ident() {
+            Some("integer") => {
+                self.pos += 1;
+                let block = self.block()?;
+                Ok(Type::Integer(integer(&block)?))
+            }
+            Some("floating_point") => {
+                self.pos += 1;
+                let block = self.block()?;
+                let size =
+                    block.number("exp_dig").unwrap_or(0) + block.number("mant_dig").unwrap_or(0);
+                if size != 32 && size != 64 {
+                    return Err(invalid(&format!("{} bit floating point", size)));
+                }
+                Ok(Type::Float {
+                    size: size as usize,
+                    align: block.number("align").unwrap_or(8) as usize,
+                    big_endian: byte_order(block.text("byte_order")),
+                })
+            }
+            Some("string") => {
+                self.pos += 1;
+                if self.at("{") {
+                    self.block()?;
+                }
+                Ok(Type::String)
+            }
+            Some("struct") => self.parse_struct(),
+            Some("enum") => self.parse_enum(),
+            Some("variant") => self.parse_variant(),
+            _ => {
+                // Aliases can be several words, like "unsigned long"
+                let mut words = Vec::new();
+                while let Some(word) = self.peek_ident() {
+                    words.push(word.to_string());
+                    self.pos += 1;
+                }
+                if declarator && words.len() > 1 {
+                    words.pop();
+                    self.pos -= 1;
+                }
+                let name = words.join(" ");
+                self.types
+                    .get(&name)
+                    .cloned()
+                    .ok_or_else(|| invalid(&format!("unknown type {:?}", name)))
+            }
+        }
+    }
+
+    // The name of a named struct, enum or variant, which isn't the name of a field when types
+    // are followed by one
+    fn type_name(&mut self) -> Option<String> {
+        let name = self.peek_ident()?.to_string();
+        self.pos += 1;
+        Some(name)
+    }
+
+    fn parse_struct(&mut self) -> Result<Type, TimelineError> {
+        self.pos += 1;
+        let name = self.type_name();
+        if !self.at("{") {
+            return self.named("struct", name);
+        }
+        let fields = self.fields()?;
+        let mut align = 1;
+        if self.peek_ident() == Some("align") {
+            self.pos += 1;
+            self.expect("(")?;
+            align = self.number()? as usize;
+            self.expect(")")?;
+        }
+        let ty = Type::Struct(fields, align.max(1));
+        if let Some(name) = name {
+            self.types.insert(format!("struct {}", name), ty.clone());
+        }
+        Ok(ty)
+    }
+
+    fn parse_enum(&mut self) -> Result<Type, TimelineError> {
+        self.pos += 1;
+        let name = if self.at(":") || self.at("{") {
+            None
+        } else {
+            self.type_name()
+        };
+        if !self.at(":") && !self.at("{") {
+            return self.named("enum", name);
+        }
+        // Enums without a type are ints
+        let container = if self.eat(":") {
+            self.parse_type(false)?
+        } else {
+            self.types
+                .get("int")
+                .cloned()
+                .ok_or_else(|| invalid("no int type"))?
+        };
+        let Type::Integer(int) = container else {
+            return Err(invalid("enum of a type that isn't an integer"));
+        };
+        self.expect("{")?;
+        let mut labels = Vec::new();
+        let mut next = 0;
+        while !self.eat("}") {
+            let label = match self.next()? {
+                Token::Ident(label) | Token::Str(label) => label,
+                token => return Err(invalid(&format!("expected a label, found {:?}", token))),
+            };
+            let (start, end) = if self.eat("=") {
+                let start = self.number()?;
+                let end = if self.eat("...") {
+                    self.number()?
+                } else {
+                    start
+                };
+                (start, end)
+            } else {
+                (next, next)
+            };
+            next = end + 1;
+            labels.push((label, start, end));
+            if !self.eat(",") {
+                self.expect("}")?;
+                break;
+            }
+        }
+        let ty = Type::Enum(int, labels);
+        if let Some(name) = name {
+            self.types.insert(format!("enum {}", name), ty.clone());
+        }
+        Ok(ty)
+    }
+
+    fn parse_variant(&mut self) -> Result<Type, TimelineError> {
+        self.pos += 1;
+        let name = if self.at("<") { None } else { self.type_name() };
+        let mut tag = String::new();
+        if self.eat("<") {
+            let Literal::Text(path) = self.literal()? else {
+                return Err(invalid("variant tag isn't a field"));
+            };
+            tag = field_name(&path);
+            self.expect(">")?;
+        }
+        if !self.at("{") {
+            let Type::Variant(named_tag, options) = self.named("variant", name)? else {
+                return Err(invalid("variant isn't a variant"));
+            };
+            // Variants declared without a tag get theirs where they're used
+            let tag = if tag.is_empty() { named_tag } else { tag };
+            return Ok(Type::Variant(tag, options));
+        }
+        let ty = Type::Variant(tag, self.fields()?);
+        if let Some(name) = name {
+            self.types.insert(format!("variant {}", name), ty.clone());
+        }
+        Ok(ty)
+    }
+
+    // The fields of a struct or the options of a variant, in braces
+    fn fields(&mut self) -> Result<Vec<(String, Type)>, TimelineError> {
+        self.expect("{")?;
+        let mut fields = Vec::new();
+        while !self.eat("}") {
+            if self.peek_ident() == Some("typealias") {
+                self.typealias()?;
+                continue;
+            }
+            let ty = self.parse_type(true)?;
+            loop {
+                fields.push(self.declarator(ty.clone())?);
+                if !self.eat(",") {
+                    break;
+                }
+            }
+            self.expect(";")?;
+        }
+        Ok(fields)
+    }
+
+    // The name of a field of type `ty`, and the lengths of the arrays or sequences it is
+    fn declarator(&mut self, ty: Type) -> Result<(String, Type), TimelineError> {
+        let name = self.ident()?;
+        let mut lengths = Vec::new();
+        while self.eat("[") {
+            lengths.push(self.literal()?);
+            self.expect("]")?;
+        }
+        let mut ty = ty;
+        for length in lengths.into_iter().rev() {
+            ty = match length {
+                Literal::Number(len) => Type::Array(Box::new(ty), len as usize),
+                Literal::Text(path) => Type::Sequence(Box::new(ty), field_name(&path)),
+            };
+        }
+        Ok((field_name(&name), ty))
+    }
+
+    fn typealias(&mut self) -> Result<(), TimelineError> {
+        self.pos += 1;
+        let ty = self.parse_type(false)?;
+        self.expect(":=")?;
+        let mut words = Vec::new();
+        while let Some(word) = self.peek_ident() {
+            words.push(word.to_string());
+            self.pos += 1;
+        }
+        self.expect(";")?;
+        self.types.insert(words.join(" "), ty);
+        Ok(())
+    }
+
+    fn typedef(&mut self) -> Result<(), TimelineError> {
+        self.pos += 1;
+        let ty = self.parse_type(true)?;
+        let name = self.ident()?;
+        self.expect(";")?;
+        self.types.insert(name, ty);
+        Ok(())
+    }
+}
+
+fn integer(block: &Block) -> Result<Integer, TimelineError> {
+    let size = block.number("size").unwrap_or(0) as usize;
+    if !(1..=64).contains(&size) {
+        return Err(invalid(&format!("{} bit integer", size)));
+    }
+    let default_align = if size.is_multiple_of(8) { 8 } else { 1 };
+    Ok(Integer {
+        size,
+        align: block
+            .number("align")
+            .map_or(default_align, |align| align.max(1) as usize),
+        signed: block.flag("signed").unwrap_or(false),
+        big_endian: byte_order(block.text("byte_order")),
+        text: block
+            .text("encoding")
+            .is_some_and(|encoding| encoding != "none"),
+        clock: block
+            .text("map")
+            .is_some_and(|map| map.starts_with("clock.")),
+    })
+}
+
+struct Clock {
+    freq: i64,
+    offset_s: i64,
+    // The offset in cycles, on top of the seconds
+    offset: i64,
+}
+
+impl Clock {
+    fn nanos(&self, cycles: u64) -> i64 {
+        let cycles = cycles as i128 + self.offset as i128;
+        (self.offset_s as i128 * 1_000_000_000 + cycles * 1_000_000_000 / self.freq.max(1) as i128)
+            as i64
+    }
+}
+
+#[derive(Default)]
+struct Stream {
+    event_header: Option<Type>,
+    packet_context: Option<Type>,
+    event_context: Option<Type>,
+}
+
+struct EventClass {
+    name: String,
+    context: Option<Type>,
+    fields: Option<Type>,
+}
+
+// What the metadata of a trace says about its streams
+struct Metadata {
+    big_endian: bool,
+    // The tracer domain, like "kernel" or "ust" for LTTng
+    domain: String,
+    // The first clock, which LTTng traces only have one of
+    clock: Clock,
+    packet_header: Option<Type>,
+    streams: HashMap<i64, Stream>,
+    // The event classes by stream id and event id
+    events: HashMap<(i64, i64), EventClass>,
+}
+
+fn parse_metadata(text: &str) -> Result<Metadata, TimelineError> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        pos: 0,
+        types: HashMap::new(),
+    };
+    let mut metadata = Metadata {
+        big_endian: false,
+        domain: String::new(),
+        clock: Clock {
+            freq: 1_000_000_000,
+            offset_s: 0,
+            offset: 0,
+        },
+        packet_header: None,
+        streams: HashMap::new(),
+        events: HashMap::new(),
+    };
+    let mut clocks = 0;
+    while parser.pos < parser.tokens.len() {
+        let block = match parser.peek_ident() {
+            Some("typealias") => {
+                parser.typealias()?;
+                continue;
+            }
+            Some("typedef") => {
+                parser.typedef()?;
+                continue;
+            }
+            Some(kind @ ("trace" | "env" | "clock" | "stream" | "event" | "callsite"))
+                if parser.tokens.get(parser.pos + 1) == Some(&Token::Punct("{")) =>
+            {
+                kind.to_string()
+            }
+            _ => {
+                // Declarations of named structs, enums and variants
+                parser.parse_type(false)?;
+                parser.expect(";")?;
+                continue;
+            }
+        };
+        parser.pos += 1;
+        let entries = parser.block()?;
+        parser.expect(";")?;
+        match block.as_str() {
+            "trace" => {
+                metadata.big_endian = byte_order(entries.text("byte_order")) == Some(true);
+                metadata.packet_header = entries.ty("packet.header");
+            }
+            "env" => metadata.domain = entries.text("domain").unwrap_or_default().to_string(),
+            "clock" => {
+                if clocks == 0 {
+                    metadata.clock = Clock {
+                        freq: entries.number("freq").unwrap_or(1_000_000_000),
+                        offset_s: entries.number("offset_s").unwrap_or(0),
+                        offset: entries.number("offset").unwrap_or(0),
+                    };
+                }
+                clocks += 1;
+            }
+            "stream" => {
+                let stream = Stream {
+                    event_header: entries.ty("event.header"),
+                    packet_context: entries.ty("packet.context"),
+                    event_context: entries.ty("event.context"),
+                };
+                metadata
+                    .streams
+                    .insert(entries.number("id").unwrap_or(0), stream);
+            }
+            "event" => {
+                let name = entries
+                    .text("name")
+                    .ok_or_else(|| invalid("event without a name"))?;
+                let event = EventClass {
+                    name: name.to_string(),
+                    context: entries.ty("context"),
+                    fields: entries.ty("fields"),
+                };
+                let id = (
+                    entries.number("stream_id").unwrap_or(0),
+                    entries.number("id").unwrap_or(0),
+                );
+                metadata.events.insert(id, event);
+            }
+            _ => {}
+        }
+    }
+    Ok(metadata)
+}
+
+// The text of a metadata file, which is either plain text or packets of text with headers
+fn metadata_text(data: &[u8]) -> Result<String, TimelineError> {
+    let magic: [u8; 4] = data
+        .get(..4)
+        .and_then(|magic| magic.try_into().ok())
+        .unwrap_or_default();
+    let big_endian = if u32::from_le_bytes(magic) == METADATA_MAGIC {
+        false
+    } else if u32::from_be_bytes(magic) == METADATA_MAGIC {
+        true
+    } else {
+        return Ok(String::from_utf8_lossy(data).into_owned());
+    };
+    let size = |bytes: &[u8]| {
+        let bytes = bytes.try_into().unwrap_or_default();
+        let size = if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        };
+        size as usize / 8
+    };
+    let mut text = String::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let header = data
+            .get(offset..offset + METADATA_HEADER_SIZE)
+            .ok_or_else(truncated)?;
+        let (content_size, packet_size) = (size(&header[24..28]), size(&header[28..32]));
+        let content = data
+            .get(offset + METADATA_HEADER_SIZE..offset + content_size)
+            .ok_or_else(truncated)?;
+        text.push_str(&String::from_utf8_lossy(content));
+        if packet_size == 0 {
+            break;
+        }
+        offset += packet_size;
+    }
+    Ok(text)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Int(i64),
+    // An enum, with the label of its value
+    Enum(i64, String),
+    Float(f64),
+    Text(String),
+    Struct(Vec<(String, Value)>),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(value) | Value::Enum(value, _) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn field(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Struct(fields) => fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+// The event id of an event header, which is the last one, as extended headers have a second
+// id for the events the first can't fit
+fn event_id(header: &Value) -> Option<i64> {
+    let Value::Struct(fields) = header else {
+        return None;
+    };
+    fields.iter().rev().find_map(|(name, value)| {
+        event_id(value).or_else(|| if name == "id" { value.as_int() } else { None })
+    })
+}
+
+// Reads the fields of a stream, which are packed in bits
+struct Decoder<'a> {
+    data: &'a [u8],
+    // The position in bits
+    pos: usize,
+    big_endian: bool,
+    // The clock of the stream, from the last field mapped to it
+    clock: u64,
+    // The fields of the structs being read, innermost last, for variants and sequences to find
+    // their tags and lengths in
+    scopes: Vec<Vec<(String, Value)>>,
+}
+
+impl Decoder<'_> {
+    fn align(&mut self, align: usize) {
+        self.pos = self.pos.div_ceil(align) * align;
+    }
+
+    fn bits(&mut self, size: usize, big_endian: bool) -> Result<u64, TimelineError> {
+        if self.pos + size > self.data.len() * 8 {
+            return Err(truncated());
+        }
+        let start = self.pos;
+        self.pos += size;
+        if start.is_multiple_of(8) && size.is_multiple_of(8) {
+            let bytes = &self.data[start / 8..self.pos / 8];
+            let fold = |value: u64, byte: &u8| value << 8 | *byte as u64;
+            return Ok(if big_endian {
+                bytes.iter().fold(0, fold)
+            } else {
+                bytes.iter().rev().fold(0, fold)
+            });
+        }
+        let mut value = 0;
+        for (i, pos) in (start..self.pos).enumerate() {
+            let byte = self.data[pos / 8];
+            if big_endian {
+                value = value << 1 | (byte >> (7 - pos % 8) & 1) as u64;
+            } else {
+                value |= (((byte >> (pos % 8)) & 1) as u64) << i;
+            }
+        }
+        Ok(value)
+    }
+
+    fn integer(&mut self, int: &Integer) -> Result<i64, TimelineError> {
+        self.align(int.align);
+        let bits = self.bits(int.size, int.big_endian.unwrap_or(self.big_endian))?;
+        if int.clock {
+            self.update_clock(bits, int.size);
+        }
+        Ok(if int.signed && int.size < 64 {
+            let shift = 64 - int.size;
+            ((bits << shift) as i64) >> shift
+        } else {
+            bits as i64
+        })
+    }
+
+    // Fields mapped to the clock with less than 64 bits only have its low bits, which wrap
+    // when they are lower than the ones before
+    fn update_clock(&mut self, value: u64, size: usize) {
+        if size >= 64 {
+            self.clock = value;
+            return;
+        }
+        let mask = (1 << size) - 1;
+        let mut clock = (self.clock & !mask) | value;
+        if value < self.clock & mask {
+            clock = clock.wrapping_add(1 << size);
+        }
+        self.clock = clock;
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Value> {
+        self.scopes.iter().rev().find_map(|scope| {
+            scope
+                .iter()
+                .rev()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value)
+        })
+    }
+
+    fn decode(&mut self, ty: &Type) -> Result<Value, TimelineError> {
+        match ty {
+            Type::Integer(int) => self.integer(int).map(Value::Int),
+            Type::Enum(int, labels) => {
+                let value = self.integer(int)?;
+                let label = labels
+                    .iter()
+                    .find(|(_, start, end)| (*start..=*end).contains(&value))
+                    .map_or_else(String::new, |(label, _, _)| label.clone());
+                Ok(Value::Enum(value, label))
+            }
+            Type::Float {
+                size,
+                align,
+                big_endian,
+            } => {
+                self.align(*align);
+                let bits = self.bits(*size, big_endian.unwrap_or(self.big_endian))?;
+                Ok(Value::Float(if *size == 32 {
+                    f32::from_bits(bits as u32) as f64
+                } else {
+                    f64::from_bits(bits)
+                }))
+            }
+            Type::String => {
+                self.align(8);
+                let start = (self.pos / 8).min(self.data.len());
+                let len = self.data[start..]
+                    .iter()
+                    .position(|b| *b == 0)
+                    .ok_or_else(truncated)?;
+                self.pos += (len + 1) * 8;
+                let text = String::from_utf8_lossy(&self.data[start..start + len]);
+                Ok(Value::Text(text.into_owned()))
+            }
+            Type::Struct(fields, _) => {
+                self.align(ty.align());
+                self.scopes.push(Vec::new());
+                for (name, field) in fields {
+                    let value = self.decode(field)?;
+                    if let Some(scope) = self.scopes.last_mut() {
+                        scope.push((name.clone(), value));
+                    }
+                }
+                Ok(Value::Struct(self.scopes.pop().unwrap_or_default()))
+            }
+            Type::Variant(tag, options) => {
+                let label = match self.lookup(tag) {
+                    Some(Value::Enum(_, label)) => field_name(label),
+                    _ => return Err(invalid(&format!("variant tag {:?} isn't an enum", tag))),
+                };
+                let (_, option) = options
+                    .iter()
+                    .find(|(name, _)| *name == label)
+                    .ok_or_else(|| invalid(&format!("variant has no option {:?}", label)))?;
+                self.decode(option)
+            }
+            Type::Array(element, len) => self.elements(element, *len),
+            Type::Sequence(element, length) => {
+                let len = self
+                    .lookup(length)
+                    .and_then(Value::as_int)
+                    .and_then(|len| usize::try_from(len).ok())
+                    .ok_or_else(|| invalid(&format!("sequence length {:?} missing", length)))?;
+                self.elements(element, len)
+            }
+        }
+    }
+
+    fn elements(&mut self, element: &Type, len: usize) -> Result<Value, TimelineError> {
+        // Every element takes at least a bit, which stops lengths out of garbage from
+        // allocating more than the stream could hold
+        if len > (self.data.len() * 8).saturating_sub(self.pos) {
+            return Err(truncated());
+        }
+        if let Type::Integer(
+            int @ Integer {
+                size: 8,
+                text: true,
+                ..
+            },
+        ) = element
+        {
+            let mut bytes = Vec::with_capacity(len);
+            for _ in 0..len {
+                bytes.push(self.integer(int)? as u8);
+            }
+            let end = bytes.iter().position(|b| *b == 0).unwrap_or(len);
+            return Ok(Value::Text(
+                String::from_utf8_lossy(&bytes[..end]).into_owned(),
+            ));
+        }
+        (0..len)
+            .map(|_| self.decode(element))
+            .collect::<Result<_, _>>()
+            .map(Value::Array)
+    }
+}
+
+// An event of a stream
+struct Record {
+    time: i64,
+    cpu: u32,
+    name: String,
+    // Whether it's from a kernel trace
+    kernel: bool,
+    // The fields of the stream and event contexts and of the event
+    fields: Vec<(String, Value)>,
+}
+
+impl Record {
+    // A field as text, like the numbers of tids
+    fn text(&self, name: &str) -> Option<String> {
+        match self.fields.iter().find(|(field, _)| field == name)?.1 {
+            Value::Text(ref text) => Some(text.clone()),
+            Value::Int(value) | Value::Enum(value, _) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+}
+
+// Read the packets of a stream file into records
+fn read_stream(
+    metadata: &Metadata,
+    data: &[u8],
+    records: &mut Vec<Record>,
+) -> Result<(), TimelineError> {
+    let total = data.len() * 8;
+    let mut decoder = Decoder {
+        data,
+        pos: 0,
+        big_endian: metadata.big_endian,
+        clock: 0,
+        scopes: Vec::new(),
+    };
+    let mut offset = 0;
+    while offset < total {
+        decoder.data = data;
+        decoder.pos = offset;
+        let header = match &metadata.packet_header {
+            Some(ty) => Some(decoder.decode(ty)?),
+            None => None,
+        };
+        let stream_id = header
+            .as_ref()
+            .and_then(|header| header.field("stream_id"))
+            .and_then(Value::as_int)
+            .unwrap_or(0);
+        let stream = metadata
+            .streams
+            .get(&stream_id)
+            .ok_or_else(|| invalid(&format!("no stream {}", stream_id)))?;
+        let context = match &stream.packet_context {
+            Some(ty) => Some(decoder.decode(ty)?),
+            None => None,
+        };
+        let size = |name| {
+            context
+                .as_ref()
+                .and_then(|context| context.field(name))
+                .and_then(Value::as_int)
+                .map(|size| size as usize)
+        };
+        // Packets without sizes are the rest of the stream
+        let content_end = size("content_size").map_or(total, |size| offset + size);
+        if content_end > total {
+            return Err(truncated());
+        }
+        let packet_end = size("packet_size").map_or(content_end, |size| offset + size);
+        let cpu = size("cpu_id").unwrap_or(0) as u32;
+        decoder.data = &data[..content_end.div_ceil(8)];
+
+        while decoder.pos < content_end {
+            let start = decoder.pos;
+            let header = match &stream.event_header {
+                Some(ty) => Some(decoder.decode(ty)?),
+                None => None,
+            };
+            let id = header.as_ref().and_then(event_id).unwrap_or(0);
+            let class = metadata
+                .events
+                .get(&(stream_id, id))
+                .ok_or_else(|| invalid(&format!("no event {} in stream {}", id, stream_id)))?;
+            let time = metadata.clock.nanos(decoder.clock);
+            let mut fields = Vec::new();
+            for ty in [&stream.event_context, &class.context, &class.fields]
+                .into_iter()
+                .flatten()
+            {
+                if let Value::Struct(values) = decoder.decode(ty)? {
+                    fields.extend(values);
+                }
+            }
+            records.push(Record {
+                time,
+                cpu,
+                name: class.name.clone(),
+                kernel: metadata.domain == "kernel",
+                fields,
+            });
+            if decoder.pos == start {
+                break;
+            }
+        }
+        if packet_end <= offset {
+            break;
+        }
+        offset = packet_end;
+    }
+    Ok(())
+}
+
+// The directories under `path` with metadata, which are traces of their own, like the kernel
+// and the userspace traces of an LTTng session
+fn find_traces(path: &Path, traces: &mut Vec<PathBuf>) -> Result<(), TimelineError> {
+    if path.join("metadata").is_file() {
+        traces.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = std::fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            find_traces(&entry, traces)?;
+        }
+    }
+    Ok(())
+}
+
+impl Timeline {
+    /// Read a Common Trace Format trace, like the traces LTTng records, into a timeline
+    ///
+    /// `path` is the directory of a trace, with its `metadata` file and its stream files, or a
+    /// directory with traces anywhere under it, like the output directory of an LTTng session
+    /// with its kernel and userspace traces, which are read together. Kernel traces are drawn
+    /// like `from_ftrace` draws the same events: a row for every CPU and for every task that
+    /// runs, from the `sched_switch` events, and triggers from the `sched_wakeup` and
+    /// `sched_wakeup_new` events, from the row of the task running where they happen. Other
+    /// kernel events are skipped. Userspace events become instants named after their event, or
+    /// the message of `tracef` and `tracelog` events, on the row of their thread if the `vtid`
+    /// and `procname` contexts were recorded, and of their CPU if not. Times are in nanoseconds
+    /// of the first clock of each trace, from the offset of the clock, which for LTTng is the
+    /// Unix epoch, so the traces of a session line up.
+    ///
+    /// This is only available with the `ctf` feature. This reads version 1.8 of the format,
+    /// with plain text or packetized metadata. This returns `TimelineError::Parse` if there is
+    /// no trace under `path`, or its metadata can't be read or its streams are truncated, and
+    /// `TimelineError::Io` if reading them fails. `Timeline::import` doesn't detect these
+    /// traces, as they are directories rather than files.
+    pub fn from_ctf(path: impl AsRef<Path>) -> Result<Timeline, TimelineError> {
+        let path = path.as_ref();
+        let mut traces = Vec::new();
+        find_traces(path, &mut traces)?;
+        if traces.is_empty() {
+            return Err(TimelineError::Parse(format!(
+                "no CTF metadata under {}",
+                path.display()
+            )));
+        }
+        let mut records = Vec::new();
+        for trace in traces {
+            let metadata =
+                parse_metadata(&metadata_text(&std::fs::read(trace.join("metadata"))?)?)?;
+            let mut streams = std::fs::read_dir(&trace)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()?;
+            streams.sort();
+            for stream in streams {
+                let name = stream.file_name().unwrap_or_default().to_string_lossy();
+                if stream.is_file() && name != "metadata" && !name.starts_with('.') {
+                    read_stream(&metadata, &std::fs::read(&stream)?, &mut records)?;
+                }
+            }
+        }
+        // Merge the streams of the CPUs and the traces, keeping the order within each
+        records.sort_by_key(|record| record.time);
+
+        let mut sched = SchedTimeline::default();
+        for record in records {
+            let (cpu, time) = (record.cpu, record.time);
+            match record.name.as_str() {
+                "sched_switch" if record.kernel => {
+                    if let (Some(comm), Some(tid)) =
+                        (record.text("next_comm"), record.text("next_tid"))
+                    {
+                        sched.switch(cpu, time, &comm, &tid);
+                    }
+                }
+                "sched_wakeup" | "sched_wakeup_new" if record.kernel => {
+                    if let (Some(comm), Some(tid)) = (record.text("comm"), record.text("tid")) {
+                        let from = sched.running_row(cpu);
+                        sched.wakeup(from, time, &comm, &tid);
+                    }
+                }
+                name if !record.kernel => {
+                    let row = match (record.text("procname"), record.text("vtid")) {
+                        (Some(comm), Some(tid)) => task_row(&comm, &tid, cpu),
+                        _ => format!("CPU {}", cpu),
+                    };
+                    let message = if name.starts_with("lttng_ust_tracef:")
+                        || name.starts_with("lttng_ust_tracelog:")
+                    {
+                        record.text("msg")
+                    } else {
+                        None
+                    };
+                    sched.instant(row, time, message.as_deref().unwrap_or(name));
+                }
+                _ => sched.event(time),
+            }
+        }
+        Ok(sched.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRELUDE: &str = r#"/* CTF 1.8 */
+typealias integer { size = 8; align = 8; signed = false; } := uint8_t;
+typealias integer { size = 32; align = 8; signed = false; } := uint32_t;
+typealias integer { size = 64; align = 8; signed = false; } := uint64_t;
+typealias integer { size = 32; align = 8; signed = true; } := int32_t;
+typealias integer { size = 5; align = 1; signed = false; } := uint5_t;
+typealias integer { size = 27; align = 1; signed = false; map = clock.monotonic.value; }
+    := uint27_clock_monotonic_t;
+typealias integer { size = 64; align = 8; signed = false; map = clock.monotonic.value; }
+    := uint64_clock_monotonic_t;
+
+trace {
+    major = 1;
+    minor = 8;
+    byte_order = le;
+    packet.header := struct {
+        uint32_t magic;
+        uint32_t stream_id;
+    };
+};
+
+clock {
+    name = "monotonic";
+    freq = 1000000000; // 1 GHz
+    offset_s = 1;
+    offset = 0;
+};
+
+struct packet_context {
+    uint64_clock_monotonic_t timestamp_begin;
+    uint64_t content_size;
+    uint64_t packet_size;
+    uint32_t cpu_id;
+} align(8);
+
+struct event_header_compact {
+    enum : uint5_t { compact = 0 ... 30, extended = 31 } id;
+    variant <id> {
+        struct { uint27_clock_monotonic_t timestamp; } compact;
+        struct { uint32_t id; uint64_clock_monotonic_t timestamp; } extended;
+    } v;
+} align(8);
+
+typealias integer { size = 8; align = 8; signed = 0; encoding = UTF8; base = 10; } := char;
+"#;
+
+    const KERNEL: &str = r#"
+env { domain = "kernel"; };
+
+stream {
+    id = 0;
+    event.header := struct event_header_compact;
+    packet.context := struct packet_context;
+};
+
+event {
+    name = "sched_switch";
+    id = 0;
+    stream_id = 0;
+    fields := struct {
+        char _prev_comm[16];
+        int32_t _prev_tid;
+        char _next_comm[16];
+        int32_t _next_tid;
+    };
+};
+
+event {
+    name = "sched_wakeup";
+    id = 1;
+    stream_id = 0;
+    fields := struct {
+        char _comm[16];
+        int32_t _tid;
+        int32_t _target_cpu;
+    };
+};
+"#;
+
+    const UST: &str = r#"
+env { domain = "ust"; };
+
+stream {
+    id = 0;
+    event.header := struct event_header_compact;
+    packet.context := struct packet_context;
+    event.context := struct {
+        int32_t _vtid;
+        string _procname;
+    };
+};
+
+event {
+    name = "app:request";
+    id = 0;
+    stream_id = 0;
+    fields := struct { uint32_t _size; };
+};
+
+event {
+    name = "lttng_ust_tracef:event";
+    id = 40;
+    stream_id = 0;
+    fields := struct {
+        uint32_t _msg_length;
+        char _msg[_msg_length];
+    };
+};
+"#;
+
+    fn comm(name: &str) -> [u8; 16] {
+        let mut comm = [0; 16];
+        comm[..name.len()].copy_from_slice(name.as_bytes());
+        comm
+    }
+
+    // An event with a compact header, which only has the low 27 bits of the time
+    fn compact(id: u32, time: u64, fields: &[&[u8]]) -> Vec<u8> {
+        let mut event = (id | (time as u32 & ((1 << 27) - 1)) << 5)
+            .to_le_bytes()
+            .to_vec();
+        event.extend(fields.concat());
+        event
+    }
+
+    fn extended(id: u32, time: u64, fields: &[&[u8]]) -> Vec<u8> {
+        let mut event = vec![31];
+        event.extend(id.to_le_bytes());
+        event.extend(time.to_le_bytes());
+        event.extend(fields.concat());
+        event
+    }
+
+    fn packet(cpu: u32, begin: u64, events: &[Vec<u8>]) -> Vec<u8> {
+        let events = events.concat();
+        let size = (36 + events.len() as u64) * 8;
+        let mut packet = 0xc1fc1fc1u32.to_le_bytes().to_vec();
+        packet.extend(0u32.to_le_bytes());
+        packet.extend(begin.to_le_bytes());
+        packet.extend(size.to_le_bytes());
+        packet.extend((size + 64).to_le_bytes());
+        packet.extend(cpu.to_le_bytes());
+        packet.extend(events);
+        packet.extend([0; 8]);
+        packet
+    }
+
+    fn packetized(text: &str) -> Vec<u8> {
+        let size = ((METADATA_HEADER_SIZE + text.len()) * 8) as u32;
+        let mut data = METADATA_MAGIC.to_le_bytes().to_vec();
+        data.extend([0; 20]);
+        data.extend(size.to_le_bytes());
+        data.extend(size.to_le_bytes());
+        data.extend([0, 0, 0, 1, 8]);
+        data.extend(text.as_bytes());
+        data
+    }
+
+    #[test]
+    fn test_from_ctf() {
+        let dir = std::env::temp_dir().join(format!("timeline-svg-ctf-{}", std::process::id()));
+        let (kernel, ust) = (dir.join("kernel"), dir.join("ust/uid/1000/64-bit"));
+        std::fs::create_dir_all(&kernel).unwrap();
+        std::fs::create_dir_all(&ust).unwrap();
+
+        let switch = |prev: &str, prev_tid: i32, next: &str, next_tid: i32, time| {
+            compact(
+                0,
+                time,
+                &[
+                    &comm(prev),
+                    &prev_tid.to_le_bytes(),
+                    &comm(next),
+                    &next_tid.to_le_bytes(),
+                ],
+            )
+        };
+        let wakeup = compact(
+            1,
+            1500,
+            &[&comm("cat"), &11i32.to_le_bytes(), &0i32.to_le_bytes()],
+        );
+        let cpu0 = packet(
+            0,
+            1000,
+            &[
+                switch("swapper/0", 0, "bash", 10, 1000),
+                wakeup,
+                switch("bash", 10, "cat", 11, 2000),
+            ],
+        );
+        std::fs::write(
+            kernel.join("metadata"),
+            packetized(&format!("{}{}", PRELUDE, KERNEL)),
+        )
+        .unwrap();
+        std::fs::write(kernel.join("channel0_0"), cpu0).unwrap();
+
+        let context = [&11i32.to_le_bytes()[..], b"cat\0"].concat();
+        let ust_cpu0 = packet(
+            0,
+            2400,
+            &[
+                compact(0, 2500, &[&context, &42u32.to_le_bytes()]),
+                extended(40, 3000, &[&context, &4u32.to_le_bytes(), b"done"]),
+            ],
+        );
+        std::fs::write(ust.join("metadata"), format!("{}{}", PRELUDE, UST)).unwrap();
+        std::fs::write(ust.join("channel0_0"), &ust_cpu0).unwrap();
+
+        let timeline = Timeline::from_ctf(&dir).unwrap();
+        assert_eq!(timeline.categories(), ["CPU 0", "bash-10", "cat-11"]);
+        let events: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name(),
+                    event.location(),
+                    event.start_time() - 1_000_000_000,
+                    event.end_time() - 1_000_000_000,
+                )
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("bash", "CPU 0", 1000, 2000),
+                ("running", "bash-10", 1000, 2000),
+                ("cat", "CPU 0", 2000, 3000),
+                ("running", "cat-11", 2000, 3000),
+                ("app:request", "cat-11", 2500, 2500),
+                ("done", "cat-11", 3000, 3000),
+            ]
+        );
+        let triggers: Vec<_> = timeline
+            .triggers()
+            .map(|trigger| (trigger.start_location(), trigger.end_location()))
+            .collect();
+        assert_eq!(triggers, [("bash-10", "cat-11")]);
+
+        std::fs::write(ust.join("channel0_0"), &ust_cpu0[..40]).unwrap();
+        assert!(matches!(
+            Timeline::from_ctf(&dir),
+            Err(TimelineError::Parse(_))
+        ));
+        std::fs::create_dir(dir.join("empty")).unwrap();
+        assert!(matches!(
+            Timeline::from_ctf(dir.join("empty")),
+            Err(TimelineError::Parse(_))
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,267 @@
+use std::io::Write;
+
+use serde_json::{json, Value};
+
+use crate::stats::csv_field;
+use crate::{TimeValue, Timeline, TimelineError, TimelineExporter};
+
+// The data of the timeline as JSON, see `Timeline::export_json`
+pub(crate) struct JsonExporter;
+
+impl TimelineExporter for JsonExporter {
+    fn format(&self) -> &str {
+        "json"
+    }
+
+    fn export(&self, timeline: &Timeline, writer: &mut dyn Write) -> Result<(), TimelineError> {
+        timeline.export_json(writer)
+    }
+}
+
+// The data of the timeline as CSV, see `Timeline::export_csv`
+pub(crate) struct CsvExporter;
+
+impl TimelineExporter for CsvExporter {
+    fn format(&self) -> &str {
+        "csv"
+    }
+
+    fn export(&self, timeline: &Timeline, writer: &mut dyn Write) -> Result<(), TimelineError> {
+        timeline.export_csv(writer)
+    }
+}
+
+const CSV_HEADER: &str = "type,id,name,location,start,end,to_location,to_id,metadata";
+
+// A whole number of units is written without a fraction, as long as JSON readers holding
+// numbers as doubles get it exactly
+fn number(value: f64) -> Value {
+    if value.fract() == 0.0 && value.abs() < (1u64 << 53) as f64 {
+        json!(value as i64)
+    } else {
+        json!(value)
+    }
+}
+
+impl<T: TimeValue> Timeline<T> {
+    // A time as the number of units it was given as
+    fn data_time(&self, time: T) -> f64 {
+        time.offset_from(T::from_nanos(0, self.units), self.units)
+    }
+
+    /// Write the events, triggers, links and markers of the timeline as JSON
+    ///
+    /// This is the data the SVG is drawn from, for tools that want to work on it rather than on
+    /// the picture. Times are numbers of the units of the timeline, as they were added, and
+    /// everything is listed in the order it was added, including events hidden by filters. The
+    /// schema is an object with these fields:
+    ///
+    /// - `nanoseconds_per_unit`: the length of the unit of the times in nanoseconds
+    /// - `events`: objects with the `id` of the handle of the event, its `name`, `location`,
+    ///   `start` and `end`, whether it is an `instant` added with `add_instant`, and its
+    ///   `metadata` as an object of strings
+    /// - `triggers`: objects with the `from` and `to` locations and the `start` and `end` times
+    ///   of a trigger
+    /// - `links`: objects with the `from` and `to` event ids of `add_trigger_between` and
+    ///   `add_link`, and the `label` of links, which is null for triggers between events
+    /// - `markers`: objects with the `label` and `time` of a marker
+    ///
+    /// This is the same as `export("json")`.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("read", 0, 5, "CPU 0");
+    /// let mut output = Vec::new();
+    /// timeline.export_json(&mut output).unwrap();
+    /// assert!(String::from_utf8(output).unwrap().contains(r#""name":"read""#));
+    /// # }
+    /// ```
+    pub fn export_json<W: Write>(&self, mut writer: W) -> Result<(), TimelineError> {
+        let events: Vec<Value> = self
+            .events()
+            .map(|(id, event)| {
+                json!({
+                    "id": id.0,
+                    "name": event.name,
+                    "location": event.location,
+                    "start": number(self.data_time(event.start_time)),
+                    "end": number(self.data_time(event.end_time)),
+                    "instant": event.instant,
+                    "metadata": event.metadata,
+                })
+            })
+            .collect();
+        let triggers: Vec<Value> = self
+            .triggers
+            .iter()
+            .map(|trigger| {
+                json!({
+                    "from": trigger.start_location,
+                    "to": trigger.end_location,
+                    "start": number(self.data_time(trigger.time)),
+                    "end": number(self.data_time(trigger.end_time)),
+                })
+            })
+            .collect();
+        let links: Vec<Value> = self
+            .links
+            .iter()
+            .map(|link| json!({ "from": link.from.0, "to": link.to.0, "label": link.label }))
+            .collect();
+        let markers: Vec<Value> = self
+            .markers
+            .iter()
+            .map(|marker| json!({ "label": marker.label, "time": number(self.data_time(marker.time)) }))
+            .collect();
+        let data = json!({
+            "nanoseconds_per_unit": self.units.nanoseconds() as u64,
+            "events": events,
+            "triggers": triggers,
+            "links": links,
+            "markers": markers,
+        });
+        serde_json::to_writer(&mut writer, &data).map_err(std::io::Error::from)?;
+        Ok(())
+    }
+
+    /// Write the events, triggers, links and markers of the timeline as CSV
+    ///
+    /// This is `export_json` as a single table for spreadsheets, with the same times and order.
+    /// The first line is the header `type,id,name,location,start,end,to_location,to_id,metadata`,
+    /// and every other line is one of these, with the fields it doesn't use left empty:
+    ///
+    /// - `event` or `instant`: the `id`, `name`, `location`, `start` and `end` of an event, and
+    ///   its `metadata` as a JSON object
+    /// - `trigger`: the `location` and `start` a trigger starts at, and the `to_location` and
+    ///   `end` it ends at
+    /// - `link`: the `id` and `location` of the event a link or trigger between events starts
+    ///   from, at its `start`, and the `to_id` and `to_location` of the event it goes to, at its
+    ///   `end`, with the label of links as the `name`
+    /// - `marker`: the `name` of a marker and its time as the `start` and `end`
+    ///
+    /// Fields with commas, quotes or line breaks are quoted. This is the same as
+    /// `export("csv")`.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("read", 0, 5, "CPU 0");
+    /// let mut output = Vec::new();
+    /// timeline.export_csv(&mut output).unwrap();
+    /// assert!(String::from_utf8(output).unwrap().ends_with("\nevent,0,read,CPU 0,0,5,,,{}\n"));
+    /// # }
+    /// ```
+    pub fn export_csv<W: Write>(&self, mut writer: W) -> Result<(), TimelineError> {
+        writeln!(writer, "{}", CSV_HEADER)?;
+        for (id, event) in self.events() {
+            let metadata = Value::from(
+                event
+                    .metadata
+                    .iter()
+                    .map(|(key, value)| (key.clone(), Value::from(value.as_str())))
+                    .collect::<serde_json::Map<_, _>>(),
+            );
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},,,{}",
+                if event.instant { "instant" } else { "event" },
+                id.0,
+                csv_field(&event.name),
+                csv_field(&event.location),
+                self.data_time(event.start_time),
+                self.data_time(event.end_time),
+                csv_field(&metadata.to_string())
+            )?;
+        }
+        for trigger in &self.triggers {
+            writeln!(
+                writer,
+                "trigger,,,{},{},{},{},,",
+                csv_field(&trigger.start_location),
+                self.data_time(trigger.time),
+                self.data_time(trigger.end_time),
+                csv_field(&trigger.end_location)
+            )?;
+        }
+        for link in &self.links {
+            if let (Some(from), Some(to)) = (self.event(link.from), self.event(link.to)) {
+                writeln!(
+                    writer,
+                    "link,{},{},{},{},{},{},{},",
+                    link.from.0,
+                    csv_field(link.label.as_deref().unwrap_or_default()),
+                    csv_field(&from.location),
+                    self.data_time(from.end_time),
+                    self.data_time(to.start_time),
+                    csv_field(&to.location),
+                    link.to.0
+                )?;
+            }
+        }
+        for marker in &self.markers {
+            let time = self.data_time(marker.time);
+            writeln!(
+                writer,
+                "marker,,{},,{},{},,,",
+                csv_field(&marker.label),
+                time,
+                time
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_data() {
+        let mut timeline = Timeline::default();
+        let from = timeline.add_event("sleep, then run", 10, 20, "CPU 0");
+        let to = timeline.add_event("run", 22, 30, "CPU \"1\"");
+        timeline.add_link(from, to, "wakes".to_string());
+        timeline.add_trigger("CPU 0", "CPU \"1\"", 25);
+        timeline.add_marker("start".to_string(), 10);
+        timeline.set_category_filter(Some(vec!["CPU 0".to_string()]));
+
+        let mut output = Vec::new();
+        timeline.export("json", &mut output).unwrap();
+        let data: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(
+            data,
+            json!({
+                "nanoseconds_per_unit": 1,
+                "events": [
+                    { "id": 0, "name": "sleep, then run", "location": "CPU 0", "start": 10,
+                      "end": 20, "instant": false, "metadata": {} },
+                    { "id": 1, "name": "run", "location": "CPU \"1\"", "start": 22, "end": 30,
+                      "instant": false, "metadata": {} },
+                ],
+                "triggers": [{ "from": "CPU 0", "to": "CPU \"1\"", "start": 25, "end": 25 }],
+                "links": [{ "from": 0, "to": 1, "label": "wakes" }],
+                "markers": [{ "label": "start", "time": 10 }],
+            })
+        );
+
+        let mut output = Vec::new();
+        timeline.export("csv", &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "type,id,name,location,start,end,to_location,to_id,metadata\n\
+             event,0,\"sleep, then run\",CPU 0,10,20,,,{}\n\
+             event,1,run,\"CPU \"\"1\"\"\",22,30,,,{}\n\
+             trigger,,,CPU 0,25,25,\"CPU \"\"1\"\"\",,\n\
+             link,0,wakes,CPU 0,20,22,\"CPU \"\"1\"\"\",1,\n\
+             marker,,start,,10,10,,,\n"
+        );
+    }
+}
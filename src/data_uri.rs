@@ -0,0 +1,76 @@
+use crate::{TimeValue, Timeline, TimelineError};
+
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Standard base64 with padding, as data URIs expect
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | (*byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_CHARS[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+impl<T: TimeValue> Timeline<T> {
+    /// The SVG of the timeline as a base64 `data:` URI
+    ///
+    /// The URI can be used as the `src` of an `<img>` or in CSS, to inline a timeline into an
+    /// HTML page without saving it anywhere. The SVG is the one `write` writes, and this fails
+    /// like `write`, except that it can't fail to write.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("read", 0, 5, "CPU 0");
+    /// let uri = timeline.to_data_uri().unwrap();
+    /// let html = format!("<img src=\"{}\">", uri);
+    /// assert!(html.starts_with("<img src=\"data:image/svg+xml;base64,PHN2Zy"));
+    /// # }
+    /// ```
+    pub fn to_data_uri(&self) -> Result<String, TimelineError> {
+        let mut svg = Vec::new();
+        self.write(&mut svg)?;
+        Ok(format!("data:image/svg+xml;base64,{}", base64(&svg)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_data_uri() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foob"), "Zm9vYg==");
+        assert_eq!(base64(&[0xfb, 0xff, 0xbf]), "+/+/");
+
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1", 0, 10, "Location 1");
+        let mut svg = Vec::new();
+        timeline.write(&mut svg).unwrap();
+        assert_eq!(
+            timeline.to_data_uri().unwrap(),
+            format!("data:image/svg+xml;base64,{}", base64(&svg))
+        );
+        timeline.add_trigger("Location 1", "Location 2", 5);
+        assert!(matches!(
+            timeline.to_data_uri(),
+            Err(TimelineError::UnknownLocation(_))
+        ));
+    }
+}
@@ -0,0 +1,227 @@
+use std::io::{BufRead, BufReader, Read};
+
+use crate::ftrace::parse_time;
+use crate::import::{new_timeline, sniff_text};
+use crate::{Timeline, TimelineError, TraceImporter};
+
+// The kernel log of dmesg, see `Timeline::from_dmesg`
+pub(crate) struct DmesgImporter;
+
+impl TraceImporter for DmesgImporter {
+    fn format(&self) -> &str {
+        "dmesg"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        sniff_text(data)
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .is_some_and(|line| parse_line(line).is_some())
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_dmesg(data)
+    }
+}
+
+// The log levels, by their number
+pub(crate) const LEVELS: [&str; 8] = [
+    "emerg", "alert", "crit", "err", "warn", "notice", "info", "debug",
+];
+
+// A line of the kernel log, like "<6>[    1.234567] usb 1-1: new high-speed USB device"
+#[derive(Debug, PartialEq)]
+struct Line<'a> {
+    level: Option<&'a str>,
+    time: i64,
+    // What the message starts with before a colon, like "usb 1-1", which most drivers and
+    // subsystems prefix their messages with
+    prefix: Option<&'a str>,
+    message: &'a str,
+}
+
+fn parse_line(line: &str) -> Option<Line<'_>> {
+    let mut rest = line.trim_start();
+    let mut level = None;
+    // `-r` prefixes the level as a number, `-x` decodes it after the facility
+    if let Some(raw) = rest.strip_prefix('<') {
+        let (number, after) = raw.split_once('>')?;
+        let number: usize = number.parse().ok()?;
+        level = Some(LEVELS[number & 7]);
+        rest = after;
+    } else if let [facility, name, after] = rest.splitn(3, ':').collect::<Vec<_>>()[..] {
+        let known = LEVELS.iter().find(|level| **level == name.trim());
+        if known.is_some() && facility.trim().bytes().all(|b| b.is_ascii_lowercase()) {
+            level = known.copied();
+            rest = after;
+        }
+    }
+    let (time, message) = rest.trim_start().strip_prefix('[')?.split_once(']')?;
+    // `-d` adds the time since the line before in angle brackets
+    let time = parse_time(time.split_whitespace().next()?)?;
+    let message = message.trim();
+    let prefix = message.split_once(": ").and_then(|(prefix, _)| {
+        let words = prefix.split_whitespace().count();
+        (words > 0 && words <= 3 && prefix.len() <= 48 && !prefix.contains('[')).then_some(prefix)
+    });
+    Some(Line {
+        level,
+        time,
+        prefix,
+        message,
+    })
+}
+
+impl Timeline {
+    /// Read the kernel log that dmesg prints into a timeline
+    ///
+    /// This reads the default output of `dmesg`, with times since boot in microseconds, also
+    /// with the levels of `-r` or `-x` and the deltas of `-d`. Every line becomes an instant,
+    /// named after its message, on the row of the subsystem or driver its message starts with,
+    /// like "usb" for "usb 1-1: new high-speed USB device", or "kernel" for lines without such
+    /// a prefix. The prefix and the level of lines that have them are their "prefix" and
+    /// "level" metadata. Lines without a time, like those of `-T`, are skipped. Times are in
+    /// nanoseconds.
+    ///
+    /// This returns `TimelineError::Parse` if no line has a time since boot, and
+    /// `TimelineError::Io` if reading fails. `Timeline::import` detects logs of this format, as
+    /// "dmesg".
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let log = "\
+    ///     [    0.000000] Linux version 6.8.0\n\
+    ///     [    1.500000] usb 1-1: new high-speed USB device number 2 using xhci_hcd\n";
+    /// let timeline = Timeline::from_dmesg(log.as_bytes()).unwrap();
+    /// assert_eq!(timeline.categories(), ["kernel", "usb"]);
+    /// assert_eq!(timeline.time_range(), Some((0, 1_500_000_000)));
+    /// # }
+    /// ```
+    pub fn from_dmesg(reader: impl Read) -> Result<Timeline, TimelineError> {
+        let mut timeline = new_timeline();
+        let mut parsed = false;
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            let Some(line) = parse_line(&line) else {
+                continue;
+            };
+            parsed = true;
+            let (row, name) = match line.prefix {
+                Some(prefix) => (
+                    prefix.split_whitespace().next().unwrap_or(prefix),
+                    line.message[prefix.len() + 1..].trim_start(),
+                ),
+                None => ("kernel", line.message),
+            };
+            let id = timeline.add_instant(name, line.time, row);
+            if let Some(prefix) = line.prefix {
+                timeline.set_event_metadata(id, "prefix".to_string(), prefix.to_string());
+            }
+            if let Some(level) = line.level {
+                timeline.set_event_metadata(id, "level".to_string(), level.to_string());
+            }
+        }
+        if !parsed {
+            return Err(TimelineError::Parse("no dmesg lines".to_string()));
+        }
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels() {
+        // `-x` decodes the facility and level, and `-r` prints them as one number
+        assert_eq!(
+            parse_line("kern  :err   : [    2.000100 <    0.000100>] ata1.00: failed command"),
+            Some(Line {
+                level: Some("err"),
+                time: 2_000_100_000,
+                prefix: Some("ata1.00"),
+                message: "ata1.00: failed command",
+            })
+        );
+        let level = |line| parse_line(line).unwrap().level;
+        assert_eq!(level("<6>[    0.250000] ACPI: Core revision"), Some("info"));
+        assert_eq!(
+            level("<14>[    0.250000] systemd[1]: started"),
+            Some("info")
+        );
+        assert_eq!(level("user  :warn  : [    3.000000] hello"), Some("warn"));
+        // Colons of the message aren't a level
+        assert_eq!(level("[    3.000000] a: warn: b"), None);
+        assert_eq!(parse_line("<x>[    0.250000] ACPI: Core revision"), None);
+    }
+
+    #[test]
+    fn test_prefixes() {
+        let prefix = |line| parse_line(line).unwrap().prefix;
+        assert_eq!(
+            prefix("[    1.6] EXT4-fs (sda1): mounted"),
+            Some("EXT4-fs (sda1)")
+        );
+        assert_eq!(prefix("[    1.6] usb 1-1: new device"), Some("usb 1-1"));
+        // Messages that merely have a colon in them
+        assert_eq!(prefix("[    1.6] Memory: 16G available"), Some("Memory"));
+        assert_eq!(prefix("[    1.6] Run /init as init process: ok"), None);
+        assert_eq!(prefix("[    1.6] systemd[1]: started"), None);
+        assert_eq!(prefix("[    1.6] Linux version 6.8.0"), None);
+        let long = format!("[    1.6] {}: ok", "x".repeat(49));
+        assert_eq!(prefix(&long), None);
+        assert_eq!(prefix("[    1.6] : empty"), None);
+    }
+
+    #[test]
+    fn test_from_dmesg() {
+        let log = "\
+<5>[    0.000000] Linux version 6.8.0 (gcc (GCC) 13.2.0) #1 SMP
+<6>[    0.250000] ACPI: Core revision 20230628
+<6>[    1.500000] usb 1-1: new high-speed USB device number 2 using xhci_hcd
+<3>[    1.600000] EXT4-fs (sda1): mounted filesystem
+    continued line without a time
+[Mon Jan  1 10:00:00 2024] a line of -T
+";
+        assert!(DmesgImporter.sniff(log.as_bytes()));
+        let timeline = Timeline::from_dmesg(log.as_bytes()).unwrap();
+        assert_eq!(timeline.categories(), ["ACPI", "EXT4-fs", "kernel", "usb"]);
+        let events: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| (event.name(), event.location(), event.start_time()))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("Linux version 6.8.0 (gcc (GCC) 13.2.0) #1 SMP", "kernel", 0),
+                ("Core revision 20230628", "ACPI", 250_000_000),
+                (
+                    "new high-speed USB device number 2 using xhci_hcd",
+                    "usb",
+                    1_500_000_000
+                ),
+                ("mounted filesystem", "EXT4-fs", 1_600_000_000),
+            ]
+        );
+        let (_, usb) = timeline.events().nth(2).unwrap();
+        assert_eq!(usb.metadata()["prefix"], "usb 1-1");
+        assert_eq!(usb.metadata()["level"], "info");
+        let (_, linux) = timeline.events().next().unwrap();
+        assert!(!linux.metadata().contains_key("prefix"));
+
+        // The wall clock times of `-T` aren't times since boot
+        assert!(!DmesgImporter.sniff(b"[Mon Jan  1 10:00:00 2024] Linux version 6.8.0\n"));
+        assert!(matches!(
+            Timeline::from_dmesg("no times\n".as_bytes()),
+            Err(TimelineError::Parse(_))
+        ));
+        assert!(matches!(
+            Timeline::from_dmesg("[Mon Jan  1 10:00:00 2024] hello\n".as_bytes()),
+            Err(TimelineError::Parse(_))
+        ));
+    }
+}
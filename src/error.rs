@@ -0,0 +1,49 @@
+use std::fmt;
+use std::io;
+
+/// An error rendering, saving or importing a timeline
+#[derive(Debug)]
+pub enum TimelineError {
+    /// A trigger references a location that has no events, so there is no row to draw it on
+    UnknownLocation(String),
+    /// An event or highlight ends before it starts, the string describes which one
+    InvalidRange(String),
+    /// Reading or writing a file failed
+    Io(io::Error),
+    /// No importer recognizes a trace, or none is registered for the format it was asked for
+    UnknownFormat(String),
+    /// An importer couldn't read a trace, the string describes why
+    Parse(String),
+    /// Rasterizing the timeline into an image failed, the string describes why
+    Raster(String),
+}
+
+impl fmt::Display for TimelineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimelineError::UnknownLocation(location) => {
+                write!(f, "unknown location {:?}, it has no events", location)
+            }
+            TimelineError::InvalidRange(what) => write!(f, "invalid range: {}", what),
+            TimelineError::Io(e) => write!(f, "{}", e),
+            TimelineError::UnknownFormat(what) => write!(f, "unknown trace format: {}", what),
+            TimelineError::Parse(what) => write!(f, "invalid trace: {}", what),
+            TimelineError::Raster(what) => write!(f, "can't rasterize: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for TimelineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TimelineError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TimelineError {
+    fn from(e: io::Error) -> Self {
+        TimelineError::Io(e)
+    }
+}
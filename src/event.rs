@@ -0,0 +1,223 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::{Layer, TimeValue};
+
+/// Drawing options for a single event
+///
+/// Events are drawn on `layer`, `Layer::Events` by default. Within a layer, events with a higher
+/// `z_index` are drawn on top of events with a lower one. Events with the same `z_index` are drawn
+/// by start time, then location, then name, and only then in the order they were added, so
+/// timelines recorded in a different order, like by racing threads, render the same.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventStyle {
+    pub z_index: i32,
+    pub layer: Layer,
+}
+
+impl Default for EventStyle {
+    fn default() -> Self {
+        EventStyle {
+            z_index: 0,
+            layer: Layer::Events,
+        }
+    }
+}
+
+/// A handle to an event added to a `Timeline`
+///
+/// Handles are returned by `Timeline::add_event` and `Timeline::insert_event`, and are only
+/// meaningful for the timeline that returned them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventId(pub(crate) usize);
+
+/// An event on the timeline
+///
+/// An event is a named span of time on one row of the timeline. Events are usually added with
+/// `Timeline::add_event`, or built with an `EventBuilder` when they carry metadata.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Event<T = i64> {
+    pub(crate) name: String,
+    pub(crate) start_time: T,
+    pub(crate) end_time: T,
+    pub(crate) location: String,
+    pub(crate) metadata: BTreeMap<String, String>,
+    pub(crate) style: EventStyle,
+    // Added with `Timeline::add_instant`, so always drawn as a glyph
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) instant: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) url: Option<String>,
+}
+
+impl<T: TimeValue> Event<T> {
+    /// The name of the event
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The time the event starts
+    pub fn start_time(&self) -> T {
+        self.start_time
+    }
+
+    /// The time the event ends
+    pub fn end_time(&self) -> T {
+        self.end_time
+    }
+
+    /// The location, or row, of the event
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    /// The key/value metadata of the event
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// The drawing options of the event
+    pub fn style(&self) -> EventStyle {
+        self.style
+    }
+
+    /// The URL the event links to, if any
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    // The order of events with the same z-index, see `EventStyle`. Events that compare equal are
+    // kept in the order they were added by stable sorts.
+    pub(crate) fn draw_order(&self, other: &Self) -> Ordering {
+        self.start_time
+            .partial_cmp(&other.start_time)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| self.location.cmp(&other.location))
+            .then_with(|| self.name.cmp(&other.name))
+    }
+}
+
+/// A builder for an `Event` with metadata
+///
+/// Metadata is free-form key/value information about an event, like the PID, TID or arguments
+/// of a traced call. It is rendered into the event's tooltip and into `data-*` attributes on the
+/// event's SVG group, so scripts embedding the SVG can pick it up.
+///
+/// ```
+/// # extern crate timeline_svg;
+/// # fn main() {
+/// use timeline_svg::{EventBuilder, Timeline};
+///
+/// let mut timeline = Timeline::default();
+/// let event = EventBuilder::new("read".to_string(), 0, 5, "CPU 0".to_string())
+///     .metadata("pid".to_string(), "1234".to_string())
+///     .metadata("fd".to_string(), "3".to_string())
+///     .build();
+/// timeline.insert_event(event);
+/// # }
+/// ```
+pub struct EventBuilder<T = i64> {
+    event: Event<T>,
+}
+
+impl<T: TimeValue> EventBuilder<T> {
+    /// Start building an event, see `Timeline::add_event` for the meaning of the arguments
+    pub fn new(
+        name: impl Into<String>,
+        start_time: T,
+        end_time: T,
+        location: impl Into<String>,
+    ) -> Self {
+        EventBuilder {
+            event: Event {
+                name: name.into(),
+                start_time,
+                end_time,
+                location: location.into(),
+                metadata: BTreeMap::new(),
+                style: EventStyle::default(),
+                instant: false,
+                url: None,
+            },
+        }
+    }
+
+    /// Add a metadata entry, replacing any previous value for `key`
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.event.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Set the drawing options of the event
+    pub fn style(mut self, style: EventStyle) -> Self {
+        self.event.style = style;
+        self
+    }
+
+    /// Link the event to `url`, like the page of a request in a tracing system
+    ///
+    /// The shape of the event is wrapped in a link to the URL, so clicking the event in a browser
+    /// opens it.
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.event.url = Some(url.into());
+        self
+    }
+
+    /// Build the event
+    pub fn build(self) -> Event<T> {
+        self.event
+    }
+}
+
+// Turn a metadata key into a valid data-* attribute name, or `None` for an empty key. Uppercase
+// letters are escaped the way `dataset` does, as a dash before the lowercase letter, so "PID" and
+// "pid" stay apart and `dataset.PID` reads "data--p-i-d". Every other character outside
+// `[a-z0-9_.]`, dashes included, becomes its code point between dashes, so "arg 0" and "arg-0"
+// don't end up on the same attribute.
+pub(crate) fn data_attribute(key: &str) -> Option<String> {
+    if key.is_empty() {
+        return None;
+    }
+    let mut name = String::from("data-");
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            name.push('-');
+            name.push(c.to_ascii_lowercase());
+        } else if c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '.' {
+            name.push(c);
+        } else {
+            name.push_str(&format!("-{}-", u32::from(c)));
+        }
+    }
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_builder() {
+        let event = EventBuilder::new("Event 1".to_string(), 1, 2, "Location 1".to_string())
+            .metadata("PID".to_string(), "1".to_string())
+            .metadata("PID".to_string(), "2".to_string())
+            .url("https://example.com/trace/1".to_string())
+            .build();
+        assert_eq!(event.name(), "Event 1");
+        assert_eq!(event.start_time(), 1);
+        assert_eq!(event.end_time(), 2);
+        assert_eq!(event.location(), "Location 1");
+        assert_eq!(event.metadata()["PID"], "2");
+        assert_eq!(event.url(), Some("https://example.com/trace/1"));
+        assert_eq!(data_attribute("PID").unwrap(), "data--p-i-d");
+        assert_eq!(data_attribute("pid").unwrap(), "data-pid");
+        assert_eq!(data_attribute("argCount").unwrap(), "data-arg-count");
+        assert_eq!(data_attribute("arg 0").unwrap(), "data-arg-32-0");
+        assert_eq!(data_attribute("arg-0").unwrap(), "data-arg-45-0");
+        assert_eq!(data_attribute("é").unwrap(), "data--233-");
+        assert_eq!(data_attribute(""), None);
+    }
+}
@@ -0,0 +1,334 @@
+use std::io::Write;
+use std::sync::{Arc, RwLock};
+
+use serde_json::{json, Value};
+
+use crate::data::{CsvExporter, JsonExporter};
+use crate::graphviz::DotExporter;
+use crate::mermaid::MermaidExporter;
+use crate::plantuml::PlantUmlExporter;
+use crate::{TimeValue, Timeline, TimelineError};
+
+/// A writer of a timeline in some format
+///
+/// Exporters are added with `register_exporter` and picked by `Timeline::export`, which lets
+/// other crates add output formats. "svg", which is the same as `Timeline::write`,
+/// "chrome-json", the Chrome trace event format, "mermaid", a Mermaid gantt chart,
+/// "plantuml", a PlantUML timing diagram, "json" and "csv", the data of the timeline, and
+/// "dot", a Graphviz graph of its triggers, are built in, as is "perfetto" with the `perfetto`
+/// feature.
+pub trait TimelineExporter: Send + Sync {
+    /// The name of the format, like "chrome-json"
+    fn format(&self) -> &str;
+
+    /// Write `timeline` in this format
+    fn export(&self, timeline: &Timeline, writer: &mut dyn Write) -> Result<(), TimelineError>;
+}
+
+// The registered exporters, which replace built in exporters of the same format
+static EXPORTERS: RwLock<Vec<Arc<dyn TimelineExporter>>> = RwLock::new(Vec::new());
+
+fn exporters() -> Vec<Arc<dyn TimelineExporter>> {
+    let mut exporters: Vec<Arc<dyn TimelineExporter>> = vec![
+        Arc::new(SvgExporter),
+        Arc::new(ChromeJsonExporter),
+        Arc::new(MermaidExporter),
+        Arc::new(PlantUmlExporter),
+        Arc::new(JsonExporter),
+        Arc::new(CsvExporter),
+        Arc::new(DotExporter),
+    ];
+    #[cfg(feature = "perfetto")]
+    exporters.push(Arc::new(crate::perfetto::PerfettoExporter));
+    // A panic while registering leaves nothing half-updated worth refusing
+    for exporter in EXPORTERS.read().unwrap_or_else(|e| e.into_inner()).iter() {
+        match exporters
+            .iter()
+            .position(|existing| existing.format() == exporter.format())
+        {
+            Some(index) => exporters[index] = exporter.clone(),
+            None => exporters.push(exporter.clone()),
+        }
+    }
+    exporters
+}
+
+/// Register an exporter for `Timeline::export`
+///
+/// An exporter registered for a format that already has one, including the built in formats,
+/// replaces it.
+pub fn register_exporter(exporter: impl TimelineExporter + 'static) {
+    let mut exporters = EXPORTERS.write().unwrap_or_else(|e| e.into_inner());
+    exporters.retain(|existing| existing.format() != exporter.format());
+    exporters.push(Arc::new(exporter));
+}
+
+/// The formats `Timeline::export` can write, the built in ones first
+pub fn export_formats() -> Vec<String> {
+    exporters()
+        .iter()
+        .map(|exporter| exporter.format().to_string())
+        .collect()
+}
+
+impl Timeline {
+    /// Write the timeline in `format`
+    ///
+    /// The format is one of `export_formats`. This returns `TimelineError::UnknownFormat` if
+    /// there is no exporter for it, and otherwise whatever the exporter returns.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("read".to_string(), 0, 5, "CPU 0".to_string());
+    /// let mut output = Vec::new();
+    /// timeline.export("chrome-json", &mut output).unwrap();
+    /// assert!(String::from_utf8(output).unwrap().contains("traceEvents"));
+    /// # }
+    /// ```
+    pub fn export<W: Write>(&self, format: &str, mut writer: W) -> Result<(), TimelineError> {
+        let exporter = exporters()
+            .into_iter()
+            .find(|exporter| exporter.format() == format)
+            .ok_or_else(|| TimelineError::UnknownFormat(format.to_string()))?;
+        exporter.export(self, &mut writer)
+    }
+}
+
+struct SvgExporter;
+
+impl TimelineExporter for SvgExporter {
+    fn format(&self) -> &str {
+        "svg"
+    }
+
+    fn export(&self, timeline: &Timeline, writer: &mut dyn Write) -> Result<(), TimelineError> {
+        timeline.write(writer)
+    }
+}
+
+// The Chrome trace event format, see `Timeline::export_chrome_trace`
+struct ChromeJsonExporter;
+
+impl TimelineExporter for ChromeJsonExporter {
+    fn format(&self) -> &str {
+        "chrome-json"
+    }
+
+    fn export(&self, timeline: &Timeline, writer: &mut dyn Write) -> Result<(), TimelineError> {
+        timeline.export_chrome_trace(writer)
+    }
+}
+
+impl<T: TimeValue> Timeline<T> {
+    /// Write the timeline in the Chrome trace event format
+    ///
+    /// The JSON can be opened in chrome://tracing or the Perfetto UI to dig into a recorded
+    /// timeline interactively. Every row becomes a named thread, events become complete events
+    /// on the thread of their location, or instant events if they were added with
+    /// `add_instant`, and markers become global instant events. Triggers, and the connectors of
+    /// `add_trigger_between` and `add_link`, become flow events from the thread they start on
+    /// to the one they end on. Times are converted to microseconds as the format expects, and
+    /// metadata of events is kept in their `args`. This is the same as `export("chrome-json")`.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// let wakeup = timeline.add_event("wakeup", 0, 2, "CPU 0");
+    /// let run = timeline.add_event("run", 5, 9, "CPU 1");
+    /// timeline.add_trigger_between(wakeup, run);
+    /// let mut output = Vec::new();
+    /// timeline.export_chrome_trace(&mut output).unwrap();
+    /// assert!(String::from_utf8(output).unwrap().contains(r#""ph":"s""#));
+    /// # }
+    /// ```
+    pub fn export_chrome_trace<W: Write>(&self, mut writer: W) -> Result<(), TimelineError> {
+        let micros = |time: T| time.to_nanos(self.units) as f64 / 1000.0;
+        let categories = self.categories();
+        let tid = |location: &str| {
+            categories
+                .iter()
+                .position(|category| *category == location)
+                .unwrap_or_default()
+        };
+        let mut events: Vec<Value> = Vec::new();
+        for (tid, category) in categories.iter().enumerate() {
+            events.push(json!({
+                "name": "thread_name",
+                "ph": "M",
+                "pid": 1,
+                "tid": tid,
+                "args": { "name": category },
+            }));
+        }
+        for (_, event) in self.events() {
+            let mut trace_event = json!({
+                "name": event.name,
+                "ts": micros(event.start_time),
+                "pid": 1,
+                "tid": tid(&event.location),
+                "args": event.metadata,
+            });
+            if event.instant {
+                trace_event["ph"] = json!("i");
+                trace_event["s"] = json!("t");
+            } else {
+                trace_event["ph"] = json!("X");
+                trace_event["dur"] = json!(micros(event.end_time) - micros(event.start_time));
+            }
+            events.push(trace_event);
+        }
+        for marker in &self.markers {
+            events.push(json!({
+                "name": marker.label,
+                "ph": "i",
+                "s": "g",
+                "ts": micros(marker.time),
+                "pid": 1,
+                "tid": 0,
+            }));
+        }
+
+        // Flows bind to the events enclosing their ends, the end of a flow to the event it
+        // points at rather than the next one
+        let mut flows = Vec::new();
+        for trigger in &self.triggers {
+            flows.push((
+                tid(&trigger.start_location),
+                micros(trigger.time),
+                tid(&trigger.end_location),
+                micros(trigger.end_time),
+            ));
+        }
+        for link in &self.links {
+            if let (Some(from), Some(to)) = (self.event(link.from), self.event(link.to)) {
+                flows.push((
+                    tid(&from.location),
+                    micros(from.end_time),
+                    tid(&to.location),
+                    micros(to.start_time),
+                ));
+            }
+        }
+        for (id, (start_tid, start, end_tid, end)) in flows.into_iter().enumerate() {
+            events.push(json!({
+                "name": "trigger",
+                "cat": "trigger",
+                "ph": "s",
+                "id": id,
+                "ts": start,
+                "pid": 1,
+                "tid": start_tid,
+            }));
+            events.push(json!({
+                "name": "trigger",
+                "cat": "trigger",
+                "ph": "f",
+                "bp": "e",
+                "id": id,
+                "ts": end,
+                "pid": 1,
+                "tid": end_tid,
+            }));
+        }
+        serde_json::to_writer(&mut writer, &json!({ "traceEvents": events }))
+            .map_err(std::io::Error::from)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TimeUnit;
+
+    struct Names;
+
+    impl TimelineExporter for Names {
+        fn format(&self) -> &str {
+            "names"
+        }
+
+        fn export(&self, timeline: &Timeline, writer: &mut dyn Write) -> Result<(), TimelineError> {
+            for (_, event) in timeline.events() {
+                writeln!(writer, "{}", event.name())?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_export() {
+        let mut timeline = Timeline::default();
+        timeline.set_units(TimeUnit::Milliseconds);
+        timeline.add_event("read".to_string(), 1, 3, "CPU 1".to_string());
+        timeline.add_event("write".to_string(), 2, 4, "CPU 0".to_string());
+        timeline.add_marker("sync".to_string(), 2);
+
+        let mut output = Vec::new();
+        timeline.export("chrome-json", &mut output).unwrap();
+        let trace: Value = serde_json::from_slice(&output).unwrap();
+        let events = trace["traceEvents"].as_array().unwrap();
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[1]["args"]["name"], "CPU 1");
+        assert_eq!(events[2]["ts"], 1000.0);
+        assert_eq!(events[2]["dur"], 2000.0);
+        assert_eq!(events[2]["tid"], 1);
+
+        register_exporter(Names);
+        assert!(export_formats().starts_with(&["svg".to_string(), "chrome-json".to_string()]));
+        let mut output = Vec::new();
+        timeline.export("names", &mut output).unwrap();
+        assert_eq!(output, b"read\nwrite\n");
+        assert!(matches!(
+            timeline.export("pdf", &mut Vec::new()),
+            Err(TimelineError::UnknownFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_export_chrome_trace() {
+        let mut timeline = Timeline::default();
+        let from = timeline.add_event("wakeup", 1000, 2000, "CPU 0");
+        let to = timeline.add_event("run", 5000, 9000, "CPU 1");
+        timeline.add_instant("irq", 3000, "CPU 1");
+        timeline.add_trigger_between(from, to);
+        timeline.add_trigger_span("CPU 1", 6000, "CPU 0", 7000);
+
+        let mut output = Vec::new();
+        timeline.export_chrome_trace(&mut output).unwrap();
+        let trace: Value = serde_json::from_slice(&output).unwrap();
+        let events = trace["traceEvents"].as_array().unwrap();
+        assert_eq!(events.len(), 9);
+        assert_eq!(events[4]["ph"], "i");
+        assert_eq!(events[4]["s"], "t");
+        assert!(events[4].get("dur").is_none());
+        let flows: Vec<_> = events[5..]
+            .iter()
+            .map(|event| {
+                (
+                    event["ph"].as_str(),
+                    event["id"].as_u64(),
+                    event["tid"].as_u64(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            flows,
+            [
+                (Some("s"), Some(0), Some(1)),
+                (Some("f"), Some(0), Some(0)),
+                (Some("s"), Some(1), Some(0)),
+                (Some("f"), Some(1), Some(1)),
+            ]
+        );
+        assert_eq!(events[5]["ts"], 6.0);
+        assert_eq!(events[8]["bp"], "e");
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashSet;
+
+#[cfg(feature = "regex")]
+use regex::Regex;
+
+use crate::{Event, TimeValue, Timeline};
+
+type Predicate<T> = Box<dyn Fn(&Event<T>) -> bool + Send + Sync>;
+
+// Which events are drawn, set with the `set_*_filter` methods of the timeline. Events that any
+// filter rejects are left out of the rendered SVG, but stay on the timeline.
+pub(crate) struct EventFilter<T> {
+    predicate: Option<Predicate<T>>,
+    #[cfg(feature = "regex")]
+    name: Option<Regex>,
+    categories: Option<HashSet<String>>,
+}
+
+impl<T> Default for EventFilter<T> {
+    fn default() -> Self {
+        EventFilter {
+            predicate: None,
+            #[cfg(feature = "regex")]
+            name: None,
+            categories: None,
+        }
+    }
+}
+
+impl<T> EventFilter<T> {
+    fn allows_category(&self, category: &str) -> bool {
+        self.categories
+            .as_ref()
+            .is_none_or(|categories| categories.contains(category))
+    }
+
+    fn allows(&self, event: &Event<T>) -> bool {
+        #[cfg(feature = "regex")]
+        if let Some(name) = &self.name {
+            if !name.is_match(&event.name) {
+                return false;
+            }
+        }
+        if !self.allows_category(&event.location) {
+            return false;
+        }
+        self.predicate
+            .as_ref()
+            .is_none_or(|predicate| predicate(event))
+    }
+}
+
+impl<T: TimeValue> Timeline<T> {
+    /// Only draw the events for which `filter` returns true
+    ///
+    /// Filters are applied when the timeline is rendered, so the same timeline can be rendered
+    /// in several ways without changing it. Rows without any drawn events are left out, along
+    /// with the triggers to and from them and the triggers between events that aren't drawn.
+    /// This replaces any earlier predicate, and is combined with the other filters.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("read".to_string(), 0, 5, "CPU 0".to_string());
+    /// timeline.add_event("idle".to_string(), 5, 9, "CPU 0".to_string());
+    /// timeline.set_event_filter(|event| event.name() != "idle");
+    /// let mut output = Vec::new();
+    /// timeline.write(&mut output).unwrap();
+    /// assert!(!String::from_utf8(output).unwrap().contains("idle"));
+    /// # }
+    /// ```
+    pub fn set_event_filter(&mut self, filter: impl Fn(&Event<T>) -> bool + Send + Sync + 'static) {
+        self.filter.predicate = Some(Box::new(filter));
+        self.invalidate_layout();
+    }
+
+    /// Only draw the events whose name matches `name`, or every event for `None`
+    ///
+    /// See `set_event_filter` for how filters are applied.
+    #[cfg(feature = "regex")]
+    pub fn set_name_filter(&mut self, name: Option<Regex>) {
+        self.filter.name = name;
+        self.invalidate_layout();
+    }
+
+    /// Only draw the rows of `categories`, or every row for `None`
+    ///
+    /// See `set_event_filter` for how filters are applied.
+    pub fn set_category_filter(&mut self, categories: Option<Vec<String>>) {
+        self.filter.categories = categories.map(|categories| categories.into_iter().collect());
+        self.invalidate_layout();
+    }
+
+    // Set the rows the category filter allows, returning the ones it allowed before
+    pub(crate) fn replace_category_filter(
+        &mut self,
+        categories: Option<HashSet<String>>,
+    ) -> Option<HashSet<String>> {
+        self.invalidate_layout();
+        std::mem::replace(&mut self.filter.categories, categories)
+    }
+
+    /// Remove every filter, so all events are drawn again
+    pub fn clear_filters(&mut self) {
+        self.filter = EventFilter::default();
+        self.invalidate_layout();
+    }
+
+    // Whether the event is drawn, see `set_event_filter`
+    pub(crate) fn is_drawn(&self, event: &Event<T>) -> bool {
+        self.filter.allows(event)
+    }
+
+    // Whether a declared row is drawn, which only the category filter decides
+    pub(crate) fn is_row_drawn(&self, category: &str) -> bool {
+        self.filter.allows_category(category)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filters() {
+        let mut timeline = Timeline::default();
+        let read = timeline.add_event("read".to_string(), 0, 5, "CPU 0".to_string());
+        let write = timeline.add_event("write".to_string(), 6, 8, "CPU 1".to_string());
+        timeline.add_event("idle".to_string(), 5, 9, "CPU 2".to_string());
+        timeline.add_trigger_between(read, write);
+        timeline.add_trigger("CPU 0".to_string(), "CPU 2".to_string(), 5);
+        let render = |timeline: &Timeline| {
+            let mut output = Vec::new();
+            timeline.write(&mut output).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        let trigger = r#"<path d="M1000,21 L1000,61""#;
+        assert!(render(&timeline).contains(trigger));
+        timeline.set_event_filter(|event| event.name() != "idle");
+        let svg = render(&timeline);
+        assert!(svg.contains("write"));
+        assert!(!svg.contains("idle"));
+        assert!(svg.contains("trigger-link"));
+        assert!(!svg.contains(trigger));
+        assert_eq!(timeline.rows().lanes(), 2);
+
+        timeline.set_category_filter(Some(vec!["CPU 0".to_string(), "CPU 2".to_string()]));
+        let svg = render(&timeline);
+        assert!(svg.contains("read"));
+        assert!(!svg.contains("write"));
+        assert!(!svg.contains("trigger-link"));
+
+        #[cfg(feature = "regex")]
+        {
+            timeline.clear_filters();
+            timeline.set_name_filter(Some(Regex::new("^(read|idle)$").unwrap()));
+            let svg = render(&timeline);
+            assert!(svg.contains("idle"));
+            assert!(!svg.contains("write"));
+        }
+
+        timeline.clear_filters();
+        assert_eq!(timeline.rows().lanes(), 3);
+        assert_eq!(timeline.events().count(), 3);
+    }
+}
@@ -0,0 +1,491 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+
+use crate::import::{new_timeline, sniff_text};
+use crate::{EventBuilder, Timeline, TimelineError, TraceImporter};
+
+// The text output of ftrace, see `Timeline::from_ftrace`
+pub(crate) struct FtraceImporter;
+
+impl TraceImporter for FtraceImporter {
+    fn format(&self) -> &str {
+        "ftrace"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        let text = sniff_text(data);
+        text.starts_with("# tracer:")
+            || text
+                .lines()
+                .find(|line| !line.starts_with('#') && !line.trim().is_empty())
+                .is_some_and(|line| parse_line(line).is_some())
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_ftrace(data)
+    }
+}
+
+// A line of ftrace output, like
+// "bash-1234 [001] d..3. 5.000100: sched_wakeup: comm=kworker/1:1 pid=56 target_cpu=001"
+#[derive(Debug, PartialEq)]
+pub(crate) struct Line<'a> {
+    pub(crate) comm: &'a str,
+    pub(crate) pid: &'a str,
+    pub(crate) cpu: u32,
+    pub(crate) time: i64,
+    // The name of the tracepoint, or the function of function tracer lines
+    pub(crate) event: &'a str,
+    pub(crate) args: &'a str,
+}
+
+// Parse a timestamp of seconds with up to nine decimals into nanoseconds, without going through
+// a float that would round the nanoseconds off. Times too large for nanoseconds in an i64 are
+// rejected.
+pub(crate) fn parse_time(text: &str) -> Option<i64> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(text) => (true, text),
+        None => (false, text),
+    };
+    let (seconds, fraction) = text.split_once('.').unwrap_or((text, ""));
+    if !seconds.bytes().all(|b| b.is_ascii_digit())
+        || fraction.len() > 9
+        || !fraction.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let nanos: i64 = format!("{:0<9}", fraction).parse().ok()?;
+    let time = seconds
+        .parse::<i64>()
+        .ok()?
+        .checked_mul(1_000_000_000)?
+        .checked_add(nanos)?;
+    // The sign is of the whole time, "-1.5" is a second and a half before 0
+    Some(if negative { -time } else { time })
+}
+
+pub(crate) fn parse_line(line: &str) -> Option<Line<'_>> {
+    // Task names can have spaces and dashes, but not the "[CPU]" that follows them
+    let open = line.find(" [")?;
+    let close = open + line[open..].find(']')?;
+    let mut task = line[..open].trim();
+    // Traces recorded with the tgid option have it in parentheses after the task
+    if task.ends_with(')') {
+        task = task[..task.rfind('(')?].trim_end();
+    }
+    let (comm, pid) = task.rsplit_once('-')?;
+    pid.parse::<u32>().ok()?;
+    let cpu = line[open + 2..close].parse().ok()?;
+
+    // The flags are optional, the timestamp is the first field ending with a colon
+    let rest = &line[close + 1..];
+    let colon = rest.find(": ")?;
+    let time = parse_time(rest[..colon].split_whitespace().last()?)?;
+    let rest = rest[colon + 2..].trim();
+    let (event, args) = match rest.split_once(": ") {
+        Some((event, args)) if !event.contains(' ') => (event, args),
+        // Function tracer lines are the function and its caller
+        _ => rest.split_once(' ').unwrap_or((rest, "")),
+    };
+    Some(Line {
+        comm: comm.trim(),
+        pid,
+        cpu,
+        time,
+        event,
+        args,
+    })
+}
+
+// The value of `key` in tracepoint arguments like "comm=kworker/1:1 pid=56", which runs until
+// the next argument, so values can have spaces
+pub(crate) fn arg<'a>(args: &'a str, key: &str) -> Option<&'a str> {
+    let start = args
+        .match_indices(&format!("{}=", key))
+        .find(|(index, _)| *index == 0 || args[..*index].ends_with(' '))
+        .map(|(index, _)| index + key.len() + 1)?;
+    let value = &args[start..];
+    let end = value
+        .match_indices(' ')
+        .find(|(index, _)| {
+            let next = &value[index + 1..];
+            next.starts_with("==>")
+                || next.split_once('=').is_some_and(|(key, _)| {
+                    key.bytes().all(|b| b.is_ascii_lowercase() || b == b'_')
+                })
+        })
+        .map_or(value.len(), |(index, _)| index);
+    Some(&value[..end])
+}
+
+// The row of a task, or of its CPU for the idle tasks, which don't get rows
+pub(crate) fn task_row(comm: &str, pid: &str, cpu: u32) -> String {
+    if pid == "0" {
+        format!("CPU {}", cpu)
+    } else {
+        format!("{}-{}", comm, pid)
+    }
+}
+
+// Builds the timeline of a kernel trace from its scheduler events, which have to be fed in time
+// order. The ftrace, trace-cmd, perf and CTF importers share it, so they all draw the same rows.
+pub(crate) struct SchedTimeline {
+    timeline: Timeline,
+    // The time of the last event
+    last: i64,
+    // The task running on every CPU, by CPU, as its comm, pid and when it started
+    running: HashMap<u32, (String, String, i64)>,
+    // The wakeups of tasks waiting to run, by pid, as the row of the waker, the time and the
+    // comm of the woken task
+    wakeups: HashMap<String, Vec<(String, i64, String)>>,
+    // The instants, as their name, time and row
+    instants: Vec<(String, i64, String)>,
+}
+
+impl Default for SchedTimeline {
+    fn default() -> Self {
+        SchedTimeline {
+            timeline: new_timeline(),
+            last: 0,
+            running: HashMap::new(),
+            wakeups: HashMap::new(),
+            instants: Vec::new(),
+        }
+    }
+}
+
+impl SchedTimeline {
+    // Any event, which the trace lasts at least until
+    pub(crate) fn event(&mut self, time: i64) {
+        self.last = self.last.max(time);
+    }
+
+    // A `sched_switch` on `cpu` to the task `next_pid`
+    pub(crate) fn switch(&mut self, cpu: u32, time: i64, next_comm: &str, next_pid: &str) {
+        self.event(time);
+        if let Some((comm, pid, start)) = self.running.remove(&cpu) {
+            self.run(cpu, &comm, &pid, (start, time));
+        }
+        self.running
+            .insert(cpu, (next_comm.to_string(), next_pid.to_string(), time));
+    }
+
+    // The row of the task running on `cpu`, for traces whose events don't say which task they
+    // happened in
+    #[cfg(feature = "ctf")]
+    pub(crate) fn running_row(&self, cpu: u32) -> String {
+        match self.running.get(&cpu) {
+            Some((comm, pid, _)) => task_row(comm, pid, cpu),
+            None => format!("CPU {}", cpu),
+        }
+    }
+
+    // A `sched_wakeup` of the task `pid` by the task on the row `from`
+    pub(crate) fn wakeup(&mut self, from: String, time: i64, comm: &str, pid: &str) {
+        self.event(time);
+        self.wakeups
+            .entry(pid.to_string())
+            .or_default()
+            .push((from, time, comm.to_string()));
+    }
+
+    // Something named `name` happening in the task on the row `from`, like a function call
+    pub(crate) fn instant(&mut self, from: String, time: i64, name: &str) {
+        self.event(time);
+        self.instants.push((name.to_string(), time, from));
+    }
+
+    // A line of ftrace output, of which the scheduler events and the function tracer lines
+    // are drawn
+    pub(crate) fn line(&mut self, line: &Line) {
+        let here = task_row(line.comm, line.pid, line.cpu);
+        match line.event {
+            "sched_switch" => {
+                if let (Some(comm), Some(pid)) =
+                    (arg(line.args, "next_comm"), arg(line.args, "next_pid"))
+                {
+                    self.switch(line.cpu, line.time, comm, pid);
+                }
+            }
+            "sched_wakeup" | "sched_wakeup_new" => {
+                if let (Some(comm), Some(pid)) = (arg(line.args, "comm"), arg(line.args, "pid")) {
+                    self.wakeup(here, line.time, comm, pid);
+                }
+            }
+            function if line.args.starts_with("<-") => self.instant(here, line.time, function),
+            _ => self.event(line.time),
+        }
+    }
+
+    // Add the events of `pid` running on `cpu` from `start` to `end`, and the triggers of the
+    // wakeups it waited for, for traces that say when tasks ran rather than when they switched
+    pub(crate) fn run(&mut self, cpu: u32, comm: &str, pid: &str, (start, end): (i64, i64)) {
+        self.event(end);
+        if pid == "0" {
+            return;
+        }
+        let row = task_row(comm, pid, cpu);
+        self.timeline.insert_event(
+            EventBuilder::new(comm, start, end, format!("CPU {}", cpu))
+                .metadata("pid".to_string(), pid.to_string())
+                .build(),
+        );
+        self.timeline.insert_event(
+            EventBuilder::new("running", start, end, row.clone())
+                .metadata("cpu".to_string(), cpu.to_string())
+                .build(),
+        );
+        for (from, time, _) in self.wakeups.remove(pid).unwrap_or_default() {
+            self.timeline
+                .add_trigger_span(from, time, row.clone(), start);
+        }
+    }
+
+    // The timeline, with the tasks still running when the trace ends running until its end
+    pub(crate) fn finish(mut self) -> Timeline {
+        let mut unfinished: Vec<_> = std::mem::take(&mut self.running).into_iter().collect();
+        unfinished.sort_by_key(|(cpu, _)| *cpu);
+        for (cpu, (comm, pid, start)) in unfinished {
+            self.run(cpu, &comm, &pid, (start, self.last));
+        }
+        let mut timeline = self.timeline;
+        // Tasks woken that never ran only get a row if something else gives them one
+        let mut waiting: Vec<_> = self.wakeups.into_iter().collect();
+        waiting.sort_by(|a, b| a.0.cmp(&b.0));
+        for (pid, wakeups) in waiting {
+            for (from, time, comm) in wakeups {
+                let to = format!("{}-{}", comm, pid);
+                if timeline.has_location(&from) && timeline.has_location(&to) {
+                    timeline.add_trigger(from, to, time);
+                }
+            }
+        }
+        for (name, time, location) in self.instants {
+            timeline.add_instant(name, time, location);
+        }
+        timeline
+    }
+}
+
+impl Timeline {
+    /// Read the text output of ftrace into a timeline
+    ///
+    /// This reads what `trace` and `trace_pipe` in the tracefs directory print, with or without
+    /// the irq flags and tgid columns. Every CPU gets a row named like "CPU 1", with an event
+    /// for every time a task runs on it, named after the task, and every task that runs gets a
+    /// row named like "bash-1234", with a "running" event for the same time, from the
+    /// `sched_switch` events. Tasks still running when the trace ends run until its last line.
+    /// `sched_wakeup` and `sched_wakeup_new` events become triggers from the row of the waker,
+    /// or of the CPU for wakeups from the idle task, to the row of the woken task, ending when
+    /// it next runs. Lines of the function tracer become instants named after the function on
+    /// the row of their task, or their CPU for the idle task. Other lines are skipped. Times
+    /// are in nanoseconds.
+    ///
+    /// This returns `TimelineError::Parse` if no line of the trace looks like ftrace output,
+    /// and `TimelineError::Io` if reading it fails. `Timeline::import` detects traces of this
+    /// format, as "ftrace".
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let trace = "\
+    ///     <idle>-0 [001] d..2. 5.000000: sched_switch: prev_comm=swapper/1 prev_pid=0 \
+    ///     prev_prio=120 prev_state=R ==> next_comm=bash next_pid=1234 next_prio=120\n\
+    ///     bash-1234 [001] d..2. 5.000010: sched_switch: prev_comm=bash prev_pid=1234 \
+    ///     prev_prio=120 prev_state=S ==> next_comm=swapper/1 next_pid=0 next_prio=120\n";
+    /// let timeline = Timeline::from_ftrace(trace.as_bytes()).unwrap();
+    /// assert_eq!(timeline.categories(), ["CPU 1", "bash-1234"]);
+    /// assert_eq!(timeline.time_range(), Some((5_000_000_000, 5_000_010_000)));
+    /// # }
+    /// ```
+    pub fn from_ftrace(reader: impl Read) -> Result<Timeline, TimelineError> {
+        let mut sched = SchedTimeline::default();
+        let mut parsed = false;
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            if line.starts_with('#') {
+                continue;
+            }
+            let Some(line) = parse_line(&line) else {
+                continue;
+            };
+            parsed = true;
+            sched.line(&line);
+        }
+        if !parsed {
+            return Err(TimelineError::Parse("no ftrace lines".to_string()));
+        }
+        Ok(sched.finish())
+    }
+
+    // Whether any event is on the row of `location`
+    pub(crate) fn has_location(&self, location: &str) -> bool {
+        self.events.iter().any(|event| event.location == location)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("5"), Some(5_000_000_000));
+        assert_eq!(parse_time("5.0001"), Some(5_000_100_000));
+        assert_eq!(parse_time("5.000000001"), Some(5_000_000_001));
+        // Rounding off nanoseconds would put events out of order
+        assert_eq!(parse_time("1.0000000001"), None);
+        assert_eq!(parse_time("1.5e3"), None);
+        assert_eq!(parse_time("-1.5"), Some(-1_500_000_000));
+        assert_eq!(parse_time("-0.25"), Some(-250_000_000));
+        assert_eq!(parse_time("9223372036.854775807"), Some(i64::MAX));
+        assert_eq!(parse_time("9223372036.854775808"), None);
+        assert_eq!(parse_time("99999999999.000000"), None);
+        assert_eq!(parse_time("--1"), None);
+        assert_eq!(parse_time("d..2."), None);
+    }
+
+    #[test]
+    fn test_parse_line() {
+        // Function tracer lines, with a tgid and a task name with spaces and dashes
+        assert_eq!(
+            parse_line("kworker/u8:2 x-77 (     77) [003] 12.5: do_sys_open <-do_syscall_64"),
+            Some(Line {
+                comm: "kworker/u8:2 x",
+                pid: "77",
+                cpu: 3,
+                time: 12_500_000_000,
+                event: "do_sys_open",
+                args: "<-do_syscall_64",
+            })
+        );
+        // Traces without the irq flags, and events without arguments
+        assert_eq!(
+            parse_line("  <idle>-0     [001]   5.000100: cpu_idle"),
+            Some(Line {
+                comm: "<idle>",
+                pid: "0",
+                cpu: 1,
+                time: 5_000_100_000,
+                event: "cpu_idle",
+                args: "",
+            })
+        );
+        assert_eq!(
+            parse_line("bash-10 [000] d..3. 1.0: sched_wakeup: comm=cat pid=11")
+                .map(|line| (line.event, line.args)),
+            Some(("sched_wakeup", "comm=cat pid=11"))
+        );
+        assert_eq!(parse_line("CPU:1 [LOST 3 EVENTS]"), None);
+        assert_eq!(
+            parse_line("bash [000] d..3. 1.0: sched_wakeup: comm=cat"),
+            None
+        );
+        assert_eq!(
+            parse_line("bash-10 [0x0] d..3. 1.0: sched_wakeup: comm=cat"),
+            None
+        );
+
+        // Values run until the next argument, so they can have spaces
+        assert_eq!(
+            arg("prev_comm=my task prev_pid=5 ==> next_comm=b", "prev_comm"),
+            Some("my task")
+        );
+        assert_eq!(
+            arg("prev_comm=a ==> next_comm=b c", "next_comm"),
+            Some("b c")
+        );
+        // Keys that end other keys aren't them
+        assert_eq!(arg("prev_comm=a next_comm=b", "comm"), None);
+        assert_eq!(arg("prev_comm=a comm=b", "comm"), Some("b"));
+        assert_eq!(arg("name=a=B pid=1", "name"), Some("a=B"));
+        assert_eq!(arg("", "pid"), None);
+        assert_eq!(task_row("bash", "10", 0), "bash-10");
+        assert_eq!(task_row("swapper/3", "0", 3), "CPU 3");
+    }
+
+    #[test]
+    fn test_from_ftrace() {
+        let trace = "# tracer: nop
+#
+          <idle>-0       [000] d..2.     1.000002: sched_switch: prev_comm=swapper/0 prev_pid=0 prev_prio=120 prev_state=R ==> next_comm=bash next_pid=10 next_prio=120
+            bash-10      [000] .....     1.000004: do_exit <-do_group_exit
+CPU:1 [LOST 3 EVENTS]
+            bash-10      [000] d..2.     1.000005: sched_switch: prev_comm=bash prev_pid=10 prev_prio=120 prev_state=S ==> next_comm=swapper/0 next_pid=0 next_prio=120
+          <idle>-0       [001] d..2.     1.000006: sched_switch: prev_comm=swapper/1 prev_pid=0 prev_prio=120 prev_state=R ==> next_comm=cat next_pid=11 next_prio=120
+          <idle>-0       [000] .....     1.000007: do_idle <-cpu_startup_entry
+             cat-11      [001] d..2.     1.000009: irq_handler_entry: irq=5 name=eth0
+";
+        assert!(FtraceImporter.sniff(trace.as_bytes()));
+        assert!(!FtraceImporter.sniff(b"# comment\nnot a trace\n"));
+        // Tasks still running run until the last line, and the idle task gets no events
+        let timeline = Timeline::from_ftrace(trace.as_bytes()).unwrap();
+        let events: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name(),
+                    event.location(),
+                    (event.start_time() - 1_000_000_000) / 1000,
+                    (event.end_time() - 1_000_000_000) / 1000,
+                )
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("bash", "CPU 0", 2, 5),
+                ("running", "bash-10", 2, 5),
+                ("cat", "CPU 1", 6, 9),
+                ("running", "cat-11", 6, 9),
+                ("do_exit", "bash-10", 4, 4),
+                ("do_idle", "CPU 0", 7, 7),
+            ]
+        );
+        let (_, bash) = timeline.events().next().unwrap();
+        assert_eq!(bash.metadata()["pid"], "10");
+        let (_, running) = timeline.events().nth(1).unwrap();
+        assert_eq!(running.metadata()["cpu"], "0");
+        assert!(timeline.write(Vec::new()).is_ok());
+        assert!(matches!(
+            Timeline::from_ftrace(&b"# tracer: nop\n#\n"[..]),
+            Err(TimelineError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_wakeups() {
+        let trace = "
+          <idle>-0       [000] d..2.     1.000000: sched_wakeup: comm=bash pid=10 prio=120 target_cpu=000
+          <idle>-0       [000] d..2.     1.000002: sched_switch: prev_comm=swapper/0 prev_pid=0 prev_prio=120 prev_state=R ==> next_comm=bash next_pid=10 next_prio=120
+            bash-10      [000] d..3.     1.000003: sched_wakeup_new: comm=cat pid=11 prio=120 target_cpu=001
+            bash-10      [000] d..3.     1.000004: sched_wakeup: comm=sleepy pid=12 prio=120 target_cpu=001
+            bash-10      [000] d..2.     1.000005: sched_switch: prev_comm=bash prev_pid=10 prev_prio=120 prev_state=S ==> next_comm=swapper/0 next_pid=0 next_prio=120
+          <idle>-0       [001] d..2.     1.000006: sched_switch: prev_comm=swapper/1 prev_pid=0 prev_prio=120 prev_state=R ==> next_comm=cat next_pid=11 next_prio=120
+";
+        // The output of trace_pipe has no header
+        assert!(FtraceImporter.sniff(trace.as_bytes()));
+        // Wakeups end when the woken task runs, and those of tasks that never run are dropped
+        let timeline = Timeline::from_ftrace(trace.as_bytes()).unwrap();
+        let triggers: Vec<_> = timeline
+            .triggers()
+            .map(|trigger| {
+                (
+                    trigger.start_location(),
+                    trigger.time() - 1_000_000_000,
+                    trigger.end_location(),
+                    trigger.end_time() - 1_000_000_000,
+                )
+            })
+            .collect();
+        assert_eq!(
+            triggers,
+            [
+                ("CPU 0", 0, "bash-10", 2000),
+                ("bash-10", 3000, "cat-11", 6000)
+            ]
+        );
+    }
+}
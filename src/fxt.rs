@@ -0,0 +1,640 @@
+use std::collections::{BTreeSet, HashMap};
+use std::io::Read;
+
+use crate::import::new_timeline;
+use crate::{EventBuilder, Timeline, TimelineError, TraceImporter};
+
+// The binary Fuchsia trace format, see `Timeline::from_fxt`
+pub(crate) struct FxtImporter;
+
+impl TraceImporter for FxtImporter {
+    fn format(&self) -> &str {
+        "fxt"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        data.starts_with(&MAGIC.to_le_bytes())
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_fxt(data)
+    }
+}
+
+// The magic number record that traces start with
+const MAGIC: u64 = 0x0016_5478_4604_0010;
+
+// `count` bits of `word` from bit `low` on
+fn bits(word: u64, low: u32, count: u32) -> u64 {
+    (word >> low) & ((1 << count) - 1)
+}
+
+// The words of a record, read from the front
+struct Record<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Record<'_> {
+    fn word(&mut self) -> Option<u64> {
+        let bytes = self.data.get(self.pos..self.pos + 8)?;
+        self.pos += 8;
+        Some(u64::from_le_bytes(bytes.try_into().ok()?))
+    }
+
+    // A string stored in the record, padded to whole words
+    fn inline(&mut self, len: usize) -> Option<String> {
+        let bytes = self.data.get(self.pos..self.pos + len)?;
+        self.pos += len.div_ceil(8) * 8;
+        Some(String::from_utf8_lossy(bytes).into_owned())
+    }
+}
+
+// Where an event is drawn, resolved into a row once the names of all threads are known
+#[derive(Clone, PartialEq)]
+enum Row {
+    // The process and thread koids
+    Thread(u64, u64),
+    Named(String),
+}
+
+struct Item {
+    name: String,
+    category: String,
+    start: i64,
+    end: i64,
+    row: Row,
+    instant: bool,
+    args: Vec<(String, String)>,
+}
+
+// An argument of a record as text, and as a number if it is one
+type Arg = (String, String, Option<f64>);
+
+#[derive(Default)]
+struct State {
+    ticks_per_second: Option<u64>,
+    strings: HashMap<u64, String>,
+    threads: HashMap<u64, (u64, u64)>,
+    // The names of threads and processes, by koid
+    names: HashMap<u64, String>,
+}
+
+impl State {
+    fn nanos(&self, ticks: u64) -> i64 {
+        let per_second = self.ticks_per_second.unwrap_or(1_000_000_000).max(1);
+        (ticks as i128 * 1_000_000_000 / per_second as i128) as i64
+    }
+
+    // The string of a string reference, which is empty, inline, or an index into the table
+    fn string(&self, record: &mut Record, reference: u64) -> Option<String> {
+        match reference {
+            0 => Some(String::new()),
+            inline if inline & 0x8000 != 0 => record.inline((inline & 0x7fff) as usize),
+            index => Some(self.strings.get(&index).cloned().unwrap_or_default()),
+        }
+    }
+
+    // The process and thread koids of a thread reference, which is inline or an index
+    fn thread(&self, record: &mut Record, reference: u64) -> Option<(u64, u64)> {
+        match reference {
+            0 => Some((record.word()?, record.word()?)),
+            index => self.threads.get(&index).copied(),
+        }
+    }
+
+    fn args(&self, record: &mut Record, count: u64) -> Option<Vec<Arg>> {
+        let mut args = Vec::new();
+        for _ in 0..count {
+            let start = record.pos;
+            let header = record.word()?;
+            let name = self.string(record, bits(header, 16, 16))?;
+            let number = |value: f64| Some((value.to_string(), Some(value)));
+            let value = match bits(header, 0, 4) {
+                1 => number(bits(header, 32, 32) as u32 as i32 as f64),
+                2 => number(bits(header, 32, 32) as f64),
+                3 => number(record.word()? as i64 as f64),
+                4 => number(record.word()? as f64),
+                5 => number(f64::from_bits(record.word()?)),
+                6 => Some((self.string(record, bits(header, 32, 16))?, None)),
+                7 => Some((format!("{:#x}", record.word()?), None)),
+                8 => Some((record.word()?.to_string(), None)),
+                9 => {
+                    let value = bits(header, 32, 1) == 1;
+                    Some((value.to_string(), Some(value as u8 as f64)))
+                }
+                _ => None,
+            };
+            if let Some((text, number)) = value {
+                args.push((name, text, number));
+            }
+            // Skip what wasn't read of arguments of unknown types
+            record.pos = start + bits(header, 4, 12) as usize * 8;
+        }
+        Some(args)
+    }
+}
+
+// What the event records of a trace add up to
+#[derive(Default)]
+struct Events {
+    items: Vec<Item>,
+    // The duration events begun and not ended yet, by thread
+    begun: HashMap<(u64, u64), Vec<Item>>,
+    // The async events begun and not ended yet, by category, name and id
+    started: HashMap<(String, String, u64), Item>,
+    // The last point of every flow, by id, as its row and time
+    flows: HashMap<u64, (Row, i64)>,
+    triggers: Vec<(Row, i64, Row, i64)>,
+    samples: Vec<(String, i64, f64)>,
+    last: i64,
+}
+
+impl Events {
+    fn event(&mut self, state: &State, header: u64, record: &mut Record) -> Option<()> {
+        let time = state.nanos(record.word()?);
+        let thread = state.thread(record, bits(header, 24, 8))?;
+        let category = state.string(record, bits(header, 32, 16))?;
+        let name = state.string(record, bits(header, 48, 16))?;
+        let args = state.args(record, bits(header, 20, 4))?;
+        self.last = self.last.max(time);
+        let item = |end: i64, row: Row, instant: bool| Item {
+            name: name.clone(),
+            category: category.clone(),
+            start: time,
+            end,
+            row,
+            instant,
+            args: args
+                .iter()
+                .map(|(key, text, _)| (key.clone(), text.clone()))
+                .collect(),
+        };
+        let here = Row::Thread(thread.0, thread.1);
+        match bits(header, 16, 4) {
+            0 => self.items.push(item(time, here, true)),
+            1 => {
+                for (key, _, value) in &args {
+                    if let Some(value) = value {
+                        self.samples
+                            .push((format!("{}.{}", name, key), time, *value));
+                    }
+                }
+            }
+            2 => self
+                .begun
+                .entry(thread)
+                .or_default()
+                .push(item(time, here, false)),
+            3 => {
+                let mut begun = self.begun.get_mut(&thread)?.pop()?;
+                begun.end = time;
+                begun.args.extend(item(time, here, false).args);
+                self.items.push(begun);
+            }
+            4 => {
+                let end = state.nanos(record.word()?);
+                self.last = self.last.max(end);
+                self.items.push(item(end, here, false));
+            }
+            5 => {
+                let id = record.word()?;
+                let started = item(time, Row::Named(name.clone()), false);
+                self.started
+                    .insert((category.clone(), name.clone(), id), started);
+            }
+            6 => self.items.push(item(time, Row::Named(name.clone()), true)),
+            7 => {
+                let id = record.word()?;
+                let mut started = self.started.remove(&(category.clone(), name.clone(), id))?;
+                started.end = time;
+                self.items.push(started);
+            }
+            kind @ 8..=10 => {
+                let id = record.word()?;
+                if let Some((row, from)) = self.flows.remove(&id) {
+                    self.triggers.push((row, from, here.clone(), time));
+                }
+                if kind != 10 {
+                    self.flows.insert(id, (here, time));
+                }
+            }
+            _ => {}
+        }
+        Some(())
+    }
+}
+
+impl Timeline {
+    /// Read a trace in the Fuchsia trace format into a timeline
+    ///
+    /// This reads the binary FXT traces that Fuchsia records, and that Perfetto and other tracers
+    /// write too. Every thread gets a row, named after its kernel object record or its process and
+    /// thread koids like "1234/5678", and prefixed with the name of its process if the trace has
+    /// more than one. Duration events, begun and ended or complete, become events on the row of
+    /// their thread, and instant events become instants there. Async events become events on a row
+    /// named after them. Flow events become triggers from each step of a flow to the next, between
+    /// threads that have events. Counter events become samples of counters named after the event
+    /// and the argument, like "memory.rss". The category and arguments of events are their
+    /// "category" and other metadata. Durations that don't end last until the end of the trace.
+    /// Times are in nanoseconds, converted from ticks with the rate of the initialization record.
+    /// Other records are skipped.
+    ///
+    /// This returns `TimelineError::Parse` if the trace doesn't start with the magic number
+    /// record or has no events, and `TimelineError::Io` if reading it fails. `Timeline::import`
+    /// detects traces of this format, as "fxt".
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// // The magic number record, and a complete event of thread 2 of process 1 from 100 to 300
+    /// // ticks, named "draw" inline
+    /// let words = [0x0016_5478_4604_0010, 0x8004_0000_0004_0064, 100, 1, 2, 0x7761_7264, 300];
+    /// let trace: Vec<u8> = words.iter().flat_map(|word: &u64| word.to_le_bytes()).collect();
+    /// let timeline = Timeline::from_fxt(&trace[..]).unwrap();
+    /// assert_eq!(timeline.categories(), ["1/2"]);
+    /// assert_eq!(timeline.time_range(), Some((100, 300)));
+    /// # }
+    /// ```
+    pub fn from_fxt(mut reader: impl Read) -> Result<Timeline, TimelineError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        if !data.starts_with(&MAGIC.to_le_bytes()) {
+            return Err(TimelineError::Parse(
+                "no FXT magic number record".to_string(),
+            ));
+        }
+        let mut state = State::default();
+        let mut events = Events::default();
+        let mut pos = 0;
+        while pos + 8 <= data.len() {
+            let header = u64::from_le_bytes(data[pos..pos + 8].try_into().unwrap_or_default());
+            let kind = bits(header, 0, 4);
+            // Large records have a wider size field
+            let size = match kind {
+                15 => bits(header, 4, 32),
+                _ => bits(header, 4, 12),
+            } as usize;
+            if size == 0 {
+                return Err(TimelineError::Parse(format!("empty record at {}", pos)));
+            }
+            let end = (pos + size * 8).min(data.len());
+            let mut record = Record {
+                data: &data[pos..end],
+                pos: 8,
+            };
+            match kind {
+                1 => state.ticks_per_second = record.word(),
+                2 => {
+                    let index = bits(header, 16, 15);
+                    if let Some(string) = record.inline(bits(header, 32, 15) as usize) {
+                        state.strings.insert(index, string);
+                    }
+                }
+                3 => {
+                    if let (Some(process), Some(thread)) = (record.word(), record.word()) {
+                        state.threads.insert(bits(header, 16, 8), (process, thread));
+                    }
+                }
+                4 => {
+                    events.event(&state, header, &mut record);
+                }
+                // Kernel objects, which name the processes and threads
+                7 => {
+                    let koid = record.word();
+                    let name = state.string(&mut record, bits(header, 24, 16));
+                    if let (Some(koid), Some(name)) = (koid, name) {
+                        state.names.insert(koid, name);
+                    }
+                }
+                _ => {}
+            }
+            pos += size * 8;
+        }
+        if events.items.is_empty() && events.begun.values().all(Vec::is_empty) {
+            return Err(TimelineError::Parse("no FXT events".to_string()));
+        }
+
+        let mut unfinished: Vec<_> = std::mem::take(&mut events.begun).into_iter().collect();
+        unfinished.sort_by_key(|(thread, _)| *thread);
+        for (_, begun) in unfinished {
+            for mut item in begun {
+                item.end = events.last;
+                events.items.push(item);
+            }
+        }
+        let processes: BTreeSet<u64> = events
+            .items
+            .iter()
+            .filter_map(|item| match item.row {
+                Row::Thread(process, _) => Some(process),
+                Row::Named(_) => None,
+            })
+            .collect();
+        let location = |row: &Row| match row {
+            Row::Named(name) => name.clone(),
+            Row::Thread(process, thread) => {
+                let name = state
+                    .names
+                    .get(thread)
+                    .cloned()
+                    .unwrap_or_else(|| format!("{}/{}", process, thread));
+                match state.names.get(process) {
+                    Some(process) if processes.len() > 1 => format!("{}/{}", process, name),
+                    _ => name,
+                }
+            }
+        };
+
+        let mut timeline = new_timeline();
+        for item in events.items {
+            let mut event = EventBuilder::new(item.name, item.start, item.end, location(&item.row));
+            if !item.category.is_empty() {
+                event = event.metadata("category".to_string(), item.category);
+            }
+            for (key, value) in item.args {
+                event = event.metadata(key, value);
+            }
+            let mut event = event.build();
+            event.instant = item.instant;
+            timeline.insert_event(event);
+        }
+        for (from, start, to, end) in events.triggers {
+            let (from, to) = (location(&from), location(&to));
+            if timeline.has_location(&from) && timeline.has_location(&to) {
+                timeline.add_trigger_span(from, start, to, end);
+            }
+        }
+        for (counter, time, value) in events.samples {
+            timeline.add_counter_sample(counter, time, value);
+        }
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Encodes records for the tests
+    #[derive(Default)]
+    struct Writer(Vec<u64>);
+
+    impl Writer {
+        fn record(&mut self, kind: u64, fields: u64, payload: &[u64]) {
+            let size = 1 + payload.len() as u64;
+            self.0.push(kind | size << 4 | fields << 16);
+            self.0.extend(payload);
+        }
+
+        fn text(text: &str) -> Vec<u64> {
+            text.as_bytes()
+                .chunks(8)
+                .map(|chunk| {
+                    let mut word = [0; 8];
+                    word[..chunk.len()].copy_from_slice(chunk);
+                    u64::from_le_bytes(word)
+                })
+                .collect()
+        }
+
+        fn bytes(&self) -> Vec<u8> {
+            self.0.iter().flat_map(|word| word.to_le_bytes()).collect()
+        }
+    }
+
+    // The fields of an event record of a type, with a number of arguments, on a thread, in the
+    // category "gfx" and with a name
+    fn event(kind: u64, args: u64, thread: u64, name: u64) -> u64 {
+        kind | args << 4 | thread << 8 | 1 << 16 | name << 32
+    }
+
+    // A trace with the strings 1 "gfx" and 2 "draw", and thread 1, which is 5678 of process 1234
+    fn trace() -> Writer {
+        let mut trace = Writer::default();
+        trace.0.push(MAGIC);
+        trace.record(2, 1 | 3 << 16, &Writer::text("gfx"));
+        trace.record(2, 2 | 4 << 16, &Writer::text("draw"));
+        trace.record(3, 1, &[1234, 5678]);
+        trace
+    }
+
+    fn events(timeline: &Timeline) -> Vec<(&str, &str, i64, i64, bool)> {
+        timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name(),
+                    event.location(),
+                    event.start_time(),
+                    event.end_time(),
+                    event.instant,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_args() {
+        assert_eq!(bits(0xabcd, 4, 8), 0xbc);
+        assert_eq!(bits(MAGIC, 0, 4), 0);
+        assert_eq!(bits(u64::MAX, 32, 32), 0xffff_ffff);
+
+        let mut state = State::default();
+        for (index, name) in ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"]
+            .iter()
+            .enumerate()
+        {
+            state.strings.insert(index as u64 + 1, name.to_string());
+        }
+        let mut words = vec![
+            1 | 1 << 4 | 1 << 16 | (-5i32 as u32 as u64) << 32,
+            2 | 1 << 4 | 2 << 16 | 7 << 32,
+            3 | 2 << 4 | 3 << 16,
+            -1i64 as u64,
+            4 | 2 << 4 | 4 << 16,
+            1 << 40,
+            5 | 2 << 4 | 5 << 16,
+            0.25f64.to_bits(),
+            6 | 2 << 4 | 6 << 16 | (0x8000 | 2) << 32,
+        ];
+        words.extend(Writer::text("hi"));
+        words.extend([7 | 2 << 4 | 7 << 16, 0xff, 8 | 2 << 4 | 8 << 16, 42]);
+        words.push(9 | 1 << 4 | 9 << 16 | 1 << 32);
+        // Arguments of unknown types are skipped by their size
+        words.extend([12 | 3 << 4 | 10 << 16, 1, 2]);
+        words.push(2 | 1 << 4 | 1 << 16 | 3 << 32);
+        let data = Writer(words).bytes();
+        let mut record = Record {
+            data: &data,
+            pos: 0,
+        };
+        let args = state.args(&mut record, 11).unwrap();
+        let args: Vec<_> = args
+            .iter()
+            .map(|(name, text, number)| (name.as_str(), text.as_str(), *number))
+            .collect();
+        assert_eq!(
+            args,
+            [
+                ("a", "-5", Some(-5.0)),
+                ("b", "7", Some(7.0)),
+                ("c", "-1", Some(-1.0)),
+                ("d", "1099511627776", Some(1099511627776.0)),
+                ("e", "0.25", Some(0.25)),
+                ("f", "hi", None),
+                ("g", "0xff", None),
+                ("h", "42", None),
+                ("i", "true", Some(1.0)),
+                ("a", "3", Some(3.0)),
+            ]
+        );
+        assert_eq!(record.pos, data.len());
+        // Arguments cut off by the end of their record
+        let mut record = Record {
+            data: &data[..20],
+            pos: 0,
+        };
+        assert!(state.args(&mut record, 3).is_none());
+    }
+
+    #[test]
+    fn test_from_fxt() {
+        let mut fxt = trace();
+        // 1000 ticks per second, so a tick is a millisecond
+        fxt.record(1, 0, &[1000]);
+        let mut name = vec![5678];
+        name.extend(Writer::text("render"));
+        fxt.record(7, 2 | (0x8000 | 6) << 8, &name);
+        // Begins "draw" with an int32 argument "frame" of 7, inline
+        let mut payload = vec![1, 1 | 2 << 4 | (0x8000 | 5) << 16 | 7 << 32];
+        payload.extend(Writer::text("frame"));
+        fxt.record(4, event(2, 1, 1, 2), &payload);
+        // A complete "layout" inside it, an instant, then the end of "draw"
+        let mut payload = vec![2];
+        payload.extend(Writer::text("layout"));
+        payload.push(3);
+        fxt.record(4, event(4, 0, 1, 0x8000 | 6), &payload);
+        fxt.record(4, event(0, 0, 1, 2), &[3]);
+        fxt.record(4, event(3, 0, 1, 2), &[5]);
+        // An unfinished duration, which lasts until the end of the trace
+        fxt.record(4, event(2, 0, 1, 2), &[6]);
+        fxt.record(4, event(0, 0, 1, 2), &[7]);
+
+        assert!(FxtImporter.sniff(&fxt.bytes()));
+        let timeline = Timeline::from_fxt(&fxt.bytes()[..]).unwrap();
+        assert_eq!(
+            events(&timeline),
+            [
+                ("layout", "render", 2_000_000, 3_000_000, false),
+                ("draw", "render", 3_000_000, 3_000_000, true),
+                ("draw", "render", 1_000_000, 5_000_000, false),
+                ("draw", "render", 7_000_000, 7_000_000, true),
+                ("draw", "render", 6_000_000, 7_000_000, false),
+            ]
+        );
+        let (_, draw) = timeline.events().nth(2).unwrap();
+        assert_eq!(draw.metadata()["category"], "gfx");
+        assert_eq!(draw.metadata()["frame"], "7");
+
+        // Async events are drawn on rows named after them, and ones that don't end aren't
+        let mut spans = trace();
+        spans.record(4, event(5, 0, 1, 2), &[10, 7]);
+        spans.record(4, event(5, 0, 1, 2), &[12, 8]);
+        spans.record(4, event(6, 0, 1, 2), &[15]);
+        spans.record(4, event(7, 0, 1, 2), &[20, 7]);
+        spans.record(4, event(7, 0, 1, 2), &[21, 9]);
+        let timeline = Timeline::from_fxt(&spans.bytes()[..]).unwrap();
+        assert_eq!(
+            events(&timeline),
+            [
+                ("draw", "draw", 15, 15, true),
+                ("draw", "draw", 10, 20, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_flows_and_counters() {
+        let mut trace = trace();
+        trace.record(4, event(0, 0, 1, 2), &[1]);
+        // A flow from thread 1 to an inline thread 9 of process 1234, which has an instant, and
+        // a flow to a thread without events
+        trace.record(4, event(8, 0, 1, 2), &[4, 42]);
+        trace.record(4, event(10, 0, 0, 2), &[6, 1234, 9, 42]);
+        trace.record(4, event(0, 0, 0, 2), &[6, 1234, 9]);
+        trace.record(4, event(8, 0, 1, 2), &[7, 43]);
+        trace.record(4, event(10, 0, 0, 2), &[8, 1234, 10, 43]);
+        // A counter with a double and a string argument, which isn't a sample
+        let mut payload = vec![9, 5 | 2 << 4 | (0x8000 | 3) << 16];
+        payload.extend(Writer::text("rss"));
+        payload.push(2.5f64.to_bits());
+        payload.push(6 | 2 << 4 | 1 << 16 | (0x8000 | 2) << 32);
+        payload.extend(Writer::text("hi"));
+        trace.record(4, event(1, 2, 1, 2), &payload);
+
+        let timeline = Timeline::from_fxt(&trace.bytes()[..]).unwrap();
+        let triggers: Vec<_> = timeline
+            .triggers()
+            .map(|trigger| {
+                (
+                    trigger.start_location(),
+                    trigger.time(),
+                    trigger.end_location(),
+                    trigger.end_time(),
+                )
+            })
+            .collect();
+        assert_eq!(triggers, [("1234/5678", 4, "1234/9", 6)]);
+        assert_eq!(timeline.counters["draw.rss"], [(9, 2.5)]);
+        assert!(!timeline.counters.contains_key("draw.gfx"));
+    }
+
+    #[test]
+    fn test_threads() {
+        // With more than one process, threads are prefixed with the name of their process
+        let mut processes = trace();
+        processes.record(2, 3 | 3 << 16, &Writer::text("app"));
+        processes.record(2, 4 | 4 << 16, &Writer::text("main"));
+        processes.record(2, 5 | 5 << 16, &Writer::text("other"));
+        processes.record(7, 1 | 3 << 8, &[1234]);
+        processes.record(7, 2 | 4 << 8, &[5678]);
+        processes.record(7, 1 | 5 << 8, &[3]);
+        processes.record(3, 2, &[3, 4]);
+        processes.record(4, event(0, 0, 1, 2), &[1]);
+        processes.record(4, event(0, 0, 2, 2), &[2]);
+        let timeline = Timeline::from_fxt(&processes.bytes()[..]).unwrap();
+        assert_eq!(timeline.categories(), ["app/main", "other/3/4"]);
+
+        let mut ticks = trace();
+        ticks.record(1, 0, &[3]);
+        ticks.record(4, event(0, 0, 1, 2), &[1]);
+        // Events of threads that weren't declared are skipped
+        ticks.record(4, event(0, 0, 5, 2), &[2]);
+        let timeline = Timeline::from_fxt(&ticks.bytes()[..]).unwrap();
+        assert_eq!(timeline.time_range(), Some((333_333_333, 333_333_333)));
+        assert_eq!(timeline.events().count(), 1);
+    }
+
+    #[test]
+    fn test_from_fxt_errors() {
+        let error = |data: Vec<u8>| match Timeline::from_fxt(&data[..]) {
+            Err(TimelineError::Parse(what)) => what,
+            other => panic!("{:?}", other.map(|_| ())),
+        };
+        // The magic number is little endian
+        assert!(!FxtImporter.sniff(&MAGIC.to_be_bytes()));
+        assert_eq!(error(vec![0; 8]), "no FXT magic number record");
+        assert_eq!(error(trace().bytes()), "no FXT events");
+        let mut empty = trace();
+        empty.0.push(4);
+        assert_eq!(error(empty.bytes()), "empty record at 64");
+        // Records cut off by the end of the trace are read as far as they go
+        let mut cut = trace();
+        cut.record(4, event(4, 0, 1, 2), &[1, 2]);
+        cut.0.pop();
+        assert_eq!(error(cut.bytes()), "no FXT events");
+    }
+}
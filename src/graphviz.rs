@@ -0,0 +1,146 @@
+use std::io::Write;
+
+use crate::{TimeValue, Timeline, TimelineError, TimelineExporter};
+
+// The Graphviz graph of the triggers of a timeline, see `Timeline::export_dot`
+pub(crate) struct DotExporter;
+
+impl TimelineExporter for DotExporter {
+    fn format(&self) -> &str {
+        "dot"
+    }
+
+    fn export(&self, timeline: &Timeline, writer: &mut dyn Write) -> Result<(), TimelineError> {
+        timeline.export_dot(writer)
+    }
+}
+
+// A DOT string, which keeps line breaks as escapes
+fn quoted(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => {}
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl<T: TimeValue> Timeline<T> {
+    /// Write the triggers of the timeline as a Graphviz DOT graph
+    ///
+    /// Where the timeline shows when things happened, the graph shows what caused what: every
+    /// row is a node, and triggers, and the connectors of `add_trigger_between` and `add_link`,
+    /// are edges from the row they start on to the one they end on. Connectors between the same
+    /// rows with the same label are drawn as one edge, labeled with the label of the links and
+    /// the number of connectors if there is more than one, so a trace with thousands of wakeups
+    /// still makes a readable graph. Render it with `dot -Tsvg`, for example. This is the same
+    /// as `export("dot")`.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// let write = timeline.add_event("write", 0, 2, "client");
+    /// let handle = timeline.add_event("handle", 5, 9, "server");
+    /// timeline.add_link(write, handle, "request".to_string());
+    /// let mut output = Vec::new();
+    /// timeline.export_dot(&mut output).unwrap();
+    /// let dot = String::from_utf8(output).unwrap();
+    /// assert!(dot.contains(r#"n0 -> n1 [label="request"];"#));
+    /// # }
+    /// ```
+    pub fn export_dot<W: Write>(&self, mut writer: W) -> Result<(), TimelineError> {
+        let categories = self.categories();
+        let node = |location: &str| categories.iter().position(|category| *category == location);
+
+        let mut connectors: Vec<(&str, &str, &str)> = self
+            .triggers
+            .iter()
+            .map(|trigger| {
+                (
+                    trigger.start_location.as_str(),
+                    trigger.end_location.as_str(),
+                    "",
+                )
+            })
+            .collect();
+        for link in &self.links {
+            if let (Some(from), Some(to)) = (self.event(link.from), self.event(link.to)) {
+                connectors.push((
+                    &from.location,
+                    &to.location,
+                    link.label.as_deref().unwrap_or_default(),
+                ));
+            }
+        }
+        // The edges in the order they first appear, with how many connectors they stand for
+        let mut edges: Vec<(usize, usize, &str, usize)> = Vec::new();
+        for (from, to, label) in connectors {
+            let (Some(from), Some(to)) = (node(from), node(to)) else {
+                continue;
+            };
+            match edges
+                .iter_mut()
+                .find(|edge| (edge.0, edge.1, edge.2) == (from, to, label))
+            {
+                Some(edge) => edge.3 += 1,
+                None => edges.push((from, to, label, 1)),
+            }
+        }
+
+        writeln!(writer, "digraph timeline {{")?;
+        writeln!(writer, "  node [shape=box];")?;
+        for (index, category) in categories.iter().enumerate() {
+            writeln!(writer, "  n{} [label={}];", index, quoted(category))?;
+        }
+        for (from, to, label, count) in edges {
+            let label = match (label, count) {
+                ("", 1) => String::new(),
+                ("", count) => count.to_string(),
+                (label, 1) => label.to_string(),
+                (label, count) => format!("{} ({})", label, count),
+            };
+            if label.is_empty() {
+                writeln!(writer, "  n{} -> n{};", from, to)?;
+            } else {
+                writeln!(writer, "  n{} -> n{} [label={}];", from, to, quoted(&label))?;
+            }
+        }
+        writeln!(writer, "}}")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_dot() {
+        let mut timeline = Timeline::default();
+        let sleep = timeline.add_event("sleep", 0, 10, "CPU \"0\"");
+        let run = timeline.add_event("run", 12, 20, "CPU 1");
+        timeline.add_event("idle", 0, 20, "CPU 2");
+        timeline.add_trigger("CPU \"0\"", "CPU 1", 3);
+        timeline.add_trigger("CPU \"0\"", "CPU 1", 5);
+        timeline.add_trigger_between(run, sleep);
+        timeline.add_link(sleep, run, "wakes".to_string());
+
+        let mut output = Vec::new();
+        timeline.export("dot", &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "digraph timeline {\n  node [shape=box];\n  n0 [label=\"CPU \\\"0\\\"\"];\n\
+             \x20 n1 [label=\"CPU 1\"];\n  n2 [label=\"CPU 2\"];\n  n0 -> n1 [label=\"2\"];\n\
+             \x20 n1 -> n0;\n  n0 -> n1 [label=\"wakes\"];\n}\n"
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{TimeValue, Timeline};
+
+// How far the row labels of a group are indented from its header, in pixels
+pub(crate) const GROUP_INDENT: f64 = 12.0;
+
+impl<T: TimeValue> Timeline<T> {
+    /// Put the row of `category` in `group`
+    ///
+    /// The rows of a group are drawn together below a header with the name of the group, with a
+    /// line separating them from the rows above, and their labels are indented, see
+    /// `set_row_labels`. Rows without a group come first, followed by the groups, sorted like the
+    /// rows are. A category is in at most one group, so adding it to another group moves it.
+    /// Rows are identified by their location, so rows of different groups need different
+    /// locations, like "node-a/CPU 0" and "node-b/CPU 0".
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// for node in ["node-a", "node-b"] {
+    ///     for cpu in 0..4 {
+    ///         let location = format!("{}/CPU {}", node, cpu);
+    ///         timeline.add_event("run".to_string(), cpu, cpu + 1, location.clone());
+    ///         timeline.add_category_to_group(location, node.to_string());
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn add_category_to_group(&mut self, category: impl Into<String>, group: impl Into<String>) {
+        self.category_groups.insert(category.into(), group.into());
+        self.invalidate_layout();
+    }
+
+    // The group of a category, if it is in one, which is the group of its topmost parent for
+    // child rows
+    pub(crate) fn category_group(&self, category: &str) -> Option<&str> {
+        let root = self.category_ancestors(category).last()?;
+        self.category_groups.get(root).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_category_groups() {
+        let mut timeline = Timeline::default();
+        for location in ["b/CPU 0", "a/CPU 1", "a/CPU 0", "IO"] {
+            timeline.add_event("Event".to_string(), 0, 1, location.to_string());
+        }
+        timeline.add_category_to_group("a/CPU 0".to_string(), "node-a".to_string());
+        timeline.add_category_to_group("a/CPU 1".to_string(), "node-a".to_string());
+        timeline.add_category_to_group("b/CPU 0".to_string(), "node-b".to_string());
+        assert_eq!(
+            timeline.categories(),
+            ["IO", "a/CPU 0", "a/CPU 1", "b/CPU 0"]
+        );
+
+        // Every group takes a row for its header
+        let rows = timeline.rows();
+        assert_eq!(rows.lanes(), 6);
+        assert_eq!(
+            rows.headers().collect::<Vec<_>>(),
+            [("node-a", 1), ("node-b", 4)]
+        );
+        assert_eq!(timeline.category_y("a/CPU 0", rows).unwrap(), 61.0);
+
+        timeline.set_row_labels(Some(80));
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert_eq!(svg.matches("class=\"group-header\"").count(), 2);
+        assert_eq!(svg.matches("class=\"group-separator\"").count(), 2);
+        assert!(svg.contains(r#"class="row-label" fill="black" font-size="10" x="-64""#));
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashSet;
+
+use crate::{TimeValue, Timeline};
+
+impl<T: TimeValue> Timeline<T> {
+    /// Make the row of `child` a child of the row of `parent`
+    ///
+    /// Child rows are drawn right below their parent, sorted like the rows are, and their labels
+    /// are indented one step deeper, see `set_row_labels`. Children can have children of their
+    /// own, like a process with threads with coroutines. The parent row is drawn as long as any
+    /// of its descendants has events, even without events of its own, and children are drawn in
+    /// the group of their topmost parent, see `add_category_to_group`.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("read".to_string(), 0, 5, "thread 1235".to_string());
+    /// timeline.add_event("write".to_string(), 3, 9, "thread 1236".to_string());
+    /// for thread in ["thread 1235", "thread 1236"] {
+    ///     timeline.set_category_parent(thread.to_string(), "process 1234".to_string());
+    /// }
+    /// timeline.set_parent_summary(true);
+    /// assert_eq!(
+    ///     timeline.categories(),
+    ///     ["process 1234", "thread 1235", "thread 1236"]
+    /// );
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This panics if `parent` is `child` or one of its descendants.
+    pub fn set_category_parent(&mut self, child: impl Into<String>, parent: impl Into<String>) {
+        let (child, parent) = (child.into(), parent.into());
+        assert!(
+            !self.category_ancestors(&parent).any(|c| c == child),
+            "row {:?} can't be a parent of its ancestor {:?}",
+            parent,
+            child
+        );
+        self.category_parents.insert(child, parent);
+        self.invalidate_layout();
+    }
+
+    /// Draw the activity of the descendants of a row on the row itself
+    ///
+    /// With `summary` set, every row with children is shaded in gray wherever one of its
+    /// descendants has an event, so collapsed views of the parent rows still show when they
+    /// were busy. The default is false.
+    pub fn set_parent_summary(&mut self, summary: bool) {
+        self.parent_summary = summary;
+    }
+
+    // The category and its ancestors, from the category up
+    pub(crate) fn category_ancestors<'a>(
+        &'a self,
+        category: &'a str,
+    ) -> impl Iterator<Item = &'a str> + 'a {
+        std::iter::successors(Some(category), |c| {
+            self.category_parents.get(*c).map(String::as_str)
+        })
+    }
+
+    // The number of ancestors of a category
+    pub(crate) fn category_depth(&self, category: &str) -> usize {
+        self.category_ancestors(category).count() - 1
+    }
+
+    // Whether a category is `ancestor` or one of its descendants
+    pub(crate) fn is_descendant(&self, category: &str, ancestor: &str) -> bool {
+        self.category_ancestors(category).any(|c| c == ancestor)
+    }
+
+    // Add the ancestors of the categories and put children right after their parents, keeping
+    // the order of `categories` otherwise
+    pub(crate) fn nest_categories<'a>(&'a self, categories: Vec<&'a str>) -> Vec<&'a str> {
+        if self.category_parents.is_empty() {
+            return categories;
+        }
+        let mut all: Vec<&str> = categories
+            .iter()
+            .flat_map(|category| self.category_ancestors(category))
+            .collect();
+        self.sort_categories(&mut all);
+        let mut nested = Vec::with_capacity(all.len());
+        let mut visited = HashSet::new();
+        for &category in &all {
+            if !self.category_parents.contains_key(category) {
+                self.nest(category, &all, &mut visited, &mut nested);
+            }
+        }
+        nested
+    }
+
+    // Add `category` and its descendants among `all`, in the order of `all`
+    fn nest<'a>(
+        &self,
+        category: &'a str,
+        all: &[&'a str],
+        visited: &mut HashSet<&'a str>,
+        nested: &mut Vec<&'a str>,
+    ) {
+        if !visited.insert(category) {
+            return;
+        }
+        nested.push(category);
+        for &child in all {
+            if self.category_parents.get(child).map(String::as_str) == Some(category) {
+                self.nest(child, all, visited, nested);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_category_hierarchy() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event".to_string(), 0, 2, "b thread".to_string());
+        timeline.add_event("Event".to_string(), 1, 3, "a thread".to_string());
+        timeline.add_event("Event".to_string(), 5, 6, "coroutine".to_string());
+        timeline.add_event("Event".to_string(), 0, 6, "idle".to_string());
+        timeline.add_event("Event".to_string(), 0, 6, "zzz".to_string());
+        timeline.set_category_parent("b thread".to_string(), "process".to_string());
+        timeline.set_category_parent("a thread".to_string(), "process".to_string());
+        timeline.set_category_parent("coroutine".to_string(), "b thread".to_string());
+        assert_eq!(
+            timeline.categories(),
+            [
+                "idle",
+                "process",
+                "a thread",
+                "b thread",
+                "coroutine",
+                "zzz"
+            ]
+        );
+        assert_eq!(timeline.category_depth("coroutine"), 2);
+
+        timeline.set_parent_summary(true);
+        timeline.set_row_labels(Some(80));
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        // The process is busy from 0 to 3 and 5 to 6, the thread from 5 to 6
+        assert_eq!(svg.matches("class=\"row-summary\"").count(), 3);
+        assert!(svg.contains(r#"class="row-summary" fill="gray" fill-opacity="0.4" height="20" width="600" x="0" y="41""#));
+        assert!(svg.contains(
+            "class=\"row-label\" fill=\"black\" font-size=\"10\" x=\"-52\" y=\"114\">\ncoroutine"
+        ));
+    }
+}
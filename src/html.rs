@@ -0,0 +1,415 @@
+// A standalone HTML page around the SVG for exploring long timelines in a browser
+//
+// The page script stretches the layers of the timeline horizontally instead of re-rendering
+// them, so it needs nothing but the SVG as written by `Timeline::write` and the `data-time-*`
+// attributes of its root element.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use crate::{TimeValue, Timeline, TimelineError};
+
+// Strokes and text keep their size while the time axis is stretched
+const PAGE_STYLE: &str = r##"
+body { margin: 0; font-family: sans-serif; }
+.toolbar { padding: 4px; }
+#timeline { overflow: auto; }
+#timeline svg { cursor: grab; user-select: none; }
+#timeline svg.panning { cursor: grabbing; }
+#timeline svg * { vector-effect: non-scaling-stroke; }
+#timeline svg.hovering g.event:not(.same-name) { opacity: 0.3; }
+#timeline g.event.same-name > :not(text):not(title),
+#timeline g.event.same-name > a > * { stroke: black; stroke-width: 2px; }
+#event-tooltip {
+  position: fixed; display: none; pointer-events: none; white-space: pre;
+  padding: 4px 6px; font-size: 12px; background: #ffffe0; border: 1px solid gray;
+}
+"##;
+
+// The wheel zooms the time axis around the pointer, dragging pans it, and the reset button goes
+// back to the whole timeline. Row labels, the bookmark index and the state legend stay in place,
+// the tick layers of `set_zoom_ticks` and clustered instants of `set_instant_threshold` are
+// switched by magnification. Clicking an entry of the bookmark index centers the view on the
+// bookmark, zooming in first if the whole timeline is shown. The view is kept in `data-view-scale`
+// and `data-view-x` of the root element for the measurement script.
+const PAN_ZOOM_SCRIPT: &str = r##"
+(function () {
+  var svg = document.querySelector("#timeline svg");
+  var NS = "http://www.w3.org/2000/svg";
+  var MAX_ZOOM = 100000;
+  var BOOKMARK_ZOOM = 10;
+  var originX = parseFloat(svg.getAttribute("data-time-origin-x"));
+  var width = parseFloat(svg.getAttribute("data-time-width"));
+  var timeScale = parseFloat(svg.getAttribute("data-time-scale"));
+  var groups = svg.querySelectorAll(":scope > g:not(.measure-cursors)");
+  var content = groups[groups.length - 1];
+
+  var fixed = document.createElementNS(NS, "g");
+  var pinned = content.querySelectorAll(
+    ".row-label, .row-heat, .group-header, .bookmark-index, .state-legend");
+  for (var i = 0; i < pinned.length; i++) {
+    fixed.appendChild(pinned[i]);
+  }
+  var clipper = document.createElementNS(NS, "g");
+  var view = document.createElementNS(NS, "g");
+  while (content.firstChild) {
+    view.appendChild(content.firstChild);
+  }
+  clipper.appendChild(view);
+  content.appendChild(clipper);
+  content.appendChild(fixed);
+
+  var defs = document.createElementNS(NS, "defs");
+  var clip = document.createElementNS(NS, "clipPath");
+  clip.setAttribute("id", "time-view");
+  var clipRect = document.createElementNS(NS, "rect");
+  clipRect.setAttribute("x", 0);
+  clipRect.setAttribute("y", -1e6);
+  clipRect.setAttribute("width", width);
+  clipRect.setAttribute("height", 2e6);
+  clip.appendChild(clipRect);
+  defs.appendChild(clip);
+  svg.insertBefore(defs, svg.firstChild);
+
+  var texts = view.querySelectorAll("text");
+  var textX = [];
+  var textTransform = [];
+  for (var i = 0; i < texts.length; i++) {
+    textX.push(parseFloat(texts[i].getAttribute("x")) || 0);
+    textTransform.push(texts[i].getAttribute("transform") || "");
+  }
+
+  var scale = 1;
+  var offset = 0;
+
+  function update() {
+    offset = Math.min(0, Math.max(width * (1 - scale), offset));
+    view.setAttribute("transform", "matrix(" + scale + ",0,0,1," + offset + ",0)");
+    if (scale > 1) {
+      clipper.setAttribute("clip-path", "url(#time-view)");
+    } else {
+      clipper.removeAttribute("clip-path");
+    }
+    svg.setAttribute("data-view-scale", scale);
+    svg.setAttribute("data-view-x", offset);
+    for (var i = 0; i < texts.length; i++) {
+      var x = textX[i];
+      var unstretch = "matrix(" + 1 / scale + ",0,0,1," + (x - x / scale) + ",0)";
+      texts[i].setAttribute("transform", (unstretch + " " + textTransform[i]).trim());
+    }
+    var ticks = svg.querySelectorAll("g.ticks[data-zoom]");
+    var best = null;
+    for (var i = 0; i < ticks.length; i++) {
+      var zoom = parseFloat(ticks[i].getAttribute("data-zoom"));
+      if (zoom <= scale && (best === null || zoom > best)) {
+        best = zoom;
+      }
+    }
+    for (var i = 0; i < ticks.length; i++) {
+      var zoom = parseFloat(ticks[i].getAttribute("data-zoom"));
+      show(ticks[i], zoom === best);
+    }
+    var markers = svg.querySelectorAll("g.instant-markers[data-zoom]");
+    for (var i = 0; i < markers.length; i++) {
+      show(markers[i], scale >= parseFloat(markers[i].getAttribute("data-zoom")));
+    }
+  }
+
+  function show(element, visible) {
+    if (visible) {
+      element.removeAttribute("display");
+    } else {
+      element.setAttribute("display", "none");
+    }
+  }
+
+  function svgX(clientX) {
+    var point = svg.createSVGPoint();
+    point.x = clientX;
+    point.y = 0;
+    return point.matrixTransform(svg.getScreenCTM().inverse()).x;
+  }
+
+  svg.addEventListener("wheel", function (e) {
+    e.preventDefault();
+    var x = svgX(e.clientX) - originX;
+    var zoomed = Math.min(MAX_ZOOM, Math.max(1, scale * Math.exp(-e.deltaY * 0.002)));
+    offset = x - (x - offset) * zoomed / scale;
+    scale = zoomed;
+    update();
+  }, { passive: false });
+
+  var dragX = null;
+  svg.addEventListener("mousedown", function (e) {
+    if (e.button !== 0 || e.shiftKey) {
+      return;
+    }
+    dragX = svgX(e.clientX);
+    svg.classList.add("panning");
+  });
+  window.addEventListener("mousemove", function (e) {
+    if (dragX === null) {
+      return;
+    }
+    var x = svgX(e.clientX);
+    offset += x - dragX;
+    dragX = x;
+    update();
+  });
+  window.addEventListener("mouseup", function () {
+    dragX = null;
+    svg.classList.remove("panning");
+  });
+
+  document.getElementById("reset-zoom").addEventListener("click", function () {
+    scale = 1;
+    offset = 0;
+    update();
+  });
+
+  var entries = svg.querySelectorAll(".bookmark-index a[data-time]");
+  for (var i = 0; i < entries.length; i++) {
+    entries[i].addEventListener("click", function (e) {
+      e.preventDefault();
+      var x = parseFloat(this.getAttribute("data-time")) * timeScale;
+      scale = Math.max(scale, BOOKMARK_ZOOM);
+      offset = width / 2 - x * scale;
+      update();
+    });
+  }
+})();
+"##;
+
+// Hovering an event highlights every event of the same name and shows a tooltip with the details
+// of the event and the statistics of its name. The tooltip replaces the `<title>` of the event,
+// whose first line is the name.
+const HOVER_SCRIPT: &str = r##"
+(function () {
+  var svg = document.querySelector("#timeline svg");
+  var tooltip = document.getElementById("event-tooltip");
+  var summaries = JSON.parse(document.getElementById("event-summaries").textContent);
+  var events = svg.querySelectorAll("g.event");
+  var details = new Map();
+  var byName = new Map();
+  for (var i = 0; i < events.length; i++) {
+    var title = events[i].querySelector(":scope > title");
+    if (!title) {
+      continue;
+    }
+    var text = title.textContent.trim();
+    var name = text.split("\n")[0];
+    events[i].removeChild(title);
+    details.set(events[i], { name: name, text: text });
+    if (!byName.has(name)) {
+      byName.set(name, []);
+    }
+    byName.get(name).push(events[i]);
+  }
+  var hovered = null;
+
+  function leave() {
+    if (hovered === null) {
+      return;
+    }
+    byName.get(details.get(hovered).name).forEach(function (event) {
+      event.classList.remove("same-name");
+    });
+    svg.classList.remove("hovering");
+    tooltip.style.display = "none";
+    hovered = null;
+  }
+
+  svg.addEventListener("mouseover", function (e) {
+    var event = e.target.closest("g.event");
+    if (event === hovered) {
+      return;
+    }
+    leave();
+    if (event === null || !details.has(event)) {
+      return;
+    }
+    hovered = event;
+    var detail = details.get(event);
+    byName.get(detail.name).forEach(function (other) {
+      other.classList.add("same-name");
+    });
+    svg.classList.add("hovering");
+    var summary = summaries[detail.name];
+    tooltip.textContent = summary ? detail.text + "\n\n" + summary : detail.text;
+    tooltip.style.display = "block";
+  });
+  svg.addEventListener("mousemove", function (e) {
+    tooltip.style.left = (e.clientX + 12) + "px";
+    tooltip.style.top = (e.clientY + 12) + "px";
+  });
+  svg.addEventListener("mouseleave", leave);
+})();
+"##;
+
+// The number of events of a name and their total, mean and longest duration in units, see
+// `name_stats`
+pub(crate) struct NameStats {
+    pub(crate) count: usize,
+    pub(crate) total: f64,
+    pub(crate) mean: f64,
+    pub(crate) max: f64,
+}
+
+// Escape text for HTML element content and quoted attribute values
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+impl<T: TimeValue> Timeline<T> {
+    /// Write the timeline as a standalone HTML page for exploring it
+    ///
+    /// The page embeds the SVG of `write` with a script to zoom the time axis with the mouse wheel,
+    /// pan it by dragging, and go back to the whole timeline with a reset button, which long traces
+    /// are unreadable without. Zooming only stretches the time axis, so the rows and text keep
+    /// their size, and it shows the denser tick layers of `set_zoom_ticks` and the clustered
+    /// instants of `set_instant_threshold` as they fit. Clicking an entry of the index of
+    /// `add_bookmark` centers the view on the bookmark. Hovering an event highlights every event of
+    /// the same name and shows its tooltip, with the number, total, mean and longest duration of
+    /// the drawn events of that name. The title of the page, the label of the button and the names
+    /// of the statistics are taken from `Strings`.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("Event 1", 0, 10, "Location 1");
+    /// let mut output = Vec::new();
+    /// timeline.write_html(&mut output).unwrap();
+    /// assert!(String::from_utf8(output).unwrap().starts_with("<!DOCTYPE html>"));
+    /// # }
+    /// ```
+    pub fn write_html<W: Write>(&self, writer: W) -> Result<(), TimelineError> {
+        self.write_page(writer, "", "")
+    }
+
+    // Write the page of `write_html`, with `style` added to its style sheet and `body` below the
+    // timeline
+    pub(crate) fn write_page<W: Write>(
+        &self,
+        mut writer: W,
+        style: &str,
+        body: &str,
+    ) -> Result<(), TimelineError> {
+        let doc = self.time_attributes(self.document(&self.colormap)?);
+        let summaries = serde_json::to_string(&self.name_summaries())
+            .map_err(|e| TimelineError::Io(e.into()))?
+            // Keep names from closing the script element
+            .replace("</", "<\\/");
+        write!(
+            writer,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+             <style>{}{}</style>\n</head>\n<body>\n<div class=\"toolbar\">\
+             <button type=\"button\" id=\"reset-zoom\">{}</button></div>\n\
+             <div id=\"timeline\">\n{}\n</div>\n{}<div id=\"event-tooltip\"></div>\n\
+             <script type=\"application/json\" id=\"event-summaries\">{}</script>\n\
+             <script>{}{}</script>\n</body>\n</html>\n",
+            escape(&self.strings.page_title),
+            PAGE_STYLE,
+            style,
+            escape(&self.strings.reset_zoom),
+            doc,
+            body,
+            summaries,
+            PAN_ZOOM_SCRIPT,
+            HOVER_SCRIPT
+        )?;
+        Ok(())
+    }
+
+    // The durations of the drawn events of every name
+    pub(crate) fn name_stats(&self) -> BTreeMap<&str, NameStats> {
+        let mut durations: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+        for event in self.events.iter().filter(|event| self.is_drawn(event)) {
+            durations
+                .entry(&event.name)
+                .or_default()
+                .push(event.end_time.offset_from(event.start_time, self.units));
+        }
+        durations
+            .into_iter()
+            .map(|(name, durations)| {
+                let total: f64 = durations.iter().sum();
+                let stats = NameStats {
+                    count: durations.len(),
+                    total,
+                    // Means are rounded so they don't print with every digit of a float
+                    mean: (total / durations.len() as f64 * 1000.0).round() / 1000.0,
+                    max: durations.iter().copied().fold(0.0, f64::max),
+                };
+                (name, stats)
+            })
+            .collect()
+    }
+
+    // The statistics of the drawn events of every name, as the lines of their tooltips
+    fn name_summaries(&self) -> BTreeMap<&str, String> {
+        let strings = &self.strings;
+        let unit = strings.unit(&self.units);
+        self.name_stats()
+            .into_iter()
+            .map(|(name, stats)| {
+                let summary = format!(
+                    "{}: {}\n{}: {} {}\n{}: {} {}\n{}: {} {}",
+                    strings.tooltip_count,
+                    stats.count,
+                    strings.tooltip_total,
+                    stats.total,
+                    unit,
+                    strings.tooltip_mean,
+                    stats.mean,
+                    unit,
+                    strings.tooltip_max,
+                    stats.max,
+                    unit
+                );
+                (name, summary)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Strings;
+
+    #[test]
+    fn test_write_html() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1", 0, 10, "Location 1");
+        timeline.add_event("Event 1", 12, 13, "Location 2");
+        timeline.add_event("</script>", 5, 7, "Location 2");
+        timeline.add_bookmark(6, "stall".to_string());
+        timeline.set_strings(Strings {
+            page_title: "Trace <1>".to_string(),
+            ..Strings::default()
+        });
+
+        let mut output = Vec::new();
+        timeline.write_html(&mut output).unwrap();
+        let html = String::from_utf8(output).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>\n<html>"));
+        assert!(html.contains("<title>Trace &lt;1&gt;</title>"));
+        assert!(html.contains(r#"<button type="button" id="reset-zoom">Reset zoom</button>"#));
+        assert!(html.contains(r#"data-time-origin-x="0""#));
+        assert!(html.contains(r#"data-time-width="2600""#));
+        // Entries of the bookmark index center the view on their time
+        assert!(html.contains(r##"<a data-time="6" href="#bookmark-0">"##));
+        assert!(html.contains(r#"querySelectorAll(".bookmark-index a[data-time]")"#));
+        assert!(html.contains(r#"<div id="event-tooltip"></div>"#));
+        assert!(html.contains(r#""Event 1":"count: 2\ntotal: 11 ns\nmean: 5.5 ns\nmax: 10 ns""#));
+        assert!(html.contains(r#"{"<\/script>":"#));
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+}
@@ -0,0 +1,287 @@
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use crate::atrace::AtraceImporter;
+use crate::blkparse::BlkparseImporter;
+use crate::bpftrace::BpftraceImporter;
+use crate::chrome::ChromeJsonImporter;
+use crate::criterion::CriterionImporter;
+use crate::dmesg::DmesgImporter;
+use crate::ftrace::FtraceImporter;
+use crate::fxt::FxtImporter;
+use crate::jaeger::JaegerImporter;
+use crate::journald::JournaldImporter;
+use crate::jsonl::JsonLinesImporter;
+use crate::otlp::OtlpImporter;
+use crate::perf::PerfScriptImporter;
+use crate::qemu::QemuImporter;
+use crate::speedscope::SpeedscopeImporter;
+use crate::strace::StraceImporter;
+use crate::sysstat::SysstatImporter;
+use crate::systemd::SystemdImporter;
+use crate::tokio_tasks::TokioTasksImporter;
+use crate::tshark::TsharkImporter;
+use crate::zipkin::ZipkinImporter;
+use crate::{ScaleMode, Timeline, TimelineError};
+
+/// A reader of a trace format, turning traces into timelines
+///
+/// Importers are added with `register_importer` and picked by `Timeline::import`, which lets
+/// other crates add support for their formats. These are built in:
+///
+/// - "chrome-json": the Chrome trace event format
+/// - "atrace": Android traces of atrace and systrace
+/// - "ftrace": the text output of ftrace
+/// - "perf-script": the output of `perf script` and `perf sched timehist`
+/// - "strace": the output of `strace` with timestamps
+/// - "blkparse": the output of blkparse
+/// - "systemd": the units of `systemd-analyze dump` and `plot --json`
+/// - "dmesg": the kernel log
+/// - "jsonl": events as JSON objects, one per line
+/// - "otlp": OpenTelemetry spans exported as JSON
+/// - "jaeger": traces downloaded from Jaeger
+/// - "zipkin": the JSON of Zipkin v2 spans
+/// - "bpftrace": the output of bpftrace scripts printing events, see `Timeline::from_bpftrace`
+/// - "fxt": the binary Fuchsia trace format
+/// - "tokio-tasks": the JSON logs of tokio's task instrumentation
+/// - "criterion": the raw samples Criterion saves of a benchmark
+/// - "journald": the JSON entries of `journalctl -o json`
+/// - "sysstat": the reports of sar and `iostat -t`, as counters
+/// - "qemu": the traces of QEMU's log and simple backends
+/// - "tshark": the packets of `tshark -T json`
+/// - "speedscope": the JSON file format of speedscope
+/// - "trace-cmd": the `.dat` files of trace-cmd, with the `trace-cmd` feature
+/// - "pcap": the pcap and pcapng packet captures of tcpdump and Wireshark, with the `pcap`
+///   feature
+/// - "pprof": pprof profiles with timestamps, with the `pprof` feature
+pub trait TraceImporter: Send + Sync {
+    /// The name of the format, like "chrome-json"
+    fn format(&self) -> &str;
+
+    /// Whether `data` looks like a trace of this format
+    ///
+    /// This is called with the whole file and should only look at as much as it needs, like a
+    /// header or the first characters.
+    fn sniff(&self, data: &[u8]) -> bool;
+
+    /// Read a trace of this format into a timeline
+    ///
+    /// Traces that can't be read should return `TimelineError::Parse`. The built in importers
+    /// return timelines scaled with `ScaleMode::FitWidth(1200)`, see `Timeline::set_scale_mode`.
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError>;
+}
+
+// A new timeline for an importer to fill. Traces are in nanoseconds, which at a column per unit
+// would make an SVG far too wide for any real trace, so they are scaled to fit a window instead.
+pub(crate) fn new_timeline() -> Timeline {
+    Timeline::builder()
+        .scale_mode(ScaleMode::FitWidth(1200))
+        .build()
+}
+
+// The start of `data` as text, which is as much as sniffers of text formats look at. Bytes that
+// aren't UTF-8, like those of binary formats or of a character cut off at the end, are replaced.
+pub(crate) fn sniff_text(data: &[u8]) -> Cow<'_, str> {
+    String::from_utf8_lossy(&data[..data.len().min(4096)])
+}
+
+// The registered importers, asked in the order they were registered and before the built in
+// importers of other formats
+static IMPORTERS: RwLock<Vec<Arc<dyn TraceImporter>>> = RwLock::new(Vec::new());
+
+fn importers() -> Vec<Arc<dyn TraceImporter>> {
+    // A panic while registering leaves nothing half-updated worth refusing
+    let mut importers = IMPORTERS.read().unwrap_or_else(|e| e.into_inner()).clone();
+    let builtin: Vec<Arc<dyn TraceImporter>> = vec![
+        Arc::new(ChromeJsonImporter),
+        // Before ftrace, as atrace traces are ftrace traces with more in them
+        Arc::new(AtraceImporter),
+        Arc::new(FtraceImporter),
+        Arc::new(PerfScriptImporter),
+        Arc::new(StraceImporter),
+        Arc::new(BlkparseImporter),
+        Arc::new(SystemdImporter),
+        Arc::new(DmesgImporter),
+        Arc::new(JsonLinesImporter),
+        Arc::new(OtlpImporter),
+        Arc::new(JaegerImporter),
+        Arc::new(ZipkinImporter),
+        Arc::new(BpftraceImporter),
+        Arc::new(FxtImporter),
+        Arc::new(TokioTasksImporter),
+        Arc::new(CriterionImporter),
+        Arc::new(JournaldImporter),
+        Arc::new(SysstatImporter),
+        Arc::new(QemuImporter),
+        Arc::new(TsharkImporter),
+        Arc::new(SpeedscopeImporter),
+        #[cfg(feature = "trace-cmd")]
+        Arc::new(crate::tracecmd::TraceCmdImporter),
+        #[cfg(feature = "pcap")]
+        Arc::new(crate::pcap::PcapImporter),
+        #[cfg(feature = "pprof")]
+        Arc::new(crate::pprof::PprofImporter),
+    ];
+    for importer in builtin {
+        if !importers
+            .iter()
+            .any(|existing| existing.format() == importer.format())
+        {
+            importers.push(importer);
+        }
+    }
+    importers
+}
+
+/// Register an importer for `Timeline::import`
+///
+/// An importer registered for a format that already has one replaces it.
+pub fn register_importer(importer: impl TraceImporter + 'static) {
+    let mut importers = IMPORTERS.write().unwrap_or_else(|e| e.into_inner());
+    importers.retain(|existing| existing.format() != importer.format());
+    importers.push(Arc::new(importer));
+}
+
+/// The formats `Timeline::import` can read, in the order they are tried
+pub fn import_formats() -> Vec<String> {
+    importers()
+        .iter()
+        .map(|importer| importer.format().to_string())
+        .collect()
+}
+
+impl Timeline {
+    /// Read a trace file into a timeline, detecting its format
+    ///
+    /// The registered importers, see `register_importer`, and then the built in ones are asked
+    /// in turn whether they recognize the file, and the first one that does reads it. This returns
+    /// `TimelineError::UnknownFormat` if none does.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::{register_importer, Timeline, TimelineError, TraceImporter};
+    ///
+    /// // One event per line, as "name,start,end,location"
+    /// struct Lines;
+    ///
+    /// impl TraceImporter for Lines {
+    ///     fn format(&self) -> &str {
+    ///         "lines"
+    ///     }
+    ///
+    ///     fn sniff(&self, data: &[u8]) -> bool {
+    ///         data.starts_with(b"# lines")
+    ///     }
+    ///
+    ///     fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+    ///         let mut timeline = Timeline::default();
+    ///         let text = String::from_utf8_lossy(data);
+    ///         for line in text.lines().skip(1) {
+    ///             let fields: Vec<&str> = line.split(',').collect();
+    ///             let [name, start, end, location] = fields[..] else {
+    ///                 return Err(TimelineError::Parse(format!("bad line {:?}", line)));
+    ///             };
+    ///             let time = |t: &str| t.parse().map_err(|_| TimelineError::Parse(t.into()));
+    ///             timeline.add_event(name, time(start)?, time(end)?, location);
+    ///         }
+    ///         Ok(timeline)
+    ///     }
+    /// }
+    ///
+    /// register_importer(Lines);
+    /// let path = std::env::temp_dir().join("timeline-svg-import-doc.txt");
+    /// std::fs::write(&path, "# lines\nread,0,5,CPU 0\n").unwrap();
+    /// let timeline = Timeline::import(&path).unwrap();
+    /// assert_eq!(timeline.time_range(), Some((0, 5)));
+    /// # std::fs::remove_file(path).unwrap();
+    /// # }
+    /// ```
+    pub fn import(path: impl AsRef<Path>) -> Result<Timeline, TimelineError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)?;
+        let importer = importers()
+            .into_iter()
+            .find(|importer| importer.sniff(&data))
+            .ok_or_else(|| {
+                TimelineError::UnknownFormat(format!("no importer recognizes {}", path.display()))
+            })?;
+        log_debug!("importing {} as {}", path.display(), importer.format());
+        importer.parse(&data)
+    }
+
+    /// Read a trace file of a known format into a timeline
+    ///
+    /// This is the same as `import`, without detecting the format. This returns
+    /// `TimelineError::UnknownFormat` if no importer is registered for `format`.
+    pub fn import_format(path: impl AsRef<Path>, format: &str) -> Result<Timeline, TimelineError> {
+        let importer = importers()
+            .into_iter()
+            .find(|importer| importer.format() == format)
+            .ok_or_else(|| TimelineError::UnknownFormat(format.to_string()))?;
+        importer.parse(&std::fs::read(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(&'static str, i64);
+
+    impl TraceImporter for Fixed {
+        fn format(&self) -> &str {
+            self.0
+        }
+
+        fn sniff(&self, data: &[u8]) -> bool {
+            data.starts_with(self.0.as_bytes())
+        }
+
+        fn parse(&self, _data: &[u8]) -> Result<Timeline, TimelineError> {
+            let mut timeline = Timeline::default();
+            timeline.add_event(self.0.to_string(), 0, self.1, "main".to_string());
+            Ok(timeline)
+        }
+    }
+
+    #[test]
+    fn test_sniff_text() {
+        assert_eq!(sniff_text(b"# tracer: nop\n"), "# tracer: nop\n");
+        // Only the start is looked at, and a character cut off there is replaced
+        let mut data = vec![b'a'; 4095];
+        data.extend("é and more".as_bytes());
+        let text = sniff_text(&data);
+        assert_eq!(text.len(), 4095 + '\u{fffd}'.len_utf8());
+        assert!(text.ends_with("a\u{fffd}"));
+        assert_eq!(sniff_text(b"\xff\x00"), "\u{fffd}\0");
+    }
+
+    #[test]
+    fn test_import() {
+        register_importer(Fixed("test-a", 1));
+        register_importer(Fixed("test-b", 2));
+        register_importer(Fixed("test-a", 3));
+        let formats = import_formats();
+        assert_eq!(formats.iter().filter(|f| *f == "test-a").count(), 1);
+
+        let dir = std::env::temp_dir().join(format!("timeline-svg-import-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("trace");
+        std::fs::write(&path, "test-a").unwrap();
+        assert_eq!(Timeline::import(&path).unwrap().time_range(), Some((0, 3)));
+        let timeline = Timeline::import_format(&path, "test-b").unwrap();
+        assert_eq!(timeline.time_range(), Some((0, 2)));
+        assert!(matches!(
+            Timeline::import_format(&path, "test-c"),
+            Err(TimelineError::UnknownFormat(_))
+        ));
+        std::fs::write(&path, "unknown").unwrap();
+        assert!(matches!(
+            Timeline::import(&path),
+            Err(TimelineError::UnknownFormat(_))
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,115 @@
+// Scripts embedded in the rendered SVG for interactive viewing in a browser
+//
+// The scripts find what they need through the `data-time-*` attributes of the root element and
+// the `event` class of event groups, so they work on the SVG as written by `Timeline::write`.
+
+use svg::node::element::Script;
+use svg::Document;
+
+use crate::{TimeValue, Timeline};
+
+// Shift-click drops up to two measurement cursors and shows the time between them, along with
+// the events each cursor crosses. A third shift-click starts over.
+const MEASURE_SCRIPT: &str = r#"
+(function () {
+  var svg = document.currentScript ? document.currentScript.ownerSVGElement : null;
+  svg = svg || document.documentElement;
+  var NS = "http://www.w3.org/2000/svg";
+  var scale = parseFloat(svg.getAttribute("data-time-scale"));
+  var originX = parseFloat(svg.getAttribute("data-time-origin-x"));
+  var unit = svg.getAttribute("data-time-unit");
+  var height = parseFloat(svg.getAttribute("height"));
+  var layer = document.createElementNS(NS, "g");
+  layer.setAttribute("class", "measure-cursors");
+  svg.appendChild(layer);
+  var cursors = [];
+
+  function format(value) {
+    return (Math.round(value * 1000) / 1000) + " " + unit;
+  }
+
+  function eventsAt(x) {
+    var names = [];
+    var events = svg.querySelectorAll("g.event");
+    for (var i = 0; i < events.length; i++) {
+      var shape = events[i].firstElementChild;
+      var box = shape.getBoundingClientRect();
+      var point = svg.createSVGPoint();
+      point.x = box.left;
+      point.y = box.top;
+      var left = point.matrixTransform(svg.getScreenCTM().inverse()).x;
+      point.x = box.right;
+      var right = point.matrixTransform(svg.getScreenCTM().inverse()).x;
+      if (x >= left && x <= right) {
+        names.push(events[i].querySelector("text").textContent.trim());
+      }
+    }
+    return names;
+  }
+
+  function text(x, y, content) {
+    var label = document.createElementNS(NS, "text");
+    label.setAttribute("x", x);
+    label.setAttribute("y", y);
+    label.setAttribute("font-size", 10);
+    label.setAttribute("fill", "crimson");
+    label.textContent = content;
+    layer.appendChild(label);
+  }
+
+  svg.addEventListener("click", function (e) {
+    if (!e.shiftKey) {
+      return;
+    }
+    if (cursors.length == 2) {
+      cursors = [];
+      while (layer.firstChild) {
+        layer.removeChild(layer.firstChild);
+      }
+    }
+    var point = svg.createSVGPoint();
+    point.x = e.clientX;
+    point.y = e.clientY;
+    var x = point.matrixTransform(svg.getScreenCTM().inverse()).x;
+    // The page of `Timeline::write_html` stretches the time axis by data-view-scale and shifts
+    // it by data-view-x
+    var viewScale = parseFloat(svg.getAttribute("data-view-scale")) || 1;
+    var viewX = parseFloat(svg.getAttribute("data-view-x")) || 0;
+    var time = (x - originX - viewX) / viewScale / scale;
+    cursors.push(time);
+    var line = document.createElementNS(NS, "line");
+    line.setAttribute("x1", x);
+    line.setAttribute("x2", x);
+    line.setAttribute("y1", 0);
+    line.setAttribute("y2", height);
+    line.setAttribute("stroke", "crimson");
+    line.setAttribute("stroke-dasharray", "4 2");
+    layer.appendChild(line);
+    text(x + 2, height - 4, format(time) + ": " + eventsAt(x).join(", "));
+    if (cursors.length == 2) {
+      text(x + 2, 10, "Δ " + format(Math.abs(cursors[1] - cursors[0])));
+    }
+  });
+})();
+"#;
+
+impl<T: TimeValue> Timeline<T> {
+    // The scripts to embed in the document, if any interactive features are enabled
+    pub(crate) fn scripts(&self) -> Option<Script> {
+        if !self.measure_cursors {
+            return None;
+        }
+        Some(Script::new(MEASURE_SCRIPT).set("type", "application/ecmascript"))
+    }
+
+    // Tag the root element with how to map x positions to times and back, for the scripts
+    pub(crate) fn time_attributes(&self, doc: Document) -> Document {
+        doc.set("data-time-scale", self.scale())
+            .set(
+                "data-time-origin-x",
+                self.margins.left + self.label_gutter.unwrap_or(0),
+            )
+            .set("data-time-width", self.span() * self.scale())
+            .set("data-time-unit", self.axis_unit_label())
+    }
+}
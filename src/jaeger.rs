@@ -0,0 +1,313 @@
+use std::io::Read;
+
+use serde_json::Value;
+
+use crate::chrome::text;
+use crate::import::sniff_text;
+use crate::spans::{spans_timeline, Span, SpanRows};
+use crate::{Timeline, TimelineError, TraceImporter};
+
+// The JSON of traces downloaded from Jaeger, see `Timeline::from_jaeger`
+pub(crate) struct JaegerImporter;
+
+impl TraceImporter for JaegerImporter {
+    fn format(&self) -> &str {
+        "jaeger"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        let text = sniff_text(data);
+        text.trim_start().starts_with(['{', '['])
+            && text.contains("\"traceID\"")
+            && text.contains("\"operationName\"")
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_jaeger(data, SpanRows::Service)
+    }
+}
+
+// Jaeger counts in microseconds since the epoch, this is the start and end of a span of them in
+// nanoseconds, or None if they are too late to tell
+fn nanos(start: i64, duration: i64) -> Option<(i64, i64)> {
+    let start = start.checked_mul(1000)?;
+    Some((start, start.checked_add(duration.checked_mul(1000)?)?))
+}
+
+// Tags and the fields of logs, as pairs of text
+fn tags(list: &Value) -> Vec<(String, String)> {
+    list.as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|tag| Some((tag["key"].as_str()?.to_string(), text(&tag["value"]))))
+        .collect()
+}
+
+fn parse_trace(trace: &Value, spans: &mut Vec<Span>) -> Result<(), TimelineError> {
+    let processes = &trace["processes"];
+    for span in trace["spans"].as_array().into_iter().flatten() {
+        let (Some(trace_id), Some(span_id), Some(start), Some(duration)) = (
+            span["traceID"].as_str(),
+            span["spanID"].as_str(),
+            span["startTime"].as_i64(),
+            span["duration"].as_i64(),
+        ) else {
+            continue;
+        };
+        let (start, end) = nanos(start, duration)
+            .ok_or_else(|| TimelineError::Parse(format!("span {} lasts too long", span_id)))?;
+        // Spans of older exports carry their process themselves
+        let process = match span["processID"].as_str() {
+            Some(id) => &processes[id],
+            None => &span["process"],
+        };
+        let mut attributes = tags(&span["tags"]);
+        let kind = attributes
+            .iter()
+            .position(|(key, _)| key == "span.kind")
+            .map(|index| attributes.remove(index).1);
+        attributes.extend(
+            tags(&process["tags"])
+                .into_iter()
+                .map(|(key, value)| (format!("process.{}", key), value)),
+        );
+        let mut parent_id = None;
+        let mut links = Vec::new();
+        for reference in span["references"].as_array().into_iter().flatten() {
+            let (Some(trace_id), Some(span_id)) =
+                (reference["traceID"].as_str(), reference["spanID"].as_str())
+            else {
+                continue;
+            };
+            if reference["refType"] == "CHILD_OF" && parent_id.is_none() {
+                parent_id = Some(span_id.to_string());
+            } else {
+                links.push((trace_id.to_string(), span_id.to_string()));
+            }
+        }
+        spans.push(Span {
+            trace_id: trace_id.to_string(),
+            span_id: span_id.to_string(),
+            parent_id,
+            links,
+            name: span["operationName"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            service: process["serviceName"].as_str().map(str::to_string),
+            kind,
+            start,
+            end,
+            attributes,
+        });
+    }
+    Ok(())
+}
+
+impl Timeline {
+    /// Read a trace downloaded from Jaeger into a timeline
+    ///
+    /// This reads the JSON that the Jaeger UI downloads and its query API returns, an object
+    /// with a `data` array of traces, a single trace, or an array of them. Every span becomes an
+    /// event named after its operation, on the row of its process, named after the service of
+    /// the process, or of its `span.kind` tag, like "server", as `rows` picks. A span gets a
+    /// trigger from the row of every span it references that is on another row, `CHILD_OF` its
+    /// parent or `FOLLOWS_FROM` others, when it starts. The ids of the trace, the span and its
+    /// parent, its "service" and "kind", its tags and the tags of its process, prefixed with
+    /// "process.", are its metadata. Times are in nanoseconds since the epoch.
+    ///
+    /// This returns `TimelineError::Parse` if the trace isn't JSON of this shape, has no spans
+    /// or has a span ending too late to tell, and `TimelineError::Io` if reading fails.
+    /// `Timeline::import` detects traces of this format, as "jaeger", and draws them by service.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::{SpanRows, Timeline};
+    ///
+    /// let trace = r#"{"data": [{
+    ///     "traceID": "a1",
+    ///     "spans": [
+    ///         {"traceID": "a1", "spanID": "01", "operationName": "GET /", "references": [],
+    ///          "startTime": 1000, "duration": 50, "processID": "p1"},
+    ///         {"traceID": "a1", "spanID": "02", "operationName": "SELECT",
+    ///          "references": [{"refType": "CHILD_OF", "traceID": "a1", "spanID": "01"}],
+    ///          "startTime": 1010, "duration": 20, "processID": "p2"}
+    ///     ],
+    ///     "processes": {"p1": {"serviceName": "frontend"}, "p2": {"serviceName": "mysql"}}
+    /// }]}"#;
+    /// let timeline = Timeline::from_jaeger(trace.as_bytes(), SpanRows::Service).unwrap();
+    /// assert_eq!(timeline.categories(), ["frontend", "mysql"]);
+    /// assert_eq!(timeline.triggers().count(), 1);
+    /// # }
+    /// ```
+    pub fn from_jaeger(reader: impl Read, rows: SpanRows) -> Result<Timeline, TimelineError> {
+        let json: Value =
+            serde_json::from_reader(reader).map_err(|e| TimelineError::Parse(e.to_string()))?;
+        let traces = match &json {
+            Value::Array(traces) => traces.as_slice(),
+            Value::Object(object) if !object.contains_key("spans") => json["data"]
+                .as_array()
+                .ok_or_else(|| TimelineError::Parse("no data array".to_string()))?,
+            _ => std::slice::from_ref(&json),
+        };
+        let mut spans = Vec::new();
+        for trace in traces {
+            parse_trace(trace, &mut spans)?;
+        }
+        spans_timeline(spans, rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tags() {
+        let list = serde_json::json!([
+            {"key": "span.kind", "type": "string", "value": "server"},
+            {"key": "http.status_code", "type": "int64", "value": 200},
+            {"key": "error", "type": "bool", "value": true},
+            {"type": "string", "value": "no key"}
+        ]);
+        assert_eq!(
+            tags(&list),
+            [
+                ("span.kind".to_string(), "server".to_string()),
+                ("http.status_code".to_string(), "200".to_string()),
+                ("error".to_string(), "true".to_string()),
+            ]
+        );
+        assert_eq!(tags(&Value::Null), []);
+    }
+
+    #[test]
+    fn test_from_jaeger() {
+        let trace = r#"{"data": [{
+            "traceID": "a1",
+            "spans": [
+                {"traceID": "a1", "spanID": "01", "operationName": "GET /", "references": [],
+                 "startTime": 1000, "duration": 50, "processID": "p1",
+                 "tags": [{"key": "span.kind", "type": "string", "value": "server"},
+                          {"key": "http.status_code", "type": "int64", "value": 200}]},
+                {"traceID": "a1", "spanID": "02", "operationName": "SELECT",
+                 "references": [{"refType": "CHILD_OF", "traceID": "a1", "spanID": "01"}],
+                 "startTime": 1010, "duration": 20, "processID": "p2",
+                 "tags": [{"key": "span.kind", "type": "string", "value": "client"}]},
+                {"traceID": "a1", "spanID": "03", "operationName": "audit",
+                 "references": [{"refType": "FOLLOWS_FROM", "traceID": "a1", "spanID": "02"}],
+                 "startTime": 1060, "duration": 5, "processID": "p1"}
+            ],
+            "processes": {
+                "p1": {"serviceName": "frontend", "tags": [{"key": "hostname", "value": "web1"}]},
+                "p2": {"serviceName": "mysql"}
+            }
+        }]}"#;
+        assert!(JaegerImporter.sniff(trace.as_bytes()));
+        // Zipkin spells the trace id otherwise
+        assert!(!JaegerImporter.sniff(br#"[{"traceId": "a1", "name": "get", "timestamp": 1}]"#));
+        let timeline = Timeline::from_jaeger(trace.as_bytes(), SpanRows::Service).unwrap();
+        let events: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name(),
+                    event.location(),
+                    event.start_time(),
+                    event.end_time(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("GET /", "frontend", 1_000_000, 1_050_000),
+                ("SELECT", "mysql", 1_010_000, 1_030_000),
+                ("audit", "frontend", 1_060_000, 1_065_000),
+            ]
+        );
+        // The kind is taken out of the tags
+        let (_, get) = timeline.events().next().unwrap();
+        assert_eq!(get.metadata()["kind"], "server");
+        assert!(!get.metadata().contains_key("span.kind"));
+        assert_eq!(get.metadata()["http.status_code"], "200");
+        assert_eq!(get.metadata()["process.hostname"], "web1");
+        let (_, select) = timeline.events().nth(1).unwrap();
+        assert_eq!(select.metadata()["parent_id"], "01");
+        let triggers: Vec<_> = timeline
+            .triggers()
+            .map(|trigger| (trigger.start_location(), trigger.end_location()))
+            .collect();
+        assert_eq!(triggers, [("frontend", "mysql"), ("mysql", "frontend")]);
+
+        let timeline = Timeline::from_jaeger(trace.as_bytes(), SpanRows::Kind).unwrap();
+        assert_eq!(timeline.categories(), ["client", "server", "unspecified"]);
+    }
+
+    #[test]
+    fn test_shapes() {
+        // A single trace, and an array of them, with the process in the spans of older exports
+        let trace = r#"{"traceID": "a1", "spans": [
+            {"traceID": "a1", "spanID": "01", "operationName": "GET /", "startTime": 1,
+             "duration": 1, "process": {"serviceName": "frontend"}}
+        ]}"#;
+        let traces = format!("[{}, {}]", trace, trace.replace("GET /", "POST /"));
+        assert!(JaegerImporter.sniff(traces.as_bytes()));
+        let timeline = Timeline::from_jaeger(traces.as_bytes(), SpanRows::Service).unwrap();
+        let rows: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| (event.name(), event.location()))
+            .collect();
+        assert_eq!(rows, [("GET /", "frontend"), ("POST /", "frontend")]);
+        let timeline = Timeline::from_jaeger(trace.as_bytes(), SpanRows::Service).unwrap();
+        assert_eq!(timeline.categories(), ["frontend"]);
+
+        // Queries that found nothing are errors, as are times too late to tell
+        for trace in [
+            r#"{"data": []}"#,
+            r#"{"errors": [{"code": 404}]}"#,
+            r#"{"data": [{"spans": [{"spanID": "01"}]}]}"#,
+            "[",
+            &trace.replace(r#""startTime": 1"#, r#""startTime": 9223372036854775"#),
+            &trace.replace(r#""duration": 1"#, r#""duration": 9223372036854775807"#),
+        ] {
+            assert!(matches!(
+                Timeline::from_jaeger(trace.as_bytes(), SpanRows::Service),
+                Err(TimelineError::Parse(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_references() {
+        // The first CHILD_OF is the parent and other references are links, while references
+        // without ids, and spans without a time or an id, are skipped
+        let trace = r#"{"data": [{"spans": [
+            {"traceID": "a1", "spanID": "01", "operationName": "a", "startTime": 1,
+             "duration": 1, "processID": "p1"},
+            {"traceID": "a1", "spanID": "02", "operationName": "b", "startTime": 2,
+             "duration": 1, "processID": "p2"},
+            {"traceID": "a1", "spanID": "03", "operationName": "c", "startTime": 3,
+             "duration": 1, "processID": "p3", "references": [
+                {"refType": "CHILD_OF", "spanID": "09"},
+                {"refType": "CHILD_OF", "traceID": "a1", "spanID": "01"},
+                {"refType": "CHILD_OF", "traceID": "a1", "spanID": "02"}
+            ]},
+            {"traceID": "a1", "spanID": "04", "operationName": "no time", "duration": 1},
+            {"traceID": "a1", "operationName": "no id", "startTime": 4, "duration": 1}
+        ], "processes": {
+            "p1": {"serviceName": "one"}, "p2": {"serviceName": "two"},
+            "p3": {"serviceName": "three"}
+        }}]}"#;
+        let timeline = Timeline::from_jaeger(trace.as_bytes(), SpanRows::Service).unwrap();
+        assert_eq!(timeline.events().count(), 3);
+        let (_, c) = timeline.events().nth(2).unwrap();
+        assert_eq!(c.metadata()["parent_id"], "01");
+        let triggers: Vec<_> = timeline
+            .triggers()
+            .map(|trigger| (trigger.start_location(), trigger.end_location()))
+            .collect();
+        assert_eq!(triggers, [("one", "three"), ("two", "three")]);
+    }
+}
@@ -0,0 +1,258 @@
+use std::io::{BufRead, BufReader, Read};
+
+use serde_json::{Map, Value};
+
+use crate::dmesg::LEVELS;
+use crate::import::{new_timeline, sniff_text};
+use crate::{Timeline, TimelineError, TraceImporter};
+
+// The JSON output of journalctl, see `Timeline::from_journald`
+pub(crate) struct JournaldImporter;
+
+impl TraceImporter for JournaldImporter {
+    fn format(&self) -> &str {
+        "journald"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        sniff_text(data)
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .is_some_and(|line| {
+                line.trim_start_matches('\x1e').starts_with('{')
+                    && line.contains("\"__REALTIME_TIMESTAMP\"")
+            })
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_journald(data)
+    }
+}
+
+// The fields that name the row of an entry, in the order they are looked for: the unit
+// systemd logs about, the unit that logged the entry, and the program that did
+const ROW_FIELDS: [&str; 7] = [
+    "UNIT",
+    "USER_UNIT",
+    "_SYSTEMD_UNIT",
+    "_SYSTEMD_USER_UNIT",
+    "SYSLOG_IDENTIFIER",
+    "_COMM",
+    "_TRANSPORT",
+];
+
+// A field of an entry as text, which the journal exports as an array of its bytes when it
+// isn't valid UTF-8, or null when it's too large
+fn field(entry: &Map<String, Value>, key: &str) -> Option<String> {
+    match entry.get(key)? {
+        Value::String(text) => Some(text.clone()),
+        Value::Array(bytes) => {
+            let bytes: Option<Vec<u8>> = bytes
+                .iter()
+                .map(|byte| byte.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+                .collect();
+            Some(String::from_utf8_lossy(&bytes?).into_owned())
+        }
+        _ => None,
+    }
+}
+
+impl Timeline {
+    /// Read the journal entries `journalctl -o json` prints into a timeline
+    ///
+    /// Every entry becomes an instant named after its message, at its realtime timestamp, on
+    /// the row of the unit systemd logged it about, like "nginx.service" for "Started nginx",
+    /// so starts, stops and restarts land on the row of the unit they are about, or else of the
+    /// unit that logged it, or of the program that did, or "kernel" for kernel messages. The
+    /// "level" of its priority, like "err", its "identifier", "pid" and "hostname" are its
+    /// metadata. The `json-seq` output is read as well, and lines that aren't entries are
+    /// skipped. Times are in nanoseconds since the epoch, to lay the entries over timelines of
+    /// other traces with wall clock times.
+    ///
+    /// This returns `TimelineError::Parse` if there are no entries or one is too late to tell,
+    /// and `TimelineError::Io` if reading fails. `Timeline::import` detects output of this
+    /// format, as "journald".
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let entries = r#"
+    /// {"__REALTIME_TIMESTAMP": "1000", "_SYSTEMD_UNIT": "init.scope", "UNIT": "nginx.service", "MESSAGE": "Started nginx"}
+    /// {"__REALTIME_TIMESTAMP": "1500", "_SYSTEMD_UNIT": "nginx.service", "MESSAGE": "listening", "PRIORITY": "6"}
+    /// "#;
+    /// let timeline = Timeline::from_journald(entries.as_bytes()).unwrap();
+    /// assert_eq!(timeline.categories(), ["nginx.service"]);
+    /// assert_eq!(timeline.time_range(), Some((1_000_000, 1_500_000)));
+    /// # }
+    /// ```
+    pub fn from_journald(reader: impl Read) -> Result<Timeline, TimelineError> {
+        let mut timeline = new_timeline();
+        let mut parsed = false;
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            let Ok(entry) =
+                serde_json::from_str::<Map<String, Value>>(line.trim_start_matches('\x1e'))
+            else {
+                continue;
+            };
+            let Some(time) =
+                field(&entry, "__REALTIME_TIMESTAMP").and_then(|micros| micros.parse::<i64>().ok())
+            else {
+                continue;
+            };
+            parsed = true;
+            let row = ROW_FIELDS
+                .iter()
+                .find_map(|key| field(&entry, key))
+                .unwrap_or_else(|| "unknown".to_string());
+            let message = field(&entry, "MESSAGE").unwrap_or_default();
+            let time = time.checked_mul(1000).ok_or_else(|| {
+                TimelineError::Parse(format!("{:?} is too late to tell", message))
+            })?;
+            let id = timeline.add_instant(message, time, row);
+            let level = field(&entry, "PRIORITY")
+                .and_then(|priority| LEVELS.get(priority.parse::<usize>().ok()?))
+                .map(|level| level.to_string());
+            let fields = [
+                ("level", level),
+                ("identifier", field(&entry, "SYSLOG_IDENTIFIER")),
+                ("pid", field(&entry, "_PID")),
+                ("hostname", field(&entry, "_HOSTNAME")),
+            ];
+            for (key, value) in fields {
+                if let Some(value) = value {
+                    timeline.set_event_metadata(id, key.to_string(), value);
+                }
+            }
+        }
+        if !parsed {
+            return Err(TimelineError::Parse("no journal entries".to_string()));
+        }
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field() {
+        let entry: Map<String, Value> = serde_json::from_str(
+            r#"{"TEXT": "text", "BYTES": [98, 97, 100, 255], "WIDE": [98, 256], "NULL": null,
+                "NUMBER": 1}"#,
+        )
+        .unwrap();
+        assert_eq!(field(&entry, "TEXT").as_deref(), Some("text"));
+        assert_eq!(field(&entry, "BYTES").as_deref(), Some("bad\u{fffd}"));
+        assert_eq!(field(&entry, "WIDE"), None);
+        assert_eq!(field(&entry, "NULL"), None);
+        assert_eq!(field(&entry, "NUMBER"), None);
+        assert_eq!(field(&entry, "MISSING"), None);
+    }
+
+    #[test]
+    fn test_from_journald() {
+        let entries = r#"
+{"__REALTIME_TIMESTAMP": "1704103200000000", "_TRANSPORT": "kernel", "MESSAGE": "Linux version 6.8.0", "PRIORITY": "5"}
+{"__REALTIME_TIMESTAMP": "1704103201000000", "_SYSTEMD_UNIT": "init.scope", "UNIT": "nginx.service", "SYSLOG_IDENTIFIER": "systemd", "_PID": "1", "MESSAGE": "nginx.service: Main process exited, code=exited, status=1/FAILURE", "PRIORITY": "4"}
+{"__REALTIME_TIMESTAMP": "1704103201500000", "_SYSTEMD_UNIT": "nginx.service", "_COMM": "nginx", "MESSAGE": [98, 97, 100, 255], "PRIORITY": "3", "_HOSTNAME": "web1"}
+"#;
+        assert!(JournaldImporter.sniff(entries.as_bytes()));
+        assert!(!JournaldImporter.sniff(b"{\"name\": \"Event 1\", \"start\": 0}\n"));
+        let timeline = Timeline::from_journald(entries.as_bytes()).unwrap();
+        let events: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name(),
+                    event.location(),
+                    event.start_time() - 1_704_103_200_000_000_000,
+                )
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("Linux version 6.8.0", "kernel", 0),
+                (
+                    "nginx.service: Main process exited, code=exited, status=1/FAILURE",
+                    "nginx.service",
+                    1_000_000_000
+                ),
+                ("bad\u{fffd}", "nginx.service", 1_500_000_000),
+            ]
+        );
+        let (_, exited) = timeline.events().nth(1).unwrap();
+        assert_eq!(exited.metadata()["level"], "warn");
+        assert_eq!(exited.metadata()["identifier"], "systemd");
+        assert_eq!(exited.metadata()["pid"], "1");
+        let (_, bad) = timeline.events().nth(2).unwrap();
+        assert_eq!(bad.metadata()["level"], "err");
+        assert_eq!(bad.metadata()["hostname"], "web1");
+
+        // Priorities that aren't syslog levels have no level
+        let priorities = r#"
+{"__REALTIME_TIMESTAMP": "1", "MESSAGE": "emerg", "PRIORITY": "0"}
+{"__REALTIME_TIMESTAMP": "2", "MESSAGE": "out of range", "PRIORITY": "8"}
+{"__REALTIME_TIMESTAMP": "3", "MESSAGE": "not a number", "PRIORITY": "high"}
+"#;
+        let timeline = Timeline::from_journald(priorities.as_bytes()).unwrap();
+        let levels: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| event.metadata().get("level").map(String::as_str))
+            .collect();
+        assert_eq!(levels, [Some("emerg"), None, None]);
+    }
+
+    #[test]
+    fn test_rows() {
+        // The unit an entry is about wins over the one that logged it, then the program
+        let entries = r#"
+{"__REALTIME_TIMESTAMP": "1", "MESSAGE": "a", "_SYSTEMD_UNIT": "user@1000.service", "USER_UNIT": "app.service"}
+{"__REALTIME_TIMESTAMP": "2", "MESSAGE": "b", "_SYSTEMD_USER_UNIT": "app.service", "SYSLOG_IDENTIFIER": "app"}
+{"__REALTIME_TIMESTAMP": "3", "MESSAGE": "c", "SYSLOG_IDENTIFIER": "sudo", "_COMM": "sudo-rs"}
+{"__REALTIME_TIMESTAMP": "4", "MESSAGE": "d", "_COMM": "cron", "_TRANSPORT": "syslog"}
+{"__REALTIME_TIMESTAMP": "5", "MESSAGE": "e", "UNIT": null}
+"#;
+        let timeline = Timeline::from_journald(entries.as_bytes()).unwrap();
+        let rows: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| event.location())
+            .collect();
+        assert_eq!(
+            rows,
+            ["app.service", "app.service", "sudo", "cron", "unknown"]
+        );
+
+        // Empty messages are kept, while entries without a usable timestamp and other lines
+        // aren't
+        let entries = "\
+-- No entries --
+\x1e{\"__REALTIME_TIMESTAMP\": \"1000\", \"_COMM\": \"cron\", \"MESSAGE\": null}
+{\"__REALTIME_TIMESTAMP\": \"yesterday\", \"MESSAGE\": \"skipped\"}
+{\"MESSAGE\": \"skipped\"}
+[1, 2]
+";
+        let timeline = Timeline::from_journald(entries.as_bytes()).unwrap();
+        let events: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| (event.name(), event.location()))
+            .collect();
+        assert_eq!(events, [("", "cron")]);
+
+        // The records of `-o json-seq` start with a record separator
+        assert!(JournaldImporter.sniff(b"\x1e{\"__REALTIME_TIMESTAMP\": \"1000\"}\n"));
+        for entries in [
+            "-- No entries --\n",
+            "{\"__REALTIME_TIMESTAMP\": \"9223372036854775807\", \"MESSAGE\": \"late\"}\n",
+        ] {
+            assert!(matches!(
+                Timeline::from_journald(entries.as_bytes()),
+                Err(TimelineError::Parse(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use std::io::{BufRead, BufReader, Read};
+
+use serde_json::{Map, Value};
+
+use crate::chrome::text;
+use crate::import::{new_timeline, sniff_text};
+use crate::{EventBuilder, Timeline, TimelineError, TraceImporter};
+
+// Events as JSON objects, one per line, see `Timeline::from_json_lines`
+pub(crate) struct JsonLinesImporter;
+
+impl TraceImporter for JsonLinesImporter {
+    fn format(&self) -> &str {
+        "jsonl"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        sniff_text(data)
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .and_then(|line| serde_json::from_str::<Map<String, Value>>(line).ok())
+            .is_some_and(|record| record.contains_key("name") && record.contains_key("start"))
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_json_lines(data)
+    }
+}
+
+// A time field of a record, in nanoseconds
+fn time(record: &Value, key: &str) -> Option<i64> {
+    let value = &record[key];
+    value
+        .as_i64()
+        .or_else(|| value.as_f64().map(|time| time.round() as i64))
+}
+
+impl Timeline {
+    /// Read events written as JSON objects, one per line, into a timeline
+    ///
+    /// This is the format for scripts to write events in, as newline-delimited JSON. Every
+    /// line is an object with these fields, and blank lines are skipped:
+    ///
+    /// - `name`: the name of the event
+    /// - `location`: the row of the event
+    /// - `start`: the time the event starts at
+    /// - `end`: the time the event ends at, which instants don't have
+    /// - `metadata`: an optional object of the metadata of the event, with values that aren't
+    ///   strings written as JSON
+    /// - `triggers`: an optional array of the triggers from the row of the event, as objects
+    ///   with the `to` location the trigger goes to, the `time` it starts at, which is the end
+    ///   of the event if it's missing, and the `end` it arrives at, which is its `time` if it's
+    ///   missing
+    ///
+    /// Times are numbers of nanoseconds, and fields that aren't listed here are ignored.
+    ///
+    /// This returns `TimelineError::Parse` if a line isn't such an object or there are none,
+    /// `TimelineError::InvalidRange` if an event ends before it starts, and
+    /// `TimelineError::Io` if reading fails. `Timeline::import` detects files of this format, as
+    /// "jsonl".
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let lines = r#"
+    /// {"name": "read", "location": "CPU 0", "start": 0, "end": 5, "triggers": [{"to": "CPU 1"}]}
+    /// {"name": "write", "location": "CPU 1", "start": 6, "end": 9, "metadata": {"fd": 3}}
+    /// "#;
+    /// let timeline = Timeline::from_json_lines(lines.as_bytes()).unwrap();
+    /// assert_eq!(timeline.categories(), ["CPU 0", "CPU 1"]);
+    /// assert_eq!(timeline.triggers().count(), 1);
+    /// # }
+    /// ```
+    pub fn from_json_lines(reader: impl Read) -> Result<Timeline, TimelineError> {
+        let mut timeline = new_timeline();
+        let mut parsed = false;
+        for (number, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let error = |what: &str| TimelineError::Parse(format!("line {}: {}", number + 1, what));
+            let record: Value = serde_json::from_str(&line).map_err(|e| error(&e.to_string()))?;
+            let name = record["name"].as_str().ok_or_else(|| error("no name"))?;
+            let location = record["location"]
+                .as_str()
+                .ok_or_else(|| error("no location"))?;
+            let start = time(&record, "start").ok_or_else(|| error("no start time"))?;
+            let end = time(&record, "end");
+            let mut event = EventBuilder::new(name, start, end.unwrap_or(start), location);
+            if let Some(metadata) = record["metadata"].as_object() {
+                for (key, value) in metadata {
+                    event = event.metadata(key.clone(), text(value));
+                }
+            }
+            let mut event = event.build();
+            match end {
+                None => event.instant = true,
+                Some(end) if end < start => {
+                    return Err(TimelineError::InvalidRange(format!(
+                        "event {:?} ends at {:?} before it starts at {:?}",
+                        name, end, start
+                    )));
+                }
+                Some(_) => {}
+            }
+            timeline.insert_event(event);
+            for trigger in record["triggers"].as_array().into_iter().flatten() {
+                let to = trigger["to"]
+                    .as_str()
+                    .ok_or_else(|| error("trigger without a to location"))?;
+                let time_from = time(trigger, "time").unwrap_or(end.unwrap_or(start));
+                let time_to = time(trigger, "end").unwrap_or(time_from);
+                timeline.add_trigger_span(location, time_from, to, time_to);
+            }
+            parsed = true;
+        }
+        if !parsed {
+            return Err(TimelineError::Parse("no json lines".to_string()));
+        }
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time() {
+        let record = serde_json::json!({"int": 10, "float": 20.5, "text": "30", "big": 1e19});
+        assert_eq!(time(&record, "int"), Some(10));
+        assert_eq!(time(&record, "float"), Some(21));
+        assert_eq!(time(&record, "text"), None);
+        assert_eq!(time(&record, "missing"), None);
+        assert_eq!(time(&record, "big"), Some(i64::MAX));
+    }
+
+    #[test]
+    fn test_from_json_lines() {
+        let lines = r#"{"name": "read", "location": "CPU 0", "start": 10, "end": 20.4, "metadata": {"fd": 3, "path": "/tmp/a"}}
+
+{"name": "wake", "location": "CPU 0", "start": 15}
+{"name": "run", "location": "CPU 1", "start": 18, "end": 30, "extra": true}
+"#;
+        assert!(JsonLinesImporter.sniff(lines.as_bytes()));
+        // Journal entries and other JSON aren't events
+        assert!(
+            !JsonLinesImporter.sniff(b"{\"__REALTIME_TIMESTAMP\": \"1\", \"MESSAGE\": \"a\"}\n")
+        );
+        assert!(!JsonLinesImporter.sniff(b"[{\"name\": \"a\", \"start\": 1}]\n"));
+        let timeline = Timeline::from_json_lines(lines.as_bytes()).unwrap();
+        let events: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name(),
+                    event.location(),
+                    event.start_time(),
+                    event.end_time(),
+                    event.instant,
+                )
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("read", "CPU 0", 10, 20, false),
+                ("wake", "CPU 0", 15, 15, true),
+                ("run", "CPU 1", 18, 30, false),
+            ]
+        );
+        // Metadata that isn't text is written as JSON
+        let (_, read) = timeline.events().next().unwrap();
+        assert_eq!(read.metadata()["fd"], "3");
+        assert_eq!(read.metadata()["path"], "/tmp/a");
+
+        let lines = r#"{"name": "wake", "location": "CPU 0", "start": 15, "triggers": [{"to": "CPU 1", "end": 18}]}
+{"name": "send", "location": "CPU 0", "start": 20, "end": 25, "triggers": [{"to": "CPU 1"}, {"to": "CPU 2", "time": 22}]}
+"#;
+        // Triggers start at the end of their event, and arrive when they start, unless they
+        // say otherwise
+        let timeline = Timeline::from_json_lines(lines.as_bytes()).unwrap();
+        let triggers: Vec<_> = timeline
+            .triggers()
+            .map(|trigger| (trigger.time(), trigger.end_time(), trigger.end_location()))
+            .collect();
+        assert_eq!(
+            triggers,
+            [(15, 18, "CPU 1"), (25, 25, "CPU 1"), (22, 22, "CPU 2")]
+        );
+    }
+
+    #[test]
+    fn test_from_json_lines_errors() {
+        let error = |lines: &str| match Timeline::from_json_lines(lines.as_bytes()) {
+            Err(TimelineError::Parse(what)) => what,
+            other => panic!("{:?}", other.map(|_| ())),
+        };
+        // Errors say which line they are on
+        assert_eq!(
+            error("{\"name\": \"a\", \"location\": \"b\", \"start\": 1}\n{\"name\": \"a\", \"start\": 1}\n"),
+            "line 2: no location"
+        );
+        assert_eq!(
+            error("{\"location\": \"b\", \"start\": 1}\n"),
+            "line 1: no name"
+        );
+        assert_eq!(
+            error("{\"name\": \"a\", \"location\": \"b\", \"start\": \"1\"}\n"),
+            "line 1: no start time"
+        );
+        assert_eq!(
+            error("{\"name\": \"a\", \"location\": \"b\", \"start\": 1, \"triggers\": [{}]}\n"),
+            "line 1: trigger without a to location"
+        );
+        assert!(error("{\"name\": \n").starts_with("line 1: "));
+        assert_eq!(error("\n  \n"), "no json lines");
+        assert!(matches!(
+            Timeline::from_json_lines(
+                "{\"name\": \"a\", \"location\": \"b\", \"start\": 2, \"end\": 1}\n".as_bytes()
+            ),
+            Err(TimelineError::InvalidRange(_))
+        ));
+    }
+}
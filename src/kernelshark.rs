@@ -0,0 +1,223 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::{Event, Timeline, TimelineError};
+
+// The numbers in a list of a session, which are plain numbers in sessions of KernelShark 1, and
+// pairs of a data stream and a number in those of KernelShark 2
+fn numbers(list: &Value) -> Vec<i64> {
+    list.as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|item| match item {
+            Value::Array(pair) => pair.last()?.as_i64(),
+            Value::String(text) => text.parse().ok(),
+            number => number.as_i64(),
+        })
+        .collect()
+}
+
+// The pid of the task an event of a scheduler timeline is about
+fn pid(event: &Event<i64>) -> Option<i64> {
+    match event.metadata().get("pid") {
+        Some(pid) => pid.parse().ok(),
+        None => event.location().rsplit_once('-')?.1.parse().ok(),
+    }
+}
+
+// The CPU an event of a scheduler timeline ran on
+fn cpu(event: &Event<i64>) -> Option<i64> {
+    match event.metadata().get("cpu") {
+        Some(cpu) => cpu.parse().ok(),
+        None => event.location().strip_prefix("CPU ")?.parse().ok(),
+    }
+}
+
+// The task and CPU filters of a session, which keep the events of the tasks and CPUs they show,
+// or drop those of the ones they hide
+#[derive(Default)]
+struct Filters {
+    show_tasks: Option<HashSet<i64>>,
+    hide_tasks: HashSet<i64>,
+    show_cpus: Option<HashSet<i64>>,
+    hide_cpus: HashSet<i64>,
+}
+
+impl Filters {
+    // The filters of every data stream of KernelShark 2, or the filters of KernelShark 1
+    fn read(filters: &Value) -> Filters {
+        let streams = match filters["data streams"].as_array() {
+            Some(streams) => streams.iter().collect(),
+            None => vec![filters],
+        };
+        let mut all = Filters::default();
+        for stream in streams {
+            let show = |key: &str, set: &mut Option<HashSet<i64>>| {
+                let list = numbers(&stream[key]);
+                if !list.is_empty() {
+                    set.get_or_insert_with(HashSet::new).extend(list);
+                }
+            };
+            show("show task filter", &mut all.show_tasks);
+            show("show cpu filter", &mut all.show_cpus);
+            all.hide_tasks.extend(numbers(&stream["hide task filter"]));
+            all.hide_cpus.extend(numbers(&stream["hide cpu filter"]));
+        }
+        all
+    }
+
+    fn is_empty(&self) -> bool {
+        self.show_tasks.is_none()
+            && self.show_cpus.is_none()
+            && self.hide_tasks.is_empty()
+            && self.hide_cpus.is_empty()
+    }
+
+    fn allows(&self, event: &Event<i64>) -> bool {
+        let allows =
+            |value: Option<i64>, show: &Option<HashSet<i64>>, hide: &HashSet<i64>| match value {
+                Some(value) => {
+                    show.as_ref().is_none_or(|show| show.contains(&value)) && !hide.contains(&value)
+                }
+                None => show.is_none(),
+            };
+        allows(pid(event), &self.show_tasks, &self.hide_tasks)
+            && allows(cpu(event), &self.show_cpus, &self.hide_cpus)
+    }
+}
+
+impl Timeline {
+    /// Read a KernelShark session into a timeline of the view it saved
+    ///
+    /// `path` is a session file KernelShark exports, of version 1 or 2. The trace it was made
+    /// of, named in its "Data" and found next to the session if the name is relative, is
+    /// imported with `Timeline::import`, so text traces of ftrace are read as they are, and the
+    /// `.dat` files of trace-cmd with the `trace-cmd` feature. The view of the session is then
+    /// applied to the scheduler rows of the trace, like "CPU 1" and "bash-1234":
+    ///
+    /// - the timeline is cropped to the range of the model, the part of the trace on screen
+    /// - only the rows of the CPU and task plots are drawn, if the session has any
+    /// - the events of tasks and CPUs the task and CPU filters hide aren't drawn
+    ///
+    /// Event filters, markers, which point at entries rather than times, and plugins are
+    /// skipped. Times are in nanoseconds, like those of the trace.
+    ///
+    /// This returns `TimelineError::Parse` if the session isn't JSON of a KernelShark session
+    /// or doesn't name its trace, the errors of `Timeline::import` for the trace, and
+    /// `TimelineError::Io` if reading the session fails. `Timeline::import` doesn't detect
+    /// sessions, as they refer to other files.
+    pub fn from_kernelshark(path: impl AsRef<Path>) -> Result<Timeline, TimelineError> {
+        let path = path.as_ref();
+        let session: Value = serde_json::from_slice(&std::fs::read(path)?)
+            .map_err(|e| TimelineError::Parse(format!("{}: {}", path.display(), e)))?;
+        if session["type"] != "kshark.config.session" {
+            return Err(TimelineError::Parse(format!(
+                "{} isn't a KernelShark session",
+                path.display()
+            )));
+        }
+        let data = session["Data"]["file"].as_str().ok_or_else(|| {
+            TimelineError::Parse(format!("{} doesn't name its trace", path.display()))
+        })?;
+        let data = path.parent().unwrap_or(Path::new("")).join(data);
+        let mut timeline = Timeline::import(data)?;
+
+        if let [start, end] = numbers(&session["Model"]["range"])[..] {
+            if start <= end {
+                timeline.crop(start, end);
+            }
+        }
+        let mut rows: Vec<String> = numbers(&session["CPUPlots"])
+            .into_iter()
+            .map(|cpu| format!("CPU {}", cpu))
+            .collect();
+        for pid in numbers(&session["TaskPlots"]) {
+            let suffix = format!("-{}", pid);
+            rows.extend(
+                timeline
+                    .categories()
+                    .into_iter()
+                    .filter(|row| row.ends_with(&suffix))
+                    .map(str::to_string),
+            );
+        }
+        if !rows.is_empty() {
+            timeline.set_category_filter(Some(rows));
+        }
+        let filters = Filters::read(&session["Filters"]);
+        if !filters.is_empty() {
+            timeline.set_event_filter(move |event| filters.allows(event));
+        }
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_kernelshark() {
+        let trace = "# tracer: nop
+          <idle>-0       [000] d..2.     1.000000: sched_switch: prev_comm=swapper/0 prev_pid=0 prev_prio=120 prev_state=R ==> next_comm=bash next_pid=10 next_prio=120
+          <idle>-0       [001] d..2.     1.000001: sched_switch: prev_comm=swapper/1 prev_pid=0 prev_prio=120 prev_state=R ==> next_comm=cat next_pid=11 next_prio=120
+            bash-10      [000] d..2.     1.000005: sched_switch: prev_comm=bash prev_pid=10 prev_prio=120 prev_state=S ==> next_comm=swapper/0 next_pid=0 next_prio=120
+             cat-11      [001] d..2.     1.000009: sched_switch: prev_comm=cat prev_pid=11 prev_prio=120 prev_state=S ==> next_comm=swapper/1 next_pid=0 next_prio=120
+";
+        let session = r#"{
+            "type": "kshark.config.session",
+            "Data": {"type": "kshark.config.data", "file": "trace.txt", "time": 1700000000},
+            "Model": {"type": "kshark.config.model", "range": [1000002000, 1000008000], "bins": 1024},
+            "Filters": {
+                "type": "kshark.config.filter",
+                "data streams": [{"stream id": 0, "hide task filter": [11]}]
+            },
+            "CPUPlots": [[0, 0], [0, 1]],
+            "TaskPlots": [[0, 10]],
+            "Markers": {"markA": {"isSet": true, "row": 3}, "markB": {"isSet": false}}
+        }"#;
+        let dir =
+            std::env::temp_dir().join(format!("timeline-svg-kernelshark-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("trace.txt"), trace).unwrap();
+        std::fs::write(dir.join("session.json"), session).unwrap();
+        let timeline = Timeline::from_kernelshark(dir.join("session.json")).unwrap();
+        assert_eq!(timeline.time_range(), Some((1_000_002_000, 1_000_008_000)));
+        let drawn: Vec<_> = timeline
+            .events()
+            .filter(|(_, event)| timeline.is_drawn(event))
+            .map(|(_, event)| (event.name(), event.location()))
+            .collect();
+        assert_eq!(drawn, [("bash", "CPU 0"), ("running", "bash-10")]);
+
+        std::fs::write(
+            dir.join("session.json"),
+            r#"{"type": "kshark.config.data"}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            Timeline::from_kernelshark(dir.join("session.json")),
+            Err(TimelineError::Parse(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Sessions of KernelShark 1 list plain numbers, and filter the one stream
+        let filters = Filters::read(&serde_json::json!({
+            "show task filter": [10, 12],
+            "hide cpu filter": [1]
+        }));
+        let event = |location: &str, pid: Option<&str>| {
+            let mut builder = crate::EventBuilder::new("e", 0, 1, location);
+            if let Some(pid) = pid {
+                builder = builder.metadata("pid".to_string(), pid.to_string());
+            }
+            builder.build()
+        };
+        assert!(filters.allows(&event("bash-10", None)));
+        assert!(!filters.allows(&event("cat-11", None)));
+        assert!(filters.allows(&event("CPU 0", Some("12"))));
+        assert!(!filters.allows(&event("CPU 1", Some("12"))));
+    }
+}
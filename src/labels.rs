@@ -0,0 +1,74 @@
+use std::borrow::Cow;
+
+use crate::{Event, TimeValue, Timeline};
+
+pub(crate) type LabelFormatter<T> = Box<dyn Fn(&Event<T>) -> String + Send + Sync>;
+pub(crate) type TickFormatter = Box<dyn Fn(f64) -> String + Send + Sync>;
+
+impl<T: TimeValue> Timeline<T> {
+    /// Write the label of every event with `format` instead of its name
+    ///
+    /// This puts more on the chart than the name, like the duration of each event. It only
+    /// changes the text drawn on the events, not their names, so colors, filters and search still
+    /// go by the name.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("db_query".to_string(), 0, 1200, "thread 1".to_string());
+    /// timeline.set_label_formatter(|event| {
+    ///     let micros = (event.end_time() - event.start_time()) as f64 / 1000.0;
+    ///     format!("{} ({}µs)", event.name(), micros)
+    /// });
+    /// let mut output = Vec::new();
+    /// timeline.write(&mut output).unwrap();
+    /// assert!(String::from_utf8(output).unwrap().contains("db_query (1.2µs)"));
+    /// # }
+    /// ```
+    pub fn set_label_formatter(
+        &mut self,
+        format: impl Fn(&Event<T>) -> String + Send + Sync + 'static,
+    ) {
+        self.label_formatter = Some(Box::new(format));
+    }
+
+    /// Write the labels of the big ticks of the time axis with `format`
+    ///
+    /// `format` is called with the time of each tick in units from the origin of the axis, see
+    /// `set_axis_origin`, and replaces the built in labels, including wall-clock times. The
+    /// times of the bookmark index are written the same way.
+    pub fn set_tick_formatter(&mut self, format: impl Fn(f64) -> String + Send + Sync + 'static) {
+        self.tick_formatter = Some(Box::new(format));
+    }
+
+    // The text drawn on an event, see `set_label_formatter`
+    pub(crate) fn event_label<'a>(&self, event: &'a Event<T>) -> Cow<'a, str> {
+        match &self.label_formatter {
+            Some(format) => Cow::Owned(format(event)),
+            None => Cow::Borrowed(&event.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formatters() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1".to_string(), 0, 10, "Location 1".to_string());
+        timeline.set_label_formatter(|event| format!("{} @ {}", event.name(), event.location()));
+        timeline.set_tick_formatter(|offset| format!("t+{}", offset));
+
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.contains("Event 1 @ Location 1"));
+        assert!(svg.contains(">\nt+0\n</text>"));
+        assert!(svg.contains("<title>Event 1\n"));
+    }
+}
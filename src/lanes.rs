@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+
+use crate::{TimeValue, Timeline, TimelineError};
+
+/// How overlapping events on a row are packed into lanes, see `Timeline::set_lane_packing`
+///
+/// Each event goes into the first lane, from the top, where it doesn't overlap another event.
+/// The strategies differ in the order the events are placed in, which decides which events end
+/// up together in the upper lanes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LanePacking {
+    /// Place events by start time, which keeps the fewest lanes
+    StartTime,
+    /// Place the longest events first, so nested shorter events sit below the events around them
+    Duration,
+    /// Place events with a higher `EventStyle::z_index` first, so they take the upper lanes
+    Priority,
+}
+
+// The rows of a rendered timeline and the lanes within them, laid out once and kept until the
+// timeline changes, see `Timeline::invalidate_layout`
+pub(crate) struct Rows {
+    categories: Vec<String>,
+    // The number of lanes above each row
+    first_lanes: Vec<usize>,
+    // The number of lanes of all rows
+    lanes: usize,
+    // The lane of each event within its row, by index into the events
+    event_lanes: Vec<usize>,
+    // The group of each group header and the lane it is drawn in
+    headers: Vec<(String, usize)>,
+    // The y position of the top of each lane, and of the bottom of the last one, without the
+    // row padding
+    lane_tops: Vec<f64>,
+}
+
+impl Rows {
+    pub(crate) fn categories(&self) -> Vec<&str> {
+        self.categories.iter().map(String::as_str).collect()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn lanes(&self) -> usize {
+        self.lanes
+    }
+
+    pub(crate) fn headers(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.headers
+            .iter()
+            .map(|(group, lane)| (group.as_str(), *lane))
+    }
+
+    pub(crate) fn lane_top(&self, lane: usize) -> f64 {
+        self.lane_tops[lane]
+    }
+
+    // The y position of the bottom of the last row, without the row padding
+    pub(crate) fn bottom(&self) -> f64 {
+        self.lane_tops[self.lanes]
+    }
+}
+
+impl<T: TimeValue> Timeline<T> {
+    /// Set how overlapping events on a row are stacked
+    ///
+    /// By default every event of a row is drawn on the same line, so overlapping events cover each
+    /// other. With a `LanePacking` the row grows extra lanes as needed and overlapping events are
+    /// placed in different lanes. Events that only touch, one ending as the other starts, share a
+    /// lane.
+    pub fn set_lane_packing(&mut self, packing: Option<LanePacking>) {
+        self.lane_packing = packing;
+        self.invalidate_layout();
+    }
+
+    /// Set the height of the row of `category`, or go back to the height of all rows for `None`
+    ///
+    /// Rows are as high as set with `TimelineBuilder::row_height` by default, which can be too
+    /// little for a counter with a wide range or too much for a row of short markers. Every lane
+    /// of the row gets this height, see `set_lane_packing`, and the events, counters and states
+    /// on it are drawn as high as their row.
+    pub fn set_category_height(&mut self, category: impl Into<String>, height: Option<u64>) {
+        let category = category.into();
+        match height {
+            Some(height) => self.category_heights.insert(category, height),
+            None => self.category_heights.remove(&category),
+        };
+        self.invalidate_layout();
+    }
+
+    // The height of the lanes of a category, see `set_category_height`
+    pub(crate) fn category_height(&self, category: &str) -> f64 {
+        self.category_heights
+            .get(category)
+            .copied()
+            .unwrap_or(self.row_height) as f64
+    }
+
+    // The layout of the rows, laid out the first time it's needed after the timeline changed
+    pub(crate) fn rows(&self) -> &Rows {
+        self.layout.get_or_init(|| self.lay_out_rows())
+    }
+
+    // Throw away the layout of the rows after something it depends on changed, like the events,
+    // the rows, their order, filters, groups, parents and heights, or the lane packing
+    pub(crate) fn invalidate_layout(&mut self) {
+        self.layout.take();
+    }
+
+    // Lay out the rows, packing the events of each row into lanes
+    fn lay_out_rows(&self) -> Rows {
+        // Only rows with events that are drawn, and declared rows, counters and state tracks that
+        // aren't filtered out, see `set_event_filter`
+        let mut categories: Vec<&str> = self
+            .events
+            .iter()
+            .filter(|event| self.is_drawn(event))
+            .map(|event| event.location.as_str())
+            .chain(
+                self.declared_categories
+                    .iter()
+                    .chain(self.counters.keys())
+                    .chain(self.state_tracks.keys())
+                    .map(String::as_str)
+                    .filter(|category| self.is_row_drawn(category)),
+            )
+            .collect();
+        self.sort_categories(&mut categories);
+        let categories = self.nest_categories(categories);
+        let mut event_lanes = vec![0; self.events.len()];
+        let mut row_lanes: HashMap<&str, usize> = HashMap::new();
+        if let Some(packing) = self.lane_packing {
+            let mut by_row: HashMap<&str, Vec<usize>> = HashMap::new();
+            for (index, event) in self.events.iter().enumerate() {
+                if !self.is_drawn(event) {
+                    continue;
+                }
+                by_row.entry(&event.location).or_default().push(index);
+            }
+            for (category, mut indices) in by_row {
+                // Ties are placed in the order events are drawn, see `EventStyle`
+                let by_start = |&a: &usize, &b: &usize| self.events[a].draw_order(&self.events[b]);
+                match packing {
+                    LanePacking::StartTime => indices.sort_by(by_start),
+                    LanePacking::Duration => indices.sort_by(|a, b| {
+                        let duration = |&i: &usize| {
+                            let event = &self.events[i];
+                            event.end_time.offset_from(event.start_time, self.units)
+                        };
+                        duration(b)
+                            .total_cmp(&duration(a))
+                            .then_with(|| by_start(a, b))
+                    }),
+                    LanePacking::Priority => indices.sort_by(|a, b| {
+                        let (z_a, z_b) =
+                            (self.events[*a].style.z_index, self.events[*b].style.z_index);
+                        z_b.cmp(&z_a).then_with(|| by_start(a, b))
+                    }),
+                }
+                // The events of each lane sorted by start and end time. Events in a lane don't
+                // overlap, so their end times are sorted too, and of the events starting before
+                // the end of another only the last one can reach past its start.
+                let mut lanes: Vec<Vec<(T, T)>> = Vec::new();
+                for index in indices {
+                    let event = &self.events[index];
+                    let (start, end) = (event.start_time, event.end_time);
+                    let fits = |lane: &Vec<(T, T)>| {
+                        let before = lane.partition_point(|&(s, _)| s < end);
+                        before == 0 || lane[before - 1].1 <= start
+                    };
+                    let lane = lanes.iter().position(fits).unwrap_or_else(|| {
+                        lanes.push(Vec::new());
+                        lanes.len() - 1
+                    });
+                    // Events placed by start time go at the end, the others are inserted in order
+                    let lane_events = &mut lanes[lane];
+                    let at = lane_events.partition_point(|&(s, e)| (s, e) <= (start, end));
+                    lane_events.insert(at, (start, end));
+                    event_lanes[index] = lane;
+                }
+                row_lanes.insert(category, lanes.len());
+            }
+        }
+        let mut first_lanes = Vec::with_capacity(categories.len());
+        let mut headers = Vec::new();
+        let mut lanes = 0;
+        // The lanes start below the time axis, which is a row high
+        let mut lane_tops = vec![self.row_height as f64];
+        for category in &categories {
+            // Groups follow each other, so a header goes above the first row of each
+            if let Some(group) = self.category_group(category) {
+                if headers.last().is_none_or(|&(last, _)| last != group) {
+                    headers.push((group, lanes));
+                    lanes += 1;
+                    lane_tops.push(lane_tops[lanes - 1] + self.row_height as f64);
+                }
+            }
+            first_lanes.push(lanes);
+            let height = self.category_height(category);
+            for _ in 0..row_lanes.get(category).copied().unwrap_or(1) {
+                lanes += 1;
+                lane_tops.push(lane_tops[lanes - 1] + height);
+            }
+        }
+        Rows {
+            categories: categories.into_iter().map(str::to_string).collect(),
+            first_lanes,
+            lanes,
+            event_lanes,
+            headers: headers
+                .into_iter()
+                .map(|(group, lane)| (group.to_string(), lane))
+                .collect(),
+            lane_tops,
+        }
+    }
+
+    // Calculate the y position of a category
+    pub(crate) fn category_y(
+        &self,
+        category: &str,
+        rows: &Rows,
+    ) -> std::result::Result<f64, TimelineError> {
+        let row = rows
+            .categories
+            .iter()
+            .position(|c| *c == category)
+            .ok_or_else(|| TimelineError::UnknownLocation(category.to_string()))?;
+        Ok(rows.lane_tops[rows.first_lanes[row]] + self.row_padding as f64)
+    }
+
+    // Calculate the y position of the event at `index`, in its lane of its category
+    pub(crate) fn event_y(
+        &self,
+        index: usize,
+        rows: &Rows,
+    ) -> std::result::Result<f64, TimelineError> {
+        let location = &self.events[index].location;
+        let y = self.category_y(location, rows)?;
+        Ok(y + rows.event_lanes[index] as f64 * self.category_height(location))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventBuilder, EventStyle};
+
+    #[test]
+    fn test_lane_packing() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("short".to_string(), 0, 2, "CPU 0".to_string());
+        timeline.add_event("long".to_string(), 1, 10, "CPU 0".to_string());
+        timeline.add_event("after".to_string(), 2, 4, "CPU 0".to_string());
+        timeline.insert_event(
+            EventBuilder::new("urgent".to_string(), 3, 5, "CPU 0".to_string())
+                .style(EventStyle {
+                    z_index: 1,
+                    ..EventStyle::default()
+                })
+                .build(),
+        );
+        timeline.add_event("other".to_string(), 0, 10, "CPU 1".to_string());
+        let lanes = |timeline: &Timeline| {
+            let rows = timeline.rows();
+            (rows.event_lanes.clone(), rows.lanes())
+        };
+
+        // Without packing events share the line of their row
+        assert_eq!(lanes(&timeline), (vec![0, 0, 0, 0, 0], 2));
+        timeline.set_lane_packing(Some(LanePacking::StartTime));
+        assert_eq!(lanes(&timeline), (vec![0, 1, 0, 2, 0], 4));
+        timeline.set_lane_packing(Some(LanePacking::Duration));
+        assert_eq!(lanes(&timeline), (vec![1, 0, 1, 2, 0], 4));
+        timeline.set_lane_packing(Some(LanePacking::Priority));
+        assert_eq!(lanes(&timeline), (vec![0, 1, 2, 0, 0], 4));
+
+        let rows = timeline.rows();
+        assert_eq!(timeline.category_y("CPU 1", rows).unwrap(), 81.0);
+        assert_eq!(timeline.event_y(1, rows).unwrap(), 41.0);
+    }
+
+    #[test]
+    fn test_lane_packing_many() {
+        // Events of every length, instants among them, in a made up but repeatable order
+        let mut timeline = Timeline::default();
+        let mut seed = 7u64;
+        for _ in 0..2000 {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let start = (seed >> 33) as i64 % 5000;
+            let length = (seed >> 20) as i64 % 40 * ((seed >> 10) as i64 % 3);
+            timeline.add_event("Event", start, start + length, "CPU 0");
+        }
+        for packing in [LanePacking::Duration, LanePacking::Priority] {
+            timeline.set_lane_packing(Some(packing));
+            let rows = timeline.rows();
+            for (i, a) in timeline.events.iter().enumerate() {
+                for (j, b) in timeline.events.iter().enumerate().skip(i + 1) {
+                    assert!(
+                        rows.event_lanes[i] != rows.event_lanes[j]
+                            || !(a.start_time < b.end_time && b.start_time < a.end_time),
+                        "{:?} puts {:?} and {:?} in one lane",
+                        packing,
+                        (a.start_time, a.end_time),
+                        (b.start_time, b.end_time)
+                    );
+                }
+            }
+        }
+
+        // By start time events go into the first lane none of the events before them overlap
+        timeline.set_lane_packing(Some(LanePacking::StartTime));
+        let events = &timeline.events;
+        let mut order: Vec<usize> = (0..events.len()).collect();
+        order.sort_by(|&a, &b| events[a].draw_order(&events[b]));
+        let mut lanes: Vec<Vec<usize>> = Vec::new();
+        let mut expected = vec![0; events.len()];
+        for index in order {
+            let (start, end) = (events[index].start_time, events[index].end_time);
+            let overlaps =
+                |&other: &usize| start < events[other].end_time && events[other].start_time < end;
+            let lane = match lanes.iter().position(|lane| !lane.iter().any(overlaps)) {
+                Some(lane) => lane,
+                None => {
+                    lanes.push(Vec::new());
+                    lanes.len() - 1
+                }
+            };
+            lanes[lane].push(index);
+            expected[index] = lane;
+        }
+        assert_eq!(timeline.rows().event_lanes, expected);
+    }
+
+    #[test]
+    fn test_category_height() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event".to_string(), 0, 2, "CPU 0".to_string());
+        timeline.add_event("Event".to_string(), 1, 3, "CPU 0".to_string());
+        timeline.add_event("Event".to_string(), 0, 3, "CPU 1".to_string());
+        timeline.set_lane_packing(Some(LanePacking::StartTime));
+        timeline.set_category_height("CPU 0".to_string(), Some(30));
+
+        let rows = timeline.rows();
+        assert_eq!(rows.bottom(), 100.0);
+        assert_eq!(timeline.event_y(1, rows).unwrap(), 51.0);
+        assert_eq!(timeline.category_y("CPU 1", rows).unwrap(), 81.0);
+
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert_eq!(svg.matches(r#"height="30""#).count(), 2);
+        assert!(svg.contains(r#"width="400" x="200" y="51""#));
+        assert!(svg.contains(r#"width="600" x="0" y="81""#));
+        assert!(svg.contains(r#"<svg height="101""#));
+
+        timeline.set_category_height("CPU 0".to_string(), None);
+        assert_eq!(timeline.rows().bottom(), 80.0);
+    }
+
+    #[test]
+    fn test_layout_cache() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event".to_string(), 0, 2, "CPU 1".to_string());
+        assert!(std::ptr::eq(timeline.rows(), timeline.rows()));
+        assert_eq!(timeline.rows().categories(), ["CPU 1"]);
+
+        // Everything the rows are laid out from throws the layout away
+        timeline.add_event("Event".to_string(), 0, 2, "CPU 0".to_string());
+        assert_eq!(timeline.rows().categories(), ["CPU 0", "CPU 1"]);
+        timeline.set_category_order(vec!["CPU 1".to_string()]);
+        assert_eq!(timeline.rows().categories(), ["CPU 1", "CPU 0"]);
+        timeline.set_category_filter(Some(vec!["CPU 0".to_string()]));
+        assert_eq!(timeline.rows().categories(), ["CPU 0"]);
+        timeline.clear_filters();
+        timeline.add_counter_sample("Memory".to_string(), 1, 5.0);
+        assert_eq!(timeline.rows().categories(), ["CPU 1", "CPU 0", "Memory"]);
+    }
+}
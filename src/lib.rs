@@ -1,12 +1,114 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
-use std::io::{Result, Write};
-use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Result, Write};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
 use rand::prelude::*;
 use svg::node::element::path::Data;
-use svg::node::element::{Group, Line, Path, Rectangle, Text};
+use svg::node::element::{Anchor, Element, Group, Line, Path, Rectangle, Text, Title};
+use svg::Node;
 
-const COLORS: &'static [&'static str] = &[
+#[macro_use]
+mod logging;
+mod ascii;
+#[cfg(feature = "tokio")]
+mod async_io;
+mod atrace;
+mod blkparse;
+mod bookmarks;
+mod bpftrace;
+mod builder;
+mod capture;
+mod chrome;
+mod counters;
+mod criterion;
+mod csv;
+#[cfg(feature = "ctf")]
+mod ctf;
+mod data;
+mod data_uri;
+mod dmesg;
+mod error;
+mod event;
+mod export;
+mod filter;
+mod ftrace;
+mod fxt;
+mod graphviz;
+mod groups;
+mod hierarchy;
+mod html;
+mod import;
+mod interactive;
+mod jaeger;
+mod journald;
+mod jsonl;
+mod kernelshark;
+mod labels;
+mod lanes;
+mod links;
+mod merge;
+mod mermaid;
+mod order;
+mod otlp;
+#[cfg(feature = "pcap")]
+mod pcap;
+mod perf;
+#[cfg(feature = "perfetto")]
+mod perfetto;
+mod plantuml;
+#[cfg(feature = "pprof")]
+mod pprof;
+mod qemu;
+mod query;
+#[cfg(feature = "raster")]
+mod raster;
+mod report;
+mod search;
+mod spans;
+mod speedscope;
+mod split;
+mod states;
+mod stats;
+mod strace;
+mod stream;
+mod sysstat;
+mod systemd;
+pub mod testing;
+mod tiles;
+mod time;
+mod tokio_tasks;
+#[cfg(feature = "trace-cmd")]
+mod tracecmd;
+mod tshark;
+#[cfg(feature = "verify")]
+mod verify;
+#[cfg(feature = "chrono")]
+mod wallclock;
+mod zipkin;
+
+pub use builder::TimelineBuilder;
+pub use capture::{scope, Scope};
+pub use counters::CounterStyle;
+pub use csv::CsvMapping;
+pub use error::TimelineError;
+pub use event::{Event, EventBuilder, EventId, EventStyle};
+pub use export::{export_formats, register_exporter, TimelineExporter};
+pub use import::{import_formats, register_importer, TraceImporter};
+pub use lanes::LanePacking;
+pub use order::natural_cmp;
+#[cfg(feature = "pcap")]
+pub use pcap::PacketEvents;
+pub use search::{EventMatch, SearchOptions};
+pub use spans::SpanRows;
+pub use split::Split;
+pub use stats::LatencyStats;
+pub use time::TimeValue;
+#[cfg(feature = "verify")]
+pub use verify::{verify_svg, Problem, VerifyReport};
+
+const COLORS: &[&str] = &[
     "blue",
     "red",
     "green",
@@ -31,6 +133,8 @@ const COLORS: &'static [&'static str] = &[
     "white",
 ];
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TimeUnit {
     Nanoseconds,
     Microseconds,
@@ -41,78 +145,1005 @@ pub enum TimeUnit {
     Days,
 }
 
-pub struct Timeline {
-    start_time: u64,
-    end_time: u64,
-    events: Vec<Event>,
-    triggers: Vec<Trigger>,
+impl TimeUnit {
+    /// The number of nanoseconds in one of this unit
+    pub fn nanoseconds(&self) -> u128 {
+        match self {
+            TimeUnit::Nanoseconds => 1,
+            TimeUnit::Microseconds => 1_000,
+            TimeUnit::Milliseconds => 1_000_000,
+            TimeUnit::Seconds => 1_000_000_000,
+            TimeUnit::Minutes => 60_000_000_000,
+            TimeUnit::Hours => 3_600_000_000_000,
+            TimeUnit::Days => 86_400_000_000_000,
+        }
+    }
+
+    /// Convert a `Duration` into a number of this unit, rounding down
+    pub fn from_duration(&self, duration: Duration) -> u64 {
+        (duration.as_nanos() / self.nanoseconds()) as u64
+    }
+}
+
+/// The drawing layers of a rendered timeline
+///
+/// Every element of the SVG is drawn into one of these layers, and the layers are emitted in the
+/// order configured with `Timeline::set_layer_order`, so later layers are drawn on top of earlier
+/// ones. The default order is the order the variants are declared in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Layer {
+    Background,
+    Rows,
+    Events,
+    Triggers,
+    Annotations,
+}
+
+impl Layer {
+    fn class(&self) -> &'static str {
+        match self {
+            Layer::Background => "layer-background",
+            Layer::Rows => "layer-rows",
+            Layer::Events => "layer-events",
+            Layer::Triggers => "layer-triggers",
+            Layer::Annotations => "layer-annotations",
+        }
+    }
+}
+
+/// How times are scaled to pixels
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScaleMode {
+    /// Every time unit is one column, `column_width` pixels wide
+    PerUnit,
+    /// The whole time range is scaled to fit the given width in pixels
+    FitWidth(u64),
+}
+
+/// Empty space around the rendered timeline, in pixels
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Margins {
+    pub top: u64,
+    pub right: u64,
+    pub bottom: u64,
+    pub left: u64,
+}
+
+// Ticks on the time axis are spaced at least this many pixels apart
+const MIN_TICK_SPACING: f64 = 100.0;
+
+/// The shape drawn for an event
+///
+/// Events are drawn as plain rectangles by default. The other shapes can be used to visually
+/// distinguish kinds of events, see `Timeline::set_event_shape`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Shape {
+    Rectangle,
+    /// A rectangle with corners rounded by the given `rx` and `ry` radii
+    Rounded {
+        rx: u64,
+        ry: u64,
+    },
+    /// A rectangle with both ends pointed
+    Hexagon,
+    /// A rectangle with the start notched and the end pointed, like an arrow
+    Chevron,
+}
+
+impl Shape {
+    // Build the element for this shape covering the given box
+    fn element(&self, x: f64, y: f64, width: f64, height: f64) -> Element {
+        let tip = (height / 2.0).min(width / 2.0);
+        let middle = y + height / 2.0;
+        let points = match self {
+            Shape::Rectangle | Shape::Rounded { .. } => {
+                let mut rect = Element::new("rect");
+                rect.assign("x", x);
+                rect.assign("y", y);
+                rect.assign("width", width);
+                rect.assign("height", height);
+                if let Shape::Rounded { rx, ry } = self {
+                    rect.assign("rx", *rx);
+                    rect.assign("ry", *ry);
+                }
+                return rect;
+            }
+            Shape::Hexagon => vec![
+                (x, middle),
+                (x + tip, y),
+                (x + width - tip, y),
+                (x + width, middle),
+                (x + width - tip, y + height),
+                (x + tip, y + height),
+            ],
+            Shape::Chevron => vec![
+                (x, y),
+                (x + width - tip, y),
+                (x + width, middle),
+                (x + width - tip, y + height),
+                (x, y + height),
+                (x + tip, middle),
+            ],
+        };
+        let points = points
+            .iter()
+            .map(|(x, y)| format!("{},{}", x, y))
+            .collect::<Vec<String>>()
+            .join(" ");
+        let mut polygon = Element::new("polygon");
+        polygon.assign("points", points);
+        polygon
+    }
+}
+
+/// How events with no duration are drawn
+///
+/// An event whose start and end time are the same has no width. By default it is drawn with
+/// its shape at the minimum event width, see `Timeline::set_min_event_width`, but it can also be
+/// drawn as a marker centered on its time instead. Events added with `Timeline::add_instant` are
+/// always drawn as a marker, a diamond unless another one is picked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InstantStyle {
+    /// Draw the event shape at the minimum event width
+    Shape,
+    /// Draw a vertical tick spanning the row
+    Tick,
+    /// Draw a diamond as tall as the row
+    Diamond,
+    /// Draw a flag with its pole on the time and a pennant to the right
+    Flag,
+}
+
+impl InstantStyle {
+    // Build the marker element for an instant at x on the row starting at y
+    fn element(&self, x: f64, y: f64, height: f64) -> Option<Element> {
+        match self {
+            InstantStyle::Shape => None,
+            InstantStyle::Tick => {
+                let mut line = Element::new("line");
+                line.assign("x1", x);
+                line.assign("y1", y);
+                line.assign("x2", x);
+                line.assign("y2", y + height);
+                line.assign("stroke-width", 2);
+                Some(line)
+            }
+            InstantStyle::Diamond => {
+                let half = height / 2.0;
+                let points = format!(
+                    "{},{} {},{} {},{} {},{}",
+                    x,
+                    y,
+                    x + half,
+                    y + half,
+                    x,
+                    y + height,
+                    x - half,
+                    y + half
+                );
+                let mut polygon = Element::new("polygon");
+                polygon.assign("points", points);
+                Some(polygon)
+            }
+            InstantStyle::Flag => {
+                let half = height / 2.0;
+                let d = format!(
+                    "M{},{} L{},{} L{},{} L{},{}",
+                    x,
+                    y + height,
+                    x,
+                    y,
+                    x + half,
+                    y + half / 2.0,
+                    x,
+                    y + half
+                );
+                let mut path = Element::new("path");
+                path.assign("d", d);
+                Some(path)
+            }
+        }
+    }
+}
+
+/// How row labels show the utilization of their row
+///
+/// The utilization of a row is the fraction of the timeline during which it has at least one
+/// event. It is shown on a scale from green for idle rows to red for saturated ones, see
+/// `Timeline::set_label_heat`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LabelHeat {
+    /// Color the label text itself
+    Text,
+    /// Draw a colored swatch in front of the label
+    Swatch,
+}
+
+// The heat color of a utilization between 0 and 1, from green through yellow to red
+fn heat_color(utilization: f64) -> String {
+    let hue = 120.0 * (1.0 - utilization.clamp(0.0, 1.0));
+    format!("hsl({:.0},80%,45%)", hue)
+}
+
+/// What `Timeline::try_add_event` does with events that start and end at the same time
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ZeroLength {
+    /// Add the event, it is drawn as described by `InstantStyle`
+    #[default]
+    Allow,
+    /// Return `TimelineError::InvalidRange`
+    Reject,
+}
+
+/// Options for `Timeline::try_add_event`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ValidationOptions {
+    /// How events without a duration are handled
+    pub zero_length: ZeroLength,
+    /// Warn about events with the same name, times and location as an existing event, through
+    /// the `log` crate when the `log` feature is enabled
+    pub warn_duplicates: bool,
+}
+
+/// Options for `Timeline::save_with`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SaveOptions {
+    /// Create any missing parent directories of the file
+    pub create_dirs: bool,
+}
+
+// Write a file atomically by writing to a temporary file in the same directory and renaming it
+// over `path` once `write` succeeds. A path of "-" writes to stdout instead.
+fn write_file(
+    path: &std::path::Path,
+    options: SaveOptions,
+    write: impl FnOnce(&mut dyn Write) -> Result<()>,
+) -> Result<()> {
+    if path.as_os_str() == "-" {
+        let stdout = std::io::stdout();
+        let mut lock = stdout.lock();
+        write(&mut lock)?;
+        return lock.flush();
+    }
+
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => std::path::Path::new("."),
+    };
+    if options.create_dirs {
+        std::fs::create_dir_all(dir)?;
+    }
+    let name = path
+        .file_name()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "path has no file name"))?;
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(name);
+    tmp_name.push(format!(".tmp{}", std::process::id()));
+    let tmp_path = dir.join(tmp_name);
+
+    let result = File::create(&tmp_path).and_then(|file| {
+        let mut writer = std::io::BufWriter::new(file);
+        write(&mut writer)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()
+    });
+    match result.and_then(|_| std::fs::rename(&tmp_path, path)) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let _ = std::fs::remove_file(&tmp_path);
+            Err(e)
+        }
+    }
+}
+
+/// The static text used when rendering a timeline
+///
+/// Every piece of text the library generates itself, as opposed to event names and marker labels
+/// which come from the caller, is taken from this table so that it can be translated. Override
+/// the fields you need and take the rest from `Strings::default()`, then hand the table to
+/// `Timeline::set_strings`.
+///
+/// ```
+/// # extern crate timeline_svg;
+/// # fn main() {
+/// use timeline_svg::{Strings, Timeline};
+///
+/// let mut timeline = Timeline::default();
+/// timeline.set_strings(Strings {
+///     axis_caption: "Zeit".to_string(),
+///     ..Strings::default()
+/// });
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Strings {
+    /// Caption of the time axis, rendered as "caption (unit)"
+    pub axis_caption: String,
+    pub nanoseconds: String,
+    pub microseconds: String,
+    pub milliseconds: String,
+    pub seconds: String,
+    pub minutes: String,
+    pub hours: String,
+    pub days: String,
+    /// Shown instead of the unit when the axis shows wall-clock times
+    pub utc: String,
+    /// Labels of the markers at the start and end of `Timeline::capture`
+    pub capture_start: String,
+    pub capture_end: String,
+    /// Caption of the index of `Timeline::add_bookmark`
+    pub bookmarks: String,
+    /// Names of the details in the tooltips of events
+    pub tooltip_start: String,
+    pub tooltip_end: String,
+    pub tooltip_duration: String,
+    pub tooltip_location: String,
+    /// Names of the statistics of events of the same name in the tooltips of
+    /// `Timeline::write_html`
+    pub tooltip_count: String,
+    pub tooltip_total: String,
+    pub tooltip_mean: String,
+    pub tooltip_max: String,
+    /// Title and reset button of the page written by `Timeline::write_html`
+    pub page_title: String,
+    pub reset_zoom: String,
+    /// Headings of the sections of `Timeline::write_report`, and of its column of event names
+    pub report_legend: String,
+    pub report_durations: String,
+    pub report_longest: String,
+    pub report_name: String,
+}
+
+impl Default for Strings {
+    fn default() -> Self {
+        Strings {
+            axis_caption: "time".to_string(),
+            nanoseconds: "ns".to_string(),
+            microseconds: "µs".to_string(),
+            milliseconds: "ms".to_string(),
+            seconds: "s".to_string(),
+            minutes: "min".to_string(),
+            hours: "h".to_string(),
+            days: "d".to_string(),
+            utc: "UTC".to_string(),
+            capture_start: "start".to_string(),
+            capture_end: "end".to_string(),
+            bookmarks: "Bookmarks".to_string(),
+            tooltip_start: "start".to_string(),
+            tooltip_end: "end".to_string(),
+            tooltip_duration: "duration".to_string(),
+            tooltip_location: "location".to_string(),
+            tooltip_count: "count".to_string(),
+            tooltip_total: "total".to_string(),
+            tooltip_mean: "mean".to_string(),
+            tooltip_max: "max".to_string(),
+            page_title: "Timeline".to_string(),
+            reset_zoom: "Reset zoom".to_string(),
+            report_legend: "Legend".to_string(),
+            report_durations: "Durations".to_string(),
+            report_longest: "Longest events".to_string(),
+            report_name: "name".to_string(),
+        }
+    }
+}
+
+impl Strings {
+    /// The label for `unit`
+    pub fn unit(&self, unit: &TimeUnit) -> &str {
+        match unit {
+            TimeUnit::Nanoseconds => &self.nanoseconds,
+            TimeUnit::Microseconds => &self.microseconds,
+            TimeUnit::Milliseconds => &self.milliseconds,
+            TimeUnit::Seconds => &self.seconds,
+            TimeUnit::Minutes => &self.minutes,
+            TimeUnit::Hours => &self.hours,
+            TimeUnit::Days => &self.days,
+        }
+    }
+}
+
+const DEFAULT_LAYER_ORDER: &[Layer] = &[
+    Layer::Background,
+    Layer::Rows,
+    Layer::Events,
+    Layer::Triggers,
+    Layer::Annotations,
+];
+
+// Get the group for a layer, creating it the first time something is drawn on that layer
+fn layer_group(layers: &mut HashMap<Layer, Group>, layer: Layer) -> &mut Group {
+    layers
+        .entry(layer)
+        .or_insert_with(|| Group::new().set("class", layer.class()))
+}
+
+// Whether the x positions from `start` to `end` are at least partly between those of `visible`,
+// which is everything if there is no `visible` range
+fn overlaps(visible: Option<(f64, f64)>, start: f64, end: f64) -> bool {
+    visible.is_none_or(|(left, right)| end >= left && start <= right)
+}
+
+/// A timeline of events, rendered as an SVG
+///
+/// The times of the timeline are of type `T`, see `TimeValue` for the types that can be used.
+///
+/// With the `serde` feature a timeline can be serialized with everything added to it and its
+/// settings, and deserialized later to render it again. The epoch, the random color picker and
+/// the filters and category comparator aren't serialized. Deserializing fails if the events and
+/// their handles don't add up, and picks colors for names that have none.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        try_from = "TimelineData<T>",
+        bound(deserialize = "T: TimeValue + serde::Deserialize<'de>")
+    )
+)]
+pub struct Timeline<T = i64> {
+    start_time: T,
+    end_time: T,
+    events: Vec<Event<T>>,
+    // The handle of each event, increasing so a handle can be found with a binary search
+    event_ids: Vec<EventId>,
+    next_event_id: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    index: OnceLock<query::EventIndex<T>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    layout: OnceLock<lanes::Rows>,
+    triggers: Vec<Trigger<T>>,
+    links: Vec<links::Link>,
+    markers: Vec<Marker<T>>,
+    bookmarks: Vec<bookmarks::Bookmark<T>>,
+    // The samples of each counter, in the order they were added
+    counters: BTreeMap<String, Vec<(T, f64)>>,
+    counter_styles: HashMap<String, CounterStyle>,
+    counter_gridlines: bool,
+    // The transitions of each state track, in the order they were added
+    state_tracks: BTreeMap<String, Vec<(T, String)>>,
+    highlights: Vec<Highlight<T>>,
+    layer_order: Vec<Layer>,
+    colormap: HashMap<String, String>,
+    // Whether running out of palette colors was reported already
+    #[cfg_attr(feature = "serde", serde(skip))]
+    palette_exhausted: bool,
+    shapes: HashMap<String, Shape>,
+    default_shape: Shape,
+    min_event_width: u64,
+    instant_style: InstantStyle,
+    lane_packing: Option<LanePacking>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    filter: filter::EventFilter<T>,
+    // Categories that get a row even without events
+    declared_categories: Vec<String>,
+    category_order: Vec<String>,
+    category_groups: HashMap<String, String>,
+    category_parents: HashMap<String, String>,
+    category_heights: HashMap<String, u64>,
+    parent_summary: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    category_comparator: Option<order::Comparator>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    label_formatter: Option<labels::LabelFormatter<T>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    tick_formatter: Option<labels::TickFormatter>,
+    zoom_ticks: u32,
+    measure_cursors: bool,
+    label_gutter: Option<u64>,
+    label_heat: Option<LabelHeat>,
+    validation: ValidationOptions,
+    units: TimeUnit,
+    strings: Strings,
+    palette: Vec<String>,
+    scale_mode: ScaleMode,
+    margins: Margins,
+    trigger_threshold: Option<usize>,
+    instant_threshold: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    epoch: Option<Instant>,
+    axis_origin: Option<T>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    rng: Option<StdRng>,
+    #[cfg(feature = "chrono")]
+    wall_clock: bool,
+    row_height: u64,
+    column_width: u64,
+    row_padding: u64,
+    column_padding: u64,
+}
+
+// Deserialize a palette, falling back to the default colors for an empty one like
+// `TimelineBuilder::palette` does, so colors can still be picked for new event names
+#[cfg(feature = "serde")]
+fn palette_or_default<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Vec<String>, D::Error> {
+    let palette: Vec<String> = serde::Deserialize::deserialize(deserializer)?;
+    if palette.is_empty() {
+        return Ok(COLORS.iter().map(|color| color.to_string()).collect());
+    }
+    Ok(palette)
+}
+
+// A deserialized timeline, before it is checked and the state that is derived from everything
+// on it is rebuilt, so that a document that was edited or made up can't break the invariants
+// the rest of the crate relies on
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct TimelineData<T> {
+    start_time: T,
+    end_time: T,
+    events: Vec<Event<T>>,
+    event_ids: Vec<EventId>,
+    next_event_id: usize,
+    triggers: Vec<Trigger<T>>,
+    links: Vec<links::Link>,
+    markers: Vec<Marker<T>>,
+    bookmarks: Vec<bookmarks::Bookmark<T>>,
+    counters: BTreeMap<String, Vec<(T, f64)>>,
+    counter_styles: HashMap<String, CounterStyle>,
+    counter_gridlines: bool,
+    state_tracks: BTreeMap<String, Vec<(T, String)>>,
+    highlights: Vec<Highlight<T>>,
+    layer_order: Vec<Layer>,
+    colormap: HashMap<String, String>,
+    shapes: HashMap<String, Shape>,
+    default_shape: Shape,
+    min_event_width: u64,
+    instant_style: InstantStyle,
+    lane_packing: Option<LanePacking>,
+    declared_categories: Vec<String>,
+    category_order: Vec<String>,
+    category_groups: HashMap<String, String>,
+    category_parents: HashMap<String, String>,
+    category_heights: HashMap<String, u64>,
+    parent_summary: bool,
+    zoom_ticks: u32,
+    measure_cursors: bool,
+    label_gutter: Option<u64>,
+    label_heat: Option<LabelHeat>,
+    validation: ValidationOptions,
     units: TimeUnit,
+    strings: Strings,
+    #[serde(deserialize_with = "palette_or_default")]
+    palette: Vec<String>,
+    scale_mode: ScaleMode,
+    margins: Margins,
+    trigger_threshold: Option<usize>,
+    instant_threshold: Option<usize>,
+    axis_origin: Option<T>,
+    #[cfg(feature = "chrono")]
+    wall_clock: bool,
     row_height: u64,
     column_width: u64,
     row_padding: u64,
     column_padding: u64,
 }
 
-struct Event {
-    name: String,
-    start_time: u64,
-    end_time: u64,
-    location: String,
+#[cfg(feature = "serde")]
+impl<T: TimeValue> TryFrom<TimelineData<T>> for Timeline<T> {
+    type Error = TimelineError;
+
+    fn try_from(data: TimelineData<T>) -> std::result::Result<Self, TimelineError> {
+        // Handles are found with a binary search, so there has to be one per event, increasing
+        if data.event_ids.len() != data.events.len()
+            || data.event_ids.windows(2).any(|ids| ids[0] >= ids[1])
+        {
+            return Err(TimelineError::Parse(format!(
+                "{} event handles for {} events",
+                data.event_ids.len(),
+                data.events.len()
+            )));
+        }
+        if let Some(event) = data
+            .events
+            .iter()
+            .find(|event| event.end_time < event.start_time)
+        {
+            return Err(TimelineError::InvalidRange(format!(
+                "event {:?} ends before it starts",
+                event.name
+            )));
+        }
+        // Handles are never reused, also those of events removed before the timeline was saved
+        let next_event_id = data
+            .event_ids
+            .last()
+            .map_or(data.next_event_id, |id| data.next_event_id.max(id.0 + 1));
+        let mut timeline = Timeline {
+            start_time: data.start_time,
+            end_time: data.end_time,
+            events: data.events,
+            event_ids: data.event_ids,
+            next_event_id,
+            index: OnceLock::new(),
+            layout: OnceLock::new(),
+            triggers: data.triggers,
+            links: data.links,
+            markers: data.markers,
+            bookmarks: data.bookmarks,
+            counters: data.counters,
+            counter_styles: data.counter_styles,
+            counter_gridlines: data.counter_gridlines,
+            state_tracks: data.state_tracks,
+            highlights: data.highlights,
+            layer_order: data.layer_order,
+            colormap: data.colormap,
+            palette_exhausted: false,
+            shapes: data.shapes,
+            default_shape: data.default_shape,
+            min_event_width: data.min_event_width,
+            instant_style: data.instant_style,
+            lane_packing: data.lane_packing,
+            filter: filter::EventFilter::default(),
+            declared_categories: data.declared_categories,
+            category_order: data.category_order,
+            category_groups: data.category_groups,
+            category_parents: data.category_parents,
+            category_heights: data.category_heights,
+            parent_summary: data.parent_summary,
+            category_comparator: None,
+            label_formatter: None,
+            tick_formatter: None,
+            zoom_ticks: data.zoom_ticks,
+            measure_cursors: data.measure_cursors,
+            label_gutter: data.label_gutter,
+            label_heat: data.label_heat,
+            validation: data.validation,
+            units: data.units,
+            strings: data.strings,
+            palette: data.palette,
+            scale_mode: data.scale_mode,
+            margins: data.margins,
+            trigger_threshold: data.trigger_threshold,
+            instant_threshold: data.instant_threshold,
+            epoch: None,
+            axis_origin: data.axis_origin,
+            rng: None,
+            #[cfg(feature = "chrono")]
+            wall_clock: data.wall_clock,
+            row_height: data.row_height,
+            column_width: data.column_width,
+            row_padding: data.row_padding,
+            column_padding: data.column_padding,
+        };
+
+        // Everything drawn in the color of its name needs one
+        let names: Vec<String> = timeline
+            .events
+            .iter()
+            .map(|event| event.name.clone())
+            .chain(timeline.counters.keys().cloned())
+            .chain(
+                timeline
+                    .state_tracks
+                    .values()
+                    .flatten()
+                    .map(|(_, state)| state.clone()),
+            )
+            .collect();
+        for name in names {
+            timeline.assign_color(&name);
+        }
+        // The time range covers everything on the timeline, and whatever range `crop` left
+        let (start, end) = (timeline.start_time, timeline.end_time);
+        timeline.update_bounds();
+        if start <= end {
+            if start < timeline.start_time {
+                timeline.start_time = start;
+            }
+            if end > timeline.end_time {
+                timeline.end_time = end;
+            }
+        }
+        Ok(timeline)
+    }
 }
 
-struct Trigger {
+/// A trigger on the timeline, see `Timeline::add_trigger`
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Trigger<T = i64> {
     start_location: String,
     end_location: String,
-    time: u64,
+    time: T,
+    end_time: T,
+}
+
+impl<T: TimeValue> Trigger<T> {
+    /// The location the trigger starts at
+    pub fn start_location(&self) -> &str {
+        &self.start_location
+    }
+
+    /// The location the trigger ends at
+    pub fn end_location(&self) -> &str {
+        &self.end_location
+    }
+
+    /// The time of the trigger, which is the time it starts at for `Timeline::add_trigger_span`
+    pub fn time(&self) -> T {
+        self.time
+    }
+
+    /// The time the trigger ends at, which is its `time` unless it was added with
+    /// `Timeline::add_trigger_span`
+    pub fn end_time(&self) -> T {
+        self.end_time
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Marker<T> {
+    label: String,
+    time: T,
+    layer: Layer,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Highlight<T> {
+    start_time: T,
+    end_time: T,
+    color: String,
+    layer: Layer,
 }
 
 impl Default for Timeline {
     fn default() -> Self {
+        Timeline::new()
+    }
+}
+
+impl Timeline {
+    /// Create a builder to configure a new timeline
+    ///
+    /// This is a shortcut for `TimelineBuilder::new()`. Use `TimelineBuilder::<T>::new()` to
+    /// configure a timeline with another time type.
+    pub fn builder() -> TimelineBuilder {
+        TimelineBuilder::new()
+    }
+}
+
+impl<T: TimeValue> Timeline<T> {
+    /// Create an empty timeline with times of type `T`
+    ///
+    /// `Timeline::default()` is the same for the default `i64` times.
+    pub fn new() -> Self {
         Timeline {
-            start_time: u64::MAX,
-            end_time: 0,
+            start_time: T::MAX,
+            end_time: T::MIN,
             events: Vec::new(),
+            event_ids: Vec::new(),
+            next_event_id: 0,
+            index: OnceLock::new(),
+            layout: OnceLock::new(),
             triggers: Vec::new(),
+            links: Vec::new(),
+            markers: Vec::new(),
+            bookmarks: Vec::new(),
+            counters: BTreeMap::new(),
+            counter_styles: HashMap::new(),
+            counter_gridlines: false,
+            state_tracks: BTreeMap::new(),
+            highlights: Vec::new(),
+            layer_order: DEFAULT_LAYER_ORDER.to_vec(),
+            colormap: HashMap::new(),
+            palette_exhausted: false,
+            shapes: HashMap::new(),
+            default_shape: Shape::Rectangle,
+            min_event_width: 1,
+            instant_style: InstantStyle::Shape,
+            lane_packing: None,
+            filter: filter::EventFilter::default(),
+            declared_categories: Vec::new(),
+            category_order: Vec::new(),
+            category_groups: HashMap::new(),
+            category_parents: HashMap::new(),
+            category_heights: HashMap::new(),
+            parent_summary: false,
+            category_comparator: None,
+            label_formatter: None,
+            tick_formatter: None,
+            zoom_ticks: 0,
+            measure_cursors: false,
+            label_gutter: None,
+            label_heat: None,
+            validation: ValidationOptions::default(),
             units: TimeUnit::Nanoseconds,
+            strings: Strings::default(),
+            palette: COLORS.iter().map(|color| color.to_string()).collect(),
+            scale_mode: ScaleMode::PerUnit,
+            margins: Margins::default(),
+            trigger_threshold: None,
+            instant_threshold: None,
+            epoch: None,
+            axis_origin: None,
+            rng: None,
+            #[cfg(feature = "chrono")]
+            wall_clock: false,
             row_height: 20,
             column_width: 200,
             row_padding: 1,
             column_padding: 0,
         }
     }
-}
 
-impl Timeline {
     /// Add an event to the timeline
     ///
     /// This function adds an event to the timeline. Events do not need to be added in
     /// chronological order. `name` will be placed into a rectangle on the timeline, on the row
     /// indicated by `location`. The rectangle will span from `start_time` to `end_time`.
-    pub fn add_event(&mut self, name: String, start_time: u64, end_time: u64, location: String) {
-        let event = Event {
-            name,
-            start_time,
-            end_time,
-            location,
-        };
+    ///
+    /// `name` and `location` take anything that turns into a `String`, so string literals can be
+    /// passed as they are and owned strings are moved in without copying them.
+    ///
+    /// The returned handle can be used to change the event later, see `set_event_end`.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// for (cpu, start) in [(0, 0), (1, 3)] {
+    ///     timeline.add_event("read", start, start + 5, format!("CPU {}", cpu));
+    /// }
+    /// timeline.add_trigger("CPU 0", "CPU 1", 3);
+    /// # }
+    /// ```
+    pub fn add_event(
+        &mut self,
+        name: impl Into<String>,
+        start_time: T,
+        end_time: T,
+        location: impl Into<String>,
+    ) -> EventId {
+        self.insert_event(EventBuilder::new(name, start_time, end_time, location).build())
+    }
+
+    /// Add an event without a duration to the timeline
+    ///
+    /// This is for things that happen at a point in time, like signals, log lines or counters
+    /// crossing a threshold. The event starts and ends at `time`, and is drawn as a glyph on its
+    /// row instead of a sliver of a rectangle: the style of `set_instant_style`, or a diamond if
+    /// that is `InstantStyle::Shape`.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("read".to_string(), 0, 5, "CPU 0".to_string());
+    /// let id = timeline.add_instant("SIGUSR1".to_string(), 3, "CPU 0".to_string());
+    /// assert_eq!(timeline.event(id).unwrap().end_time(), 3);
+    /// # }
+    /// ```
+    pub fn add_instant(
+        &mut self,
+        name: impl Into<String>,
+        time: T,
+        location: impl Into<String>,
+    ) -> EventId {
+        let mut event = EventBuilder::new(name, time, time, location).build();
+        event.instant = true;
+        self.insert_event(event)
+    }
+
+    /// Add an event to the timeline, checking its time range first
+    ///
+    /// This is the same as `add_event`, but returns `TimelineError::InvalidRange` instead of
+    /// adding an event that ends before it starts. What happens to events without a duration
+    /// and to duplicate events is configured with `set_validation`.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// assert!(timeline.try_add_event("read".to_string(), 5, 3, "CPU 0".to_string()).is_err());
+    /// # }
+    /// ```
+    pub fn try_add_event(
+        &mut self,
+        name: impl Into<String>,
+        start_time: T,
+        end_time: T,
+        location: impl Into<String>,
+    ) -> std::result::Result<EventId, TimelineError> {
+        let (name, location) = (name.into(), location.into());
+        if end_time < start_time {
+            return Err(TimelineError::InvalidRange(format!(
+                "event {:?} ends at {:?} before it starts at {:?}",
+                name, end_time, start_time
+            )));
+        }
+        if end_time == start_time && self.validation.zero_length == ZeroLength::Reject {
+            return Err(TimelineError::InvalidRange(format!(
+                "event {:?} at {:?} has no duration",
+                name, start_time
+            )));
+        }
+        if self.validation.warn_duplicates
+            && self.events.iter().any(|event| {
+                event.name == name
+                    && event.start_time == start_time
+                    && event.end_time == end_time
+                    && event.location == location
+            })
+        {
+            log_warn!(
+                "duplicate event {:?} at {:?} on {:?}",
+                name,
+                start_time,
+                location
+            );
+        }
+        Ok(self.add_event(name, start_time, end_time, location))
+    }
+
+    /// Set how `try_add_event` validates events
+    pub fn set_validation(&mut self, options: ValidationOptions) {
+        self.validation = options;
+    }
+
+    /// Add a built event to the timeline
+    ///
+    /// This is the same as `add_event`, but takes an `Event` made with an `EventBuilder`, which
+    /// allows the event to carry metadata. The returned handle refers to the event in other
+    /// calls, like `add_trigger_between`.
+    pub fn insert_event(&mut self, event: Event<T>) -> EventId {
         if event.start_time < self.start_time {
             self.start_time = event.start_time;
         }
         if event.end_time > self.end_time {
             self.end_time = event.end_time;
         }
+        self.assign_color(&event.name);
+        let id = EventId(self.next_event_id);
+        self.next_event_id += 1;
         self.events.push(event);
+        self.invalidate_index();
+        self.event_ids.push(id);
+        id
     }
 
-    /// Add a trigger to the timeline
-    ///
-    /// This function adds a trigger to the timeline. They are independent of the events, but the
-    /// common usecase is that triggers exist where events occur. `start_location` and
-    /// `end_location` are the locations of the trigger, and `time` is the time at which the
-    /// trigger occurs.
+    // Pick a color for an event name that doesn't have one yet
+    fn assign_color(&mut self, name: &str) {
+        if self.colormap.contains_key(name) {
+            return;
+        }
+        // Imported colormaps can have more colors than the palette already
+        if self.colormap.len() >= self.palette.len() && !self.palette_exhausted {
+            self.palette_exhausted = true;
+            log_warn!(
+                "palette of {} colors exhausted at event {:?}, colors will repeat",
+                self.palette.len(),
+                name
+            );
+        }
+        let index = match &mut self.rng {
+            Some(rng) => rng.gen_range(0..self.palette.len()),
+            None => rand::thread_rng().gen_range(0..self.palette.len()),
+        };
+        let color = self.palette[index].clone();
+        self.colormap.insert(name.to_string(), color);
+    }
+
+    /// Add a row for `category`, even if it has no events
     ///
-    /// As an example, process A on CPU 0 wakes up process B on CPU 1. The sequence would look
-    /// something like this
+    /// Rows normally only exist for the locations of events, so a CPU that was idle for the
+    /// whole trace would silently be left out. Declared rows are drawn empty until events are
+    /// added to them, and triggers can be drawn to them. They are kept by `clear`.
     ///
     /// ```
     /// # extern crate timeline_svg;
@@ -120,173 +1151,1707 @@ impl Timeline {
     /// use timeline_svg::Timeline;
     ///
     /// let mut timeline = Timeline::default();
-    /// timeline.add_event("Process A".to_string(), 0, 1, "CPU 0".to_string());
-    /// timeline.add_event("Process B".to_string(), 1, 2, "CPU 1".to_string());
-    /// timeline.add_trigger("CPU 0".to_string(), "CPU 1".to_string(), 1);
+    /// timeline.add_category("CPU 1".to_string());
+    /// timeline.add_event("read".to_string(), 0, 5, "CPU 0".to_string());
+    /// assert_eq!(timeline.categories(), ["CPU 0", "CPU 1"]);
     /// # }
     /// ```
-    pub fn add_trigger(&mut self, start_location: String, end_location: String, time: u64) {
-        let trigger = Trigger {
-            start_location,
-            end_location,
-            time,
-        };
-        if trigger.time < self.start_time {
-            self.start_time = trigger.time;
+    pub fn add_category(&mut self, category: impl Into<String>) {
+        let category = category.into();
+        if !self.declared_categories.contains(&category) {
+            self.declared_categories.push(category);
+            self.invalidate_layout();
         }
-        if trigger.time > self.end_time {
-            self.end_time = trigger.time;
-        }
-        self.triggers.push(trigger);
     }
 
-    /// Save the timeline to a file
+    /// The events of the timeline with their handles, in the order they were added
+    pub fn events(&self) -> impl Iterator<Item = (EventId, &Event<T>)> {
+        self.event_ids.iter().copied().zip(&self.events)
+    }
+
+    /// The triggers of the timeline, in the order they were added
     ///
-    /// This function saves the timeline to a file. The timeline is saved as an SVG file. The
-    /// `filename` is created or overwritten with the SVG of the timeline.  This can return an
-    /// `Result<io::Error>` if there is an issue writing the file.
-    pub fn save(&self, filename: &str) -> Result<()> {
-        let mut file = File::create(filename)?;
-        self.write(&mut file)
+    /// This doesn't include triggers between events, see `add_trigger_between`.
+    pub fn triggers(&self) -> impl Iterator<Item = &Trigger<T>> {
+        self.triggers.iter()
     }
 
-    /// Set the units of the timeline
+    /// The locations of the events, the declared categories, the counters and the state tracks,
+    /// which are the rows of the rendered timeline, in the order of the rows
     ///
-    /// This function sets the units of the timeline. The default is nanoseconds. The units are
-    /// used to properly label the timeline.
-    pub fn set_units(&mut self, units: TimeUnit) {
-        self.units = units;
+    /// Rows are sorted alphabetically unless configured otherwise, see `set_category_order`.
+    pub fn categories(&self) -> Vec<&str> {
+        let mut categories: Vec<&str> = self
+            .events
+            .iter()
+            .map(|event| event.location.as_str())
+            .chain(self.declared_categories.iter().map(String::as_str))
+            .chain(self.counters.keys().map(String::as_str))
+            .chain(self.state_tracks.keys().map(String::as_str))
+            .collect();
+        self.sort_categories(&mut categories);
+        self.nest_categories(categories)
     }
 
-    fn make_timeline_box(&self) -> Group {
-        let num_secs = self.end_time - self.start_time;
-        let width = num_secs * self.column_width;
-        let big_tick = self.row_height / 2;
-        let small_tick = self.row_height / 4;
+    /// The first and last time on the timeline, or `None` if nothing was added
+    pub fn time_range(&self) -> Option<(T, T)> {
+        if self.end_time < self.start_time {
+            None
+        } else {
+            Some((self.start_time, self.end_time))
+        }
+    }
 
-        let mut g = Group::new();
-        g = g.add(
-            Line::new()
-                .set("x1", 0)
-                .set("y1", self.row_height)
-                .set("x2", width)
-                .set("y2", self.row_height)
-                .set("stroke", "black")
-                .set("stroke-width", 1),
-        );
+    /// The event behind a handle, if the handle is from this timeline
+    pub fn event(&self, id: EventId) -> Option<&Event<T>> {
+        Some(&self.events[self.event_index(id)?])
+    }
 
-        for i in 0..num_secs {
-            // Big tick for our start
-            g = g
-                .add(
-                    Line::new()
-                        .set("x1", i * self.column_width)
-                        .set("y1", self.row_height)
-                        .set("x2", i * self.column_width)
-                        .set("y2", self.row_height - big_tick)
-                        .set("stroke", "black")
-                        .set("stroke-width", 1),
-                )
-                .add(
-                    Text::new(format!("{}", i))
-                        .set("x", i * self.column_width)
-                        .set("y", self.row_height - big_tick)
-                        .set("font-size", 10)
-                        .set("fill", "black"),
-                );
+    // The index of the event behind a handle in `events`
+    fn event_index(&self, id: EventId) -> Option<usize> {
+        self.event_ids.binary_search(&id).ok()
+    }
 
-            // Small ticks for the middle parts
-            for tick in 1..9 {
-                let x = i * self.column_width + (self.column_width / 10) * tick;
-                let line = Line::new()
-                    .set("x1", x)
-                    .set("y1", self.row_height)
-                    .set("x2", x)
-                    .set("y2", self.row_height - small_tick)
-                    .set("stroke", "black")
-                    .set("stroke-width", 1);
-                g = g.add(line);
-            }
+    // The index of the event behind a handle that must be valid
+    fn expect_event(&self, id: EventId) -> usize {
+        self.event_index(id)
+            .expect("event handle from another timeline or of a removed event")
+    }
+
+    /// Change the end time of an event
+    ///
+    /// This allows recording an event when it starts, before its end time is known, and filling
+    /// in the end once it is.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// let read = timeline.add_event("read".to_string(), 10, 10, "CPU 0".to_string());
+    /// // ... later, once the read has finished ...
+    /// timeline.set_event_end(read, 25);
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This panics if `id` wasn't returned by this timeline, or its event was removed.
+    pub fn set_event_end(&mut self, id: EventId, end_time: T) {
+        let index = self.expect_event(id);
+        let event = &mut self.events[index];
+        let old_end = event.end_time;
+        event.end_time = end_time;
+        self.invalidate_index();
+        if end_time > self.end_time {
+            self.end_time = end_time;
+        } else if end_time < old_end && old_end == self.end_time {
+            self.update_bounds();
         }
-        g
     }
 
-    // Calculate the x position of a time
-    fn time_x(&self, time: u64) -> u64 {
-        let padding = if time == self.start_time {
-            0
-        } else {
-            self.column_padding
-        };
-        (time - self.start_time) * self.column_width + padding
+    /// Change the name of an event, which also picks its color by the new name
+    ///
+    /// # Panics
+    ///
+    /// This panics if `id` wasn't returned by this timeline, or its event was removed.
+    pub fn rename_event(&mut self, id: EventId, name: impl Into<String>) {
+        let name = name.into();
+        let index = self.expect_event(id);
+        self.assign_color(&name);
+        self.events[index].name = name;
+        // Filters can look at the name
+        self.invalidate_layout();
     }
 
-    // Calculate the y position of a category
-    fn category_y(&self, category: &String, categories: &Vec<String>) -> u64 {
-        let y = categories.iter().position(|c| c == category).unwrap() as u64;
-        (y + 1) * self.row_height + self.row_padding
+    /// Add a metadata entry to an event, replacing any previous value for `key`
+    ///
+    /// # Panics
+    ///
+    /// This panics if `id` wasn't returned by this timeline, or its event was removed.
+    pub fn set_event_metadata(
+        &mut self,
+        id: EventId,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) {
+        let index = self.expect_event(id);
+        self.events[index].metadata.insert(key.into(), value.into());
+        // So can the metadata
+        self.invalidate_layout();
     }
 
-    /// Write the SVG of the timeline to a writer
+    /// Remove an event from the timeline
     ///
-    /// This function writes the SVG of the timeline to a writer. The timeline is drawn with events
-    /// on each category, with triggers connecting the events. Random colors are used for the
-    /// events, and the colors are kept consistent with the same event.
-    pub fn write(&self, writer: &mut dyn Write) -> Result<()> {
-        let mut categories: Vec<String> = self
-            .events
-            .iter()
-            .map(|event| event.location.clone())
-            .collect::<Vec<String>>();
-        categories.sort();
-        let mut colormap: HashMap<String, String> = HashMap::new();
+    /// This returns the removed event, or `None` if there is no event behind `id`. Triggers
+    /// added between the event and another one with `add_trigger_between` are removed with it.
+    /// The handles of the other events stay valid.
+    pub fn remove_event(&mut self, id: EventId) -> Option<Event<T>> {
+        let index = self.event_index(id)?;
+        self.event_ids.remove(index);
+        let event = self.events.remove(index);
+        self.invalidate_index();
+        self.links.retain(|link| link.from != id && link.to != id);
+        self.update_bounds();
+        Some(event)
+    }
 
-        let num_secs = self.end_time - self.start_time;
-        let width = num_secs * self.column_width;
-        let height = (categories.len() as u64) * self.row_height + self.row_height;
+    /// Remove all triggers at `time`
+    ///
+    /// This returns the number of triggers removed. Triggers spanning time are removed by the
+    /// time they start at. Triggers between events, see
+    /// `add_trigger_between`, are removed with `remove_event` instead.
+    pub fn remove_triggers_at(&mut self, time: T) -> usize {
+        let count = self.triggers.len();
+        self.triggers.retain(|trigger| trigger.time != time);
+        let removed = count - self.triggers.len();
+        if removed > 0 {
+            self.update_bounds();
+        }
+        removed
+    }
 
-        let mut doc = svg::Document::new()
-            .set("width", width)
-            .set("height", height)
-            .add(self.make_timeline_box());
+    /// Remove everything added to the timeline
+    ///
+    /// This removes all events, triggers, markers, bookmarks, counter samples, state transitions
+    /// and highlights so the timeline can be reused, while every setting is kept. The colors
+    /// already picked for event names are kept as well, so events keep their colors across
+    /// recordings. Handles of removed events are never reused.
+    pub fn clear(&mut self) {
+        self.events.clear();
+        self.invalidate_index();
+        self.event_ids.clear();
+        self.triggers.clear();
+        self.links.clear();
+        self.markers.clear();
+        self.bookmarks.clear();
+        self.counters.clear();
+        self.state_tracks.clear();
+        self.highlights.clear();
+        self.start_time = T::MAX;
+        self.end_time = T::MIN;
+    }
+
+    /// Crop the timeline to the time range from `start` to `end`
+    ///
+    /// This removes everything outside of the range, and clips events and highlights that
+    /// straddle its edges so they start or end at the edge. The timeline then spans exactly the
+    /// range, to zoom into part of a long trace. Triggers are removed with the events they
+    /// connect, and with the last event of their rows. The handles of the remaining events stay
+    /// valid.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// let id = timeline.add_event("read".to_string(), 0, 500, "CPU 0".to_string());
+    /// timeline.crop(100, 200);
+    /// assert_eq!(timeline.event(id).unwrap().end_time(), 200);
+    /// assert_eq!(timeline.time_range(), Some((100, 200)));
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This panics if `end` is before `start`.
+    pub fn crop(&mut self, start: T, end: T) {
+        assert!(start <= end, "crop range ends before it starts");
+        // Events with a duration are kept if some of it is in the range, events without one if
+        // they are in the range at all
+        let keep: Vec<bool> = self
+            .events
+            .iter()
+            .map(|event| {
+                let instant = event.start_time == event.end_time;
+                let (before, after) = if instant {
+                    (event.end_time < start, event.start_time > end)
+                } else {
+                    (event.end_time <= start, event.start_time >= end)
+                };
+                !before && !after
+            })
+            .collect();
+        let mut flags = keep.iter();
+        self.event_ids.retain(|_| *flags.next().unwrap());
+        let mut flags = keep.iter();
+        self.events.retain(|_| *flags.next().unwrap());
+        for event in &mut self.events {
+            if event.start_time < start {
+                event.start_time = start;
+            }
+            if event.end_time > end {
+                event.end_time = end;
+            }
+        }
+        self.invalidate_index();
+
+        let ids = &self.event_ids;
+        self.links.retain(|link| {
+            ids.binary_search(&link.from).is_ok() && ids.binary_search(&link.to).is_ok()
+        });
+        self.markers
+            .retain(|marker| marker.time >= start && marker.time <= end);
+        self.bookmarks
+            .retain(|bookmark| bookmark.time >= start && bookmark.time <= end);
+        for samples in self.counters.values_mut() {
+            samples.retain(|&(time, _)| time >= start && time <= end);
+        }
+        self.counters.retain(|_, samples| !samples.is_empty());
+        self.crop_states(start, end);
+        // Triggers are kept if both of their rows are still there, whether for events, declared
+        // rows, counters or state tracks
+        let locations: HashSet<&str> = self
+            .events
+            .iter()
+            .map(|e| e.location.as_str())
+            .chain(self.declared_categories.iter().map(String::as_str))
+            .chain(self.counters.keys().map(String::as_str))
+            .chain(self.state_tracks.keys().map(String::as_str))
+            .collect();
+        self.triggers.retain(|trigger| {
+            trigger.time >= start
+                && trigger.time <= end
+                && trigger.end_time >= start
+                && trigger.end_time <= end
+                && locations.contains(trigger.start_location.as_str())
+                && locations.contains(trigger.end_location.as_str())
+        });
+        self.highlights
+            .retain(|highlight| highlight.end_time >= start && highlight.start_time <= end);
+        for highlight in &mut self.highlights {
+            if highlight.start_time < start {
+                highlight.start_time = start;
+            }
+            if highlight.end_time > end {
+                highlight.end_time = end;
+            }
+        }
+        self.start_time = start;
+        self.end_time = end;
+    }
+
+    // Recompute the time range of the timeline from everything on it, for when something on it
+    // shrank
+    fn update_bounds(&mut self) {
+        let times = self
+            .events
+            .iter()
+            .flat_map(|event| [event.start_time, event.end_time])
+            .chain(
+                self.triggers
+                    .iter()
+                    .flat_map(|trigger| [trigger.time, trigger.end_time]),
+            )
+            .chain(self.markers.iter().map(|marker| marker.time))
+            .chain(self.bookmarks.iter().map(|bookmark| bookmark.time))
+            .chain(self.counters.values().flatten().map(|&(time, _)| time))
+            .chain(self.state_tracks.values().flatten().map(|(time, _)| *time))
+            .chain(
+                self.highlights
+                    .iter()
+                    .flat_map(|highlight| [highlight.start_time, highlight.end_time]),
+            );
+        self.start_time = T::MAX;
+        self.end_time = T::MIN;
+        for time in times {
+            if time < self.start_time {
+                self.start_time = time;
+            }
+            if time > self.end_time {
+                self.end_time = time;
+            }
+        }
+    }
+
+    /// Add an event to the timeline using `std::time` types
+    ///
+    /// This is the same as `add_event`, but the event starts at the `start` instant and lasts for
+    /// `duration`. Instants are converted to times relative to the timeline's epoch, in the units
+    /// of the timeline, see `set_epoch` and `set_units`.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use std::time::Instant;
+    /// use timeline_svg::{TimeUnit, Timeline};
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.set_units(TimeUnit::Microseconds);
+    /// let start = Instant::now();
+    /// // ... do some work ...
+    /// timeline.add_event_duration("work".to_string(), start, start.elapsed(), "main".to_string());
+    /// # }
+    /// ```
+    pub fn add_event_duration(
+        &mut self,
+        name: impl Into<String>,
+        start: Instant,
+        duration: Duration,
+        location: impl Into<String>,
+    ) -> EventId {
+        let start = self.instant_nanos(start);
+        let start_time = T::from_nanos(start, self.units);
+        let end_time = T::from_nanos(start + duration.as_nanos() as i128, self.units);
+        self.add_event(name, start_time, end_time, location)
+    }
+
+    /// Add a trigger to the timeline at an `Instant`
+    ///
+    /// This is the same as `add_trigger`, with `time` converted like `add_event_duration` does.
+    pub fn add_trigger_instant(
+        &mut self,
+        start_location: impl Into<String>,
+        end_location: impl Into<String>,
+        time: Instant,
+    ) {
+        let time = T::from_nanos(self.instant_nanos(time), self.units);
+        self.add_trigger(start_location, end_location, time);
+    }
+
+    /// Set the epoch of the timeline
+    ///
+    /// The epoch is the `Instant` that is time 0 for the `std::time` based functions like
+    /// `add_event_duration`. If it isn't set, the first instant added to the timeline becomes the
+    /// epoch. Instants before the epoch get negative times.
+    pub fn set_epoch(&mut self, epoch: Instant) {
+        self.epoch = Some(epoch);
+    }
+
+    // The number of nanoseconds from the epoch to an Instant
+    fn instant_nanos(&mut self, instant: Instant) -> i128 {
+        let epoch = *self.epoch.get_or_insert(instant);
+        if instant >= epoch {
+            (instant - epoch).as_nanos() as i128
+        } else {
+            -((epoch - instant).as_nanos() as i128)
+        }
+    }
+
+    /// Add a trigger to the timeline
+    ///
+    /// This function adds a trigger to the timeline. They are independent of the events, but the
+    /// common usecase is that triggers exist where events occur. `start_location` and
+    /// `end_location` are the locations of the trigger, and `time` is the time at which the
+    /// trigger occurs.
+    ///
+    /// As an example, process A on CPU 0 wakes up process B on CPU 1. The sequence would look
+    /// something like this
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("Process A".to_string(), 0, 1, "CPU 0".to_string());
+    /// timeline.add_event("Process B".to_string(), 1, 2, "CPU 1".to_string());
+    /// timeline.add_trigger("CPU 0".to_string(), "CPU 1".to_string(), 1);
+    /// # }
+    /// ```
+    pub fn add_trigger(
+        &mut self,
+        start_location: impl Into<String>,
+        end_location: impl Into<String>,
+        time: T,
+    ) {
+        self.add_trigger_span(start_location, time, end_location, time);
+    }
+
+    /// Add a trigger that takes time
+    ///
+    /// This is `add_trigger` for a trigger that leaves `start_location` at `start_time` and
+    /// arrives at `end_location` at `end_time`, drawn as a sloped arrow between the two. The
+    /// slope shows latency, like the time from a waker waking a task to the task running, and
+    /// is included in `trigger_latency_stats`.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("waker".to_string(), 0, 1, "CPU 0".to_string());
+    /// timeline.add_event("wakee".to_string(), 4, 6, "CPU 1".to_string());
+    /// timeline.add_trigger_span("CPU 0".to_string(), 1, "CPU 1".to_string(), 4);
+    /// # }
+    /// ```
+    pub fn add_trigger_span(
+        &mut self,
+        start_location: impl Into<String>,
+        start_time: T,
+        end_location: impl Into<String>,
+        end_time: T,
+    ) {
+        let trigger = Trigger {
+            start_location: start_location.into(),
+            end_location: end_location.into(),
+            time: start_time,
+            end_time,
+        };
+        for time in [trigger.time, trigger.end_time] {
+            if time < self.start_time {
+                self.start_time = time;
+            }
+            if time > self.end_time {
+                self.end_time = time;
+            }
+        }
+        self.triggers.push(trigger);
+    }
+
+    /// Add a trigger between two events
+    ///
+    /// Unlike `add_trigger`, which draws a vertical line between two whole rows, this draws a
+    /// connector from the end edge of the `from` event to the start edge of the `to` event. This
+    /// keeps the trigger unambiguous when a row has several events at the same time.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::{EventBuilder, Timeline};
+    ///
+    /// let mut timeline = Timeline::default();
+    /// let send = timeline.insert_event(
+    ///     EventBuilder::new("send".to_string(), 0, 2, "CPU 0".to_string()).build(),
+    /// );
+    /// let receive = timeline.insert_event(
+    ///     EventBuilder::new("receive".to_string(), 3, 5, "CPU 1".to_string()).build(),
+    /// );
+    /// timeline.add_trigger_between(send, receive);
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This panics if either handle wasn't returned by this timeline, or its event was removed.
+    pub fn add_trigger_between(&mut self, from: EventId, to: EventId) {
+        self.expect_event(from);
+        self.expect_event(to);
+        self.links.push(links::Link {
+            from,
+            to,
+            label: None,
+        });
+    }
+
+    /// Add a marker to the timeline
+    ///
+    /// This function adds a marker, a labeled vertical line spanning every row at `time`. Markers
+    /// are drawn on the `Layer::Annotations` layer, so with the default layer order they sit on
+    /// top of everything else.
+    pub fn add_marker(&mut self, label: impl Into<String>, time: T) {
+        self.add_marker_on_layer(label, time, Layer::Annotations);
+    }
+
+    /// Add a marker to a specific layer of the timeline
+    ///
+    /// This is the same as `add_marker`, but the marker is drawn on `layer`, which allows a marker
+    /// to be placed underneath the events, for example.
+    pub fn add_marker_on_layer(&mut self, label: impl Into<String>, time: T, layer: Layer) {
+        let marker = Marker {
+            label: label.into(),
+            time,
+            layer,
+        };
+        if marker.time < self.start_time {
+            self.start_time = marker.time;
+        }
+        if marker.time > self.end_time {
+            self.end_time = marker.time;
+        }
+        self.markers.push(marker);
+    }
+
+    /// Highlight a time range of the timeline
+    ///
+    /// This function shades the time from `start_time` to `end_time` across every row with a
+    /// translucent `color`, which can be any valid SVG color. The highlight is drawn on `layer`,
+    /// use `Layer::Background` to shade behind the events or `Layer::Annotations` to shade over
+    /// them.
+    pub fn add_highlight(
+        &mut self,
+        start_time: T,
+        end_time: T,
+        color: impl Into<String>,
+        layer: Layer,
+    ) {
+        let highlight = Highlight {
+            start_time,
+            end_time,
+            color: color.into(),
+            layer,
+        };
+        if highlight.start_time < self.start_time {
+            self.start_time = highlight.start_time;
+        }
+        if highlight.end_time > self.end_time {
+            self.end_time = highlight.end_time;
+        }
+        self.highlights.push(highlight);
+    }
+
+    /// Set the order the layers are drawn in
+    ///
+    /// Layers are drawn first to last, so the last layer in `order` ends up on top. Any layer that
+    /// is left out of `order` is not drawn at all. For example, to draw the triggers underneath
+    /// the events
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::{Layer, Timeline};
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.set_layer_order(vec![
+    ///     Layer::Background,
+    ///     Layer::Rows,
+    ///     Layer::Triggers,
+    ///     Layer::Events,
+    ///     Layer::Annotations,
+    /// ]);
+    /// # }
+    /// ```
+    pub fn set_layer_order(&mut self, order: Vec<Layer>) {
+        self.layer_order = order;
+    }
+
+    /// Move a layer to the top of the drawing order
+    ///
+    /// The layer is drawn after every other layer. If the layer was previously hidden by
+    /// `set_layer_order` it is drawn again.
+    pub fn raise_layer(&mut self, layer: Layer) {
+        self.layer_order.retain(|l| *l != layer);
+        self.layer_order.push(layer);
+    }
+
+    /// Move a layer to the bottom of the drawing order
+    ///
+    /// The layer is drawn before every other layer. If the layer was previously hidden by
+    /// `set_layer_order` it is drawn again.
+    pub fn lower_layer(&mut self, layer: Layer) {
+        self.layer_order.retain(|l| *l != layer);
+        self.layer_order.insert(0, layer);
+    }
+
+    /// Export the color assignments of the timeline
+    ///
+    /// This function returns the mapping of event names to colors as a JSON object, for example
+    /// `{"Process A":"blue","Process B":"red"}`. The result can be handed to `import_colormap` on
+    /// this or another timeline so the same event names keep the same colors across renders.
+    pub fn export_colormap(&self) -> String {
+        let map: serde_json::Map<String, serde_json::Value> = self
+            .colormap
+            .iter()
+            .map(|(name, color)| (name.clone(), serde_json::Value::from(color.as_str())))
+            .collect();
+        serde_json::Value::Object(map).to_string()
+    }
+
+    /// Import color assignments into the timeline
+    ///
+    /// This function reads a JSON object mapping event names to colors, as produced by
+    /// `export_colormap`. Imported colors replace any colors already chosen for those names, and
+    /// are used for events added later as well. Any valid SVG color can be used. This returns an
+    /// `io::Error` of kind `InvalidData` if `json` is not an object of strings.
+    pub fn import_colormap(&mut self, json: &str) -> Result<()> {
+        let value: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        let map = value
+            .as_object()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "colormap must be a JSON object"))?;
+        let mut colors = Vec::new();
+        for (name, color) in map {
+            let color = color.as_str().ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("color for {} must be a string", name),
+                )
+            })?;
+            colors.push((name.clone(), color.to_string()));
+        }
+        self.colormap.extend(colors);
+        Ok(())
+    }
+
+    /// Set the shape used for events of a kind
+    ///
+    /// Every event named `name` is drawn with `shape` instead of the default shape, for example to
+    /// tell async tasks apart from syscalls.
+    pub fn set_event_shape(&mut self, name: impl Into<String>, shape: Shape) {
+        self.shapes.insert(name.into(), shape);
+    }
+
+    /// Set the default shape of events
+    ///
+    /// This is the shape used for events without a shape set by `set_event_shape`. The default is
+    /// `Shape::Rectangle`.
+    pub fn set_default_shape(&mut self, shape: Shape) {
+        self.default_shape = shape;
+    }
+
+    /// Set the minimum width of events
+    ///
+    /// Events are as wide as their duration, so very short events could end up too thin to see.
+    /// Any event narrower than `width` pixels is drawn `width` pixels wide instead. The default is
+    /// 1 pixel.
+    pub fn set_min_event_width(&mut self, width: u64) {
+        self.min_event_width = width;
+    }
+
+    /// Set how events with no duration are drawn
+    ///
+    /// The default is `InstantStyle::Shape`, which draws them like any other event at the minimum
+    /// event width.
+    pub fn set_instant_style(&mut self, style: InstantStyle) {
+        self.instant_style = style;
+    }
+
+    /// Embed denser tick layers for zooming in
+    ///
+    /// Each of the `levels` extra layers of ticks on the time axis is spaced for 10 times more
+    /// magnification than the one before, so the axis stays readable when a viewer zooms in on the
+    /// SVG. The layers are groups with the class `ticks` and the magnification they are meant for
+    /// in `data-zoom`, and all but the base layer are hidden, to be switched by a script of the
+    /// page embedding the SVG, like the one of `write_html`. Denser layers have many more ticks, so
+    /// this makes the output larger. The default is 0, which only draws the base ticks.
+    pub fn set_zoom_ticks(&mut self, levels: u32) {
+        self.zoom_ticks = levels;
+    }
+
+    /// Embed a script for measuring time between two cursors
+    ///
+    /// When the SVG is opened in a browser, shift-clicking the timeline drops a cursor showing
+    /// its time since the start of the timeline and the events it crosses. A second shift-click
+    /// drops another cursor and shows the time between the two, and a third starts over. The
+    /// default is no script.
+    pub fn set_measure_cursors(&mut self, enabled: bool) {
+        self.measure_cursors = enabled;
+    }
+
+    /// Label the rows in a gutter to the left of the timeline
+    ///
+    /// With `Some(width)` a gutter `width` pixels wide is added in front of the time axis, with
+    /// the location of each row written next to it. `None`, the default, draws no labels.
+    pub fn set_row_labels(&mut self, width: Option<u64>) {
+        self.label_gutter = width;
+    }
 
+    /// Color the row labels by the utilization of their rows
+    ///
+    /// This makes saturated rows stand out even when the busy part of the timeline is scrolled
+    /// out of view. It only has an effect when row labels are enabled with `set_row_labels`.
+    /// `None`, the default, draws the labels in black.
+    pub fn set_label_heat(&mut self, heat: Option<LabelHeat>) {
+        self.label_heat = heat;
+    }
+
+    /// Bundle triggers in crowded pixel columns
+    ///
+    /// When more than `threshold` triggers land in the same pixel column of the rendered
+    /// timeline, they are replaced by a single gray density tick spanning the rows they touch,
+    /// with the number of bundled triggers in its `data-count` attribute. This keeps thousands of
+    /// triggers from turning the chart solid black. `None`, the default, draws every trigger.
+    pub fn set_trigger_threshold(&mut self, threshold: Option<usize>) {
+        self.trigger_threshold = threshold;
+    }
+
+    /// Cluster the events without duration of crowded rows
+    ///
+    /// When a row has more than `threshold` events that start and end at the same time, like an
+    /// interrupt storm, they are drawn as gray density bars instead, one per pixel column, as tall
+    /// as the number of events in the column relative to the busiest column of the row. The bars
+    /// have the number of events in their `data-count` attribute. The events themselves are kept
+    /// in a hidden group with the class `instant-markers`, and the magnification at which they
+    /// stop overlapping in `data-zoom`, like the tick layers of `set_zoom_ticks`. `None`, the
+    /// default, draws every event.
+    pub fn set_instant_threshold(&mut self, threshold: Option<usize>) {
+        self.instant_threshold = threshold;
+    }
+
+    /// Save the timeline to a file
+    ///
+    /// This function saves the timeline to a file. The timeline is saved as an SVG file. The
+    /// `filename` is created or overwritten with the SVG of the timeline, or the SVG is written
+    /// to stdout if `filename` is "-". The file is written atomically, the SVG goes to a
+    /// temporary file next to `filename` that is renamed over it once it is complete, so a
+    /// failed render never clobbers a previous good file. This returns an error if the timeline
+    /// can't be rendered, see `write`, or `TimelineError::Io` if there is an issue writing the
+    /// file.
+    pub fn save(
+        &self,
+        filename: impl AsRef<std::path::Path>,
+    ) -> std::result::Result<(), TimelineError> {
+        self.save_with(filename, SaveOptions::default())
+    }
+
+    /// Save the timeline to a file with options
+    ///
+    /// This is the same as `save`, with the extra behavior described by `options`.
+    pub fn save_with(
+        &self,
+        filename: impl AsRef<std::path::Path>,
+        options: SaveOptions,
+    ) -> std::result::Result<(), TimelineError> {
+        // Render before touching the file, so a timeline that can't be rendered leaves no trace
+        let doc = self.document(&self.colormap)?;
+        write_file(filename.as_ref(), options, |writer| {
+            stream::write_document(doc, writer)
+        })?;
+        Ok(())
+    }
+
+    /// Set the units of the timeline
+    ///
+    /// This function sets the units of the timeline. The default is nanoseconds. The units are
+    /// used to properly label the timeline.
+    pub fn set_units(&mut self, units: TimeUnit) {
+        self.units = units;
+    }
+
+    /// Set how times are scaled to pixels
+    ///
+    /// This is the same as `TimelineBuilder::scale_mode`, for timelines that weren't built, like
+    /// those of `Timeline::import`.
+    pub fn set_scale_mode(&mut self, scale_mode: ScaleMode) {
+        self.scale_mode = scale_mode;
+    }
+
+    /// Set the origin of the time axis
+    ///
+    /// The ticks of the time axis are labeled with their time relative to the origin, so with an
+    /// origin of `Some(0)` the labels are the times the events were added with, including negative
+    /// times for events before a reference point. `None`, the default, uses the start of the
+    /// timeline as the origin so the labels count up from 0.
+    pub fn set_axis_origin(&mut self, origin: Option<T>) {
+        self.axis_origin = origin;
+    }
+
+    /// Set the static text of the timeline
+    ///
+    /// This function replaces the table of text the library generates itself, like the caption
+    /// of the time axis, so that rendered timelines can be translated. See `Strings`.
+    pub fn set_strings(&mut self, strings: Strings) {
+        self.strings = strings;
+    }
+
+    // The box and time axis of the timeline, cut to the x positions of `visible` if given
+    fn make_timeline_box(&self, visible: Option<(f64, f64)>) -> Group {
+        let span = self.span();
+        let scale = self.scale();
+        let width = span * scale;
+        let row_height = self.row_height as f64;
+        let big_tick = row_height / 2.0;
+        let step = self.tick_step();
+        let (left, right) = match visible {
+            Some((left, right)) => (left.max(0.0), right.min(width)),
+            None => (0.0, width),
+        };
+
+        let mut g = Group::new();
+        g = g.add(
+            Line::new()
+                .set("x1", left)
+                .set("y1", row_height)
+                .set("x2", right)
+                .set("y2", row_height)
+                .set("stroke", "black")
+                .set("stroke-width", 1),
+        );
+        g = g.add(
+            Text::new(format!(
+                "{} ({})",
+                self.strings.axis_caption,
+                self.axis_unit_label()
+            ))
+            .set("x", right)
+            .set("y", row_height - big_tick)
+            .set("font-size", 10)
+            .set("text-anchor", "end")
+            .set("fill", "black"),
+        );
+
+        if self.zoom_ticks == 0 {
+            return self.add_ticks(g, step, scale, visible);
+        }
+
+        // Every tick layer is drawn at the scale of the document, the denser ones only become
+        // readable once a viewer zooms in and shows them instead of the base layer
+        let mut last_step = step;
+        g = g.add(self.add_ticks(Self::tick_layer(1.0), step, scale, visible));
+        for level in 1..=self.zoom_ticks {
+            let zoom = 10f64.powi(level as i32);
+            let step = self.tick_step_at(scale * zoom);
+            if step >= last_step {
+                break;
+            }
+            last_step = step;
+            let layer = Self::tick_layer(zoom).set("display", "none");
+            g = g.add(self.add_ticks(layer, step, scale, visible));
+        }
+        g
+    }
+
+    // An empty group for the ticks shown from `zoom` times magnification
+    fn tick_layer(zoom: f64) -> Group {
+        Group::new().set("class", "ticks").set("data-zoom", zoom)
+    }
+
+    // Add the ticks of the axis `step` units apart to `g`
+    fn add_ticks(&self, mut g: Group, step: f64, scale: f64, visible: Option<(f64, f64)>) -> Group {
+        let span = self.span();
+        let row_height = self.row_height as f64;
+        let big_tick = row_height / 2.0;
+        let small_tick = row_height / 4.0;
+
+        // Big ticks land on multiples of the step away from the origin of the axis
+        let origin = match self.axis_origin {
+            Some(origin) if span > 0.0 => origin.offset_from(self.start_time, self.units),
+            _ => 0.0,
+        };
+        let mut offset = origin.rem_euclid(step);
+        while offset < span {
+            let tick_x = offset * scale;
+            if !overlaps(visible, tick_x, tick_x + step * scale) {
+                offset += step;
+                continue;
+            }
+            // Big tick for our start, unless only the small ticks after it are visible
+            if overlaps(visible, tick_x, tick_x) {
+                g = g
+                    .add(
+                        Line::new()
+                            .set("x1", tick_x)
+                            .set("y1", row_height)
+                            .set("x2", tick_x)
+                            .set("y2", row_height - big_tick)
+                            .set("stroke", "black")
+                            .set("stroke-width", 1),
+                    )
+                    .add(
+                        Text::new(self.tick_label(offset, origin, step))
+                            .set("x", tick_x)
+                            .set("y", row_height - big_tick)
+                            .set("font-size", 10)
+                            .set("fill", "black"),
+                    );
+            }
+
+            // Small ticks for the middle parts
+            for tick in 1..9 {
+                let x = tick_x + (step * scale / 10.0) * tick as f64;
+                if !overlaps(visible, x, x) {
+                    continue;
+                }
+                let line = Line::new()
+                    .set("x1", x)
+                    .set("y1", row_height)
+                    .set("x2", x)
+                    .set("y2", row_height - small_tick)
+                    .set("stroke", "black")
+                    .set("stroke-width", 1);
+                g = g.add(line);
+            }
+            offset += step;
+        }
+        g
+    }
+
+    // The label of the big tick `offset` units from the start, relative to the axis origin
+    fn tick_label(&self, offset: f64, origin: f64, step: f64) -> String {
+        if let Some(format) = &self.tick_formatter {
+            return format(offset - origin);
+        }
+        #[cfg(feature = "chrono")]
+        if self.wall_clock {
+            return self.wall_clock_label(offset, step);
+        }
+        time::format_offset(offset - origin, step)
+    }
+
+    // The unit shown in the caption of the time axis
+    fn axis_unit_label(&self) -> &str {
+        #[cfg(feature = "chrono")]
+        if self.wall_clock {
+            return &self.strings.utc;
+        }
+        self.strings.unit(&self.units)
+    }
+
+    // The text shown when hovering over an event: its name, times, location and metadata
+    fn event_tooltip(&self, event: &Event<T>) -> String {
+        let strings = &self.strings;
+        let unit = strings.unit(&self.units);
+        // Exact times in the units of the timeline, which only differ from how they were given
+        // for `Duration`
+        let units = |time: T| time.to_nanos(self.units) as f64 / self.units.nanoseconds() as f64;
+        let mut tooltip = format!(
+            "{}\n{}: {} {}\n{}: {} {}\n{}: {} {}\n{}: {}",
+            event.name,
+            strings.tooltip_start,
+            units(event.start_time),
+            unit,
+            strings.tooltip_end,
+            units(event.end_time),
+            unit,
+            strings.tooltip_duration,
+            event.end_time.offset_from(event.start_time, self.units),
+            unit,
+            strings.tooltip_location,
+            event.location
+        );
+        for (key, value) in &event.metadata {
+            tooltip.push_str(&format!("\n{}: {}", key, value));
+        }
+        tooltip
+    }
+
+    // The number of time units the timeline spans
+    fn span(&self) -> f64 {
+        if self.end_time < self.start_time {
+            0.0
+        } else {
+            self.end_time.offset_from(self.start_time, self.units)
+        }
+    }
+
+    // The number of pixels per time unit
+    fn scale(&self) -> f64 {
+        match self.scale_mode {
+            ScaleMode::PerUnit => self.column_width as f64,
+            ScaleMode::FitWidth(width) if self.span() > 0.0 => width as f64 / self.span(),
+            ScaleMode::FitWidth(width) => width as f64,
+        }
+    }
+
+    // The number of time units between big ticks on the axis, picked from 1, 2 and 5 times a
+    // power of 10 so that the ticks are at least MIN_TICK_SPACING pixels apart
+    fn tick_step(&self) -> f64 {
+        self.tick_step_at(self.scale())
+    }
+
+    // The tick step at `scale` pixels per time unit
+    fn tick_step_at(&self, scale: f64) -> f64 {
+        let span = self.span();
+        if span <= 0.0 {
+            return T::MIN_STEP.max(1.0);
+        }
+        let smallest = (MIN_TICK_SPACING / scale).min(span).max(T::MIN_STEP);
+        let mut magnitude = 10f64.powf(smallest.log10().floor());
+        loop {
+            for step in [magnitude, magnitude * 2.0, magnitude * 5.0] {
+                if step >= smallest && step >= T::MIN_STEP {
+                    return step;
+                }
+            }
+            magnitude *= 10.0;
+        }
+    }
+
+    // Calculate the x position of a time
+    fn time_x(&self, time: T) -> f64 {
+        let padding = if time == self.start_time {
+            0
+        } else {
+            self.column_padding
+        };
+        time.offset_from(self.start_time, self.units) * self.scale() + padding as f64
+    }
+
+    // The fraction of the timeline during which a category has at least one event
+    fn utilization(&self, category: &str) -> f64 {
+        let span = self.span();
+        if span <= 0.0 {
+            return 0.0;
+        }
+        let busy: f64 = self
+            .busy_ranges(|event| event.location == category)
+            .into_iter()
+            .map(|(start, end)| end.offset_from(start, self.units))
+            .sum();
+        busy / span
+    }
+
+    // The time ranges during which at least one of the drawn events matching `filter` happens,
+    // sorted, with overlapping events merged so busy time isn't counted twice
+    fn busy_ranges(&self, filter: impl Fn(&Event<T>) -> bool) -> Vec<(T, T)> {
+        let mut ranges: Vec<(T, T)> = self
+            .events
+            .iter()
+            .filter(|event| filter(event) && self.is_drawn(event))
+            .map(|event| (event.start_time, event.end_time))
+            .collect();
+        ranges.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        let mut merged: Vec<(T, T)> = Vec::new();
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some((_, current_end)) if start <= *current_end => {
+                    if end > *current_end {
+                        *current_end = end;
+                    }
+                }
+                _ => merged.push((start, end)),
+            }
+        }
+        merged
+    }
+
+    // Calculate the rendered width of an event
+    fn event_width(&self, event: &Event<T>) -> f64 {
+        let width = event.end_time.offset_from(event.start_time, self.units) * self.scale();
+        width.max(self.min_event_width as f64)
+    }
+
+    /// Write the SVG of the timeline to a writer
+    ///
+    /// This function writes the SVG of the timeline to a writer. The timeline is drawn with events
+    /// on each category, with triggers connecting the events. Random colors are used for the
+    /// events, and the colors are kept consistent with the same event, see `export_colormap`.
+    /// The SVG is streamed into `writer` as it is serialized, so its text is never held in
+    /// memory as a whole. Memory still grows with the timeline, as the elements of the SVG are
+    /// all drawn before the first is written, and only dropped as they are written. `writer` is
+    /// anything that implements `Write`, like a `&mut Vec<u8>` or a `File`, see `write_async`
+    /// for asynchronous writers.
+    ///
+    /// This returns `TimelineError::UnknownLocation` if a trigger references a location without
+    /// any events, `TimelineError::InvalidRange` if an event or highlight ends before it starts,
+    /// and `TimelineError::Io` if writing fails.
+    pub fn write<W: Write>(&self, writer: W) -> std::result::Result<(), TimelineError> {
+        let doc = self.document(&self.colormap)?;
+        stream::write_document(doc, writer)?;
+        Ok(())
+    }
+
+    // Check that everything on the timeline spans a valid range of time
+    fn check_ranges(&self) -> std::result::Result<(), TimelineError> {
         for event in &self.events {
-            let color = colormap
-                .entry(event.name.clone())
-                .or_insert_with(|| {
-                    let mut rng = rand::thread_rng();
-                    COLORS[rng.gen_range(0..COLORS.len())].to_string()
-                });
+            if event.end_time < event.start_time {
+                return Err(TimelineError::InvalidRange(format!(
+                    "event {:?} ends at {:?} before it starts at {:?}",
+                    event.name, event.end_time, event.start_time
+                )));
+            }
+        }
+        for highlight in &self.highlights {
+            if highlight.end_time < highlight.start_time {
+                return Err(TimelineError::InvalidRange(format!(
+                    "highlight ends at {:?} before it starts at {:?}",
+                    highlight.end_time, highlight.start_time
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the SVG of the time range from `start` to `end` to a writer
+    ///
+    /// This renders a slice of the timeline as a standalone SVG, for viewers that show a long
+    /// timeline in tiles or refresh only the part that changed. The slice is laid out exactly
+    /// like the whole timeline, with the same rows and scale, and its `viewBox` covers just the
+    /// range, so slices placed next to each other line up. Only what overlaps the range is
+    /// drawn, and the events are found with the index used by `events_in_range`. The layout of
+    /// the rows is kept too, so rendering the next slice doesn't lay them out again unless the
+    /// timeline changed in between. Row labels and interactive scripts are left out.
+    ///
+    /// This returns `TimelineError::InvalidRange` if `end` is before `start`, and otherwise fails
+    /// like `write`.
+    pub fn render_region<W: Write>(
+        &self,
+        start: T,
+        end: T,
+        writer: W,
+    ) -> std::result::Result<(), TimelineError> {
+        if end < start {
+            return Err(TimelineError::InvalidRange(format!(
+                "region ends at {:?} before it starts at {:?}",
+                end, start
+            )));
+        }
+        let doc = self.render(&self.colormap, Some((start, end)))?;
+        stream::write_document(doc, writer)?;
+        Ok(())
+    }
+
+    /// Write the timeline as a `<g>` fragment to embed in a larger SVG
+    ///
+    /// This writes what `write` draws inside a group with the class `timeline` instead of an
+    /// `<svg>` root, without a width, height or scripts, so it can be pasted into a hand-built
+    /// dashboard and placed with a `transform` on it or a group around it. The fragment takes the
+    /// same space as the SVG of `write`, starting at the origin. Its ids, like those of the
+    /// arrowheads of `add_link`, are the same in every fragment, so two fragments with links in
+    /// one document share their definitions.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("Event 1", 0, 10, "Location 1");
+    /// let mut output = Vec::new();
+    /// timeline.write_fragment(&mut output).unwrap();
+    /// assert!(String::from_utf8(output).unwrap().starts_with("<g class=\"timeline\">"));
+    /// # }
+    /// ```
+    pub fn write_fragment<W: Write>(&self, writer: W) -> std::result::Result<(), TimelineError> {
+        let mut doc = self.document(&self.colormap)?;
+        let mut fragment = Group::new().set("class", "timeline");
+        for child in std::mem::take((*doc).get_children_mut()) {
+            if child.get_name() != "script" {
+                fragment.append(child);
+            }
+        }
+        stream::write_document(fragment, writer)?;
+        Ok(())
+    }
+
+    // Build the SVG document of the timeline, coloring events with `colormap`
+    fn document(
+        &self,
+        colormap: &HashMap<String, String>,
+    ) -> std::result::Result<svg::Document, TimelineError> {
+        self.render(colormap, None)
+    }
+
+    // Build the SVG document of the timeline, or of the time range of `region` only
+    fn render(
+        &self,
+        colormap: &HashMap<String, String>,
+        region: Option<(T, T)>,
+    ) -> std::result::Result<svg::Document, TimelineError> {
+        self.check_ranges()?;
+
+        let rows = self.rows();
+        let categories: &[&str] = &rows.categories();
+
+        let width = self.span() * self.scale();
+        // Events are pushed down by the row padding, so leave room for it below the last row
+        let height = rows.bottom() + self.row_padding as f64;
+        let row_height = self.row_height as f64;
+        let visible = region.map(|(start, end)| (self.time_x(start), self.time_x(end)));
+
+        let mut layers: HashMap<Layer, Group> = HashMap::new();
+
+        layer_group(&mut layers, Layer::Background).append(self.make_timeline_box(visible));
+
+        // Indices of the events to draw
+        let mut events: Vec<usize> = match region {
+            Some((start, end)) => {
+                let mut events: Vec<usize> = self
+                    .events_in_range(start, end)
+                    .into_iter()
+                    .map(|(id, _)| self.expect_event(id))
+                    .collect();
+                // Back in the order they were added, so ties stack like in the whole timeline
+                events.sort();
+                events
+            }
+            None => (0..self.events.len()).collect(),
+        };
+        events.retain(|&index| self.is_drawn(&self.events[index]));
+        // Stable sort, so events that tie keep the order they were added in, see `EventStyle`
+        events.sort_by(|&a, &b| {
+            let (a, b) = (&self.events[a], &self.events[b]);
+            a.style
+                .z_index
+                .cmp(&b.style.z_index)
+                .then_with(|| a.draw_order(b))
+        });
+        // The hidden events and the density of the rows with too many events without duration
+        let mut clusters: HashMap<&str, (Group, BTreeMap<i64, usize>)> = HashMap::new();
+        if let Some(threshold) = self.instant_threshold {
+            let mut counts: HashMap<&str, usize> = HashMap::new();
+            for event in &self.events {
+                if event.start_time == event.end_time && self.is_drawn(event) {
+                    *counts.entry(&event.location).or_default() += 1;
+                }
+            }
+            for (location, count) in counts {
+                if count > threshold {
+                    clusters.insert(location, (Group::new(), BTreeMap::new()));
+                }
+            }
+        }
+
+        for index in events {
+            let event = &self.events[index];
+            let color = colormap.get(&event.name).map_or("gray", String::as_str);
             let x = self.time_x(event.start_time);
-            let y = self.category_y(&event.location, &categories);
-            let rect = Rectangle::new()
+            let y = self.event_y(index, rows)?;
+            let event_height = self.category_height(&event.location);
+            let instant = match self.instant_style {
+                InstantStyle::Shape if event.instant => {
+                    InstantStyle::Diamond.element(x, y, event_height)
+                }
+                style if event.start_time == event.end_time => style.element(x, y, event_height),
+                _ => None,
+            };
+            let mut rect = instant.unwrap_or_else(|| {
+                let width = self.event_width(event);
+                if width > event.end_time.offset_from(event.start_time, self.units) * self.scale() {
+                    log_debug!(
+                        "event {:?} at {:?} clamped to the minimum width of {}px",
+                        event.name,
+                        event.start_time,
+                        self.min_event_width
+                    );
+                }
+                let shape = self.shapes.get(&event.name).unwrap_or(&self.default_shape);
+                shape.element(x, y, width, event_height)
+            });
+            rect.assign("fill", color);
+            rect.assign("stroke", color);
+            let label = Text::new(self.event_label(event))
                 .set("x", x)
-                .set("y", y)
-                .set("width", self.column_width)
-                .set("height", self.row_height)
-                .set("fill", (*color).clone());
-            let label = Text::new(event.name.clone())
-                .set("x", x)
-                .set("y", y + 10)
+                .set("y", y + 10.0)
                 .set("font-size", 10)
                 .set("fill", "black");
-            let g = Group::new().add(rect).add(label);
-            doc = doc.add(g);
+            let mut g = match &event.url {
+                Some(url) => Group::new().add(Anchor::new().set("href", url.as_str()).add(rect)),
+                None => Group::new().add(rect),
+            };
+            g = g.set("class", "event").add(label);
+            for (key, value) in &event.metadata {
+                if let Some(name) = event::data_attribute(key) {
+                    g.assign(name, value.as_str());
+                }
+            }
+            g.append(Title::new(self.event_tooltip(event)));
+            if event.start_time == event.end_time {
+                if let Some((markers, columns)) = clusters.get_mut(event.location.as_str()) {
+                    *columns.entry(x.floor() as i64).or_default() += 1;
+                    markers.append(g);
+                    continue;
+                }
+            }
+            layer_group(&mut layers, event.style.layer).append(g);
+        }
+
+        for &category in categories {
+            let y = self.category_y(category, rows)?;
+            let height = self.category_height(category);
+            if let Some(track) = self.counter_track(category, y, height) {
+                layer_group(&mut layers, Layer::Events).append(track);
+            }
+            if let Some(track) = self.state_track(category, y, height) {
+                layer_group(&mut layers, Layer::Events).append(track);
+            }
+        }
+
+        let mut clusters: Vec<_> = clusters.into_iter().collect();
+        clusters.sort_by_key(|(location, _)| *location);
+        for (location, (markers, columns)) in clusters {
+            let y = self.category_y(location, rows)?;
+            let row_height = self.category_height(location);
+            let busiest = columns.values().copied().max().unwrap_or(1);
+            log_debug!(
+                "clustered the instants of {:?} into {} density bars",
+                location,
+                columns.len()
+            );
+            let g = layer_group(&mut layers, Layer::Events);
+            for (column, count) in columns {
+                let height = row_height * count as f64 / busiest as f64;
+                let bar = Rectangle::new()
+                    .set("x", column)
+                    .set("y", y + row_height - height)
+                    .set("width", 1)
+                    .set("height", height)
+                    .set("fill", "dimgray")
+                    .set("class", "instant-density")
+                    .set("data-count", count);
+                g.append(bar);
+            }
+            g.append(
+                markers
+                    .set("class", "instant-markers")
+                    .set("data-zoom", busiest)
+                    .set("display", "none"),
+            );
+        }
+
+        // Triggers to rows whose events are all filtered out aren't drawn, triggers to rows
+        // without any events are still an error
+        let hidden: HashSet<&str> = self
+            .categories()
+            .into_iter()
+            .filter(|category| !categories.contains(category))
+            .collect();
+        let mut triggers: Vec<&Trigger<T>> = self
+            .triggers
+            .iter()
+            .filter(|trigger| {
+                let shown = !hidden.contains(trigger.start_location.as_str())
+                    && !hidden.contains(trigger.end_location.as_str());
+                if !shown {
+                    log_debug!(
+                        "dropped the trigger from {:?} to {:?} at {:?} on a filtered row",
+                        trigger.start_location,
+                        trigger.end_location,
+                        trigger.time
+                    );
+                }
+                shown
+            })
+            .collect();
+        // By time and then locations, and otherwise in the order they were added, like events
+        triggers.sort_by(|a, b| {
+            a.time
+                .partial_cmp(&b.time)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| {
+                    a.end_time
+                        .partial_cmp(&b.end_time)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .then_with(|| a.start_location.cmp(&b.start_location))
+                .then_with(|| a.end_location.cmp(&b.end_location))
+        });
+
+        // Count the triggers in each pixel column so crowded columns can be bundled, which leaves
+        // out the sloped ones
+        let mut columns: HashMap<i64, (usize, f64, f64)> = HashMap::new();
+        if self.trigger_threshold.is_some() {
+            for trigger in triggers.iter().filter(|t| t.time == t.end_time) {
+                let start_y = self.category_y(&trigger.start_location, rows)?;
+                let end_y = self.category_y(&trigger.end_location, rows)?;
+                // The bottom of the lower row
+                let bottom = (start_y + self.category_height(&trigger.start_location))
+                    .max(end_y + self.category_height(&trigger.end_location));
+                let column = columns
+                    .entry(self.time_x(trigger.time).floor() as i64)
+                    .or_insert((0, f64::MAX, f64::MIN));
+                column.0 += 1;
+                column.1 = column.1.min(start_y).min(end_y);
+                column.2 = column.2.max(bottom);
+            }
         }
+        let threshold = self.trigger_threshold.unwrap_or(usize::MAX);
+        columns.retain(|_, (count, _, _)| *count > threshold);
 
-        for trigger in &self.triggers {
+        let mut arrows = false;
+        for trigger in &triggers {
             let x = self.time_x(trigger.time);
-            let start_y = self.category_y(&trigger.start_location, &categories);
-            let end_y = self.category_y(&trigger.end_location, &categories);
-            let data = Data::new().move_to((x, start_y)).line_to((x, end_y));
+            let end_x = self.time_x(trigger.end_time);
+            let sloped = trigger.time != trigger.end_time;
+            if !sloped && columns.contains_key(&(x.floor() as i64)) {
+                continue;
+            }
+            if !overlaps(visible, x.min(end_x), x.max(end_x)) {
+                continue;
+            }
+            let start_y = self.category_y(&trigger.start_location, rows)?;
+            let end_y = self.category_y(&trigger.end_location, rows)?;
+            let data = Data::new().move_to((x, start_y)).line_to((end_x, end_y));
+            let mut path = Path::new()
+                .set("d", data)
+                .set("stroke", "black")
+                .set("stroke-width", 1)
+                .set("fill", "none");
+            if sloped {
+                arrows = true;
+                path = path
+                    .set("class", "trigger-span")
+                    .set("marker-end", format!("url(#{})", links::ARROW_ID));
+            }
+            layer_group(&mut layers, Layer::Triggers).append(path);
+        }
+
+        let mut links: Vec<(usize, usize, Option<&str>)> = self
+            .links
+            .iter()
+            .map(|link| {
+                let (from, to) = (self.expect_event(link.from), self.expect_event(link.to));
+                (from, to, link.label.as_deref())
+            })
+            .filter(|&(from, to, _)| {
+                self.is_drawn(&self.events[from]) && self.is_drawn(&self.events[to])
+            })
+            .collect();
+        // In the draw order of the events they connect
+        links.sort_by(|a, b| {
+            let events = &self.events;
+            events[a.0]
+                .draw_order(&events[b.0])
+                .then_with(|| events[a.1].draw_order(&events[b.1]))
+        });
+        for (from, to, label) in links {
+            let middle = |index: usize| self.category_height(&self.events[index].location) / 2.0;
+            let start_x =
+                self.time_x(self.events[from].start_time) + self.event_width(&self.events[from]);
+            let start_y = self.event_y(from, rows)? + middle(from);
+            let end_x = self.time_x(self.events[to].start_time);
+            let end_y = self.event_y(to, rows)? + middle(to);
+            if !overlaps(visible, start_x.min(end_x), start_x.max(end_x)) {
+                continue;
+            }
+            let data = Data::new()
+                .move_to((start_x, start_y))
+                .line_to((end_x, end_y));
             let path = Path::new()
                 .set("d", data)
                 .set("stroke", "black")
                 .set("stroke-width", 1)
                 .set("fill", "none");
-            doc = doc.add(path);
+            let Some(label) = label else {
+                layer_group(&mut layers, Layer::Triggers).append(path.set("class", "trigger-link"));
+                continue;
+            };
+            arrows = true;
+            let path = path
+                .set("class", "event-link")
+                .set("marker-end", format!("url(#{})", links::ARROW_ID));
+            let label = Text::new(label.to_string())
+                .set("x", (start_x + end_x) / 2.0)
+                .set("y", (start_y + end_y) / 2.0 - 2.0)
+                .set("font-size", 10)
+                .set("text-anchor", "middle")
+                .set("fill", "black")
+                .set("class", "link-label");
+            layer_group(&mut layers, Layer::Triggers).append(path);
+            layer_group(&mut layers, Layer::Triggers).append(label);
+        }
+
+        let mut columns: Vec<(i64, (usize, f64, f64))> = columns.into_iter().collect();
+        columns.sort_by_key(|(column, _)| *column);
+        for (column, (count, top, bottom)) in columns {
+            if !overlaps(visible, column as f64, column as f64 + 1.0) {
+                continue;
+            }
+            log_debug!(
+                "bundled {} triggers at x={} into a density tick",
+                count,
+                column
+            );
+            let x = column as f64 + 0.5;
+            let line = Line::new()
+                .set("x1", x)
+                .set("y1", top)
+                .set("x2", x)
+                .set("y2", bottom)
+                .set("stroke", "gray")
+                .set("stroke-width", 1)
+                .set("class", "trigger-density")
+                .set("data-count", count);
+            layer_group(&mut layers, Layer::Triggers).append(line);
+        }
+
+        for highlight in &self.highlights {
+            let x = self.time_x(highlight.start_time);
+            if !overlaps(visible, x, self.time_x(highlight.end_time)) {
+                continue;
+            }
+            let rect = Rectangle::new()
+                .set("x", x)
+                .set("y", row_height)
+                .set("width", self.time_x(highlight.end_time) - x)
+                .set("height", height - row_height)
+                .set("fill", highlight.color.as_str())
+                .set("fill-opacity", 0.3);
+            layer_group(&mut layers, highlight.layer).append(rect);
+        }
+
+        for marker in &self.markers {
+            let x = self.time_x(marker.time);
+            if !overlaps(visible, x, x) {
+                continue;
+            }
+            let line = Line::new()
+                .set("x1", x)
+                .set("y1", row_height)
+                .set("x2", x)
+                .set("y2", height)
+                .set("stroke", "black")
+                .set("stroke-width", 1)
+                .set("stroke-dasharray", "4 2");
+            let label = Text::new(marker.label.as_str())
+                .set("x", x + 2.0)
+                .set("y", row_height + 10.0)
+                .set("font-size", 10)
+                .set("fill", "black");
+            let g = Group::new().add(line).add(label);
+            layer_group(&mut layers, marker.layer).append(g);
+        }
+
+        for index in 0..self.bookmarks.len() {
+            let x = self.time_x(self.bookmarks[index].time);
+            if !overlaps(visible, x, x) {
+                continue;
+            }
+            let line = self.bookmark_line(index, row_height, height);
+            layer_group(&mut layers, Layer::Annotations).append(line);
+        }
+
+        if self.parent_summary {
+            for &parent in categories {
+                let children = categories
+                    .iter()
+                    .any(|c| self.category_parents.get(*c).map(String::as_str) == Some(parent));
+                if !children {
+                    continue;
+                }
+                let y = self.category_y(parent, rows)?;
+                let ranges = self.busy_ranges(|event| {
+                    event.location != parent && self.is_descendant(&event.location, parent)
+                });
+                for (start, end) in ranges {
+                    let x = self.time_x(start);
+                    let end_x = self.time_x(end);
+                    if !overlaps(visible, x, end_x) {
+                        continue;
+                    }
+                    let rect = Rectangle::new()
+                        .set("x", x)
+                        .set("y", y)
+                        .set("width", end_x - x)
+                        .set("height", self.category_height(parent))
+                        .set("fill", "gray")
+                        .set("fill-opacity", 0.4)
+                        .set("class", "row-summary");
+                    layer_group(&mut layers, Layer::Rows).append(rect);
+                }
+            }
+        }
+
+        // A region is a slice of the time axis, the row labels left of it aren't part of it
+        let label_gutter = self.label_gutter.filter(|_| region.is_none());
+        for (group, lane) in rows.headers() {
+            let y = rows.lane_top(lane);
+            let left = label_gutter.map_or(2.0, |gutter| 4.0 - gutter as f64);
+            let line = Line::new()
+                .set("x1", left.min(0.0))
+                .set("y1", y)
+                .set("x2", width)
+                .set("y2", y)
+                .set("stroke", "gray")
+                .set("stroke-width", 1)
+                .set("class", "group-separator");
+            let header = Text::new(group.to_string())
+                .set("x", left)
+                .set("y", y + row_height * 0.7)
+                .set("font-size", 10)
+                .set("font-weight", "bold")
+                .set("fill", "black")
+                .set("class", "group-header");
+            layer_group(&mut layers, Layer::Rows).append(line);
+            layer_group(&mut layers, Layer::Rows).append(header);
+        }
+        if let Some(gutter) = label_gutter {
+            for category in categories {
+                let y = self.category_y(category, rows)? - self.row_padding as f64;
+                let heat = self
+                    .label_heat
+                    .map(|heat| (heat, self.utilization(category)));
+                // Rows of a group are indented below its header, and child rows below their parent
+                let depth = self.category_depth(category)
+                    + usize::from(self.category_group(category).is_some());
+                let indent = depth as f64 * groups::GROUP_INDENT;
+                let mut label = Text::new(category.to_string())
+                    .set("x", 4.0 + indent - gutter as f64)
+                    .set("y", y + row_height * 0.7)
+                    .set("font-size", 10)
+                    .set("fill", "black")
+                    .set("class", "row-label");
+                match heat {
+                    Some((LabelHeat::Text, utilization)) => {
+                        label = label
+                            .set("fill", heat_color(utilization))
+                            .set("data-utilization", format!("{:.3}", utilization));
+                    }
+                    Some((LabelHeat::Swatch, utilization)) => {
+                        let size = (row_height / 2.0).min(gutter as f64 / 4.0);
+                        let swatch = Rectangle::new()
+                            .set("x", 2.0 + indent - gutter as f64)
+                            .set("y", y + (row_height - size) / 2.0)
+                            .set("width", size)
+                            .set("height", size)
+                            .set("fill", heat_color(utilization))
+                            .set("class", "row-heat")
+                            .set("data-utilization", format!("{:.3}", utilization));
+                        layer_group(&mut layers, Layer::Rows).append(swatch);
+                        label = label.set("x", 4.0 + indent + size - gutter as f64);
+                    }
+                    None => {}
+                }
+                layer_group(&mut layers, Layer::Rows).append(label);
+            }
+        }
+
+        // The gutter sits left of the time axis, so everything else moves right by its width
+        let margins = self.margins;
+        let gutter = label_gutter.unwrap_or(0);
+        // The bookmark index sits below the rows, and like the labels isn't part of a region
+        let index_height = if self.bookmarks.is_empty() || region.is_some() {
+            0.0
+        } else {
+            (self.bookmarks.len() + 1) as f64 * row_height
+        };
+        if index_height > 0.0 {
+            layer_group(&mut layers, Layer::Annotations).append(self.bookmark_index(height));
+        }
+        // So is the legend of the states drawn, below the index
+        let state_rows: Vec<&str> = categories
+            .iter()
+            .copied()
+            .filter(|category| self.state_tracks.contains_key(*category))
+            .collect();
+        let legend_height = if state_rows.is_empty() || region.is_some() {
+            0.0
+        } else {
+            row_height
+        };
+        if legend_height > 0.0 {
+            let legend = self.state_legend(&state_rows, height + index_height);
+            layer_group(&mut layers, Layer::Annotations).append(legend);
+        }
+        let full_height =
+            height + index_height + legend_height + (margins.top + margins.bottom) as f64;
+        let doc = match visible {
+            Some((start_x, end_x)) => svg::Document::new()
+                .set("width", end_x - start_x)
+                .set("height", full_height)
+                .set(
+                    "viewBox",
+                    (
+                        margins.left as f64 + start_x,
+                        0,
+                        end_x - start_x,
+                        full_height,
+                    ),
+                )
+                .set("data-region-x", start_x),
+            None => svg::Document::new()
+                .set(
+                    "width",
+                    width + (gutter + margins.left + margins.right) as f64,
+                )
+                .set("height", full_height),
+        };
+        let scripts = self.scripts().filter(|_| region.is_none());
+        let doc = match scripts {
+            Some(script) => self.time_attributes(doc).add(script),
+            None => doc,
+        };
+        let doc = if arrows {
+            doc.add(links::arrow_definitions())
+        } else {
+            doc
+        };
+        let mut content = Group::new();
+        if margins != Margins::default() || gutter > 0 {
+            content = content.set(
+                "transform",
+                format!("translate({},{})", margins.left + gutter, margins.top),
+            );
         }
-        writer.write_all(doc.to_string().as_bytes())
+        for layer in &self.layer_order {
+            if let Some(g) = layers.remove(layer) {
+                content = content.add(g);
+            }
+        }
+        Ok(doc.add(content))
     }
 }
 
@@ -326,25 +2891,764 @@ mod tests {
         timeline.save("timeline.svg").unwrap();
     }
 
+    #[test]
+    fn test_save_with() {
+        let dir = std::env::temp_dir().join(format!("timeline-svg-{}", std::process::id()));
+        let path = dir.join("nested").join("timeline.svg");
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1".to_string(), 1, 2, "Location 1".to_string());
+
+        assert!(timeline.save(&path).is_err());
+        timeline
+            .save_with(&path, SaveOptions { create_dirs: true })
+            .unwrap();
+        let svg = std::fs::read_to_string(&path).unwrap();
+        assert!(svg.contains("Event 1"));
+        assert_eq!(
+            std::fs::read_dir(path.parent().unwrap()).unwrap().count(),
+            1
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_add_event_duration() {
+        let mut timeline = Timeline::default();
+        timeline.set_units(TimeUnit::Milliseconds);
+        let epoch = Instant::now();
+        timeline.set_epoch(epoch);
+        timeline.add_event_duration(
+            "Event 1".to_string(),
+            epoch + Duration::from_millis(5),
+            Duration::from_micros(2500),
+            "Location 1".to_string(),
+        );
+        timeline.add_trigger_instant(
+            "Location 1".to_string(),
+            "Location 1".to_string(),
+            epoch + Duration::from_millis(10),
+        );
+        assert_eq!(timeline.events[0].start_time, 5);
+        assert_eq!(timeline.events[0].end_time, 7);
+        assert_eq!(timeline.triggers[0].time, 10);
+    }
+
+    #[test]
+    fn test_signed_times() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1".to_string(), -5, -2, "Location 1".to_string());
+        timeline.add_trigger("Location 1".to_string(), "Location 1".to_string(), 1);
+        assert_eq!(timeline.start_time, -5);
+        assert_eq!(timeline.end_time, 1);
+        assert_eq!(timeline.span(), 6.0);
+        assert_eq!(timeline.time_x(-2), 600.0);
+
+        timeline.set_axis_origin(Some(0));
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.contains(">\n-5\n</text>"));
+        assert!(svg.contains(">\n0\n</text>"));
+    }
+
+    #[test]
+    fn test_time_types() {
+        let mut timeline = Timeline::<f64>::new();
+        timeline.add_event("Event 1".to_string(), 0.25, 0.75, "Location 1".to_string());
+        assert_eq!(timeline.time_x(0.5), 50.0);
+        assert_eq!(timeline.tick_step(), 0.5);
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.contains(">\n0.0\n</text>"));
+
+        let mut timeline = TimelineBuilder::<Duration>::new()
+            .units(TimeUnit::Milliseconds)
+            .build();
+        timeline.add_event(
+            "Event 1".to_string(),
+            Duration::from_millis(1),
+            Duration::from_micros(3500),
+            "Location 1".to_string(),
+        );
+        assert_eq!(timeline.span(), 2.5);
+        assert_eq!(timeline.time_x(Duration::from_millis(2)), 200.0);
+    }
+
     #[test]
     fn test_offsets() {
         let mut timeline = Timeline::default();
         timeline.add_event("Event 1".to_string(), 1, 2, "Location 1".to_string());
         timeline.add_event("Event 2".to_string(), 3, 4, "Location 2".to_string());
         timeline.add_trigger("Location 1".to_string(), "Location 2".to_string(), 1);
-        let categories = vec!["Location 1".to_string(), "Location 2".to_string()];
+        assert_eq!(timeline.categories(), ["Location 1", "Location 2"]);
+        let rows = timeline.rows();
+
+        assert_eq!(timeline.time_x(1), 0.0);
+        assert_eq!(timeline.time_x(2), 200.0);
+        assert_eq!(timeline.time_x(3), 400.0);
+        assert_eq!(timeline.time_x(4), 600.0);
+        assert_eq!(timeline.category_y("Location 1", rows).unwrap(), 21.0);
+        assert_eq!(timeline.category_y("Location 2", rows).unwrap(), 41.0);
+    }
+
+    #[test]
+    fn test_zoom_ticks() {
+        let mut timeline = Timeline::<f64>::new();
+        timeline.add_event("Event 1".to_string(), 0.0, 1.0, "Location 1".to_string());
+        timeline.set_zoom_ticks(2);
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.contains(r#"class="ticks" data-zoom="1">"#));
+        assert!(svg.contains(r#"class="ticks" data-zoom="100" display="none""#));
+        assert!(svg.contains(">\n0.005\n</text>"));
+
+        // Integer times can't be split any further than one unit
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1".to_string(), 0, 10, "Location 1".to_string());
+        timeline.set_zoom_ticks(2);
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(!svg.contains(r#"data-zoom="10""#));
+    }
+
+    #[test]
+    fn test_render_region() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1".to_string(), 0, 10, "Location 1".to_string());
+        timeline.add_event("Event 2".to_string(), 40, 50, "Location 2".to_string());
+        timeline.add_marker("Marker".to_string(), 45);
+        timeline.set_row_labels(Some(80));
+        let mut output = Vec::new();
+        timeline.render_region(5, 20, &mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.contains("Event 1"));
+        assert!(!svg.contains("Event 2"));
+        assert!(!svg.contains("Marker"));
+        assert!(!svg.contains("row-label"));
+        assert!(svg.contains(r#"viewBox="1000 0 3000 61""#));
+        // The axis is cut to the region too
+        assert!(svg.contains(r#"<line stroke="black" stroke-width="1" x1="1000" x2="4000""#));
+        assert!(svg.contains(r#"text-anchor="end" x="4000""#));
+        assert!(timeline.render_region(20, 5, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_measure_cursors() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1".to_string(), 0, 2, "Location 1".to_string());
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        assert!(!String::from_utf8(output).unwrap().contains("<script"));
+
+        timeline.set_measure_cursors(true);
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.contains(r#"data-time-scale="200""#));
+        assert!(svg.contains(r#"data-time-unit="ns""#));
+        assert!(svg.contains("<script"));
+        assert!(svg.contains(r#"<g class="event">"#));
+    }
+
+    #[test]
+    fn test_write_fragment() {
+        let mut timeline = Timeline::default();
+        let from = timeline.add_event("Event 1", 0, 10, "Location 1");
+        let to = timeline.add_event("Event 2", 12, 20, "Location 2");
+        timeline.add_link(from, to, "wakes".to_string());
+        timeline.set_measure_cursors(true);
+
+        let mut output = Vec::new();
+        timeline.write_fragment(&mut output).unwrap();
+        let fragment = String::from_utf8(output).unwrap();
+        assert!(fragment.starts_with("<g class=\"timeline\">\n"));
+        assert!(fragment.ends_with("\n</g>"));
+        assert!(!fragment.contains("<svg") && !fragment.contains("<script"));
+        assert!(fragment.contains("<defs>") && fragment.contains("Event 2"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let mut timeline = Timeline::default();
+        let from = timeline.add_event("Event 1".to_string(), 1, 2, "Location 1".to_string());
+        let to = timeline.add_event("Event 2".to_string(), 3, 4, "Location 2".to_string());
+        timeline.add_trigger_between(from, to);
+        timeline.add_marker("Marker".to_string(), 2);
+        timeline.set_event_shape("Event 2".to_string(), Shape::Hexagon);
+
+        let json = serde_json::to_string(&timeline).unwrap();
+        let mut copy: Timeline = serde_json::from_str(&json).unwrap();
+        let render = |timeline: &Timeline| {
+            let mut output = Vec::new();
+            timeline.write(&mut output).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+        assert_eq!(render(&copy), render(&timeline));
+        let id = copy.add_event("Event 3".to_string(), 5, 6, "Location 1".to_string());
+        assert_eq!(id, EventId(2));
+
+        // An empty palette would leave no colors to pick from
+        let mut json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        json["palette"] = serde_json::json!([]);
+        let mut copy: Timeline = serde_json::from_value(json).unwrap();
+        assert_eq!(copy.palette, Timeline::default().palette);
+        copy.add_event("Event 4", 7, 8, "Location 1");
+
+        // What is derived from the rest is rebuilt rather than trusted
+        let mut json: serde_json::Value = serde_json::to_value(&timeline).unwrap();
+        json["colormap"] = serde_json::json!({});
+        json["next_event_id"] = serde_json::json!(0);
+        json["start_time"] = serde_json::json!(3);
+        json["end_time"] = serde_json::json!(3);
+        let mut copy: Timeline = serde_json::from_value(json).unwrap();
+        assert!(copy.colormap.contains_key("Event 1") && copy.colormap.contains_key("Event 2"));
+        assert_eq!(copy.time_range(), Some((1, 4)));
+        assert_eq!(copy.add_event("Event 3", 5, 6, "Location 1"), EventId(2));
+        render(&copy);
+        // and timelines whose events don't add up are errors
+        for (key, value) in [
+            ("event_ids", serde_json::json!([])),
+            ("event_ids", serde_json::json!([1, 0])),
+            ("events", serde_json::json!([])),
+        ] {
+            let mut json: serde_json::Value = serde_json::to_value(&timeline).unwrap();
+            json[key] = value;
+            assert!(serde_json::from_value::<Timeline>(json).is_err());
+        }
+        let mut json: serde_json::Value = serde_json::to_value(&timeline).unwrap();
+        json["events"][0]["end_time"] = serde_json::json!(0);
+        assert!(serde_json::from_value::<Timeline>(json).is_err());
+    }
+
+    #[test]
+    fn test_row_labels() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1".to_string(), 0, 2, "Location 1".to_string());
+        timeline.add_event("Event 2".to_string(), 1, 3, "Location 1".to_string());
+        timeline.add_event("Event 3".to_string(), 0, 1, "Location 2".to_string());
+        timeline.add_event("Event 4".to_string(), 3, 4, "Location 2".to_string());
+        assert_eq!(timeline.utilization("Location 1"), 0.75);
+        assert_eq!(timeline.utilization("Location 2"), 0.5);
+        assert_eq!(heat_color(1.0), "hsl(0,80%,45%)");
+
+        timeline.set_row_labels(Some(80));
+        timeline.set_label_heat(Some(LabelHeat::Swatch));
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.contains(r#"width="880""#));
+        assert!(svg.contains(r#"height="61""#));
+        assert!(svg.contains("translate(80,0)"));
+        assert!(svg.contains(r#"class="row-label""#));
+        assert!(svg.contains(r#"data-utilization="0.750""#));
+    }
+
+    #[test]
+    fn test_try_add_event() {
+        let mut timeline = Timeline::default();
+        assert!(timeline
+            .try_add_event("Event 1".to_string(), 2, 1, "Location 1".to_string())
+            .is_err());
+        let id = timeline
+            .try_add_event("Event 1".to_string(), 1, 1, "Location 1".to_string())
+            .unwrap();
+        assert_eq!(id, EventId(0));
+
+        timeline.set_validation(ValidationOptions {
+            zero_length: ZeroLength::Reject,
+            warn_duplicates: true,
+        });
+        assert!(matches!(
+            timeline.try_add_event("Event 1".to_string(), 1, 1, "Location 1".to_string()),
+            Err(TimelineError::InvalidRange(_))
+        ));
+        timeline
+            .try_add_event("Event 1".to_string(), 1, 2, "Location 1".to_string())
+            .unwrap();
+        timeline
+            .try_add_event("Event 1".to_string(), 1, 2, "Location 1".to_string())
+            .unwrap();
+        assert_eq!(timeline.events.len(), 3);
+    }
+
+    #[test]
+    fn test_event_handles() {
+        let mut timeline = Timeline::default();
+        let first = timeline.add_event("Event 1".to_string(), 1, 1, "Location 1".to_string());
+        let second = timeline.add_event("Event 2".to_string(), 2, 8, "Location 1".to_string());
+        timeline.set_event_end(first, 10);
+        assert_eq!(timeline.end_time, 10);
+        timeline.set_event_end(first, 3);
+        assert_eq!(timeline.end_time, 8);
+        assert_eq!(timeline.event(first).unwrap().end_time(), 3);
+
+        timeline.rename_event(second, "Event 3".to_string());
+        timeline.set_event_metadata(second, "PID".to_string(), "1".to_string());
+        let event = timeline.event(second).unwrap();
+        assert_eq!(event.name(), "Event 3");
+        assert_eq!(event.metadata()["PID"], "1");
+        assert!(timeline.colormap.contains_key("Event 3"));
+        assert!(timeline.event(EventId(2)).is_none());
+    }
+
+    #[test]
+    fn test_accessors() {
+        let mut timeline = Timeline::default();
+        assert_eq!(timeline.time_range(), None);
+        let id = timeline.add_event("Event 1".to_string(), 1, 2, "Location 2".to_string());
+        timeline.add_event("Event 2".to_string(), 3, 4, "Location 1".to_string());
+        timeline.add_event("Event 3".to_string(), 5, 6, "Location 2".to_string());
+        timeline.add_trigger("Location 2".to_string(), "Location 1".to_string(), 7);
+
+        let (first_id, first) = timeline.events().next().unwrap();
+        assert_eq!(first_id, id);
+        assert_eq!(first.name(), "Event 1");
+        assert_eq!(timeline.events().count(), 3);
+        let trigger = timeline.triggers().next().unwrap();
+        assert_eq!(trigger.start_location(), "Location 2");
+        assert_eq!(trigger.end_location(), "Location 1");
+        assert_eq!(trigger.time(), 7);
+        assert_eq!(timeline.categories(), ["Location 1", "Location 2"]);
+        assert_eq!(timeline.time_range(), Some((1, 7)));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut timeline = Timeline::default();
+        let first = timeline.add_event("Event 1".to_string(), 1, 2, "Location 1".to_string());
+        let second = timeline.add_event("Event 2".to_string(), 3, 9, "Location 2".to_string());
+        let third = timeline.add_event("Event 3".to_string(), 4, 5, "Location 1".to_string());
+        timeline.add_trigger_between(first, second);
+        timeline.add_trigger("Location 1".to_string(), "Location 2".to_string(), 4);
+        timeline.add_trigger("Location 1".to_string(), "Location 2".to_string(), 4);
+
+        assert_eq!(timeline.remove_event(second).unwrap().name(), "Event 2");
+        assert!(timeline.remove_event(second).is_none());
+        assert!(timeline.links.is_empty());
+        assert_eq!(timeline.end_time, 5);
+        assert_eq!(timeline.event(third).unwrap().name(), "Event 3");
+        assert_eq!(timeline.remove_triggers_at(4), 2);
+        assert!(timeline.triggers.is_empty());
+
+        timeline.clear();
+        assert!(timeline.events.is_empty());
+        assert_eq!(timeline.span(), 0.0);
+        let fourth = timeline.add_event("Event 1".to_string(), 0, 1, "Location 1".to_string());
+        assert_eq!(fourth, EventId(3));
+        assert!(timeline.event(first).is_none());
+    }
+
+    #[test]
+    fn test_crop() {
+        let mut timeline = Timeline::default();
+        let first = timeline.add_event("Event 1".to_string(), 0, 10, "Location 1".to_string());
+        let second = timeline.add_event("Event 2".to_string(), 12, 14, "Location 2".to_string());
+        let third = timeline.add_event("Event 3".to_string(), 20, 30, "Location 3".to_string());
+        timeline.add_event("Event 4".to_string(), 15, 15, "Location 1".to_string());
+        timeline.add_trigger_between(first, third);
+        timeline.add_trigger_between(first, second);
+        timeline.add_trigger("Location 1".to_string(), "Location 3".to_string(), 13);
+        timeline.add_trigger("Location 1".to_string(), "Location 2".to_string(), 14);
+        timeline.add_marker("Marker".to_string(), 25);
+        timeline.add_highlight(5, 25, "yellow".to_string(), Layer::Background);
+        // Rows without events keep their triggers as long as they are still there
+        timeline.add_category("Location 5");
+        timeline.add_counter_sample("Memory", 10, 1.0);
+        timeline.add_counter_sample("Swap", 2, 1.0);
+        timeline.add_trigger("Location 1", "Location 5", 9);
+        timeline.add_trigger("Location 2", "Memory", 13);
+        timeline.add_trigger("Location 1", "Swap", 9);
 
-        assert_eq!(timeline.time_x(1), 0);
-        assert_eq!(timeline.time_x(2), 200);
-        assert_eq!(timeline.time_x(3), 400);
-        assert_eq!(timeline.time_x(4), 600);
+        timeline.crop(8, 20);
+        let events: Vec<(&str, i64, i64)> = timeline
+            .events()
+            .map(|(_, event)| (event.name(), event.start_time(), event.end_time()))
+            .collect();
+        assert_eq!(
+            events,
+            [("Event 1", 8, 10), ("Event 2", 12, 14), ("Event 4", 15, 15)]
+        );
+        let links: Vec<_> = timeline.links.iter().map(|l| (l.from, l.to)).collect();
+        assert_eq!(links, [(first, second)]);
+        let triggers: Vec<_> = timeline
+            .triggers()
+            .map(|trigger| (trigger.end_location(), trigger.time()))
+            .collect();
         assert_eq!(
-            timeline.category_y(&"Location 1".to_string(), &categories),
-            21
+            triggers,
+            [("Location 2", 14), ("Location 5", 9), ("Memory", 13)]
         );
+        assert!(timeline.markers.is_empty());
         assert_eq!(
-            timeline.category_y(&"Location 2".to_string(), &categories),
-            41
+            (
+                timeline.highlights[0].start_time,
+                timeline.highlights[0].end_time
+            ),
+            (8, 20)
+        );
+        assert_eq!(timeline.time_range(), Some((8, 20)));
+        timeline.write(&mut Vec::new()).unwrap();
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1".to_string(), 1, 2, "Location 1".to_string());
+        timeline.add_trigger("Location 1".to_string(), "Location 2".to_string(), 1);
+        let mut output = Vec::new();
+        match timeline.write(&mut output) {
+            Err(TimelineError::UnknownLocation(location)) => assert_eq!(location, "Location 2"),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1".to_string(), 2, 1, "Location 1".to_string());
+        assert!(matches!(
+            timeline.write(&mut output),
+            Err(TimelineError::InvalidRange(_))
+        ));
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_colormap() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1".to_string(), 1, 2, "Location 1".to_string());
+        timeline
+            .import_colormap(r#"{"Event 1":"red","Event 2":"blue"}"#)
+            .unwrap();
+        timeline.add_event("Event 2".to_string(), 3, 4, "Location 2".to_string());
+
+        let mut other = Timeline::default();
+        other.import_colormap(&timeline.export_colormap()).unwrap();
+        assert_eq!(other.colormap["Event 1"], "red");
+        assert_eq!(other.colormap["Event 2"], "blue");
+
+        assert!(timeline.import_colormap("[]").is_err());
+        assert!(timeline.import_colormap(r#"{"Event 1":1}"#).is_err());
+
+        // Running out of colors is noticed even if an imported colormap had more already
+        let mut timeline = Timeline::builder().palette(vec!["red".to_string()]).build();
+        timeline
+            .import_colormap(r#"{"Event 1":"red","Event 2":"blue"}"#)
+            .unwrap();
+        assert!(!timeline.palette_exhausted);
+        timeline.add_event("Event 3", 0, 1, "Location 1");
+        assert!(timeline.palette_exhausted);
+    }
+
+    #[test]
+    fn test_strings() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1".to_string(), 1, 2, "Location 1".to_string());
+        timeline.set_units(TimeUnit::Milliseconds);
+        timeline.set_strings(Strings {
+            axis_caption: "Zeit".to_string(),
+            ..Strings::default()
+        });
+
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.contains("Zeit (ms)"));
+    }
+
+    #[test]
+    fn test_event_tooltip() {
+        let mut timeline: Timeline<Duration> = Timeline::new();
+        timeline.set_units(TimeUnit::Milliseconds);
+        timeline.add_event(
+            "Event 1".to_string(),
+            Duration::from_micros(1500),
+            Duration::from_millis(4),
+            "Location 1".to_string(),
+        );
+
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.contains(
+            "<title>Event 1\nstart: 1.5 ms\nend: 4 ms\nduration: 2.5 ms\nlocation: Location 1</title>"
+        ));
+    }
+
+    #[test]
+    fn test_shapes() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1".to_string(), 1, 2, "Location 1".to_string());
+        timeline.add_event("Event 2".to_string(), 2, 3, "Location 1".to_string());
+        timeline.set_default_shape(Shape::Rounded { rx: 3, ry: 3 });
+        timeline.set_event_shape("Event 2".to_string(), Shape::Chevron);
+
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.contains(r#"rx="3""#));
+        assert!(svg.contains(r#"points="200,21 390,21 400,31 390,41 200,41 210,31""#));
+    }
+
+    #[test]
+    fn test_event_width() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1".to_string(), 1, 3, "Location 1".to_string());
+        timeline.add_event("Event 2".to_string(), 4, 4, "Location 1".to_string());
+        assert_eq!(timeline.event_width(&timeline.events[0]), 400.0);
+        assert_eq!(timeline.event_width(&timeline.events[1]), 1.0);
+        timeline.set_min_event_width(5);
+        assert_eq!(timeline.event_width(&timeline.events[1]), 5.0);
+
+        timeline.set_instant_style(InstantStyle::Diamond);
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.contains(r#"points="600,21 610,31 600,41 590,31""#));
+    }
+
+    #[test]
+    fn test_add_instant() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1".to_string(), 1, 3, "Location 1".to_string());
+        timeline.add_event("Event 2".to_string(), 2, 2, "Location 1".to_string());
+        let id = timeline.add_instant("Signal".to_string(), 4, "Location 2".to_string());
+        assert_eq!(timeline.event(id).unwrap().start_time(), 4);
+        assert_eq!(timeline.time_range(), Some((1, 4)));
+        let render = |timeline: &Timeline| {
+            let mut output = Vec::new();
+            timeline.write(&mut output).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        // Only the instant is a glyph, the other event without duration keeps its shape
+        let svg = render(&timeline);
+        assert!(svg.contains(r#"points="600,41 610,51 600,61 590,51""#));
+        assert_eq!(svg.matches("<polygon").count(), 1);
+
+        timeline.set_instant_style(InstantStyle::Flag);
+        let svg = render(&timeline);
+        assert!(svg.contains(r#"d="M600,61 L600,41 L610,46 L600,51""#));
+        assert_eq!(svg.matches("<path d=\"M").count(), 2);
+    }
+
+    #[test]
+    fn test_trigger_between() {
+        let mut timeline = Timeline::default();
+        let from = timeline.insert_event(
+            EventBuilder::new("Event 1".to_string(), 1, 2, "Location 1".to_string()).build(),
         );
+        let to = timeline.insert_event(
+            EventBuilder::new("Event 2".to_string(), 3, 4, "Location 2".to_string()).build(),
+        );
+        assert_eq!(to, EventId(1));
+        timeline.add_trigger_between(from, to);
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.contains(r#"class="trigger-link" d="M200,31 L400,51""#));
+    }
+
+    #[test]
+    fn test_trigger_span() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1".to_string(), 0, 1, "Location 1".to_string());
+        timeline.add_event("Event 2".to_string(), 3, 4, "Location 2".to_string());
+        timeline.add_trigger_span("Location 1".to_string(), 1, "Location 2".to_string(), 5);
+        timeline.add_trigger("Location 1".to_string(), "Location 2".to_string(), 2);
+        assert_eq!(timeline.time_range(), Some((0, 5)));
+        assert_eq!(timeline.triggers().next().unwrap().end_time(), 5);
+        assert_eq!(timeline.trigger_latency_stats()[0].mean, 4.0);
+
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.contains(
+            r#"class="trigger-span" d="M200,21 L1000,41" fill="none" marker-end="url(#link-arrow)""#
+        ));
+        assert!(svg.contains(r#"<path d="M400,21 L400,41""#));
+        assert!(svg.contains(r#"<marker id="link-arrow""#));
+
+        timeline.crop(0, 4);
+        assert_eq!(timeline.triggers().count(), 1);
+    }
+
+    #[test]
+    fn test_trigger_threshold() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1".to_string(), 1, 2, "Location 1".to_string());
+        timeline.add_event("Event 2".to_string(), 1, 2, "Location 2".to_string());
+        for _ in 0..3 {
+            timeline.add_trigger("Location 1".to_string(), "Location 2".to_string(), 1);
+        }
+        timeline.add_trigger("Location 1".to_string(), "Location 2".to_string(), 2);
+        timeline.set_trigger_threshold(Some(2));
+
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert_eq!(svg.matches("<path").count(), 1);
+        assert_eq!(svg.matches("trigger-density").count(), 1);
+        assert!(svg.contains(r#"data-count="3""#));
+    }
+
+    #[test]
+    fn test_event_metadata() {
+        let mut timeline = Timeline::default();
+        let event = EventBuilder::new("Event 1".to_string(), 1, 2, "Location 1".to_string())
+            .metadata("pid".to_string(), "1234".to_string())
+            .build();
+        timeline.insert_event(event);
+
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.contains(r#"data-pid="1234""#));
+        assert!(!svg.contains("<a "));
+        assert!(svg.contains("<title>Event 1\nstart: 1 ns\nend: 2 ns\nduration: 1 ns\nlocation: Location 1\npid: 1234</title>"));
+    }
+
+    #[test]
+    fn test_event_url() {
+        let mut timeline = Timeline::default();
+        let event = EventBuilder::new("Event 1".to_string(), 1, 2, "Location 1".to_string())
+            .url("https://example.com/trace?id=1&span=2".to_string())
+            .build();
+        timeline.insert_event(event);
+
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.contains(
+            "<g class=\"event\">\n<a href=\"https://example.com/trace?id=1&amp;span=2\">\n<rect"
+        ));
+    }
+
+    #[test]
+    fn test_z_index() {
+        let mut timeline = Timeline::default();
+        let event = EventBuilder::new("Event 1".to_string(), 1, 2, "Location 1".to_string())
+            .style(EventStyle {
+                z_index: 1,
+                layer: Layer::Events,
+            })
+            .build();
+        timeline.insert_event(event);
+        timeline.add_event("Event 2".to_string(), 1, 2, "Location 1".to_string());
+        let event = EventBuilder::new("Event 3".to_string(), 1, 2, "Location 1".to_string())
+            .style(EventStyle {
+                z_index: 0,
+                layer: Layer::Annotations,
+            })
+            .build();
+        timeline.insert_event(event);
+        timeline.add_highlight(1, 2, "yellow".to_string(), Layer::Background);
+
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.find("Event 2").unwrap() < svg.find("Event 1").unwrap());
+        assert!(svg.find("layer-annotations").unwrap() < svg.find("Event 3").unwrap());
+        assert!(svg.find("yellow").unwrap() < svg.find("layer-events").unwrap());
+    }
+
+    #[test]
+    fn test_instant_threshold() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event".to_string(), 0, 10, "Location 1".to_string());
+        for time in [1, 1, 1, 1, 2, 2, 5] {
+            timeline.add_event("IRQ".to_string(), time, time, "Location 2".to_string());
+        }
+        timeline.add_event("IRQ".to_string(), 3, 3, "Location 1".to_string());
+        timeline.set_instant_threshold(Some(2));
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert_eq!(svg.matches("instant-density").count(), 3);
+        assert!(svg.contains(
+            r#"class="instant-density" data-count="4" fill="dimgray" height="20" width="1" x="200" y="41""#
+        ));
+        assert!(svg.contains(r#"data-count="2" fill="dimgray" height="10""#));
+        assert!(svg.contains(r#"class="instant-markers" data-zoom="4" display="none""#));
+        assert_eq!(svg.matches("class=\"event\"").count(), 9);
+    }
+
+    #[test]
+    fn test_add_category() {
+        let mut timeline = Timeline::default();
+        timeline.add_category("Location 3".to_string());
+        timeline.add_category("Location 1".to_string());
+        timeline.add_event("Event 1".to_string(), 1, 2, "Location 1".to_string());
+        timeline.add_trigger("Location 1".to_string(), "Location 3".to_string(), 1);
+        assert_eq!(timeline.categories(), ["Location 1", "Location 3"]);
+        assert_eq!(timeline.time_range(), Some((1, 2)));
+
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.contains(r#"<path d="M0,21 L0,41""#));
+        assert!(svg.contains(r#"height="61""#));
+
+        timeline.set_category_filter(Some(vec!["Location 1".to_string()]));
+        assert_eq!(timeline.rows().categories(), ["Location 1"]);
+        timeline.clear();
+        assert_eq!(timeline.categories(), ["Location 1", "Location 3"]);
+    }
+
+    #[test]
+    fn test_draw_order() {
+        let render = |timeline: &Timeline| {
+            let mut output = Vec::new();
+            timeline.write(&mut output).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+        let events = [
+            ("B", 1, "Location 2"),
+            ("A", 1, "Location 2"),
+            ("C", 1, "Location 1"),
+            ("D", 0, "Location 2"),
+        ];
+        // One color, so colors don't depend on the order names were first seen in either
+        let mut forward = Timeline::builder().palette(vec!["red".to_string()]).build();
+        let mut backward = Timeline::builder().palette(vec!["red".to_string()]).build();
+        for &(name, start, location) in &events {
+            forward.add_event(name.to_string(), start, 2, location.to_string());
+            forward.add_trigger("Location 1".to_string(), location.to_string(), 1);
+        }
+        for &(name, start, location) in events.iter().rev() {
+            backward.add_event(name.to_string(), start, 2, location.to_string());
+            backward.add_trigger("Location 1".to_string(), location.to_string(), 1);
+        }
+
+        let svg = render(&forward);
+        assert_eq!(svg, render(&backward));
+        let position = |name: &str| svg.find(&format!(">\n{}\n<", name)).unwrap();
+        assert!(position("D") < position("C"));
+        assert!(position("C") < position("A"));
+        assert!(position("A") < position("B"));
+    }
+
+    #[test]
+    fn test_layer_order() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1".to_string(), 1, 2, "Location 1".to_string());
+        timeline.add_trigger("Location 1".to_string(), "Location 1".to_string(), 1);
+        timeline.add_marker("Marker".to_string(), 2);
+
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.find("layer-events").unwrap() < svg.find("layer-triggers").unwrap());
+        assert!(svg.find("layer-triggers").unwrap() < svg.find("layer-annotations").unwrap());
+
+        timeline.lower_layer(Layer::Triggers);
+        timeline.set_layer_order(vec![Layer::Triggers, Layer::Events]);
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.find("layer-triggers").unwrap() < svg.find("layer-events").unwrap());
+        assert!(!svg.contains("layer-annotations"));
+        assert!(!svg.contains("layer-background"));
     }
 }
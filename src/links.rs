@@ -0,0 +1,98 @@
+use svg::node::element::{Definitions, Marker, Path};
+
+use crate::{EventId, TimeValue, Timeline};
+
+// The id of the arrowhead drawn at the end of labeled links
+pub(crate) const ARROW_ID: &str = "link-arrow";
+
+// A connector between two events, see `add_trigger_between` and `add_link`
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct Link {
+    pub(crate) from: EventId,
+    pub(crate) to: EventId,
+    // Links added with `add_link` have a label, possibly empty, and an arrowhead
+    pub(crate) label: Option<String>,
+}
+
+impl<T: TimeValue> Timeline<T> {
+    /// Add a labeled arrow from one event to another
+    ///
+    /// This is `add_trigger_between` for causality: the connector from the end of the `from`
+    /// event to the start of the `to` event gets an arrowhead and `label` written at its middle,
+    /// like "wakes" or "sends request". The events can be on any rows and at any times, so the
+    /// arrow can point back in time if `to` starts before `from` ends.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// let write = timeline.add_event("write".to_string(), 0, 2, "client".to_string());
+    /// let handle = timeline.add_event("handle".to_string(), 5, 9, "server".to_string());
+    /// timeline.add_link(write, handle, "request".to_string());
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This panics if either handle wasn't returned by this timeline, or its event was removed.
+    pub fn add_link(&mut self, from: EventId, to: EventId, label: impl Into<String>) {
+        self.expect_event(from);
+        self.expect_event(to);
+        self.links.push(Link {
+            from,
+            to,
+            label: Some(label.into()),
+        });
+    }
+}
+
+// The arrowhead of labeled links, referenced by their `marker-end`
+pub(crate) fn arrow_definitions() -> Definitions {
+    let head = Path::new()
+        .set("d", "M0,0 L8,4 L0,8 z")
+        .set("fill", "black");
+    let marker = Marker::new()
+        .set("id", ARROW_ID)
+        .set("viewBox", "0 0 8 8")
+        .set("refX", 8)
+        .set("refY", 4)
+        .set("markerWidth", 8)
+        .set("markerHeight", 8)
+        .set("orient", "auto")
+        .add(head);
+    Definitions::new().add(marker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_link() {
+        let mut timeline = Timeline::default();
+        let from = timeline.add_event("Event 1".to_string(), 1, 2, "Location 1".to_string());
+        let to = timeline.add_event("Event 2".to_string(), 4, 5, "Location 2".to_string());
+        timeline.add_trigger_between(from, to);
+        let render = |timeline: &Timeline| {
+            let mut output = Vec::new();
+            timeline.write(&mut output).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+        assert!(!render(&timeline).contains(ARROW_ID));
+
+        timeline.add_link(to, from, "wakes".to_string());
+        let svg = render(&timeline);
+        assert!(svg.contains(r#"<marker id="link-arrow""#));
+        assert!(svg.contains(
+            r#"class="event-link" d="M800,51 L0,31" fill="none" marker-end="url(#link-arrow)""#
+        ));
+        assert!(svg.contains("class=\"link-label\" fill=\"black\" font-size=\"10\" text-anchor=\"middle\" x=\"400\" y=\"39\">\nwakes"));
+        assert_eq!(timeline.trigger_latency_stats().len(), 2);
+
+        timeline.remove_event(from);
+        assert!(timeline.links.is_empty());
+    }
+}
@@ -0,0 +1,33 @@
+// Logging of notable render decisions
+//
+// With the `log` feature enabled these forward to the `log` crate, so pipelines rendering
+// timelines can notice when data was bundled, clamped or otherwise altered. Without it they
+// compile to nothing, but still type check their arguments.
+
+#[cfg(feature = "log")]
+macro_rules! log_debug {
+    ($($arg:tt)*) => { ::log::debug!(target: "timeline_svg", $($arg)*) };
+}
+
+#[cfg(not(feature = "log"))]
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        if false {
+            let _ = format_args!($($arg)*);
+        }
+    };
+}
+
+#[cfg(feature = "log")]
+macro_rules! log_warn {
+    ($($arg:tt)*) => { ::log::warn!(target: "timeline_svg", $($arg)*) };
+}
+
+#[cfg(not(feature = "log"))]
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        if false {
+            let _ = format_args!($($arg)*);
+        }
+    };
+}
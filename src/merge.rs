@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use crate::{EventId, TimeValue, Timeline};
+
+impl<T: TimeValue> Timeline<T> {
+    /// Add everything on `other` to this timeline
+    ///
+    /// This combines traces collected separately, like on different machines or in different
+    /// processes, into one chart. The events, triggers, triggers between events, markers,
+    /// bookmarks, counter samples, state transitions, highlights and declared rows of `other` are
+    /// added after those of this timeline, and event names that have no color here keep their
+    /// color from `other`. Times of `other` are converted to the units of this timeline. Handles
+    /// returned by `other` aren't valid for this timeline.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("read".to_string(), 0, 5, "host-a".to_string());
+    /// let mut other = Timeline::default();
+    /// other.add_event("write".to_string(), 3, 8, "host-b".to_string());
+    /// timeline.merge(other);
+    /// assert_eq!(timeline.time_range(), Some((0, 8)));
+    /// # }
+    /// ```
+    pub fn merge(&mut self, other: Timeline<T>) {
+        self.merge_shifted(other, 0.0);
+    }
+
+    /// Add everything on `other` to this timeline, shifted in time
+    ///
+    /// This is the same as `merge`, but every time of `other` is moved by `offset` units of this
+    /// timeline, to line up traces whose clocks don't agree. A negative `offset` moves `other`
+    /// earlier.
+    pub fn merge_shifted(&mut self, other: Timeline<T>, offset: f64) {
+        let (from, to) = (other.units, self.units);
+        let convert = |time: T| {
+            let time = if from == to {
+                time
+            } else {
+                T::from_nanos(time.to_nanos(from), to)
+            };
+            if offset == 0.0 {
+                time
+            } else {
+                time.shift(offset, to)
+            }
+        };
+
+        for (name, color) in other.colormap {
+            self.colormap.entry(name).or_insert(color);
+        }
+        for (name, shape) in other.shapes {
+            self.shapes.entry(name).or_insert(shape);
+        }
+
+        let mut ids: HashMap<EventId, EventId> = HashMap::new();
+        for (id, mut event) in other.event_ids.into_iter().zip(other.events) {
+            event.start_time = convert(event.start_time);
+            event.end_time = convert(event.end_time);
+            ids.insert(id, self.insert_event(event));
+        }
+        for mut link in other.links {
+            if let (Some(&from), Some(&to)) = (ids.get(&link.from), ids.get(&link.to)) {
+                (link.from, link.to) = (from, to);
+                self.links.push(link);
+            }
+        }
+        for trigger in other.triggers {
+            self.add_trigger_span(
+                trigger.start_location,
+                convert(trigger.time),
+                trigger.end_location,
+                convert(trigger.end_time),
+            );
+        }
+        for marker in other.markers {
+            self.add_marker_on_layer(marker.label, convert(marker.time), marker.layer);
+        }
+        for bookmark in other.bookmarks {
+            self.add_bookmark(convert(bookmark.time), bookmark.name);
+        }
+        for (counter, samples) in other.counters {
+            for (time, value) in samples {
+                self.add_counter_sample(counter.clone(), convert(time), value);
+            }
+        }
+        for (counter, style) in other.counter_styles {
+            self.counter_styles.entry(counter).or_insert(style);
+        }
+        for (track, transitions) in other.state_tracks {
+            for (time, state) in transitions {
+                self.add_state_transition(track.clone(), convert(time), state);
+            }
+        }
+        for category in other.declared_categories {
+            self.add_category(category);
+        }
+        for highlight in other.highlights {
+            self.add_highlight(
+                convert(highlight.start_time),
+                convert(highlight.end_time),
+                highlight.color,
+                highlight.layer,
+            );
+        }
+        log_debug!("merged {} events from another timeline", ids.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TimeUnit;
+
+    #[test]
+    fn test_merge() {
+        let mut timeline = Timeline::default();
+        timeline.set_units(TimeUnit::Milliseconds);
+        timeline.add_event("read".to_string(), 0, 5, "host-a".to_string());
+
+        let mut other = Timeline::default();
+        other.set_units(TimeUnit::Microseconds);
+        let send = other.add_event("send".to_string(), 1000, 2000, "host-b".to_string());
+        let receive = other.add_event("receive".to_string(), 3000, 4000, "host-b".to_string());
+        other.add_trigger_between(send, receive);
+        other.add_marker("sync".to_string(), 500);
+        let color = other.export_colormap();
+
+        timeline.merge_shifted(other, 10.0);
+        let events: Vec<(&str, i64, i64)> = timeline
+            .events()
+            .map(|(_, event)| (event.name(), event.start_time(), event.end_time()))
+            .collect();
+        assert_eq!(
+            events,
+            [("read", 0, 5), ("send", 11, 12), ("receive", 13, 14)]
+        );
+        assert_eq!(timeline.time_range(), Some((0, 14)));
+        assert_eq!(timeline.trigger_latency_stats()[0].mean, 1.0);
+        let colors: HashMap<String, String> = serde_json::from_str(&color).unwrap();
+        assert_eq!(timeline.colormap["send"], colors["send"]);
+    }
+}
@@ -0,0 +1,113 @@
+use std::io::Write;
+
+use crate::{TimeValue, Timeline, TimelineError, TimelineExporter};
+
+// The Mermaid gantt chart format, see `Timeline::export_mermaid`
+pub(crate) struct MermaidExporter;
+
+impl TimelineExporter for MermaidExporter {
+    fn format(&self) -> &str {
+        "mermaid"
+    }
+
+    fn export(&self, timeline: &Timeline, writer: &mut dyn Write) -> Result<(), TimelineError> {
+        timeline.export_mermaid(writer)
+    }
+}
+
+// Mermaid ends task names at colons and statements at semicolons and line breaks, so those are
+// written as entity codes or spaces
+fn task_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '#' => "#35;".to_string(),
+            ':' => "#58;".to_string(),
+            ';' => "#59;".to_string(),
+            '\n' | '\r' => " ".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+impl<T: TimeValue> Timeline<T> {
+    /// Write the events of the timeline as a Mermaid gantt chart
+    ///
+    /// The chart is text, so timelines can be embedded in Markdown documents and wikis that
+    /// render Mermaid. Every row becomes a section with a task per event, in the order they were
+    /// added, and events without duration become milestones. Mermaid counts time in whole
+    /// milliseconds, so times are written as the whole number of units since the start of the
+    /// timeline, and the axis is labeled with those numbers. This is the same as
+    /// `export("mermaid")`.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("read", 0, 5, "CPU 0");
+    /// let mut output = Vec::new();
+    /// timeline.export_mermaid(&mut output).unwrap();
+    /// assert!(String::from_utf8(output).unwrap().contains("read : 0, 5"));
+    /// # }
+    /// ```
+    pub fn export_mermaid<W: Write>(&self, mut writer: W) -> Result<(), TimelineError> {
+        let offset = |time: T| time.offset_from(self.start_time, self.units).round();
+        writeln!(writer, "gantt")?;
+        writeln!(writer, "    dateFormat x")?;
+        writeln!(writer, "    axisFormat %Q")?;
+        for category in self.categories() {
+            let mut events = self
+                .events()
+                .map(|(_, event)| event)
+                .filter(|event| event.location == category)
+                .peekable();
+            if events.peek().is_none() {
+                continue;
+            }
+            writeln!(writer, "    section {}", task_name(category))?;
+            for event in events {
+                let (start, end) = (offset(event.start_time), offset(event.end_time));
+                if event.start_time == event.end_time {
+                    writeln!(
+                        writer,
+                        "    {} : milestone, {}, 0",
+                        task_name(&event.name),
+                        start
+                    )?;
+                } else {
+                    writeln!(
+                        writer,
+                        "    {} : {}, {}",
+                        task_name(&event.name),
+                        start,
+                        end
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_mermaid() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("write: #1", 12, 20, "CPU 1");
+        timeline.add_event("read", 10, 15, "CPU 0");
+        timeline.add_event("irq", 14, 14, "CPU 0");
+        timeline.add_category("CPU 2".to_string());
+
+        let mut output = Vec::new();
+        timeline.export("mermaid", &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "gantt\n    dateFormat x\n    axisFormat %Q\n    section CPU 0\n    read : 0, 5\n    \
+             irq : milestone, 4, 0\n    section CPU 1\n    write#58; #35;1 : 2, 10\n"
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use std::cmp::Ordering;
+
+use crate::{TimeValue, Timeline};
+
+pub(crate) type Comparator = Box<dyn Fn(&str, &str) -> Ordering + Send + Sync>;
+
+/// Compare strings with runs of digits compared as numbers
+///
+/// This sorts "CPU 2" before "CPU 10", which a plain string comparison doesn't. Pass it to
+/// `Timeline::set_category_comparator` to sort rows this way.
+///
+/// ```
+/// # extern crate timeline_svg;
+/// # fn main() {
+/// let mut rows = vec!["CPU 10", "CPU 2", "CPU 1"];
+/// rows.sort_by(|a, b| timeline_svg::natural_cmp(a, b));
+/// assert_eq!(rows, ["CPU 1", "CPU 2", "CPU 10"]);
+/// # }
+/// ```
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_rest, mut b_rest) = (a, b);
+    while let (Some(a_next), Some(b_next)) = (a_rest.chars().next(), b_rest.chars().next()) {
+        let ordering = if a_next.is_ascii_digit() && b_next.is_ascii_digit() {
+            let a_digits = digit_run(a_rest);
+            let b_digits = digit_run(b_rest);
+            a_rest = &a_rest[a_digits.len()..];
+            b_rest = &b_rest[b_digits.len()..];
+            let (a_number, b_number) = (
+                a_digits.trim_start_matches('0'),
+                b_digits.trim_start_matches('0'),
+            );
+            a_number
+                .len()
+                .cmp(&b_number.len())
+                .then_with(|| a_number.cmp(b_number))
+        } else {
+            a_rest = &a_rest[a_next.len_utf8()..];
+            b_rest = &b_rest[b_next.len_utf8()..];
+            a_next.cmp(&b_next)
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    // Shorter strings first, and "01" and "1" apart
+    a_rest.len().cmp(&b_rest.len()).then_with(|| a.cmp(b))
+}
+
+// The digits at the start of `s`
+fn digit_run(s: &str) -> &str {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    &s[..end]
+}
+
+impl<T: TimeValue> Timeline<T> {
+    /// Draw the rows of `order` first, in that order
+    ///
+    /// Rows are sorted alphabetically by default, or with the comparator of
+    /// `set_category_comparator`. The rows listed in `order` come before all others instead, in
+    /// the order they are listed, so the important rows can be put on top. Listed categories
+    /// without events don't get a row, unless they are declared with `add_category`.
+    pub fn set_category_order(&mut self, order: Vec<String>) {
+        self.category_order = order;
+        self.invalidate_layout();
+    }
+
+    /// Sort the rows with `compare` instead of alphabetically
+    ///
+    /// Rows listed with `set_category_order` still come first. See `natural_cmp` for sorting
+    /// numbered rows like "CPU 2" and "CPU 10" by their numbers.
+    pub fn set_category_comparator(
+        &mut self,
+        compare: impl Fn(&str, &str) -> Ordering + Send + Sync + 'static,
+    ) {
+        self.category_comparator = Some(Box::new(compare));
+        self.invalidate_layout();
+    }
+
+    // Sort and dedup categories into the order of the rows, see `set_category_order` and
+    // `add_category_to_group`
+    pub(crate) fn sort_categories(&self, categories: &mut Vec<&str>) {
+        let compare = |a: &str, b: &str| match &self.category_comparator {
+            Some(compare) => compare(a, b),
+            None => a.cmp(b),
+        };
+        // Rows without a group first, then the groups sorted like rows
+        let group_order = |a: &str, b: &str| {
+            let groups = (self.category_group(a), self.category_group(b));
+            match groups {
+                (Some(a), Some(b)) => compare(a, b).then_with(|| a.cmp(b)),
+                (a, b) => a.is_some().cmp(&b.is_some()),
+            }
+        };
+        let rank = |category: &str| {
+            self.category_order
+                .iter()
+                .position(|c| c == category)
+                .unwrap_or(usize::MAX)
+        };
+        categories.sort_by(|a, b| {
+            group_order(a, b)
+                .then_with(|| rank(a).cmp(&rank(b)))
+                .then_with(|| compare(a, b))
+                // Keep equal names next to each other for the dedup
+                .then_with(|| a.cmp(b))
+        });
+        categories.dedup();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_category_order() {
+        assert_eq!(natural_cmp("CPU 2", "CPU 10"), Ordering::Less);
+        assert_eq!(natural_cmp("a10b2", "a10b01"), Ordering::Greater);
+        assert_eq!(natural_cmp("a", "a1"), Ordering::Less);
+        assert_eq!(natural_cmp("x01", "x1"), Ordering::Less);
+        assert_eq!(natural_cmp("é2", "é2"), Ordering::Equal);
+
+        let mut timeline = Timeline::default();
+        for location in ["CPU 10", "CPU 2", "IO", "CPU 1"] {
+            timeline.add_event("Event".to_string(), 0, 1, location.to_string());
+        }
+        assert_eq!(timeline.categories(), ["CPU 1", "CPU 10", "CPU 2", "IO"]);
+        timeline.set_category_comparator(natural_cmp);
+        assert_eq!(timeline.categories(), ["CPU 1", "CPU 2", "CPU 10", "IO"]);
+        timeline.set_category_order(vec!["IO".to_string(), "Net".to_string()]);
+        assert_eq!(timeline.categories(), ["IO", "CPU 1", "CPU 2", "CPU 10"]);
+        let rows = timeline.rows();
+        assert_eq!(timeline.category_y("IO", rows).unwrap(), 21.0);
+    }
+}
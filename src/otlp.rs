@@ -0,0 +1,652 @@
+use std::io::Read;
+
+use serde_json::Value;
+
+use crate::chrome::text;
+use crate::import::sniff_text;
+use crate::spans::{spans_timeline, Span, SpanRows};
+use crate::{Timeline, TimelineError, TraceImporter};
+
+// The JSON of OpenTelemetry span exports, see `Timeline::from_otlp`
+pub(crate) struct OtlpImporter;
+
+impl TraceImporter for OtlpImporter {
+    fn format(&self) -> &str {
+        "otlp"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        let text = sniff_text(data);
+        text.trim_start().starts_with('{') && text.contains("\"resourceSpans\"")
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_otlp(data, SpanRows::Service)
+    }
+}
+
+// The span kinds by their number, which start with "SPAN_KIND_" in JSON enums
+const KINDS: [&str; 6] = [
+    "unspecified",
+    "internal",
+    "server",
+    "client",
+    "producer",
+    "consumer",
+];
+
+fn kind(number: i64) -> Option<String> {
+    KINDS
+        .get(usize::try_from(number).ok()?)
+        .filter(|kind| **kind != "unspecified")
+        .map(|kind| kind.to_string())
+}
+
+// An `AnyValue` of JSON as text, like the values of attributes
+fn any_value(value: &Value) -> String {
+    match value.as_object().and_then(|value| value.values().next()) {
+        Some(inner) => text(inner),
+        None => text(value),
+    }
+}
+
+// `KeyValue`s of JSON as pairs of text
+fn attributes(list: &Value) -> Vec<(String, String)> {
+    list.as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|attribute| {
+            Some((
+                attribute["key"].as_str()?.to_string(),
+                any_value(&attribute["value"]),
+            ))
+        })
+        .collect()
+}
+
+// The times of OTLP JSON, which are strings of nanoseconds since the epoch to keep them exact,
+// though some exporters write them as numbers
+fn nanos(value: &Value) -> Option<i64> {
+    match value {
+        Value::String(text) => text.parse().ok(),
+        other => other.as_i64(),
+    }
+}
+
+fn parse_json(text: &str) -> Result<Vec<Span>, TimelineError> {
+    let export: Value =
+        serde_json::from_str(text).map_err(|e| TimelineError::Parse(e.to_string()))?;
+    let resource_spans = export["resourceSpans"]
+        .as_array()
+        .ok_or_else(|| TimelineError::Parse("no resourceSpans array".to_string()))?;
+    let mut spans = Vec::new();
+    for resource in resource_spans {
+        let service = attributes(&resource["resource"]["attributes"])
+            .into_iter()
+            .find(|(key, _)| key == "service.name")
+            .map(|(_, service)| service);
+        // Exporters before OTLP 1.0 call scopes instrumentation libraries
+        let scopes = resource["scopeSpans"]
+            .as_array()
+            .or_else(|| resource["instrumentationLibrarySpans"].as_array());
+        for span in scopes
+            .into_iter()
+            .flatten()
+            .flat_map(|scope| scope["spans"].as_array().into_iter().flatten())
+        {
+            let kind = match &span["kind"] {
+                Value::String(name) => {
+                    let name = name.strip_prefix("SPAN_KIND_").unwrap_or(name);
+                    Some(name.to_lowercase()).filter(|kind| kind != "unspecified")
+                }
+                number => number.as_i64().and_then(kind),
+            };
+            let mut attributes = attributes(&span["attributes"]);
+            if span["status"]["code"] == 2 || span["status"]["code"] == "STATUS_CODE_ERROR" {
+                attributes.push(("status".to_string(), "error".to_string()));
+                if let Some(message) = span["status"]["message"].as_str() {
+                    attributes.push(("status_message".to_string(), message.to_string()));
+                }
+            }
+            let (Some(trace_id), Some(span_id), Some(start), Some(end)) = (
+                span["traceId"].as_str(),
+                span["spanId"].as_str(),
+                nanos(&span["startTimeUnixNano"]),
+                nanos(&span["endTimeUnixNano"]),
+            ) else {
+                continue;
+            };
+            spans.push(Span {
+                trace_id: trace_id.to_lowercase(),
+                span_id: span_id.to_lowercase(),
+                parent_id: span["parentSpanId"]
+                    .as_str()
+                    .filter(|parent| !parent.is_empty())
+                    .map(str::to_lowercase),
+                links: Vec::new(),
+                name: span["name"].as_str().unwrap_or_default().to_string(),
+                service: service.clone(),
+                kind,
+                start,
+                end,
+                attributes,
+            });
+        }
+    }
+    Ok(spans)
+}
+
+// The messages of the OTLP protobuf encoding, declared by hand with the field numbers of
+// opentelemetry/proto/trace/v1/trace.proto like those of the Perfetto exporter. Oneofs are
+// declared as optional fields, which have the same encoding.
+#[cfg(feature = "otlp")]
+mod proto {
+    use prost::Message;
+
+    // Also the encoding of `ExportTraceServiceRequest`, which has the same field
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct TracesData {
+        #[prost(message, repeated, tag = "1")]
+        pub(super) resource_spans: Vec<ResourceSpans>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct ResourceSpans {
+        #[prost(message, optional, tag = "1")]
+        pub(super) resource: Option<Resource>,
+        #[prost(message, repeated, tag = "2")]
+        pub(super) scope_spans: Vec<ScopeSpans>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct Resource {
+        #[prost(message, repeated, tag = "1")]
+        pub(super) attributes: Vec<KeyValue>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct ScopeSpans {
+        #[prost(message, repeated, tag = "2")]
+        pub(super) spans: Vec<Span>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct Span {
+        #[prost(bytes = "vec", tag = "1")]
+        pub(super) trace_id: Vec<u8>,
+        #[prost(bytes = "vec", tag = "2")]
+        pub(super) span_id: Vec<u8>,
+        #[prost(bytes = "vec", tag = "4")]
+        pub(super) parent_span_id: Vec<u8>,
+        #[prost(string, tag = "5")]
+        pub(super) name: String,
+        #[prost(int32, tag = "6")]
+        pub(super) kind: i32,
+        #[prost(fixed64, tag = "7")]
+        pub(super) start_time_unix_nano: u64,
+        #[prost(fixed64, tag = "8")]
+        pub(super) end_time_unix_nano: u64,
+        #[prost(message, repeated, tag = "9")]
+        pub(super) attributes: Vec<KeyValue>,
+        #[prost(message, optional, tag = "15")]
+        pub(super) status: Option<Status>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct KeyValue {
+        #[prost(string, tag = "1")]
+        pub(super) key: String,
+        #[prost(message, optional, tag = "2")]
+        pub(super) value: Option<AnyValue>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct AnyValue {
+        #[prost(string, optional, tag = "1")]
+        pub(super) string_value: Option<String>,
+        #[prost(bool, optional, tag = "2")]
+        pub(super) bool_value: Option<bool>,
+        #[prost(int64, optional, tag = "3")]
+        pub(super) int_value: Option<i64>,
+        #[prost(double, optional, tag = "4")]
+        pub(super) double_value: Option<f64>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct Status {
+        #[prost(string, tag = "2")]
+        pub(super) message: String,
+        #[prost(int32, tag = "3")]
+        pub(super) code: i32,
+    }
+}
+
+// Ids as the lowercase hex that OTLP JSON and trace UIs show them in
+#[cfg(feature = "otlp")]
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(feature = "otlp")]
+fn proto_attributes(list: &[proto::KeyValue]) -> Vec<(String, String)> {
+    list.iter()
+        .map(|attribute| {
+            let value = attribute.value.clone().unwrap_or_default();
+            let value = value
+                .string_value
+                .or(value.bool_value.map(|value| value.to_string()))
+                .or(value.int_value.map(|value| value.to_string()))
+                .or(value.double_value.map(|value| value.to_string()))
+                .unwrap_or_default();
+            (attribute.key.clone(), value)
+        })
+        .collect()
+}
+
+#[cfg(feature = "otlp")]
+fn parse_proto(data: &[u8]) -> Result<Vec<Span>, TimelineError> {
+    use prost::Message;
+
+    let traces =
+        proto::TracesData::decode(data).map_err(|e| TimelineError::Parse(e.to_string()))?;
+    let mut spans = Vec::new();
+    for resource in traces.resource_spans {
+        let service = proto_attributes(&resource.resource.unwrap_or_default().attributes)
+            .into_iter()
+            .find(|(key, _)| key == "service.name")
+            .map(|(_, service)| service);
+        for span in resource
+            .scope_spans
+            .into_iter()
+            .flat_map(|scope| scope.spans)
+        {
+            let mut attributes = proto_attributes(&span.attributes);
+            if let Some(status) = span.status.filter(|status| status.code == 2) {
+                attributes.push(("status".to_string(), "error".to_string()));
+                if !status.message.is_empty() {
+                    attributes.push(("status_message".to_string(), status.message));
+                }
+            }
+            spans.push(Span {
+                trace_id: hex(&span.trace_id),
+                span_id: hex(&span.span_id),
+                parent_id: Some(hex(&span.parent_span_id)).filter(|parent| !parent.is_empty()),
+                links: Vec::new(),
+                name: span.name,
+                service: service.clone(),
+                kind: kind(span.kind.into()),
+                start: span.start_time_unix_nano as i64,
+                end: span.end_time_unix_nano as i64,
+                attributes,
+            });
+        }
+    }
+    Ok(spans)
+}
+
+#[cfg(not(feature = "otlp"))]
+fn parse_proto(_data: &[u8]) -> Result<Vec<Span>, TimelineError> {
+    Err(TimelineError::Parse(
+        "not OTLP JSON, and reading protobuf needs the otlp feature".to_string(),
+    ))
+}
+
+impl Timeline {
+    /// Read the spans of an OpenTelemetry trace export into a timeline
+    ///
+    /// This reads the OTLP JSON encoding of `TracesData` or `ExportTraceServiceRequest`, like
+    /// the file exporter of the OpenTelemetry Collector writes, and with the `otlp` feature also
+    /// their protobuf encoding. Every span becomes an event named after it, on the row of its
+    /// service, named after the `service.name` of its resource, or of its kind, like "server",
+    /// as `rows` picks. A span whose parent is on another row gets a trigger from the row of
+    /// its parent when it starts. The ids of the trace, the span and its parent, its "service",
+    /// "kind" and attributes are its metadata, and spans with an error status have a "status"
+    /// of "error". Times are in nanoseconds since the epoch.
+    ///
+    /// This returns `TimelineError::Parse` if the export can't be read or has no spans, and
+    /// `TimelineError::Io` if reading fails. `Timeline::import` detects JSON exports, as
+    /// "otlp", and draws them by service.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::{SpanRows, Timeline};
+    ///
+    /// let export = r#"{"resourceSpans": [{
+    ///     "resource": {"attributes": [
+    ///         {"key": "service.name", "value": {"stringValue": "frontend"}}
+    ///     ]},
+    ///     "scopeSpans": [{"spans": [{
+    ///         "traceId": "5b8efff798038103d269b633813fc60c", "spanId": "eee19b7ec3c1b174",
+    ///         "name": "GET /", "kind": 2,
+    ///         "startTimeUnixNano": "1000", "endTimeUnixNano": "5000"
+    ///     }]}]
+    /// }]}"#;
+    /// let timeline = Timeline::from_otlp(export.as_bytes(), SpanRows::Service).unwrap();
+    /// assert_eq!(timeline.categories(), ["frontend"]);
+    /// assert_eq!(timeline.time_range(), Some((1000, 5000)));
+    /// # }
+    /// ```
+    pub fn from_otlp(mut reader: impl Read, rows: SpanRows) -> Result<Timeline, TimelineError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let json = data
+            .iter()
+            .find(|b| !b.is_ascii_whitespace())
+            .is_some_and(|b| *b == b'{');
+        let spans = if json {
+            parse_json(&String::from_utf8_lossy(&data))?
+        } else {
+            parse_proto(&data)?
+        };
+        spans_timeline(spans, rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const EXPORT: &str = r#"{"resourceSpans": [
+        {
+            "resource": {"attributes": [
+                {"key": "host.name", "value": {"stringValue": "web-1"}},
+                {"key": "service.name", "value": {"stringValue": "frontend"}}
+            ]},
+            "scopeSpans": [{"spans": [
+                {"traceId": "AA", "spanId": "01", "name": "GET /", "kind": "SPAN_KIND_SERVER",
+                 "startTimeUnixNano": "100", "endTimeUnixNano": "900",
+                 "attributes": [{"key": "http.status_code", "value": {"intValue": "500"}}]},
+                {"traceId": "aa", "spanId": "02", "parentSpanId": "01", "name": "render",
+                 "kind": 1, "startTimeUnixNano": 150, "endTimeUnixNano": 300}
+            ]}]
+        },
+        {
+            "resource": {"attributes": []},
+            "instrumentationLibrarySpans": [{"spans": [
+                {"traceId": "aa", "spanId": "03", "parentSpanId": "01", "name": "SELECT",
+                 "kind": 3, "startTimeUnixNano": "200", "endTimeUnixNano": "400"}
+            ]}]
+        }
+    ]}"#;
+
+    // An export of spans of one resource without a service
+    fn export(spans: Value) -> String {
+        json!({"resourceSpans": [{"scopeSpans": [{"spans": spans}]}]}).to_string()
+    }
+
+    #[test]
+    fn test_attributes() {
+        let list = json!([
+            {"key": "s", "value": {"stringValue": "text"}},
+            {"key": "i", "value": {"intValue": "7"}},
+            {"key": "b", "value": {"boolValue": true}},
+            {"key": "d", "value": {"doubleValue": 0.5}},
+            {"key": "plain", "value": 3},
+            {"value": {"stringValue": "no key"}}
+        ]);
+        let pairs: Vec<_> = attributes(&list)
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        assert_eq!(pairs, ["s=text", "i=7", "b=true", "d=0.5", "plain=3"]);
+        assert!(attributes(&json!({"key": "s"})).is_empty());
+        assert_eq!(kind(2).as_deref(), Some("server"));
+        assert_eq!(kind(5).as_deref(), Some("consumer"));
+        assert_eq!(kind(0), None);
+        assert_eq!(kind(6), None);
+        assert_eq!(kind(-1), None);
+        assert_eq!(
+            nanos(&json!("1700000000000000001")),
+            Some(1_700_000_000_000_000_001)
+        );
+        assert_eq!(nanos(&json!(150)), Some(150));
+        assert_eq!(nanos(&json!("1.5")), None);
+        assert_eq!(nanos(&Value::Null), None);
+    }
+
+    #[test]
+    fn test_from_otlp() {
+        assert!(OtlpImporter.sniff(EXPORT.as_bytes()));
+        assert!(!OtlpImporter.sniff(b"{\"data\": [{\"traceID\": \"aa\"}]}"));
+        let timeline = Timeline::from_otlp(EXPORT.as_bytes(), SpanRows::Service).unwrap();
+        // Spans of resources without a service, and of scopes of old exporters, have a row
+        assert_eq!(timeline.categories(), ["frontend", "unknown service"]);
+        let events: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name(),
+                    event.location(),
+                    event.start_time(),
+                    event.end_time(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("GET /", "frontend", 100, 900),
+                ("render", "frontend", 150, 300),
+                ("SELECT", "unknown service", 200, 400),
+            ]
+        );
+        let (_, get) = timeline.events().next().unwrap();
+        assert_eq!(get.metadata()["trace_id"], "aa");
+        assert_eq!(get.metadata()["service"], "frontend");
+        assert_eq!(get.metadata()["kind"], "server");
+        assert_eq!(get.metadata()["http.status_code"], "500");
+        assert!(!get.metadata().contains_key("parent_id"));
+        let triggers = |timeline: &Timeline| {
+            timeline
+                .triggers()
+                .map(|trigger| {
+                    (
+                        trigger.start_location().to_string(),
+                        trigger.end_location().to_string(),
+                        trigger.time(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            triggers(&timeline),
+            [("frontend".into(), "unknown service".into(), 200)]
+        );
+
+        let timeline = Timeline::from_otlp(EXPORT.as_bytes(), SpanRows::Kind).unwrap();
+        assert_eq!(timeline.categories(), ["client", "internal", "server"]);
+        assert_eq!(
+            triggers(&timeline),
+            [
+                ("server".into(), "internal".into(), 150),
+                ("server".into(), "client".into(), 200)
+            ]
+        );
+        // Unspecified kinds, by name or number, are drawn together
+        let spans = json!([
+            {"traceId": "aa", "spanId": "01", "kind": "SPAN_KIND_UNSPECIFIED",
+             "startTimeUnixNano": "1", "endTimeUnixNano": "2"},
+            {"traceId": "aa", "spanId": "02", "kind": 0,
+             "startTimeUnixNano": "3", "endTimeUnixNano": "4"},
+            {"traceId": "aa", "spanId": "03", "kind": "PRODUCER",
+             "startTimeUnixNano": "5", "endTimeUnixNano": "6"}
+        ]);
+        let timeline = Timeline::from_otlp(export(spans).as_bytes(), SpanRows::Kind).unwrap();
+        assert_eq!(timeline.categories(), ["producer", "unspecified"]);
+    }
+
+    #[test]
+    fn test_status_and_incomplete_spans() {
+        let spans = json!([
+            {"traceId": "aa", "spanId": "01", "startTimeUnixNano": "1", "endTimeUnixNano": "2",
+             "status": {"code": 2, "message": "boom"}},
+            {"traceId": "aa", "spanId": "02", "startTimeUnixNano": "3", "endTimeUnixNano": "4",
+             "status": {"code": "STATUS_CODE_ERROR"}},
+            {"traceId": "aa", "spanId": "03", "startTimeUnixNano": "5", "endTimeUnixNano": "6",
+             "status": {"code": 1, "message": "fine"}}
+        ]);
+        let timeline = Timeline::from_otlp(export(spans).as_bytes(), SpanRows::Service).unwrap();
+        let statuses: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.metadata().get("status").map(String::as_str),
+                    event.metadata().get("status_message").map(String::as_str),
+                )
+            })
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                (Some("error"), Some("boom")),
+                (Some("error"), None),
+                (None, None),
+            ]
+        );
+
+        // Spans without ids or times are skipped, and empty parent ids are roots
+        let spans = json!([
+            {"traceId": "aa", "spanId": "01", "parentSpanId": "", "name": "root",
+             "startTimeUnixNano": "1", "endTimeUnixNano": "2"},
+            {"traceId": "aa", "name": "no id", "startTimeUnixNano": "1", "endTimeUnixNano": "2"},
+            {"spanId": "02", "name": "no trace", "startTimeUnixNano": "1", "endTimeUnixNano": "2"},
+            {"traceId": "aa", "spanId": "03", "name": "no end", "startTimeUnixNano": "1"}
+        ]);
+        let timeline = Timeline::from_otlp(export(spans).as_bytes(), SpanRows::Service).unwrap();
+        let names: Vec<_> = timeline.events().map(|(_, event)| event.name()).collect();
+        assert_eq!(names, ["root"]);
+        let (_, root) = timeline.events().next().unwrap();
+        assert!(!root.metadata().contains_key("parent_id"));
+
+        // Exports without any complete spans are errors
+        for export in [
+            r#"{"resourceSpans": []}"#,
+            r#"{"resourceSpans": [{"scopeSpans": [{"spans": [{}]}]}]}"#,
+            r#"{"resource_spans": []}"#,
+            r#"{"resourceSpans": "#,
+            "",
+        ] {
+            assert!(
+                matches!(
+                    Timeline::from_otlp(export.as_bytes(), SpanRows::Service),
+                    Err(TimelineError::Parse(_))
+                ),
+                "{}",
+                export
+            );
+        }
+    }
+
+    #[cfg(feature = "otlp")]
+    #[test]
+    fn test_from_otlp_proto() {
+        use prost::Message;
+
+        let traces = proto::TracesData {
+            resource_spans: vec![proto::ResourceSpans {
+                resource: Some(proto::Resource {
+                    attributes: vec![proto::KeyValue {
+                        key: "service.name".to_string(),
+                        value: Some(proto::AnyValue {
+                            string_value: Some("backend".to_string()),
+                            ..Default::default()
+                        }),
+                    }],
+                }),
+                scope_spans: vec![proto::ScopeSpans {
+                    spans: vec![proto::Span {
+                        trace_id: vec![0xab; 16],
+                        span_id: vec![0x01; 8],
+                        name: "query".to_string(),
+                        kind: 3,
+                        start_time_unix_nano: 10,
+                        end_time_unix_nano: 20,
+                        ..Default::default()
+                    }],
+                }],
+            }],
+        };
+        let data = traces.encode_to_vec();
+        let timeline = Timeline::from_otlp(&data[..], SpanRows::Service).unwrap();
+        assert_eq!(timeline.categories(), ["backend"]);
+        let (_, query) = timeline.events().next().unwrap();
+        assert_eq!(query.metadata()["span_id"], "0101010101010101");
+        assert_eq!(query.metadata()["kind"], "client");
+
+        let attribute = |key: &str, value: Option<proto::AnyValue>| proto::KeyValue {
+            key: key.to_string(),
+            value,
+        };
+        let list = [
+            attribute(
+                "b",
+                Some(proto::AnyValue {
+                    bool_value: Some(false),
+                    ..Default::default()
+                }),
+            ),
+            attribute(
+                "i",
+                Some(proto::AnyValue {
+                    int_value: Some(-3),
+                    ..Default::default()
+                }),
+            ),
+            attribute(
+                "d",
+                Some(proto::AnyValue {
+                    double_value: Some(1.5),
+                    ..Default::default()
+                }),
+            ),
+            attribute("none", None),
+        ];
+        let pairs: Vec<_> = proto_attributes(&list)
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        assert_eq!(pairs, ["b=false", "i=-3", "d=1.5", "none="]);
+
+        let span = |id: u8, code: i32| proto::Span {
+            trace_id: vec![0xab; 16],
+            span_id: vec![id; 8],
+            parent_span_id: if id == 1 { Vec::new() } else { vec![1; 8] },
+            start_time_unix_nano: id.into(),
+            end_time_unix_nano: 10,
+            status: Some(proto::Status {
+                message: if code == 2 {
+                    "boom".to_string()
+                } else {
+                    String::new()
+                },
+                code,
+            }),
+            ..Default::default()
+        };
+        let traces = proto::TracesData {
+            resource_spans: vec![proto::ResourceSpans {
+                resource: None,
+                scope_spans: vec![proto::ScopeSpans {
+                    spans: vec![span(1, 2), span(2, 1)],
+                }],
+            }],
+        };
+        let data = traces.encode_to_vec();
+        let timeline = Timeline::from_otlp(&data[..], SpanRows::Service).unwrap();
+        assert_eq!(timeline.categories(), ["unknown service"]);
+        let (_, failed) = timeline.events().next().unwrap();
+        assert_eq!(failed.metadata()["status"], "error");
+        assert_eq!(failed.metadata()["status_message"], "boom");
+        assert!(!failed.metadata().contains_key("parent_id"));
+        let (_, ok) = timeline.events().nth(1).unwrap();
+        assert!(!ok.metadata().contains_key("status"));
+        assert_eq!(ok.metadata()["parent_id"], "0101010101010101");
+        assert!(matches!(
+            Timeline::from_otlp(&b"\x0a\xff"[..], SpanRows::Service),
+            Err(TimelineError::Parse(_))
+        ));
+    }
+}
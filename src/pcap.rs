@@ -0,0 +1,906 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::import::new_timeline;
+use crate::{EventBuilder, Timeline, TimelineError, TraceImporter};
+
+// The first bytes of a pcap file as written on little endian machines, with times in
+// microseconds or nanoseconds, and of the section header block of a pcapng file
+const PCAP_MICROS: [u8; 4] = [0xd4, 0xc3, 0xb2, 0xa1];
+const PCAP_NANOS: [u8; 4] = [0x4d, 0x3c, 0xb2, 0xa1];
+const PCAPNG: [u8; 4] = [0x0a, 0x0d, 0x0d, 0x0a];
+
+// The pcapng blocks this reads
+const BLOCK_INTERFACE: u32 = 1;
+const BLOCK_ENHANCED_PACKET: u32 = 6;
+const OPTION_TSRESOL: u16 = 9;
+
+// The link layer types of the frames this reads
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LOOP: u32 = 108;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+// The TCP flags, in the order they are named in
+const TCP_FLAGS: [(u8, &str); 6] = [
+    (0x02, "SYN"),
+    (0x01, "FIN"),
+    (0x04, "RST"),
+    (0x08, "PSH"),
+    (0x20, "URG"),
+    (0x10, "ACK"),
+];
+
+/// What `Timeline::from_pcap` draws of the packets of a capture
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PacketEvents {
+    /// An instant for every packet
+    #[default]
+    Packets,
+    /// An event for every request or response, from the first to the last of the packets
+    /// carrying it
+    Messages,
+}
+
+// Packet captures of tcpdump and Wireshark, see `Timeline::from_pcap`
+pub(crate) struct PcapImporter;
+
+impl TraceImporter for PcapImporter {
+    fn format(&self) -> &str {
+        "pcap"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        [PCAP_MICROS, PCAP_NANOS, PCAPNG]
+            .iter()
+            .any(|magic| data.starts_with(magic) || data.starts_with(&reversed(*magic)))
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_pcap(data, PacketEvents::Packets)
+    }
+}
+
+fn reversed(mut bytes: [u8; 4]) -> [u8; 4] {
+    bytes.reverse();
+    bytes
+}
+
+fn truncated() -> TimelineError {
+    TimelineError::Parse("packet capture is truncated".to_string())
+}
+
+// Reads the fields of a capture in its byte order
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl<'a> Cursor<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], TimelineError> {
+        let end = self.pos.checked_add(len).ok_or_else(truncated)?;
+        let bytes = self.data.get(self.pos..end).ok_or_else(truncated)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, TimelineError> {
+        let bytes = self.bytes(2)?.try_into().unwrap_or_default();
+        if self.big_endian {
+            Ok(u16::from_be_bytes(bytes))
+        } else {
+            Ok(u16::from_le_bytes(bytes))
+        }
+    }
+
+    fn u32(&mut self) -> Result<u32, TimelineError> {
+        let bytes = self.bytes(4)?.try_into().unwrap_or_default();
+        if self.big_endian {
+            Ok(u32::from_be_bytes(bytes))
+        } else {
+            Ok(u32::from_le_bytes(bytes))
+        }
+    }
+}
+
+// A frame captured off a link, with the time it was captured at in nanoseconds
+struct Frame<'a> {
+    time: i64,
+    linktype: u32,
+    data: &'a [u8],
+}
+
+fn read_pcap(data: &[u8]) -> Result<Vec<Frame<'_>>, TimelineError> {
+    let magic: [u8; 4] = data.get(..4).ok_or_else(truncated)?.try_into().unwrap();
+    let mut cursor = Cursor {
+        data,
+        pos: 0,
+        big_endian: magic == reversed(PCAP_MICROS) || magic == reversed(PCAP_NANOS),
+    };
+    let nanos = magic == PCAP_NANOS || magic == reversed(PCAP_NANOS);
+    cursor.bytes(20)?;
+    // The high bits of the link type tell about frame check sequences
+    let linktype = cursor.u32()? & 0x0fff_ffff;
+    let mut frames = Vec::new();
+    while cursor.pos < data.len() {
+        let seconds = cursor.u32()? as i64;
+        let fraction = cursor.u32()? as i64;
+        let captured = cursor.u32()? as usize;
+        cursor.u32()?;
+        let fraction = if nanos { fraction } else { fraction * 1000 };
+        frames.push(Frame {
+            time: seconds * 1_000_000_000 + fraction,
+            linktype,
+            data: cursor.bytes(captured)?,
+        });
+    }
+    Ok(frames)
+}
+
+// A timestamp of a pcapng interface in nanoseconds, by the resolution of the interface: a
+// power of ten, or of two with the high bit set
+fn pcapng_nanos(timestamp: u64, resolution: u8) -> i64 {
+    if resolution & 0x80 != 0 {
+        return ((timestamp as u128 * 1_000_000_000) >> (resolution & 0x7f)) as i64;
+    }
+    match resolution as u32 {
+        exponent @ 0..=9 => (timestamp * 10u64.pow(9 - exponent)) as i64,
+        exponent => (timestamp / 10u64.saturating_pow(exponent - 9)) as i64,
+    }
+}
+
+fn read_pcapng(data: &[u8]) -> Result<Vec<Frame<'_>>, TimelineError> {
+    let mut cursor = Cursor {
+        data,
+        pos: 0,
+        big_endian: false,
+    };
+    // The link type and the time resolution of every interface of the section
+    let mut interfaces: Vec<(u32, u8)> = Vec::new();
+    let mut frames = Vec::new();
+    while cursor.pos < data.len() {
+        let start = cursor.pos;
+        if data[start..].starts_with(&PCAPNG) {
+            // Every section has its byte order, and its interfaces
+            let order = data.get(start + 8..start + 12).ok_or_else(truncated)?;
+            cursor.big_endian = order == [0x1a, 0x2b, 0x3c, 0x4d];
+            interfaces.clear();
+        }
+        let kind = cursor.u32()?;
+        let len = cursor.u32()? as usize;
+        if len < 12 || !len.is_multiple_of(4) {
+            return Err(TimelineError::Parse(format!(
+                "pcapng block of {} bytes at {}",
+                len, start
+            )));
+        }
+        let mut block = Cursor {
+            data: cursor.bytes(len - 8)?,
+            pos: 0,
+            big_endian: cursor.big_endian,
+        };
+        match kind {
+            BLOCK_INTERFACE => {
+                let linktype = block.u16()? as u32;
+                block.bytes(6)?;
+                let mut resolution = 6;
+                while block.pos + 4 <= block.data.len() - 4 {
+                    let (code, len) = (block.u16()?, block.u16()? as usize);
+                    let value = block.bytes(len.next_multiple_of(4))?;
+                    if code == OPTION_TSRESOL && len == 1 {
+                        resolution = value[0];
+                    }
+                    if code == 0 {
+                        break;
+                    }
+                }
+                interfaces.push((linktype, resolution));
+            }
+            BLOCK_ENHANCED_PACKET => {
+                let interface = block.u32()? as usize;
+                let timestamp = (block.u32()? as u64) << 32 | block.u32()? as u64;
+                let captured = block.u32()? as usize;
+                block.u32()?;
+                let &(linktype, resolution) = interfaces.get(interface).ok_or_else(|| {
+                    TimelineError::Parse(format!("packet of unknown interface {}", interface))
+                })?;
+                frames.push(Frame {
+                    time: pcapng_nanos(timestamp, resolution),
+                    linktype,
+                    data: block.bytes(captured)?,
+                });
+            }
+            // Simple packet blocks have no times to draw them at, and other blocks no packets
+            _ => {}
+        }
+    }
+    Ok(frames)
+}
+
+// The IP packet in a frame, if it has one
+fn ip_packet(linktype: u32, frame: &[u8]) -> Option<&[u8]> {
+    let is_ip = |ethertype: &[u8]| matches!(ethertype, [0x08, 0x00] | [0x86, 0xdd]);
+    match linktype {
+        LINKTYPE_ETHERNET => {
+            let mut offset = 12;
+            // Past the tags of VLANs
+            while matches!(frame.get(offset..offset + 2)?, [0x81, 0x00] | [0x88, 0xa8]) {
+                offset += 4;
+            }
+            if !is_ip(frame.get(offset..offset + 2)?) {
+                return None;
+            }
+            frame.get(offset + 2..)
+        }
+        LINKTYPE_NULL | LINKTYPE_LOOP => frame.get(4..),
+        LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => Some(frame),
+        LINKTYPE_LINUX_SLL if is_ip(frame.get(14..16)?) => frame.get(16..),
+        LINKTYPE_LINUX_SLL2 if is_ip(frame.get(..2)?) => frame.get(20..),
+        _ => None,
+    }
+}
+
+// A packet between two endpoints, like "10.0.0.1:80", or addresses for ICMP
+struct Packet {
+    time: i64,
+    from: String,
+    to: String,
+    // Like "UDP", or the flags of TCP packets, like "SYN,ACK"
+    name: String,
+    // The bytes of the IP packet, and of what it carries past the headers
+    length: usize,
+    payload: usize,
+}
+
+fn parse_packet(time: i64, ip: &[u8]) -> Option<Packet> {
+    let (protocol, from, to, ip) = match ip.first()? >> 4 {
+        4 => {
+            let total = u16::from_be_bytes([*ip.get(2)?, *ip.get(3)?]) as usize;
+            // Captures of segmentation offload have no total length
+            let end = if total == 0 {
+                ip.len()
+            } else {
+                total.min(ip.len())
+            };
+            // Only the first fragment carries the header of TCP or UDP
+            if u16::from_be_bytes([*ip.get(6)?, *ip.get(7)?]) & 0x1fff != 0 {
+                return None;
+            }
+            let from: [u8; 4] = ip.get(12..16)?.try_into().ok()?;
+            let to: [u8; 4] = ip.get(16..20)?.try_into().ok()?;
+            (ip[9], IpAddr::from(from), IpAddr::from(to), ip.get(..end)?)
+        }
+        6 => {
+            let payload = u16::from_be_bytes([*ip.get(4)?, *ip.get(5)?]) as usize;
+            let from: [u8; 16] = ip.get(8..24)?.try_into().ok()?;
+            let to: [u8; 16] = ip.get(24..40)?.try_into().ok()?;
+            let end = (40 + payload).min(ip.len());
+            (ip[6], IpAddr::from(from), IpAddr::from(to), ip.get(..end)?)
+        }
+        _ => return None,
+    };
+    // Past the header of IP, IPv6 extension headers aside
+    let header = if ip[0] >> 4 == 4 {
+        (ip[0] & 0x0f) as usize * 4
+    } else {
+        40
+    };
+    let body = ip.get(header..)?;
+    let port = |offset: usize| {
+        Some(u16::from_be_bytes([
+            *body.get(offset)?,
+            *body.get(offset + 1)?,
+        ]))
+    };
+    let (from, to, name, payload) = match protocol {
+        6 => {
+            let header = (*body.get(12)? >> 4) as usize * 4;
+            let flags = *body.get(13)?;
+            let names: Vec<&str> = TCP_FLAGS
+                .iter()
+                .filter(|(flag, _)| flags & flag != 0)
+                .map(|(_, name)| *name)
+                .collect();
+            let name = if names.is_empty() {
+                "TCP".to_string()
+            } else {
+                names.join(",")
+            };
+            let from = SocketAddr::new(from, port(0)?).to_string();
+            let to = SocketAddr::new(to, port(2)?).to_string();
+            (from, to, name, body.len().saturating_sub(header))
+        }
+        17 => {
+            let from = SocketAddr::new(from, port(0)?).to_string();
+            let to = SocketAddr::new(to, port(2)?).to_string();
+            (from, to, "UDP".to_string(), body.len().saturating_sub(8))
+        }
+        1 | 58 => {
+            let name = if protocol == 1 { "ICMP" } else { "ICMPv6" };
+            (
+                from.to_string(),
+                to.to_string(),
+                name.to_string(),
+                body.len(),
+            )
+        }
+        _ => return None,
+    };
+    Some(Packet {
+        time,
+        from,
+        to,
+        name,
+        length: ip.len(),
+        payload,
+    })
+}
+
+// The packets with a payload that went one way in a row, a request or a response
+struct Message {
+    row: String,
+    request: bool,
+    start: i64,
+    end: i64,
+    packets: usize,
+    bytes: usize,
+}
+
+// Two endpoints talking, and the message they are in the middle of
+struct Conversation {
+    client: String,
+    message: Option<Message>,
+}
+
+impl Timeline {
+    /// Read a packet capture into a timeline
+    ///
+    /// This reads the pcap and pcapng files of tcpdump, Wireshark and dumpcap, of Ethernet,
+    /// loopback, Linux cooked and raw IP captures, and draws the TCP, UDP and ICMP packets in
+    /// them on a row for every direction of every flow, like "10.0.0.1:40000 > 10.0.0.2:80",
+    /// with the rows of the two directions of a flow next to each other. As `events` picks,
+    /// every packet becomes an instant, named after its TCP flags, like "SYN,ACK", or its
+    /// protocol, with its "length" and "payload" in bytes as its metadata, or every message
+    /// does, the packets with a payload that go one way before any go back.
+    ///
+    /// The endpoint that sent the first packet of a flow is its client, and its messages are
+    /// requests, named "request", and the messages back are responses, named "response", with
+    /// their number of "packets" and "bytes" of payload as their metadata. A request gets a
+    /// trigger from its last packet to the first packet of its response. Times are in
+    /// nanoseconds since the epoch.
+    ///
+    /// This is only available with the `pcap` feature. This returns `TimelineError::Parse` if
+    /// the capture isn't a pcap or pcapng file, is truncated or has no packets of these
+    /// protocols, and `TimelineError::Io` if reading it fails. `Timeline::import` detects
+    /// captures of this format, as "pcap", and draws their packets.
+    pub fn from_pcap(
+        mut reader: impl Read,
+        events: PacketEvents,
+    ) -> Result<Timeline, TimelineError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let magic = data.get(..4).ok_or_else(truncated)?;
+        let frames = if magic == PCAPNG {
+            read_pcapng(&data)?
+        } else if [PCAP_MICROS, PCAP_NANOS]
+            .iter()
+            .any(|pcap| magic == pcap || magic == reversed(*pcap))
+        {
+            read_pcap(&data)?
+        } else {
+            return Err(TimelineError::Parse(
+                "not a pcap or pcapng file".to_string(),
+            ));
+        };
+        let mut packets: Vec<Packet> = frames
+            .iter()
+            .filter_map(|frame| parse_packet(frame.time, ip_packet(frame.linktype, frame.data)?))
+            .collect();
+        if packets.is_empty() {
+            return Err(TimelineError::Parse(
+                "no TCP, UDP or ICMP packets".to_string(),
+            ));
+        }
+        packets.sort_by_key(|packet| packet.time);
+
+        let mut timeline = new_timeline();
+        let mut order = Vec::new();
+        let mut conversations: HashMap<(String, String), Conversation> = HashMap::new();
+        let mut messages = Vec::new();
+        let mut exchanges = Vec::new();
+        for packet in packets {
+            let row = format!("{} > {}", packet.from, packet.to);
+            let key = if packet.from < packet.to {
+                (packet.from.clone(), packet.to.clone())
+            } else {
+                (packet.to.clone(), packet.from.clone())
+            };
+            let conversation = conversations.entry(key).or_insert_with(|| {
+                order.push(row.clone());
+                order.push(format!("{} > {}", packet.to, packet.from));
+                Conversation {
+                    client: packet.from.clone(),
+                    message: None,
+                }
+            });
+            if events == PacketEvents::Packets {
+                let id = timeline.add_instant(packet.name, packet.time, row.clone());
+                timeline.set_event_metadata(id, "length".to_string(), packet.length.to_string());
+                timeline.set_event_metadata(id, "payload".to_string(), packet.payload.to_string());
+            }
+            if packet.payload == 0 {
+                continue;
+            }
+            match &mut conversation.message {
+                Some(message) if message.row == row => {
+                    message.end = packet.time;
+                    message.packets += 1;
+                    message.bytes += packet.payload;
+                }
+                current => {
+                    let request = packet.from == conversation.client;
+                    if let Some(previous) = current.take() {
+                        if previous.request && !request {
+                            exchanges.push((
+                                previous.row.clone(),
+                                previous.end,
+                                row.clone(),
+                                packet.time,
+                            ));
+                        }
+                        messages.push(previous);
+                    }
+                    *current = Some(Message {
+                        row,
+                        request,
+                        start: packet.time,
+                        end: packet.time,
+                        packets: 1,
+                        bytes: packet.payload,
+                    });
+                }
+            }
+        }
+
+        if events == PacketEvents::Messages {
+            messages.extend(
+                conversations
+                    .into_values()
+                    .filter_map(|conversation| conversation.message),
+            );
+            messages.sort_by(|a, b| (a.start, &a.row).cmp(&(b.start, &b.row)));
+            for message in messages {
+                let name = if message.request {
+                    "request"
+                } else {
+                    "response"
+                };
+                let event =
+                    EventBuilder::new(name.to_string(), message.start, message.end, message.row)
+                        .metadata("packets".to_string(), message.packets.to_string())
+                        .metadata("bytes".to_string(), message.bytes.to_string());
+                timeline.insert_event(event.build());
+            }
+        }
+        for (request, end, response, start) in exchanges {
+            timeline.add_trigger_span(request, end, response, start);
+        }
+        timeline.set_category_order(order);
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT: [u8; 4] = [10, 0, 0, 1];
+    const SERVER: [u8; 4] = [10, 0, 0, 2];
+    const REQUEST: &str = "10.0.0.1:40000 > 10.0.0.2:80";
+    const RESPONSE: &str = "10.0.0.2:80 > 10.0.0.1:40000";
+
+    // An IPv4 packet of a protocol carrying `body`
+    fn ipv4(protocol: u8, from: [u8; 4], to: [u8; 4], body: &[u8]) -> Vec<u8> {
+        let total = (20 + body.len()) as u16;
+        let mut ip = vec![0x45, 0];
+        ip.extend(total.to_be_bytes());
+        ip.extend([0, 0, 0x40, 0, 64, protocol, 0, 0]);
+        ip.extend(from);
+        ip.extend(to);
+        ip.extend(body);
+        ip
+    }
+
+    // An IPv6 packet of a protocol carrying `body`
+    fn ipv6(protocol: u8, from: [u8; 16], to: [u8; 16], body: &[u8]) -> Vec<u8> {
+        let mut ip = vec![0x60, 0, 0, 0];
+        ip.extend((body.len() as u16).to_be_bytes());
+        ip.extend([protocol, 64]);
+        ip.extend(from);
+        ip.extend(to);
+        ip.extend(body);
+        ip
+    }
+
+    fn tcp(ports: (u16, u16), flags: u8, payload: &[u8]) -> Vec<u8> {
+        let mut tcp = ports.0.to_be_bytes().to_vec();
+        tcp.extend(ports.1.to_be_bytes());
+        tcp.extend([0; 8]);
+        tcp.extend([0x50, flags, 0, 0, 0, 0, 0, 0]);
+        tcp.extend(payload);
+        tcp
+    }
+
+    fn udp(ports: (u16, u16), payload: &[u8]) -> Vec<u8> {
+        let mut udp = ports.0.to_be_bytes().to_vec();
+        udp.extend(ports.1.to_be_bytes());
+        udp.extend((8 + payload.len() as u16).to_be_bytes());
+        udp.extend([0, 0]);
+        udp.extend(payload);
+        udp
+    }
+
+    // An Ethernet frame of an IP packet
+    fn ethernet(ip: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0; 12];
+        let ethertype = if ip[0] >> 4 == 6 { 0x86dd } else { 0x0800 };
+        frame.extend(u16::to_be_bytes(ethertype));
+        frame.extend(ip);
+        // Ethernet pads short frames
+        frame.resize(frame.len().max(60), 0);
+        frame
+    }
+
+    // An Ethernet frame of an IPv4 packet of TCP with `flags` and `payload`
+    fn tcp_frame(
+        from: [u8; 4],
+        to: [u8; 4],
+        ports: (u16, u16),
+        flags: u8,
+        payload: &[u8],
+    ) -> Vec<u8> {
+        ethernet(&ipv4(6, from, to, &tcp(ports, flags, payload)))
+    }
+
+    // A connection, a request in two packets, its response and the close, at nanoseconds past
+    // a second
+    fn exchange() -> Vec<(u32, Vec<u8>)> {
+        vec![
+            (1_000, tcp_frame(CLIENT, SERVER, (40000, 80), 0x02, b"")),
+            (1_100, tcp_frame(SERVER, CLIENT, (80, 40000), 0x12, b"")),
+            (
+                1_200,
+                tcp_frame(CLIENT, SERVER, (40000, 80), 0x18, b"GET / HTTP/1.1\r\n"),
+            ),
+            (1_300, tcp_frame(CLIENT, SERVER, (40000, 80), 0x18, b"\r\n")),
+            (
+                1_500,
+                tcp_frame(SERVER, CLIENT, (80, 40000), 0x18, b"HTTP/1.1 200 OK\r\n"),
+            ),
+            (1_600, tcp_frame(CLIENT, SERVER, (40000, 80), 0x11, b"")),
+        ]
+    }
+
+    // A pcap file of frames captured at 5 seconds and a fraction, in the byte order the magic
+    // number is in
+    fn pcap(magic: [u8; 4], linktype: u32, frames: &[(u32, Vec<u8>)]) -> Vec<u8> {
+        let big_endian = magic[0] == 0xa1;
+        let word = |value: u32| {
+            if big_endian {
+                value.to_be_bytes()
+            } else {
+                value.to_le_bytes()
+            }
+        };
+        let mut pcap = magic.to_vec();
+        pcap.extend(if big_endian {
+            [0, 2, 0, 4]
+        } else {
+            [2, 0, 4, 0]
+        });
+        pcap.extend([0; 8]);
+        pcap.extend(word(65535));
+        pcap.extend(word(linktype));
+        for (fraction, frame) in frames {
+            pcap.extend(word(5));
+            pcap.extend(word(*fraction));
+            pcap.extend(word(frame.len() as u32));
+            pcap.extend(word(frame.len() as u32));
+            pcap.extend(frame);
+        }
+        pcap
+    }
+
+    // A pcapng file of one Ethernet interface, with a resolution option if there is one
+    fn pcapng(resolution: Option<u8>, frames: &[(u64, Vec<u8>)]) -> Vec<u8> {
+        let mut pcapng = PCAPNG.to_vec();
+        pcapng.extend(28u32.to_le_bytes());
+        pcapng.extend([0x4d, 0x3c, 0x2b, 0x1a, 1, 0, 0, 0]);
+        pcapng.extend(u64::MAX.to_le_bytes());
+        pcapng.extend(28u32.to_le_bytes());
+        let mut options = Vec::new();
+        if let Some(resolution) = resolution {
+            options.extend(OPTION_TSRESOL.to_le_bytes());
+            options.extend(1u16.to_le_bytes());
+            options.extend([resolution, 0, 0, 0]);
+            options.extend([0; 4]);
+        }
+        let len = 20 + options.len() as u32;
+        pcapng.extend(BLOCK_INTERFACE.to_le_bytes());
+        pcapng.extend(len.to_le_bytes());
+        pcapng.extend([1, 0, 0, 0, 0, 0, 0, 0]);
+        pcapng.extend(options);
+        pcapng.extend(len.to_le_bytes());
+        for (timestamp, frame) in frames {
+            let len = 32 + frame.len().next_multiple_of(4) as u32;
+            pcapng.extend(BLOCK_ENHANCED_PACKET.to_le_bytes());
+            pcapng.extend(len.to_le_bytes());
+            pcapng.extend(0u32.to_le_bytes());
+            pcapng.extend(((timestamp >> 32) as u32).to_le_bytes());
+            pcapng.extend((*timestamp as u32).to_le_bytes());
+            pcapng.extend((frame.len() as u32).to_le_bytes());
+            pcapng.extend((frame.len() as u32).to_le_bytes());
+            pcapng.extend(frame);
+            pcapng.resize(pcapng.len().next_multiple_of(4), 0);
+            pcapng.extend(len.to_le_bytes());
+        }
+        pcapng
+    }
+
+    fn events(timeline: &Timeline) -> Vec<(&str, &str, i64, i64)> {
+        timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name(),
+                    event.location(),
+                    event.start_time() % 1_000_000_000,
+                    event.end_time() % 1_000_000_000,
+                )
+            })
+            .collect()
+    }
+
+    fn error(data: &[u8]) -> String {
+        match Timeline::from_pcap(data, PacketEvents::Packets) {
+            Err(TimelineError::Parse(what)) => what,
+            other => panic!("{:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_ip_packet() {
+        let ip = ipv4(17, CLIENT, SERVER, &udp((5000, 53), b"q"));
+        assert!(ip_packet(LINKTYPE_ETHERNET, &ethernet(&ip))
+            .unwrap()
+            .starts_with(&ip));
+        // Past VLAN tags
+        let mut tagged = vec![0; 12];
+        tagged.extend([0x81, 0x00, 0, 1, 0x88, 0xa8, 0, 2, 0x08, 0x00]);
+        tagged.extend(&ip);
+        assert_eq!(ip_packet(LINKTYPE_ETHERNET, &tagged), Some(&ip[..]));
+        let mut arp = vec![0; 12];
+        arp.extend([0x08, 0x06]);
+        arp.extend(&ip);
+        assert_eq!(ip_packet(LINKTYPE_ETHERNET, &arp), None);
+        assert_eq!(ip_packet(LINKTYPE_ETHERNET, &[0; 10]), None);
+
+        let mut null = vec![2, 0, 0, 0];
+        null.extend(&ip);
+        assert_eq!(ip_packet(LINKTYPE_NULL, &null), Some(&ip[..]));
+        assert_eq!(ip_packet(LINKTYPE_LOOP, &null), Some(&ip[..]));
+        for linktype in [LINKTYPE_RAW, LINKTYPE_IPV4, LINKTYPE_IPV6] {
+            assert_eq!(ip_packet(linktype, &ip), Some(&ip[..]));
+        }
+        let mut sll = vec![0; 14];
+        sll.extend([0x08, 0x00]);
+        sll.extend(&ip);
+        assert_eq!(ip_packet(LINKTYPE_LINUX_SLL, &sll), Some(&ip[..]));
+        sll[15] = 0x06;
+        assert_eq!(ip_packet(LINKTYPE_LINUX_SLL, &sll), None);
+        let mut sll2 = vec![0x86, 0xdd];
+        sll2.extend([0; 18]);
+        sll2.extend(&ip);
+        assert_eq!(ip_packet(LINKTYPE_LINUX_SLL2, &sll2), Some(&ip[..]));
+        assert_eq!(ip_packet(147, &ip), None);
+    }
+
+    #[test]
+    fn test_parse_packet() {
+        let packet = |ip: &[u8]| {
+            parse_packet(0, ip).map(|packet| {
+                (
+                    packet.from,
+                    packet.to,
+                    packet.name,
+                    packet.length,
+                    packet.payload,
+                )
+            })
+        };
+        let owned = |from: &str, to: &str, name: &str, length: usize, payload: usize| {
+            Some((
+                from.to_string(),
+                to.to_string(),
+                name.to_string(),
+                length,
+                payload,
+            ))
+        };
+        // The padding of the frame isn't part of the packet
+        let mut ip = ipv4(17, CLIENT, SERVER, &udp((5000, 53), b"abcd"));
+        ip.extend([0; 6]);
+        assert_eq!(
+            packet(&ip),
+            owned("10.0.0.1:5000", "10.0.0.2:53", "UDP", 32, 4)
+        );
+        // Unless the packet doesn't say how long it is
+        ip[2..4].copy_from_slice(&[0, 0]);
+        assert_eq!(
+            packet(&ip),
+            owned("10.0.0.1:5000", "10.0.0.2:53", "UDP", 38, 10)
+        );
+        assert_eq!(
+            packet(&ipv4(6, CLIENT, SERVER, &tcp((1, 2), 0, b"xy"))),
+            owned("10.0.0.1:1", "10.0.0.2:2", "TCP", 42, 2)
+        );
+        assert_eq!(
+            packet(&ipv4(6, CLIENT, SERVER, &tcp((1, 2), 0x3f, b""))),
+            owned("10.0.0.1:1", "10.0.0.2:2", "SYN,FIN,RST,PSH,URG,ACK", 40, 0)
+        );
+        assert_eq!(
+            packet(&ipv4(1, CLIENT, SERVER, &[8, 0, 0, 0, 0, 0, 0, 1])),
+            owned("10.0.0.1", "10.0.0.2", "ICMP", 28, 8)
+        );
+        let localhost = std::net::Ipv6Addr::LOCALHOST.octets();
+        assert_eq!(
+            packet(&ipv6(17, localhost, localhost, &udp((53, 5353), b"ab"))),
+            owned("[::1]:53", "[::1]:5353", "UDP", 50, 2)
+        );
+        assert_eq!(
+            packet(&ipv6(58, localhost, localhost, &[128, 0, 0, 0])),
+            owned("::1", "::1", "ICMPv6", 44, 4)
+        );
+
+        // Fragments past the first, other protocols and versions, and cut off headers
+        let mut fragment = ipv4(17, CLIENT, SERVER, &udp((5000, 53), b"abcd"));
+        fragment[7] = 1;
+        assert_eq!(packet(&fragment), None);
+        assert_eq!(packet(&ipv4(47, CLIENT, SERVER, &[0; 8])), None);
+        let mut version = ipv4(17, CLIENT, SERVER, &udp((5000, 53), b""));
+        version[0] = 0x55;
+        assert_eq!(packet(&version), None);
+        assert_eq!(packet(&ipv4(6, CLIENT, SERVER, &[0; 10])), None);
+        assert_eq!(packet(&ipv4(17, CLIENT, SERVER, &[0; 2])), None);
+        assert_eq!(packet(&[0x45, 0, 0]), None);
+        assert_eq!(packet(&[]), None);
+    }
+
+    #[test]
+    fn test_from_pcap() {
+        let capture = pcap(PCAP_NANOS, LINKTYPE_ETHERNET, &exchange());
+        assert!(PcapImporter.sniff(&capture));
+        let timeline = Timeline::from_pcap(capture.as_slice(), PacketEvents::Packets).unwrap();
+        assert_eq!(timeline.categories(), [REQUEST, RESPONSE]);
+        assert_eq!(
+            events(&timeline),
+            [
+                ("SYN", REQUEST, 1_000, 1_000),
+                ("SYN,ACK", RESPONSE, 1_100, 1_100),
+                ("PSH,ACK", REQUEST, 1_200, 1_200),
+                ("PSH,ACK", REQUEST, 1_300, 1_300),
+                ("PSH,ACK", RESPONSE, 1_500, 1_500),
+                ("FIN,ACK", REQUEST, 1_600, 1_600),
+            ]
+        );
+        let (_, get) = timeline.events().nth(2).unwrap();
+        assert_eq!(get.metadata()["length"], "56");
+        assert_eq!(get.metadata()["payload"], "16");
+        // A trigger from the end of the request to the start of the response
+        let triggers: Vec<_> = timeline
+            .triggers()
+            .map(|trigger| {
+                (
+                    trigger.start_location(),
+                    trigger.time() - 5_000_000_000,
+                    trigger.end_time() - 5_000_000_000,
+                )
+            })
+            .collect();
+        assert_eq!(triggers, [(REQUEST, 1_300, 1_500)]);
+
+        // Captures of big endian machines, with times in microseconds, link types with the
+        // bits about frame check sequences, and packets out of order
+        let mut frames = exchange();
+        frames.swap(0, 1);
+        let capture = pcap(
+            reversed(PCAP_MICROS),
+            0x1000_0000 | LINKTYPE_ETHERNET,
+            &frames,
+        );
+        assert!(PcapImporter.sniff(&capture));
+        let timeline = Timeline::from_pcap(capture.as_slice(), PacketEvents::Packets).unwrap();
+        let starts: Vec<_> = events(&timeline)
+            .into_iter()
+            .map(|(name, _, start, _)| (name, start))
+            .collect();
+        assert_eq!(starts[..2], [("SYN", 1_000_000), ("SYN,ACK", 1_100_000)]);
+        // The client is the end that sent the first packet
+        assert_eq!(timeline.categories(), [REQUEST, RESPONSE]);
+    }
+
+    #[test]
+    fn test_pcapng() {
+        assert_eq!(pcapng_nanos(1_500, 6), 1_500_000);
+        assert_eq!(pcapng_nanos(1_500, 9), 1_500);
+        assert_eq!(pcapng_nanos(3, 0), 3_000_000_000);
+        assert_eq!(pcapng_nanos(1_500_000, 12), 1_500);
+        // Powers of two
+        assert_eq!(pcapng_nanos(1024, 0x80 | 10), 1_000_000_000);
+        assert_eq!(pcapng_nanos(1, 0x80 | 1), 500_000_000);
+
+        let mut frames: Vec<_> = exchange()
+            .into_iter()
+            .map(|(micros, frame)| (micros as u64, frame))
+            .collect();
+        frames.push((
+            1_700,
+            tcp_frame(CLIENT, SERVER, (40000, 80), 0x18, b"GET /b\r\n"),
+        ));
+        let capture = pcapng(None, &frames);
+        assert!(PcapImporter.sniff(&capture));
+        let timeline = Timeline::from_pcap(capture.as_slice(), PacketEvents::Messages).unwrap();
+        // The default resolution of pcapng is microseconds, and requests without a response
+        // have no trigger
+        assert_eq!(
+            events(&timeline),
+            [
+                ("request", REQUEST, 1_200_000, 1_300_000),
+                ("response", RESPONSE, 1_500_000, 1_500_000),
+                ("request", REQUEST, 1_700_000, 1_700_000),
+            ]
+        );
+        let (_, get) = timeline.events().next().unwrap();
+        assert_eq!(get.metadata()["packets"], "2");
+        assert_eq!(get.metadata()["bytes"], "18");
+        assert_eq!(timeline.triggers().count(), 1);
+
+        // Interfaces can have a resolution of their own
+        let frames = [(
+            7_000_000_123,
+            ethernet(&ipv4(17, CLIENT, SERVER, &udp((5000, 53), b"q"))),
+        )];
+        let capture = pcapng(Some(9), &frames);
+        let timeline = Timeline::from_pcap(capture.as_slice(), PacketEvents::Packets).unwrap();
+        assert_eq!(timeline.time_range(), Some((7_000_000_123, 7_000_000_123)));
+        assert_eq!(timeline.categories(), ["10.0.0.1:5000 > 10.0.0.2:53"]);
+    }
+
+    #[test]
+    fn test_from_pcap_errors() {
+        assert_eq!(error(&PCAP_MICROS), "packet capture is truncated");
+        assert!(!PcapImporter.sniff(b"GIF89a"));
+        assert_eq!(error(b"GIF89a"), "not a pcap or pcapng file");
+        assert_eq!(error(b"ab"), "packet capture is truncated");
+        // Packets cut off by the end of the capture
+        let mut capture = pcap(PCAP_MICROS, LINKTYPE_ETHERNET, &exchange());
+        capture.pop();
+        assert_eq!(error(&capture), "packet capture is truncated");
+        // Frames of other link types, or without IP
+        let capture = pcap(PCAP_MICROS, 147, &exchange());
+        assert_eq!(error(&capture), "no TCP, UDP or ICMP packets");
+
+        let mut capture = pcapng(None, &[(1, tcp_frame(CLIENT, SERVER, (1, 2), 0, b""))]);
+        capture[32..36].copy_from_slice(&10u32.to_le_bytes());
+        assert_eq!(error(&capture), "pcapng block of 10 bytes at 28");
+        let mut capture = pcapng(None, &[(1, tcp_frame(CLIENT, SERVER, (1, 2), 0, b""))]);
+        capture[56..60].copy_from_slice(&1u32.to_le_bytes());
+        assert_eq!(error(&capture), "packet of unknown interface 1");
+    }
+}
@@ -0,0 +1,477 @@
+use std::io::{BufRead, BufReader, Read};
+
+use crate::ftrace::{arg, parse_time, task_row, SchedTimeline};
+use crate::import::sniff_text;
+use crate::{Timeline, TimelineError, TraceImporter};
+
+// The text output of `perf script` and `perf sched timehist`, see `Timeline::from_perf_script`
+pub(crate) struct PerfScriptImporter;
+
+impl TraceImporter for PerfScriptImporter {
+    fn format(&self) -> &str {
+        "perf-script"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        sniff_text(data)
+            .lines()
+            .filter(|line| !line.starts_with('#') && !line.trim().is_empty())
+            .take(4)
+            .any(|line| parse_script_line(line).is_some() || parse_timehist_line(line).is_some())
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_perf_script(data)
+    }
+}
+
+// A line of `perf script`, like
+// "bash  1234 [001] 12345.678901: sched:sched_wakeup: comm=cat pid=56 prio=120 target_cpu=001"
+// or, for samples, "bash  1234 [001] 12345.678901:  250000 cpu-clock:pppH:  ffffffff81 func+0x10"
+#[derive(Debug, PartialEq)]
+struct ScriptLine<'a> {
+    comm: &'a str,
+    tid: &'a str,
+    cpu: u32,
+    time: i64,
+    event: &'a str,
+    args: &'a str,
+}
+
+fn parse_script_line(line: &str) -> Option<ScriptLine<'_>> {
+    let open = line.find(" [")?;
+    let close = open + line[open..].find(']')?;
+    let cpu = line[open + 2..close].trim().parse().ok()?;
+    // The task is its comm and its tid, or its pid and tid as "pid/tid"
+    let (comm, ids) = line[..open].trim().rsplit_once(char::is_whitespace)?;
+    let tid = ids.rsplit('/').next()?;
+    tid.parse::<u32>().ok()?;
+
+    let rest = &line[close + 1..];
+    let colon = rest.find(": ")?;
+    let time = parse_time(rest[..colon].trim())?;
+    let mut rest = rest[colon + 2..].trim_start();
+    // Samples have their period before the event
+    if let Some((period, after)) = rest.split_once(' ') {
+        if period.bytes().all(|b| b.is_ascii_digit()) {
+            rest = after.trim_start();
+        }
+    }
+    let (event, args) = match rest.split_once(": ") {
+        Some((event, args)) => (event, args.trim()),
+        None => (rest.strip_suffix(':')?, ""),
+    };
+    if event.contains(' ') {
+        return None;
+    }
+    Some(ScriptLine {
+        comm: comm.trim(),
+        tid,
+        cpu,
+        time,
+        event,
+        args,
+    })
+}
+
+// The function of a sample or a frame of a call chain, like "ffffffff81000000 func+0x10 (dso)"
+fn symbol(frame: &str) -> Option<&str> {
+    let mut fields = frame.split_whitespace();
+    u64::from_str_radix(fields.next()?, 16).ok()?;
+    let symbol = fields.next()?;
+    let symbol = symbol.split("+0x").next()?;
+    (!symbol.starts_with('(') && symbol != "[unknown]").then_some(symbol)
+}
+
+// A line of `perf sched timehist`, like
+// "79371.874569 [0011]  gcc[31949]   0.014   0.000   1.148" for a task that ran until a time,
+// or "79371.874569 [0011]  gcc[31949]   awakened: cc1[31950]" with `-w`
+#[derive(Debug, PartialEq)]
+enum TimehistLine<'a> {
+    Run {
+        comm: &'a str,
+        tid: &'a str,
+        cpu: u32,
+        time: i64,
+        // How long the task ran for, in nanoseconds
+        run: i64,
+    },
+    Wakeup {
+        comm: &'a str,
+        tid: &'a str,
+        cpu: u32,
+        time: i64,
+        woken_comm: &'a str,
+        woken_tid: &'a str,
+    },
+}
+
+// A task of `perf sched timehist`, like "gcc[31949]" or "gcc[31949/31900]", as its comm and tid
+fn timehist_task(task: &str) -> Option<(&str, &str)> {
+    let (comm, ids) = task.strip_suffix(']')?.rsplit_once('[')?;
+    let tid = ids.split('/').next()?;
+    tid.parse::<u32>().ok()?;
+    Some((comm, tid))
+}
+
+fn parse_timehist_line(line: &str) -> Option<TimehistLine<'_>> {
+    let (time, rest) = line.trim_start().split_once(' ')?;
+    let time = parse_time(time)?;
+    let (cpu, rest) = rest.trim_start().split_once(' ')?;
+    let cpu = cpu.strip_prefix('[')?.strip_suffix(']')?.parse().ok()?;
+    let rest = rest.trim_start();
+    // Task names can have spaces, but end with the ids in brackets
+    let end = rest
+        .match_indices("] ")
+        .next()
+        .map_or(rest.len(), |(index, _)| index + 1);
+    let (comm, tid) = timehist_task(&rest[..end])?;
+    let rest = rest[end..].trim();
+    if let Some(woken) = rest.strip_prefix("awakened:") {
+        let (woken_comm, woken_tid) = timehist_task(woken.trim())?;
+        return Some(TimehistLine::Wakeup {
+            comm,
+            tid,
+            cpu,
+            time,
+            woken_comm,
+            woken_tid,
+        });
+    }
+    // The wait time, scheduling delay and run time, in milliseconds
+    let run: f64 = rest.split_whitespace().nth(2)?.parse().ok()?;
+    Some(TimehistLine::Run {
+        comm,
+        tid,
+        cpu,
+        time,
+        run: (run * 1_000_000.0).round() as i64,
+    })
+}
+
+impl Timeline {
+    /// Read the output of `perf script` or `perf sched timehist` into a timeline
+    ///
+    /// This draws the scheduling of a recording of `perf record` or `perf sched record` like
+    /// `from_ftrace` draws the same events: a row for every CPU and for every thread that runs,
+    /// named like "bash-1234" after its thread id, with triggers for wakeups. From `perf
+    /// script`, threads run between `sched:sched_switch` events, `sched:sched_wakeup` and
+    /// `sched:sched_wakeup_new` events become triggers, and other samples become instants on
+    /// the row of their thread named after the function they sampled, taken from the sample or
+    /// the top of its call chain, or after their event. From `perf sched timehist`, every line
+    /// is a run of a thread, and the wakeups of `-w` become triggers. Times are in nanoseconds.
+    ///
+    /// This returns `TimelineError::Parse` if no line looks like either output, and
+    /// `TimelineError::Io` if reading it fails. `Timeline::import` detects both, as
+    /// "perf-script".
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let timehist = "\
+    ///     100.000500 [0001]  gcc[31949]     0.014      0.000      0.500\n\
+    ///     100.000900 [0001]  cc1[31950]     0.010      0.002      0.200\n";
+    /// let timeline = Timeline::from_perf_script(timehist.as_bytes()).unwrap();
+    /// assert_eq!(timeline.categories(), ["CPU 1", "cc1-31950", "gcc-31949"]);
+    /// assert_eq!(timeline.time_range(), Some((100_000_000_000, 100_000_900_000)));
+    /// # }
+    /// ```
+    pub fn from_perf_script(reader: impl Read) -> Result<Timeline, TimelineError> {
+        let mut sched = SchedTimeline::default();
+        let mut parsed = false;
+        // A sample waiting for the top of its call chain to be named after
+        let mut sample: Option<(String, i64, String)> = None;
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            if line.starts_with('#') {
+                continue;
+            }
+            if let Some((row, time, event)) = sample.take() {
+                let name = symbol(&line).unwrap_or(&event);
+                sched.instant(row, time, name);
+                // The rest of the call chain
+                if symbol(&line).is_some() {
+                    continue;
+                }
+            }
+            if let Some(line) = parse_timehist_line(&line) {
+                parsed = true;
+                match line {
+                    TimehistLine::Run {
+                        comm,
+                        tid,
+                        cpu,
+                        time,
+                        run,
+                    } => sched.run(cpu, comm, tid, (time - run, time)),
+                    TimehistLine::Wakeup {
+                        comm,
+                        tid,
+                        cpu,
+                        time,
+                        woken_comm,
+                        woken_tid,
+                    } => sched.wakeup(task_row(comm, tid, cpu), time, woken_comm, woken_tid),
+                }
+                continue;
+            }
+            let Some(line) = parse_script_line(&line) else {
+                continue;
+            };
+            parsed = true;
+            let here = task_row(line.comm, line.tid, line.cpu);
+            match line.event.strip_prefix("sched:") {
+                Some("sched_switch") => {
+                    if let (Some(comm), Some(pid)) =
+                        (arg(line.args, "next_comm"), arg(line.args, "next_pid"))
+                    {
+                        sched.switch(line.cpu, line.time, comm, pid);
+                    }
+                }
+                Some("sched_wakeup" | "sched_wakeup_new") => {
+                    if let (Some(comm), Some(pid)) = (arg(line.args, "comm"), arg(line.args, "pid"))
+                    {
+                        sched.wakeup(here, line.time, comm, pid);
+                    }
+                }
+                Some(_) => sched.event(line.time),
+                None => match symbol(line.args) {
+                    Some(function) => sched.instant(here, line.time, function),
+                    None => {
+                        let event = line.event.split(':').next().unwrap_or(line.event);
+                        sample = Some((here, line.time, event.to_string()));
+                    }
+                },
+            }
+        }
+        if let Some((row, time, event)) = sample {
+            sched.instant(row, time, &event);
+        }
+        if !parsed {
+            return Err(TimelineError::Parse(
+                "no perf script or timehist lines".to_string(),
+            ));
+        }
+        Ok(sched.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = "# ========
+# captured on: today
+         swapper     0 [000]     1.000000: sched:sched_wakeup: comm=bash pid=10 prio=120 target_cpu=000
+         swapper     0 [000]     1.000002: sched:sched_switch: prev_comm=swapper/0 prev_pid=0 prev_prio=120 prev_state=R ==> next_comm=bash next_pid=10 next_prio=120
+            bash    10 [000]     1.000003: sched:sched_wakeup: comm=cat pid=11 prio=120 target_cpu=001
+            bash    10 [000]     1.000005: sched:sched_switch: prev_comm=bash prev_pid=10 prev_prio=120 prev_state=S ==> next_comm=swapper/0 next_pid=0 next_prio=120
+         swapper     0 [001]     1.000006: sched:sched_switch: prev_comm=swapper/1 prev_pid=0 prev_prio=120 prev_state=R ==> next_comm=cat next_pid=11 next_prio=120
+             cat    11 [001]     1.000009: sched:sched_switch: prev_comm=cat prev_pid=11 prev_prio=120 prev_state=S ==> next_comm=swapper/1 next_pid=0 next_prio=120
+";
+
+    const TIMEHIST: &str =
+        "           time    cpu  task name                       wait time  sch delay   run time
+                        [tid/pid]                          (msec)     (msec)     (msec)
+--------------- ------  ------------------------------  ---------  ---------  ---------
+       2.001000 [0000]  my task[20/20]                      0.000      0.000      1.000
+       2.001000 [0000]  my task[20/20]                                         awakened: cat[21/20]
+       2.001100 [0000]  <idle>                              0.000      0.000      0.100
+       2.001500 [0000]  cat[21/20]                          0.000      0.100      0.400
+";
+
+    #[test]
+    fn test_parse_script_line() {
+        // Threads can be named by their pid and tid, and samples have a period
+        assert_eq!(
+            parse_script_line("kworker/0:1-eve 1000/55 [002] 7.5: 250000 cpu-clock:pppH: "),
+            Some(ScriptLine {
+                comm: "kworker/0:1-eve",
+                tid: "55",
+                cpu: 2,
+                time: 7_500_000_000,
+                event: "cpu-clock:pppH",
+                args: "",
+            })
+        );
+        assert_eq!(
+            parse_script_line("  my app  42 [001] 3.000001: sched:sched_wakeup: comm=cat pid=56"),
+            Some(ScriptLine {
+                comm: "my app",
+                tid: "42",
+                cpu: 1,
+                time: 3_000_001_000,
+                event: "sched:sched_wakeup",
+                args: "comm=cat pid=56",
+            })
+        );
+        assert_eq!(
+            parse_script_line("bash abc [001] 3.0: sched:sched_wakeup: "),
+            None
+        );
+        assert_eq!(
+            parse_script_line("bash 42 [x] 3.0: sched:sched_wakeup: "),
+            None
+        );
+        assert_eq!(parse_script_line("bash 42 [001] 3.0: not an event"), None);
+        assert_eq!(
+            parse_script_line("\t    ffffffff81002000 schedule+0x5"),
+            None
+        );
+
+        assert_eq!(
+            symbol("ffffffff81001010 do_exit+0x10 ([kernel.kallsyms])"),
+            Some("do_exit")
+        );
+        assert_eq!(symbol("  7f00 main (/usr/bin/app)"), Some("main"));
+        assert_eq!(symbol("7f00 [unknown] (/usr/bin/app)"), None);
+        assert_eq!(symbol("7f00 ([kernel.kallsyms])"), None);
+        assert_eq!(symbol("do_exit+0x10"), None);
+        assert_eq!(symbol(""), None);
+    }
+
+    #[test]
+    fn test_parse_timehist_line() {
+        assert_eq!(
+            parse_timehist_line("  2.001500 [0003]  my task[21/20]  0.000  0.100  0.400"),
+            Some(TimehistLine::Run {
+                comm: "my task",
+                tid: "21",
+                cpu: 3,
+                time: 2_001_500_000,
+                run: 400_000,
+            })
+        );
+        assert_eq!(
+            parse_timehist_line("  2.001000 [0000]  gcc[20]    awakened: cc1[21]"),
+            Some(TimehistLine::Wakeup {
+                comm: "gcc",
+                tid: "20",
+                cpu: 0,
+                time: 2_001_000_000,
+                woken_comm: "cc1",
+                woken_tid: "21",
+            })
+        );
+        // The idle task has no ids, and the header no times
+        assert_eq!(
+            parse_timehist_line("       2.001100 [0000]  <idle>    0.000      0.000      0.100"),
+            None
+        );
+        assert_eq!(parse_timehist_line(TIMEHIST.lines().next().unwrap()), None);
+        assert_eq!(parse_timehist_line("  2.0 [0000]  gcc[20]  0.000"), None);
+    }
+
+    #[test]
+    fn test_from_perf_script() {
+        assert!(PerfScriptImporter.sniff(SCRIPT.as_bytes()));
+        // ftrace puts the flags of a task between its CPU and time
+        assert!(!PerfScriptImporter.sniff(
+            b"  bash-10  [000] d..2.  1.000005: sched_switch: prev_comm=bash prev_pid=10\n"
+        ));
+        let timeline = Timeline::from_perf_script(SCRIPT.as_bytes()).unwrap();
+        assert_eq!(
+            timeline.categories(),
+            ["CPU 0", "CPU 1", "bash-10", "cat-11"]
+        );
+        let events: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name(),
+                    event.location(),
+                    event.start_time() - 1_000_000_000,
+                    event.end_time() - 1_000_000_000,
+                )
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("bash", "CPU 0", 2000, 5000),
+                ("running", "bash-10", 2000, 5000),
+                ("cat", "CPU 1", 6000, 9000),
+                ("running", "cat-11", 6000, 9000),
+            ]
+        );
+        // The idle task wakes tasks up from the row of its CPU
+        let triggers: Vec<_> = timeline
+            .triggers()
+            .map(|trigger| (trigger.start_location(), trigger.end_location()))
+            .collect();
+        assert_eq!(triggers, [("CPU 0", "bash-10"), ("bash-10", "cat-11")]);
+
+        // Samples are named after their function, the top of their call chain, or their event
+        let script = "\
+            bash    10 [000]     1.000004:     250000 cpu-clock:pppH:  ffffffff81001010 do_exit+0x10 ([kernel.kallsyms])
+            bash    10 [000]     1.000005:     250000 cpu-clock:pppH:
+\t    ffffffff81002000 schedule+0x5 ([kernel.kallsyms])
+\t    ffffffff81003000 do_syscall_64+0x40 ([kernel.kallsyms])
+
+            bash    10 [000]     1.000006:     250000 cpu-clock:pppH:
+\t    7f0000 [unknown] (/usr/bin/bash)
+             cat    11 [001]     1.000009:     250000 cpu-clock:pppH:
+";
+        let timeline = Timeline::from_perf_script(script.as_bytes()).unwrap();
+        let samples: Vec<_> = timeline
+            .events()
+            .filter(|(_, event)| event.instant)
+            .map(|(_, event)| {
+                (
+                    event.name(),
+                    event.location(),
+                    event.start_time() - 1_000_000_000,
+                )
+            })
+            .collect();
+        assert_eq!(
+            samples,
+            [
+                ("do_exit", "bash-10", 4000),
+                ("schedule", "bash-10", 5000),
+                ("cpu-clock", "bash-10", 6000),
+                ("cpu-clock", "cat-11", 9000),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_timehist() {
+        assert!(PerfScriptImporter.sniff(TIMEHIST.as_bytes()));
+        let timeline = Timeline::from_perf_script(TIMEHIST.as_bytes()).unwrap();
+        assert_eq!(timeline.categories(), ["CPU 0", "cat-21", "my task-20"]);
+        // Runs end at the time of their line
+        let runs: Vec<_> = timeline
+            .events()
+            .filter(|(_, event)| event.location() == "CPU 0")
+            .map(|(_, event)| (event.name(), event.start_time(), event.end_time()))
+            .collect();
+        assert_eq!(
+            runs,
+            [
+                ("my task", 2_000_000_000, 2_001_000_000),
+                ("cat", 2_001_100_000, 2_001_500_000),
+            ]
+        );
+        let trigger = timeline.triggers().next().unwrap();
+        assert_eq!(
+            (trigger.start_location(), trigger.end_location()),
+            ("my task-20", "cat-21")
+        );
+        // Output without any lines is an error, and so is output that isn't UTF-8
+        assert!(matches!(
+            Timeline::from_perf_script(&b"{}"[..]),
+            Err(TimelineError::Parse(_))
+        ));
+        assert!(matches!(
+            Timeline::from_perf_script(&b"# captured on: today\n"[..]),
+            Err(TimelineError::Parse(_))
+        ));
+        assert!(matches!(
+            Timeline::from_perf_script(&b"bash 10 [000] 1.0: sched:sched_switch: \xff\n"[..]),
+            Err(TimelineError::Io(_))
+        ));
+    }
+}
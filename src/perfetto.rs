@@ -0,0 +1,334 @@
+// The subset of the Perfetto trace format written by `Timeline::export_perfetto`
+//
+// The messages are declared by hand with the field numbers of perfetto_trace.proto, so there is
+// nothing to generate at build time. Fields the exporter doesn't use are left out, which protobuf
+// readers accept.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::Write;
+
+use prost::Message;
+
+use crate::{TimeValue, Timeline, TimelineError, TimelineExporter};
+
+#[derive(Clone, PartialEq, Message)]
+struct Trace {
+    #[prost(message, repeated, tag = "1")]
+    packet: Vec<TracePacket>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct TracePacket {
+    #[prost(uint64, optional, tag = "8")]
+    timestamp: Option<u64>,
+    #[prost(uint32, optional, tag = "10")]
+    trusted_packet_sequence_id: Option<u32>,
+    #[prost(message, optional, tag = "11")]
+    track_event: Option<TrackEvent>,
+    #[prost(message, optional, tag = "60")]
+    track_descriptor: Option<TrackDescriptor>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct TrackDescriptor {
+    #[prost(uint64, optional, tag = "1")]
+    uuid: Option<u64>,
+    #[prost(string, optional, tag = "2")]
+    name: Option<String>,
+    #[prost(uint64, optional, tag = "5")]
+    parent_uuid: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct TrackEvent {
+    #[prost(message, repeated, tag = "4")]
+    debug_annotations: Vec<DebugAnnotation>,
+    #[prost(enumeration = "EventType", optional, tag = "9")]
+    r#type: Option<i32>,
+    #[prost(uint64, optional, tag = "11")]
+    track_uuid: Option<u64>,
+    #[prost(string, optional, tag = "23")]
+    name: Option<String>,
+    #[prost(fixed64, repeated, packed = "false", tag = "47")]
+    flow_ids: Vec<u64>,
+    #[prost(fixed64, repeated, packed = "false", tag = "48")]
+    terminating_flow_ids: Vec<u64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct DebugAnnotation {
+    #[prost(string, optional, tag = "6")]
+    string_value: Option<String>,
+    #[prost(string, optional, tag = "10")]
+    name: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+enum EventType {
+    SliceBegin = 1,
+    SliceEnd = 2,
+    Instant = 3,
+}
+
+// All packets of the trace come from one writer
+const SEQUENCE_ID: u32 = 1;
+
+// The track of the markers, the tracks of the rows are numbered from 1 after it
+const MARKER_TRACK: u64 = 1;
+
+fn track_packet(uuid: u64, name: &str, parent_uuid: Option<u64>) -> TracePacket {
+    TracePacket {
+        trusted_packet_sequence_id: Some(SEQUENCE_ID),
+        track_descriptor: Some(TrackDescriptor {
+            uuid: Some(uuid),
+            name: Some(name.to_string()),
+            parent_uuid,
+        }),
+        ..Default::default()
+    }
+}
+
+// The Perfetto trace format, see `Timeline::export_perfetto`
+pub(crate) struct PerfettoExporter;
+
+impl TimelineExporter for PerfettoExporter {
+    fn format(&self) -> &str {
+        "perfetto"
+    }
+
+    fn export(&self, timeline: &Timeline, writer: &mut dyn Write) -> Result<(), TimelineError> {
+        timeline.export_perfetto(writer)
+    }
+}
+
+impl<T: TimeValue> Timeline<T> {
+    /// Write the timeline as a Perfetto protobuf trace
+    ///
+    /// The trace opens in the Perfetto UI, and can be queried with its SQL trace processor.
+    /// Every row becomes a track, with a child track for each extra lane its overlapping events
+    /// need, events become slices on the track of their location with their metadata as debug
+    /// annotations, events without duration become instant events, and markers become instant
+    /// events on a track of their own. Triggers become flows between instant events on the
+    /// tracks they start and end on, and the connectors of `add_trigger_between` and `add_link`
+    /// flows between the slices of their events. Times are written in nanoseconds.
+    ///
+    /// This is only available with the `perfetto` feature, which also makes it available as
+    /// `export("perfetto")`.
+    pub fn export_perfetto<W: Write>(&self, mut writer: W) -> Result<(), TimelineError> {
+        let nanos = |time: T| time.to_nanos(self.units).max(0) as u64;
+        let categories = self.categories();
+        let row_track = |location: &str| {
+            categories
+                .iter()
+                .position(|category| *category == location)
+                .map_or(MARKER_TRACK, |row| MARKER_TRACK + 1 + row as u64)
+        };
+
+        let mut tracks = vec![track_packet(MARKER_TRACK, "markers", None)];
+        for category in &categories {
+            tracks.push(track_packet(row_track(category), category, None));
+        }
+
+        // Events of a row that overlap without nesting go on extra lanes, so the slices of every
+        // track nest. Each lane keeps the end times of its open slices.
+        let mut events: Vec<_> = self.events().collect();
+        // Of events starting together the longest goes first, as it encloses the others
+        events.sort_by(|(_, a), (_, b)| {
+            let start = a.start_time.partial_cmp(&b.start_time);
+            let end = b.end_time.partial_cmp(&a.end_time);
+            start
+                .unwrap_or(Ordering::Equal)
+                .then(end.unwrap_or(Ordering::Equal))
+        });
+        let mut lanes: HashMap<&str, Vec<(u64, Vec<T>)>> = HashMap::new();
+        let mut next_track = MARKER_TRACK + 1 + categories.len() as u64;
+        let mut event_tracks = HashMap::new();
+        for (id, event) in &events {
+            let location_lanes = lanes.entry(&event.location).or_default();
+            let mut fits = None;
+            for (index, (_, open)) in location_lanes.iter_mut().enumerate() {
+                while open.last().is_some_and(|end| *end <= event.start_time) {
+                    open.pop();
+                }
+                if fits.is_none() && open.last().is_none_or(|end| *end >= event.end_time) {
+                    fits = Some(index);
+                }
+            }
+            let index = fits.unwrap_or_else(|| {
+                let track = if location_lanes.is_empty() {
+                    row_track(&event.location)
+                } else {
+                    let track = next_track;
+                    next_track += 1;
+                    let parent = row_track(&event.location);
+                    tracks.push(track_packet(track, &event.location, Some(parent)));
+                    track
+                };
+                location_lanes.push((track, Vec::new()));
+                location_lanes.len() - 1
+            });
+            let (track, open) = &mut location_lanes[index];
+            open.push(event.end_time);
+            event_tracks.insert(*id, *track);
+        }
+
+        // Packets are sorted by time, and then by `order`: slices end before others begin at the
+        // same time, in the reverse order they began
+        let mut packets: Vec<(u64, i64, TracePacket)> = Vec::new();
+        let mut push = |time: u64, order: i64, kind: EventType, mut event: TrackEvent| {
+            event.r#type = Some(kind as i32);
+            let packet = TracePacket {
+                timestamp: Some(time),
+                trusted_packet_sequence_id: Some(SEQUENCE_ID),
+                track_event: Some(event),
+                ..Default::default()
+            };
+            packets.push((time, order, packet));
+        };
+
+        let mut flows: HashMap<_, (Vec<u64>, Vec<u64>)> = HashMap::new();
+        let mut flow_id = 0;
+        for link in &self.links {
+            if self.event(link.from).is_some() && self.event(link.to).is_some() {
+                flow_id += 1;
+                flows.entry(link.from).or_default().0.push(flow_id);
+                flows.entry(link.to).or_default().1.push(flow_id);
+            }
+        }
+
+        let count = events.len() as i64;
+        for (index, (id, event)) in events.iter().enumerate() {
+            let index = index as i64;
+            let (flow_ids, terminating_flow_ids) = flows.remove(id).unwrap_or_default();
+            let instant = event.instant || event.start_time == event.end_time;
+            let kind = if instant {
+                EventType::Instant
+            } else {
+                EventType::SliceBegin
+            };
+            let track_uuid = Some(event_tracks[id]);
+            let begin = TrackEvent {
+                debug_annotations: event
+                    .metadata
+                    .iter()
+                    .map(|(key, value)| DebugAnnotation {
+                        name: Some(key.clone()),
+                        string_value: Some(value.clone()),
+                    })
+                    .collect(),
+                track_uuid,
+                name: Some(event.name.clone()),
+                flow_ids,
+                terminating_flow_ids,
+                ..Default::default()
+            };
+            push(nanos(event.start_time), count + index, kind, begin);
+            if !instant {
+                let end = TrackEvent {
+                    track_uuid,
+                    ..Default::default()
+                };
+                push(nanos(event.end_time), -index, EventType::SliceEnd, end);
+            }
+        }
+        for marker in &self.markers {
+            let instant = TrackEvent {
+                track_uuid: Some(MARKER_TRACK),
+                name: Some(marker.label.clone()),
+                ..Default::default()
+            };
+            push(nanos(marker.time), 0, EventType::Instant, instant);
+        }
+        for trigger in &self.triggers {
+            flow_id += 1;
+            let start = TrackEvent {
+                track_uuid: Some(row_track(&trigger.start_location)),
+                name: Some("trigger".to_string()),
+                flow_ids: vec![flow_id],
+                ..Default::default()
+            };
+            let end = TrackEvent {
+                track_uuid: Some(row_track(&trigger.end_location)),
+                name: Some("trigger".to_string()),
+                terminating_flow_ids: vec![flow_id],
+                ..Default::default()
+            };
+            push(nanos(trigger.time), 0, EventType::Instant, start);
+            push(nanos(trigger.end_time), 0, EventType::Instant, end);
+        }
+        packets.sort_by_key(|(time, order, _)| (*time, *order));
+
+        let trace = Trace {
+            packet: tracks
+                .into_iter()
+                .chain(packets.into_iter().map(|(_, _, packet)| packet))
+                .collect(),
+        };
+        writer.write_all(&trace.encode_to_vec())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_perfetto() {
+        let mut timeline = Timeline::default();
+        let from = timeline.add_event("wakeup", 0, 20, "CPU 0");
+        timeline.add_event("nested", 5, 10, "CPU 0");
+        timeline.add_event("overlap", 15, 30, "CPU 0");
+        let to = timeline.add_event("run", 40, 50, "CPU 1");
+        timeline.add_trigger_between(from, to);
+        timeline.add_trigger("CPU 1", "CPU 0", 45);
+        timeline.add_marker("sync".to_string(), 25);
+
+        let mut output = Vec::new();
+        timeline.export_perfetto(&mut output).unwrap();
+        let trace = Trace::decode(output.as_slice()).unwrap();
+        let tracks: Vec<_> = trace
+            .packet
+            .iter()
+            .filter_map(|packet| packet.track_descriptor.as_ref())
+            .map(|track| (track.uuid, track.name.as_deref(), track.parent_uuid))
+            .collect();
+        // "overlap" doesn't nest in "wakeup", so it gets a lane of its own
+        assert_eq!(
+            tracks,
+            [
+                (Some(1), Some("markers"), None),
+                (Some(2), Some("CPU 0"), None),
+                (Some(3), Some("CPU 1"), None),
+                (Some(4), Some("CPU 0"), Some(2)),
+            ]
+        );
+
+        let events: Vec<_> = trace
+            .packet
+            .iter()
+            .filter_map(|packet| Some((packet.timestamp?, packet.track_event.as_ref()?)))
+            .collect();
+        assert_eq!(events.len(), 11);
+        assert!(events.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        let (_, wakeup) = events[0];
+        assert_eq!(wakeup.name.as_deref(), Some("wakeup"));
+        assert_eq!(wakeup.flow_ids, [1]);
+        let run = events
+            .iter()
+            .find(|(_, e)| e.name.as_deref() == Some("run"));
+        assert_eq!(run.unwrap().1.terminating_flow_ids, [1]);
+        let overlap = events
+            .iter()
+            .find(|(_, e)| e.name.as_deref() == Some("overlap"));
+        assert_eq!(overlap.unwrap().1.track_uuid, Some(4));
+        let trigger = events.iter().find(|(_, e)| e.flow_ids == [2]);
+        assert_eq!(trigger.unwrap().1.track_uuid, Some(3));
+
+        let mut exported = Vec::new();
+        timeline.export("perfetto", &mut exported).unwrap();
+        assert_eq!(exported, output);
+    }
+}
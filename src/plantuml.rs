@@ -0,0 +1,162 @@
+use std::io::Write;
+
+use crate::{TimeValue, Timeline, TimelineError, TimelineExporter};
+
+// The PlantUML timing diagram format, see `Timeline::export_plantuml`
+pub(crate) struct PlantUmlExporter;
+
+impl TimelineExporter for PlantUmlExporter {
+    fn format(&self) -> &str {
+        "plantuml"
+    }
+
+    fn export(&self, timeline: &Timeline, writer: &mut dyn Write) -> Result<(), TimelineError> {
+        timeline.export_plantuml(writer)
+    }
+}
+
+// PlantUML strings can't escape quotes or span lines
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "'").replace(['\n', '\r'], " "))
+}
+
+impl<T: TimeValue> Timeline<T> {
+    /// Write the timeline as a PlantUML timing diagram
+    ///
+    /// Every row becomes a concise lane, which is in the state of the event running on it, named
+    /// after the event, and hidden between events. When events of a row overlap, the lane is in the
+    /// state of the one that started last. Triggers, and the connectors of `add_trigger_between`
+    /// and `add_link`, become messages between the lanes, labeled with the labels of links. Events
+    /// without duration have no state to show and are left out. Times are written in units since
+    /// the start of the timeline. This is the same as `export("plantuml")`.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("read", 0, 5, "CPU 0");
+    /// let mut output = Vec::new();
+    /// timeline.export_plantuml(&mut output).unwrap();
+    /// assert!(String::from_utf8(output).unwrap().contains("L0 is \"read\""));
+    /// # }
+    /// ```
+    pub fn export_plantuml<W: Write>(&self, mut writer: W) -> Result<(), TimelineError> {
+        let offset = |time: T| time.offset_from(self.start_time, self.units);
+        let categories = self.categories();
+        let lane = |location: &str| {
+            categories
+                .iter()
+                .position(|category| *category == location)
+                .map(|index| format!("L{}", index))
+        };
+
+        // The statements at each time, state changes first
+        let mut statements: Vec<(f64, bool, String)> = Vec::new();
+        for (index, category) in categories.iter().enumerate() {
+            let mut events: Vec<_> = self
+                .events()
+                .map(|(_, event)| event)
+                .filter(|event| event.location == *category && event.start_time < event.end_time)
+                .collect();
+            events.sort_by(|a, b| {
+                a.start_time
+                    .partial_cmp(&b.start_time)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            let mut times: Vec<T> = events
+                .iter()
+                .flat_map(|event| [event.start_time, event.end_time])
+                .collect();
+            times.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+            times.dedup();
+            let mut state = None;
+            for time in times {
+                let current = events
+                    .iter()
+                    .rev()
+                    .find(|event| event.start_time <= time && time < event.end_time)
+                    .map(|event| event.name.as_str());
+                if current != state {
+                    let name = current.map_or("{-}".to_string(), quoted);
+                    statements.push((offset(time), false, format!("L{} is {}", index, name)));
+                    state = current;
+                }
+            }
+        }
+        let mut message = |from: &str, start: T, to: &str, end: T, label: Option<&str>| {
+            if let (Some(from), Some(to)) = (lane(from), lane(to)) {
+                let mut statement = format!("{} -> {}", from, to);
+                if start != end {
+                    statement.push_str(&format!("@{}", offset(end)));
+                }
+                if let Some(label) = label.filter(|label| !label.is_empty()) {
+                    statement.push_str(&format!(" : {}", label.replace(['\n', '\r'], " ")));
+                }
+                statements.push((offset(start), true, statement));
+            }
+        };
+        for trigger in &self.triggers {
+            message(
+                &trigger.start_location,
+                trigger.time,
+                &trigger.end_location,
+                trigger.end_time,
+                None,
+            );
+        }
+        for link in &self.links {
+            if let (Some(from), Some(to)) = (self.event(link.from), self.event(link.to)) {
+                message(
+                    &from.location,
+                    from.end_time,
+                    &to.location,
+                    to.start_time,
+                    link.label.as_deref(),
+                );
+            }
+        }
+        statements.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+        writeln!(writer, "@startuml")?;
+        for (index, category) in categories.iter().enumerate() {
+            writeln!(writer, "concise {} as L{}", quoted(category), index)?;
+        }
+        let mut now = None;
+        for (time, _, statement) in statements {
+            if now != Some(time) {
+                writeln!(writer, "@{}", time)?;
+                now = Some(time);
+            }
+            writeln!(writer, "{}", statement)?;
+        }
+        writeln!(writer, "@enduml")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_plantuml() {
+        let mut timeline = Timeline::default();
+        let from = timeline.add_event("sleep", 10, 20, "CPU 0");
+        timeline.add_event("irq \"5\"", 12, 14, "CPU 0");
+        timeline.add_event("tick", 15, 15, "CPU 0");
+        let to = timeline.add_event("run", 22, 30, "CPU 1");
+        timeline.add_trigger_between(from, to);
+        timeline.add_trigger("CPU 1", "CPU 0", 25);
+
+        let mut output = Vec::new();
+        timeline.export("plantuml", &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "@startuml\nconcise \"CPU 0\" as L0\nconcise \"CPU 1\" as L1\n@0\nL0 is \"sleep\"\n\
+             @2\nL0 is \"irq '5'\"\n@4\nL0 is \"sleep\"\n@10\nL0 is {-}\nL0 -> L1@12\n\
+             @12\nL1 is \"run\"\n@15\nL1 -> L0\n@20\nL1 is {-}\n@enduml\n"
+        );
+    }
+}
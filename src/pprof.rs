@@ -0,0 +1,539 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+use prost::Message;
+
+use crate::import::new_timeline;
+use crate::{EventBuilder, Timeline, TimelineError, TraceImporter};
+
+// The pprof profiles of continuous profilers, see `Timeline::from_pprof`
+pub(crate) struct PprofImporter;
+
+impl TraceImporter for PprofImporter {
+    fn format(&self) -> &str {
+        "pprof"
+    }
+
+    // Protobuf has no magic number, so this decodes the whole profile and checks for what
+    // every profile has
+    fn sniff(&self, data: &[u8]) -> bool {
+        if !data.starts_with(&GZIP) && data.first() != Some(&0x0a) {
+            return false;
+        }
+        decode(data).is_ok_and(|profile| {
+            !profile.sample_type.is_empty()
+                && profile.string_table.first().is_some_and(String::is_empty)
+        })
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_pprof(data)
+    }
+}
+
+// Profiles are usually gzipped
+const GZIP: [u8; 2] = [0x1f, 0x8b];
+
+// The labels that continuous profilers give samples the time they were taken at, in
+// nanoseconds since the epoch
+const TIME_LABELS: [&str; 3] = ["end_timestamp_ns", "timestamp_ns", "timestamp"];
+// The labels of the thread a sample was taken on, in the order they are looked for
+const THREAD_LABELS: [&str; 5] = ["thread name", "thread", "thread id", "tid", "goroutine"];
+
+// The messages of profile.proto, declared by hand with its field numbers like those of the
+// OTLP importer
+mod proto {
+    use prost::Message;
+
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct Profile {
+        #[prost(message, repeated, tag = "1")]
+        pub(super) sample_type: Vec<ValueType>,
+        #[prost(message, repeated, tag = "2")]
+        pub(super) sample: Vec<Sample>,
+        #[prost(message, repeated, tag = "4")]
+        pub(super) location: Vec<Location>,
+        #[prost(message, repeated, tag = "5")]
+        pub(super) function: Vec<Function>,
+        #[prost(string, repeated, tag = "6")]
+        pub(super) string_table: Vec<String>,
+        #[prost(int64, tag = "9")]
+        pub(super) time_nanos: i64,
+        #[prost(message, optional, tag = "11")]
+        pub(super) period_type: Option<ValueType>,
+        #[prost(int64, tag = "12")]
+        pub(super) period: i64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct ValueType {
+        #[prost(int64, tag = "1")]
+        pub(super) r#type: i64,
+        #[prost(int64, tag = "2")]
+        pub(super) unit: i64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct Sample {
+        #[prost(uint64, repeated, tag = "1")]
+        pub(super) location_id: Vec<u64>,
+        #[prost(int64, repeated, tag = "2")]
+        pub(super) value: Vec<i64>,
+        #[prost(message, repeated, tag = "3")]
+        pub(super) label: Vec<Label>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct Label {
+        #[prost(int64, tag = "1")]
+        pub(super) key: i64,
+        #[prost(int64, tag = "2")]
+        pub(super) str: i64,
+        #[prost(int64, tag = "3")]
+        pub(super) num: i64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct Location {
+        #[prost(uint64, tag = "1")]
+        pub(super) id: u64,
+        #[prost(uint64, tag = "3")]
+        pub(super) address: u64,
+        #[prost(message, repeated, tag = "4")]
+        pub(super) line: Vec<Line>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct Line {
+        #[prost(uint64, tag = "1")]
+        pub(super) function_id: u64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub(super) struct Function {
+        #[prost(uint64, tag = "1")]
+        pub(super) id: u64,
+        #[prost(int64, tag = "2")]
+        pub(super) name: i64,
+    }
+}
+
+fn decode(data: &[u8]) -> Result<proto::Profile, TimelineError> {
+    let mut unzipped = Vec::new();
+    let data = if data.starts_with(&GZIP) {
+        GzDecoder::new(data).read_to_end(&mut unzipped)?;
+        &unzipped[..]
+    } else {
+        data
+    };
+    proto::Profile::decode(data).map_err(|e| TimelineError::Parse(e.to_string()))
+}
+
+// The nanoseconds in one of `unit`, for the units of periods
+fn nanos_per(unit: &str) -> Option<i64> {
+    match unit {
+        "nanoseconds" => Some(1),
+        "microseconds" => Some(1000),
+        "milliseconds" => Some(1_000_000),
+        "seconds" => Some(1_000_000_000),
+        _ => None,
+    }
+}
+
+// A sample with a time, as the functions of its stack from the root to the leaf
+struct TimedSample {
+    time: i64,
+    stack: Vec<String>,
+    value: i64,
+}
+
+impl Timeline {
+    /// Read a pprof profile with timestamps into a timeline
+    ///
+    /// This reads the protobuf profiles of pprof, gzipped or not, whose samples carry the time they
+    /// were taken at in a label, as continuous profilers like Datadog's write them, in nanoseconds
+    /// since the epoch under "end_timestamp_ns", "timestamp_ns" or "timestamp". Every thread, from
+    /// a "thread name", "thread", "thread id", "tid" or "goroutine" label like "thread main", gets
+    /// a row, or "all threads" for samples without one. Samples in the same function one after the
+    /// other on a thread become one event, named after the function at the leaf of their stack,
+    /// lasting from the first sample until the period of the profile after the last, or the next
+    /// sample if that is sooner, so a function that was sampled the whole time is one long event.
+    /// The period is 10ms, the rate of Go and most profilers, for profiles that don't have one in a
+    /// unit of time. The number of "samples", the sum of the first value of the samples, like
+    /// "cpu", and the "stack" of the first sample, from the root to the leaf separated by ";", are
+    /// the metadata of every event. Samples without a time are skipped. Times are in nanoseconds
+    /// since the epoch.
+    ///
+    /// This is only available with the `pprof` feature. This returns `TimelineError::Parse` if
+    /// the profile can't be decoded or has no samples with a time, and `TimelineError::Io` if
+    /// reading it fails. `Timeline::import` detects profiles of this format, as "pprof".
+    pub fn from_pprof(mut reader: impl Read) -> Result<Timeline, TimelineError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let profile = decode(&data)?;
+        let string = |index: i64| {
+            usize::try_from(index)
+                .ok()
+                .and_then(|index| profile.string_table.get(index))
+                .map_or("", String::as_str)
+        };
+        let functions: HashMap<u64, &str> = profile
+            .function
+            .iter()
+            .map(|function| (function.id, string(function.name)))
+            .collect();
+        // The functions of every location, inlined ones first like the lines of a location
+        let locations: HashMap<u64, Vec<String>> = profile
+            .location
+            .iter()
+            .map(|location| {
+                let mut names: Vec<String> = location
+                    .line
+                    .iter()
+                    .filter_map(|line| functions.get(&line.function_id))
+                    .map(|name| name.to_string())
+                    .collect();
+                if names.is_empty() {
+                    names.push(format!("{:#x}", location.address));
+                }
+                (location.id, names)
+            })
+            .collect();
+        let period = profile
+            .period_type
+            .as_ref()
+            .and_then(|period_type| nanos_per(string(period_type.unit)))
+            .map(|nanos| profile.period * nanos)
+            .filter(|period| *period > 0)
+            .unwrap_or(10_000_000);
+
+        let mut threads: BTreeMap<String, Vec<TimedSample>> = BTreeMap::new();
+        for sample in &profile.sample {
+            let label = |keys: &[&str]| {
+                keys.iter().find_map(|key| {
+                    let label = sample
+                        .label
+                        .iter()
+                        .find(|label| string(label.key) == *key)?;
+                    Some(match string(label.str) {
+                        "" => label.num.to_string(),
+                        text => text.to_string(),
+                    })
+                })
+            };
+            let Some(time) = label(&TIME_LABELS).and_then(|time| time.parse().ok()) else {
+                continue;
+            };
+            let thread = label(&THREAD_LABELS)
+                .map(|thread| format!("thread {}", thread))
+                .unwrap_or_else(|| "all threads".to_string());
+            // Locations are listed from the leaf to the root
+            let stack = sample
+                .location_id
+                .iter()
+                .rev()
+                .flat_map(|id| locations.get(id).into_iter().flatten().rev())
+                .cloned()
+                .collect();
+            threads.entry(thread).or_default().push(TimedSample {
+                time,
+                stack,
+                value: sample.value.first().copied().unwrap_or_default(),
+            });
+        }
+        if threads.is_empty() {
+            return Err(TimelineError::Parse("no samples with a time".to_string()));
+        }
+
+        let value_name = profile
+            .sample_type
+            .first()
+            .map_or("value", |value_type| string(value_type.r#type))
+            .to_string();
+        let mut timeline = new_timeline();
+        for (thread, mut samples) in threads {
+            samples.sort_by_key(|sample| sample.time);
+            let mut samples = samples.into_iter().peekable();
+            while let Some(first) = samples.next() {
+                let leaf = first.stack.last().cloned().unwrap_or_default();
+                let (mut end, mut count, mut value) = (first.time + period, 1, first.value);
+                // Samples more than half a period late still follow on, for profilers that
+                // don't sample exactly on time
+                while let Some(next) = samples.next_if(|next| {
+                    next.time <= end + period / 2 && next.stack.last() == first.stack.last()
+                }) {
+                    end = next.time + period;
+                    count += 1;
+                    value += next.value;
+                }
+                if let Some(next) = samples.peek() {
+                    end = end.min(next.time);
+                }
+                timeline.insert_event(
+                    EventBuilder::new(leaf, first.time, end, thread.clone())
+                        .metadata("samples".to_string(), count.to_string())
+                        .metadata(value_name.clone(), value.to_string())
+                        .metadata("stack".to_string(), first.stack.join(";"))
+                        .build(),
+                );
+            }
+        }
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::proto::*;
+    use super::*;
+
+    const MS: i64 = 1_000_000;
+
+    // The string table of the test profiles
+    const STRINGS: [&str; 15] = [
+        "",
+        "cpu",
+        "nanoseconds",
+        "main",
+        "compute",
+        "write",
+        "end_timestamp_ns",
+        "thread name",
+        "worker",
+        "tid",
+        "timestamp",
+        "milliseconds",
+        "count",
+        "goroutine",
+        "1004000000",
+    ];
+
+    // A sample of the stack of locations, from the leaf to the root, with labels of keys and
+    // strings or numbers
+    fn sample(location_id: Vec<u64>, labels: &[(i64, i64, i64)]) -> Sample {
+        Sample {
+            location_id,
+            value: vec![10 * MS],
+            label: labels
+                .iter()
+                .map(|&(key, str, num)| Label { key, str, num })
+                .collect(),
+        }
+    }
+
+    // A sample with an "end_timestamp_ns" of milliseconds past a second, on a thread if there
+    // is one
+    fn timed(location_id: Vec<u64>, ms: i64, thread: Option<i64>) -> Sample {
+        let mut sample = sample(location_id, &[(6, 0, 1000 * MS + ms * MS)]);
+        if let Some(thread) = thread {
+            sample.label.push(Label {
+                key: 7,
+                str: thread,
+                num: 0,
+            });
+        }
+        sample
+    }
+
+    // A CPU profile with a period of 10ms, of the functions main, compute and write, at
+    // locations 1 and 2, 3 with none, and 4 with write inlined into compute
+    fn profile(sample: Vec<Sample>) -> Profile {
+        let location = |id: u64, functions: &[u64]| Location {
+            id,
+            address: id * 0x1000,
+            line: functions
+                .iter()
+                .map(|&function_id| Line { function_id })
+                .collect(),
+        };
+        Profile {
+            sample_type: vec![ValueType { r#type: 1, unit: 2 }],
+            sample,
+            location: vec![
+                location(1, &[1]),
+                location(2, &[2]),
+                location(3, &[]),
+                location(4, &[3, 2]),
+            ],
+            function: vec![
+                Function { id: 1, name: 3 },
+                Function { id: 2, name: 4 },
+                Function { id: 3, name: 5 },
+            ],
+            string_table: STRINGS.iter().map(|s| s.to_string()).collect(),
+            time_nanos: 1000 * MS,
+            period_type: Some(ValueType { r#type: 1, unit: 2 }),
+            period: 10 * MS,
+        }
+    }
+
+    // The events of a timeline, with times in milliseconds past a second
+    fn events(timeline: &Timeline) -> Vec<(&str, &str, i64, i64)> {
+        timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name(),
+                    event.location(),
+                    event.start_time() / MS - 1000,
+                    event.end_time() / MS - 1000,
+                )
+            })
+            .collect()
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(data).unwrap();
+        gzip.finish().unwrap()
+    }
+
+    #[test]
+    fn test_from_pprof() {
+        let threads = profile(vec![
+            timed(vec![2, 1], 0, Some(8)),
+            timed(vec![2, 1], 10, Some(8)),
+            // A little late, which still follows on
+            timed(vec![2, 1], 24, Some(8)),
+            timed(vec![3, 1], 30, Some(8)),
+            timed(vec![2, 1], 100, Some(8)),
+            timed(vec![1], 0, None),
+            // Samples without a time are skipped
+            sample(vec![1], &[]),
+        ]);
+        let data = threads.encode_to_vec();
+        assert!(PprofImporter.sniff(&data));
+        assert!(PprofImporter.sniff(&gzip(&data)));
+        let timeline = Timeline::from_pprof(&gzip(&data)[..]).unwrap();
+        assert_eq!(timeline.categories(), ["all threads", "thread worker"]);
+        // Locations without functions are named after their address
+        assert_eq!(
+            events(&timeline),
+            [
+                ("main", "all threads", 0, 10),
+                ("compute", "thread worker", 0, 30),
+                ("0x3000", "thread worker", 30, 40),
+                ("compute", "thread worker", 100, 110),
+            ]
+        );
+        let (_, compute) = timeline.events().nth(1).unwrap();
+        assert_eq!(compute.metadata()["samples"], "3");
+        assert_eq!(compute.metadata()["cpu"], "30000000");
+        assert_eq!(compute.metadata()["stack"], "main;compute");
+
+        // Inlined functions are listed before the function they are inlined into
+        let inlined = profile(vec![timed(vec![4, 1], 0, None)]);
+        let timeline = Timeline::from_pprof(&inlined.encode_to_vec()[..]).unwrap();
+        let (_, write) = timeline.events().next().unwrap();
+        assert_eq!(write.name(), "write");
+        assert_eq!(write.metadata()["stack"], "main;compute;write");
+    }
+
+    #[test]
+    fn test_labels() {
+        let profile = profile(vec![
+            sample(vec![1], &[(10, 0, 1000 * MS), (9, 0, 42)]),
+            // "end_timestamp_ns" comes before "timestamp", and "thread name" before "tid"
+            sample(
+                vec![1],
+                &[(10, 0, 5), (6, 0, 1002 * MS), (9, 0, 42), (7, 8, 0)],
+            ),
+            sample(vec![1], &[(6, 0, 1003 * MS), (13, 0, 7)]),
+            // Times can be text, which has to be a number
+            sample(vec![1], &[(10, 14, 0)]),
+            sample(vec![1], &[(6, 3, 0)]),
+        ]);
+        let timeline = Timeline::from_pprof(&profile.encode_to_vec()[..]).unwrap();
+        assert_eq!(
+            events(&timeline),
+            [
+                ("main", "all threads", 4, 14),
+                ("main", "thread 42", 0, 10),
+                ("main", "thread 7", 3, 13),
+                ("main", "thread worker", 2, 12),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_period() {
+        assert_eq!(nanos_per("nanoseconds"), Some(1));
+        assert_eq!(nanos_per("milliseconds"), Some(MS));
+        assert_eq!(nanos_per("seconds"), Some(1_000_000_000));
+        assert_eq!(nanos_per("count"), None);
+
+        let five = Profile {
+            period_type: Some(ValueType {
+                r#type: 1,
+                unit: 11,
+            }),
+            period: 5,
+            ..profile(vec![
+                timed(vec![1], 0, None),
+                timed(vec![1], 5, None),
+                timed(vec![1], 20, None),
+            ])
+        };
+        let timeline = Timeline::from_pprof(&five.encode_to_vec()[..]).unwrap();
+        assert_eq!(
+            events(&timeline),
+            [
+                ("main", "all threads", 0, 10),
+                ("main", "all threads", 20, 25)
+            ]
+        );
+        // Periods that aren't times are 10ms, and events end when the next sample is sooner
+        let counted = Profile {
+            period_type: Some(ValueType {
+                r#type: 1,
+                unit: 12,
+            }),
+            period: 1,
+            ..profile(vec![timed(vec![1], 0, None), timed(vec![2, 1], 4, None)])
+        };
+        let timeline = Timeline::from_pprof(&counted.encode_to_vec()[..]).unwrap();
+        assert_eq!(
+            events(&timeline),
+            [
+                ("main", "all threads", 0, 4),
+                ("compute", "all threads", 4, 14)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_from_pprof_errors() {
+        // Other protobuf messages lack the sample types or the empty first string
+        let no_types = Profile {
+            sample_type: Vec::new(),
+            ..profile(Vec::new())
+        };
+        assert!(!PprofImporter.sniff(&no_types.encode_to_vec()));
+        let no_strings = Profile {
+            string_table: Vec::new(),
+            ..profile(Vec::new())
+        };
+        assert!(!PprofImporter.sniff(&no_strings.encode_to_vec()));
+        assert!(!PprofImporter.sniff(&gzip(b"not a profile")));
+
+        let untimed = profile(vec![sample(vec![1], &[(9, 0, 42)])]);
+        assert!(matches!(
+            Timeline::from_pprof(&untimed.encode_to_vec()[..]),
+            Err(TimelineError::Parse(_))
+        ));
+        assert!(matches!(
+            Timeline::from_pprof(&b"\x0anot a profile"[..]),
+            Err(TimelineError::Parse(_))
+        ));
+        assert!(matches!(
+            Timeline::from_pprof(&b"\x1f\x8bcut off"[..]),
+            Err(TimelineError::Io(_))
+        ));
+    }
+}
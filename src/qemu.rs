@@ -0,0 +1,634 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+
+use crate::ftrace::parse_time;
+use crate::import::{new_timeline, sniff_text};
+use crate::{Timeline, TimelineError, TraceImporter};
+
+// The traces of QEMU's log and simple backends, see `Timeline::from_qemu_trace` and
+// `Timeline::from_qemu_simpletrace`
+pub(crate) struct QemuImporter;
+
+impl TraceImporter for QemuImporter {
+    fn format(&self) -> &str {
+        "qemu"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        if data.starts_with(&HEADER) {
+            return true;
+        }
+        sniff_text(data)
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .is_some_and(|line| parse_line(line).is_some())
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        if data.starts_with(&HEADER) {
+            Timeline::from_qemu_simpletrace(data, "")
+        } else {
+            Timeline::from_qemu_trace(data)
+        }
+    }
+}
+
+// The event ID and the magic number that simple traces start with, before their version
+const HEADER: [u8; 16] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xb4, 0x29, 0xa4, 0x0a, 0xcb, 0x77, 0xb1, 0xf2,
+];
+const DROPPED_EVENT_ID: u64 = 0xffff_ffff_ffff_fffe;
+const RECORD_TYPE_MAPPING: u64 = 0;
+
+// The names of the arguments that events give the index of their vCPU in
+const CPU_ARGS: [&str; 4] = ["cpu_index", "cpu", "vcpu", "cpu_id"];
+
+// A line of the log backend, like "1234@1700000000.000100:kvm_run_exit cpu_index 0, reason 2"
+#[derive(Debug, PartialEq)]
+struct Line<'a> {
+    tid: &'a str,
+    time: i64,
+    name: &'a str,
+    args: &'a str,
+}
+
+fn parse_line(line: &str) -> Option<Line<'_>> {
+    let (tid, rest) = line.trim().split_once('@')?;
+    let (time, rest) = rest.split_once(':')?;
+    if tid.is_empty() || !tid.bytes().all(|b| b.is_ascii_digit()) || !time.contains('.') {
+        return None;
+    }
+    let (name, args) = rest.split_once(' ').unwrap_or((rest, ""));
+    if name.is_empty() || !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_') {
+        return None;
+    }
+    Some(Line {
+        tid,
+        time: parse_time(time)?,
+        name,
+        args: args.trim(),
+    })
+}
+
+// The index of the vCPU an event is about, from an argument like "cpu_index 0" or "cpu=1" of its
+// message
+fn vcpu(args: &str) -> Option<u32> {
+    let words: Vec<&str> = args
+        .split(|c: char| c.is_whitespace() || matches!(c, ',' | '=' | ':'))
+        .filter(|word| !word.is_empty())
+        .collect();
+    words
+        .windows(2)
+        .find(|pair| CPU_ARGS.contains(&pair[0]))
+        .and_then(|pair| pair[1].parse().ok())
+}
+
+// The row of an event: the vCPU it is about, or else the subsystem it is named after, like
+// "virtio" for "virtio_queue_notify"
+fn row(name: &str, vcpu: Option<u32>) -> String {
+    match vcpu {
+        Some(vcpu) => format!("vCPU {}", vcpu),
+        None => name.split('_').next().unwrap_or(name).to_string(),
+    }
+}
+
+// An argument of an event in a trace-events file, like "uint64_t addr"
+struct Arg {
+    name: String,
+    kind: ArgKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ArgKind {
+    String,
+    Signed,
+    Unsigned,
+    Pointer,
+}
+
+// The arguments of the events of trace-events files, by the name of the event, from lines like
+// `kvm_run_exit(int cpu_index, uint32_t reason) "cpu_index %d, reason %d"`
+fn parse_trace_events(text: &str) -> HashMap<&str, Vec<Arg>> {
+    let mut events = HashMap::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        let Some((prefix, rest)) = line.split_once('(') else {
+            continue;
+        };
+        let Some((args, _)) = rest.split_once(')') else {
+            continue;
+        };
+        // Names can have properties before them, like "disable"
+        let Some(name) = prefix.split_whitespace().last() else {
+            continue;
+        };
+        let args = args
+            .split(',')
+            .map(str::trim)
+            .filter(|arg| !arg.is_empty() && *arg != "void")
+            .filter_map(|arg| {
+                let (kind, name) = arg.rsplit_once(|c: char| c.is_whitespace() || c == '*')?;
+                let kind = if arg.contains('*') {
+                    if kind.contains("char") {
+                        ArgKind::String
+                    } else {
+                        ArgKind::Pointer
+                    }
+                } else if kind.contains("uint") || kind.contains("unsigned") || kind == "size_t" {
+                    ArgKind::Unsigned
+                } else {
+                    ArgKind::Signed
+                };
+                Some(Arg {
+                    name: name.to_string(),
+                    kind,
+                })
+            })
+            .collect();
+        events.insert(name, args);
+    }
+    events
+}
+
+// The fields of a simple trace, in little-endian
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.bytes(8)?.try_into().ok()?))
+    }
+}
+
+// The values of the arguments of a record, as text, or None if they don't fit the record
+fn read_args(mut args: Reader, kinds: &[Arg]) -> Option<Vec<(String, String)>> {
+    kinds
+        .iter()
+        .map(|arg| {
+            let value = match arg.kind {
+                ArgKind::String => {
+                    let len = args.u32()? as usize;
+                    String::from_utf8_lossy(args.bytes(len)?).into_owned()
+                }
+                ArgKind::Signed => (args.u64()? as i64).to_string(),
+                ArgKind::Unsigned => args.u64()?.to_string(),
+                ArgKind::Pointer => format!("{:#x}", args.u64()?),
+            };
+            Some((arg.name.clone(), value))
+        })
+        .collect()
+}
+
+impl Timeline {
+    /// Read a trace of QEMU's log backend into a timeline
+    ///
+    /// This reads the lines the log backend writes with `-msg timestamp=on`, like
+    /// "1234@1700000000.000100:kvm_run_exit cpu_index 0, reason 2", and skips the others. Every
+    /// trace event becomes an instant named after the event, on the row of the vCPU it is about,
+    /// like "vCPU 0" for events with a "cpu_index", "cpu", "vcpu" or "cpu_id" argument, so
+    /// vmexits and MMIO accesses line up per vCPU, or else on the row of the subsystem the
+    /// event is named after, like "virtio" for "virtio_queue_notify". The message and the ID
+    /// of the thread that traced it are its "args" and "tid" metadata. Times are in
+    /// nanoseconds since the epoch.
+    ///
+    /// This returns `TimelineError::Parse` if no line has a timestamp, and `TimelineError::Io`
+    /// if reading fails. `Timeline::import` detects traces of this format, as "qemu".
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let trace = "\
+    ///     1234@1.000100:kvm_run_exit cpu_index 0, reason 2\n\
+    ///     1235@1.000200:virtio_queue_notify vdev 0x55d0 n 0 vq 0x55e0\n";
+    /// let timeline = Timeline::from_qemu_trace(trace.as_bytes()).unwrap();
+    /// assert_eq!(timeline.categories(), ["vCPU 0", "virtio"]);
+    /// assert_eq!(timeline.time_range(), Some((1_000_100_000, 1_000_200_000)));
+    /// # }
+    /// ```
+    pub fn from_qemu_trace(reader: impl Read) -> Result<Timeline, TimelineError> {
+        let mut timeline = new_timeline();
+        let mut parsed = false;
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            let Some(line) = parse_line(&line) else {
+                continue;
+            };
+            parsed = true;
+            let row = row(line.name, vcpu(line.args));
+            let id = timeline.add_instant(line.name, line.time, row);
+            timeline.set_event_metadata(id, "args".to_string(), line.args.to_string());
+            timeline.set_event_metadata(id, "tid".to_string(), line.tid.to_string());
+        }
+        if !parsed {
+            return Err(TimelineError::Parse("no QEMU trace events".to_string()));
+        }
+        Ok(timeline)
+    }
+
+    /// Read a trace of QEMU's simple backend into a timeline
+    ///
+    /// This reads the binary `trace-<pid>` files the simple backend writes, of version 4,
+    /// which name their events but leave the types of their arguments to the trace-events
+    /// files of QEMU's source. With the text of those files in `trace_events`, the arguments
+    /// of every event are its metadata, by their names, and events with a vCPU argument are
+    /// drawn on its row, as `Timeline::from_qemu_trace` does. The arguments of events it
+    /// doesn't describe are skipped, and with an empty `trace_events` all events are drawn on
+    /// the row of their subsystem. The ID of the thread that traced an event is its "tid"
+    /// metadata, and records of dropped events are skipped. Times are in nanoseconds of the
+    /// monotonic clock.
+    ///
+    /// This returns `TimelineError::Parse` if the trace doesn't start with the header of
+    /// version 4 or has no events, and `TimelineError::Io` if reading it fails.
+    /// `Timeline::import` detects traces of this format, as "qemu", and reads them without
+    /// trace-events files.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut trace = Vec::new();
+    /// // The header
+    /// for word in [u64::MAX, 0xf2b1_77cb_0aa4_29b4, 4] {
+    ///     trace.extend(word.to_le_bytes());
+    /// }
+    /// // The mapping of event 7 to "kvm_run_exit"
+    /// trace.extend(0u64.to_le_bytes());
+    /// trace.extend(7u64.to_le_bytes());
+    /// trace.extend(12u32.to_le_bytes());
+    /// trace.extend(b"kvm_run_exit");
+    /// // Event 7 at 5000ns by thread 1234, with the arguments 1 and 2
+    /// trace.extend(1u64.to_le_bytes());
+    /// trace.extend(7u64.to_le_bytes());
+    /// trace.extend(5000u64.to_le_bytes());
+    /// trace.extend(40u32.to_le_bytes());
+    /// trace.extend(1234u32.to_le_bytes());
+    /// trace.extend(1u64.to_le_bytes());
+    /// trace.extend(2u64.to_le_bytes());
+    ///
+    /// let events = r#"kvm_run_exit(int cpu_index, uint32_t reason) "cpu_index %d, reason %d""#;
+    /// let timeline = Timeline::from_qemu_simpletrace(&trace[..], events).unwrap();
+    /// assert_eq!(timeline.categories(), ["vCPU 1"]);
+    /// assert_eq!(timeline.time_range(), Some((5000, 5000)));
+    /// # }
+    /// ```
+    pub fn from_qemu_simpletrace(
+        mut reader: impl Read,
+        trace_events: &str,
+    ) -> Result<Timeline, TimelineError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        if !data.starts_with(&HEADER) {
+            return Err(TimelineError::Parse("not a QEMU simple trace".to_string()));
+        }
+        let mut reader = Reader {
+            data: &data,
+            pos: HEADER.len(),
+        };
+        match reader.u64() {
+            Some(4) => {}
+            version => {
+                return Err(TimelineError::Parse(format!(
+                    "unsupported QEMU simple trace version {:?}",
+                    version
+                )))
+            }
+        }
+        let kinds = parse_trace_events(trace_events);
+        let mut names: HashMap<u64, String> = HashMap::new();
+        let mut timeline = new_timeline();
+        let mut parsed = false;
+        // A trace can end in the middle of a record when QEMU is killed
+        while let Some(record_type) = reader.u64() {
+            if record_type == RECORD_TYPE_MAPPING {
+                let Some(id) = reader.u64() else { break };
+                let Some(name) = reader.u32().and_then(|len| reader.bytes(len as usize)) else {
+                    break;
+                };
+                names.insert(id, String::from_utf8_lossy(name).into_owned());
+                continue;
+            }
+            let (Some(id), Some(time), Some(len), Some(tid)) =
+                (reader.u64(), reader.u64(), reader.u32(), reader.u32())
+            else {
+                break;
+            };
+            // The length counts the 24 bytes of the fields before the arguments
+            let Some(args) = (len as usize)
+                .checked_sub(24)
+                .and_then(|len| reader.bytes(len))
+            else {
+                break;
+            };
+            if id == DROPPED_EVENT_ID {
+                continue;
+            }
+            let name = names
+                .get(&id)
+                .cloned()
+                .unwrap_or_else(|| format!("event {}", id));
+            let args = kinds
+                .get(name.as_str())
+                .and_then(|kinds| read_args(Reader { data: args, pos: 0 }, kinds))
+                .unwrap_or_default();
+            let vcpu = args
+                .iter()
+                .find(|(name, _)| CPU_ARGS.contains(&name.as_str()))
+                .and_then(|(_, value)| value.parse().ok());
+            let row = row(&name, vcpu);
+            let id = timeline.add_instant(name, time as i64, row);
+            timeline.set_event_metadata(id, "tid".to_string(), tid.to_string());
+            for (key, value) in args {
+                timeline.set_event_metadata(id, key, value);
+            }
+            parsed = true;
+        }
+        if !parsed {
+            return Err(TimelineError::Parse("no QEMU trace events".to_string()));
+        }
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A simple trace of version 4 with the records `build` adds
+    fn simpletrace(build: impl FnOnce(&mut Vec<u8>)) -> Vec<u8> {
+        let mut trace = Vec::new();
+        for word in [u64::MAX, 0xf2b1_77cb_0aa4_29b4, 4] {
+            trace.extend(word.to_le_bytes());
+        }
+        build(&mut trace);
+        trace
+    }
+
+    fn mapping(trace: &mut Vec<u8>, id: u64, name: &str) {
+        trace.extend(RECORD_TYPE_MAPPING.to_le_bytes());
+        trace.extend(id.to_le_bytes());
+        trace.extend((name.len() as u32).to_le_bytes());
+        trace.extend(name.as_bytes());
+    }
+
+    fn record(trace: &mut Vec<u8>, id: u64, time: u64, args: &[u8]) {
+        trace.extend(1u64.to_le_bytes());
+        trace.extend(id.to_le_bytes());
+        trace.extend(time.to_le_bytes());
+        trace.extend((24 + args.len() as u32).to_le_bytes());
+        trace.extend(42u32.to_le_bytes());
+        trace.extend(args);
+    }
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(
+            parse_line("1234@1700000000.000100:memory_region_ops_read cpu 1 mr 0x55 addr 0x10"),
+            Some(Line {
+                tid: "1234",
+                time: 1_700_000_000_000_100_000,
+                name: "memory_region_ops_read",
+                args: "cpu 1 mr 0x55 addr 0x10",
+            })
+        );
+        // Events without arguments, and lines indented by the terminal
+        assert_eq!(
+            parse_line("  1234@1.5:vm_stop  "),
+            Some(Line {
+                tid: "1234",
+                time: 1_500_000_000,
+                name: "vm_stop",
+                args: "",
+            })
+        );
+        // The messages of QEMU itself, and lines without a timestamp or event name
+        assert_eq!(
+            parse_line("qemu-system-x86_64: warning: host doesn't support"),
+            None
+        );
+        assert_eq!(parse_line("1234@1700000000:kvm_run_exit reason 2"), None);
+        assert_eq!(parse_line("tid@1.0:kvm_run_exit reason 2"), None);
+        assert_eq!(parse_line("1234@1.0:kvm-run-exit reason 2"), None);
+        assert_eq!(parse_line("1234@1.0: reason 2"), None);
+
+        assert_eq!(vcpu("cpu_index 3, type 0xae80, arg (nil)"), Some(3));
+        assert_eq!(vcpu("cpu=1 addr 0x10"), Some(1));
+        assert_eq!(vcpu("vcpu: 2"), Some(2));
+        assert_eq!(vcpu("cpu_id 0x1"), None);
+        assert_eq!(vcpu("vdev 0x55d0 n 0 vq 0x55e0"), None);
+        assert_eq!(vcpu("cpu"), None);
+        assert_eq!(row("virtio_queue_notify", None), "virtio");
+        assert_eq!(row("virtio_queue_notify", Some(2)), "vCPU 2");
+        assert_eq!(row("exit", None), "exit");
+
+        let events = parse_trace_events(
+            "\
+# Comment(int ignored)
+disable kvm_run_exit(int cpu_index, uint32_t reason) \"cpu_index %d, reason %d\"
+virtio_set_status(void *vdev, uint8_t val) \"vdev %p val %u\"
+qemu_name(const char *name, size_t len, unsigned long flags) \"name %s\"
+vm_stop(void) \"\"
+not an event
+",
+        );
+        let kinds = |name: &str| -> Vec<(&str, ArgKind)> {
+            events[name]
+                .iter()
+                .map(|arg| (arg.name.as_str(), arg.kind))
+                .collect()
+        };
+        assert_eq!(events.len(), 4);
+        assert_eq!(
+            kinds("kvm_run_exit"),
+            [
+                ("cpu_index", ArgKind::Signed),
+                ("reason", ArgKind::Unsigned)
+            ]
+        );
+        assert_eq!(
+            kinds("virtio_set_status"),
+            [("vdev", ArgKind::Pointer), ("val", ArgKind::Unsigned)]
+        );
+        assert_eq!(
+            kinds("qemu_name"),
+            [
+                ("name", ArgKind::String),
+                ("len", ArgKind::Unsigned),
+                ("flags", ArgKind::Unsigned)
+            ]
+        );
+        assert_eq!(kinds("vm_stop"), []);
+    }
+
+    #[test]
+    fn test_from_qemu_trace() {
+        let trace = "\
+1234@1700000000.000100:kvm_vcpu_ioctl cpu_index 0, type 0xae80, arg (nil)
+1234@1700000000.000150:kvm_run_exit cpu_index 0, reason 2
+1235@1700000000.000200:memory_region_ops_write cpu 1 mr 0x55 addr 0x10 value 0x1 size 4 name 'virtio-pci'
+kvm_run_exit cpu_index 1, reason 3
+1236@1700000000.000300:virtio_queue_notify vdev 0x55d0 n 0 vq 0x55e0
+";
+        assert!(QemuImporter.sniff(trace.as_bytes()));
+        assert!(!QemuImporter.sniff(b"qemu-system-x86_64: warning: host doesn't support\n"));
+        let timeline = Timeline::from_qemu_trace(trace.as_bytes()).unwrap();
+        let events: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name(),
+                    event.location(),
+                    event.start_time() - 1_700_000_000_000_000_000,
+                )
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("kvm_vcpu_ioctl", "vCPU 0", 100_000),
+                ("kvm_run_exit", "vCPU 0", 150_000),
+                ("memory_region_ops_write", "vCPU 1", 200_000),
+                ("virtio_queue_notify", "virtio", 300_000),
+            ]
+        );
+        let (_, exit) = timeline.events().nth(1).unwrap();
+        assert_eq!(exit.metadata()["args"], "cpu_index 0, reason 2");
+        assert_eq!(exit.metadata()["tid"], "1234");
+
+        // Without `-msg timestamp=on` the log backend writes no thread or time
+        assert!(matches!(
+            Timeline::from_qemu_trace("kvm_run_exit cpu_index 1, reason 3\n".as_bytes()),
+            Err(TimelineError::Parse(_))
+        ));
+        assert!(matches!(
+            Timeline::from_qemu_trace(&b""[..]),
+            Err(TimelineError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_qemu_simpletrace() {
+        let trace = simpletrace(|trace| {
+            mapping(trace, 1, "virtio_set_status");
+            mapping(trace, 2, "kvm_run_exit");
+            let mut args = 0x55d0u64.to_le_bytes().to_vec();
+            args.extend(7u64.to_le_bytes());
+            record(trace, 1, 100, &args);
+            let mut args = (-1i64).to_le_bytes().to_vec();
+            args.extend(2u64.to_le_bytes());
+            record(trace, 2, 200, &args);
+            record(trace, DROPPED_EVENT_ID, 250, &3u64.to_le_bytes());
+            // An event without a mapping
+            record(trace, 3, 300, &[]);
+        });
+        let events = "\
+virtio_set_status(void *vdev, uint8_t val) \"vdev %p val %u\"
+kvm_run_exit(int cpu_index, uint32_t reason) \"cpu_index %d, reason %d\"
+";
+        assert!(QemuImporter.sniff(&trace));
+        let timeline = Timeline::from_qemu_simpletrace(&trace[..], events).unwrap();
+        let rows: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| (event.name(), event.location(), event.start_time()))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("virtio_set_status", "virtio", 100),
+                ("kvm_run_exit", "kvm", 200),
+                ("event 3", "event 3", 300),
+            ]
+        );
+        let (_, status) = timeline.events().next().unwrap();
+        assert_eq!(status.metadata()["vdev"], "0x55d0");
+        assert_eq!(status.metadata()["val"], "7");
+        assert_eq!(status.metadata()["tid"], "42");
+        let (_, exit) = timeline.events().nth(1).unwrap();
+        assert_eq!(exit.metadata()["cpu_index"], "-1");
+        assert_eq!(exit.metadata()["reason"], "2");
+
+        let timeline = Timeline::from_qemu_simpletrace(&trace[..], "").unwrap();
+        assert_eq!(timeline.categories(), ["event 3", "kvm", "virtio"]);
+        assert_eq!(timeline.events().next().unwrap().1.metadata().len(), 1);
+
+        // Strings have their length first, and a cpu argument puts the event on its vCPU
+        let names = simpletrace(|trace| {
+            mapping(trace, 1, "qemu_name");
+            let mut args = 3u32.to_le_bytes().to_vec();
+            args.extend(b"abc");
+            args.extend(1u64.to_le_bytes());
+            record(trace, 1, 100, &args);
+            // Arguments that are shorter than the trace-events file says
+            record(trace, 1, 200, &5u32.to_le_bytes());
+        });
+        let events = "qemu_name(const char *name, int cpu) \"name %s\"";
+        let timeline = Timeline::from_qemu_simpletrace(&names[..], events).unwrap();
+        let rows: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| (event.location(), event.metadata().len()))
+            .collect();
+        assert_eq!(rows, [("vCPU 1", 3), ("qemu", 1)]);
+        let (_, first) = timeline.events().next().unwrap();
+        assert_eq!(first.metadata()["name"], "abc");
+    }
+
+    #[test]
+    fn test_from_qemu_simpletrace_cut_off() {
+        // QEMU killed in the middle of a record leaves the records before it
+        let mut trace = simpletrace(|trace| {
+            mapping(trace, 1, "vm_stop");
+            record(trace, 1, 100, &[]);
+        });
+        trace.extend(1u64.to_le_bytes());
+        trace.extend(1u64.to_le_bytes());
+        let timeline = Timeline::from_qemu_simpletrace(&trace[..], "").unwrap();
+        assert_eq!(timeline.time_range(), Some((100, 100)));
+
+        // A length shorter than the fields of a record ends the trace too
+        let mut trace = simpletrace(|trace| record(trace, 1, 100, &[]));
+        trace.extend(1u64.to_le_bytes());
+        trace.extend(1u64.to_le_bytes());
+        trace.extend(200u64.to_le_bytes());
+        trace.extend(8u32.to_le_bytes());
+        trace.extend(42u32.to_le_bytes());
+        let timeline = Timeline::from_qemu_simpletrace(&trace[..], "").unwrap();
+        assert_eq!(timeline.time_range(), Some((100, 100)));
+
+        // Other versions, logs, and traces of only dropped events are errors
+        let mut trace = simpletrace(|trace| record(trace, 1, 100, &[]));
+        trace[16] = 3;
+        assert!(matches!(
+            Timeline::from_qemu_simpletrace(&trace[..], ""),
+            Err(TimelineError::Parse(_))
+        ));
+        assert!(matches!(
+            Timeline::from_qemu_simpletrace(&b"1234@1.0:vm_stop"[..], ""),
+            Err(TimelineError::Parse(_))
+        ));
+        let trace = simpletrace(|trace| {
+            mapping(trace, 1, "vm_stop");
+            record(trace, DROPPED_EVENT_ID, 100, &1u64.to_le_bytes());
+        });
+        assert!(matches!(
+            Timeline::from_qemu_simpletrace(&trace[..], ""),
+            Err(TimelineError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use crate::{Event, EventId, TimeValue, Timeline};
+
+// An index of the events of a timeline, built on the first query after the events change
+pub(crate) struct EventIndex<T> {
+    // Indices into the events, sorted by start time and then the rest of the draw order
+    by_start: Vec<usize>,
+    // The latest end time of the events up to each position of `by_start`
+    max_end: Vec<T>,
+    // Indices into the events of each location, sorted by start time
+    by_location: HashMap<String, Vec<usize>>,
+}
+
+impl<T: TimeValue> EventIndex<T> {
+    fn new(events: &[Event<T>]) -> Self {
+        // In the order events are drawn, so queries list events like they appear
+        let mut by_start: Vec<usize> = (0..events.len()).collect();
+        by_start.sort_by(|&a, &b| events[a].draw_order(&events[b]));
+        let mut max_end = Vec::with_capacity(by_start.len());
+        let mut by_location: HashMap<String, Vec<usize>> = HashMap::new();
+        for &index in &by_start {
+            let event = &events[index];
+            let end = match max_end.last() {
+                Some(&end) if end > event.end_time => end,
+                _ => event.end_time,
+            };
+            max_end.push(end);
+            by_location
+                .entry(event.location.clone())
+                .or_default()
+                .push(index);
+        }
+        EventIndex {
+            by_start,
+            max_end,
+            by_location,
+        }
+    }
+}
+
+impl<T: TimeValue> Timeline<T> {
+    // The index of the events, building it if the events changed since the last query
+    fn index(&self) -> &EventIndex<T> {
+        self.index.get_or_init(|| EventIndex::new(&self.events))
+    }
+
+    // Throw away the index after the events changed, and the layout of the rows they are on
+    pub(crate) fn invalidate_index(&mut self) {
+        self.index.take();
+        self.invalidate_layout();
+    }
+
+    /// The events that overlap the time range from `start` to `end`, sorted by start time
+    ///
+    /// An event overlaps the range if it starts before or at `end` and ends at or after `start`,
+    /// so events touching the range are included.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("read".to_string(), 0, 5, "CPU 0".to_string());
+    /// timeline.add_event("write".to_string(), 10, 15, "CPU 1".to_string());
+    /// let events = timeline.events_in_range(4, 8);
+    /// assert_eq!(events.len(), 1);
+    /// assert_eq!(events[0].1.name(), "read");
+    /// # }
+    /// ```
+    pub fn events_in_range(&self, start: T, end: T) -> Vec<(EventId, &Event<T>)> {
+        let index = self.index();
+        // Events from `last` on start after the range, and the latest end before `first` is
+        // before the range, so only the events in between can overlap it
+        let last = index
+            .by_start
+            .partition_point(|&i| self.events[i].start_time <= end);
+        let first = index.max_end[..last].partition_point(|&max_end| max_end < start);
+        index.by_start[first..last]
+            .iter()
+            .filter(|&&i| self.events[i].end_time >= start)
+            .map(|&i| (self.event_ids[i], &self.events[i]))
+            .collect()
+    }
+
+    /// The events on the row of `location`, sorted by start time
+    pub fn events_for_location(&self, location: &str) -> Vec<(EventId, &Event<T>)> {
+        match self.index().by_location.get(location) {
+            Some(indices) => indices
+                .iter()
+                .map(|&i| (self.event_ids[i], &self.events[i]))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queries() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1".to_string(), 0, 100, "Location 1".to_string());
+        timeline.add_event("Event 2".to_string(), 10, 20, "Location 2".to_string());
+        let late = timeline.add_event("Event 3".to_string(), 30, 40, "Location 1".to_string());
+        timeline.add_event("Event 4".to_string(), 5, 6, "Location 2".to_string());
+
+        let names = |events: Vec<(EventId, &Event)>| {
+            events
+                .iter()
+                .map(|(_, event)| event.name().to_string())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            names(timeline.events_in_range(15, 30)),
+            ["Event 1", "Event 2", "Event 3"]
+        );
+        assert_eq!(names(timeline.events_in_range(50, 60)), ["Event 1"]);
+        assert!(timeline.events_in_range(200, 300).is_empty());
+        assert_eq!(
+            names(timeline.events_for_location("Location 2")),
+            ["Event 4", "Event 2"]
+        );
+        assert!(timeline.events_for_location("Location 3").is_empty());
+
+        // The index follows changes to the events
+        timeline.set_event_end(late, 70);
+        assert_eq!(timeline.events_in_range(60, 60).len(), 2);
+        timeline.remove_event(late);
+        assert_eq!(
+            names(timeline.events_for_location("Location 1")),
+            ["Event 1"]
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use resvg::tiny_skia::{Color, Pixmap, Transform};
+use resvg::usvg::{Options, Tree};
+
+use crate::{write_file, SaveOptions, TimeValue, Timeline, TimelineError};
+
+impl<T: TimeValue> Timeline<T> {
+    /// Save the timeline to a PNG file
+    ///
+    /// The timeline is rendered like `save` does and rasterized with `resvg`, at `scale` pixels per
+    /// SVG pixel, so a scale of 2 gives an image twice as wide and high as the SVG. The image has a
+    /// white background, and text is drawn with the fonts installed on the system. The file is
+    /// written atomically like `save` writes it, or to stdout if `filename` is "-". This returns
+    /// `TimelineError::Raster` if the image can't be drawn, like when `scale` makes it empty or too
+    /// big.
+    pub fn save_png(
+        &self,
+        filename: impl AsRef<std::path::Path>,
+        scale: f32,
+    ) -> Result<(), TimelineError> {
+        let png = self.png(scale)?;
+        write_file(filename.as_ref(), SaveOptions::default(), |writer| {
+            writer.write_all(&png)
+        })?;
+        Ok(())
+    }
+
+    // The timeline rasterized at `scale` and encoded as PNG
+    fn png(&self, scale: f32) -> Result<Vec<u8>, TimelineError> {
+        let svg = self.document(&self.colormap)?.to_string();
+        let mut options = Options::default();
+        options.fontdb_mut().load_system_fonts();
+        let tree =
+            Tree::from_str(&svg, &options).map_err(|e| TimelineError::Raster(e.to_string()))?;
+        let size = tree.size();
+        let width = (size.width() * scale).ceil();
+        let height = (size.height() * scale).ceil();
+        let mut pixmap = (scale.is_finite() && scale > 0.0)
+            .then(|| Pixmap::new(width as u32, height as u32))
+            .flatten()
+            .ok_or_else(|| {
+                TimelineError::Raster(format!("can't draw a {}x{} image", width, height))
+            })?;
+        pixmap.fill(Color::WHITE);
+        resvg::render(
+            &tree,
+            Transform::from_scale(scale, scale),
+            &mut pixmap.as_mut(),
+        );
+        pixmap
+            .encode_png()
+            .map_err(|e| TimelineError::Raster(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_png() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1", 0, 10, "Location 1");
+        let dir = std::env::temp_dir().join(format!("timeline-png-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("timeline.png");
+
+        timeline.save_png(&path, 2.0).unwrap();
+        let png = std::fs::read(&path).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        let pixmap = Pixmap::decode_png(&png).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (4000, 82));
+
+        assert!(matches!(
+            timeline.save_png(&path, 0.0),
+            Err(TimelineError::Raster(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
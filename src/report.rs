@@ -0,0 +1,204 @@
+use std::fmt::Write as _;
+use std::io::Write;
+
+use crate::html::escape;
+use crate::{TimeValue, Timeline, TimelineError};
+
+// How many events the table of the longest events lists
+const LONGEST_EVENTS: usize = 10;
+
+// A color as a value of the style attribute of its swatch, or gray for anything that isn't a
+// color name, hex color or color function, which could add declarations or load a URL
+fn css_color(color: &str) -> &str {
+    let plain = color
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || b"#(),.% -".contains(&b));
+    let function = match color.split_once('(') {
+        Some((name, _)) => ["rgb", "rgba", "hsl", "hsla"].contains(&name),
+        None => true,
+    };
+    if !color.is_empty() && plain && function {
+        color
+    } else {
+        "gray"
+    }
+}
+
+const REPORT_STYLE: &str = r#"
+.report { padding: 0 8px 8px; }
+.report h2 { font-size: 16px; margin: 16px 0 4px; }
+.legend { list-style: none; padding: 0; margin: 0; }
+.legend li { display: inline-block; margin-right: 16px; }
+.swatch { display: inline-block; width: 10px; height: 10px; margin-right: 4px; }
+.report table { border-collapse: collapse; }
+.report th, .report td { padding: 2px 8px; border-bottom: 1px solid lightgray; }
+.report th { text-align: left; }
+.report td.number { text-align: right; }
+"#;
+
+impl<T: TimeValue> Timeline<T> {
+    /// Write a report of the timeline as a single HTML file
+    ///
+    /// The report is a complete artifact to attach to a bug report: the page of `write_html`,
+    /// with the interactive timeline, followed by a legend of the colors of the event names, a
+    /// table of the number, total, mean and longest duration of the events of every name, and a
+    /// table of the longest events. Only drawn events are included, see `set_event_filter`.
+    /// Durations are in the units of the timeline, and the headings are taken from `Strings`.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("read", 0, 5, "CPU 0");
+    /// let mut output = Vec::new();
+    /// timeline.write_report(&mut output).unwrap();
+    /// assert!(String::from_utf8(output).unwrap().contains("Longest events"));
+    /// # }
+    /// ```
+    pub fn write_report<W: Write>(&self, writer: W) -> Result<(), TimelineError> {
+        let strings = &self.strings;
+        let unit = escape(strings.unit(&self.units));
+        let stats = self.name_stats();
+        // Writing to a String can't fail
+        let mut body = String::from("<div class=\"report\">\n");
+
+        let _ = writeln!(
+            body,
+            "<h2>{}</h2>\n<ul class=\"legend\">",
+            escape(&strings.report_legend)
+        );
+        for name in stats.keys() {
+            let color = self
+                .colormap
+                .get(*name)
+                .map_or("gray", |color| css_color(color));
+            let _ = writeln!(
+                body,
+                "<li><span class=\"swatch\" style=\"background: {}\"></span>{}</li>",
+                escape(color),
+                escape(name)
+            );
+        }
+        body.push_str("</ul>\n");
+
+        let _ = writeln!(
+            body,
+            "<h2>{}</h2>\n<table>\n<tr><th>{}</th><th>{}</th><th>{} ({})</th><th>{} ({})</th>\
+             <th>{} ({})</th></tr>",
+            escape(&strings.report_durations),
+            escape(&strings.report_name),
+            escape(&strings.tooltip_count),
+            escape(&strings.tooltip_total),
+            unit,
+            escape(&strings.tooltip_mean),
+            unit,
+            escape(&strings.tooltip_max),
+            unit
+        );
+        for (name, stats) in &stats {
+            let _ = writeln!(
+                body,
+                "<tr><td>{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td>\
+                 <td class=\"number\">{}</td><td class=\"number\">{}</td></tr>",
+                escape(name),
+                stats.count,
+                stats.total,
+                stats.mean,
+                stats.max
+            );
+        }
+        body.push_str("</table>\n");
+
+        let mut longest: Vec<_> = self
+            .events
+            .iter()
+            .filter(|event| self.is_drawn(event))
+            .map(|event| {
+                (
+                    event.end_time.offset_from(event.start_time, self.units),
+                    event,
+                )
+            })
+            .collect();
+        // Longest first, and of equally long events the earliest
+        longest.sort_by(|(a, a_event), (b, b_event)| {
+            b.total_cmp(a).then(
+                a_event
+                    .start_time
+                    .partial_cmp(&b_event.start_time)
+                    .unwrap_or(std::cmp::Ordering::Equal),
+            )
+        });
+        let _ = writeln!(
+            body,
+            "<h2>{}</h2>\n<table>\n<tr><th>{}</th><th>{}</th><th>{} ({})</th>\
+             <th>{} ({})</th></tr>",
+            escape(&strings.report_longest),
+            escape(&strings.report_name),
+            escape(&strings.tooltip_location),
+            escape(&strings.tooltip_start),
+            unit,
+            escape(&strings.tooltip_duration),
+            unit
+        );
+        for (duration, event) in longest.into_iter().take(LONGEST_EVENTS) {
+            let _ = writeln!(
+                body,
+                "<tr><td>{}</td><td>{}</td><td class=\"number\">{}</td>\
+                 <td class=\"number\">{}</td></tr>",
+                escape(&event.name),
+                escape(&event.location),
+                event.start_time.offset_from(self.start_time, self.units),
+                duration
+            );
+        }
+        body.push_str("</table>\n</div>\n");
+
+        self.write_page(writer, REPORT_STYLE, &body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_css_color() {
+        assert_eq!(css_color("red"), "red");
+        assert_eq!(css_color("#a0b1c2"), "#a0b1c2");
+        assert_eq!(css_color("hsl(0, 80%, 45%)"), "hsl(0, 80%, 45%)");
+        assert_eq!(css_color("red; background-image: url(x)"), "gray");
+        assert_eq!(css_color("url(x.png)"), "gray");
+        assert_eq!(css_color("red\" onclick=\"x"), "gray");
+        assert_eq!(css_color(""), "gray");
+    }
+
+    #[test]
+    fn test_write_report() {
+        let mut timeline = Timeline::default();
+        for i in 0..12 {
+            timeline.add_event("read", i * 10, i * 10 + i, "CPU 0");
+        }
+        timeline.add_event("<write>", 5, 105, "CPU 1");
+
+        let mut output = Vec::new();
+        timeline.write_report(&mut output).unwrap();
+        let html = String::from_utf8(output).unwrap();
+        assert!(html.contains("<div id=\"timeline\">\n<svg"));
+        assert!(html.contains("</span>&lt;write&gt;</li>"));
+        assert!(html.contains(
+            "<tr><td>read</td><td class=\"number\">12</td><td class=\"number\">66</td>\
+             <td class=\"number\">5.5</td><td class=\"number\">11</td></tr>"
+        ));
+        // The header and the ten longest events
+        let longest = &html[html.find("Longest events").unwrap()..];
+        assert_eq!(longest.matches("<tr>").count(), 11);
+        assert!(longest.contains(
+            "<tr><td>&lt;write&gt;</td><td>CPU 1</td><td class=\"number\">5</td>\
+             <td class=\"number\">100</td></tr>\n<tr><td>read</td><td>CPU 0</td>\
+             <td class=\"number\">110</td><td class=\"number\">11</td></tr>"
+        ));
+    }
+}
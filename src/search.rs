@@ -0,0 +1,170 @@
+use crate::{Event, EventId, TimeValue, Timeline};
+
+/// Options for `Timeline::find_events_with`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Also match text that contains the characters of the query in order, with other characters
+    /// in between, like "rdblk" matching "read_block"
+    pub fuzzy: bool,
+    /// Match upper and lower case letters exactly instead of ignoring case
+    pub case_sensitive: bool,
+}
+
+/// An event found by `Timeline::find_events`
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventMatch<'a, T = i64> {
+    /// The handle of the event
+    pub id: EventId,
+    /// The event, which has its times and location
+    pub event: &'a Event<T>,
+    /// What matched, "name" or the key of the matching metadata entry
+    pub field: String,
+    /// How well the query matched, 1.0 for a substring and less for looser fuzzy matches
+    pub score: f64,
+}
+
+impl<T: TimeValue> Timeline<T> {
+    /// Find events by name or metadata
+    ///
+    /// This returns the events whose name, or any metadata key or value, contains `query`,
+    /// ignoring case, in the order they were added. See `find_events_with` for fuzzy matching.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("read_block".to_string(), 0, 5, "CPU 0".to_string());
+    /// timeline.add_event("write_block".to_string(), 5, 7, "CPU 1".to_string());
+    /// let matches = timeline.find_events("READ");
+    /// assert_eq!(matches.len(), 1);
+    /// assert_eq!(matches[0].event.location(), "CPU 0");
+    /// # }
+    /// ```
+    pub fn find_events(&self, query: &str) -> Vec<EventMatch<'_, T>> {
+        self.find_events_with(query, SearchOptions::default())
+    }
+
+    /// Find events by name or metadata with options
+    ///
+    /// This is the same as `find_events`, with the matching described by `options`. The best
+    /// matches come first, ties are kept in the order the events were added. An event is
+    /// returned once, for the field it matches best.
+    pub fn find_events_with(&self, query: &str, options: SearchOptions) -> Vec<EventMatch<'_, T>> {
+        let normalize = |text: &str| {
+            if options.case_sensitive {
+                text.to_string()
+            } else {
+                text.to_lowercase()
+            }
+        };
+        let query = normalize(query);
+        let mut matches: Vec<EventMatch<'_, T>> = Vec::new();
+        for (index, event) in self.events.iter().enumerate() {
+            let fields = std::iter::once(("name", event.name.as_str())).chain(
+                event
+                    .metadata
+                    .iter()
+                    .flat_map(|(key, value)| [(key.as_str(), key.as_str()), (key, value)]),
+            );
+            let mut best: Option<(f64, &str)> = None;
+            for (field, text) in fields {
+                let score = match_score(&query, &normalize(text), options.fuzzy);
+                if let Some(score) = score {
+                    if best.is_none_or(|(best, _)| score > best) {
+                        best = Some((score, field));
+                    }
+                }
+            }
+            if let Some((score, field)) = best {
+                matches.push(EventMatch {
+                    id: self.event_ids[index],
+                    event,
+                    field: field.to_string(),
+                    score,
+                });
+            }
+        }
+        // Stable sort, so equally good matches stay in the order they were added
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches
+    }
+}
+
+// Score how well `query` matches `text`, or None if it doesn't. Fuzzy matches score the length of
+// the query over the length of the text they span, so tighter matches score higher.
+fn match_score(query: &str, text: &str, fuzzy: bool) -> Option<f64> {
+    if text.contains(query) {
+        return Some(1.0);
+    }
+    if !fuzzy || query.is_empty() {
+        return None;
+    }
+    let query: Vec<char> = query.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    // Try every start of the first character and keep the tightest span, a query of one
+    // character is always a substring match already
+    let mut best: Option<usize> = None;
+    for start in (0..text.len()).filter(|&i| text[i] == query[0]) {
+        let mut next = 1;
+        for (i, &c) in text.iter().enumerate().skip(start + 1) {
+            if next == query.len() {
+                break;
+            }
+            if c == query[next] {
+                next += 1;
+                if next == query.len() {
+                    let span = i - start + 1;
+                    best = Some(best.map_or(span, |best| best.min(span)));
+                }
+            }
+        }
+    }
+    best.map(|span| query.len() as f64 / span as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EventBuilder;
+
+    #[test]
+    fn test_find_events() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("read_block".to_string(), 0, 5, "CPU 0".to_string());
+        timeline.insert_event(
+            EventBuilder::new("write".to_string(), 5, 7, "CPU 1".to_string())
+                .metadata("path".to_string(), "/var/log/readme".to_string())
+                .build(),
+        );
+        timeline.add_event("rd".to_string(), 7, 8, "CPU 1".to_string());
+
+        let matches = timeline.find_events("read");
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].id, EventId(0));
+        assert_eq!(matches[1].field, "path");
+        assert!(timeline
+            .find_events_with(
+                "READ",
+                SearchOptions {
+                    case_sensitive: true,
+                    ..SearchOptions::default()
+                }
+            )
+            .is_empty());
+
+        let matches = timeline.find_events_with(
+            "rdblk",
+            SearchOptions {
+                fuzzy: true,
+                ..SearchOptions::default()
+            },
+        );
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].event.name(), "read_block");
+        assert_eq!(match_score("rb", "read_rb", true), Some(1.0));
+        assert_eq!(match_score("rk", "rack", true), Some(0.5));
+        assert_eq!(match_score("x", "rack", true), None);
+    }
+}
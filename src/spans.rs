@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use crate::import::new_timeline;
+use crate::{EventBuilder, Timeline, TimelineError};
+
+/// The rows spans of distributed traces are drawn on, by `Timeline::from_otlp`,
+/// `Timeline::from_jaeger` and `Timeline::from_zipkin`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SpanRows {
+    /// A row for every service, named after it
+    #[default]
+    Service,
+    /// A row for every kind of span, like "server" and "client"
+    Kind,
+}
+
+// A span of a distributed trace, as the span importers read it
+pub(crate) struct Span {
+    pub(crate) trace_id: String,
+    pub(crate) span_id: String,
+    pub(crate) parent_id: Option<String>,
+    // The trace and span ids of other spans this one follows from, besides its parent
+    pub(crate) links: Vec<(String, String)>,
+    pub(crate) name: String,
+    pub(crate) service: Option<String>,
+    // Like "server", or None for unspecified kinds
+    pub(crate) kind: Option<String>,
+    pub(crate) start: i64,
+    pub(crate) end: i64,
+    // The attributes or tags of the span
+    pub(crate) attributes: Vec<(String, String)>,
+}
+
+// Draw spans into a timeline, in the order they started, with a trigger from the row of every
+// parent or linked span to the row of each span on another row, when that span starts
+pub(crate) fn spans_timeline(
+    mut spans: Vec<Span>,
+    rows: SpanRows,
+) -> Result<Timeline, TimelineError> {
+    if spans.is_empty() {
+        return Err(TimelineError::Parse("no spans".to_string()));
+    }
+    spans.sort_by_key(|span| span.start);
+    let row = |span: &Span| match rows {
+        SpanRows::Service => span
+            .service
+            .clone()
+            .unwrap_or_else(|| "unknown service".to_string()),
+        SpanRows::Kind => span
+            .kind
+            .clone()
+            .unwrap_or_else(|| "unspecified".to_string()),
+    };
+    // Zipkin shares the id of a span between its client and server halves, its id leads to the
+    // half that starts first, the client
+    let mut rows: HashMap<(&str, &str), String> = HashMap::new();
+    for span in &spans {
+        rows.entry((span.trace_id.as_str(), span.span_id.as_str()))
+            .or_insert_with(|| row(span));
+    }
+
+    let mut timeline = new_timeline();
+    for span in &spans {
+        let location = &row(span);
+        let mut event = EventBuilder::new(
+            span.name.clone(),
+            span.start,
+            span.end.max(span.start),
+            location.clone(),
+        )
+        .metadata("trace_id".to_string(), span.trace_id.clone())
+        .metadata("span_id".to_string(), span.span_id.clone());
+        let fields = [
+            ("parent_id", &span.parent_id),
+            ("service", &span.service),
+            ("kind", &span.kind),
+        ];
+        for (key, value) in fields {
+            if let Some(value) = value {
+                event = event.metadata(key.to_string(), value.clone());
+            }
+        }
+        for (key, value) in &span.attributes {
+            event = event.metadata(key.clone(), value.clone());
+        }
+        timeline.insert_event(event.build());
+
+        let parent = span
+            .parent_id
+            .as_deref()
+            .map(|parent| (span.trace_id.as_str(), parent));
+        let links = span
+            .links
+            .iter()
+            .map(|(trace_id, span_id)| (trace_id.as_str(), span_id.as_str()));
+        let mut from: Vec<&String> = parent
+            .into_iter()
+            .chain(links)
+            .filter_map(|id| rows.get(&id))
+            .collect();
+        from.sort();
+        from.dedup();
+        for from in from {
+            if from != location {
+                timeline.add_trigger(from.clone(), location.clone(), span.start);
+            }
+        }
+    }
+    Ok(timeline)
+}
@@ -0,0 +1,364 @@
+use std::io::Read;
+
+use serde_json::Value;
+
+use crate::chrome::text;
+use crate::import::{new_timeline, sniff_text};
+use crate::{EventBuilder, Timeline, TimelineError, TraceImporter};
+
+// The file format of speedscope, see `Timeline::from_speedscope`
+pub(crate) struct SpeedscopeImporter;
+
+impl TraceImporter for SpeedscopeImporter {
+    fn format(&self) -> &str {
+        "speedscope"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        let text = sniff_text(data);
+        text.trim_start().starts_with('{')
+            && (text.contains("speedscope.app/file-format-schema.json")
+                || (text.contains("\"shared\"") && text.contains("\"profiles\"")))
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_speedscope(data)
+    }
+}
+
+// The nanoseconds in one of the unit of a profile, where values that aren't times, like bytes,
+// are taken as they are
+fn nanos_per(unit: &str) -> f64 {
+    match unit {
+        "microseconds" => 1e3,
+        "milliseconds" => 1e6,
+        "seconds" => 1e9,
+        _ => 1.0,
+    }
+}
+
+// Turns the frames that open and close on the row of a profile into events, innermost last
+struct Stack<'a> {
+    frames: &'a [Value],
+    row: String,
+    open: Vec<(usize, i64)>,
+}
+
+impl Stack<'_> {
+    fn open(&mut self, frame: usize, time: i64) {
+        self.open.push((frame, time));
+    }
+
+    // Close the innermost open `frame`, and the frames opened inside it, which a well-formed
+    // profile has closed already
+    fn close(&mut self, timeline: &mut Timeline, frame: usize, time: i64) {
+        if let Some(index) = self.open.iter().rposition(|(open, _)| *open == frame) {
+            self.close_to(timeline, index, time);
+        }
+    }
+
+    // Close the open frames until only `depth` are left
+    fn close_to(&mut self, timeline: &mut Timeline, depth: usize, time: i64) {
+        while self.open.len() > depth {
+            let Some((frame, start)) = self.open.pop() else {
+                break;
+            };
+            let info = self.frames.get(frame).unwrap_or(&Value::Null);
+            let name = match info["name"].as_str() {
+                Some(name) => name.to_string(),
+                None => format!("frame {}", frame),
+            };
+            let mut builder = EventBuilder::new(name, start, time.max(start), self.row.clone());
+            for key in ["file", "line", "col"] {
+                if let Some(value) = info.get(key).filter(|value| !value.is_null()) {
+                    builder = builder.metadata(key.to_string(), text(value));
+                }
+            }
+            timeline.insert_event(builder.build());
+        }
+    }
+}
+
+impl Timeline {
+    /// Read a speedscope file into a timeline
+    ///
+    /// This reads the JSON file format of speedscope, which many profilers export and
+    /// speedscope converts other formats to. Every profile in it gets a row, named after the
+    /// profile, like "thread 1", or "profile 1" for profiles without a name, by their number
+    /// from 1. The frames of evented profiles become events from when they are opened (`O`) to
+    /// when they are closed (`C`), nested on the row of their profile, and the stacks of sampled
+    /// profiles do too, laid out one after the other by their weights, with the frames that
+    /// samples in a row share drawn as one event. Events are named after their frame, with its
+    /// "file", "line" and "col" as their metadata, and frames that are never closed last until
+    /// the end value of their profile. Times are in nanoseconds, from the unit of each profile,
+    /// and values of other units, like bytes, are taken as they are.
+    ///
+    /// This returns `TimelineError::Parse` if the file isn't JSON of the expected shape or has
+    /// no profiles, and `TimelineError::Io` if reading it fails. `Timeline::import` detects
+    /// files of this format, as "speedscope".
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let file = r#"{
+    ///     "$schema": "https://www.speedscope.app/file-format-schema.json",
+    ///     "shared": {"frames": [{"name": "main"}, {"name": "parse"}]},
+    ///     "profiles": [{
+    ///         "type": "evented", "name": "thread 1", "unit": "microseconds",
+    ///         "startValue": 0, "endValue": 10,
+    ///         "events": [
+    ///             {"type": "O", "frame": 0, "at": 0},
+    ///             {"type": "O", "frame": 1, "at": 2},
+    ///             {"type": "C", "frame": 1, "at": 7},
+    ///             {"type": "C", "frame": 0, "at": 10}
+    ///         ]
+    ///     }]
+    /// }"#;
+    /// let timeline = Timeline::from_speedscope(file.as_bytes()).unwrap();
+    /// assert_eq!(timeline.categories(), ["thread 1"]);
+    /// assert_eq!(timeline.time_range(), Some((0, 10_000)));
+    /// # }
+    /// ```
+    pub fn from_speedscope(reader: impl Read) -> Result<Timeline, TimelineError> {
+        let file: Value =
+            serde_json::from_reader(reader).map_err(|e| TimelineError::Parse(e.to_string()))?;
+        let profiles = file["profiles"]
+            .as_array()
+            .filter(|profiles| !profiles.is_empty())
+            .ok_or_else(|| TimelineError::Parse("no profiles".to_string()))?;
+        let frames = file["shared"]["frames"]
+            .as_array()
+            .map_or(&[][..], Vec::as_slice);
+        let mut timeline = new_timeline();
+        for (index, profile) in profiles.iter().enumerate() {
+            let scale = nanos_per(profile["unit"].as_str().unwrap_or_default());
+            let nanos = |value: &Value| value.as_f64().map(|value| (value * scale).round() as i64);
+            let row = match profile["name"].as_str() {
+                Some(name) if !name.is_empty() => name.to_string(),
+                _ => format!("profile {}", index + 1),
+            };
+            let frame = |value: &Value| value.as_u64().map(|frame| frame as usize);
+            let start = nanos(&profile["startValue"]).unwrap_or(0);
+            let mut time = start;
+            let mut stack = Stack {
+                frames,
+                row,
+                open: Vec::new(),
+            };
+            match profile["type"].as_str() {
+                Some("evented") => {
+                    for event in profile["events"].as_array().into_iter().flatten() {
+                        let (Some(frame), Some(at)) = (frame(&event["frame"]), nanos(&event["at"]))
+                        else {
+                            continue;
+                        };
+                        time = time.max(at);
+                        match event["type"].as_str() {
+                            Some("O") => stack.open(frame, at),
+                            Some("C") => stack.close(&mut timeline, frame, at),
+                            _ => {}
+                        }
+                    }
+                }
+                Some("sampled") => {
+                    let weights = profile["weights"].as_array().map_or(&[][..], Vec::as_slice);
+                    let samples = profile["samples"].as_array().into_iter().flatten();
+                    for (sample, weight) in samples.zip(weights) {
+                        let sample: Vec<usize> = sample
+                            .as_array()
+                            .into_iter()
+                            .flatten()
+                            .filter_map(frame)
+                            .collect();
+                        let shared = stack
+                            .open
+                            .iter()
+                            .zip(&sample)
+                            .take_while(|((open, _), frame)| open == *frame)
+                            .count();
+                        stack.close_to(&mut timeline, shared, time);
+                        for frame in &sample[shared..] {
+                            stack.open(*frame, time);
+                        }
+                        time += nanos(weight).unwrap_or(0).max(0);
+                    }
+                }
+                _ => continue,
+            }
+            let end = nanos(&profile["endValue"]).map_or(time, |end| end.max(time));
+            stack.close_to(&mut timeline, 0, end);
+        }
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(file: &str) -> Vec<(String, String, i64, i64)> {
+        let timeline = Timeline::from_speedscope(file.as_bytes()).unwrap();
+        timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name().to_string(),
+                    event.location().to_string(),
+                    event.start_time(),
+                    event.end_time(),
+                )
+            })
+            .collect()
+    }
+
+    fn event(name: &str, row: &str, start: i64, end: i64) -> (String, String, i64, i64) {
+        (name.to_string(), row.to_string(), start, end)
+    }
+
+    #[test]
+    fn test_evented() {
+        let file = r#"{
+            "shared": {
+                "frames": [
+                    {"name": "main", "file": "main.rs", "line": 3, "col": null},
+                    {"name": "parse"},
+                    {"name": "lex", "file": "lex.rs", "line": 10, "col": 5},
+                    {}
+                ]
+            },
+            "profiles": [{
+                "type": "evented", "name": "thread 1", "unit": "milliseconds",
+                "startValue": 1, "endValue": 20,
+                "events": [
+                    {"type": "O", "frame": 0, "at": 1},
+                    {"type": "O", "frame": 1, "at": 2},
+                    {"type": "O", "frame": 2, "at": 3},
+                    {"type": "C", "frame": 1, "at": 5.5},
+                    {"type": "C", "frame": 2, "at": 5.6},
+                    {"type": "O", "frame": 3, "at": 6},
+                    {"type": "O", "at": 7},
+                    {"type": "X", "frame": 1, "at": 8}
+                ]
+            }]
+        }"#;
+        // Files that exporters write without the schema
+        assert!(SpeedscopeImporter.sniff(file.as_bytes()));
+        // Closing a frame closes the frames opened inside it, and frames that are never closed
+        // last until the end value
+        let ms = 1_000_000;
+        assert_eq!(
+            events(file),
+            [
+                event("lex", "thread 1", 3 * ms, 5_500_000),
+                event("parse", "thread 1", 2 * ms, 5_500_000),
+                event("frame 3", "thread 1", 6 * ms, 20 * ms),
+                event("main", "thread 1", ms, 20 * ms),
+            ]
+        );
+        let timeline = Timeline::from_speedscope(file.as_bytes()).unwrap();
+        let (_, lex) = timeline.events().next().unwrap();
+        assert_eq!(lex.metadata()["file"], "lex.rs");
+        assert_eq!(lex.metadata()["line"], "10");
+        assert_eq!(lex.metadata()["col"], "5");
+        let (_, main) = timeline.events().nth(3).unwrap();
+        assert_eq!(main.metadata().len(), 2);
+
+        // Profiles that run past their end value, or don't have one, end at their last event
+        let file = r#"{
+            "shared": {"frames": [{"name": "main"}]},
+            "profiles": [
+                {
+                    "type": "evented", "name": "late", "unit": "nanoseconds", "endValue": 5,
+                    "events": [{"type": "O", "frame": 0, "at": 2}, {"type": "O", "frame": 0, "at": 9}]
+                },
+                {
+                    "type": "evented", "name": "open", "unit": "nanoseconds",
+                    "events": [{"type": "O", "frame": 0, "at": 4}]
+                }
+            ]
+        }"#;
+        assert_eq!(
+            events(file),
+            [
+                event("main", "late", 9, 9),
+                event("main", "late", 2, 9),
+                event("main", "open", 4, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sampled() {
+        let file = r#"{
+            "shared": {"frames": [{"name": "main"}, {"name": "parse"}, {"name": "lex"}]},
+            "profiles": [{
+                "type": "sampled", "unit": "microseconds",
+                "startValue": 0, "endValue": 0.04,
+                "samples": [[0, 1], [0, 1, 2], [0, 2], [0, 2], [1]],
+                "weights": [0.01, 0.01, 0.005, 0.015]
+            }]
+        }"#;
+        // Frames that samples in a row share are one event, and samples without a weight are
+        // skipped
+        assert_eq!(
+            events(file),
+            [
+                event("lex", "profile 1", 10, 20),
+                event("parse", "profile 1", 0, 20),
+                event("lex", "profile 1", 20, 40),
+                event("main", "profile 1", 0, 40),
+            ]
+        );
+
+        // Weights of other units, like bytes, are taken as they are, and negative ones as 0
+        let file = r#"{
+            "shared": {"frames": [{"name": "alloc"}, {"name": "grow"}]},
+            "profiles": [{
+                "type": "sampled", "name": "heap", "unit": "bytes",
+                "samples": [[0], [1], [0]],
+                "weights": [1024, -5, 512]
+            }]
+        }"#;
+        assert_eq!(
+            events(file),
+            [
+                event("alloc", "heap", 0, 1024),
+                event("grow", "heap", 1024, 1024),
+                event("alloc", "heap", 1024, 1536),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_profiles() {
+        let file = r#"{
+            "shared": {"frames": [{"name": "main"}]},
+            "profiles": [
+                {"type": "unknown", "name": "skipped"},
+                {"type": "evented", "name": "", "events": [{"type": "O", "frame": 0, "at": 1}]},
+                {"type": "evented", "name": "named", "events": [{"type": "O", "frame": 0, "at": 2}]}
+            ]
+        }"#;
+        assert!(SpeedscopeImporter
+            .sniff(br#"{"$schema": "https://www.speedscope.app/file-format-schema.json"}"#));
+        assert!(!SpeedscopeImporter.sniff(br#"{"traceEvents": [], "profiles": []}"#));
+        // Profiles are numbered from 1, also counting the ones that are skipped
+        let timeline = Timeline::from_speedscope(file.as_bytes()).unwrap();
+        assert_eq!(timeline.categories(), ["named", "profile 2"]);
+
+        // Files need frames and profiles
+        for file in [
+            r#"{"profiles": []}"#,
+            r#"{"shared": {"frames": []}}"#,
+            r#"{"profiles": {}}"#,
+            "{",
+        ] {
+            assert!(matches!(
+                Timeline::from_speedscope(file.as_bytes()),
+                Err(TimelineError::Parse(_))
+            ));
+        }
+    }
+}
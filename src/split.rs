@@ -0,0 +1,153 @@
+use std::path::{Path, PathBuf};
+
+use crate::html::escape;
+use crate::{write_file, SaveOptions, TimeValue, Timeline, TimelineError};
+
+/// How `Timeline::save_split` splits the rows of a timeline into files
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Split {
+    /// A file for every group, see `Timeline::add_category_to_group`, and one for the rows
+    /// without a group
+    Groups,
+    /// A file for every row, along with its child rows
+    Categories,
+}
+
+// The page of `save_split` showing the files one below the other
+const INDEX_HEAD: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<style>
+body { margin: 0; }
+img { display: block; }
+</style>
+</head>
+<body>
+"#;
+
+const INDEX_TAIL: &str = "</body>\n</html>\n";
+
+impl<T: TimeValue> Timeline<T> {
+    /// Save the rows of the timeline into separate SVG files, and a page showing them
+    ///
+    /// A trace with hundreds of rows doesn't fit on one canvas. This saves the rows of every
+    /// group, or every row, depending on `split`, as "part-N.svg" in `dir`, numbered from 0 in
+    /// the order of the rows, along with an "index.html" page that shows the parts one below the
+    /// other. Every part is drawn like `write` draws the timeline with a category filter of its
+    /// rows, so all parts share the time axis and colors of the whole timeline, and triggers
+    /// between rows of different parts are left out. `dir` is created if it doesn't exist, and
+    /// every file is written atomically like `save` writes it. This returns the paths of the
+    /// parts, and fails like `save`.
+    ///
+    /// The category filter of the timeline is replaced while the parts are rendered, which is
+    /// why this takes the timeline mutably, and is restored afterwards. Rows it hides aren't in
+    /// any part.
+    pub fn save_split(
+        &mut self,
+        dir: impl AsRef<Path>,
+        split: Split,
+    ) -> Result<Vec<PathBuf>, TimelineError> {
+        let dir = dir.as_ref();
+        let options = SaveOptions { create_dirs: true };
+
+        // The name and rows of every part, in the order of the rows
+        let mut parts: Vec<(String, Vec<String>)> = Vec::new();
+        let rows = self.rows();
+        for category in rows.categories() {
+            let name = match split {
+                Split::Groups => self.category_group(category).unwrap_or_default(),
+                Split::Categories => self.category_ancestors(category).last().unwrap_or(category),
+            };
+            match parts.last_mut() {
+                Some((last, categories)) if last == name => categories.push(category.to_string()),
+                _ => parts.push((name.to_string(), vec![category.to_string()])),
+            }
+        }
+
+        let filter = self.replace_category_filter(None);
+        let mut rendered = Vec::new();
+        for (_, categories) in &parts {
+            self.replace_category_filter(Some(categories.iter().cloned().collect()));
+            let mut svg = Vec::new();
+            let result = self.write(&mut svg);
+            rendered.push(result.map(|()| svg));
+        }
+        self.replace_category_filter(filter);
+
+        let mut paths = Vec::new();
+        let mut index = INDEX_HEAD.to_string();
+        for (part, ((name, _), svg)) in parts.iter().zip(rendered).enumerate() {
+            let svg = svg?;
+            let file = format!("part-{}.svg", part);
+            let path = dir.join(&file);
+            write_file(&path, options, |writer| writer.write_all(&svg))?;
+            index.push_str(&format!(
+                "<img src=\"{}\" alt=\"{}\">\n",
+                file,
+                escape(name)
+            ));
+            paths.push(path);
+        }
+        index.push_str(INDEX_TAIL);
+        write_file(&dir.join("index.html"), options, |writer| {
+            writer.write_all(index.as_bytes())
+        })?;
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_split() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("read", 0, 10, "IO");
+        timeline.add_event("run", 2, 4, "a/CPU 0");
+        timeline.add_event("run", 4, 6, "a/CPU 1");
+        timeline.add_event("run", 6, 8, "b/CPU 0");
+        timeline.add_trigger("a/CPU 0", "b/CPU 0", 6);
+        for (category, group) in [("a/CPU 0", "a"), ("a/CPU 1", "a"), ("b/CPU 0", "b")] {
+            timeline.add_category_to_group(category.to_string(), group.to_string());
+        }
+        timeline.set_category_filter(Some(
+            ["IO", "a/CPU 0", "a/CPU 1"].map(String::from).to_vec(),
+        ));
+        let dir = std::env::temp_dir()
+            .join(format!("timeline-split-{}", std::process::id()))
+            .join("split");
+
+        let paths = timeline.save_split(&dir, Split::Groups).unwrap();
+        assert_eq!(
+            paths,
+            [0, 1].map(|part| dir.join(format!("part-{}.svg", part)))
+        );
+        let part = std::fs::read_to_string(&paths[1]).unwrap();
+        // The width of the whole timeline, and the rows of the group
+        assert!(part.contains(r#"width="2000""#));
+        assert!(part.contains("a/CPU 1") && !part.contains("IO"));
+        let index = std::fs::read_to_string(dir.join("index.html")).unwrap();
+        assert!(
+            index.contains("<img src=\"part-0.svg\" alt=\"\">\n<img src=\"part-1.svg\" alt=\"a\">")
+        );
+
+        // The filter of the timeline is back
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        assert!(!String::from_utf8(output).unwrap().contains("b/CPU 0"));
+        timeline.set_category_filter(None);
+        let paths = timeline.save_split(&dir, Split::Categories).unwrap();
+        assert_eq!(paths.len(), 4);
+        let part = std::fs::read_to_string(&paths[3]).unwrap();
+        assert!(part.contains("b/CPU 0") && !part.contains("trigger"));
+
+        // Names can't break out of the attribute they are in
+        timeline.add_event("run", 8, 9, "'\"><script>");
+        timeline.save_split(&dir, Split::Categories).unwrap();
+        let index = std::fs::read_to_string(dir.join("index.html")).unwrap();
+        assert!(index.contains(" alt=\"&#39;&quot;&gt;&lt;script&gt;\">"));
+        std::fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,187 @@
+use svg::node::element::{Group, Rectangle, Text, Title};
+
+use crate::{TimeValue, Timeline};
+
+// How far apart the entries of the state legend are, on top of the width of their names
+const LEGEND_SPACING: f64 = 24.0;
+
+impl<T: TimeValue> Timeline<T> {
+    /// Record that `track` is in `state` from `time` on
+    ///
+    /// State tracks show mutually exclusive states, like a thread being running, sleeping or
+    /// blocked. Every track gets a row of its own, named and sorted like the rows of events,
+    /// filled with a segment per state from each transition to the next, and from the last
+    /// transition to the end of the timeline. States are colored like event names, and a legend
+    /// of every state is drawn below the timeline. Transitions can be added in any order, and
+    /// extend the time range like events do.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_state_transition("thread 1".to_string(), 0, "Running".to_string());
+    /// timeline.add_state_transition("thread 1".to_string(), 5, "Sleeping".to_string());
+    /// timeline.add_state_transition("thread 1".to_string(), 9, "Running".to_string());
+    /// assert_eq!(timeline.categories(), ["thread 1"]);
+    /// # }
+    /// ```
+    pub fn add_state_transition(
+        &mut self,
+        track: impl Into<String>,
+        time: T,
+        state: impl Into<String>,
+    ) {
+        let (track, state) = (track.into(), state.into());
+        if time < self.start_time {
+            self.start_time = time;
+        }
+        if time > self.end_time {
+            self.end_time = time;
+        }
+        self.assign_color(&state);
+        self.state_tracks
+            .entry(track)
+            .or_default()
+            .push((time, state));
+        self.invalidate_layout();
+    }
+
+    // The transitions of a state track sorted by time
+    fn state_transitions(&self, track: &str) -> Option<Vec<&(T, String)>> {
+        let mut transitions: Vec<_> = self.state_tracks.get(track)?.iter().collect();
+        if transitions.is_empty() {
+            return None;
+        }
+        transitions.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        Some(transitions)
+    }
+
+    // The segments of the state track `track` on the row starting at `y` and `height` high, if it
+    // has any
+    pub(crate) fn state_track(&self, track: &str, y: f64, height: f64) -> Option<Group> {
+        let transitions = self.state_transitions(track)?;
+        let width = self.span() * self.scale();
+        let mut g = Group::new().set("class", "states").set("data-track", track);
+        for (i, (time, state)) in transitions.iter().map(|t| (t.0, &t.1)).enumerate() {
+            let x = self.time_x(time);
+            let end_x = transitions
+                .get(i + 1)
+                .map_or(width, |(next, _)| self.time_x(*next));
+            if end_x <= x {
+                continue;
+            }
+            let segment = Rectangle::new()
+                .set("x", x)
+                .set("y", y)
+                .set("width", end_x - x)
+                .set("height", height)
+                .set(
+                    "fill",
+                    self.colormap.get(state).map_or("gray", String::as_str),
+                )
+                .set("class", "state")
+                .set("data-state", state.as_str())
+                .add(Title::new(state.as_str()));
+            g = g.add(segment);
+        }
+        Some(g)
+    }
+
+    // Drop the transitions outside of `start` to `end`, moving the state the tracks are in at
+    // `start` there, see `crop`
+    pub(crate) fn crop_states(&mut self, start: T, end: T) {
+        for transitions in self.state_tracks.values_mut() {
+            transitions.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+            let current = transitions
+                .iter()
+                .rposition(|(time, _)| *time <= start)
+                .map(|i| transitions[i].1.clone());
+            transitions.retain(|(time, _)| *time > start && *time <= end);
+            if let Some(state) = current {
+                transitions.insert(0, (start, state));
+            }
+        }
+        self.state_tracks
+            .retain(|_, transitions| !transitions.is_empty());
+    }
+
+    // The legend of every state of the state tracks in `tracks`, sorted by name, on a row
+    // starting at `top`
+    pub(crate) fn state_legend(&self, tracks: &[&str], top: f64) -> Group {
+        let mut states: Vec<&str> = tracks
+            .iter()
+            .filter_map(|track| self.state_tracks.get(*track))
+            .flatten()
+            .map(|(_, state)| state.as_str())
+            .collect();
+        states.sort();
+        states.dedup();
+        let row_height = self.row_height as f64;
+        let size = row_height / 2.0;
+        let mut legend = Group::new().set("class", "state-legend");
+        let mut x = 0.0;
+        for state in states {
+            let swatch = Rectangle::new()
+                .set("x", x)
+                .set("y", top + (row_height - size) / 2.0)
+                .set("width", size)
+                .set("height", size)
+                .set(
+                    "fill",
+                    self.colormap.get(state).map_or("gray", String::as_str),
+                );
+            let name = Text::new(state.to_string())
+                .set("x", x + size + 4.0)
+                .set("y", top + row_height * 0.7)
+                .set("font-size", 10)
+                .set("fill", "black");
+            legend = legend.add(swatch).add(name);
+            // A rough width of the name at the font size of 10
+            x += size + LEGEND_SPACING + state.chars().count() as f64 * 6.0;
+        }
+        legend
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_tracks() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event".to_string(), 0, 10, "Location".to_string());
+        timeline.add_state_transition("thread".to_string(), 6, "Running".to_string());
+        timeline.add_state_transition("thread".to_string(), 2, "Blocked".to_string());
+        timeline.add_state_transition("thread".to_string(), 4, "Running".to_string());
+        assert_eq!(timeline.categories(), ["Location", "thread"]);
+        assert_eq!(timeline.time_range(), Some((0, 10)));
+        let render = |timeline: &Timeline| {
+            let mut output = Vec::new();
+            timeline.write(&mut output).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        let svg = render(&timeline);
+        assert_eq!(svg.matches("class=\"state\"").count(), 3);
+        assert!(svg.contains(r#"class="state" data-state="Blocked" fill="#));
+        assert!(svg.contains(r#"height="20" width="800" x="1200" y="41""#));
+        // The legend takes a row below the rows
+        assert!(svg.contains(r#"height="81""#));
+        assert!(svg.contains("Blocked\n</text>"));
+
+        timeline.crop(5, 10);
+        let transitions: Vec<_> = timeline.state_tracks["thread"].clone();
+        assert_eq!(
+            transitions,
+            [(5, "Running".to_string()), (6, "Running".to_string())]
+        );
+
+        timeline.set_category_filter(Some(vec!["Location".to_string()]));
+        let svg = render(&timeline);
+        assert!(!svg.contains("state-legend"));
+        assert!(svg.contains(r#"height="41""#));
+    }
+}
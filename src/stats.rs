@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use crate::{TimeValue, Timeline, TimelineError};
+
+/// Latency statistics of the triggers between two locations
+///
+/// The latency of a trigger between events, see `Timeline::add_trigger_between`, is the time
+/// from the end of the event it starts at to the start of the event it ends at, like the time
+/// from a wakeup to the woken task running. The latency of a trigger added with
+/// `Timeline::add_trigger_span` is the time from its start to its end. All times are in the
+/// units of the timeline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LatencyStats {
+    pub start_location: String,
+    pub end_location: String,
+    pub count: usize,
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+    /// The median latency
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+}
+
+// The nearest-rank percentile of sorted values
+fn percentile(sorted: &[f64], percent: f64) -> f64 {
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+impl<T: TimeValue> Timeline<T> {
+    /// Latency statistics of the triggers between events, per pair of locations
+    ///
+    /// The statistics are sorted by start and then end location. Triggers added with
+    /// `add_trigger` have no duration and aren't included, but those of `add_trigger_span` that
+    /// take time are.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// let wakeup = timeline.add_event("wakeup".to_string(), 0, 2, "CPU 0".to_string());
+    /// let run = timeline.add_event("run".to_string(), 5, 9, "CPU 1".to_string());
+    /// timeline.add_trigger_between(wakeup, run);
+    /// let stats = timeline.trigger_latency_stats();
+    /// assert_eq!(stats[0].mean, 3.0);
+    /// # }
+    /// ```
+    pub fn trigger_latency_stats(&self) -> Vec<LatencyStats> {
+        let mut latencies: BTreeMap<(&str, &str), Vec<f64>> = BTreeMap::new();
+        for link in &self.links {
+            let (Some(from), Some(to)) = (self.event(link.from), self.event(link.to)) else {
+                continue;
+            };
+            let latency = to.start_time.offset_from(from.end_time, self.units);
+            latencies
+                .entry((&from.location, &to.location))
+                .or_default()
+                .push(latency);
+        }
+        for trigger in &self.triggers {
+            if trigger.time != trigger.end_time {
+                latencies
+                    .entry((&trigger.start_location, &trigger.end_location))
+                    .or_default()
+                    .push(trigger.end_time.offset_from(trigger.time, self.units));
+            }
+        }
+        latencies
+            .into_iter()
+            .map(|((start_location, end_location), mut values)| {
+                values.sort_by(f64::total_cmp);
+                LatencyStats {
+                    start_location: start_location.to_string(),
+                    end_location: end_location.to_string(),
+                    count: values.len(),
+                    mean: values.iter().sum::<f64>() / values.len() as f64,
+                    min: values[0],
+                    max: values[values.len() - 1],
+                    p50: percentile(&values, 50.0),
+                    p90: percentile(&values, 90.0),
+                    p99: percentile(&values, 99.0),
+                }
+            })
+            .collect()
+    }
+
+    /// Write the trigger latency statistics as CSV
+    ///
+    /// The CSV has a header line and one line per pair of locations, see
+    /// `trigger_latency_stats`.
+    pub fn export_latency_csv<W: Write>(&self, mut writer: W) -> Result<(), TimelineError> {
+        writeln!(
+            writer,
+            "start_location,end_location,count,mean,min,max,p50,p90,p99"
+        )?;
+        for stats in self.trigger_latency_stats() {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{},{}",
+                csv_field(&stats.start_location),
+                csv_field(&stats.end_location),
+                stats.count,
+                stats.mean,
+                stats.min,
+                stats.max,
+                stats.p50,
+                stats.p90,
+                stats.p99
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Quote a CSV field if it contains separators, quotes or line breaks, doubling its quotes
+pub(crate) fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_stats() {
+        let mut timeline = Timeline::default();
+        for latency in 1..=10 {
+            let start = latency * 100;
+            let from = timeline.add_event("wakeup".to_string(), start, start + 1, "CPU 0");
+            let to = timeline.add_event(
+                "run".to_string(),
+                start + 1 + latency,
+                start + 50,
+                "CPU, 1".to_string(),
+            );
+            timeline.add_trigger_between(from, to);
+        }
+        timeline.add_trigger("CPU 0".to_string(), "CPU, 1".to_string(), 3);
+
+        let stats = timeline.trigger_latency_stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].count, 10);
+        assert_eq!(stats[0].mean, 5.5);
+        assert_eq!((stats[0].min, stats[0].max), (1.0, 10.0));
+        assert_eq!((stats[0].p50, stats[0].p90, stats[0].p99), (5.0, 9.0, 10.0));
+
+        let mut output = Vec::new();
+        timeline.export_latency_csv(&mut output).unwrap();
+        let csv = String::from_utf8(output).unwrap();
+        assert_eq!(
+            csv.lines().nth(1).unwrap(),
+            "CPU 0,\"CPU, 1\",10,5.5,1,10,5,9,10"
+        );
+    }
+}
@@ -0,0 +1,493 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+
+use crate::ftrace::parse_time;
+use crate::import::{new_timeline, sniff_text};
+use crate::{EventBuilder, Timeline, TimelineError, TraceImporter};
+
+// The output of `strace` with timestamps, see `Timeline::from_strace`
+pub(crate) struct StraceImporter;
+
+impl TraceImporter for StraceImporter {
+    fn format(&self) -> &str {
+        "strace"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        sniff_text(data)
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .and_then(parse_line)
+            .is_some_and(|line| !matches!(line.kind, Kind::Resumed { .. }))
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_strace(data)
+    }
+}
+
+// A line of strace output, like
+// "1234 1700000000.000100 openat(AT_FDCWD, \"/etc/passwd\", O_RDONLY) = 3 <0.000012>"
+#[derive(Debug, PartialEq)]
+struct Line<'a> {
+    // The pid, which lines only have with `-f`
+    pid: Option<&'a str>,
+    time: i64,
+    kind: Kind<'a>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Kind<'a> {
+    // A whole syscall, with what it returned and how long it took with `-T`
+    Syscall {
+        name: &'a str,
+        args: &'a str,
+        result: &'a str,
+        duration: Option<i64>,
+    },
+    // A syscall that another process interrupted the line of, which a later line resumes
+    Unfinished {
+        name: &'a str,
+        args: &'a str,
+    },
+    Resumed {
+        name: &'a str,
+        result: &'a str,
+        duration: Option<i64>,
+    },
+    // A signal, like "--- SIGCHLD {si_signo=SIGCHLD, ...} ---"
+    Signal(&'a str),
+    // The end of a process, like "+++ exited with 0 +++"
+    Exit(&'a str),
+}
+
+// A time of `-ttt`, in seconds since the epoch, or of `-tt`, like "10:30:01.123456", since
+// midnight
+fn parse_timestamp(text: &str) -> Option<i64> {
+    let mut parts = text.split(':');
+    let (Some(hours), Some(minutes), Some(seconds)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return text.contains('.').then(|| parse_time(text)).flatten();
+    };
+    let hours: i64 = hours.parse().ok()?;
+    let minutes = hours.checked_mul(60)?.checked_add(minutes.parse().ok()?)?;
+    minutes
+        .checked_mul(60_000_000_000)?
+        .checked_add(parse_time(seconds)?)
+}
+
+// The result of a syscall and the time it took, from the end of its line like
+// "= -1 ENOENT (No such file or directory) <0.000010>"
+fn result(text: &str) -> (&str, Option<i64>) {
+    let text = text.trim();
+    let result = text.strip_prefix('=').unwrap_or(text).trim_start();
+    match result.rsplit_once(" <") {
+        Some((result, duration)) if duration.ends_with('>') => (
+            result.trim_end(),
+            parse_time(duration.trim_end_matches('>')),
+        ),
+        _ => (result, None),
+    }
+}
+
+fn parse_line(line: &str) -> Option<Line<'_>> {
+    let mut rest = line.trim();
+    // `-f` prefixes lines with their pid, or "[pid 1234]" when writing to a terminal
+    let mut pid = None;
+    if let Some(after) = rest.strip_prefix("[pid") {
+        let (id, after) = after.split_once(']')?;
+        pid = Some(id.trim());
+        rest = after.trim_start();
+    } else if let Some((id, after)) = rest.split_once(' ') {
+        if id.bytes().all(|b| b.is_ascii_digit()) {
+            pid = Some(id);
+            rest = after.trim_start();
+        }
+    }
+    let (time, rest) = rest.split_once(' ')?;
+    let time = parse_timestamp(time)?;
+    let rest = rest.trim_start();
+
+    let kind = if let Some(signal) = rest.strip_prefix("--- ") {
+        Kind::Signal(signal.split_whitespace().next()?)
+    } else if let Some(exit) = rest.strip_prefix("+++ ") {
+        Kind::Exit(exit.strip_suffix(" +++")?)
+    } else if let Some(resumed) = rest.strip_prefix("<... ") {
+        let (name, rest) = resumed.split_once(" resumed>")?;
+        let (_, rest) = rest.rsplit_once(") = ").or_else(|| rest.rsplit_once(')'))?;
+        let (result, duration) = result(rest);
+        Kind::Resumed {
+            name,
+            result,
+            duration,
+        }
+    } else {
+        let (name, args) = rest.split_once('(')?;
+        if name.is_empty() || !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_') {
+            return None;
+        }
+        if let Some(args) = args.strip_suffix("<unfinished ...>") {
+            Kind::Unfinished {
+                name,
+                args: args.trim_end().trim_end_matches(',').trim_end(),
+            }
+        } else {
+            // Arguments can have anything in their strings, but the result comes last
+            let (args, rest) = args.rsplit_once(") = ").or_else(|| args.rsplit_once(")"))?;
+            let (result, duration) = result(rest);
+            Kind::Syscall {
+                name,
+                args,
+                result,
+                duration,
+            }
+        }
+    };
+    Some(Line { pid, time, kind })
+}
+
+impl Timeline {
+    /// Read the output of `strace` into a timeline
+    ///
+    /// This reads what `strace -ttt -T -f` prints, or `-tt` for times of the day, with every
+    /// process or thread getting a row named like "pid 1234", or "main" for lines without a
+    /// pid, like those of the first process when `-f` writes to a terminal. Every syscall
+    /// becomes an event named after it that lasts for the time `-T` adds, with its arguments
+    /// and result as "args" and "result" metadata, or an instant without `-T`. Syscalls
+    /// interrupted by lines of other processes are put back together, and ones that never
+    /// return last until the end of the trace. Signals and exits become instants, and the
+    /// `clone`, `clone3`, `fork` and `vfork` that start a traced process become triggers from
+    /// the parent to the child. Times are in nanoseconds.
+    ///
+    /// This returns `TimelineError::Parse` if no line looks like strace output with
+    /// timestamps or a syscall ends before it starts, and `TimelineError::Io` if reading it
+    /// fails. `Timeline::import` detects traces of this format, as "strace".
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let trace = "\
+    ///     1234 5.000000 openat(AT_FDCWD, \"/etc/passwd\", O_RDONLY) = 3 <0.000012>\n\
+    ///     1234 5.000100 read(3, \"root:x:0:0\"..., 4096) = 1024 <0.000005>\n";
+    /// let timeline = Timeline::from_strace(trace.as_bytes()).unwrap();
+    /// assert_eq!(timeline.categories(), ["pid 1234"]);
+    /// assert_eq!(timeline.time_range(), Some((5_000_000_000, 5_000_105_000)));
+    /// # }
+    /// ```
+    pub fn from_strace(reader: impl Read) -> Result<Timeline, TimelineError> {
+        let mut timeline = new_timeline();
+        let mut parsed = false;
+        let mut end = 0;
+        // The unfinished syscall of every row, as its name, arguments and start
+        let mut unfinished: HashMap<String, (String, String, i64)> = HashMap::new();
+        // The processes started, as the row of the parent, the time and the row of the child
+        let mut children = Vec::new();
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            let Some(line) = parse_line(&line) else {
+                continue;
+            };
+            parsed = true;
+            let row = line
+                .pid
+                .map_or_else(|| "main".to_string(), |pid| format!("pid {}", pid));
+            end = end.max(line.time);
+            let (name, args, start, result, finish) = match line.kind {
+                Kind::Signal(name) | Kind::Exit(name) => {
+                    timeline.add_instant(name, line.time, row);
+                    continue;
+                }
+                Kind::Unfinished { name, args } => {
+                    unfinished.insert(row, (name.to_string(), args.to_string(), line.time));
+                    continue;
+                }
+                Kind::Resumed {
+                    name,
+                    result,
+                    duration,
+                } => {
+                    let (name, args, start) = match unfinished.remove(&row) {
+                        Some((_, args, start)) => (name.to_string(), args, start),
+                        None => (name.to_string(), String::new(), line.time),
+                    };
+                    // Without `-T`, resumed syscalls end when they are resumed
+                    let finish = match duration {
+                        Some(duration) => start.checked_add(duration),
+                        None => Some(line.time),
+                    };
+                    (name, args, start, result, finish)
+                }
+                Kind::Syscall {
+                    name,
+                    args,
+                    result,
+                    duration,
+                } => (
+                    name.to_string(),
+                    args.to_string(),
+                    line.time,
+                    result,
+                    line.time.checked_add(duration.unwrap_or(0)),
+                ),
+            };
+            let finish = finish.ok_or_else(|| {
+                TimelineError::Parse(format!("{} at {} lasts too long", name, start))
+            })?;
+            if finish < start {
+                return Err(TimelineError::Parse(format!(
+                    "{} at {} ends before it starts",
+                    name, start
+                )));
+            }
+            end = end.max(finish);
+            if matches!(name.as_str(), "clone" | "clone3" | "fork" | "vfork") {
+                if let Ok(child) = result.parse::<u32>() {
+                    children.push((row.clone(), finish, format!("pid {}", child)));
+                }
+            }
+            timeline.insert_event(
+                EventBuilder::new(name, start, finish, row)
+                    .metadata("args".to_string(), args)
+                    .metadata("result".to_string(), result.to_string())
+                    .build(),
+            );
+        }
+        if !parsed {
+            return Err(TimelineError::Parse(
+                "no strace lines with timestamps".to_string(),
+            ));
+        }
+        let mut unfinished: Vec<_> = unfinished.into_iter().collect();
+        unfinished.sort();
+        for (row, (name, args, start)) in unfinished {
+            timeline.insert_event(
+                EventBuilder::new(name, start, end, row)
+                    .metadata("args".to_string(), args)
+                    .build(),
+            );
+        }
+        // Children that were never traced don't have rows to point at
+        for (parent, time, child) in children {
+            if timeline.has_location(&child) {
+                timeline.add_trigger(parent, child, time);
+            }
+        }
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The events of a timeline, as their names, rows and microseconds after a second
+    fn events(timeline: &Timeline) -> Vec<(&str, &str, i64, i64)> {
+        timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name(),
+                    event.location(),
+                    (event.start_time() - 1_000_000_000) / 1000,
+                    (event.end_time() - 1_000_000_000) / 1000,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(
+            parse_timestamp("1700000000.000100"),
+            Some(1_700_000_000_000_100_000)
+        );
+        assert_eq!(parse_timestamp("10:00:01.5"), Some(36_001_500_000_000));
+        assert_eq!(parse_timestamp("00:01:00"), Some(60_000_000_000));
+        // Pids aren't times
+        assert_eq!(parse_timestamp("1234"), None);
+        assert_eq!(parse_timestamp("10:xx:01.5"), None);
+        assert_eq!(parse_timestamp("9999999999999:00:00"), None);
+        assert_eq!(
+            result(" = -1 ENOENT (No such file or directory) <0.000010>"),
+            ("-1 ENOENT (No such file or directory)", Some(10_000))
+        );
+        assert_eq!(result("= 3"), ("3", None));
+        assert_eq!(result(" = ?"), ("?", None));
+        assert_eq!(result("= 0 <bad"), ("0 <bad", None));
+
+        // Results come after the last parenthesis, whatever the strings in the arguments hold
+        assert_eq!(
+            parse_line(
+                "[pid  42] 10:00:01.5 read(0, \"a) = b\", 3) = -1 EAGAIN (Try again) <0.25>"
+            ),
+            Some(Line {
+                pid: Some("42"),
+                time: 36_001_500_000_000,
+                kind: Kind::Syscall {
+                    name: "read",
+                    args: "0, \"a) = b\", 3",
+                    result: "-1 EAGAIN (Try again)",
+                    duration: Some(250_000_000),
+                },
+            })
+        );
+        assert_eq!(
+            parse_line("1.5 wait4(-1,  <unfinished ...>"),
+            Some(Line {
+                pid: None,
+                time: 1_500_000_000,
+                kind: Kind::Unfinished {
+                    name: "wait4",
+                    args: "-1",
+                },
+            })
+        );
+        assert_eq!(
+            parse_line("7 1.5 <... wait4 resumed>, NULL, 0, NULL) = 8 <0.1>"),
+            Some(Line {
+                pid: Some("7"),
+                time: 1_500_000_000,
+                kind: Kind::Resumed {
+                    name: "wait4",
+                    result: "8",
+                    duration: Some(100_000_000),
+                },
+            })
+        );
+        assert_eq!(
+            parse_line("7 1.5 --- SIGCHLD {si_signo=SIGCHLD} ---").map(|line| line.kind),
+            Some(Kind::Signal("SIGCHLD"))
+        );
+        assert_eq!(
+            parse_line("7 1.5 +++ killed by SIGKILL +++").map(|line| line.kind),
+            Some(Kind::Exit("killed by SIGKILL"))
+        );
+        // Lines need timestamps
+        assert_eq!(
+            parse_line("execve(\"/bin/ls\", [\"ls\"], 0x7ffd) = 0"),
+            None
+        );
+        assert_eq!(parse_line("1.5 strace: Process 7 attached"), None);
+    }
+
+    #[test]
+    fn test_from_strace() {
+        let trace = "\
+10 1.000000 execve(\"/bin/sh\", [\"sh\"], 0x7ffd /* 20 vars */) = 0 <0.000500>
+10 1.001000 clone(child_stack=NULL, flags=SIGCHLD) = 11 <0.000100>
+11 1.001200 futex(0x7f00, FUTEX_WAIT, 0, NULL <unfinished ...>
+10 1.001300 write(1, \"hi\\n\", 3) = 3 <0.000010>
+11 1.002000 <... futex resumed>) = 0 <0.000800>
+11 1.003000 +++ exited with 0 +++
+10 1.003100 --- SIGCHLD {si_signo=SIGCHLD, si_code=CLD_EXITED, si_pid=11} ---
+";
+        assert!(StraceImporter.sniff(trace.as_bytes()));
+        // Traces don't start in the middle of a syscall
+        assert!(!StraceImporter.sniff(b"10 1.002000 <... futex resumed>) = 0\n"));
+        let timeline = Timeline::from_strace(trace.as_bytes()).unwrap();
+        assert_eq!(timeline.categories(), ["pid 10", "pid 11"]);
+        assert_eq!(
+            events(&timeline),
+            [
+                ("execve", "pid 10", 0, 500),
+                ("clone", "pid 10", 1000, 1100),
+                ("write", "pid 10", 1300, 1310),
+                ("futex", "pid 11", 1200, 2000),
+                ("exited with 0", "pid 11", 3000, 3000),
+                ("SIGCHLD", "pid 10", 3100, 3100),
+            ]
+        );
+        let (_, write) = timeline.events().nth(2).unwrap();
+        assert_eq!(write.metadata()["args"], "1, \"hi\\n\", 3");
+        assert_eq!(write.metadata()["result"], "3");
+        // The unfinished half of a syscall has its arguments
+        let (_, futex) = timeline.events().nth(3).unwrap();
+        assert_eq!(futex.metadata()["args"], "0x7f00, FUTEX_WAIT, 0, NULL");
+        assert_eq!(futex.metadata()["result"], "0");
+        // Children start when the clone returns
+        let trigger = timeline.triggers().next().unwrap();
+        assert_eq!(
+            (
+                trigger.start_location(),
+                trigger.end_location(),
+                trigger.time()
+            ),
+            ("pid 10", "pid 11", 1_001_100_000)
+        );
+    }
+
+    #[test]
+    fn test_without_durations() {
+        // Without `-T` syscalls are instants, resumed ones last until they resume, and
+        // the first process has no pid
+        let trace = "\
+1.000000 clone(child_stack=NULL, flags=SIGCHLD) = 11
+1.001000 wait4(-1,  <unfinished ...>
+[pid 11] 1.001500 getpid() = 11
+1.002000 <... wait4 resumed>, NULL, 0, NULL) = 11
+1.003000 <... read resumed>\"a\", 1) = 1
+";
+        let timeline = Timeline::from_strace(trace.as_bytes()).unwrap();
+        assert_eq!(
+            events(&timeline),
+            [
+                ("clone", "main", 0, 0),
+                ("getpid", "pid 11", 1500, 1500),
+                ("wait4", "main", 1000, 2000),
+                ("read", "main", 3000, 3000),
+            ]
+        );
+        assert_eq!(timeline.triggers().count(), 1);
+
+        // but can't end before they start, or too late to tell
+        for trace in [
+            "1.002000 wait4(-1,  <unfinished ...>\n1.001000 <... wait4 resumed>) = 11\n",
+            "1.000000 read(0, \"\", 1) = 0 <-0.000010>\n",
+            "1.000000 read(0, \"\", 1) = 0 <9223372036.000000>\n",
+        ] {
+            assert!(matches!(
+                Timeline::from_strace(trace.as_bytes()),
+                Err(TimelineError::Parse(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_unfinished() {
+        // Syscalls that never return last until the end of the trace, and children that were
+        // never traced have no triggers
+        let trace = "\
+10 1.000000 clone(child_stack=NULL, flags=SIGCHLD) = 12 <0.000100>
+10 1.001000 wait4(-1,  <unfinished ...>
+11 1.002000 read(0,  <unfinished ...>
+11 1.004000 --- SIGTERM {si_signo=SIGTERM} ---
+";
+        let timeline = Timeline::from_strace(trace.as_bytes()).unwrap();
+        assert_eq!(
+            events(&timeline),
+            [
+                ("clone", "pid 10", 0, 100),
+                ("SIGTERM", "pid 11", 4000, 4000),
+                ("wait4", "pid 10", 1000, 4000),
+                ("read", "pid 11", 2000, 4000),
+            ]
+        );
+        assert_eq!(timeline.triggers().count(), 0);
+
+        // Output without timestamps is an error, and so is output that isn't UTF-8
+        assert!(matches!(
+            Timeline::from_strace("no timestamps here".as_bytes()),
+            Err(TimelineError::Parse(_))
+        ));
+        assert!(matches!(
+            Timeline::from_strace("".as_bytes()),
+            Err(TimelineError::Parse(_))
+        ));
+        assert!(matches!(
+            Timeline::from_strace(&b"1.0 read(0) = 0\n\xff\n"[..]),
+            Err(TimelineError::Io(_))
+        ));
+    }
+}
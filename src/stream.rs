@@ -0,0 +1,145 @@
+// Writing documents without serializing them to a string first
+//
+// `Document::to_string` holds the whole text of the SVG next to the tree it was built from, which
+// doubles the peak memory of big timelines. Writing the tree an element at a time, descending
+// into the groups the layers are drawn in, and dropping every element once it is written, keeps
+// only the tree. That is not flat memory: the layers are all filled in one pass over the
+// timeline, so the whole tree is drawn before any of it is written, and the peak still grows
+// with the timeline.
+
+use std::io::{self, BufWriter, Write};
+
+use svg::node::Node;
+
+// What is left to write of a document, in reverse order
+enum Piece {
+    // An element, after a line break unless it's the root
+    Node(Box<dyn Node>, bool),
+    // The closing tag of an element whose children were written
+    Close(String),
+}
+
+// The text of `doc` in pieces that together spell it exactly as `doc.to_string()` would, an
+// element at a time. This works for any element, like the group of `Timeline::write_fragment`.
+pub(crate) fn chunks(doc: impl Node) -> impl Iterator<Item = String> {
+    let mut pieces = vec![Piece::Node(Box::new(doc), false)];
+    std::iter::from_fn(move || {
+        let (mut node, line) = match pieces.pop()? {
+            Piece::Node(node, line) => (node, line),
+            Piece::Close(tag) => return Some(tag),
+        };
+        let prefix = if line { "\n" } else { "" };
+        // Children are put on lines of their own, except for text that elements which aren't
+        // bareable keep next to their tags, so those elements are written whole
+        let bareable = node.is_bareable();
+        let children = match node.get_children_mut() {
+            Some(children)
+                if !children.is_empty()
+                    && (bareable || !children.iter().any(|child| child.is_bare())) =>
+            {
+                std::mem::take(children)
+            }
+            _ => return Some(format!("{}{}", prefix, node)),
+        };
+        // The element without children is written as an empty element, whose attributes make
+        // its opening tag instead
+        let empty = node.to_string();
+        pieces.push(Piece::Close(format!("\n</{}>", node.get_name())));
+        pieces.extend(
+            children
+                .into_iter()
+                .rev()
+                .map(|child| Piece::Node(child, true)),
+        );
+        Some(format!(
+            "{}{}>",
+            prefix,
+            empty.strip_suffix("/>").unwrap_or(&empty)
+        ))
+    })
+}
+
+// Write `doc` exactly as `doc.to_string()` would spell it
+pub(crate) fn write_document(doc: impl Node, writer: impl Write) -> io::Result<()> {
+    let mut writer = BufWriter::new(writer);
+    for chunk in chunks(doc) {
+        writer.write_all(chunk.as_bytes())?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Timeline;
+    use svg::node::element::Group;
+    use svg::Document;
+
+    #[test]
+    fn test_write_document() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event <1>", 0, 10, "Location 1");
+        timeline.add_marker("Marker \"1\"".to_string(), 5);
+        timeline.set_measure_cursors(true);
+        let doc = timeline.document(&timeline.colormap).unwrap();
+        let expected = doc.to_string();
+
+        let mut output = Vec::new();
+        write_document(doc, &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+
+        let mut output = Vec::new();
+        write_document(Document::new().set("width", 10), &mut output).unwrap();
+        assert_eq!(
+            output,
+            br#"<svg width="10" xmlns="http://www.w3.org/2000/svg"/>"#
+        );
+
+        // Any element can be the root, like the group of a fragment
+        let mut group = Group::new().set("class", "timeline");
+        for child in (*timeline.document(&timeline.colormap).unwrap()).get_children() {
+            group.append(child.clone());
+        }
+        let expected = group.to_string();
+        let mut output = Vec::new();
+        write_document(group, &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+
+    // Records the size of every write it gets
+    struct Sizes(Vec<usize>);
+
+    impl Write for Sizes {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.push(buf.len());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write_document_in_chunks() {
+        let mut timeline = Timeline::default();
+        for i in 0..2000 {
+            timeline.add_event(format!("Event {}", i), i * 10, i * 10 + 5, "Location 1");
+        }
+        let doc = timeline.document(&timeline.colormap).unwrap();
+        let expected = doc.to_string();
+        let length = expected.len();
+
+        // Every layer is in one group, which is written an element at a time too
+        let chunks: Vec<String> = chunks(doc.clone()).collect();
+        assert_eq!(chunks.concat(), expected);
+        assert!(chunks.len() > 2000);
+        assert!(chunks.iter().all(|chunk| chunk.len() < 1000));
+
+        let mut sizes = Sizes(Vec::new());
+        write_document(doc, &mut sizes).unwrap();
+        assert_eq!(sizes.0.iter().sum::<usize>(), length);
+        assert!(sizes.0.len() > 1);
+        assert!(sizes.0.iter().all(|size| *size < length / 2));
+    }
+}
@@ -0,0 +1,394 @@
+use std::io::{BufRead, BufReader, Read};
+
+use crate::ftrace::parse_time;
+use crate::import::{new_timeline, sniff_text};
+use crate::systemd::days_from_civil;
+use crate::{Timeline, TimelineError, TraceImporter};
+
+// The reports of sar and iostat, see `Timeline::from_sysstat`
+pub(crate) struct SysstatImporter;
+
+impl TraceImporter for SysstatImporter {
+    fn format(&self) -> &str {
+        "sysstat"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        sniff_text(data)
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .is_some_and(|line| line.starts_with("Linux ") && line.trim_end().ends_with("CPU)"))
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_sysstat(data)
+    }
+}
+
+const DAY: i64 = 24 * 60 * 60 * 1_000_000_000;
+
+// A date like "01/31/2024" or "01/31/24", as the US locale prints them, or "2024-01-31", in days
+// since the epoch
+fn parse_date(text: &str) -> Option<i64> {
+    let numbers: Vec<i64> = text
+        .split(['/', '-'])
+        .map(|number| number.parse().ok())
+        .collect::<Option<_>>()?;
+    let [first, second, third] = numbers[..] else {
+        return None;
+    };
+    let (year, month, day) = if text.contains('-') {
+        (first, second, third)
+    } else if third < 100 {
+        (2000 + third, first, second)
+    } else {
+        (third, first, second)
+    };
+    days_from_civil(year, month, day)
+}
+
+// A time of the day like "10:00:01", in nanoseconds since midnight
+fn parse_clock(text: &str) -> Option<i64> {
+    let mut fields = text.split(':');
+    let (hours, minutes, seconds) = (fields.next()?, fields.next()?, fields.next()?);
+    if fields.next().is_some() {
+        return None;
+    }
+    let minutes = hours
+        .parse::<i64>()
+        .ok()?
+        .checked_mul(60)?
+        .checked_add(minutes.parse().ok()?)?;
+    minutes
+        .checked_mul(60_000_000_000)?
+        .checked_add(parse_time(seconds)?)
+}
+
+// Take the time of the day off the start of `fields`, with the "AM" or "PM" that follows it in
+// the US locale
+fn take_clock(fields: &mut &[&str]) -> Option<i64> {
+    let mut time = parse_clock(fields.first()?)?;
+    *fields = &fields[1..];
+    match fields.first() {
+        Some(&"AM") => {
+            time %= DAY / 2;
+            *fields = &fields[1..];
+        }
+        Some(&"PM") => {
+            time = time % (DAY / 2) + DAY / 2;
+            *fields = &fields[1..];
+        }
+        _ => {}
+    }
+    Some(time)
+}
+
+// The timestamp iostat prints before every report with `-t`, like "01/31/2024 10:00:01 AM" or
+// "2024-01-31T10:00:01+0000", in nanoseconds since the epoch
+fn parse_timestamp(fields: &[&str]) -> Option<i64> {
+    if let [iso] = fields {
+        let (date, time) = iso.split_once('T')?;
+        let time = time.split(['+', '-', 'Z']).next()?;
+        return parse_date(date)?
+            .checked_mul(DAY)?
+            .checked_add(parse_clock(time)?);
+    }
+    let mut rest = &fields[1..];
+    let time = parse_date(fields.first()?)?
+        .checked_mul(DAY)?
+        .checked_add(take_clock(&mut rest)?)?;
+    rest.is_empty().then_some(time)
+}
+
+// A value, also with the decimal comma of some locales
+fn parse_value(text: &str) -> Option<f64> {
+    text.replace(',', ".").parse().ok()
+}
+
+// The header of a table of a report, like "CPU %user %nice" or "Device r/s w/s"
+struct Header {
+    // The name of the column that the rows of the table are keyed by, like "CPU" or "Device",
+    // or what the table is about, like "avg-cpu"
+    prefix: String,
+    // Whether the first field of every row is its key, like "all" or "sda"
+    keyed: bool,
+    columns: Vec<String>,
+}
+
+impl Timeline {
+    /// Read the reports of sar and iostat into counters of a timeline
+    ///
+    /// This reads the text output of sysstat's `sar`, of any of its reports, and of `iostat -t`,
+    /// also with `-x`, and turns every value into a sample of a counter, so the use of the CPUs
+    /// and disks shares the x-axis with the events of other traces. Counters are named after
+    /// the key of their row and their column, like "CPU all %user", "DEV sda tps" or
+    /// "IFACE eth0 rxkB/s" for sar, and "avg-cpu %iowait" or "Device sda r_await" for iostat,
+    /// or only after their column for tables without keys, like "tps" of `sar -b`. The averages
+    /// sar ends with, and the reports of iostat before its first timestamp, are skipped.
+    ///
+    /// Times are in nanoseconds since the epoch, from the date of the "Linux" line that starts
+    /// the output, or since midnight without it, and times of a table of sar that go back are
+    /// taken to be of the next day. Times and dates are read as the C, ISO and US locales print
+    /// them.
+    ///
+    /// This returns `TimelineError::Parse` if there are no samples, and `TimelineError::Io` if
+    /// reading fails. `Timeline::import` detects output of this format, as "sysstat".
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let output = "\
+    ///     Linux 6.8.0 (host) \t01/01/1970 \t_x86_64_\t(8 CPU)\n\
+    ///     \n\
+    ///     00:00:01        CPU     %user   %system     %idle\n\
+    ///     00:00:02        all      2.00      1.00     97.00\n\
+    ///     00:00:03        all     50.00      5.00     45.00\n\
+    ///     Average:        all     26.00      3.00     71.00\n";
+    /// let timeline = Timeline::from_sysstat(output.as_bytes()).unwrap();
+    /// assert_eq!(
+    ///     timeline.categories(),
+    ///     ["CPU all %idle", "CPU all %system", "CPU all %user"]
+    /// );
+    /// assert_eq!(timeline.time_range(), Some((2_000_000_000, 3_000_000_000)));
+    /// # }
+    /// ```
+    pub fn from_sysstat(reader: impl Read) -> Result<Timeline, TimelineError> {
+        let mut timeline = new_timeline();
+        let mut parsed = false;
+        // The day of the output, and the days the times of the day of a table of sar wrapped
+        // around midnight, as every table of a report starts at the first time again
+        let mut date = 0;
+        let mut wrapped = 0;
+        let mut last_clock = None;
+        // The time of the report of iostat that is being read
+        let mut report = None;
+        let mut header: Option<Header> = None;
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let mut fields = &fields[..];
+            match fields.first() {
+                None => {
+                    header = None;
+                    wrapped = 0;
+                    last_clock = None;
+                    continue;
+                }
+                Some(&"Linux") => {
+                    if let Some(day) = fields.iter().find_map(|field| parse_date(field)) {
+                        date = day * DAY;
+                    }
+                    header = None;
+                    continue;
+                }
+                Some(first) if first.starts_with("Average") || first.starts_with("Summary") => {
+                    continue;
+                }
+                _ => {}
+            }
+            if let Some(time) = parse_timestamp(fields) {
+                report = Some(time);
+                header = None;
+                continue;
+            }
+            let time = match take_clock(&mut fields) {
+                Some(clock) => {
+                    if last_clock.is_some_and(|last| clock < last) {
+                        wrapped += DAY;
+                    }
+                    last_clock = Some(clock);
+                    Some(date + wrapped + clock)
+                }
+                None => report,
+            };
+            let Some(last) = fields.last() else {
+                continue;
+            };
+            // Headers end in the name of a column, rows in a value
+            if parse_value(last).is_none() {
+                let first = fields[0].trim_end_matches(':');
+                let keyed = first.starts_with(|c: char| c.is_ascii_uppercase());
+                header = Some(if keyed || fields[0].ends_with(':') {
+                    Header {
+                        prefix: first.to_string(),
+                        keyed,
+                        columns: fields[1..].iter().map(|field| field.to_string()).collect(),
+                    }
+                } else {
+                    Header {
+                        prefix: String::new(),
+                        keyed,
+                        columns: fields.iter().map(|field| field.to_string()).collect(),
+                    }
+                });
+                continue;
+            }
+            let (Some(header), Some(time)) = (&header, time) else {
+                continue;
+            };
+            let (prefix, values) = match (header.keyed, header.prefix.is_empty()) {
+                (true, _) => (format!("{} {} ", header.prefix, fields[0]), &fields[1..]),
+                (false, true) => (String::new(), fields),
+                (false, false) => (format!("{} ", header.prefix), fields),
+            };
+            for (column, value) in header.columns.iter().zip(values) {
+                if let Some(value) = parse_value(value) {
+                    timeline.add_counter_sample(format!("{}{}", prefix, column), time, value);
+                    parsed = true;
+                }
+            }
+        }
+        if !parsed {
+            return Err(TimelineError::Parse("no sysstat samples".to_string()));
+        }
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = 3_600_000_000_000;
+    const SECOND: i64 = 1_000_000_000;
+
+    #[test]
+    fn test_parse_timestamp() {
+        let day = days_from_civil(2024, 1, 31).unwrap();
+        assert_eq!(parse_date("01/31/2024"), Some(day));
+        assert_eq!(parse_date("01/31/24"), Some(day));
+        assert_eq!(parse_date("2024-01-31"), Some(day));
+        // Days and months of other locales, and things that aren't dates
+        assert_eq!(parse_date("31/01/2024"), None);
+        assert_eq!(parse_date("01/31"), None);
+        assert_eq!(parse_date("_x86_64_"), None);
+        assert_eq!(parse_date("(8"), None);
+
+        let day = days_from_civil(2024, 1, 1).unwrap() * DAY;
+        assert_eq!(parse_clock("10:00:01.5"), Some(10 * HOUR + 1_500_000_000));
+        assert_eq!(parse_clock("10:00"), None);
+        assert_eq!(parse_clock("10:00:01:00"), None);
+        assert_eq!(parse_clock("9999999999999:00:01"), None);
+        // 12 AM is midnight, and 12 PM noon
+        assert_eq!(
+            parse_timestamp(&["01/01/24", "12:30:00", "AM"]),
+            Some(day + HOUR / 2)
+        );
+        assert_eq!(
+            parse_timestamp(&["01/01/24", "12:30:00", "PM"]),
+            Some(day + 12 * HOUR + HOUR / 2)
+        );
+        assert_eq!(
+            parse_timestamp(&["01/01/2024", "01:00:00", "PM"]),
+            Some(day + 13 * HOUR)
+        );
+        assert_eq!(
+            parse_timestamp(&["2024-01-01T10:00:01+0000"]),
+            Some(day + 10 * HOUR + SECOND)
+        );
+        assert_eq!(
+            parse_timestamp(&["2024-01-01T10:00:01-0500"]),
+            Some(day + 10 * HOUR + SECOND)
+        );
+        assert_eq!(parse_timestamp(&["01/01/24", "10:00:01", "all"]), None);
+        assert_eq!(parse_timestamp(&["10:00:01", "all"]), None);
+        assert_eq!(parse_timestamp(&["99999999999-01-01T10:00:01+0000"]), None);
+    }
+
+    #[test]
+    fn test_sar() {
+        let sar = "\
+Linux 6.8.0-31-generic (host) \t01/01/2024 \t_x86_64_\t(8 CPU)
+
+10:00:01 AM     CPU     %user     %nice   %system   %iowait    %steal     %idle
+10:00:02 AM     all      2.00      0.00      1.00      0.00      0.00     97.00
+10:00:02 AM       0      4.00      0.00      2.00      0.00      0.00     94.00
+Average:        all      2.00      0.00      1.00      0.00      0.00     97.00
+
+10:00:01 AM     IFACE   rxpck/s   txpck/s    rxkB/s
+10:00:02 AM      eth0     10.00      5.00      1.50
+
+10:00:01 AM       tps      rtps      wtps   bread/s   bwrtn/s
+10:00:02 AM      4.00      1.00      3.00      8.00     24.00
+Summary:         4.00      1.00      3.00      8.00     24.00
+";
+        assert!(SysstatImporter.sniff(sar.as_bytes()));
+        // The banner of the kernel log isn't one of sysstat
+        assert!(!SysstatImporter.sniff(b"Linux version 6.8.0 (gcc 13.2.0)\n"));
+        let timeline = Timeline::from_sysstat(sar.as_bytes()).unwrap();
+        let time = days_from_civil(2024, 1, 1).unwrap() * DAY + 10 * HOUR + 2 * SECOND;
+        assert_eq!(timeline.counters["CPU all %user"], [(time, 2.0)]);
+        assert_eq!(timeline.counters["CPU 0 %idle"], [(time, 94.0)]);
+        assert_eq!(timeline.counters["IFACE eth0 rxkB/s"], [(time, 1.5)]);
+        assert_eq!(timeline.counters["tps"], [(time, 4.0)]);
+        assert_eq!(timeline.counters.len(), 2 * 6 + 3 + 5);
+
+        // Without the line that starts the output, times are since midnight
+        let sar = "\
+00:00:01        CPU     %user
+00:00:02        all      2.00
+";
+        let timeline = Timeline::from_sysstat(sar.as_bytes()).unwrap();
+        assert_eq!(timeline.counters["CPU all %user"], [(2 * SECOND, 2.0)]);
+    }
+
+    #[test]
+    fn test_sar_past_midnight() {
+        // Every table starts at the first time again, and wraps around midnight on its own
+        let sar = "\
+Linux 6.8.0-31-generic (host) \t01/01/2024 \t_x86_64_\t(8 CPU)
+
+11:59:58 PM     CPU     %user     %nice   %system   %iowait    %steal     %idle
+11:59:59 PM     all      2,00      0,00      1,00      0,00      0,00     97,00
+12:00:00 AM     all     50.00      0.00      5.00     10.00      0.00     35.00
+
+11:59:58 PM       tps      rtps      wtps   bread/s   bwrtn/s
+11:59:59 PM      4.00      1.00      3.00      8.00     24.00
+";
+        let timeline = Timeline::from_sysstat(sar.as_bytes()).unwrap();
+        let midnight = days_from_civil(2024, 1, 2).unwrap() * DAY;
+        assert_eq!(
+            timeline.counters["CPU all %user"],
+            [(midnight - SECOND, 2.0), (midnight, 50.0)]
+        );
+        assert_eq!(timeline.counters["tps"], [(midnight - SECOND, 4.0)]);
+    }
+
+    #[test]
+    fn test_iostat() {
+        let iostat = "\
+Linux 6.8.0 (host) \t2024-01-01 \t_x86_64_\t(8 CPU)
+
+avg-cpu:  %user   %nice %system %iowait  %steal   %idle
+           1.00    0.00    1.00    0.00    0.00   98.00
+
+2024-01-01T10:00:01+0000
+avg-cpu:  %user   %nice %system %iowait  %steal   %idle
+           3.00    0.00    2.00   12.50    0.00   82.50
+
+Device            r/s     w/s  r_await  w_await  %util
+nvme0n1         10.00   20.00     0.50     1.25   4.00
+sda              0.00    1.00     0.00     8.00   0.80
+";
+        assert!(SysstatImporter.sniff(iostat.as_bytes()));
+        let timeline = Timeline::from_sysstat(iostat.as_bytes()).unwrap();
+        // The report before the first timestamp is the average since boot, and skipped
+        let time = days_from_civil(2024, 1, 1).unwrap() * DAY + 10 * HOUR + SECOND;
+        assert_eq!(timeline.counters["avg-cpu %iowait"], [(time, 12.5)]);
+        assert_eq!(timeline.counters["Device sda w_await"], [(time, 8.0)]);
+        assert_eq!(timeline.counters["Device nvme0n1 %util"], [(time, 4.0)]);
+        assert_eq!(timeline.counters.len(), 6 + 2 * 5);
+
+        // Output without any samples, or only their averages, is an error
+        assert!(matches!(
+            Timeline::from_sysstat("Linux 6.8.0 (host)\n".as_bytes()),
+            Err(TimelineError::Parse(_))
+        ));
+        assert!(matches!(
+            Timeline::from_sysstat("Average:  CPU  %user\nAverage:  all  2.00\n".as_bytes()),
+            Err(TimelineError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,377 @@
+use std::io::Read;
+
+use serde_json::Value;
+
+use crate::ftrace::parse_time;
+use crate::import::{new_timeline, sniff_text};
+use crate::{Timeline, TimelineError, TraceImporter};
+
+// The output of `systemd-analyze dump` and `systemd-analyze plot --json`, see
+// `Timeline::from_systemd`
+pub(crate) struct SystemdImporter;
+
+impl TraceImporter for SystemdImporter {
+    fn format(&self) -> &str {
+        "systemd"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        let text = sniff_text(data);
+        text.lines().any(|line| line.starts_with("-> Unit "))
+            || (text.trim_start().starts_with('[') && text.contains("\"activating\""))
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_systemd(data)
+    }
+}
+
+// The phases of a unit, named after the state they start, in the order a unit goes through them
+const PHASES: [&str; 3] = ["activating", "active", "deactivating"];
+
+// When a unit left and entered its states: when it started activating, became active, started
+// deactivating and became inactive
+struct Unit {
+    name: String,
+    times: [Option<i64>; 4],
+}
+
+// The days from 1970-01-01 to a date, after Howard Hinnant's `days_from_civil`, or None if the
+// month or day is out of range or the year too far off to count the days of
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> Option<i64> {
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let year = if month <= 2 {
+        year.checked_sub(1)?
+    } else {
+        year
+    };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era.checked_mul(146_097)?.checked_add(day_of_era - 719_468)
+}
+
+// A timestamp of a dump, like "Mon 2024-01-01 10:00:05 UTC" or with microseconds, in
+// nanoseconds since the epoch, or None for "n/a". The time zone is ignored, as all the times of
+// a dump are in the same one.
+fn parse_timestamp(text: &str) -> Option<i64> {
+    let mut fields = text
+        .split_whitespace()
+        .skip_while(|field| field.bytes().all(|b| b.is_ascii_alphabetic()));
+    let mut date = fields.next()?.split('-');
+    let (year, month, day) = (date.next()?, date.next()?, date.next()?);
+    let days = days_from_civil(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)?;
+    let mut time = fields.next()?.split(':');
+    let (hours, minutes, seconds) = (time.next()?, time.next()?, time.next()?);
+    let minutes = days
+        .checked_mul(24)?
+        .checked_add(hours.parse().ok()?)?
+        .checked_mul(60)?
+        .checked_add(minutes.parse().ok()?)?;
+    minutes
+        .checked_mul(60_000_000_000)?
+        .checked_add(parse_time(seconds)?)
+}
+
+fn parse_dump(text: &str) -> Vec<Unit> {
+    let mut units: Vec<Unit> = Vec::new();
+    let mut in_unit = false;
+    for line in text.lines() {
+        if let Some(header) = line.strip_prefix("-> ") {
+            in_unit = match header
+                .strip_prefix("Unit ")
+                .and_then(|name| name.strip_suffix(':'))
+            {
+                Some(name) => {
+                    units.push(Unit {
+                        name: name.to_string(),
+                        times: [None; 4],
+                    });
+                    true
+                }
+                None => false,
+            };
+            continue;
+        }
+        let (Some(unit), true) = (units.last_mut(), in_unit) else {
+            continue;
+        };
+        let Some((key, value)) = line.trim().split_once(": ") else {
+            continue;
+        };
+        let index = match key {
+            "Inactive Exit Timestamp" => 0,
+            "Active Enter Timestamp" => 1,
+            "Active Exit Timestamp" => 2,
+            "Inactive Enter Timestamp" => 3,
+            _ => continue,
+        };
+        unit.times[index] = parse_timestamp(value);
+    }
+    units
+}
+
+fn parse_plot(text: &str) -> Result<Vec<Unit>, TimelineError> {
+    let plot: Value =
+        serde_json::from_str(text).map_err(|e| TimelineError::Parse(e.to_string()))?;
+    let entries = plot
+        .as_array()
+        .ok_or_else(|| TimelineError::Parse("plot isn't an array".to_string()))?;
+    // Plots count in microseconds
+    let time = |entry: &Value, key: &str| {
+        entry[key]
+            .as_f64()
+            .map(|micros| (micros * 1000.0).round() as i64)
+    };
+    Ok(entries
+        .iter()
+        .filter_map(|entry| {
+            Some(Unit {
+                name: entry["name"].as_str()?.to_string(),
+                times: [
+                    time(entry, "activating"),
+                    time(entry, "activated"),
+                    time(entry, "deactivating"),
+                    time(entry, "deactivated"),
+                ],
+            })
+        })
+        .collect())
+}
+
+impl Timeline {
+    /// Read the units of a systemd boot into a timeline, like a boot chart
+    ///
+    /// This reads the output of `systemd-analyze dump`, or the JSON of `systemd-analyze plot
+    /// --json=short`. Every unit that was started gets a row named after it, like
+    /// "ssh.service", with an event for each phase it went through: "activating", "active" and
+    /// "deactivating". Phases that haven't ended last until the last time of any unit. The
+    /// rows are in the order the units started, like `systemd-analyze plot` draws them. Times
+    /// are in nanoseconds, since the epoch for dumps, which only have whole seconds, and since
+    /// the start of the plot for plots.
+    ///
+    /// This returns `TimelineError::Parse` if no unit was started, and `TimelineError::Io` if
+    /// reading fails. `Timeline::import` detects both formats, as "systemd".
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let plot = r#"[
+    ///     {"name": "systemd-journald.service", "activating": 1000, "activated": 4000},
+    ///     {"name": "dbus.service", "activating": 2000, "activated": 2500}
+    /// ]"#;
+    /// let timeline = Timeline::from_systemd(plot.as_bytes()).unwrap();
+    /// assert_eq!(timeline.categories(), ["systemd-journald.service", "dbus.service"]);
+    /// assert_eq!(timeline.time_range(), Some((1_000_000, 4_000_000)));
+    /// # }
+    /// ```
+    pub fn from_systemd(mut reader: impl Read) -> Result<Timeline, TimelineError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let mut units = if text.trim_start().starts_with('[') {
+            parse_plot(&text)?
+        } else {
+            parse_dump(&text)
+        };
+        units.retain(|unit| unit.times[..3].iter().any(Option::is_some));
+        if units.is_empty() {
+            return Err(TimelineError::Parse("no units were started".to_string()));
+        }
+        let end = units
+            .iter()
+            .flat_map(|unit| unit.times.iter().flatten())
+            .max()
+            .copied()
+            .unwrap_or(0);
+        units.sort_by_key(|unit| unit.times.iter().flatten().min().copied());
+
+        let mut timeline = new_timeline();
+        for unit in &units {
+            for (phase, name) in PHASES.iter().enumerate() {
+                let Some(start) = unit.times[phase] else {
+                    continue;
+                };
+                // Times left from before a unit was restarted are older than its phases
+                let finish = unit.times[phase + 1..]
+                    .iter()
+                    .flatten()
+                    .find(|time| **time >= start)
+                    .copied()
+                    .unwrap_or(end);
+                if finish > start {
+                    timeline.add_event(*name, start, finish, unit.name.clone());
+                }
+            }
+        }
+        timeline.set_category_order(units.into_iter().map(|unit| unit.name).collect());
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The events of a timeline, as their names, rows, starts and ends
+    fn events(timeline: &Timeline) -> Vec<(&str, &str, i64, i64)> {
+        timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name(),
+                    event.location(),
+                    event.start_time(),
+                    event.end_time(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(
+            parse_timestamp("Mon 2024-01-01 10:00:05.5 UTC"),
+            Some(1_704_103_205_500_000_000)
+        );
+        // The day and the time zone are optional
+        assert_eq!(
+            parse_timestamp("2024-01-01 10:00:05"),
+            Some(1_704_103_205_000_000_000)
+        );
+        assert_eq!(parse_timestamp("n/a"), None);
+        assert_eq!(parse_timestamp("Mon 2024-01-01"), None);
+        assert_eq!(parse_timestamp("Mon 2024-01 10:00:05 UTC"), None);
+        assert_eq!(parse_timestamp("99999999999-01-01 10:00:05"), None);
+        assert_eq!(parse_timestamp("2024-01-01 9999999999999:00:00"), None);
+        assert_eq!(days_from_civil(1970, 1, 1), Some(0));
+        assert_eq!(days_from_civil(2000, 3, 1), Some(11_017));
+        assert_eq!(
+            days_from_civil(2024, 2, 29).map(|day| day + 1),
+            days_from_civil(2024, 3, 1)
+        );
+        assert_eq!(days_from_civil(1969, 12, 31), Some(-1));
+        assert_eq!(days_from_civil(2024, 13, 1), None);
+        assert_eq!(days_from_civil(i64::MIN, 1, 1), None);
+        assert_eq!(days_from_civil(i64::MAX, 12, 31), None);
+    }
+
+    #[test]
+    fn test_dump() {
+        let dump = "\
+Timestamp firmware: n/a
+-> Unit ssh.service:
+\tDescription: OpenBSD Secure Shell server
+\tInactive Exit Timestamp: Mon 2024-01-01 10:00:04 UTC
+\tActive Enter Timestamp: Mon 2024-01-01 10:00:06 UTC
+\tActive Exit Timestamp: n/a
+\tInactive Enter Timestamp: n/a
+-> Unit cleanup.service:
+\tInactive Exit Timestamp: Mon 2024-01-01 10:00:02 UTC
+\tActive Enter Timestamp: Mon 2024-01-01 10:00:03 UTC
+\tActive Exit Timestamp: Mon 2024-01-01 10:00:05 UTC
+\tInactive Enter Timestamp: Mon 2024-01-01 10:00:07 UTC
+-> Unit never.service:
+\tInactive Exit Timestamp: n/a
+-> Job 42:
+\tInactive Exit Timestamp: Mon 2024-01-01 09:00:00 UTC
+";
+        assert!(SystemdImporter.sniff(dump.as_bytes()));
+        assert!(!SystemdImporter.sniff(b"  -> Unit ssh.service:\n"));
+        // Units that never started and the timestamps of jobs are left out, and phases that
+        // haven't ended last until the end
+        let timeline = Timeline::from_systemd(dump.as_bytes()).unwrap();
+        assert_eq!(timeline.categories(), ["cleanup.service", "ssh.service"]);
+        let start = parse_timestamp("2024-01-01 10:00:00").unwrap();
+        let events: Vec<_> = events(&timeline)
+            .into_iter()
+            .map(|(name, row, from, to)| {
+                (
+                    name,
+                    row,
+                    (from - start) / 1_000_000_000,
+                    (to - start) / 1_000_000_000,
+                )
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("activating", "cleanup.service", 2, 3),
+                ("active", "cleanup.service", 3, 5),
+                ("deactivating", "cleanup.service", 5, 7),
+                ("activating", "ssh.service", 4, 6),
+                ("active", "ssh.service", 6, 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plot() {
+        // Plots count in fractions of microseconds, units that were only deactivated have rows
+        // too, and phases that took no time aren't drawn
+        let plot = r#"[
+            {"name": "b.service", "activating": 2000.4, "activated": 2500, "deactivating": null},
+            {"name": "a.service", "activating": 1000, "activated": 1000, "deactivating": 3000},
+            {"name": "c.mount", "deactivating": 500, "deactivated": 800},
+            {"activating": 10}
+        ]"#;
+        assert!(SystemdImporter.sniff(plot.as_bytes()));
+        assert!(!SystemdImporter.sniff(b"[{\"name\": \"a\", \"start\": 1}]"));
+        let timeline = Timeline::from_systemd(plot.as_bytes()).unwrap();
+        assert_eq!(timeline.categories(), ["c.mount", "a.service", "b.service"]);
+        assert_eq!(
+            events(&timeline),
+            [
+                ("deactivating", "c.mount", 500_000, 800_000),
+                ("active", "a.service", 1_000_000, 3_000_000),
+                ("activating", "b.service", 2_000_400, 2_500_000),
+                ("active", "b.service", 2_500_000, 3_000_000),
+            ]
+        );
+
+        // The times of a unit that was stopped before it was started again are from before
+        // its phases, which end when the next later time does
+        let plot = r#"[
+            {"name": "a.service", "activating": 5000, "activated": 6000,
+             "deactivating": 1000, "deactivated": 2000},
+            {"name": "b.service", "activating": 3000, "activated": 8000}
+        ]"#;
+        let timeline = Timeline::from_systemd(plot.as_bytes()).unwrap();
+        assert_eq!(
+            events(&timeline),
+            [
+                ("activating", "a.service", 5_000_000, 6_000_000),
+                ("active", "a.service", 6_000_000, 8_000_000),
+                ("deactivating", "a.service", 1_000_000, 2_000_000),
+                ("activating", "b.service", 3_000_000, 8_000_000),
+            ]
+        );
+
+        // Dumps and plots without any units are errors
+        for text in [
+            "-> Unit a.service:\n",
+            "",
+            "[{\"name\": \"a.service\"}]",
+            "[{\"name\": ",
+            "[\n",
+        ] {
+            assert!(
+                matches!(
+                    Timeline::from_systemd(text.as_bytes()),
+                    Err(TimelineError::Parse(_))
+                ),
+                "{}",
+                text
+            );
+        }
+        assert!(matches!(
+            Timeline::from_systemd(&b"-> Unit \xff:\n"[..]),
+            Err(TimelineError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,239 @@
+//! Helpers for snapshot testing rendered timelines
+//!
+//! Applications that build timelines from their own instrumentation can use these helpers to
+//! check that the rendered output doesn't change unexpectedly. The timeline is rendered to a
+//! normalized string that doesn't depend on the random colors picked for events, which is
+//! compared to a fixture file stored next to the tests.
+//!
+//! ```no_run
+//! # extern crate timeline_svg;
+//! # fn main() {
+//! use timeline_svg::{testing, Timeline};
+//!
+//! let mut timeline = Timeline::default();
+//! timeline.add_event("Process A".to_string(), 0, 1, "CPU 0".to_string());
+//! testing::assert_fixture(&timeline, "tests/fixtures/process_a.svg");
+//! # }
+//! ```
+//!
+//! Set the `TIMELINE_SVG_BLESS` environment variable to write the current output to the fixtures
+//! instead of comparing against them, which is also how new fixtures are created. Without it a
+//! missing fixture fails the comparison, so a test can't pass without a snapshot to check.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::{TimeValue, Timeline, TimelineError};
+
+/// The environment variable that makes `assert_fixture` rewrite fixtures
+pub const BLESS_VAR: &str = "TIMELINE_SVG_BLESS";
+
+// The most cells `diff` puts in its longest common subsequence table, about 32MB
+const MAX_DIFF_CELLS: usize = 1 << 22;
+
+/// Render a timeline to a normalized SVG string
+///
+/// Events are colored from the palette in the order of their sorted names instead of the colors
+/// picked at random when they were added. Only the names of drawn events, counters and states
+/// count, so renamed, removed or filtered events and imported colors don't shift the colors of
+/// the rest. Attributes are always written in sorted order, and
+/// the string ends with a newline. This fails like `Timeline::write` does.
+pub fn render_normalized<T: TimeValue>(timeline: &Timeline<T>) -> Result<String, TimelineError> {
+    let names: BTreeSet<&String> = timeline
+        .events
+        .iter()
+        .filter(|event| timeline.is_drawn(event))
+        .map(|event| &event.name)
+        .chain(timeline.counters.keys())
+        .chain(
+            timeline
+                .state_tracks
+                .values()
+                .flatten()
+                .map(|(_, state)| state),
+        )
+        .collect();
+    let colormap: HashMap<String, String> = names
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let color = &timeline.palette[i % timeline.palette.len()];
+            (name.clone(), color.clone())
+        })
+        .collect();
+    let mut output = timeline.document(&colormap)?.to_string();
+    output.push('\n');
+    Ok(output)
+}
+
+/// Compare a rendered timeline against a fixture file
+///
+/// This returns `Ok` if the normalized rendering of `timeline` matches the contents of
+/// `fixture`, and otherwise an `Err` with a line diff of the expected and actual output, or
+/// saying that the fixture doesn't exist. The fixture is written instead if the
+/// `TIMELINE_SVG_BLESS` environment variable is set.
+pub fn compare_fixture<T: TimeValue>(
+    timeline: &Timeline<T>,
+    fixture: impl AsRef<Path>,
+) -> Result<(), String> {
+    let fixture = fixture.as_ref();
+    let actual = render_normalized(timeline).map_err(|e| e.to_string())?;
+    if std::env::var_os(BLESS_VAR).is_some() {
+        if let Some(dir) = fixture.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        return fs::write(fixture, actual).map_err(|e| e.to_string());
+    }
+    if !fixture.exists() {
+        return Err(format!(
+            "fixture {} doesn't exist\n(set {} to create it)",
+            fixture.display(),
+            BLESS_VAR
+        ));
+    }
+    let expected = fs::read_to_string(fixture).map_err(|e| e.to_string())?;
+    match diff(&expected, &actual) {
+        None => Ok(()),
+        Some(diff) => Err(format!(
+            "rendered timeline does not match {}\n(set {} to update it)\n{}",
+            fixture.display(),
+            BLESS_VAR,
+            diff
+        )),
+    }
+}
+
+/// Assert that a rendered timeline matches a fixture file
+///
+/// This is `compare_fixture` that panics with the diff on a mismatch.
+pub fn assert_fixture<T: TimeValue>(timeline: &Timeline<T>, fixture: impl AsRef<Path>) {
+    if let Err(message) = compare_fixture(timeline, fixture) {
+        panic!("{}", message);
+    }
+}
+
+/// Diff two strings line by line
+///
+/// This returns `None` if the strings are equal, and otherwise the differing lines, prefixed
+/// with "-" for lines only in `expected` and "+" for lines only in `actual`, along with their
+/// line numbers. Changes too large to line up in reasonable memory are listed line by line
+/// instead, pairing the lines with the same number.
+pub fn diff(expected: &str, actual: &str) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+
+    // Trim the common prefix and suffix so the quadratic part only covers the changes
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_changed = &old[prefix..old.len() - suffix];
+    let new_changed = &new[prefix..new.len() - suffix];
+
+    let mut output = String::new();
+    if old_changed.len().saturating_mul(new_changed.len()) > MAX_DIFF_CELLS {
+        for i in 0..old_changed.len().max(new_changed.len()) {
+            let (a, b) = (old_changed.get(i), new_changed.get(i));
+            if a == b {
+                continue;
+            }
+            if let Some(line) = a {
+                output.push_str(&format!("{:>5} -{}\n", prefix + i + 1, line));
+            }
+            if let Some(line) = b {
+                output.push_str(&format!("{:>5} +{}\n", prefix + i + 1, line));
+            }
+        }
+        return Some(output);
+    }
+
+    // Longest common subsequence table of the changed lines
+    let mut lcs = vec![vec![0usize; new_changed.len() + 1]; old_changed.len() + 1];
+    for i in (0..old_changed.len()).rev() {
+        for j in (0..new_changed.len()).rev() {
+            lcs[i][j] = if old_changed[i] == new_changed[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < old_changed.len() || j < new_changed.len() {
+        if i < old_changed.len() && j < new_changed.len() && old_changed[i] == new_changed[j] {
+            i += 1;
+            j += 1;
+        } else if j == new_changed.len()
+            || (i < old_changed.len() && lcs[i + 1][j] >= lcs[i][j + 1])
+        {
+            output.push_str(&format!("{:>5} -{}\n", prefix + i + 1, old_changed[i]));
+            i += 1;
+        } else {
+            output.push_str(&format!("{:>5} +{}\n", prefix + j + 1, new_changed[j]));
+            j += 1;
+        }
+    }
+    if output.is_empty() {
+        // Only the line endings differ
+        output.push_str("(the outputs differ in whitespace at the end of lines)\n");
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(diff("a\nb\n", "a\nb\n"), None);
+        assert_eq!(
+            diff("a\nb\nc\n", "a\nx\nc\n").unwrap(),
+            "    2 -b\n    2 +x\n"
+        );
+
+        // Large changes are paired up line by line instead of lined up
+        let old: String = (0..3000).map(|i| format!("{}\n", i)).collect();
+        let new: String = (0..3000).map(|i| format!("{}\n", i + 1)).collect();
+        let output = diff(&old, &new).unwrap();
+        assert!(output.starts_with("    1 -0\n    1 +1\n    2 -1\n    2 +2\n"));
+        assert_eq!(output.lines().count(), 6000);
+    }
+
+    #[test]
+    fn test_fixture() {
+        let fixture = std::env::temp_dir()
+            .join(format!("timeline-svg-fixture-{}", std::process::id()))
+            .join("fixture.svg");
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1".to_string(), 1, 2, "Location 1".to_string());
+        let rendered = render_normalized(&timeline).unwrap();
+        assert!(rendered.contains(r#"fill="blue""#));
+
+        // Colors that aren't drawn don't change the snapshot
+        let mut other = Timeline::default();
+        other.add_event("Event 1".to_string(), 1, 2, "Location 1".to_string());
+        other.import_colormap(r#"{"Another event":"red"}"#).unwrap();
+        assert_eq!(render_normalized(&other).unwrap(), rendered);
+
+        let message = compare_fixture(&timeline, &fixture).unwrap_err();
+        assert!(message.contains(BLESS_VAR));
+        assert!(!fixture.exists());
+
+        fs::create_dir_all(fixture.parent().unwrap()).unwrap();
+        fs::write(&fixture, rendered).unwrap();
+        assert_fixture(&timeline, &fixture);
+        timeline.add_event("Event 2".to_string(), 2, 3, "Location 1".to_string());
+        let message = compare_fixture(&timeline, &fixture).unwrap_err();
+        assert!(message.contains("+"));
+        fs::remove_dir_all(fixture.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,116 @@
+use std::path::{Path, PathBuf};
+
+use crate::{write_file, SaveOptions, TimeValue, Timeline, TimelineError};
+
+// The page of `save_tiles` showing the tiles side by side. Tiles only load as they scroll into
+// view, so the browser never holds more than a few of them.
+const INDEX_HEAD: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<style>
+body { margin: 0; }
+.tiles { display: flex; overflow-x: auto; }
+.tiles img { display: block; flex: none; }
+</style>
+</head>
+<body>
+<div class="tiles">
+"#;
+
+const INDEX_TAIL: &str = "</div>\n</body>\n</html>\n";
+
+impl<T: TimeValue> Timeline<T> {
+    /// Save the timeline as `tiles` SVG files of equal time windows and a page showing them
+    ///
+    /// A single SVG of a long trace with many events can be so big that browsers give up on
+    /// it. This splits the time range into `tiles` windows and saves each with `render_region`
+    /// as "tile-N.svg" in `dir`, numbered from 0, along with an "index.html" page that shows the
+    /// tiles next to each other as one timeline, loading them as they scroll into view. `dir`
+    /// is created if it doesn't exist, and every file is written atomically like `save` writes
+    /// it. A timeline without anything on it is saved as a single tile. This returns the paths
+    /// of the tiles in time order.
+    ///
+    /// This returns `TimelineError::InvalidRange` if `tiles` is 0, and otherwise fails like
+    /// `save`.
+    pub fn save_tiles(
+        &self,
+        dir: impl AsRef<Path>,
+        tiles: usize,
+    ) -> Result<Vec<PathBuf>, TimelineError> {
+        if tiles == 0 {
+            return Err(TimelineError::InvalidRange(
+                "a timeline can't be split into 0 tiles".to_string(),
+            ));
+        }
+        let dir = dir.as_ref();
+        let options = SaveOptions { create_dirs: true };
+        let span = self.span();
+        let tiles = if span > 0.0 { tiles } else { 1 };
+
+        let mut paths = Vec::new();
+        let mut index = INDEX_HEAD.to_string();
+        for tile in 0..tiles {
+            let name = format!("tile-{}.svg", tile);
+            let path = dir.join(&name);
+            let mut svg = Vec::new();
+            if span > 0.0 {
+                let start = self
+                    .start_time
+                    .shift(span * tile as f64 / tiles as f64, self.units);
+                let end = if tile + 1 == tiles {
+                    self.end_time
+                } else {
+                    self.start_time
+                        .shift(span * (tile + 1) as f64 / tiles as f64, self.units)
+                };
+                self.render_region(start, end, &mut svg)?;
+            } else {
+                self.write(&mut svg)?;
+            }
+            write_file(&path, options, |writer| writer.write_all(&svg))?;
+            index.push_str(&format!("<img src=\"{}\" loading=\"lazy\">\n", name));
+            paths.push(path);
+        }
+        index.push_str(INDEX_TAIL);
+        write_file(&dir.join("index.html"), options, |writer| {
+            writer.write_all(index.as_bytes())
+        })?;
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_tiles() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1", 0, 6, "Location 1");
+        timeline.add_event("Event 2", 7, 12, "Location 2");
+        let dir = std::env::temp_dir()
+            .join(format!("timeline-tiles-{}", std::process::id()))
+            .join("tiles");
+
+        let paths = timeline.save_tiles(&dir, 3).unwrap();
+        assert_eq!(
+            paths,
+            [0, 1, 2].map(|tile| dir.join(format!("tile-{}.svg", tile)))
+        );
+        let tile = std::fs::read_to_string(&paths[1]).unwrap();
+        assert!(tile.contains(r#"viewBox="800 0 800 61""#));
+        assert!(tile.contains("Event 1") && tile.contains("Event 2"));
+        let tile = std::fs::read_to_string(&paths[2]).unwrap();
+        assert!(!tile.contains("Event 1"));
+        let index = std::fs::read_to_string(dir.join("index.html")).unwrap();
+        assert_eq!(index.matches("<img ").count(), 3);
+        assert!(index.contains(r#"<img src="tile-2.svg" loading="lazy">"#));
+
+        assert!(matches!(
+            timeline.save_tiles(&dir, 0),
+            Err(TimelineError::InvalidRange(_))
+        ));
+        std::fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+}
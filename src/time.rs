@@ -0,0 +1,165 @@
+use std::fmt::Debug;
+use std::time::Duration;
+
+use crate::TimeUnit;
+
+/// A type that can be used for the times of a `Timeline`
+///
+/// Times are stored as given and only turned into pixel offsets when the timeline is rendered,
+/// so no precision is lost by converting everything to one representation up front. This is
+/// implemented for the common integer types, floats and `Duration`.
+///
+/// Numbers are taken to already be in the units of the timeline, see `Timeline::set_units`,
+/// while a `Duration` is converted to those units when rendering.
+pub trait TimeValue: Copy + PartialOrd + Debug {
+    /// The smallest time, used as the end of an empty timeline
+    const MIN: Self;
+    /// The largest time, used as the start of an empty timeline
+    const MAX: Self;
+    /// The smallest distance between ticks on the axis, in units
+    const MIN_STEP: f64;
+
+    /// The number of units from `origin` to `self`, negative if `self` is before `origin`
+    fn offset_from(self, origin: Self, unit: TimeUnit) -> f64;
+
+    /// The time as a number of nanoseconds
+    fn to_nanos(self, unit: TimeUnit) -> i128;
+
+    /// The time closest to a number of nanoseconds
+    fn from_nanos(nanos: i128, unit: TimeUnit) -> Self;
+
+    /// The time closest to `units` units after `self`, or before it if `units` is negative
+    fn shift(self, units: f64, unit: TimeUnit) -> Self;
+}
+
+macro_rules! impl_integer_time {
+    ($($t:ty),*) => {$(
+        impl TimeValue for $t {
+            const MIN: Self = <$t>::MIN;
+            const MAX: Self = <$t>::MAX;
+            const MIN_STEP: f64 = 1.0;
+
+            fn offset_from(self, origin: Self, _unit: TimeUnit) -> f64 {
+                (self as i128 - origin as i128) as f64
+            }
+
+            fn to_nanos(self, unit: TimeUnit) -> i128 {
+                self as i128 * unit.nanoseconds() as i128
+            }
+
+            fn from_nanos(nanos: i128, unit: TimeUnit) -> Self {
+                let units = nanos.div_euclid(unit.nanoseconds() as i128);
+                units.clamp(<$t>::MIN as i128, <$t>::MAX as i128) as $t
+            }
+
+            fn shift(self, units: f64, _unit: TimeUnit) -> Self {
+                // Casting a float to an integer saturates
+                (self as f64 + units.round()) as $t
+            }
+        }
+    )*};
+}
+
+impl_integer_time!(i32, i64, u32, u64);
+
+macro_rules! impl_float_time {
+    ($($t:ty),*) => {$(
+        impl TimeValue for $t {
+            const MIN: Self = <$t>::NEG_INFINITY;
+            const MAX: Self = <$t>::INFINITY;
+            const MIN_STEP: f64 = 0.0;
+
+            fn offset_from(self, origin: Self, _unit: TimeUnit) -> f64 {
+                (self - origin) as f64
+            }
+
+            fn to_nanos(self, unit: TimeUnit) -> i128 {
+                (self as f64 * unit.nanoseconds() as f64) as i128
+            }
+
+            fn from_nanos(nanos: i128, unit: TimeUnit) -> Self {
+                (nanos as f64 / unit.nanoseconds() as f64) as $t
+            }
+
+            fn shift(self, units: f64, _unit: TimeUnit) -> Self {
+                self + units as $t
+            }
+        }
+    )*};
+}
+
+impl_float_time!(f32, f64);
+
+impl TimeValue for Duration {
+    const MIN: Self = Duration::ZERO;
+    const MAX: Self = Duration::MAX;
+    const MIN_STEP: f64 = 0.0;
+
+    fn offset_from(self, origin: Self, unit: TimeUnit) -> f64 {
+        let nanos = self.as_nanos() as i128 - origin.as_nanos() as i128;
+        nanos as f64 / unit.nanoseconds() as f64
+    }
+
+    fn to_nanos(self, _unit: TimeUnit) -> i128 {
+        self.as_nanos() as i128
+    }
+
+    fn shift(self, units: f64, unit: TimeUnit) -> Self {
+        let nanos = (units * unit.nanoseconds() as f64) as i128;
+        <Self as TimeValue>::from_nanos(self.as_nanos() as i128 + nanos, unit)
+    }
+
+    fn from_nanos(nanos: i128, _unit: TimeUnit) -> Self {
+        let nanos = nanos.max(0) as u128;
+        Duration::new(
+            (nanos / 1_000_000_000) as u64,
+            (nanos % 1_000_000_000) as u32,
+        )
+    }
+}
+
+// Format an axis label for `value` units with ticks `step` units apart, using as many decimals as
+// the step needs
+pub(crate) fn format_offset(value: f64, step: f64) -> String {
+    if step >= 1.0 && step.fract() == 0.0 {
+        return format!("{}", value.round() as i64);
+    }
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    let label = format!("{:.*}", decimals, value);
+    // Avoid labeling the origin "-0.00"
+    if label.trim_start_matches(['-', '0', '.']).is_empty() {
+        return format!("{:.*}", decimals, 0.0);
+    }
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_values() {
+        assert_eq!(5u64.offset_from(7, TimeUnit::Seconds), -2.0);
+        assert_eq!(i64::from_nanos(-1, TimeUnit::Microseconds), -1);
+        assert_eq!(u32::from_nanos(-1, TimeUnit::Microseconds), 0);
+        assert_eq!(1.5f64.to_nanos(TimeUnit::Microseconds), 1500);
+        let duration = Duration::from_millis(3);
+        assert_eq!(
+            duration.offset_from(Duration::from_millis(1), TimeUnit::Microseconds),
+            2000.0
+        );
+        assert_eq!(
+            <Duration as TimeValue>::from_nanos(1_500_000_000, TimeUnit::Seconds),
+            Duration::from_millis(1500)
+        );
+        assert_eq!(5u32.shift(-7.0, TimeUnit::Seconds), 0);
+        assert_eq!((-5i64).shift(2.4, TimeUnit::Seconds), -3);
+        assert_eq!(
+            duration.shift(-0.5, TimeUnit::Milliseconds),
+            Duration::from_micros(2500)
+        );
+        assert_eq!(format_offset(3.0, 1.0), "3");
+        assert_eq!(format_offset(0.25, 0.05), "0.25");
+        assert_eq!(format_offset(-0.0001, 0.1), "0.0");
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+
+use serde_json::Value;
+
+use crate::chrome::text;
+use crate::ftrace::parse_time;
+use crate::import::{new_timeline, sniff_text};
+use crate::systemd::days_from_civil;
+use crate::{EventBuilder, Timeline, TimelineError, TraceImporter};
+
+// The JSON logs of tokio's task instrumentation, see `Timeline::from_tokio_tasks`
+pub(crate) struct TokioTasksImporter;
+
+impl TraceImporter for TokioTasksImporter {
+    fn format(&self) -> &str {
+        "tokio-tasks"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        let text = sniff_text(data);
+        text.trim_start().starts_with('{')
+            && (text.contains("\"runtime.spawn\"") || text.contains("\"tokio::task::waker\""))
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_tokio_tasks(data)
+    }
+}
+
+// A timestamp of tracing-subscriber, like "2024-01-01T10:00:00.000001Z" or an uptime like
+// "0.001500000s", in nanoseconds
+fn parse_timestamp(text: &str) -> Option<i64> {
+    if let Some(seconds) = text.trim().strip_suffix('s') {
+        return parse_time(seconds);
+    }
+    let (date, time) = text.split_once('T')?;
+    let mut date = date.split('-');
+    let (year, month, day) = (date.next()?, date.next()?, date.next()?);
+    let days = days_from_civil(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)?;
+    let (clock, offset) = match time.strip_suffix('Z') {
+        Some(clock) => (clock, 0),
+        None => {
+            let index = time.rfind(['+', '-'])?;
+            let (hours, minutes) = time[index + 1..].split_once(':')?;
+            let offset = hours
+                .parse::<i64>()
+                .ok()?
+                .checked_mul(60)?
+                .checked_add(minutes.parse().ok()?)?;
+            let sign = if time[index..].starts_with('-') {
+                -1
+            } else {
+                1
+            };
+            (&time[..index], sign * offset)
+        }
+    };
+    let mut clock = clock.split(':');
+    let (hours, minutes, seconds) = (clock.next()?, clock.next()?, clock.next()?);
+    let minutes = days
+        .checked_mul(24)?
+        .checked_add(hours.parse().ok()?)?
+        .checked_mul(60)?
+        .checked_add(minutes.parse().ok()?)?
+        .checked_sub(offset)?;
+    minutes
+        .checked_mul(60_000_000_000)?
+        .checked_add(parse_time(seconds)?)
+}
+
+// The row of the thread that logged a line, like "tokio-runtime-worker-2" for the thread named
+// "tokio-runtime-worker" with the id "ThreadId(2)"
+fn thread_row(line: &Value) -> String {
+    let name = line["threadName"].as_str().unwrap_or("thread");
+    match line["threadId"].as_str() {
+        Some(id) => {
+            let id = id
+                .strip_prefix("ThreadId(")
+                .and_then(|id| id.strip_suffix(')'))
+                .unwrap_or(id);
+            format!("{}-{}", name, id)
+        }
+        None => name.to_string(),
+    }
+}
+
+// A poll of a task, as it began
+struct Poll {
+    task: String,
+    start: i64,
+    span: Value,
+}
+
+impl Timeline {
+    /// Read the task instrumentation of tokio into a timeline
+    ///
+    /// tokio built with `--cfg tokio_unstable` and its `tracing` feature instruments every
+    /// task with a `runtime.spawn` span, that is entered whenever the task is polled, and logs
+    /// an event with the target `tokio::task::waker` whenever a task is woken. This reads them
+    /// from the JSON lines of `tracing-subscriber` logging the entering and exiting of spans,
+    /// along with thread ids and names, like this subscriber does:
+    ///
+    /// ```text
+    /// tracing_subscriber::fmt()
+    ///     .json()
+    ///     .with_span_events(FmtSpan::ENTER | FmtSpan::EXIT)
+    ///     .with_thread_ids(true)
+    ///     .with_thread_names(true)
+    ///     .with_env_filter("tokio=trace,runtime=trace")
+    ///     .init();
+    /// ```
+    ///
+    /// Every poll of a task becomes an event named after the task, or like "task 3" for tasks
+    /// without names, on the row of the thread that polled it, like "tokio-runtime-worker-2".
+    /// The fields of the span, like "task.id", "kind" and "loc.file", are its metadata. A
+    /// `waker.wake` or `waker.wake_by_ref` of a task becomes a trigger from the row of the
+    /// thread that woke it, when it did, to the next poll of the task, if that is on another
+    /// row. Polls that don't end last until the last time of the log, and other lines are
+    /// skipped. Times are in nanoseconds, since the epoch or since the start of the program
+    /// with an uptime timer.
+    ///
+    /// This returns `TimelineError::Parse` if the log has no polls, and `TimelineError::Io` if
+    /// reading it fails. `Timeline::import` detects logs of this format, as "tokio-tasks".
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let log = r#"
+    /// {"timestamp":"0.001000000s","fields":{"message":"enter"},"span":{"task.id":1,"name":"runtime.spawn"},"threadName":"tokio-runtime-worker","threadId":"ThreadId(2)"}
+    /// {"timestamp":"0.001500000s","fields":{"message":"exit"},"span":{"task.id":1,"name":"runtime.spawn"},"threadName":"tokio-runtime-worker","threadId":"ThreadId(2)"}
+    /// "#;
+    /// let timeline = Timeline::from_tokio_tasks(log.as_bytes()).unwrap();
+    /// assert_eq!(timeline.categories(), ["tokio-runtime-worker-2"]);
+    /// assert_eq!(timeline.time_range(), Some((1_000_000, 1_500_000)));
+    /// # }
+    /// ```
+    pub fn from_tokio_tasks(reader: impl Read) -> Result<Timeline, TimelineError> {
+        let mut last = 0;
+        // The polls begun and not ended yet, by thread
+        let mut open: HashMap<String, Vec<Poll>> = HashMap::new();
+        // The polls, with their row and end
+        let mut polls = Vec::new();
+        // The first wakeup of every task since it was last polled, as the row and time
+        let mut woken: HashMap<String, (String, i64)> = HashMap::new();
+        let mut wakeups = Vec::new();
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            let Ok(line) = serde_json::from_str::<Value>(&line) else {
+                continue;
+            };
+            let Some(time) = line["timestamp"].as_str().and_then(parse_timestamp) else {
+                continue;
+            };
+            last = last.max(time);
+            let fields = &line["fields"];
+            let span = &line["span"];
+            if line["target"] == "tokio::task::waker" {
+                if fields["op"] == "waker.wake" || fields["op"] == "waker.wake_by_ref" {
+                    woken
+                        .entry(text(&fields["task.id"]))
+                        .or_insert((thread_row(&line), time));
+                }
+                continue;
+            }
+            if span["name"] != "runtime.spawn" {
+                continue;
+            }
+            let task = text(&span["task.id"]);
+            let row = thread_row(&line);
+            match fields["message"].as_str() {
+                Some("enter") => {
+                    if let Some((waker, woke)) = woken.remove(&task) {
+                        if waker != row {
+                            wakeups.push((waker, woke, row.clone(), time));
+                        }
+                    }
+                    open.entry(row).or_default().push(Poll {
+                        task,
+                        start: time,
+                        span: span.clone(),
+                    });
+                }
+                Some("exit") => {
+                    let Some(polling) = open.get_mut(&row) else {
+                        continue;
+                    };
+                    if let Some(index) = polling.iter().rposition(|poll| poll.task == task) {
+                        let poll = polling.remove(index);
+                        polls.push((poll, row, time));
+                    }
+                }
+                _ => {}
+            }
+        }
+        let mut unfinished: Vec<_> = open.into_iter().collect();
+        unfinished.sort_by(|a, b| a.0.cmp(&b.0));
+        for (row, begun) in unfinished {
+            for poll in begun {
+                polls.push((poll, row.clone(), last));
+            }
+        }
+        if polls.is_empty() {
+            return Err(TimelineError::Parse("no polls of tasks".to_string()));
+        }
+
+        let mut timeline = new_timeline();
+        polls.sort_by_key(|(poll, _, _)| poll.start);
+        for (poll, row, end) in polls {
+            let name = match poll.span["task.name"].as_str() {
+                Some(name) => name.to_string(),
+                None => format!("task {}", poll.task),
+            };
+            let mut event = EventBuilder::new(name, poll.start, end.max(poll.start), row);
+            for (key, value) in poll.span.as_object().into_iter().flatten() {
+                if key != "name" {
+                    event = event.metadata(key.clone(), text(value));
+                }
+            }
+            timeline.insert_event(event.build());
+        }
+        for (waker, woke, row, polled) in wakeups {
+            if timeline.has_location(&waker) {
+                timeline.add_trigger_span(waker, woke, row, polled);
+            }
+        }
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    // A line logged on a worker thread at a time in microseconds of uptime
+    fn line(micros: i64, worker: u32, mut line: Value) -> String {
+        line["timestamp"] = json!(format!("0.{:09}s", micros * 1000));
+        line["threadName"] = json!("tokio-runtime-worker");
+        line["threadId"] = json!(format!("ThreadId({})", worker));
+        line.to_string()
+    }
+
+    // The entering or exiting of the span of a task
+    fn poll(micros: i64, worker: u32, message: &str, task: u32) -> String {
+        let span = json!({"kind": "task", "task.id": task, "name": "runtime.spawn"});
+        line(
+            micros,
+            worker,
+            json!({"fields": {"message": message}, "span": span}),
+        )
+    }
+
+    fn wake(micros: i64, worker: u32, op: &str, task: u32) -> String {
+        let fields = json!({"op": op, "task.id": task});
+        line(
+            micros,
+            worker,
+            json!({"target": "tokio::task::waker", "fields": fields}),
+        )
+    }
+
+    fn events(timeline: &Timeline) -> Vec<(&str, &str, i64, i64)> {
+        timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name(),
+                    event.location(),
+                    event.start_time() / 1000,
+                    event.end_time() / 1000,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(
+            parse_timestamp("1970-01-02T00:00:01.5Z"),
+            Some(86_401_500_000_000)
+        );
+        assert_eq!(
+            parse_timestamp("1970-01-02T02:00:01.5+02:00"),
+            Some(86_401_500_000_000)
+        );
+        assert_eq!(
+            parse_timestamp("1970-01-01T23:30:01.5-00:30"),
+            Some(86_401_500_000_000)
+        );
+        assert_eq!(parse_timestamp("0.001500000s"), Some(1_500_000));
+        assert_eq!(parse_timestamp("1970-01-02T00:00:01.5"), None);
+        assert_eq!(parse_timestamp("1970-01-02"), None);
+        assert_eq!(parse_timestamp("soon"), None);
+        assert_eq!(parse_timestamp("1970-01-02T9999999999999:00:01.5Z"), None);
+        assert_eq!(
+            parse_timestamp("1970-01-02T00:00:01.5+999999999999999999:00"),
+            None
+        );
+        assert_eq!(
+            thread_row(&json!({"threadName": "worker", "threadId": "ThreadId(2)"})),
+            "worker-2"
+        );
+        assert_eq!(thread_row(&json!({"threadId": "7"})), "thread-7");
+        assert_eq!(thread_row(&json!({"threadName": "main"})), "main");
+        assert_eq!(thread_row(&json!({})), "thread");
+    }
+
+    #[test]
+    fn test_from_tokio_tasks() {
+        let named = json!({
+            "kind": "task",
+            "task.id": 2,
+            "task.name": "accept",
+            "loc.file": "src/main.rs",
+            "loc.line": 10,
+            "name": "runtime.spawn",
+        });
+        let log = [
+            poll(1, 2, "enter", 1),
+            poll(3, 2, "exit", 1),
+            "not json".to_string(),
+            line(4, 3, json!({"fields": {"message": "enter"}, "span": named})),
+            line(5, 3, json!({"fields": {"message": "hello"}, "span": named})),
+            line(6, 3, json!({"fields": {"message": "exit"}, "span": named})),
+            line(
+                7,
+                3,
+                json!({"fields": {"message": "enter"}, "span": {"name": "other"}}),
+            ),
+        ]
+        .join("\n");
+        assert!(TokioTasksImporter.sniff(log.as_bytes()));
+        // Other logs of tracing-subscriber aren't of tasks
+        assert!(!TokioTasksImporter.sniff(b"{\"fields\": {\"message\": \"enter\"}}\n"));
+        let timeline = Timeline::from_tokio_tasks(log.as_bytes()).unwrap();
+        assert_eq!(
+            events(&timeline),
+            [
+                ("task 1", "tokio-runtime-worker-2", 1, 3),
+                ("accept", "tokio-runtime-worker-3", 4, 6),
+            ]
+        );
+        // The fields of the span are the metadata of its polls, as text
+        let (_, accept) = timeline.events().nth(1).unwrap();
+        assert_eq!(accept.metadata()["task.id"], "2");
+        assert_eq!(accept.metadata()["task.name"], "accept");
+        assert_eq!(accept.metadata()["loc.line"], "10");
+        assert!(!accept.metadata().contains_key("name"));
+
+        // Polls nest, exits of polls that weren't entered are skipped, and polls that don't
+        // end last until the last time of the log
+        let unfinished = [
+            poll(1, 2, "enter", 1),
+            poll(2, 2, "enter", 2),
+            poll(3, 2, "exit", 1),
+            poll(4, 2, "exit", 3),
+            poll(5, 5, "exit", 1),
+            wake(6, 3, "waker.wake", 1),
+            r#"{"fields":{"message":"exit"},"span":{"task.id":2,"name":"runtime.spawn"}}"#
+                .to_string(),
+        ]
+        .join("\n");
+        let timeline = Timeline::from_tokio_tasks(unfinished.as_bytes()).unwrap();
+        assert_eq!(
+            events(&timeline),
+            [
+                ("task 1", "tokio-runtime-worker-2", 1, 3),
+                ("task 2", "tokio-runtime-worker-2", 2, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wakeups() {
+        let log = [
+            poll(1, 2, "enter", 1),
+            poll(2, 2, "exit", 1),
+            poll(3, 3, "enter", 2),
+            // Only the first wakeup since a poll counts, and clones don't wake
+            wake(4, 3, "waker.wake_by_ref", 1),
+            wake(5, 3, "waker.wake", 1),
+            wake(5, 3, "waker.clone", 2),
+            poll(6, 3, "exit", 2),
+            poll(7, 2, "enter", 1),
+            poll(8, 2, "exit", 1),
+            // Threads that never poll don't have rows to start triggers on
+            wake(9, 4, "waker.wake", 2),
+            poll(10, 2, "enter", 2),
+            poll(11, 2, "exit", 2),
+            // Tasks woken on the row they are polled on don't need triggers
+            wake(12, 2, "waker.wake", 1),
+            poll(13, 2, "enter", 1),
+            poll(14, 2, "exit", 1),
+        ]
+        .join("\n");
+        let timeline = Timeline::from_tokio_tasks(log.as_bytes()).unwrap();
+        let triggers: Vec<_> = timeline
+            .triggers()
+            .map(|trigger| {
+                (
+                    trigger.start_location(),
+                    trigger.end_location(),
+                    trigger.time(),
+                    trigger.end_time(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            triggers,
+            [(
+                "tokio-runtime-worker-3",
+                "tokio-runtime-worker-2",
+                4000,
+                7000
+            )]
+        );
+
+        // Logs without any polls are errors
+        assert!(matches!(
+            Timeline::from_tokio_tasks("{}\n".as_bytes()),
+            Err(TimelineError::Parse(_))
+        ));
+        assert!(matches!(
+            Timeline::from_tokio_tasks(wake(1, 2, "waker.wake", 1).as_bytes()),
+            Err(TimelineError::Parse(_))
+        ));
+        assert!(matches!(
+            Timeline::from_tokio_tasks(&b"{\"timestamp\": \"\xff\"}\n"[..]),
+            Err(TimelineError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,719 @@
+use std::collections::HashMap;
+use std::io::Read;
+
+use crate::ftrace::{task_row, SchedTimeline};
+use crate::{Timeline, TimelineError, TraceImporter};
+
+const MAGIC: &[u8] = b"\x17\x08\x44tracing";
+
+// Ring buffer event types, in the low five bits of the header of every event
+const TYPE_PADDING: u32 = 29;
+const TYPE_TIME_EXTEND: u32 = 30;
+const TYPE_TIME_STAMP: u32 = 31;
+const TIME_SHIFT: u32 = 27;
+
+// The low bits of the commit field of a page header are the length of its data, the high ones
+// flag missed events
+const COMMIT_MASK: u64 = (1 << 27) - 1;
+
+// The trace-cmd binary format, see `Timeline::from_trace_cmd`
+pub(crate) struct TraceCmdImporter;
+
+impl TraceImporter for TraceCmdImporter {
+    fn format(&self) -> &str {
+        "trace-cmd"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        data.starts_with(MAGIC)
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_trace_cmd(data)
+    }
+}
+
+fn truncated() -> TimelineError {
+    TimelineError::Parse("trace-cmd file is truncated".to_string())
+}
+
+// Reads the fields of a trace-cmd file in its byte order
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl<'a> Cursor<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], TimelineError> {
+        let end = self.pos.checked_add(len).ok_or_else(truncated)?;
+        let bytes = self.data.get(self.pos..end).ok_or_else(truncated)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn uint(&mut self, len: usize) -> Result<u64, TimelineError> {
+        let bytes = self.bytes(len)?;
+        Ok(uint(bytes, self.big_endian))
+    }
+
+    // A string ended by a nul
+    fn string(&mut self) -> Result<&'a str, TimelineError> {
+        let len = self.data[self.pos.min(self.data.len())..]
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or_else(truncated)?;
+        let text = std::str::from_utf8(self.bytes(len)?)
+            .map_err(|e| TimelineError::Parse(e.to_string()))?;
+        self.pos += 1;
+        Ok(text)
+    }
+
+    // A section of text preceded by its size in `len` bytes
+    fn text(&mut self, len: usize) -> Result<String, TimelineError> {
+        let size = self.uint(len)? as usize;
+        Ok(String::from_utf8_lossy(self.bytes(size)?).into_owned())
+    }
+}
+
+fn uint(bytes: &[u8], big_endian: bool) -> u64 {
+    let fold = |value: u64, byte: &u8| value << 8 | *byte as u64;
+    if big_endian {
+        bytes.iter().fold(0, fold)
+    } else {
+        bytes.iter().rev().fold(0, fold)
+    }
+}
+
+// The offset and size of the fields of a record, from the "field:" lines of a format like
+// "field:pid_t prev_pid;	offset:24;	size:4;	signed:1;"
+fn parse_fields(format: &str) -> HashMap<String, (usize, usize)> {
+    let mut fields = HashMap::new();
+    for line in format.lines() {
+        let mut parts = line.trim().split(';');
+        let Some(declaration) = parts.next().and_then(|part| part.strip_prefix("field:")) else {
+            continue;
+        };
+        let name = declaration.split_whitespace().last().unwrap_or_default();
+        let name = name.split('[').next().unwrap_or_default();
+        let value = |key: &str| {
+            line.split(';')
+                .find_map(|part| part.trim().strip_prefix(key))
+                .and_then(|value| value.parse().ok())
+        };
+        if let (Some(offset), Some(size)) = (value("offset:"), value("size:")) {
+            fields.insert(name.to_string(), (offset, size));
+        }
+    }
+    fields
+}
+
+// The format of an event, from its "name:" and "ID:" lines and its fields
+struct Format {
+    name: String,
+    fields: HashMap<String, (usize, usize)>,
+}
+
+fn parse_format(format: &str) -> Option<(u64, Format)> {
+    let line = |key: &str| {
+        format
+            .lines()
+            .find_map(|line| line.strip_prefix(key))
+            .map(str::trim)
+    };
+    let id = line("ID:")?.parse().ok()?;
+    let name = line("name:")?.to_string();
+    Some((
+        id,
+        Format {
+            name,
+            fields: parse_fields(format),
+        },
+    ))
+}
+
+// A record of the ring buffer of a CPU
+struct Record<'a> {
+    cpu: u32,
+    time: u64,
+    data: &'a [u8],
+}
+
+impl Timeline {
+    /// Read a trace-cmd `.dat` file into a timeline
+    ///
+    /// This reads what `trace-cmd record` saves, in version 6 of the format, without running
+    /// `trace-cmd report` first, and draws it like `from_ftrace` draws the text of the same
+    /// trace: a row for every CPU and for every task that runs, from the `sched_switch` events,
+    /// triggers from the `sched_wakeup` and `sched_wakeup_new` events, and instants for the
+    /// function tracer, named after the functions in the kernel symbols the file carries. Names
+    /// of waking tasks come from the saved command lines. Only the top level buffer is read,
+    /// and other events are skipped.
+    ///
+    /// This is only available with the `trace-cmd` feature. This returns
+    /// `TimelineError::Parse` if the file isn't a trace-cmd file of a supported version or is
+    /// truncated, and `TimelineError::Io` if reading it fails. `Timeline::import` detects
+    /// files of this format, as "trace-cmd".
+    pub fn from_trace_cmd(mut reader: impl Read) -> Result<Timeline, TimelineError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        if !data.starts_with(MAGIC) {
+            return Err(TimelineError::Parse("not a trace-cmd file".to_string()));
+        }
+        let mut cursor = Cursor {
+            data: &data,
+            pos: MAGIC.len(),
+            big_endian: false,
+        };
+        let version = cursor.string()?;
+        if version != "6" {
+            return Err(TimelineError::Parse(format!(
+                "trace-cmd file version {} isn't supported",
+                version
+            )));
+        }
+        cursor.big_endian = cursor.bytes(1)?[0] == 1;
+        let _long_size = cursor.bytes(1)?;
+        let page_size = cursor.uint(4)? as usize;
+
+        if cursor.bytes(12)? != b"header_page\0" {
+            return Err(TimelineError::Parse("missing page header".to_string()));
+        }
+        let page_fields = parse_fields(&cursor.text(8)?);
+        let (commit_offset, commit_size) = page_fields.get("commit").copied().unwrap_or((8, 8));
+        let data_offset = page_fields.get("data").map_or(16, |(offset, _)| *offset);
+        if cursor.bytes(13)? != b"header_event\0" {
+            return Err(TimelineError::Parse("missing event header".to_string()));
+        }
+        cursor.text(8)?;
+
+        let mut formats = HashMap::new();
+        for _ in 0..cursor.uint(4)? {
+            formats.extend(parse_format(&cursor.text(8)?));
+        }
+        for _ in 0..cursor.uint(4)? {
+            cursor.string()?;
+            for _ in 0..cursor.uint(4)? {
+                formats.extend(parse_format(&cursor.text(8)?));
+            }
+        }
+        let mut symbols: Vec<(u64, String)> = cursor
+            .text(4)?
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let address = u64::from_str_radix(fields.next()?, 16).ok()?;
+                Some((address, fields.nth(1)?.to_string()))
+            })
+            .collect();
+        symbols.sort();
+        cursor.text(4)?;
+        let comms: HashMap<String, String> = cursor
+            .text(8)?
+            .lines()
+            .filter_map(|line| {
+                let (pid, comm) = line.split_once(' ')?;
+                Some((pid.to_string(), comm.to_string()))
+            })
+            .collect();
+
+        let cpus = cursor.uint(4)?;
+        let mut section = cursor.bytes(10)?;
+        if section == b"options  \0" {
+            loop {
+                if cursor.uint(2)? == 0 {
+                    break;
+                }
+                let size = cursor.uint(4)? as usize;
+                cursor.bytes(size)?;
+            }
+            section = cursor.bytes(10)?;
+        }
+        if section != b"flyrecord\0" {
+            return Err(TimelineError::Parse(
+                "only flyrecord traces are supported".to_string(),
+            ));
+        }
+
+        let mut records = Vec::new();
+        for cpu in 0..cpus as u32 {
+            let offset = cursor.uint(8)? as usize;
+            let size = cursor.uint(8)? as usize;
+            let buffer = data
+                .get(offset..offset.saturating_add(size))
+                .ok_or_else(truncated)?;
+            for page in buffer.chunks(page_size.max(1)) {
+                read_page(
+                    page,
+                    cpu,
+                    cursor.big_endian,
+                    (commit_offset, commit_size, data_offset),
+                    &mut records,
+                )?;
+            }
+        }
+        // Merge the buffers of the CPUs, keeping the order within each
+        records.sort_by_key(|record| record.time);
+
+        let mut sched = SchedTimeline::default();
+        for record in records {
+            let time = record.time as i64;
+            let Some(format) = uint_field(&record, &formats, "common_type", cursor.big_endian)
+                .and_then(|id| formats.get(&id))
+            else {
+                sched.event(time);
+                continue;
+            };
+            let number = |name| {
+                uint_field(&record, &formats, name, cursor.big_endian)
+                    .map(|value| (value as i32).to_string())
+            };
+            let string = |name: &str| {
+                let (offset, size) = format.fields.get(name)?;
+                let bytes = record.data.get(*offset..offset + size)?;
+                let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+                Some(String::from_utf8_lossy(&bytes[..end]).into_owned())
+            };
+            let here = || {
+                let pid = number("common_pid").unwrap_or_default();
+                let comm = comms.get(&pid).map_or("<...>", String::as_str);
+                task_row(comm, &pid, record.cpu)
+            };
+            match format.name.as_str() {
+                "sched_switch" => {
+                    if let (Some(comm), Some(pid)) = (string("next_comm"), number("next_pid")) {
+                        sched.switch(record.cpu, time, &comm, &pid);
+                    }
+                }
+                "sched_wakeup" | "sched_wakeup_new" => {
+                    if let (Some(comm), Some(pid)) = (string("comm"), number("pid")) {
+                        sched.wakeup(here(), time, &comm, &pid);
+                    }
+                }
+                "function" => {
+                    let ip = uint_field(&record, &formats, "ip", cursor.big_endian);
+                    let index = ip.map(|ip| symbols.partition_point(|(address, _)| *address <= ip));
+                    match (ip, index) {
+                        (Some(_), Some(index)) if index > 0 => {
+                            sched.instant(here(), time, &symbols[index - 1].1)
+                        }
+                        (Some(ip), _) => sched.instant(here(), time, &format!("{:#x}", ip)),
+                        _ => sched.event(time),
+                    }
+                }
+                _ => sched.event(time),
+            }
+        }
+        Ok(sched.finish())
+    }
+}
+
+// An unsigned field of a record, found in the format of the event the record is
+fn uint_field(
+    record: &Record,
+    formats: &HashMap<u64, Format>,
+    name: &str,
+    big_endian: bool,
+) -> Option<u64> {
+    // Every event starts with its type, which says where its other fields are
+    let id = uint(record.data.get(0..2)?, big_endian);
+    let (offset, size) = match name {
+        "common_type" => (0, 2),
+        name => *formats.get(&id)?.fields.get(name)?,
+    };
+    Some(uint(record.data.get(offset..offset + size)?, big_endian))
+}
+
+// Read the records of a page of the ring buffer of `cpu`, laid out as described by the offset
+// and size of the commit field and the offset of the data in its header
+fn read_page<'a>(
+    page: &'a [u8],
+    cpu: u32,
+    big_endian: bool,
+    (commit_offset, commit_size, data_offset): (usize, usize, usize),
+    records: &mut Vec<Record<'a>>,
+) -> Result<(), TimelineError> {
+    let field = |offset: usize, size: usize| {
+        page.get(offset..offset + size)
+            .map(|bytes| uint(bytes, big_endian))
+            .ok_or_else(truncated)
+    };
+    let mut time = field(0, 8)?;
+    let length = (field(commit_offset, commit_size)? & COMMIT_MASK) as usize;
+    let end = (data_offset + length).min(page.len());
+    let mut pos = data_offset;
+    while pos + 4 <= end {
+        let header = field(pos, 4)? as u32;
+        let (type_len, delta) = if big_endian {
+            (header >> TIME_SHIFT, header & ((1 << TIME_SHIFT) - 1))
+        } else {
+            (header & 0x1f, header >> 5)
+        };
+        pos += 4;
+        match type_len {
+            TYPE_PADDING => {
+                // Padding without a delta fills the rest of the page
+                if delta == 0 {
+                    break;
+                }
+                time += delta as u64;
+                pos += field(pos, 4)? as usize;
+            }
+            TYPE_TIME_EXTEND => {
+                time += (field(pos, 4)? << TIME_SHIFT) + delta as u64;
+                pos += 4;
+            }
+            TYPE_TIME_STAMP => {
+                time = (field(pos, 4)? << TIME_SHIFT) | delta as u64;
+                pos += 4;
+            }
+            _ => {
+                time += delta as u64;
+                let len = if type_len == 0 {
+                    let len = (field(pos, 4)? as usize).saturating_sub(4);
+                    pos += 4;
+                    (len + 3) & !3
+                } else {
+                    type_len as usize * 4
+                };
+                let data = page.get(pos..pos + len).ok_or_else(truncated)?;
+                records.push(Record { cpu, time, data });
+                pos += len;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SWITCH_FORMAT: &str = "name: sched_switch
+ID: 316
+format:
+\tfield:unsigned short common_type;\toffset:0;\tsize:2;\tsigned:0;
+\tfield:int common_pid;\toffset:4;\tsize:4;\tsigned:1;
+
+\tfield:char prev_comm[16];\toffset:8;\tsize:16;\tsigned:0;
+\tfield:pid_t prev_pid;\toffset:24;\tsize:4;\tsigned:1;
+\tfield:char next_comm[16];\toffset:28;\tsize:16;\tsigned:0;
+\tfield:pid_t next_pid;\toffset:44;\tsize:4;\tsigned:1;
+";
+
+    const WAKEUP_FORMAT: &str = "name: sched_wakeup
+ID: 318
+format:
+\tfield:unsigned short common_type;\toffset:0;\tsize:2;\tsigned:0;
+\tfield:int common_pid;\toffset:4;\tsize:4;\tsigned:1;
+
+\tfield:char comm[16];\toffset:8;\tsize:16;\tsigned:0;
+\tfield:pid_t pid;\toffset:24;\tsize:4;\tsigned:1;
+";
+
+    const FUNCTION_FORMAT: &str = "name: function
+ID: 1
+format:
+\tfield:unsigned short common_type;\toffset:0;\tsize:2;\tsigned:0;
+\tfield:int common_pid;\toffset:4;\tsize:4;\tsigned:1;
+
+\tfield:unsigned long ip;\toffset:8;\tsize:8;\tsigned:0;
+\tfield:unsigned long parent_ip;\toffset:16;\tsize:8;\tsigned:0;
+";
+
+    fn comm(name: &str) -> [u8; 16] {
+        let mut comm = [0; 16];
+        comm[..name.len()].copy_from_slice(name.as_bytes());
+        comm
+    }
+
+    fn record(id: u16, pid: i32, fields: &[&[u8]]) -> Vec<u8> {
+        let mut record = id.to_le_bytes().to_vec();
+        record.extend([0, 0]);
+        record.extend(pid.to_le_bytes());
+        for field in fields {
+            record.extend(*field);
+        }
+        record
+    }
+
+    // A ring buffer event of `record`, `delta` nanoseconds after the one before it
+    fn event(delta: u32, record: &[u8]) -> Vec<u8> {
+        let mut event = ((delta << 5) | (record.len() as u32 / 4))
+            .to_le_bytes()
+            .to_vec();
+        event.extend(record);
+        event
+    }
+
+    fn page(time: u64, events: &[Vec<u8>]) -> Vec<u8> {
+        let data = events.concat();
+        let mut page = time.to_le_bytes().to_vec();
+        page.extend((data.len() as u64 | 1 << 31).to_le_bytes());
+        page.extend(data);
+        page.resize(4096, 0);
+        page
+    }
+
+    fn trace_cmd_file(pages: &[Vec<u8>]) -> Vec<u8> {
+        let mut file = MAGIC.to_vec();
+        file.extend(b"6\0\x00\x08");
+        file.extend(4096u32.to_le_bytes());
+        let text = |file: &mut Vec<u8>, size_len: usize, text: &str| {
+            file.extend(&(text.len() as u64).to_le_bytes()[..size_len]);
+            file.extend(text.as_bytes());
+        };
+        file.extend(b"header_page\0");
+        text(
+            &mut file,
+            8,
+            "\tfield: u64 timestamp;\toffset:0;\tsize:8;\tsigned:0;\n\
+             \tfield: local_t commit;\toffset:8;\tsize:8;\tsigned:1;\n\
+             \tfield: char data;\toffset:16;\tsize:4080;\tsigned:1;\n",
+        );
+        file.extend(b"header_event\0");
+        text(&mut file, 8, "# compressed entry header\n");
+        file.extend(1u32.to_le_bytes());
+        text(&mut file, 8, FUNCTION_FORMAT);
+        file.extend(1u32.to_le_bytes());
+        file.extend(b"sched\0");
+        file.extend(2u32.to_le_bytes());
+        text(&mut file, 8, SWITCH_FORMAT);
+        text(&mut file, 8, WAKEUP_FORMAT);
+        text(
+            &mut file,
+            4,
+            "ffffffff81000000 T _stext\nffffffff81001000 T do_exit\n",
+        );
+        text(&mut file, 4, "");
+        text(&mut file, 8, "10 bash\n11 cat\n");
+        file.extend((pages.len() as u32).to_le_bytes());
+        file.extend(b"options  \0");
+        file.extend(3u16.to_le_bytes());
+        file.extend(4u32.to_le_bytes());
+        file.extend(b"abcd");
+        file.extend(0u16.to_le_bytes());
+        file.extend(b"flyrecord\0");
+        let start = file.len() + pages.len() * 16;
+        for (cpu, page) in pages.iter().enumerate() {
+            file.extend(((start + cpu * 4096) as u64).to_le_bytes());
+            file.extend((page.len() as u64).to_le_bytes());
+        }
+        file.extend(pages.concat());
+        file
+    }
+
+    fn switch(prev: &str, prev_pid: i32, next: &str, next_pid: i32) -> Vec<u8> {
+        let (prev_comm, next_comm) = (comm(prev), comm(next));
+        let (prev_pid_bytes, next_pid_bytes) = (prev_pid.to_le_bytes(), next_pid.to_le_bytes());
+        record(
+            316,
+            prev_pid,
+            &[&prev_comm, &prev_pid_bytes, &next_comm, &next_pid_bytes],
+        )
+    }
+
+    fn function(pid: i32, ip: u64) -> Vec<u8> {
+        record(1, pid, &[&ip.to_le_bytes(), &[0; 8]])
+    }
+
+    fn events(timeline: &Timeline) -> Vec<(&str, &str, i64, i64)> {
+        timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name(),
+                    event.location(),
+                    event.start_time(),
+                    event.end_time(),
+                )
+            })
+            .collect()
+    }
+
+    fn error(file: &[u8]) -> String {
+        match Timeline::from_trace_cmd(file) {
+            Err(TimelineError::Parse(what)) => what,
+            other => panic!("{:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_parse_format() {
+        let (id, format) = parse_format(SWITCH_FORMAT).unwrap();
+        assert_eq!((id, format.name.as_str()), (316, "sched_switch"));
+        // Arrays are named without their length
+        assert_eq!(format.fields["next_comm"], (28, 16));
+        assert_eq!(format.fields["common_type"], (0, 2));
+        assert_eq!(format.fields.len(), 6);
+        // Fields without an offset or size are skipped
+        let fields =
+            parse_fields("\tfield:int a;\toffset:4;\n\tfield:int b;\toffset:8;\tsize:4;\n");
+        assert_eq!(
+            fields.into_iter().collect::<Vec<_>>(),
+            [("b".to_string(), (8, 4))]
+        );
+        assert!(parse_format("name: no_id\nformat:\n").is_none());
+        assert!(parse_format("ID: 7\nformat:\n").is_none());
+        assert!(parse_format("name: bad\nID: seven\n").is_none());
+    }
+
+    #[test]
+    fn test_read_page() {
+        assert_eq!(uint(&[0x01, 0x02], false), 0x0201);
+        assert_eq!(uint(&[0x01, 0x02], true), 0x0102);
+        assert_eq!(uint(&u64::MAX.to_le_bytes(), false), u64::MAX);
+
+        let word = |value: u32| value.to_le_bytes().to_vec();
+        let events = [
+            word(5 << 5 | 1),
+            vec![1; 4],
+            // Deltas too large for an event header extend the time
+            word(3 << 5 | TYPE_TIME_EXTEND),
+            word(1),
+            word(1),
+            vec![2; 4],
+            // Absolute timestamps replace it
+            word(7 << 5 | TYPE_TIME_STAMP),
+            word(2),
+            // Records too large for the type and length field have their length first
+            word(1 << 5),
+            word(10),
+            vec![3; 8],
+            // Padding with a delta moves the time on and skips its length, which counts itself
+            word(2 << 5 | TYPE_PADDING),
+            word(8),
+            vec![0xff; 4],
+            word(1 << 5 | 1),
+            vec![4; 4],
+            // Padding without a delta ends the page
+            word(TYPE_PADDING),
+            word(1 << 5 | 1),
+            vec![5; 4],
+        ]
+        .concat();
+        let mut page = 1000u64.to_le_bytes().to_vec();
+        // The high bits of the commit field flag missed events
+        page.extend((events.len() as u64 | 1 << 31).to_le_bytes());
+        page.extend(events);
+        let mut records = Vec::new();
+        read_page(&page, 3, false, (8, 8, 16), &mut records).unwrap();
+        let records: Vec<_> = records
+            .iter()
+            .map(|record| (record.cpu, record.time, record.data.to_vec()))
+            .collect();
+        let extended = 1005 + (1 << 27) + 3;
+        let stamped = (2 << 27) | 7;
+        assert_eq!(
+            records,
+            [
+                (3, 1005, vec![1; 4]),
+                (3, extended, vec![2; 4]),
+                (3, stamped + 1, vec![3; 8]),
+                (3, stamped + 4, vec![4; 4]),
+            ]
+        );
+
+        // Big endian headers have the type and length in their high bits
+        let mut page = 1000u64.to_be_bytes().to_vec();
+        page.extend(8u64.to_be_bytes());
+        page.extend((1u32 << TIME_SHIFT | 5).to_be_bytes());
+        page.extend([6; 4]);
+        let mut records = Vec::new();
+        read_page(&page, 0, true, (8, 8, 16), &mut records).unwrap();
+        assert_eq!(records[0].time, 1005);
+        assert_eq!(records[0].data, [6; 4]);
+
+        // Records cut off by the end of the page
+        let mut page = 1000u64.to_le_bytes().to_vec();
+        page.extend(8u64.to_le_bytes());
+        page.extend(word(5 << 5 | 2));
+        page.extend(vec![6; 4]);
+        assert!(read_page(&page, 0, false, (8, 8, 16), &mut Vec::new()).is_err());
+        assert!(read_page(&page[..12], 0, false, (8, 8, 16), &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_from_trace_cmd() {
+        let cpu0 = page(
+            1000,
+            &[
+                event(0, &record(318, 0, &[&comm("bash"), &10i32.to_le_bytes()])),
+                event(2, &switch("swapper/0", 0, "bash", 10)),
+                event(1, &record(318, 10, &[&comm("cat"), &11i32.to_le_bytes()])),
+                event(1, &function(10, 0xffffffff81001010)),
+                event(1, &switch("bash", 10, "swapper/0", 0)),
+            ],
+        );
+        let cpu1 = page(1000, &[event(6, &switch("swapper/1", 0, "cat", 11))]);
+        let file = trace_cmd_file(&[cpu0, cpu1]);
+        assert!(TraceCmdImporter.sniff(&file));
+        let timeline = Timeline::from_trace_cmd(&file[..]).unwrap();
+        assert_eq!(
+            timeline.categories(),
+            ["CPU 0", "CPU 1", "bash-10", "cat-11"]
+        );
+        assert_eq!(
+            events(&timeline),
+            [
+                ("bash", "CPU 0", 1002, 1005),
+                ("running", "bash-10", 1002, 1005),
+                ("cat", "CPU 1", 1006, 1006),
+                ("running", "cat-11", 1006, 1006),
+                ("do_exit", "bash-10", 1004, 1004),
+            ]
+        );
+        // Wakeups by the idle task start on the row of its CPU
+        let triggers: Vec<_> = timeline
+            .triggers()
+            .map(|trigger| (trigger.start_location(), trigger.end_location()))
+            .collect();
+        assert_eq!(triggers, [("CPU 0", "bash-10"), ("bash-10", "cat-11")]);
+
+        // Addresses before the first symbol are drawn as they are, tasks without a saved
+        // command line are "<...>", and events of other types only move the end of the trace
+        let cpu0 = page(
+            1000,
+            &[
+                event(0, &switch("swapper/0", 0, "bash", 10)),
+                event(1, &function(10, 0xffffffff81000000)),
+                event(1, &function(12, 0xffffffff80000000)),
+                event(1, &record(99, 10, &[&[0; 8]])),
+            ],
+        );
+        let file = trace_cmd_file(&[cpu0]);
+        let timeline = Timeline::from_trace_cmd(&file[..]).unwrap();
+        assert_eq!(
+            events(&timeline),
+            [
+                ("bash", "CPU 0", 1000, 1003),
+                ("running", "bash-10", 1000, 1003),
+                ("_stext", "bash-10", 1001, 1001),
+                ("0xffffffff80000000", "<...>-12", 1002, 1002),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_from_trace_cmd_errors() {
+        assert!(!TraceCmdImporter.sniff(b"# tracer: nop\n"));
+        assert_eq!(error(b"# tracer: nop\n"), "not a trace-cmd file");
+        let mut file = trace_cmd_file(&[page(1000, &[])]);
+        file[MAGIC.len()] = b'7';
+        assert_eq!(error(&file), "trace-cmd file version 7 isn't supported");
+
+        let file = trace_cmd_file(&[page(1000, &[])]);
+        assert_eq!(error(&file[..200]), "trace-cmd file is truncated");
+        assert_eq!(
+            error(&file[..file.len() - 1]),
+            "trace-cmd file is truncated"
+        );
+        let mut latency = file.clone();
+        let flyrecord = file.windows(10).position(|w| w == b"flyrecord\0").unwrap();
+        latency[flyrecord..flyrecord + 10].copy_from_slice(b"latency\0\0\0");
+        assert_eq!(error(&latency), "only flyrecord traces are supported");
+        let mut headerless = file;
+        headerless[MAGIC.len() + 8] = b'H';
+        assert_eq!(error(&headerless), "missing page header");
+    }
+}
@@ -0,0 +1,371 @@
+use std::io::Read;
+
+use serde_json::Value;
+
+use crate::ftrace::parse_time;
+use crate::import::{new_timeline, sniff_text};
+use crate::systemd::days_from_civil;
+use crate::{Timeline, TimelineError, TraceImporter};
+
+// The JSON that `tshark -T json` exports, see `Timeline::from_tshark`
+pub(crate) struct TsharkImporter;
+
+impl TraceImporter for TsharkImporter {
+    fn format(&self) -> &str {
+        "tshark"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        let text = sniff_text(data);
+        text.trim_start().starts_with('[')
+            && text.contains("\"_source\"")
+            && text.contains("\"layers\"")
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_tshark(data, &[])
+    }
+}
+
+// The value of the field `name` anywhere in `layers`, as fields are nested in the trees of their
+// protocols, like "http.request.method" in the "GET / HTTP/1.1\r\n" tree of "http". Fields that
+// occur more than once, and those of `-e`, are arrays.
+fn find(layers: &Value, name: &str) -> Option<String> {
+    let object = layers.as_object()?;
+    if let Some(value) = object.get(name) {
+        return match value {
+            Value::String(text) => Some(text.clone()),
+            Value::Array(values) => Some(
+                values
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            _ => None,
+        };
+    }
+    object.values().find_map(|value| match value {
+        Value::Array(values) => values.iter().find_map(|value| find(value, name)),
+        value => find(value, name),
+    })
+}
+
+// The time of a packet, in seconds since the epoch like "1704103200.000100000", or as the
+// "2024-01-01T10:00:00.000100000Z" of newer versions, in nanoseconds
+fn parse_epoch(text: &str) -> Option<i64> {
+    let Some((date, time)) = text.split_once('T') else {
+        return parse_time(text);
+    };
+    let mut date = date.split('-');
+    let (year, month, day) = (date.next()?, date.next()?, date.next()?);
+    let days = days_from_civil(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)?;
+    let mut time = time.trim_end_matches('Z').split(':');
+    let (hours, minutes, seconds) = (time.next()?, time.next()?, time.next()?);
+    let minutes = days
+        .checked_mul(24)?
+        .checked_add(hours.parse().ok()?)?
+        .checked_mul(60)?
+        .checked_add(minutes.parse().ok()?)?;
+    minutes
+        .checked_mul(60_000_000_000)?
+        .checked_add(parse_time(seconds)?)
+}
+
+// An endpoint of a packet, like "10.0.0.1:443", or "10.0.0.1" for protocols without ports
+fn endpoint(layers: &Value, end: &str) -> Option<String> {
+    let address = find(layers, &format!("ip.{}", end))
+        .or_else(|| find(layers, &format!("ipv6.{}", end)))
+        .or_else(|| find(layers, &format!("eth.{}", end)))?;
+    let port = ["tcp", "udp"]
+        .iter()
+        .find_map(|protocol| find(layers, &format!("{}.{}port", protocol, end)));
+    Some(match port {
+        Some(port) if address.contains(':') => format!("[{}]:{}", address, port),
+        Some(port) => format!("{}:{}", address, port),
+        None => address,
+    })
+}
+
+impl Timeline {
+    /// Read the packets `tshark -T json` exports into a timeline
+    ///
+    /// Every packet becomes an instant, named after the values of `fields` it has, in their
+    /// order and separated by spaces, like "GET /index.html" for "http.request.method" and
+    /// "http.request.uri", or after its highest protocol, like "tls", if it has none of them.
+    /// Packets of TCP and UDP are drawn on a row for every stream Wireshark found, like
+    /// "tcp stream 0", so the packets of a connection line up, and other packets on the row of
+    /// their highest protocol. The endpoints, the length and the protocols of a packet are its
+    /// "src", "dst", "length" and "protocols" metadata, and the values of `fields` are metadata
+    /// too, so that fields like "tcp.analysis.ack_rtt" can be read off the events. Fields are
+    /// found in the trees of protocols they are nested in, and in exports of only the fields of
+    /// `-e`. Times are in nanoseconds since the epoch.
+    ///
+    /// This returns `TimelineError::Parse` if the export isn't a JSON array or has no packets
+    /// with times, and `TimelineError::Io` if reading fails. `Timeline::import` detects exports
+    /// of this format, as "tshark", and names their packets after their protocols.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let export = r#"[
+    ///     {"_source": {"layers": {
+    ///         "frame": {"frame.time_epoch": "1.000000000", "frame.protocols": "eth:ip:tcp:http"},
+    ///         "tcp": {"tcp.stream": "0"},
+    ///         "http": {"GET / HTTP/1.1\r\n": {"http.request.method": "GET"}}
+    ///     }}},
+    ///     {"_source": {"layers": {
+    ///         "frame": {"frame.time_epoch": "1.000500000", "frame.protocols": "eth:ip:tcp"},
+    ///         "tcp": {"tcp.stream": "0"}
+    ///     }}}
+    /// ]"#;
+    /// let timeline = Timeline::from_tshark(export.as_bytes(), &["http.request.method"]).unwrap();
+    /// assert_eq!(timeline.categories(), ["tcp stream 0"]);
+    /// assert_eq!(timeline.time_range(), Some((1_000_000_000, 1_000_500_000)));
+    /// # }
+    /// ```
+    pub fn from_tshark(reader: impl Read, fields: &[&str]) -> Result<Timeline, TimelineError> {
+        let json: Value =
+            serde_json::from_reader(reader).map_err(|e| TimelineError::Parse(e.to_string()))?;
+        let packets = json
+            .as_array()
+            .ok_or_else(|| TimelineError::Parse("packets aren't an array".to_string()))?;
+        let mut timeline = new_timeline();
+        let mut parsed = false;
+        for packet in packets {
+            let layers = &packet["_source"]["layers"];
+            let Some(time) = find(layers, "frame.time_epoch").and_then(|time| parse_epoch(&time))
+            else {
+                continue;
+            };
+            parsed = true;
+            let protocols = find(layers, "frame.protocols").unwrap_or_default();
+            let protocol = protocols.rsplit(':').next().unwrap_or_default().to_string();
+            let row = ["tcp", "udp"]
+                .iter()
+                .find_map(|name| {
+                    let stream = find(layers, &format!("{}.stream", name))?;
+                    Some(format!("{} stream {}", name, stream))
+                })
+                .unwrap_or_else(|| protocol.clone());
+            let values: Vec<(&str, String)> = fields
+                .iter()
+                .filter_map(|field| Some((*field, find(layers, field)?)))
+                .collect();
+            let name = if values.is_empty() {
+                protocol
+            } else {
+                values
+                    .iter()
+                    .map(|(_, value)| value.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+            let id = timeline.add_instant(name, time, row);
+            let metadata = [
+                ("src", endpoint(layers, "src")),
+                ("dst", endpoint(layers, "dst")),
+                ("length", find(layers, "frame.len")),
+                ("protocols", Some(protocols)),
+            ];
+            for (key, value) in metadata {
+                if let Some(value) = value {
+                    timeline.set_event_metadata(id, key.to_string(), value);
+                }
+            }
+            for (field, value) in values {
+                timeline.set_event_metadata(id, field.to_string(), value);
+            }
+        }
+        if !parsed {
+            return Err(TimelineError::Parse("no packets with times".to_string()));
+        }
+        Ok(timeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPOCH: i64 = 1_704_103_200_000_000_000;
+
+    #[test]
+    fn test_find() {
+        let layers = serde_json::json!({
+            "frame": {"frame.len": "140"},
+            "http": {
+                "GET / HTTP/1.1\r\n": {"http.request.method": "GET"},
+                "http.request.line": ["Host: a\r\n", "Accept: */*\r\n"]
+            },
+            "tcp": [{"tcp.stream": "0"}, {"tcp.stream": "1"}],
+            "udp.stream": ["2"],
+            "ip.ttl": 64
+        });
+        assert_eq!(find(&layers, "frame.len").as_deref(), Some("140"));
+        assert_eq!(find(&layers, "http.request.method").as_deref(), Some("GET"));
+        // Fields that occur more than once are joined, and in layers of tunnels the outer one
+        // is found
+        assert_eq!(
+            find(&layers, "http.request.line").as_deref(),
+            Some("Host: a\r\n,Accept: */*\r\n")
+        );
+        assert_eq!(find(&layers, "tcp.stream").as_deref(), Some("0"));
+        assert_eq!(find(&layers, "udp.stream").as_deref(), Some("2"));
+        assert_eq!(find(&layers, "ip.ttl"), None);
+        assert_eq!(find(&layers, "dns.qry.name"), None);
+
+        let layers = serde_json::json!({
+            "ip": {"ip.src": "10.0.0.1"},
+            "ipv6": {"ipv6.dst": "fe80::1"},
+            "udp": {"udp.srcport": "53", "udp.dstport": "5353"}
+        });
+        assert_eq!(endpoint(&layers, "src").as_deref(), Some("10.0.0.1:53"));
+        assert_eq!(endpoint(&layers, "dst").as_deref(), Some("[fe80::1]:5353"));
+        let layers = serde_json::json!({"eth": {"eth.src": "00:11:22:33:44:55"}});
+        assert_eq!(
+            endpoint(&layers, "src").as_deref(),
+            Some("00:11:22:33:44:55")
+        );
+        assert_eq!(endpoint(&layers, "dst"), None);
+    }
+
+    #[test]
+    fn test_parse_epoch() {
+        assert_eq!(parse_epoch("1704103200.000100000"), Some(EPOCH + 100_000));
+        assert_eq!(
+            parse_epoch("2024-01-01T10:00:00.000100000Z"),
+            Some(EPOCH + 100_000)
+        );
+        assert_eq!(parse_epoch("2024-01-01T10:00:00"), Some(EPOCH));
+        assert_eq!(parse_epoch("Jan  1, 2024 10:00:00.000100000 UTC"), None);
+        assert_eq!(parse_epoch("2024-01-01T10:00Z"), None);
+        assert_eq!(parse_epoch("2024-01-01T9999999999999:00:00Z"), None);
+        assert_eq!(parse_epoch("99999999999.000000000"), None);
+    }
+
+    #[test]
+    fn test_from_tshark() {
+        let export = r#"[
+  {
+    "_source": {
+      "layers": {
+        "frame": {
+          "frame.time_epoch": "1704103200.000100000",
+          "frame.len": "140",
+          "frame.protocols": "eth:ethertype:ip:tcp:http"
+        },
+        "ip": {"ip.src": "10.0.0.1", "ip.dst": "10.0.0.2"},
+        "tcp": {"tcp.srcport": "40000", "tcp.dstport": "80", "tcp.stream": "3"},
+        "http": {
+          "GET /index.html HTTP/1.1\r\n": {
+            "http.request.method": "GET",
+            "http.request.uri": "/index.html"
+          }
+        }
+      }
+    }
+  },
+  {
+    "_source": {
+      "layers": {
+        "frame": {
+          "frame.time_epoch": "2024-01-01T10:00:00.000600000Z",
+          "frame.protocols": "eth:ethertype:ip:tcp"
+        },
+        "tcp": {
+          "tcp.stream": "3",
+          "tcp.analysis": {"tcp.analysis.ack_rtt": "0.000500000"}
+        }
+      }
+    }
+  },
+  {
+    "_source": {
+      "layers": {
+        "frame": {"frame.time_epoch": "1704103200.002000000", "frame.protocols": "eth:ethertype:arp"}
+      }
+    }
+  }
+]"#;
+        assert!(TsharkImporter.sniff(export.as_bytes()));
+        // Chrome traces are arrays too, and `-T ek` writes lines of objects
+        assert!(!TsharkImporter.sniff(br#"[{"name": "a", "ph": "X", "ts": 0}]"#));
+        assert!(!TsharkImporter.sniff(
+            br#"{"index": {"_index": "packets"}}
+{"timestamp": "1704103200000", "layers": {"frame": {}}}"#
+        ));
+        let fields = [
+            "http.request.method",
+            "http.request.uri",
+            "tcp.analysis.ack_rtt",
+        ];
+        let timeline = Timeline::from_tshark(export.as_bytes(), &fields).unwrap();
+        let events: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| (event.name(), event.location(), event.start_time() - EPOCH))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("GET /index.html", "tcp stream 3", 100_000),
+                ("0.000500000", "tcp stream 3", 600_000),
+                ("arp", "arp", 2_000_000),
+            ]
+        );
+        let (_, get) = timeline.events().next().unwrap();
+        assert_eq!(get.metadata()["src"], "10.0.0.1:40000");
+        assert_eq!(get.metadata()["dst"], "10.0.0.2:80");
+        assert_eq!(get.metadata()["length"], "140");
+        assert_eq!(get.metadata()["protocols"], "eth:ethertype:ip:tcp:http");
+        assert_eq!(get.metadata()["http.request.uri"], "/index.html");
+        let (_, ack) = timeline.events().nth(1).unwrap();
+        assert_eq!(ack.metadata()["tcp.analysis.ack_rtt"], "0.000500000");
+        assert!(!ack.metadata().contains_key("src"));
+
+        // Without fields packets are named after their highest protocol
+        let timeline = Timeline::from_tshark(export.as_bytes(), &[]).unwrap();
+        let names: Vec<_> = timeline.events().map(|(_, event)| event.name()).collect();
+        assert_eq!(names, ["http", "tcp", "arp"]);
+    }
+
+    #[test]
+    fn test_from_tshark_fields_only() {
+        // `-T json -e` exports only the fields asked for, as arrays at the top of the layers
+        let export = r#"[
+  {"_source": {"layers": {
+    "frame.time_epoch": ["1704103200.001000000"],
+    "frame.protocols": ["eth:ethertype:ipv6:udp:dns"],
+    "ipv6.src": ["fe80::1"],
+    "udp.srcport": ["53"],
+    "udp.stream": ["0"],
+    "dns.qry.name": ["example.com"]
+  }}},
+  {"_source": {"layers": {"dns.qry.name": ["no time"]}}}
+]"#;
+        let timeline = Timeline::from_tshark(export.as_bytes(), &["dns.qry.name"]).unwrap();
+        let events: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| (event.name(), event.location(), event.start_time() - EPOCH))
+            .collect();
+        assert_eq!(events, [("example.com", "udp stream 0", 1_000_000)]);
+        let (_, dns) = timeline.events().next().unwrap();
+        assert_eq!(dns.metadata()["src"], "[fe80::1]:53");
+
+        // Exports need packets with a time
+        assert!(matches!(
+            Timeline::from_tshark(r#"{"packets": []}"#.as_bytes(), &[]),
+            Err(TimelineError::Parse(_))
+        ));
+        assert!(matches!(
+            Timeline::from_tshark(r#"[{"_source": {"layers": {}}}]"#.as_bytes(), &[]),
+            Err(TimelineError::Parse(_))
+        ));
+        assert!(matches!(
+            Timeline::from_tshark("[{".as_bytes(), &[]),
+            Err(TimelineError::Parse(_))
+        ));
+    }
+}
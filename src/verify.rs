@@ -0,0 +1,481 @@
+use std::fmt;
+
+use roxmltree::{Document, Node};
+
+use crate::{TimeValue, Timeline};
+
+// Coordinates this close to the edge of the document still count as inside it
+const TOLERANCE: f64 = 1e-6;
+
+/// A problem found in a rendered timeline by `Timeline::verify`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    /// The timeline can't be rendered at all, see `Timeline::write`
+    Unrenderable(String),
+    /// The SVG isn't well-formed XML, or has no usable size
+    Malformed(String),
+    /// A geometry attribute isn't a finite number
+    InvalidNumber {
+        element: String,
+        attribute: String,
+        value: String,
+    },
+    /// A point of an element lies outside of the document
+    OutOfBounds { element: String, x: f64, y: f64 },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::Unrenderable(message) => write!(f, "can't render: {}", message),
+            Problem::Malformed(message) => write!(f, "malformed SVG: {}", message),
+            Problem::InvalidNumber {
+                element,
+                attribute,
+                value,
+            } => write!(f, "<{}> has an invalid {}: {:?}", element, attribute, value),
+            Problem::OutOfBounds { element, x, y } => {
+                write!(
+                    f,
+                    "<{}> reaches ({}, {}) outside the document",
+                    element, x, y
+                )
+            }
+        }
+    }
+}
+
+/// The result of checking a rendered timeline
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VerifyReport {
+    pub problems: Vec<Problem>,
+}
+
+impl VerifyReport {
+    /// Whether no problems were found
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl fmt::Display for VerifyReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_ok() {
+            return write!(f, "no problems found");
+        }
+        for problem in &self.problems {
+            writeln!(f, "{}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: TimeValue> Timeline<T> {
+    /// Render the timeline and check the output
+    ///
+    /// The SVG is rendered as `write` would, parsed back, and checked for structural problems:
+    /// it must be well-formed, every geometry attribute must be a finite number, and every
+    /// element must lie within the bounds of the document. This is meant as a safety net for
+    /// pipelines that publish timelines without anyone looking at them first.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::Timeline;
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.add_event("Process A".to_string(), 0, 1, "CPU 0".to_string());
+    /// let report = timeline.verify();
+    /// assert!(report.is_ok(), "{}", report);
+    /// # }
+    /// ```
+    pub fn verify(&self) -> VerifyReport {
+        match self.document(&self.colormap) {
+            Ok(document) => verify_svg(&document.to_string()),
+            Err(error) => VerifyReport {
+                problems: vec![Problem::Unrenderable(error.to_string())],
+            },
+        }
+    }
+}
+
+/// Check an SVG document for structural problems, see `Timeline::verify`
+pub fn verify_svg(svg: &str) -> VerifyReport {
+    let mut report = VerifyReport::default();
+    let document = match Document::parse(svg) {
+        Ok(document) => document,
+        Err(error) => {
+            report.problems.push(Problem::Malformed(error.to_string()));
+            return report;
+        }
+    };
+    let root = document.root_element();
+    // Elements are placed in the coordinates of the view box, where it has one
+    let view_box = match root.attribute("viewBox") {
+        Some(value) => match view_box(value) {
+            Some(view_box) => Some(view_box),
+            None => {
+                report
+                    .problems
+                    .push(Problem::Malformed(format!("invalid viewBox {:?}", value)));
+                return report;
+            }
+        },
+        None => match (
+            number(root, "width", &mut report),
+            number(root, "height", &mut report),
+        ) {
+            (Some(width), Some(height)) => Some((0.0, 0.0, width, height)),
+            _ => None,
+        },
+    };
+    match view_box {
+        Some(view_box) => check_node(root, (0.0, 0.0), view_box, &mut report),
+        None => report
+            .problems
+            .push(Problem::Malformed("the document has no size".to_string())),
+    }
+    report
+}
+
+// Check the geometry of `node` and its children, which are offset by `origin`, against the
+// `view_box` of the document, as its x, y, width and height
+fn check_node(
+    node: Node,
+    origin: (f64, f64),
+    view_box: (f64, f64, f64, f64),
+    report: &mut VerifyReport,
+) {
+    let name = node.tag_name().name();
+    let mut points = Vec::new();
+    let mut pair = |x: Option<f64>, y: Option<f64>| {
+        if let (Some(x), Some(y)) = (x, y) {
+            points.push((x, y));
+        }
+    };
+    match name {
+        "rect" => {
+            let x = number(node, "x", report);
+            let y = number(node, "y", report);
+            let width = number(node, "width", report);
+            let height = number(node, "height", report);
+            pair(x, y);
+            pair(
+                x.zip(width).map(|(x, width)| x + width),
+                y.zip(height).map(|(y, height)| y + height),
+            );
+        }
+        "line" => {
+            pair(number(node, "x1", report), number(node, "y1", report));
+            pair(number(node, "x2", report), number(node, "y2", report));
+        }
+        "text" => pair(number(node, "x", report), number(node, "y", report)),
+        "polygon" | "polyline" => {
+            if let Some(value) = node.attribute("points") {
+                let numbers: Vec<&str> = value
+                    .split(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+                    .filter(|s| !s.is_empty())
+                    .collect();
+                for coordinates in numbers.chunks(2) {
+                    let parsed: Vec<Option<f64>> = coordinates
+                        .iter()
+                        .map(|value| finite(name, "points", value, report))
+                        .collect();
+                    if let [x, y] = parsed[..] {
+                        pair(x, y);
+                    }
+                }
+            }
+        }
+        "path" => {
+            if let Some(value) = node.attribute("d") {
+                for (x, y) in path_points(value, report) {
+                    pair(Some(x), Some(y));
+                }
+            }
+        }
+        _ => {}
+    }
+    for (x, y) in points {
+        let (x, y) = (origin.0 + x, origin.1 + y);
+        let (left, top, width, height) = view_box;
+        if x < left - TOLERANCE
+            || y < top - TOLERANCE
+            || x > left + width + TOLERANCE
+            || y > top + height + TOLERANCE
+        {
+            report.problems.push(Problem::OutOfBounds {
+                element: name.to_string(),
+                x,
+                y,
+            });
+        }
+    }
+
+    let origin = match node.attribute("transform").and_then(translation) {
+        Some((dx, dy)) => (origin.0 + dx, origin.1 + dy),
+        None => origin,
+    };
+    for child in node.children().filter(|child| child.is_element()) {
+        check_node(child, origin, view_box, report);
+    }
+}
+
+// The commands and numbers of the data of a path, like "M0,0 H10 v-5", where words that aren't
+// commands, like "NaN", are kept whole as numbers to be reported
+fn path_tokens(data: &str) -> Vec<&str> {
+    const COMMANDS: &str = "MmLlHhVvCcSsQqTtAaZz";
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut chars = data.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c.is_ascii_alphabetic() && !(start.is_some() && "eE".contains(c)) {
+            if let Some(begin) = start.take() {
+                tokens.push(&data[begin..i]);
+            }
+            let mut end = i + 1;
+            while let Some(&(j, c)) = chars.peek().filter(|(_, c)| c.is_ascii_alphabetic()) {
+                end = j + c.len_utf8();
+                chars.next();
+            }
+            let word = &data[i..end];
+            if word.chars().all(|c| COMMANDS.contains(c)) {
+                tokens.extend((i..end).map(|j| &data[j..j + 1]));
+            } else {
+                tokens.push(word);
+            }
+            continue;
+        }
+        let number = c.is_ascii_digit() || c == '.' || "eE".contains(c);
+        let sign = "+-".contains(c);
+        if let Some(begin) = start {
+            // A sign starts a number of its own, unless it's the one of an exponent
+            if number || sign && data[..i].ends_with(['e', 'E']) {
+                continue;
+            }
+            tokens.push(&data[begin..i]);
+            start = None;
+        }
+        if number || sign {
+            start = Some(i);
+        }
+    }
+    if let Some(begin) = start {
+        tokens.push(&data[begin..]);
+    }
+    tokens
+}
+
+// The points a path reaches, its end points and the control points of its curves, in absolute
+// coordinates, reporting the numbers of its data that aren't finite
+fn path_points(data: &str, report: &mut VerifyReport) -> Vec<(f64, f64)> {
+    let mut points = Vec::new();
+    let (mut current, mut subpath) = ((0.0, 0.0), (0.0, 0.0));
+    let mut command = None;
+    let mut arguments: Vec<f64> = Vec::new();
+    for token in path_tokens(data) {
+        if token.len() == 1 && token.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            command = token.chars().next();
+            arguments.clear();
+            if matches!(command, Some('Z' | 'z')) {
+                current = subpath;
+            }
+            continue;
+        }
+        let Some(value) = finite("path", "d", token, report) else {
+            // The rest of the command can't be placed without it
+            command = None;
+            continue;
+        };
+        let Some(letter) = command else {
+            continue;
+        };
+        arguments.push(value);
+        // The number of arguments every command takes
+        let count = match letter.to_ascii_uppercase() {
+            'H' | 'V' => 1,
+            'M' | 'L' | 'T' => 2,
+            'S' | 'Q' => 4,
+            'C' => 6,
+            'A' => 7,
+            _ => {
+                command = None;
+                continue;
+            }
+        };
+        if arguments.len() < count {
+            continue;
+        }
+        let relative = letter.is_ascii_lowercase();
+        let offset = if relative { current } else { (0.0, 0.0) };
+        let point = |x: f64, y: f64| (offset.0 + x, offset.1 + y);
+        let end = match letter.to_ascii_uppercase() {
+            'H' => (if relative { current.0 + value } else { value }, current.1),
+            'V' => (current.0, if relative { current.1 + value } else { value }),
+            // The radii and flags of an arc aren't points
+            'A' => point(arguments[5], arguments[6]),
+            _ => {
+                for control in arguments[..count - 2].chunks(2) {
+                    points.push(point(control[0], control[1]));
+                }
+                point(arguments[count - 2], arguments[count - 1])
+            }
+        };
+        points.push(end);
+        current = end;
+        match letter {
+            // Pairs after those of a move draw lines
+            'M' => {
+                subpath = end;
+                command = Some('L');
+            }
+            'm' => {
+                subpath = end;
+                command = Some('l');
+            }
+            _ => {}
+        }
+        arguments.clear();
+    }
+    points
+}
+
+// Parse a numeric attribute, reporting it if it isn't a finite number
+fn number(node: Node, attribute: &str, report: &mut VerifyReport) -> Option<f64> {
+    let value = node.attribute(attribute)?;
+    finite(node.tag_name().name(), attribute, value, report)
+}
+
+fn finite(element: &str, attribute: &str, value: &str, report: &mut VerifyReport) -> Option<f64> {
+    match value.trim().parse::<f64>() {
+        Ok(number) if number.is_finite() => Some(number),
+        _ => {
+            report.problems.push(Problem::InvalidNumber {
+                element: element.to_string(),
+                attribute: attribute.to_string(),
+                value: value.to_string(),
+            });
+            None
+        }
+    }
+}
+
+// The x, y, width and height of a "viewBox", of which the sizes can't be negative
+fn view_box(value: &str) -> Option<(f64, f64, f64, f64)> {
+    let numbers: Vec<f64> = value
+        .split([',', ' '])
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<f64>().ok().filter(|number| number.is_finite()))
+        .collect::<Option<_>>()?;
+    match numbers[..] {
+        [x, y, width, height] if width >= 0.0 && height >= 0.0 => Some((x, y, width, height)),
+        _ => None,
+    }
+}
+
+// The offset of a "translate(x,y)" transform
+fn translation(transform: &str) -> Option<(f64, f64)> {
+    let arguments = transform
+        .trim()
+        .strip_prefix("translate(")?
+        .strip_suffix(')')?;
+    let mut numbers = arguments
+        .split([',', ' '])
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<f64>().ok());
+    let x = numbers.next()??;
+    let y = numbers.next().unwrap_or(Some(0.0))?;
+    Some((x, y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CounterStyle, Margins};
+
+    #[test]
+    fn test_verify() {
+        let mut timeline = Timeline::builder()
+            .margins(Margins {
+                top: 5,
+                right: 5,
+                bottom: 5,
+                left: 5,
+            })
+            .build();
+        timeline.add_event("Event 1".to_string(), 1, 2, "Location 1".to_string());
+        timeline.add_event("Event 2".to_string(), 3, 4, "Location 2".to_string());
+        timeline.add_trigger("Location 1".to_string(), "Location 2".to_string(), 1);
+        let report = timeline.verify();
+        assert!(report.is_ok(), "{}", report);
+
+        // Regions are drawn where they are in the whole timeline, and moved into view by their
+        // view box
+        let mut region = Vec::new();
+        timeline.render_region(3, 4, &mut region).unwrap();
+        let report = verify_svg(&String::from_utf8(region).unwrap());
+        assert!(report.is_ok(), "{}", report);
+        let report = verify_svg(
+            r#"<svg width="10" height="10" viewBox="100 0 10 10"><rect x="100" y="0" width="10" height="10"/><line x1="95" y1="5" x2="105" y2="5"/></svg>"#,
+        );
+        assert_eq!(
+            report.problems,
+            [Problem::OutOfBounds {
+                element: "line".to_string(),
+                x: 95.0,
+                y: 5.0
+            }]
+        );
+        let report = verify_svg(r#"<svg viewBox="0 0 -1 10"/>"#);
+        assert!(matches!(report.problems[..], [Problem::Malformed(_)]));
+
+        let report = verify_svg(r#"<svg width="10" height="10"><rect x="5" y="5" width="10""#);
+        assert!(matches!(report.problems[..], [Problem::Malformed(_)]));
+        let report = verify_svg(
+            r#"<svg width="10" height="10"><g transform="translate(5,0)"><rect x="0" y="0" width="10" height="NaN"/><line x1="0" y1="0" x2="6" y2="0"/></g></svg>"#,
+        );
+        assert_eq!(report.problems.len(), 2);
+        assert_eq!(
+            report.problems[1],
+            Problem::OutOfBounds {
+                element: "line".to_string(),
+                x: 11.0,
+                y: 0.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_verify_paths() {
+        let mut timeline = Timeline::default();
+        timeline.add_event("Event 1".to_string(), 0, 100, "Location 1".to_string());
+        for (time, value) in [(0, 1000.0), (40, 5.0), (80, 300.0)] {
+            timeline.add_counter_sample("Memory".to_string(), time, value);
+        }
+        timeline.set_counter_style("Memory".to_string(), CounterStyle::Area);
+        let report = timeline.verify();
+        assert!(report.is_ok(), "{}", report);
+
+        // Horizontal and vertical lines take one number, and relative ones add to the last point
+        let report = verify_svg(
+            r#"<svg width="10" height="10"><path d="M1,1 H9 V9 h-8 zm2-1 l1e0,2 v-4 C 1 1 2 2 3 3 A 5 5 0 0 1 11 5 L NaN,1"/></svg>"#,
+        );
+        let points: Vec<_> = report
+            .problems
+            .iter()
+            .map(|problem| match problem {
+                Problem::OutOfBounds { x, y, .. } => Some((*x, *y)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(points, [None, Some((4.0, -2.0)), Some((11.0, 5.0))]);
+        assert_eq!(
+            report.problems[0],
+            Problem::InvalidNumber {
+                element: "path".to_string(),
+                attribute: "d".to_string(),
+                value: "NaN".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use chrono::{DateTime, Utc};
+
+use crate::{EventId, TimeValue, Timeline};
+
+// The number of nanoseconds in a day
+const DAY: f64 = 86_400_000_000_000.0;
+
+impl<T: TimeValue> Timeline<T> {
+    /// Add an event to the timeline using wall-clock timestamps
+    ///
+    /// This is the same as `add_event`, but the times are `DateTime<Utc>`s, which are stored as
+    /// the time since the Unix epoch in the units of the timeline, so timestamps before the epoch
+    /// are negative. Use `set_wall_clock_axis` to label the axis with clock times.
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use chrono::{TimeZone, Utc};
+    /// use timeline_svg::{TimeUnit, Timeline};
+    ///
+    /// let mut timeline = Timeline::default();
+    /// timeline.set_units(TimeUnit::Seconds);
+    /// timeline.set_wall_clock_axis(true);
+    /// let start = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+    /// let end = Utc.with_ymd_and_hms(2024, 1, 1, 12, 5, 0).unwrap();
+    /// timeline.add_event_datetime("backup".to_string(), start, end, "host".to_string());
+    /// # }
+    /// ```
+    pub fn add_event_datetime(
+        &mut self,
+        name: impl Into<String>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        location: impl Into<String>,
+    ) -> EventId {
+        let start_time = self.datetime_time(start);
+        let end_time = self.datetime_time(end);
+        self.add_event(name, start_time, end_time, location)
+    }
+
+    /// Add a trigger to the timeline at a wall-clock timestamp
+    ///
+    /// This is the same as `add_trigger`, with `time` converted like `add_event_datetime` does.
+    pub fn add_trigger_datetime(
+        &mut self,
+        start_location: impl Into<String>,
+        end_location: impl Into<String>,
+        time: DateTime<Utc>,
+    ) {
+        let time = self.datetime_time(time);
+        self.add_trigger(start_location, end_location, time);
+    }
+
+    /// Label the time axis with wall-clock times
+    ///
+    /// With this enabled the times of the timeline are treated as time since the Unix epoch, and
+    /// the ticks of the axis are labeled with UTC clock times, including the date when the
+    /// timeline spans more than a day.
+    pub fn set_wall_clock_axis(&mut self, enabled: bool) {
+        self.wall_clock = enabled;
+    }
+
+    // Convert a timestamp into a time of the timeline
+    fn datetime_time(&self, time: DateTime<Utc>) -> T {
+        let nanos = time.timestamp_nanos_opt().unwrap_or(i64::MAX);
+        T::from_nanos(nanos as i128, self.units)
+    }
+
+    // Format the tick `offset` units from the start of the timeline, with ticks `step` units
+    // apart, as a clock time
+    pub(crate) fn wall_clock_label(&self, offset: f64, step: f64) -> String {
+        let unit = self.units.nanoseconds() as f64;
+        let nanos = self.start_time.to_nanos(self.units) + (offset * unit) as i128;
+        let nanos = nanos.clamp(i64::MIN as i128, i64::MAX as i128);
+        let time = DateTime::from_timestamp_nanos(nanos as i64);
+        let step = step * unit;
+        let format = if self.span() * unit >= DAY {
+            "%Y-%m-%d %H:%M:%S"
+        } else if step >= 1_000_000_000.0 {
+            "%H:%M:%S"
+        } else if step >= 1_000_000.0 {
+            "%H:%M:%S%.3f"
+        } else if step >= 1_000.0 {
+            "%H:%M:%S%.6f"
+        } else {
+            "%H:%M:%S%.9f"
+        };
+        time.format(format).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::TimeUnit;
+
+    #[test]
+    fn test_wall_clock_axis() {
+        let mut timeline = Timeline::default();
+        timeline.set_units(TimeUnit::Milliseconds);
+        timeline.set_wall_clock_axis(true);
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        timeline.add_event_datetime(
+            "Event 1".to_string(),
+            start,
+            start + chrono::Duration::milliseconds(3),
+            "Location 1".to_string(),
+        );
+        assert_eq!(timeline.start_time, 1_704_110_400_000);
+        assert_eq!(
+            timeline.wall_clock_label(1.0, timeline.tick_step()),
+            "12:00:00.001"
+        );
+
+        let mut output = Vec::new();
+        timeline.write(&mut output).unwrap();
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.contains("12:00:00.002"));
+        assert!(svg.contains("time (UTC)"));
+    }
+}
@@ -0,0 +1,303 @@
+use std::io::Read;
+
+use serde_json::Value;
+
+use crate::chrome::text;
+use crate::import::sniff_text;
+use crate::spans::{spans_timeline, Span, SpanRows};
+use crate::{Timeline, TimelineError, TraceImporter};
+
+// The JSON of Zipkin v2 spans, see `Timeline::from_zipkin`
+pub(crate) struct ZipkinImporter;
+
+impl TraceImporter for ZipkinImporter {
+    fn format(&self) -> &str {
+        "zipkin"
+    }
+
+    fn sniff(&self, data: &[u8]) -> bool {
+        let text = sniff_text(data);
+        text.trim_start().starts_with('[')
+            && text.contains("\"traceId\"")
+            && (text.contains("\"localEndpoint\"") || text.contains("\"timestamp\""))
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Timeline, TimelineError> {
+        Timeline::from_zipkin(data, SpanRows::Service)
+    }
+}
+
+// Zipkin counts in microseconds since the epoch, this is the start and end of a span of them in
+// nanoseconds, or None if they are too late to tell
+fn nanos(start: i64, duration: i64) -> Option<(i64, i64)> {
+    let start = start.checked_mul(1000)?;
+    Some((start, start.checked_add(duration.checked_mul(1000)?)?))
+}
+
+// A span, or None if it lacks its ids or start
+fn parse_span(span: &Value) -> Result<Option<Span>, TimelineError> {
+    let (Some(trace_id), Some(span_id), Some(start)) = (
+        span["traceId"].as_str(),
+        span["id"].as_str(),
+        span["timestamp"].as_i64(),
+    ) else {
+        return Ok(None);
+    };
+    let (start, end) = nanos(start, span["duration"].as_i64().unwrap_or(0))
+        .ok_or_else(|| TimelineError::Parse(format!("span {} lasts too long", span_id)))?;
+    let (trace_id, span_id) = (trace_id.to_lowercase(), span_id.to_lowercase());
+    let mut attributes: Vec<(String, String)> = span["tags"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(key, value)| (key.clone(), text(value)))
+        .collect();
+    if let Some(remote) = span["remoteEndpoint"]["serviceName"].as_str() {
+        attributes.push(("remote_service".to_string(), remote.to_string()));
+    }
+    // The server half of a span shared with its client follows from the client half
+    let links = if span["shared"] == true {
+        vec![(trace_id.clone(), span_id.clone())]
+    } else {
+        Vec::new()
+    };
+    Ok(Some(Span {
+        parent_id: span["parentId"].as_str().map(str::to_lowercase),
+        links,
+        name: span["name"].as_str().unwrap_or_default().to_string(),
+        service: span["localEndpoint"]["serviceName"]
+            .as_str()
+            .map(str::to_string),
+        kind: span["kind"].as_str().map(str::to_lowercase),
+        start,
+        end,
+        attributes,
+        trace_id,
+        span_id,
+    }))
+}
+
+impl Timeline {
+    /// Read Zipkin spans into a timeline
+    ///
+    /// This reads the JSON of the Zipkin v2 API, an array of spans like a trace is downloaded,
+    /// or an array of such traces like `/api/v2/traces` returns. Every span becomes an event
+    /// with its name, on the row of the service of its local endpoint, or of its kind, like
+    /// "server", as `rows` picks. A span gets a trigger from the row of its parent when it
+    /// starts, if that is another row, and the server half of a span shared with its client
+    /// gets one from the row of the client half. The ids of the trace, the span and its parent,
+    /// its "service", "kind" and "remote_service", and its tags are its metadata. Times are in
+    /// nanoseconds since the epoch.
+    ///
+    /// This returns `TimelineError::Parse` if the spans aren't JSON of this shape, there are none
+    /// or one ends too late to tell, and `TimelineError::Io` if reading fails. `Timeline::import`
+    /// detects spans of this format, as "zipkin", and draws them by service.
+    ///
+    /// ```
+    /// # extern crate timeline_svg;
+    /// # fn main() {
+    /// use timeline_svg::{SpanRows, Timeline};
+    ///
+    /// let spans = r#"[
+    ///     {"traceId": "a1", "id": "01", "name": "get /", "kind": "SERVER",
+    ///      "timestamp": 1000, "duration": 50, "localEndpoint": {"serviceName": "frontend"}},
+    ///     {"traceId": "a1", "id": "02", "parentId": "01", "name": "query", "kind": "CLIENT",
+    ///      "timestamp": 1010, "duration": 20, "localEndpoint": {"serviceName": "backend"}}
+    /// ]"#;
+    /// let timeline = Timeline::from_zipkin(spans.as_bytes(), SpanRows::Service).unwrap();
+    /// assert_eq!(timeline.categories(), ["backend", "frontend"]);
+    /// assert_eq!(timeline.triggers().count(), 1);
+    /// # }
+    /// ```
+    pub fn from_zipkin(reader: impl Read, rows: SpanRows) -> Result<Timeline, TimelineError> {
+        let json: Value =
+            serde_json::from_reader(reader).map_err(|e| TimelineError::Parse(e.to_string()))?;
+        let list = json
+            .as_array()
+            .ok_or_else(|| TimelineError::Parse("spans aren't an array".to_string()))?;
+        let spans = list
+            .iter()
+            .flat_map(|item| match item {
+                Value::Array(trace) => trace.iter().collect(),
+                span => vec![span],
+            })
+            .filter_map(|span| parse_span(span).transpose())
+            .collect::<Result<_, _>>()?;
+        spans_timeline(spans, rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const TRACE: &str = r#"[
+        {"traceId": "A1", "id": "01", "name": "get /", "kind": "SERVER",
+         "timestamp": 1000, "duration": 50, "localEndpoint": {"serviceName": "frontend"},
+         "tags": {"http.path": "/"}},
+        {"traceId": "a1", "id": "02", "parentId": "01", "name": "get /api", "kind": "CLIENT",
+         "timestamp": 1010, "duration": 30, "localEndpoint": {"serviceName": "frontend"},
+         "remoteEndpoint": {"serviceName": "backend"}},
+        {"traceId": "a1", "id": "02", "parentId": "01", "name": "get /api", "kind": "SERVER",
+         "timestamp": 1012, "duration": 25, "localEndpoint": {"serviceName": "backend"},
+         "shared": true},
+        {"traceId": "a1", "id": "03", "parentId": "02", "name": "cache miss",
+         "timestamp": 1015, "localEndpoint": {"serviceName": "backend"}}
+    ]"#;
+
+    fn triggers(timeline: &Timeline) -> Vec<(&str, &str, i64)> {
+        timeline
+            .triggers()
+            .map(|trigger| {
+                (
+                    trigger.start_location(),
+                    trigger.end_location(),
+                    trigger.time(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_span() {
+        let span = parse_span(&json!({
+            "traceId": "A1", "id": "0B", "parentId": "0A", "kind": "SERVER",
+            "timestamp": 1000, "duration": 50, "shared": true,
+            "tags": {"http.status_code": "200"},
+            "remoteEndpoint": {"serviceName": "frontend"}
+        }))
+        .unwrap()
+        .unwrap();
+        // Ids are compared in lower case, and spans without names or services have neither
+        assert_eq!(
+            (
+                span.trace_id.as_str(),
+                span.span_id.as_str(),
+                span.parent_id.as_deref()
+            ),
+            ("a1", "0b", Some("0a"))
+        );
+        assert_eq!((span.name.as_str(), span.service), ("", None));
+        assert_eq!(span.kind.as_deref(), Some("server"));
+        assert_eq!((span.start, span.end), (1_000_000, 1_050_000));
+        assert_eq!(span.links, [("a1".to_string(), "0b".to_string())]);
+        assert_eq!(
+            span.attributes,
+            [
+                ("http.status_code".to_string(), "200".to_string()),
+                ("remote_service".to_string(), "frontend".to_string()),
+            ]
+        );
+        // Spans need ids and a start, and may lack a duration
+        let span = parse_span(&json!({"traceId": "a1", "id": "01", "timestamp": 5}))
+            .unwrap()
+            .unwrap();
+        assert_eq!((span.start, span.end), (5000, 5000));
+        assert!(span.links.is_empty());
+        assert!(matches!(
+            parse_span(&json!({"traceId": "a1", "id": "01"})),
+            Ok(None)
+        ));
+        assert!(matches!(
+            parse_span(&json!({"traceId": "a1", "timestamp": 5})),
+            Ok(None)
+        ));
+        assert!(matches!(
+            parse_span(&json!({"id": "01", "timestamp": 5})),
+            Ok(None)
+        ));
+        // but their times have to fit
+        assert!(matches!(
+            parse_span(&json!({"traceId": "a1", "id": "01", "timestamp": i64::MAX})),
+            Err(TimelineError::Parse(_))
+        ));
+        assert!(matches!(
+            parse_span(&json!({
+                "traceId": "a1", "id": "01", "timestamp": 5, "duration": i64::MAX
+            })),
+            Err(TimelineError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_zipkin() {
+        assert!(ZipkinImporter.sniff(TRACE.as_bytes()));
+        // Jaeger and OTLP have trace ids too, in objects
+        assert!(!ZipkinImporter.sniff(b"{\"data\": [{\"traceID\": \"a1\", \"spans\": []}]}"));
+        let timeline = Timeline::from_zipkin(TRACE.as_bytes(), SpanRows::Service).unwrap();
+        let events: Vec<_> = timeline
+            .events()
+            .map(|(_, event)| {
+                (
+                    event.name(),
+                    event.location(),
+                    event.start_time(),
+                    event.end_time(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("get /", "frontend", 1_000_000, 1_050_000),
+                ("get /api", "frontend", 1_010_000, 1_040_000),
+                ("get /api", "backend", 1_012_000, 1_037_000),
+                ("cache miss", "backend", 1_015_000, 1_015_000),
+            ]
+        );
+        let (_, server) = timeline.events().next().unwrap();
+        assert_eq!(server.metadata()["trace_id"], "a1");
+        assert_eq!(server.metadata()["http.path"], "/");
+        let (_, client) = timeline.events().nth(1).unwrap();
+        assert_eq!(client.metadata()["parent_id"], "01");
+        assert_eq!(client.metadata()["kind"], "client");
+        assert_eq!(client.metadata()["remote_service"], "backend");
+        // The server half of a shared span follows from the client half, and its children
+        // from the client half too, which has its id first
+        assert_eq!(
+            triggers(&timeline),
+            [
+                ("frontend", "backend", 1_012_000),
+                ("frontend", "backend", 1_015_000)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_traces() {
+        // Lists of traces are read like one list, skipping what isn't a span
+        let traces = format!("[{}, [{{\"traceId\": \"b2\"}}], 7]", TRACE);
+        let timeline = Timeline::from_zipkin(traces.as_bytes(), SpanRows::Service).unwrap();
+        assert_eq!(timeline.events().count(), 4);
+        let trace: Value = serde_json::from_str(TRACE).unwrap();
+        let nested = json!([trace]).to_string();
+        assert!(ZipkinImporter.sniff(nested.as_bytes()));
+        let timeline = Timeline::from_zipkin(nested.as_bytes(), SpanRows::Service).unwrap();
+        assert_eq!(timeline.events().count(), 4);
+
+        let timeline = Timeline::from_zipkin(TRACE.as_bytes(), SpanRows::Kind).unwrap();
+        assert_eq!(timeline.categories(), ["client", "server", "unspecified"]);
+        assert_eq!(
+            triggers(&timeline),
+            [
+                ("server", "client", 1_010_000),
+                ("client", "server", 1_012_000),
+                ("client", "unspecified", 1_015_000)
+            ]
+        );
+
+        // but they need a span with an id and a time
+        for spans in ["{}", "[]", "[{\"traceId\": \"a1\"}]", "[{\"traceId\": "] {
+            assert!(
+                matches!(
+                    Timeline::from_zipkin(spans.as_bytes(), SpanRows::Service),
+                    Err(TimelineError::Parse(_))
+                ),
+                "{}",
+                spans
+            );
+        }
+    }
+}